@@ -1,5 +1,7 @@
 //! The main browser struct to manage pages.
 
+use crate::http::HttpRequest;
+use crate::log::Diagnostic;
 use crate::log::Log;
 use crate::log::LogLevel;
 use crate::renderer::page::Page;
@@ -14,6 +16,11 @@ pub struct Browser {
     active_page_index: usize,
     pages: Vec<Rc<RefCell<Page>>>,
     logs: Vec<Log>,
+    /// Whether a page is allowed to navigate by `<meta http-equiv="refresh">`.
+    meta_refresh_enabled: bool,
+    /// The navigation of the top-level page requested by a document, e.g. by following a link.
+    /// A UI takes it with `take_navigation_request()` after it sends an input to the page.
+    navigation_request: Option<HttpRequest>,
 }
 
 impl Browser {
@@ -24,6 +31,8 @@ impl Browser {
             active_page_index: 0,
             pages: Vec::new(),
             logs: Vec::new(),
+            meta_refresh_enabled: true,
+            navigation_request: None,
         }));
 
         page.set_browser(Rc::downgrade(&browser));
@@ -42,10 +51,35 @@ impl Browser {
             .push_url_for_subresource(src);
     }
 
+    pub fn meta_refresh_enabled(&self) -> bool {
+        self.meta_refresh_enabled
+    }
+
+    pub fn set_meta_refresh_enabled(&mut self, enabled: bool) {
+        self.meta_refresh_enabled = enabled;
+    }
+
+    /// Requests the UI to navigate the top-level page by `request`.
+    pub fn request_navigation(&mut self, request: HttpRequest) {
+        self.navigation_request = Some(request);
+    }
+
+    pub fn take_navigation_request(&mut self) -> Option<HttpRequest> {
+        self.navigation_request.take()
+    }
+
     pub fn logs(&self) -> Vec<Log> {
         self.logs.clone()
     }
 
+    /// Returns the parse errors in the logs.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.logs
+            .iter()
+            .filter_map(|log| log.diagnostic())
+            .collect()
+    }
+
     pub fn clear_logs(&mut self) {
         self.logs = Vec::new();
     }
@@ -61,4 +95,8 @@ impl Browser {
     pub fn console_error(&mut self, log: String) {
         self.logs.push(Log::new(LogLevel::Error, log));
     }
+
+    pub fn console_diagnostic(&mut self, diagnostic: Diagnostic) {
+        self.logs.push(Log::from_diagnostic(diagnostic));
+    }
 }
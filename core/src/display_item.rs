@@ -1,5 +1,7 @@
 //! This is used from UI component.
 
+use crate::constants::CHAR_HEIGHT_WITH_PADDING;
+use crate::constants::CHAR_WIDTH;
 use crate::renderer::layout::computed_style::ComputedStyle;
 use crate::renderer::layout::layout_point::LayoutPoint;
 use crate::renderer::layout::layout_size::LayoutSize;
@@ -46,4 +48,140 @@ impl DisplayItem {
             }
         )
     }
+
+    pub fn layout_point(&self) -> LayoutPoint {
+        match self {
+            DisplayItem::Rect { layout_point, .. }
+            | DisplayItem::Text { layout_point, .. }
+            | DisplayItem::Img { layout_point, .. } => *layout_point,
+        }
+    }
+
+    /// Returns this item moved to `layout_point`.
+    fn moved_to(&self, point: LayoutPoint) -> Self {
+        let mut item = self.clone();
+        match &mut item {
+            DisplayItem::Rect { layout_point, .. }
+            | DisplayItem::Text { layout_point, .. }
+            | DisplayItem::Img { layout_point, .. } => *layout_point = point,
+        }
+        item
+    }
+
+    /// Returns this item moved by `offset`. This is used to paint the content of a child frame
+    /// into the display list of its parent.
+    pub fn translate(&self, offset: LayoutPoint) -> Self {
+        let point = self.layout_point();
+        self.moved_to(LayoutPoint::new(
+            point.x() + offset.x(),
+            point.y() + offset.y(),
+        ))
+    }
+
+    /// Returns the part of this item inside the rectangle at `clip_point` with `clip_size`, or
+    /// None if nothing is inside. A text is clipped by characters, and a line of text or an image
+    /// which doesn't fit vertically is dropped.
+    pub fn clip(&self, clip_point: LayoutPoint, clip_size: LayoutSize) -> Option<Self> {
+        let (left, top) = (clip_point.x(), clip_point.y());
+        let (right, bottom) = (left + clip_size.width(), top + clip_size.height());
+        let point = self.layout_point();
+
+        match self {
+            DisplayItem::Rect {
+                style, layout_size, ..
+            } => {
+                let x = point.x().max(left);
+                let y = point.y().max(top);
+                let width = (point.x() + layout_size.width()).min(right) - x;
+                let height = (point.y() + layout_size.height()).min(bottom) - y;
+                if width <= 0 || height <= 0 {
+                    return None;
+                }
+                Some(DisplayItem::Rect {
+                    style: style.clone(),
+                    layout_point: LayoutPoint::new(x, y),
+                    layout_size: LayoutSize::new(width, height),
+                })
+            }
+            DisplayItem::Text { text, style, .. } => {
+                let ratio = style.font_size().ratio();
+                let char_width = CHAR_WIDTH * ratio;
+                if point.y() < top || point.y() + CHAR_HEIGHT_WITH_PADDING * ratio > bottom {
+                    return None;
+                }
+                // Drop the characters which start on the left of the rectangle and the ones which
+                // end on the right of it.
+                let skipped = if point.x() < left {
+                    (left - point.x() + char_width - 1) / char_width
+                } else {
+                    0
+                };
+                let x = point.x() + skipped * char_width;
+                let count = (right - x) / char_width;
+                if count <= 0 {
+                    return None;
+                }
+                let text: String = text
+                    .chars()
+                    .skip(skipped as usize)
+                    .take(count as usize)
+                    .collect();
+                if text.is_empty() {
+                    return None;
+                }
+                Some(DisplayItem::Text {
+                    text,
+                    style: style.clone(),
+                    layout_point: LayoutPoint::new(x, point.y()),
+                })
+            }
+            DisplayItem::Img { .. } => {
+                if point.x() < left || point.x() >= right || point.y() < top || point.y() >= bottom
+                {
+                    return None;
+                }
+                Some(self.clone())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::dom::node::Node;
+    use crate::renderer::dom::node::NodeKind;
+    use alloc::rc::Rc;
+    use alloc::string::ToString;
+    use core::cell::RefCell;
+
+    #[test]
+    fn test_clip_text() {
+        let mut style = ComputedStyle::new();
+        let node = Rc::new(RefCell::new(Node::new(NodeKind::Text(
+            "abcdef".to_string(),
+        ))));
+        style.defaulting(&node, None);
+        let item = DisplayItem::Text {
+            text: "abcdef".to_string(),
+            style: style.clone(),
+            layout_point: LayoutPoint::new(0, 10),
+        };
+        let clip_size = LayoutSize::new(CHAR_WIDTH * 3, CHAR_HEIGHT_WITH_PADDING);
+
+        // The characters outside of the rectangle are dropped.
+        let clipped = item
+            .translate(LayoutPoint::new(-CHAR_WIDTH, 0))
+            .clip(LayoutPoint::new(0, 10), clip_size);
+        assert_eq!(
+            Some(DisplayItem::Text {
+                text: "bcd".to_string(),
+                style: style.clone(),
+                layout_point: LayoutPoint::new(0, 10),
+            }),
+            clipped
+        );
+        // A line which doesn't fit vertically is dropped.
+        assert_eq!(None, item.clip(LayoutPoint::new(0, 11), clip_size));
+    }
 }
@@ -0,0 +1,549 @@
+//! The decoders in the Encoding Standard. Every decoder uses the "replacement" error mode, which
+//! emits U+FFFD REPLACEMENT CHARACTER for an error.
+//! https://encoding.spec.whatwg.org/#decoders
+
+use crate::encoding::index::{JIS0208, JIS0212, WINDOWS_1252};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+const REPLACEMENT_CHARACTER: char = '\u{FFFD}';
+
+/// https://encoding.spec.whatwg.org/#concept-stream
+///
+/// A stream of bytes which can have bytes prepended to it.
+struct ByteQueue<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    /// The bytes prepended to the stream in the reverse order.
+    prepended: Vec<u8>,
+}
+
+impl<'a> ByteQueue<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            position: 0,
+            prepended: Vec::new(),
+        }
+    }
+
+    /// Returns `None` for end-of-queue.
+    fn read(&mut self) -> Option<u8> {
+        if let Some(b) = self.prepended.pop() {
+            return Some(b);
+        }
+
+        let b = self.bytes.get(self.position).copied();
+        if b.is_some() {
+            self.position += 1;
+        }
+        b
+    }
+
+    fn prepend(&mut self, b: u8) {
+        self.prepended.push(b);
+    }
+}
+
+/// Returns the code point for `pointer` in `index`.
+/// https://encoding.spec.whatwg.org/#index-code-point
+fn index_code_point(index: &[u16], pointer: usize) -> Option<char> {
+    match index.get(pointer) {
+        Some(0) | None => None,
+        Some(c) => char::from_u32(*c as u32),
+    }
+}
+
+/// https://encoding.spec.whatwg.org/#utf-8-decoder
+pub fn decode_utf8(bytes: &[u8]) -> String {
+    // `from_utf8_lossy` replaces each maximal subpart of an ill-formed sequence with one U+FFFD
+    // REPLACEMENT CHARACTER, which is what the UTF-8 decoder does.
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+/// https://encoding.spec.whatwg.org/#shared-utf-16-decoder
+pub fn decode_utf16(bytes: &[u8], is_big_endian: bool) -> String {
+    let units = bytes.chunks(2).map(|pair| {
+        if pair.len() < 2 {
+            // "2. If byte is end-of-queue and either UTF-16 lead byte or UTF-16 lead surrogate is
+            // non-null, set UTF-16 lead byte and UTF-16 lead surrogate to null, and return error."
+            return None;
+        }
+
+        // "5. Let code unit be the result of:
+        //   is UTF-16BE decoder is true: (UTF-16 lead byte << 8) + byte.
+        //   is UTF-16BE decoder is false: (byte << 8) + UTF-16 lead byte."
+        Some(if is_big_endian {
+            u16::from_be_bytes([pair[0], pair[1]])
+        } else {
+            u16::from_le_bytes([pair[0], pair[1]])
+        })
+    });
+
+    let mut s = String::new();
+    let mut complete_units = Vec::new();
+    for unit in units {
+        match unit {
+            Some(u) => complete_units.push(u),
+            None => {
+                s.extend(
+                    char::decode_utf16(complete_units.drain(..))
+                        .map(|c| c.unwrap_or(REPLACEMENT_CHARACTER)),
+                );
+                s.push(REPLACEMENT_CHARACTER);
+            }
+        }
+    }
+    s.extend(char::decode_utf16(complete_units).map(|c| c.unwrap_or(REPLACEMENT_CHARACTER)));
+    s
+}
+
+/// https://encoding.spec.whatwg.org/#shift_jis-decoder
+pub fn decode_shift_jis(bytes: &[u8]) -> String {
+    let mut s = String::new();
+    let mut queue = ByteQueue::new(bytes);
+    // "Shift_JIS's decoder has an associated Shift_JIS lead (initially 0x00)."
+    let mut lead: u8 = 0;
+
+    loop {
+        let byte = match queue.read() {
+            Some(b) => b,
+            None => {
+                // "1. If byte is end-of-queue and Shift_JIS lead is not 0x00, set Shift_JIS lead
+                // to 0x00 and return error.
+                // 2. If byte is end-of-queue and Shift_JIS lead is 0x00, return finished."
+                if lead != 0 {
+                    s.push(REPLACEMENT_CHARACTER);
+                }
+                return s;
+            }
+        };
+
+        // "3. If Shift_JIS lead is not 0x00, let lead be Shift_JIS lead, let pointer be null,
+        // set Shift_JIS lead to 0x00, and then:"
+        if lead != 0 {
+            let l = lead as usize;
+            lead = 0;
+            let b = byte as usize;
+
+            // "1. Let offset be 0x40, if byte is less than 0x7F, otherwise 0x41.
+            // 2. Let lead offset be 0x81, if lead is less than 0xA0, otherwise 0xC1.
+            // 3. If byte is in the range 0x40 to 0x7E, inclusive, or 0x80 to 0xFC, inclusive, set
+            // pointer to (lead − lead offset) × 188 + byte − offset."
+            let offset = if b < 0x7F { 0x40 } else { 0x41 };
+            let lead_offset = if l < 0xA0 { 0x81 } else { 0xC1 };
+            let pointer = if (0x40..=0x7E).contains(&b) || (0x80..=0xFC).contains(&b) {
+                Some((l - lead_offset) * 188 + b - offset)
+            } else {
+                None
+            };
+
+            // "4. If pointer is in the range 8836 to 10715, inclusive, return a code point whose
+            // value is 0xE000 − 8836 + pointer.
+            // 5. Let code point be null, if pointer is null, otherwise the index code point for
+            // pointer in index jis0208."
+            let code_point = match pointer {
+                Some(p) if (8836..=10715).contains(&p) => {
+                    char::from_u32((0xE000 - 8836 + p) as u32)
+                }
+                Some(p) => index_code_point(&JIS0208, p),
+                None => None,
+            };
+
+            // "6. If code point is non-null, return a code point whose value is code point.
+            // 7. If byte is an ASCII byte, restore byte to ioQueue.
+            // 8. Return error."
+            match code_point {
+                Some(c) => s.push(c),
+                None => {
+                    if byte.is_ascii() {
+                        queue.prepend(byte);
+                    }
+                    s.push(REPLACEMENT_CHARACTER);
+                }
+            }
+            continue;
+        }
+
+        match byte {
+            // "4. If byte is an ASCII byte or 0x80, return a code point whose value is byte."
+            0x00..=0x80 => s.push(byte as char),
+            // "5. If byte is in the range 0xA1 to 0xDF, inclusive, return a code point whose value
+            // is 0xFF61 − 0xA1 + byte."
+            0xA1..=0xDF => {
+                s.push(char::from_u32(0xFF61 - 0xA1 + byte as u32).unwrap_or(REPLACEMENT_CHARACTER))
+            }
+            // "6. If byte is in the range 0x81 to 0x9F, inclusive, or 0xE0 to 0xFC, inclusive, set
+            // Shift_JIS lead to byte and return continue."
+            0x81..=0x9F | 0xE0..=0xFC => lead = byte,
+            // "7. Return error."
+            _ => s.push(REPLACEMENT_CHARACTER),
+        }
+    }
+}
+
+/// https://encoding.spec.whatwg.org/#euc-jp-decoder
+pub fn decode_euc_jp(bytes: &[u8]) -> String {
+    let mut s = String::new();
+    let mut queue = ByteQueue::new(bytes);
+    // "EUC-JP's decoder has an associated EUC-JP jis0212 (initially false) and EUC-JP lead
+    // (initially 0x00)."
+    let mut jis0212 = false;
+    let mut lead: u8 = 0;
+
+    loop {
+        let byte = match queue.read() {
+            Some(b) => b,
+            None => {
+                // "1. If byte is end-of-queue and EUC-JP lead is not 0x00, set EUC-JP lead to 0x00,
+                // and return error.
+                // 2. If byte is end-of-queue and EUC-JP lead is 0x00, return finished."
+                if lead != 0 {
+                    s.push(REPLACEMENT_CHARACTER);
+                }
+                return s;
+            }
+        };
+
+        // "3. If EUC-JP lead is 0x8E and byte is in the range 0xA1 to 0xDF, inclusive, set EUC-JP
+        // lead to 0x00 and return a code point whose value is 0xFF61 − 0xA1 + byte."
+        if lead == 0x8E && (0xA1..=0xDF).contains(&byte) {
+            lead = 0;
+            s.push(char::from_u32(0xFF61 - 0xA1 + byte as u32).unwrap_or(REPLACEMENT_CHARACTER));
+            continue;
+        }
+
+        // "4. If EUC-JP lead is 0x8F and byte is in the range 0xA1 to 0xFE, inclusive, set the
+        // EUC-JP jis0212 to true, set EUC-JP lead to byte, and return continue."
+        if lead == 0x8F && (0xA1..=0xFE).contains(&byte) {
+            jis0212 = true;
+            lead = byte;
+            continue;
+        }
+
+        // "5. If EUC-JP lead is not 0x00, let lead be EUC-JP lead, set EUC-JP lead to 0x00, and
+        // then:"
+        if lead != 0 {
+            let l = lead;
+            lead = 0;
+
+            // "1. Let code point be null.
+            // 2. If lead and byte are both in the range 0xA1 to 0xFE, inclusive, then set code
+            // point to the index code point for (lead − 0xA1) × 94 + byte − 0xA1 in index jis0208
+            // if EUC-JP jis0212 is false and in index jis0212 otherwise."
+            let code_point = if (0xA1..=0xFE).contains(&l) && (0xA1..=0xFE).contains(&byte) {
+                let pointer = (l as usize - 0xA1) * 94 + byte as usize - 0xA1;
+                if jis0212 {
+                    index_code_point(&JIS0212, pointer)
+                } else {
+                    index_code_point(&JIS0208, pointer)
+                }
+            } else {
+                None
+            };
+
+            // "3. Set EUC-JP jis0212 to false.
+            // 4. If code point is non-null, return a code point whose value is code point.
+            // 5. If byte is an ASCII byte, restore byte to ioQueue.
+            // 6. Return error."
+            jis0212 = false;
+            match code_point {
+                Some(c) => s.push(c),
+                None => {
+                    if byte.is_ascii() {
+                        queue.prepend(byte);
+                    }
+                    s.push(REPLACEMENT_CHARACTER);
+                }
+            }
+            continue;
+        }
+
+        match byte {
+            // "6. If byte is an ASCII byte, return a code point whose value is byte."
+            0x00..=0x7F => s.push(byte as char),
+            // "7. If byte is 0x8E, 0x8F, or in the range 0xA1 to 0xFE, inclusive, set EUC-JP lead
+            // to byte and return continue."
+            0x8E | 0x8F | 0xA1..=0xFE => lead = byte,
+            // "8. Return error."
+            _ => s.push(REPLACEMENT_CHARACTER),
+        }
+    }
+}
+
+/// https://encoding.spec.whatwg.org/#iso-2022-jp-decoder
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Iso2022JpState {
+    Ascii,
+    Roman,
+    Katakana,
+    LeadByte,
+    TrailByte,
+    EscapeStart,
+    Escape,
+}
+
+/// https://encoding.spec.whatwg.org/#iso-2022-jp-decoder
+pub fn decode_iso_2022_jp(bytes: &[u8]) -> String {
+    let mut s = String::new();
+    let mut queue = ByteQueue::new(bytes);
+    // "ISO-2022-JP's decoder has an associated ISO-2022-JP decoder state (initially ASCII),
+    // ISO-2022-JP decoder output state (initially ASCII), ISO-2022-JP lead (initially 0x00), and
+    // ISO-2022-JP output (initially false)."
+    let mut state = Iso2022JpState::Ascii;
+    let mut output_state = Iso2022JpState::Ascii;
+    let mut lead: u8 = 0;
+    let mut output = false;
+
+    loop {
+        let byte = queue.read();
+
+        match state {
+            Iso2022JpState::Ascii => match byte {
+                Some(0x1B) => state = Iso2022JpState::EscapeStart,
+                Some(b @ 0x00..=0x7F) if b != 0x0E && b != 0x0F => {
+                    output = false;
+                    s.push(b as char);
+                }
+                None => return s,
+                _ => {
+                    output = false;
+                    s.push(REPLACEMENT_CHARACTER);
+                }
+            },
+            Iso2022JpState::Roman => match byte {
+                Some(0x1B) => state = Iso2022JpState::EscapeStart,
+                Some(0x5C) => {
+                    output = false;
+                    s.push('\u{A5}');
+                }
+                Some(0x7E) => {
+                    output = false;
+                    s.push('\u{203E}');
+                }
+                Some(b @ 0x00..=0x7F) if b != 0x0E && b != 0x0F => {
+                    output = false;
+                    s.push(b as char);
+                }
+                None => return s,
+                _ => {
+                    output = false;
+                    s.push(REPLACEMENT_CHARACTER);
+                }
+            },
+            Iso2022JpState::Katakana => match byte {
+                Some(0x1B) => state = Iso2022JpState::EscapeStart,
+                Some(b @ 0x21..=0x5F) => {
+                    output = false;
+                    s.push(
+                        char::from_u32(0xFF61 - 0x21 + b as u32).unwrap_or(REPLACEMENT_CHARACTER),
+                    );
+                }
+                None => return s,
+                _ => {
+                    output = false;
+                    s.push(REPLACEMENT_CHARACTER);
+                }
+            },
+            Iso2022JpState::LeadByte => match byte {
+                Some(0x1B) => state = Iso2022JpState::EscapeStart,
+                Some(b @ 0x21..=0x7E) => {
+                    output = false;
+                    lead = b;
+                    state = Iso2022JpState::TrailByte;
+                }
+                None => return s,
+                _ => {
+                    output = false;
+                    s.push(REPLACEMENT_CHARACTER);
+                }
+            },
+            Iso2022JpState::TrailByte => match byte {
+                Some(0x1B) => {
+                    state = Iso2022JpState::EscapeStart;
+                    s.push(REPLACEMENT_CHARACTER);
+                }
+                Some(b @ 0x21..=0x7E) => {
+                    // "1. Set the ISO-2022-JP decoder state to Lead byte.
+                    // 2. Let pointer be (ISO-2022-JP lead − 0x21) × 94 + byte − 0x21.
+                    // 3. Let code point be the index code point for pointer in index jis0208.
+                    // 4. If code point is null, return error.
+                    // 5. Return a code point whose value is code point."
+                    state = Iso2022JpState::LeadByte;
+                    let pointer = (lead as usize - 0x21) * 94 + b as usize - 0x21;
+                    s.push(index_code_point(&JIS0208, pointer).unwrap_or(REPLACEMENT_CHARACTER));
+                }
+                _ => {
+                    state = Iso2022JpState::LeadByte;
+                    s.push(REPLACEMENT_CHARACTER);
+                }
+            },
+            Iso2022JpState::EscapeStart => {
+                // "1. If byte is either 0x24 or 0x28, set ISO-2022-JP lead to byte, ISO-2022-JP
+                // decoder state to escape, and return continue.
+                // 2. Restore byte to ioQueue.
+                // 3. Set ISO-2022-JP output to false, ISO-2022-JP decoder state to ISO-2022-JP
+                // decoder output state, and return error."
+                match byte {
+                    Some(b @ (0x24 | 0x28)) => {
+                        lead = b;
+                        state = Iso2022JpState::Escape;
+                    }
+                    _ => {
+                        if let Some(b) = byte {
+                            queue.prepend(b);
+                        }
+                        output = false;
+                        state = output_state;
+                        s.push(REPLACEMENT_CHARACTER);
+                    }
+                }
+            }
+            Iso2022JpState::Escape => {
+                // "1. Let lead be ISO-2022-JP lead and set ISO-2022-JP lead to 0x00.
+                // 2. Let state be null.
+                // 3. If lead is 0x28 and byte is 0x42, set state to ASCII.
+                // 4. If lead is 0x28 and byte is 0x4A, set state to Roman.
+                // 5. If lead is 0x28 and byte is 0x49, set state to katakana.
+                // 6. If lead is 0x24 and byte is either 0x40 or 0x42, set state to lead byte."
+                let l = lead;
+                lead = 0;
+                let next_state = match (l, byte) {
+                    (0x28, Some(0x42)) => Some(Iso2022JpState::Ascii),
+                    (0x28, Some(0x4A)) => Some(Iso2022JpState::Roman),
+                    (0x28, Some(0x49)) => Some(Iso2022JpState::Katakana),
+                    (0x24, Some(0x40 | 0x42)) => Some(Iso2022JpState::LeadByte),
+                    _ => None,
+                };
+
+                match next_state {
+                    // "7. If state is non-null, then:
+                    //   1. Set ISO-2022-JP decoder state and ISO-2022-JP decoder output state to
+                    //   state.
+                    //   2. Let output be the value of ISO-2022-JP output.
+                    //   3. Set ISO-2022-JP output to true.
+                    //   4. Return continue, if output is false, and error otherwise."
+                    Some(next) => {
+                        state = next;
+                        output_state = next;
+                        if output {
+                            s.push(REPLACEMENT_CHARACTER);
+                        }
+                        output = true;
+                    }
+                    // "8. If byte is end-of-queue, then restore lead to ioQueue; otherwise, restore
+                    // « lead, byte » to ioQueue.
+                    // 9. Set ISO-2022-JP output to false, ISO-2022-JP decoder state to ISO-2022-JP
+                    // decoder output state and return error."
+                    None => {
+                        if let Some(b) = byte {
+                            queue.prepend(b);
+                        }
+                        queue.prepend(l);
+                        output = false;
+                        state = output_state;
+                        s.push(REPLACEMENT_CHARACTER);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// https://encoding.spec.whatwg.org/#single-byte-decoder
+pub fn decode_windows_1252(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| {
+            // "2. If byte is an ASCII byte, return a code point whose value is byte.
+            // 3. Let code point be the index code point for byte − 0x80 in index single-byte.
+            // 5. Return a code point whose value is code point."
+            if b.is_ascii() {
+                *b as char
+            } else {
+                index_code_point(&WINDOWS_1252, *b as usize - 0x80).unwrap_or(REPLACEMENT_CHARACTER)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_utf8() {
+        assert_eq!(
+            "あ\u{FFFD}a".to_string(),
+            decode_utf8(&[0xE3, 0x81, 0x82, 0xE3, 0x81, 0x61])
+        );
+    }
+
+    #[test]
+    fn test_utf16() {
+        assert_eq!(
+            "aあ😀".to_string(),
+            decode_utf16(&[0x00, 0x61, 0x30, 0x42, 0xD8, 0x3D, 0xDE, 0x00], true)
+        );
+        assert_eq!(
+            "a\u{FFFD}".to_string(),
+            decode_utf16(&[0x61, 0x00, 0x00, 0xD8], false)
+        );
+        assert_eq!(
+            "a\u{FFFD}".to_string(),
+            decode_utf16(&[0x61, 0x00, 0x62], false)
+        );
+    }
+
+    #[test]
+    fn test_shift_jis() {
+        // "日本語" followed by a half-width katakana "ｱ", a wave dash and a user-defined character.
+        assert_eq!(
+            "日本語ｱ〜\u{E000}".to_string(),
+            decode_shift_jis(&[0x93, 0xFA, 0x96, 0x7B, 0x8C, 0xEA, 0xB1, 0x81, 0x60, 0xF0, 0x40])
+        );
+        // An invalid trail byte which is an ASCII byte is processed again.
+        assert_eq!("\u{FFFD}?".to_string(), decode_shift_jis(&[0x81, 0x3F]));
+        assert_eq!("a\u{FFFD}".to_string(), decode_shift_jis(&[0x61, 0x93]));
+    }
+
+    #[test]
+    fn test_euc_jp() {
+        // "日本語", a half-width katakana "ｱ" and "丂" in JIS X 0212.
+        assert_eq!(
+            "日本語ｱ丂".to_string(),
+            decode_euc_jp(&[0xC6, 0xFC, 0xCB, 0xDC, 0xB8, 0xEC, 0x8E, 0xB1, 0x8F, 0xB0, 0xA1])
+        );
+        assert_eq!("\u{FFFD}a".to_string(), decode_euc_jp(&[0xC6, 0x61]));
+    }
+
+    #[test]
+    fn test_iso_2022_jp() {
+        // ESC $ B switches to JIS X 0208 and ESC ( B switches back to ASCII.
+        assert_eq!(
+            "a日本b".to_string(),
+            decode_iso_2022_jp(&[
+                0x61, 0x1B, 0x24, 0x42, 0x46, 0x7C, 0x4B, 0x5C, 0x1B, 0x28, 0x42, 0x62
+            ])
+        );
+        // ESC ( J switches to JIS X 0201 Roman, and ESC ( I to katakana.
+        assert_eq!(
+            "¥‾ｱ".to_string(),
+            decode_iso_2022_jp(&[0x1B, 0x28, 0x4A, 0x5C, 0x7E, 0x1B, 0x28, 0x49, 0x31])
+        );
+        // An unknown escape sequence is an error and its bytes are processed again.
+        assert_eq!(
+            "\u{FFFD}(Z".to_string(),
+            decode_iso_2022_jp(&[0x1B, 0x28, 0x5A])
+        );
+    }
+
+    #[test]
+    fn test_windows_1252() {
+        assert_eq!(
+            "a€é\u{81}".to_string(),
+            decode_windows_1252(&[0x61, 0x80, 0xE9, 0x81])
+        );
+    }
+}
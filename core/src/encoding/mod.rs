@@ -0,0 +1,139 @@
+//! A subset of the Encoding Standard.
+//! https://encoding.spec.whatwg.org/
+//!
+//! This module supports UTF-8, UTF-16BE, UTF-16LE, Shift_JIS, EUC-JP, ISO-2022-JP and
+//! windows-1252, and decodes a byte stream in one of them into a string.
+
+use alloc::string::String;
+
+pub mod decoder;
+pub mod index;
+pub mod sniff;
+
+/// https://encoding.spec.whatwg.org/#encoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// https://encoding.spec.whatwg.org/#utf-8
+    Utf8,
+    /// https://encoding.spec.whatwg.org/#utf-16be
+    Utf16Be,
+    /// https://encoding.spec.whatwg.org/#utf-16le
+    Utf16Le,
+    /// https://encoding.spec.whatwg.org/#shift_jis
+    ShiftJis,
+    /// https://encoding.spec.whatwg.org/#euc-jp
+    EucJp,
+    /// https://encoding.spec.whatwg.org/#iso-2022-jp
+    Iso2022Jp,
+    /// https://encoding.spec.whatwg.org/#windows-1252
+    Windows1252,
+}
+
+impl Encoding {
+    /// https://encoding.spec.whatwg.org/#concept-encoding-get
+    pub fn for_label(label: &str) -> Option<Self> {
+        // "1. Remove any leading and trailing ASCII whitespace from label.
+        // 2. If label is an ASCII case-insensitive match for any of the labels listed in the
+        // table below, then return the corresponding encoding; otherwise return failure."
+        let label = label
+            .trim_matches(|c: char| c.is_ascii_whitespace())
+            .to_ascii_lowercase();
+
+        match label.as_str() {
+            "unicode-1-1-utf-8" | "unicode11utf8" | "unicode20utf8" | "utf-8" | "utf8"
+            | "x-unicode20utf8" => Some(Encoding::Utf8),
+            "unicodefffe" | "utf-16be" => Some(Encoding::Utf16Be),
+            "csunicode" | "iso-10646-ucs-2" | "ucs-2" | "unicode" | "unicodefeff" | "utf-16"
+            | "utf-16le" => Some(Encoding::Utf16Le),
+            "csshiftjis" | "ms932" | "ms_kanji" | "shift-jis" | "shift_jis" | "sjis"
+            | "windows-31j" | "x-sjis" => Some(Encoding::ShiftJis),
+            "cseucpkdfmtjapanese" | "euc-jp" | "x-euc-jp" => Some(Encoding::EucJp),
+            "csiso2022jp" | "iso-2022-jp" => Some(Encoding::Iso2022Jp),
+            "ansi_x3.4-1968" | "ascii" | "cp1252" | "cp819" | "csisolatin1" | "ibm819"
+            | "iso-8859-1" | "iso-ir-100" | "iso8859-1" | "iso88591" | "iso_8859-1"
+            | "iso_8859-1:1987" | "l1" | "latin1" | "us-ascii" | "windows-1252" | "x-cp1252" => {
+                Some(Encoding::Windows1252)
+            }
+            _ => None,
+        }
+    }
+
+    /// https://encoding.spec.whatwg.org/#concept-encoding-name
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::ShiftJis => "Shift_JIS",
+            Encoding::EucJp => "EUC-JP",
+            Encoding::Iso2022Jp => "ISO-2022-JP",
+            Encoding::Windows1252 => "windows-1252",
+        }
+    }
+
+    /// Decodes `bytes` into a string. A BOM at the start of `bytes` takes precedence over this
+    /// encoding.
+    /// https://encoding.spec.whatwg.org/#decode
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        // "1. Let BOMEncoding be the result of BOM sniffing ioQueue.
+        // 2. If BOMEncoding is non-null:
+        //   1. Set encoding to BOMEncoding.
+        //   2. Read three bytes from ioQueue, if BOMEncoding is UTF-8; otherwise read two bytes."
+        let (encoding, bytes) = match sniff::bom_sniff(bytes) {
+            Some(Encoding::Utf8) => (Encoding::Utf8, &bytes[3..]),
+            Some(e) => (e, &bytes[2..]),
+            None => (*self, bytes),
+        };
+
+        // "4. Process a queue with an instance of encoding's decoder, ioQueue, output, and
+        // "replacement"."
+        match encoding {
+            Encoding::Utf8 => decoder::decode_utf8(bytes),
+            Encoding::Utf16Be => decoder::decode_utf16(bytes, true),
+            Encoding::Utf16Le => decoder::decode_utf16(bytes, false),
+            Encoding::ShiftJis => decoder::decode_shift_jis(bytes),
+            Encoding::EucJp => decoder::decode_euc_jp(bytes),
+            Encoding::Iso2022Jp => decoder::decode_iso_2022_jp(bytes),
+            Encoding::Windows1252 => decoder::decode_windows_1252(bytes),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_for_label() {
+        assert_eq!(
+            Some(Encoding::ShiftJis),
+            Encoding::for_label(" Shift_JIS\n")
+        );
+        assert_eq!(Some(Encoding::ShiftJis), Encoding::for_label("windows-31j"));
+        assert_eq!(Some(Encoding::EucJp), Encoding::for_label("EUC-JP"));
+        assert_eq!(Some(Encoding::Utf16Le), Encoding::for_label("utf-16"));
+        assert_eq!(
+            Some(Encoding::Windows1252),
+            Encoding::for_label("iso-8859-1")
+        );
+        assert_eq!(None, Encoding::for_label("utf-7"));
+    }
+
+    #[test]
+    fn test_decode_with_bom() {
+        // A BOM wins over the given encoding.
+        assert_eq!(
+            "a".to_string(),
+            Encoding::ShiftJis.decode(&[0xEF, 0xBB, 0xBF, 0x61])
+        );
+        assert_eq!(
+            "a".to_string(),
+            Encoding::Utf8.decode(&[0xFE, 0xFF, 0x00, 0x61])
+        );
+        assert_eq!(
+            "a".to_string(),
+            Encoding::Utf8.decode(&[0xFF, 0xFE, 0x61, 0x00])
+        );
+    }
+}
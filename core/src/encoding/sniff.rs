@@ -0,0 +1,497 @@
+//! The encoding sniffing algorithm in the HTML spec.
+//! https://html.spec.whatwg.org/multipage/parsing.html#determining-the-character-encoding
+
+use crate::encoding::Encoding;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// The encoding used when no encoding is found in a byte stream. The spec leaves the default to
+/// an implementation, and this browser has assumed UTF-8 for every document.
+const DEFAULT_ENCODING: Encoding = Encoding::Utf8;
+
+/// How many bytes the prescan looks at.
+/// https://html.spec.whatwg.org/multipage/parsing.html#prescan-a-byte-stream-to-determine-its-encoding
+const PRESCAN_LENGTH: usize = 1024;
+
+/// https://encoding.spec.whatwg.org/#bom-sniff
+pub fn bom_sniff(bytes: &[u8]) -> Option<Encoding> {
+    // "2. For each of the rows in the following table, starting with the first one and going
+    // down, if BOM starts with the bytes given in the first column, then return the encoding given
+    // in the cell in the second column of that row. Otherwise, return null."
+    if bytes.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        Some(Encoding::Utf8)
+    } else if bytes.starts_with(&[0xFE, 0xFF]) {
+        Some(Encoding::Utf16Be)
+    } else if bytes.starts_with(&[0xFF, 0xFE]) {
+        Some(Encoding::Utf16Le)
+    } else {
+        None
+    }
+}
+
+/// Returns the encoding of `bytes`. `transport_encoding` is the encoding given by the charset
+/// parameter of the Content-Type header, if any.
+/// https://html.spec.whatwg.org/multipage/parsing.html#encoding-sniffing-algorithm
+pub fn sniff(bytes: &[u8], transport_encoding: Option<Encoding>) -> Encoding {
+    // "1. If the result of BOM sniffing is an encoding, return that encoding with confidence
+    // certain."
+    if let Some(encoding) = bom_sniff(bytes) {
+        return encoding;
+    }
+
+    // "4. If the transport layer specifies a character encoding, and it is supported, return that
+    // encoding with the confidence certain."
+    if let Some(encoding) = transport_encoding {
+        return encoding;
+    }
+
+    // "5. Optionally, prescan the byte stream to determine its encoding, with the end condition
+    // being when the user agent decides that scanning further bytes would not be efficient. ...
+    // If the result of prescanning is not failure, return it with confidence tentative."
+    let end = bytes.len().min(PRESCAN_LENGTH);
+    if let Some(encoding) = prescan(&bytes[..end]) {
+        return encoding;
+    }
+
+    // "9. Otherwise, return an implementation-defined or user-specified default character
+    // encoding, with the confidence tentative."
+    DEFAULT_ENCODING
+}
+
+/// https://html.spec.whatwg.org/multipage/parsing.html#prescan-a-byte-stream-to-determine-its-encoding
+pub fn prescan(bytes: &[u8]) -> Option<Encoding> {
+    // "3. Prescan for UTF-16 XML declarations: If position points to:
+    //   A sequence of bytes starting with: 0x3C, 0x0, 0x3F, 0x0, 0x78, 0x0
+    //     Return UTF-16LE.
+    //   A sequence of bytes starting with: 0x0, 0x3C, 0x0, 0x3F, 0x0, 0x78
+    //     Return UTF-16BE."
+    if bytes.starts_with(&[0x3C, 0x00, 0x3F, 0x00, 0x78, 0x00]) {
+        return Some(Encoding::Utf16Le);
+    }
+    if bytes.starts_with(&[0x00, 0x3C, 0x00, 0x3F, 0x00, 0x78]) {
+        return Some(Encoding::Utf16Be);
+    }
+
+    let starts_with_ignore_case = |position: usize, pattern: &[u8]| match bytes.get(position..) {
+        Some(rest) => {
+            rest.len() >= pattern.len() && rest[..pattern.len()].eq_ignore_ascii_case(pattern)
+        }
+        None => false,
+    };
+
+    // "4. Loop: If position points to:"
+    let mut position = 0;
+    while position < bytes.len() {
+        if starts_with_ignore_case(position, b"<!--") {
+            // "A sequence of bytes starting with: 0x3C 0x21 0x2D 0x2D (`<!--`)
+            //   Advance the position pointer so that it points at the first 0x3E byte which is
+            //   preceded by two 0x2D bytes (i.e. at the end of an ASCII '-->' sequence) and comes
+            //   after the 0x3C byte that was found. (The two 0x2D bytes can be the same as those in
+            //   the '<!--' sequence.)"
+            position += 2;
+            while position < bytes.len() && !starts_with_ignore_case(position, b"-->") {
+                position += 1;
+            }
+            position += 2;
+        } else if starts_with_ignore_case(position, b"<meta")
+            && bytes
+                .get(position + 5)
+                .is_some_and(|b| b.is_ascii_whitespace() || *b == b'/')
+        {
+            // "A sequence of bytes starting with: 0x3C, 0x4D or 0x6D, 0x45 or 0x65, 0x54 or 0x74,
+            // 0x41 or 0x61, and one of 0x09, 0x0A, 0x0C, 0x0D, 0x20, 0x2F (case-insensitive ASCII
+            // '<meta' followed by a space or slash)"
+            position += 6;
+            if let Some(encoding) = prescan_meta(bytes, &mut position) {
+                return Some(encoding);
+            }
+            continue;
+        } else if bytes.get(position) == Some(&b'<')
+            && (bytes
+                .get(position + 1)
+                .is_some_and(|b| b.is_ascii_alphabetic())
+                || (bytes.get(position + 1) == Some(&b'/')
+                    && bytes
+                        .get(position + 2)
+                        .is_some_and(|b| b.is_ascii_alphabetic())))
+        {
+            // "A sequence of bytes starting with a 0x3C byte (<), optionally a 0x2F byte (/), and
+            // finally a byte in the range 0x41-0x5A or 0x61-0x7A (A-Z or a-z)
+            //   1. Advance the position pointer so that it points at the next 0x09 (HT), 0x0A (LF),
+            //   0x0C (FF), 0x0D (CR), 0x20 (SP), or 0x3E (>) byte.
+            //   2. Repeatedly get an attribute until no further attributes can be found, then jump
+            //   to the step below labeled next byte."
+            while position < bytes.len()
+                && !bytes[position].is_ascii_whitespace()
+                && bytes[position] != b'>'
+            {
+                position += 1;
+            }
+            while get_attribute(bytes, &mut position).is_some() {}
+        } else if starts_with_ignore_case(position, b"<!")
+            || starts_with_ignore_case(position, b"</")
+            || starts_with_ignore_case(position, b"<?")
+        {
+            // "A sequence of bytes starting with: 0x3C 0x21 (`<!`)
+            // A sequence of bytes starting with: 0x3C 0x2F (`</`)
+            // A sequence of bytes starting with: 0x3C 0x3F (`<?`)
+            //   Advance the position pointer so that it points at the first 0x3E byte (>) that
+            //   comes after the 0x3C byte that was found."
+            position += 1;
+            while position < bytes.len() && bytes[position] != b'>' {
+                position += 1;
+            }
+        }
+
+        // "Next byte: Move position so it points at the next byte in the input byte stream, and
+        // return to the step above labeled loop."
+        position += 1;
+    }
+
+    None
+}
+
+/// Processes the attributes of a `<meta>` tag. `position` points at the byte after `<meta` and
+/// a space or slash.
+fn prescan_meta(bytes: &[u8], position: &mut usize) -> Option<Encoding> {
+    // "2. Let attribute list be an empty list of strings.
+    // 3. Let got pragma be false.
+    // 4. Let need pragma be null.
+    // 5. Let charset be the null value (which, for the purposes of this algorithm, is distinct
+    // from an unrecognized encoding or the empty string)."
+    let mut attribute_list: Vec<String> = Vec::new();
+    let mut got_pragma = false;
+    let mut need_pragma: Option<bool> = None;
+    let mut charset: Option<Option<Encoding>> = None;
+
+    // "6. Attributes: Get an attribute and its value. If no attribute was sniffed, then jump to
+    // the processing step below."
+    while let Some((name, value)) = get_attribute(bytes, position) {
+        // "7. If the attribute's name is already in attribute list, then return to the step
+        // labeled attributes.
+        // 8. Add the attribute's name to attribute list."
+        if attribute_list.contains(&name) {
+            continue;
+        }
+        attribute_list.push(name.clone());
+
+        // "9. Run the appropriate step from the following list, if one applies:"
+        match name.as_str() {
+            // "If the attribute's name is "http-equiv"
+            //   If the attribute's value is "content-type", then set got pragma to true."
+            "http-equiv" => {
+                if value == "content-type" {
+                    got_pragma = true;
+                }
+            }
+            // "If the attribute's name is "content"
+            //   Apply the algorithm for extracting a character encoding from a meta element,
+            //   giving the attribute's value as the string to parse. If a character encoding is
+            //   returned, and if charset is still set to null, let charset be the encoding
+            //   returned, and set need pragma to true."
+            "content" => {
+                if let Some(encoding) = extract_encoding_from_meta(&value) {
+                    if charset.is_none() {
+                        charset = Some(Some(encoding));
+                        need_pragma = Some(true);
+                    }
+                }
+            }
+            // "If the attribute's name is "charset"
+            //   Let charset be the result of getting an encoding from the attribute's value, and
+            //   set need pragma to false."
+            "charset" => {
+                charset = Some(Encoding::for_label(&value));
+                need_pragma = Some(false);
+            }
+            _ => {}
+        }
+    }
+
+    // "11. Processing: If need pragma is null, then jump to the step below labeled next byte.
+    // 12. If need pragma is true but got pragma is false, then jump to the step below labeled next
+    // byte.
+    // 13. If charset is failure, then jump to the step below labeled next byte.
+    // 14. If charset is UTF-16BE/LE, then set charset to UTF-8.
+    // 16. Return charset."
+    match need_pragma {
+        None => return None,
+        Some(true) if !got_pragma => return None,
+        _ => {}
+    }
+    match charset.flatten() {
+        Some(Encoding::Utf16Be | Encoding::Utf16Le) => Some(Encoding::Utf8),
+        encoding => encoding,
+    }
+}
+
+/// Returns a pair of the name and the value of an attribute, or `None` if no attribute is
+/// found. Both are lowercased.
+/// https://html.spec.whatwg.org/multipage/parsing.html#concept-get-attributes-when-sniffing
+fn get_attribute(bytes: &[u8], position: &mut usize) -> Option<(String, String)> {
+    let byte = |position: &usize| bytes.get(*position).copied();
+
+    // "1. If the byte at position is one of 0x09 (HT), 0x0A (LF), 0x0C (FF), 0x0D (CR), 0x20
+    // (SP), or 0x2F (/) then advance position to the next byte and redo this step."
+    while byte(position).is_some_and(|b| b.is_ascii_whitespace() || b == b'/') {
+        *position += 1;
+    }
+
+    // "2. If the byte at position is 0x3E (>), then abort the get an attribute algorithm. There
+    // isn't one."
+    // "3. Otherwise, the byte at position is the start of the attribute name. Let attribute name
+    // and attribute value be the empty string."
+    let mut name = String::new();
+
+    // "4. Process the byte at position as follows:"
+    loop {
+        match byte(position)? {
+            // "If it is 0x3D (=), and the attribute name is longer than the empty string
+            //   Advance position to the next byte and jump to the step below labeled value."
+            b'=' if !name.is_empty() => {
+                *position += 1;
+                return get_attribute_value(bytes, position, name);
+            }
+            // "If it is 0x09 (HT), 0x0A (LF), 0x0C (FF), 0x0D (CR), or 0x20 (SP)
+            //   Jump to the step below labeled spaces."
+            b if b.is_ascii_whitespace() => break,
+            // "If it is 0x2F (/) or 0x3E (>)
+            //   Abort the get an attribute algorithm. The attribute's name is the value of
+            //   attribute name, its value is the empty string."
+            b'/' | b'>' => {
+                if name.is_empty() {
+                    return None;
+                }
+                return Some((name, String::new()));
+            }
+            // "If it is in the range 0x41 (A) to 0x5A (Z)
+            //   Append the code point b+0x20 to attribute name (where b is the value of the byte
+            //   at position).
+            // Anything else
+            //   Append the code point with the same value as the byte at position to attribute
+            //   name."
+            b => name.push(b.to_ascii_lowercase() as char),
+        }
+        // "5. Advance position to the next byte and return to the previous step."
+        *position += 1;
+    }
+
+    // "6. Spaces: If the byte at position is one of 0x09 (HT), 0x0A (LF), 0x0C (FF), 0x0D (CR),
+    // or 0x20 (SP) then advance position to the next byte, then, repeat this step."
+    while byte(position).is_some_and(|b| b.is_ascii_whitespace()) {
+        *position += 1;
+    }
+
+    // "7. If the byte at position is not 0x3D (=), abort the get an attribute algorithm. The
+    // attribute's name is the value of attribute name, its value is the empty string.
+    // 8. Advance position past the 0x3D (=) byte."
+    if byte(position)? != b'=' {
+        return Some((name, String::new()));
+    }
+    *position += 1;
+    get_attribute_value(bytes, position, name)
+}
+
+/// Runs the steps from the step labeled "value" in the "get an attribute" algorithm.
+/// https://html.spec.whatwg.org/multipage/parsing.html#concept-get-attributes-when-sniffing
+fn get_attribute_value(
+    bytes: &[u8],
+    position: &mut usize,
+    name: String,
+) -> Option<(String, String)> {
+    let byte = |position: &usize| bytes.get(*position).copied();
+    let mut value = String::new();
+
+    // "9. Value: If the byte at position is one of 0x09 (HT), 0x0A (LF), 0x0C (FF), 0x0D (CR), or
+    // 0x20 (SP) then advance position to the next byte, then, repeat this step."
+    while byte(position).is_some_and(|b| b.is_ascii_whitespace()) {
+        *position += 1;
+    }
+
+    // "10. Process the byte at position as follows:"
+    match byte(position)? {
+        // "If it is 0x22 (") or 0x27 (')
+        //   1. Let b be the value of the byte at position.
+        //   2. Quote loop: Advance position to the next byte.
+        //   3. If the value of the byte at position is the value of b, then advance position to
+        //   the next byte and abort the "get an attribute" algorithm. The attribute's name is the
+        //   value of attribute name, and its value is the value of attribute value.
+        //   4. Otherwise, if the value of the byte at position is in the range 0x41 (A) to 0x5A
+        //   (Z), then append a code point to attribute value whose value is 0x20 more than the
+        //   value of the byte at position.
+        //   5. Otherwise, append a code point to attribute value whose value is the same as the
+        //   value of the byte at position.
+        //   6. Return to the step above labeled quote loop."
+        quote @ (b'"' | b'\'') => loop {
+            *position += 1;
+            let b = byte(position)?;
+            if b == quote {
+                *position += 1;
+                return Some((name, value));
+            }
+            value.push(b.to_ascii_lowercase() as char);
+        },
+        // "If it is 0x3E (>)
+        //   Abort the get an attribute algorithm. The attribute's name is the value of attribute
+        //   name, its value is the empty string."
+        b'>' => return Some((name, value)),
+        // "If it is in the range 0x41 (A) to 0x5A (Z)
+        //   Append a code point b+0x20 to attribute value (where b is the value of the byte at
+        //   position). Advance position to the next byte.
+        // Anything else
+        //   Append a code point with the same value as the byte at position to attribute value.
+        //   Advance position to the next byte."
+        b => {
+            value.push(b.to_ascii_lowercase() as char);
+            *position += 1;
+        }
+    }
+
+    // "11. Process the byte at position as follows:
+    //   If it is 0x09 (HT), 0x0A (LF), 0x0C (FF), 0x0D (CR), 0x20 (SP), or 0x3E (>)
+    //     Abort the get an attribute algorithm. The attribute's name is the value of attribute
+    //     name and its value is the value of attribute value.
+    //   If it is in the range 0x41 (A) to 0x5A (Z)
+    //     Append a code point b+0x20 to attribute value (where b is the value of the byte at
+    //     position).
+    //   Anything else
+    //     Append a code point with the same value as the byte at position to attribute value.
+    // 12. Advance position to the next byte and return to the previous step."
+    loop {
+        let b = byte(position)?;
+        if b.is_ascii_whitespace() || b == b'>' {
+            return Some((name, value));
+        }
+        value.push(b.to_ascii_lowercase() as char);
+        *position += 1;
+    }
+}
+
+/// Returns the encoding given by `charset=` in `s`. This works for the value of a Content-Type
+/// header too because it has the same form as the content attribute of a `<meta>` element.
+/// https://html.spec.whatwg.org/multipage/urls-and-fetching.html#algorithm-for-extracting-a-character-encoding-from-a-meta-element
+pub fn extract_encoding_from_meta(s: &str) -> Option<Encoding> {
+    let lowercase = s.to_ascii_lowercase();
+    let s = lowercase.as_bytes();
+
+    // "1. Let position be a pointer into s, initially pointing at the start of the string."
+    let mut position = 0;
+    loop {
+        // "2. Loop: Find the first seven characters in s after position that are an ASCII
+        // case-insensitive match for the word "charset". If no such match is found, return
+        // nothing."
+        let found = s[position..].windows(7).position(|w| w == b"charset")?;
+        position += found + 7;
+
+        // "3. Skip any ASCII whitespace that immediately follow the word "charset" (there might
+        // not be any)."
+        while s.get(position).is_some_and(|b| b.is_ascii_whitespace()) {
+            position += 1;
+        }
+
+        // "4. If the next character is not a U+003D EQUALS SIGN (=), then move position to point
+        // just before that next character, and jump back to the step labeled loop."
+        if s.get(position) == Some(&b'=') {
+            position += 1;
+            break;
+        }
+    }
+
+    // "5. Skip any ASCII whitespace that immediately follow the equals sign (there might not be
+    // any)."
+    while s.get(position).is_some_and(|b| b.is_ascii_whitespace()) {
+        position += 1;
+    }
+
+    // "6. Process the next character as follows:"
+    match s.get(position)? {
+        // "If it is a U+0022 QUOTATION MARK character (") and there is a later U+0022 QUOTATION
+        // MARK character (") in s
+        // If it is a U+0027 APOSTROPHE character (') and there is a later U+0027 APOSTROPHE
+        // character (') in s
+        //   Return the result of getting an encoding from the substring that is between this
+        //   character and the next earliest occurrence of this character.
+        // If it is an unmatched U+0022 QUOTATION MARK character (")
+        // If it is an unmatched U+0027 APOSTROPHE character (')
+        //   Return nothing."
+        quote @ (b'"' | b'\'') => {
+            let start = position + 1;
+            let length = s[start..].iter().position(|b| b == quote)?;
+            Encoding::for_label(&lowercase[start..start + length])
+        }
+        // "Otherwise
+        //   Return the result of getting an encoding from the substring that consists of this
+        //   character up to but not including the first ASCII whitespace or U+003B SEMICOLON
+        //   character (;), or the end of s, whichever comes first."
+        _ => {
+            let length = s[position..]
+                .iter()
+                .position(|b| b.is_ascii_whitespace() || *b == b';')
+                .unwrap_or(s.len() - position);
+            Encoding::for_label(&lowercase[position..position + length])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sniff_order() {
+        // A BOM wins over the transport layer.
+        assert_eq!(
+            Encoding::Utf8,
+            sniff(&[0xEF, 0xBB, 0xBF, 0x61], Some(Encoding::ShiftJis))
+        );
+        // The transport layer wins over a meta element.
+        assert_eq!(
+            Encoding::EucJp,
+            sniff(b"<meta charset=shift_jis>", Some(Encoding::EucJp))
+        );
+        assert_eq!(Encoding::ShiftJis, sniff(b"<meta charset=shift_jis>", None));
+        assert_eq!(Encoding::Utf8, sniff(b"<p>a</p>", None));
+    }
+
+    #[test]
+    fn test_prescan() {
+        assert_eq!(
+            Some(Encoding::ShiftJis),
+            prescan(b"<!doctype html><html><head><META CHARSET=\"Shift_JIS\">")
+        );
+        assert_eq!(
+            Some(Encoding::EucJp),
+            prescan(b"<meta http-equiv=Content-Type content='text/html; charset=EUC-JP'>")
+        );
+        // The pragma is needed for the content attribute.
+        assert_eq!(None, prescan(b"<meta content='text/html; charset=EUC-JP'>"));
+        // A meta element in a comment or in an attribute value is skipped.
+        assert_eq!(
+            Some(Encoding::Iso2022Jp),
+            prescan(b"<!-- <meta charset=euc-jp> --><a title='<meta charset=euc-jp>'><meta charset=iso-2022-jp>")
+        );
+        // UTF-16 is replaced with UTF-8.
+        assert_eq!(Some(Encoding::Utf8), prescan(b"<meta charset=utf-16>"));
+    }
+
+    #[test]
+    fn test_extract_encoding_from_meta() {
+        assert_eq!(
+            Some(Encoding::ShiftJis),
+            extract_encoding_from_meta("text/html; charset=Shift_JIS")
+        );
+        assert_eq!(
+            Some(Encoding::EucJp),
+            extract_encoding_from_meta("text/html;charset = \"euc-jp\"")
+        );
+        assert_eq!(
+            Some(Encoding::Utf8),
+            extract_encoding_from_meta("charsetcharset=utf-8")
+        );
+        assert_eq!(
+            None,
+            extract_encoding_from_meta("text/html; charset='utf-8")
+        );
+        assert_eq!(None, extract_encoding_from_meta("text/html"));
+    }
+}
@@ -6,12 +6,14 @@
 //! RFC 7235: https://datatracker.ietf.org/doc/html/rfc7235
 
 use crate::alloc::string::ToString;
+use crate::encoding::sniff::{extract_encoding_from_meta, sniff};
 use crate::error::Error;
 use alloc::format;
 use alloc::string::String;
+use alloc::vec;
 use alloc::vec::Vec;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Header {
     name: String,
     value: String,
@@ -21,6 +23,66 @@ impl Header {
     pub fn new(name: String, value: String) -> Self {
         Self { name, value }
     }
+
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    pub fn value(&self) -> String {
+        self.value.clone()
+    }
+}
+
+/// A request that the browser asks the network layer to send. The UI passes it to a function
+/// that sends it over HTTP, e.g. when a user enters a URL or submits a form.
+/// https://fetch.spec.whatwg.org/#concept-request
+/// https://datatracker.ietf.org/doc/html/rfc7230#section-3.1.1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpRequest {
+    method: String,
+    url: String,
+    headers: Vec<Header>,
+    body: String,
+}
+
+impl HttpRequest {
+    /// Creates a GET request for `url`.
+    /// https://datatracker.ietf.org/doc/html/rfc7231#section-4.3.1
+    pub fn get(url: String) -> Self {
+        Self {
+            method: "GET".to_string(),
+            url,
+            headers: Vec::new(),
+            body: String::new(),
+        }
+    }
+
+    /// Creates a POST request for `url` with a body whose media type is `content_type`.
+    /// https://datatracker.ietf.org/doc/html/rfc7231#section-4.3.3
+    pub fn post(url: String, content_type: String, body: String) -> Self {
+        Self {
+            method: "POST".to_string(),
+            url,
+            headers: vec![Header::new("Content-Type".to_string(), content_type)],
+            body,
+        }
+    }
+
+    pub fn method(&self) -> String {
+        self.method.clone()
+    }
+
+    pub fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    pub fn headers(&self) -> Vec<Header> {
+        self.headers.clone()
+    }
+
+    pub fn body(&self) -> String {
+        self.body.clone()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -77,6 +139,41 @@ impl HttpResponse {
         })
     }
 
+    /// Parses a response whose body may be in an encoding other than UTF-8. The body is decoded
+    /// with the encoding given by the Content-Type header or sniffed from the body.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#determining-the-character-encoding
+    pub fn from_bytes(raw_response: &[u8]) -> Result<Self, Error> {
+        // The status line and the headers are ASCII, and the body starts after the first empty
+        // line.
+        let (head, body) = match (0..raw_response.len()).find_map(|i| {
+            let rest = &raw_response[i..];
+            if rest.starts_with(b"\r\n\r\n") {
+                Some((i, i + 4))
+            } else if rest.starts_with(b"\n\n") {
+                Some((i, i + 2))
+            } else {
+                None
+            }
+        }) {
+            Some((head_end, body_start)) => {
+                (&raw_response[..head_end], &raw_response[body_start..])
+            }
+            None => (raw_response, &[][..]),
+        };
+
+        let mut response = Self::new(String::from_utf8_lossy(head).into_owned() + "\n\n")?;
+
+        let transport_encoding = response
+            .headers
+            .iter()
+            .find(|h| h.name.eq_ignore_ascii_case("Content-Type"))
+            .and_then(|h| extract_encoding_from_meta(&h.value));
+        let encoding = sniff(body, transport_encoding);
+        response.body = encoding.decode(body).replace("\r\n", "\n");
+
+        Ok(response)
+    }
+
     pub fn version(&self) -> String {
         self.version.clone()
     }
@@ -112,6 +209,34 @@ impl HttpResponse {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_get_request() {
+        let req = HttpRequest::get("http://example.com/search?q=a".to_string());
+        assert_eq!(req.method(), "GET");
+        assert_eq!(req.url(), "http://example.com/search?q=a");
+        assert!(req.headers().is_empty());
+        assert_eq!(req.body(), "");
+    }
+
+    #[test]
+    fn test_post_request() {
+        let req = HttpRequest::post(
+            "http://example.com/login".to_string(),
+            "application/x-www-form-urlencoded".to_string(),
+            "user=a&pass=b".to_string(),
+        );
+        assert_eq!(req.method(), "POST");
+        assert_eq!(req.url(), "http://example.com/login");
+        assert_eq!(
+            req.headers(),
+            vec![Header::new(
+                "Content-Type".to_string(),
+                "application/x-www-form-urlencoded".to_string()
+            )]
+        );
+        assert_eq!(req.body(), "user=a&pass=b");
+    }
+
     #[test]
     fn test_invalid() {
         let raw = "HTTP/1.1 200 OK".to_string();
@@ -175,4 +300,25 @@ mod tests {
 
         assert_eq!(res.body(), "body message".to_string());
     }
+
+    #[test]
+    fn test_from_bytes() {
+        // "日本語" in Shift_JIS with the charset in the Content-Type header.
+        let mut raw =
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=Shift_JIS\r\n\r\n".to_vec();
+        raw.extend_from_slice(&[0x93, 0xFA, 0x96, 0x7B, 0x8C, 0xEA]);
+        let res = HttpResponse::from_bytes(&raw).expect("failed to parse http response");
+        assert_eq!(res.status_code(), 200);
+        assert_eq!(
+            res.header_value("Content-Type"),
+            Ok("text/html; charset=Shift_JIS".to_string())
+        );
+        assert_eq!(res.body(), "日本語".to_string());
+
+        // "日本語" in EUC-JP with the charset in a meta element.
+        let mut raw = b"HTTP/1.1 200 OK\nContent-Type: text/html\n\n<meta charset=euc-jp>".to_vec();
+        raw.extend_from_slice(&[0xC6, 0xFC, 0xCB, 0xDC, 0xB8, 0xEC]);
+        let res = HttpResponse::from_bytes(&raw).expect("failed to parse http response");
+        assert_eq!(res.body(), "<meta charset=euc-jp>日本語".to_string());
+    }
 }
@@ -5,6 +5,7 @@ extern crate alloc;
 pub mod browser;
 pub mod constants;
 pub mod display_item;
+pub mod encoding;
 pub mod error;
 pub mod http;
 pub mod log;
@@ -1,3 +1,4 @@
+use crate::renderer::source::SourcePosition;
 use alloc::format;
 use alloc::string::String;
 use alloc::string::ToString;
@@ -9,15 +10,106 @@ pub enum LogLevel {
     Error,
 }
 
+/// The kind of a source text where a diagnostic is found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceKind {
+    Html,
+    Css,
+    Js,
+}
+
+/// A parse error found in a source text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    kind: SourceKind,
+    /// The error code. HTML parse errors use the codes defined in the HTML spec (e.g.
+    /// "eof-in-tag").
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parse-errors
+    code: String,
+    /// The URL of the source text. This is empty if the source text is not fetched from a URL.
+    url: String,
+    position: SourcePosition,
+}
+
+impl Diagnostic {
+    pub fn new(kind: SourceKind, code: String, url: String, position: SourcePosition) -> Self {
+        Self {
+            kind,
+            code,
+            url,
+            position,
+        }
+    }
+
+    pub fn kind(&self) -> SourceKind {
+        self.kind
+    }
+
+    pub fn code(&self) -> String {
+        self.code.clone()
+    }
+
+    pub fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    pub fn position(&self) -> SourcePosition {
+        self.position
+    }
+}
+
+impl ToString for Diagnostic {
+    fn to_string(&self) -> String {
+        let kind = match self.kind {
+            SourceKind::Html => "HTML parse error",
+            SourceKind::Css => "CSS parse error",
+            SourceKind::Js => "JavaScript syntax error",
+        };
+        let url = if self.url.is_empty() {
+            "(inline)"
+        } else {
+            &self.url
+        };
+        format!("{}:{}: {}: {}", url, self.position, kind, self.code)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Log {
     level: LogLevel,
     log: String,
+    diagnostic: Option<Diagnostic>,
 }
 
 impl Log {
     pub fn new(level: LogLevel, log: String) -> Self {
-        Self { level, log }
+        Self {
+            level,
+            log,
+            diagnostic: None,
+        }
+    }
+
+    /// Creates a log for a parse error. A syntax error in a script is an error because the script
+    /// is not run, and the others are warnings because the parsers recover from them.
+    pub fn from_diagnostic(diagnostic: Diagnostic) -> Self {
+        let level = match diagnostic.kind() {
+            SourceKind::Js => LogLevel::Error,
+            SourceKind::Html | SourceKind::Css => LogLevel::Warning,
+        };
+        Self {
+            level,
+            log: diagnostic.to_string(),
+            diagnostic: Some(diagnostic),
+        }
+    }
+
+    pub fn level(&self) -> LogLevel {
+        self.level.clone()
+    }
+
+    pub fn diagnostic(&self) -> Option<Diagnostic> {
+        self.diagnostic.clone()
     }
 }
 
@@ -30,3 +122,35 @@ impl ToString for Log {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagnostic_log() {
+        let log = Log::from_diagnostic(Diagnostic::new(
+            SourceKind::Html,
+            "eof-in-tag".to_string(),
+            "http://example.com/index.html".to_string(),
+            SourcePosition::new(3, 14),
+        ));
+        assert_eq!(LogLevel::Warning, log.level());
+        assert_eq!(
+            "Warning: http://example.com/index.html:3:14: HTML parse error: eof-in-tag".to_string(),
+            log.to_string()
+        );
+
+        let log = Log::from_diagnostic(Diagnostic::new(
+            SourceKind::Js,
+            "unexpected-character".to_string(),
+            String::new(),
+            SourcePosition::new(1, 5),
+        ));
+        assert_eq!(LogLevel::Error, log.level());
+        assert_eq!(
+            "Error: (inline):1:5: JavaScript syntax error: unexpected-character".to_string(),
+            log.to_string()
+        );
+    }
+}
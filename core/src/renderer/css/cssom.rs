@@ -7,16 +7,21 @@
 //! https://www.w3.org/TR/css-syntax-3/#parsing
 
 use crate::browser::Browser;
+use crate::log::Diagnostic;
+use crate::log::SourceKind;
 use crate::renderer::css::token::CssToken;
 use crate::renderer::css::token::CssTokenizer;
+use crate::renderer::source::SourcePosition;
+use crate::utils::console_diagnostic;
 use crate::utils::console_warning;
+use alloc::boxed::Box;
 use alloc::format;
 use alloc::rc::Weak;
 use alloc::string::String;
 use alloc::string::ToString;
+use alloc::vec;
 use alloc::vec::Vec;
 use core::cell::RefCell;
-use core::iter::Peekable;
 
 // e.g.
 // div {
@@ -95,7 +100,6 @@ impl AtRule {
 /// https://www.w3.org/TR/cssom-1/#cssstylerule
 /// https://developer.mozilla.org/en-US/docs/Web/API/CSSStyleRule
 pub struct QualifiedRule {
-    // TODO: support multiple selectors
     /// https://www.w3.org/TR/selectors-4/#typedef-selector-list
     /// The prelude of the qualified rule is parsed as a <selector-list>.
     pub selector: Selector,
@@ -136,6 +140,13 @@ pub enum Selector {
     ClassSelector(String),
     /// https://www.w3.org/TR/selectors-4/#id-selectors
     IdSelector(String),
+    /// https://www.w3.org/TR/selectors-4/#compound
+    CompoundSelector(Vec<Selector>),
+    /// The ancestor and the subject of a descendant combinator, e.g. "div" and "p" of "div p".
+    /// https://www.w3.org/TR/selectors-4/#descendant-combinators
+    DescendantCombinator(Box<Selector>, Box<Selector>),
+    /// https://www.w3.org/TR/selectors-4/#selector-list
+    SelectorList(Vec<Selector>),
     /// This is an unofficial selector.
     UnknownSelector,
 }
@@ -179,77 +190,195 @@ pub type ComponentValue = CssToken;
 #[derive(Debug, Clone)]
 pub struct CssParser {
     browser: Weak<RefCell<Browser>>,
-    t: Peekable<CssTokenizer>,
+    t: CssTokenizer,
+    /// The URL of the style sheet, which is reported with parse errors.
+    url: String,
+    /// The position of the start of the style sheet in the document at `url`.
+    start_position: SourcePosition,
 }
 
 impl CssParser {
     pub fn new(browser: Weak<RefCell<Browser>>, t: CssTokenizer) -> Self {
         Self {
             browser,
-            t: t.peekable(),
+            t,
+            url: String::new(),
+            start_position: SourcePosition::default(),
         }
     }
 
-    fn consume_ident(&mut self) -> String {
-        let token = match self.t.next() {
-            Some(t) => t,
-            None => panic!("should have a token but got None"),
-        };
+    pub fn set_url(&mut self, url: String) {
+        self.url = url;
+    }
 
-        match token {
-            CssToken::Ident(ref ident) => ident.to_string(),
-            _ => {
-                panic!("Parse error: {:?} is an unexpected token.", token);
+    /// Sets the position of the start of the style sheet in the document, for a style sheet
+    /// embedded in a style element. Parse errors are reported at positions in the document.
+    pub fn set_start_position(&mut self, position: SourcePosition) {
+        self.start_position = position;
+    }
+
+    /// Reports a parse error at the current input token to the browser console.
+    /// https://www.w3.org/TR/css-syntax-3/#parse-errors
+    fn parse_error(&self, code: &str) {
+        self.parse_error_at(code, self.t.position());
+    }
+
+    fn parse_error_at(&self, code: &str, position: SourcePosition) {
+        console_diagnostic(
+            &self.browser,
+            Diagnostic::new(
+                SourceKind::Css,
+                code.to_string(),
+                self.url.clone(),
+                position.offset_by(self.start_position),
+            ),
+        );
+    }
+
+    fn consume_ident(&mut self) -> Option<String> {
+        match self.t.next() {
+            Some(CssToken::Ident(ident)) => Some(ident),
+            Some(_) => {
+                self.parse_error("expected-ident");
+                None
+            }
+            None => {
+                self.parse_error("unexpected-eof");
+                None
             }
         }
     }
 
     /// https://www.w3.org/TR/css-syntax-3/#consume-component-value
-    fn consume_component_value(&mut self) -> ComponentValue {
-        self.t
-            .next()
-            .expect("should have a token in consume_component_value")
+    fn consume_component_value(&mut self) -> Option<ComponentValue> {
+        let value = self.t.next();
+        if value.is_none() {
+            self.parse_error("unexpected-eof");
+        }
+        value
     }
 
     /// https://www.w3.org/TR/css-syntax-3/#qualified-rule
     /// Note: Most qualified rules will be style rules, where the prelude is a selector [SELECT]
     /// and the block a list of declarations.
+    ///
+    /// Consumes the prelude up to "{". A rule whose selector is not supported matches no element.
     fn consume_selector(&mut self) -> Selector {
-        let token = match self.t.next() {
-            Some(t) => t,
-            None => panic!("should have a token but got None"),
-        };
-
-        match token {
-            // TODO: support tag.class and tag#id
-            CssToken::HashToken(value) => Selector::IdSelector(value[1..].to_string()),
-            CssToken::Delim(delim) => {
-                if delim == '.' {
-                    return Selector::ClassSelector(self.consume_ident());
+        match self.consume_selector_list() {
+            Some(selector) => selector,
+            None => {
+                // Skip the rest of the prelude.
+                while !matches!(self.t.peek(), Some(CssToken::OpenCurly) | None) {
+                    self.t.next();
                 }
-                panic!("Parse error: {:?} is an unexpected token.", token);
+                Selector::UnknownSelector
             }
-            CssToken::Ident(ident) => {
-                // TODO: fix this. Skip pseudo-classes such as :link and :visited
-                if self.t.peek() == Some(&CssToken::Colon) {
-                    while self.t.peek() != Some(&CssToken::OpenCurly) {
-                        self.t.next();
-                    }
+        }
+    }
+
+    /// Consumes a selector list up to "{" or the end of the input. Returns None after reporting
+    /// a parse error if it has a selector which this parser doesn't support, e.g. a child
+    /// combinator or a pseudo-class.
+    ///
+    /// <selector-list> = <complex-selector-list>
+    /// <complex-selector-list> = <complex-selector>#
+    /// https://www.w3.org/TR/selectors-4/#grammar
+    fn consume_selector_list(&mut self) -> Option<Selector> {
+        let mut selectors = vec![self.consume_complex_selector()?];
+        while self.t.peek() == Some(&CssToken::Delim(',')) {
+            // consume ','
+            assert!(self.t.next().is_some());
+            selectors.push(self.consume_complex_selector()?);
+        }
+
+        match selectors.len() {
+            1 => selectors.pop(),
+            _ => Some(Selector::SelectorList(selectors)),
+        }
+    }
+
+    /// <complex-selector> = <compound-selector> [ <combinator>? <compound-selector> ]*
+    ///
+    /// Only the descendant combinator, which is whitespace, is supported.
+    /// https://www.w3.org/TR/selectors-4/#grammar
+    fn consume_complex_selector(&mut self) -> Option<Selector> {
+        let mut selector = self.consume_compound_selector()?;
+
+        loop {
+            let whitespace = self.t.is_next_preceded_by_whitespace();
+            match self.t.peek() {
+                Some(CssToken::OpenCurly) | Some(CssToken::Delim(',')) | None => {
+                    return Some(selector)
+                }
+                Some(CssToken::Ident(_))
+                | Some(CssToken::HashToken(_))
+                | Some(CssToken::Delim('.'))
+                    if whitespace =>
+                {
+                    let descendant = self.consume_compound_selector()?;
+                    selector =
+                        Selector::DescendantCombinator(Box::new(selector), Box::new(descendant));
+                }
+                _ => {
+                    self.unsupported_selector();
+                    return None;
                 }
-                Selector::TypeSelector(ident.to_string())
             }
-            CssToken::AtKeyword(_keyword) => {
-                // skip until "{" comes
-                while self.t.peek() != Some(&CssToken::OpenCurly) {
-                    self.t.next();
+        }
+    }
+
+    /// <compound-selector> = [ <type-selector>? <subclass-selector>* ]!
+    /// https://www.w3.org/TR/selectors-4/#grammar
+    fn consume_compound_selector(&mut self) -> Option<Selector> {
+        let mut selectors = Vec::new();
+
+        // A type selector comes first.
+        if let Some(CssToken::Ident(_)) = self.t.peek() {
+            if let Some(CssToken::Ident(ident)) = self.t.next() {
+                selectors.push(Selector::TypeSelector(ident));
+            }
+        }
+
+        // Simple selectors which are separated by whitespace belong to different compound
+        // selectors.
+        while matches!(
+            self.t.peek(),
+            Some(CssToken::HashToken(_)) | Some(CssToken::Delim('.'))
+        ) && (selectors.is_empty() || !self.t.is_next_preceded_by_whitespace())
+        {
+            match self.t.next() {
+                Some(CssToken::HashToken(value)) => {
+                    selectors.push(Selector::IdSelector(value[1..].to_string()))
                 }
-                Selector::UnknownSelector
+                _ => selectors.push(Selector::ClassSelector(self.consume_ident()?)),
             }
-            _ => {
-                console_warning(&self.browser, format!("unexpected token {:?}", token));
+        }
+
+        match selectors.len() {
+            0 => {
+                self.unsupported_selector();
+                None
+            }
+            1 => selectors.pop(),
+            _ => Some(Selector::CompoundSelector(selectors)),
+        }
+    }
+
+    /// Reports the next input token which can't appear at the current position of a selector. It's
+    /// consumed unless it's "{", which starts the block of the rule.
+    fn unsupported_selector(&mut self) {
+        match self.t.peek() {
+            Some(CssToken::OpenCurly) => self.parse_error("unexpected-token-in-selector"),
+            // TODO: support other combinators, pseudo-classes and attribute selectors.
+            Some(CssToken::Delim(_)) | Some(CssToken::Colon) => {
                 self.t.next();
-                Selector::UnknownSelector
+                self.parse_error("unsupported-selector");
+            }
+            Some(_) => {
+                self.t.next();
+                self.parse_error("unexpected-token-in-selector");
             }
+            None => self.parse_error("unexpected-eof"),
         }
     }
 
@@ -257,13 +386,16 @@ impl CssParser {
     fn consume_declaration(&mut self) -> Option<Declaration> {
         // Create a new declaration with its name set to the value of the current input token.
         let mut declaration = Declaration::new();
-        declaration.set_property(self.consume_ident());
+        declaration.set_property(self.consume_ident()?);
 
         // "2. If the next input token is anything other than a <colon-token>, this is a parse error.
         // Return nothing. Otherwise, consume the next input token."
         match self.t.next() {
             Some(CssToken::Colon) => {}
-            _ => return None,
+            _ => {
+                self.parse_error("missing-colon-in-declaration");
+                return None;
+            }
         }
 
         // "3. While the next input token is a <whitespace-token>, consume the next input token."
@@ -271,7 +403,7 @@ impl CssParser {
         // "4. As long as the next input token is anything other than an <EOF-token>, consume a
         // component value and append it to the declaration’s value."
         // TODO: support multiple values in one declaration.
-        declaration.set_value(self.consume_component_value());
+        declaration.set_value(self.consume_component_value()?);
 
         Some(declaration)
     }
@@ -305,14 +437,8 @@ impl CssParser {
                     }
                 }
                 _ => {
-                    console_warning(
-                        &self.browser,
-                        format!(
-                            "unexpected token in consume_list_of_declarations {:?}",
-                            token
-                        ),
-                    );
                     self.t.next();
+                    self.parse_error("unexpected-token-in-declaration-list");
                 }
             }
         }
@@ -424,9 +550,29 @@ impl CssParser {
         // 3. Assign rules to the stylesheet’s value.
         sheet.set_rules(self.consume_list_of_rules());
 
+        // Report the errors found by the tokenizer as well.
+        for (code, position) in self.t.take_errors() {
+            self.parse_error_at(code, position);
+        }
+
         // 4. Return the stylesheet.
         sheet
     }
+
+    /// Parses the whole input as a selector. Returns None if the input is not a selector which
+    /// this parser supports, e.g. it has a child combinator or a pseudo-class.
+    /// https://www.w3.org/TR/selectors-4/#parse-selector
+    pub fn parse_selector(&mut self) -> Option<Selector> {
+        // "1. Let selector be the result of parsing source as a <selector-list>. If this returns
+        // failure, it's an invalid selector; return failure."
+        let selector = self.consume_selector_list()?;
+
+        // The whole input must be consumed.
+        if self.t.next().is_some() {
+            return None;
+        }
+        Some(selector)
+    }
 }
 
 #[cfg(test)]
@@ -550,4 +696,151 @@ mod tests {
             i += 1;
         }
     }
+
+    #[test]
+    fn test_complex_selectors() {
+        let browser = Browser::new();
+        let style = "h1, div p.a { color: red; }\na:hover { color: blue; }".to_string();
+        let t = CssTokenizer::new(style);
+        let cssom = CssParser::new(Rc::downgrade(&browser), t).parse_stylesheet();
+
+        let type_selector = |name: &str| Selector::TypeSelector(name.to_string());
+        assert_eq!(2, cssom.rules.len());
+        assert_eq!(
+            Selector::SelectorList(vec![
+                type_selector("h1"),
+                Selector::DescendantCombinator(
+                    Box::new(type_selector("div")),
+                    Box::new(Selector::CompoundSelector(vec![
+                        type_selector("p"),
+                        Selector::ClassSelector("a".to_string()),
+                    ])),
+                ),
+            ]),
+            cssom.rules[0].selector
+        );
+        assert_eq!(Selector::UnknownSelector, cssom.rules[1].selector);
+        assert_eq!(1, cssom.rules[1].declarations.len());
+
+        let logs: Vec<String> = browser
+            .borrow()
+            .logs()
+            .iter()
+            .map(|log| log.to_string())
+            .collect();
+        assert_eq!(
+            vec!["Warning: (inline):2:2: CSS parse error: unsupported-selector".to_string()],
+            logs
+        );
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let browser = Browser::new();
+        let style = "p { color red; width: 100; }\ndiv ~ a { }".to_string();
+        let t = CssTokenizer::new(style);
+        let mut parser = CssParser::new(Rc::downgrade(&browser), t);
+        parser.set_url("http://example.com/style.css".to_string());
+        let cssom = parser.parse_stylesheet();
+
+        let mut rule = QualifiedRule::default();
+        rule.set_selector(Selector::TypeSelector("p".to_string()));
+        let mut declaration = Declaration::default();
+        declaration.set_property("width".to_string());
+        declaration.set_value(ComponentValue::Number(100.0));
+        rule.set_declarations(vec![declaration]);
+        assert_eq!(2, cssom.rules.len());
+        assert_eq!(rule, cssom.rules[0]);
+        // A rule whose selector is not supported matches no element.
+        assert_eq!(Selector::UnknownSelector, cssom.rules[1].selector);
+
+        let logs: Vec<String> = browser
+            .borrow()
+            .logs()
+            .iter()
+            .map(|log| log.to_string())
+            .collect();
+        assert_eq!(
+            vec![
+                "Warning: http://example.com/style.css:1:11: CSS parse error: missing-colon-in-declaration".to_string(),
+                "Warning: http://example.com/style.css:2:5: CSS parse error: unsupported-selector".to_string(),
+            ],
+            logs
+        );
+    }
+
+    #[test]
+    fn test_unterminated_string() {
+        let browser = Browser::new();
+        let style = "p { content: 'a\"b'; }\np { content: \"abc".to_string();
+        let t = CssTokenizer::new(style);
+        let mut parser = CssParser::new(Rc::downgrade(&browser), t);
+        let cssom = parser.parse_stylesheet();
+
+        // A string ends only at the quote which starts it.
+        let mut rule = QualifiedRule::default();
+        rule.set_selector(Selector::TypeSelector("p".to_string()));
+        let mut declaration = Declaration::default();
+        declaration.set_property("content".to_string());
+        declaration.set_value(ComponentValue::StringToken("a\"b".to_string()));
+        rule.set_declarations(vec![declaration]);
+        assert_eq!(rule, cssom.rules[0]);
+
+        let logs: Vec<String> = browser
+            .borrow()
+            .logs()
+            .iter()
+            .map(|log| log.to_string())
+            .collect();
+        assert_eq!(
+            vec!["Warning: (inline):2:14: CSS parse error: unterminated-string".to_string()],
+            logs
+        );
+    }
+
+    #[test]
+    fn test_parse_selector() {
+        let parse = |selector: &str| {
+            let t = CssTokenizer::new(selector.to_string());
+            CssParser::new(Weak::new(), t).parse_selector()
+        };
+        assert_eq!(Some(Selector::TypeSelector("p".to_string())), parse(" p "));
+        assert_eq!(Some(Selector::IdSelector("id".to_string())), parse("#id"));
+        assert_eq!(
+            Some(Selector::ClassSelector("class".to_string())),
+            parse(".class")
+        );
+        let type_selector = |name: &str| Selector::TypeSelector(name.to_string());
+        let class_selector = |name: &str| Selector::ClassSelector(name.to_string());
+        assert_eq!(
+            Some(Selector::CompoundSelector(vec![
+                type_selector("p"),
+                class_selector("a"),
+                Selector::IdSelector("id".to_string()),
+            ])),
+            parse("p.a#id")
+        );
+        assert_eq!(
+            Some(Selector::DescendantCombinator(
+                Box::new(Selector::DescendantCombinator(
+                    Box::new(type_selector("div")),
+                    Box::new(class_selector("a")),
+                )),
+                Box::new(type_selector("p")),
+            )),
+            parse("div .a\np")
+        );
+        assert_eq!(
+            Some(Selector::SelectorList(vec![
+                type_selector("h1"),
+                Selector::CompoundSelector(vec![type_selector("p"), class_selector("a")]),
+            ])),
+            parse("h1 , p.a")
+        );
+        assert_eq!(None, parse(""));
+        assert_eq!(None, parse("div > p"));
+        assert_eq!(None, parse("a:hover"));
+        assert_eq!(None, parse("h1,"));
+        assert_eq!(None, parse("."));
+    }
 }
@@ -1,2 +1,3 @@
 pub mod cssom;
+pub mod selector;
 pub mod token;
@@ -0,0 +1,199 @@
+//! This module matches selectors against DOM nodes. It's shared by the style resolution and the
+//! DOM APIs which take a selector such as `querySelector()`.
+//! https://www.w3.org/TR/selectors-4/#match-against-element
+
+use crate::renderer::css::cssom::Selector;
+use crate::renderer::dom::node::Node;
+use crate::renderer::dom::node::NodeKind;
+
+/// Returns true if `node` is an element and `selector` matches it.
+/// https://www.w3.org/TR/selectors-4/#match-a-selector-against-an-element
+pub fn matches_selector(selector: &Selector, node: &Node) -> bool {
+    let element = match node.kind {
+        NodeKind::Element(ref e) => e,
+        _ => return false,
+    };
+
+    match selector {
+        // Type selectors are matched ASCII case-insensitively against HTML elements.
+        // https://html.spec.whatwg.org/multipage/semantics-other.html#case-sensitivity-of-selectors
+        Selector::TypeSelector(type_name) => element.local_name().eq_ignore_ascii_case(type_name),
+        // The classes of an element are the tokens of its class attribute split on ASCII
+        // whitespace.
+        // https://dom.spec.whatwg.org/#concept-class
+        Selector::ClassSelector(class_name) => match element.get_attribute("class") {
+            Some(classes) => classes
+                .split_ascii_whitespace()
+                .any(|class| class == class_name),
+            None => false,
+        },
+        // https://dom.spec.whatwg.org/#concept-id
+        Selector::IdSelector(id_name) => element.get_attribute("id").as_ref() == Some(id_name),
+        // "A compound selector is a sequence of simple selectors that are not separated by a
+        // combinator, and represents a set of simultaneous conditions on a single element."
+        Selector::CompoundSelector(selectors) => {
+            selectors.iter().all(|s| matches_selector(s, node))
+        }
+        // "A selector of the form "A B" represents an element B that is an arbitrary descendant of
+        // some ancestor element A."
+        Selector::DescendantCombinator(ancestor, selector) => {
+            if !matches_selector(selector, node) {
+                return false;
+            }
+            let mut parent = node.parent().upgrade();
+            while let Some(p) = parent {
+                if matches_selector(ancestor, &p.borrow()) {
+                    return true;
+                }
+                parent = p.borrow().parent().upgrade();
+            }
+            false
+        }
+        // "A selector list represents the union of all elements selected by each of the
+        // individual selectors in the selector list."
+        Selector::SelectorList(selectors) => selectors.iter().any(|s| matches_selector(s, node)),
+        Selector::UnknownSelector => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::dom::api::get_element_by_id;
+    use crate::renderer::dom::node::Element;
+    use crate::renderer::html::attribute::Attribute;
+    use crate::renderer::test_util::create_document;
+    use alloc::boxed::Box;
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+
+    fn element(name: &str, attributes: &[(&str, &str)]) -> Node {
+        let attributes = attributes
+            .iter()
+            .map(|(name, value)| {
+                let mut attr = Attribute::new();
+                for c in name.chars() {
+                    attr.add_char(c, true);
+                }
+                for c in value.chars() {
+                    attr.add_char(c, false);
+                }
+                attr
+            })
+            .collect::<Vec<Attribute>>();
+        Node::new(NodeKind::Element(Element::new(name, attributes)))
+    }
+
+    #[test]
+    fn test_matches_selector() {
+        let p = element("p", &[("id", "main"), ("class", " a\tb ")]);
+        assert!(matches_selector(
+            &Selector::TypeSelector("P".to_string()),
+            &p
+        ));
+        assert!(!matches_selector(
+            &Selector::TypeSelector("div".to_string()),
+            &p
+        ));
+        assert!(matches_selector(
+            &Selector::IdSelector("main".to_string()),
+            &p
+        ));
+        assert!(matches_selector(
+            &Selector::ClassSelector("b".to_string()),
+            &p
+        ));
+        assert!(!matches_selector(
+            &Selector::ClassSelector("a b".to_string()),
+            &p
+        ));
+        assert!(!matches_selector(&Selector::UnknownSelector, &p));
+
+        let text = Node::new(NodeKind::Text("p".to_string()));
+        assert!(!matches_selector(
+            &Selector::TypeSelector("p".to_string()),
+            &text
+        ));
+    }
+
+    #[test]
+    fn test_compound_selector() {
+        let p = element("p", &[("id", "main"), ("class", "a b")]);
+        let compound = |selectors: &[Selector]| Selector::CompoundSelector(selectors.to_vec());
+        let type_selector = Selector::TypeSelector("p".to_string());
+        let class_selector = Selector::ClassSelector("a".to_string());
+        let id_selector = Selector::IdSelector("main".to_string());
+        assert!(matches_selector(
+            &compound(&[type_selector.clone(), class_selector.clone(), id_selector]),
+            &p
+        ));
+        assert!(matches_selector(
+            &compound(&[
+                class_selector.clone(),
+                Selector::ClassSelector("b".to_string())
+            ]),
+            &p
+        ));
+        assert!(!matches_selector(
+            &compound(&[type_selector, Selector::ClassSelector("c".to_string())]),
+            &p
+        ));
+        assert!(!matches_selector(
+            &compound(&[Selector::TypeSelector("div".to_string()), class_selector]),
+            &p
+        ));
+    }
+
+    #[test]
+    fn test_descendant_combinator() {
+        let document =
+            create_document("<div class=a><section><p id=p>x</p></section></div><p id=q>y</p>");
+        let p = get_element_by_id(Some(document.clone()), &"p".to_string()).unwrap();
+        let q = get_element_by_id(Some(document.clone()), &"q".to_string()).unwrap();
+        let descendant = |ancestor: Selector, selector: Selector| {
+            Selector::DescendantCombinator(Box::new(ancestor), Box::new(selector))
+        };
+        let type_selector = |name: &str| Selector::TypeSelector(name.to_string());
+
+        // The ancestor doesn't need to be the parent.
+        let a_p = descendant(Selector::ClassSelector("a".to_string()), type_selector("p"));
+        assert!(matches_selector(&a_p, &p.borrow()));
+        assert!(!matches_selector(&a_p, &q.borrow()));
+        assert!(matches_selector(
+            &descendant(
+                descendant(type_selector("div"), type_selector("section")),
+                type_selector("p")
+            ),
+            &p.borrow()
+        ));
+        // The element itself is not its ancestor.
+        assert!(!matches_selector(
+            &descendant(type_selector("p"), type_selector("p")),
+            &p.borrow()
+        ));
+        assert!(!matches_selector(
+            &descendant(type_selector("section"), type_selector("div")),
+            &p.borrow()
+        ));
+    }
+
+    #[test]
+    fn test_selector_list() {
+        let p = element("p", &[("class", "a")]);
+        let list = |selectors: &[Selector]| Selector::SelectorList(selectors.to_vec());
+        assert!(matches_selector(
+            &list(&[
+                Selector::TypeSelector("h1".to_string()),
+                Selector::ClassSelector("a".to_string())
+            ]),
+            &p
+        ));
+        assert!(!matches_selector(
+            &list(&[
+                Selector::TypeSelector("h1".to_string()),
+                Selector::UnknownSelector
+            ]),
+            &p
+        ));
+    }
+}
@@ -4,6 +4,8 @@
 //! 4. Tokenization
 //! https://www.w3.org/TR/css-syntax-3/#tokenization
 
+use crate::renderer::source::LineMap;
+use crate::renderer::source::SourcePosition;
 use alloc::string::String;
 use alloc::vec::Vec;
 
@@ -41,16 +43,68 @@ pub enum CssToken {
 pub struct CssTokenizer {
     pos: usize,
     input: Vec<char>,
+    line_map: LineMap,
+    /// The index of the first code point of the token being consumed.
+    token_start: usize,
+    /// The position of the current input token, which is the last token returned by `next`.
+    position: SourcePosition,
+    /// The next input token and its position if it's already consumed by `peek`.
+    peeked: Option<(Option<CssToken>, SourcePosition)>,
+    /// The parse errors found so far and their positions.
+    errors: Vec<(&'static str, SourcePosition)>,
+    /// Whether whitespace precedes the token consumed last, which is the next input token if it's
+    /// already consumed by `peek`.
+    whitespace_before: bool,
 }
 
 impl CssTokenizer {
     pub fn new(css: String) -> Self {
+        let input: Vec<char> = css.chars().collect();
         Self {
             pos: 0,
-            input: css.chars().collect(),
+            line_map: LineMap::new(&input),
+            input,
+            token_start: 0,
+            position: SourcePosition::default(),
+            peeked: None,
+            errors: Vec::new(),
+            whitespace_before: false,
         }
     }
 
+    /// Returns the parse errors found so far and clears them.
+    pub fn take_errors(&mut self) -> Vec<(&'static str, SourcePosition)> {
+        core::mem::take(&mut self.errors)
+    }
+
+    /// Returns the position of the current input token.
+    pub fn position(&self) -> SourcePosition {
+        self.position
+    }
+
+    /// Returns the next input token without consuming it.
+    /// https://www.w3.org/TR/css-syntax-3/#next-input-token
+    pub fn peek(&mut self) -> Option<&CssToken> {
+        if self.peeked.is_none() {
+            let token = self.consume_token();
+            let position = self.line_map.position(self.token_start);
+            self.peeked = Some((token, position));
+        }
+
+        match &self.peeked {
+            Some((token, _)) => token.as_ref(),
+            None => None,
+        }
+    }
+
+    /// Returns true if whitespace precedes the next input token. Whitespace between compound
+    /// selectors is a descendant combinator.
+    /// https://www.w3.org/TR/selectors-4/#descendant-combinators
+    pub fn is_next_preceded_by_whitespace(&mut self) -> bool {
+        self.peek();
+        self.whitespace_before
+    }
+
     /// https://www.w3.org/TR/css-syntax-3/#consume-name
     fn consume_ident_token(&mut self) -> String {
         let mut s = String::new();
@@ -58,6 +112,11 @@ impl CssTokenizer {
 
         loop {
             self.pos += 1;
+            // The name ends at the end of the input, e.g. in a selector given to
+            // `querySelector()`.
+            if self.pos >= self.input.len() {
+                break;
+            }
             let c = self.input[self.pos];
             match c {
                 'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => {
@@ -72,19 +131,28 @@ impl CssTokenizer {
 
     /// https://www.w3.org/TR/css-syntax-3/#consume-a-string-token
     fn consume_string_token(&mut self) -> String {
+        // "This algorithm may be called with an ending code point, which denotes the code point
+        // that ends the string."
+        let ending = self.input[self.pos];
         let mut s = String::new();
 
         loop {
+            self.pos += 1;
+            // "EOF: This is a parse error. Return the <string-token>."
             if self.pos >= self.input.len() {
+                self.errors.push((
+                    "unterminated-string",
+                    self.line_map.position(self.token_start),
+                ));
                 return s;
             }
 
-            self.pos += 1;
             let c = self.input[self.pos];
-            match c {
-                '"' | '\'' => break,
-                _ => s.push(c),
+            // "ending code point: Return the <string-token>."
+            if c == ending {
+                break;
             }
+            s.push(c);
         }
 
         s
@@ -124,19 +192,18 @@ impl CssTokenizer {
 
         num
     }
-}
-
-impl Iterator for CssTokenizer {
-    type Item = CssToken;
 
     /// https://www.w3.org/TR/css-syntax-3/#consume-token
-    fn next(&mut self) -> Option<Self::Item> {
+    fn consume_token(&mut self) -> Option<CssToken> {
+        self.whitespace_before = false;
         loop {
             if self.pos >= self.input.len() {
+                self.token_start = self.pos;
                 return None;
             }
 
             let c = self.input[self.pos];
+            self.token_start = self.pos;
 
             let token = match c {
                 '"' | '\'' => {
@@ -175,9 +242,10 @@ impl Iterator for CssTokenizer {
                     // If the next 3 input code points would start an ident sequence, consume an
                     // ident sequence, create an <at-keyword-token> with its value set to the
                     // returned value, and return it.
-                    if self.input[self.pos + 1].is_ascii_alphabetic()
-                        && self.input[self.pos + 2].is_alphanumeric()
-                        && self.input[self.pos + 3].is_alphanumeric()
+                    let next = |i: usize| self.input.get(self.pos + i).copied().unwrap_or(' ');
+                    if next(1).is_ascii_alphabetic()
+                        && next(2).is_alphanumeric()
+                        && next(3).is_alphanumeric()
                     {
                         // skip '@'
                         self.pos += 1;
@@ -209,20 +277,13 @@ impl Iterator for CssTokenizer {
                 // "Consume as much whitespace as possible. Return a <whitespace-token>."
                 // https://www.w3.org/TR/css-syntax-3/#consume-token
                 ' ' | '\n' => {
+                    self.whitespace_before = true;
                     self.pos += 1;
                     continue;
                 }
-                _ => {
-                    /*
-                    console_error(
-                        self.browser.clone(),
-                        format!("char {} is not supported yet", c),
-                    );
-                    self.pos += 1;
-                    continue;
-                    */
-                    panic!("char {} is not supported yet", c);
-                }
+                // "anything else
+                // Return a <delim-token> with its value set to the current input code point."
+                _ => CssToken::Delim(c),
             };
 
             self.pos += 1;
@@ -231,6 +292,22 @@ impl Iterator for CssTokenizer {
     }
 }
 
+impl Iterator for CssTokenizer {
+    type Item = CssToken;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (token, position) = match self.peeked.take() {
+            Some(peeked) => peeked,
+            None => {
+                let token = self.consume_token();
+                (token, self.line_map.position(self.token_start))
+            }
+        };
+        self.position = position;
+        token
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -243,6 +320,15 @@ mod tests {
         assert!(t.next().is_none());
     }
 
+    #[test]
+    fn test_ident_at_end() {
+        let style = "p #id".to_string();
+        let mut t = CssTokenizer::new(style);
+        assert_eq!(Some(CssToken::Ident("p".to_string())), t.next());
+        assert_eq!(Some(CssToken::HashToken("#id".to_string())), t.next());
+        assert!(t.next().is_none());
+    }
+
     #[test]
     fn test_one_rule() {
         let style = "p { color: red; }".to_string();
@@ -4,18 +4,24 @@
 //! https://dom.spec.whatwg.org/#eventtarget-activation-behavior
 
 use crate::renderer::dom::event::Event;
+use crate::renderer::dom::mutation::tree_root;
 use crate::renderer::dom::node::ElementKind;
 use crate::renderer::dom::node::Node;
 use crate::renderer::dom::node::NodeKind;
+use crate::renderer::dom::window::Navigation;
+use crate::url::Url;
 use alloc::rc::Rc;
 use core::cell::RefCell;
 
 /// https://dom.spec.whatwg.org/#eventtarget-activation-behavior
-pub type ActivationBehavior = fn(node: Rc<RefCell<Node>>, e: Event);
+pub type ActivationBehavior = fn(node: Rc<RefCell<Node>>, event: &Event);
 
 pub fn get_activation_behavior(node_kind: &NodeKind) -> Option<ActivationBehavior> {
     match node_kind {
-        NodeKind::Document | NodeKind::Text(_) => None,
+        NodeKind::Document
+        | NodeKind::DocumentType(_)
+        | NodeKind::Comment(_)
+        | NodeKind::Text(_) => None,
         NodeKind::Element(e) => {
             match e.kind() {
                 ElementKind::A => {
@@ -35,17 +41,48 @@ pub fn get_activation_behavior(node_kind: &NodeKind) -> Option<ActivationBehavio
 /// https://html.spec.whatwg.org/multipage/links.html#links-created-by-a-and-area-elements
 /// https://html.spec.whatwg.org/multipage/links.html#following-hyperlinks-2
 /// https://html.spec.whatwg.org/multipage/browsing-the-web.html#navigate
-fn follow_hyperlink(node: Rc<RefCell<Node>>, _event: Event) {
+fn follow_hyperlink(node: Rc<RefCell<Node>>, _event: &Event) {
     let element = match node.borrow().get_element() {
         Some(e) => e,
         None => return,
     };
 
-    // "1. If element has no href attribute, then return."
-    let _href = match element.get_attribute("href") {
+    // "1. If subject cannot navigate, then return."
+    // "An element element cannot navigate if element is not a descendant of a document."
+    let document = tree_root(&node);
+    if document.borrow().kind() != NodeKind::Document {
+        return;
+    }
+    let window = match document.borrow().window().upgrade() {
+        Some(window) => window,
+        None => return,
+    };
+
+    // "If element has no href attribute, then return."
+    let href = match element.get_attribute("href") {
         Some(href) => href,
         None => return,
     };
 
-    // navigate
+    // "2. Let targetAttributeValue be the empty string."
+    // "3. If subject is an a or area element, then set targetAttributeValue to the result of
+    // getting an element's target given subject."
+    // https://html.spec.whatwg.org/multipage/semantics.html#get-an-element's-target
+    let target = element.get_attribute("target").unwrap_or_default();
+
+    // "Let urlString be the result of encoding-parsing-and-serializing a URL given subject's
+    // href attribute value, relative to subject's node document."
+    let base_url = window.borrow().base_url();
+    let url = match Url::new(base_url).parse() {
+        Ok(base) => base.resolve(&href),
+        // "If urlString is failure, then return."
+        Err(_) => return,
+    };
+
+    // "Navigate targetNavigable to urlString using subject's node document, with
+    // referrerPolicy set to referrerPolicy, userInvolvement set to userInvolvement, and
+    // sourceElement set to subject."
+    // The navigable is chosen by the page which holds the document because a document doesn't
+    // know the other navigables.
+    window.borrow_mut().navigate(Navigation::new(url, target));
 }
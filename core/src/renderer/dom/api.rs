@@ -1,8 +1,17 @@
+use crate::renderer::css::cssom::CssParser;
+use crate::renderer::css::cssom::Selector;
+use crate::renderer::css::selector::matches_selector;
+use crate::renderer::css::token::CssTokenizer;
+use crate::renderer::dom::exception::DomException;
+use crate::renderer::dom::mutation::children;
+use crate::renderer::dom::mutation::descendants;
+use crate::renderer::dom::mutation::insert;
+use crate::renderer::dom::mutation::set_text_content;
 use crate::renderer::dom::node::Element;
 use crate::renderer::dom::node::ElementKind;
 use crate::renderer::dom::node::Node;
 use crate::renderer::dom::node::NodeKind;
-use alloc::rc::Rc;
+use alloc::rc::{Rc, Weak};
 use alloc::string::String;
 use alloc::string::ToString;
 use alloc::vec::Vec;
@@ -34,7 +43,8 @@ pub fn get_element_by_id(
     }
 }
 
-// TODO: return an array of Node instead of one Node.
+/// Returns the first element of `element_kind` among `node` and its following siblings and their
+/// descendants. Use `get_elements_by_tag_name` to get all of them.
 pub fn get_target_element_node(
     node: Option<Rc<RefCell<Node>>>,
     element_kind: ElementKind,
@@ -88,3 +98,417 @@ pub fn get_js_content(root: Rc<RefCell<Node>>) -> String {
     };
     content
 }
+
+/// Returns the first element of `kind` among `node` and its following siblings and their
+/// descendants, in tree order.
+fn first_element_of_kind(
+    node: Option<Rc<RefCell<Node>>>,
+    kind: ElementKind,
+) -> Option<Rc<RefCell<Node>>> {
+    let mut node = node;
+    while let Some(n) = node {
+        if n.borrow().element_kind() == Some(kind) {
+            return Some(n);
+        }
+        if let Some(found) = first_element_of_kind(n.borrow().first_child(), kind) {
+            return Some(found);
+        }
+        node = n.borrow().next_sibling();
+    }
+    None
+}
+
+/// Returns the first element for which `predicate` returns true among `node` and its following
+/// siblings and their descendants, in tree order.
+fn first_element_matching(
+    node: Option<Rc<RefCell<Node>>>,
+    predicate: &dyn Fn(&Element) -> bool,
+) -> Option<Rc<RefCell<Node>>> {
+    let mut node = node;
+    while let Some(n) = node {
+        if let Some(e) = n.borrow().get_element() {
+            if predicate(&e) {
+                return Some(n.clone());
+            }
+        }
+        if let Some(found) = first_element_matching(n.borrow().first_child(), predicate) {
+            return Some(found);
+        }
+        node = n.borrow().next_sibling();
+    }
+    None
+}
+
+/// Returns the first child of `node` which is an element of `kind`.
+fn first_child_of_kind(node: &Rc<RefCell<Node>>, kind: ElementKind) -> Option<Rc<RefCell<Node>>> {
+    let mut child = node.borrow().first_child();
+    while let Some(c) = child {
+        if c.borrow().element_kind() == Some(kind) {
+            return Some(c);
+        }
+        child = c.borrow().next_sibling();
+    }
+    None
+}
+
+/// https://html.spec.whatwg.org/multipage/dom.html#the-title-element-2
+pub fn title_element(document: &Rc<RefCell<Node>>) -> Option<Rc<RefCell<Node>>> {
+    // "The title element of a document is the first title element in the document (in tree
+    // order), if there is one, or null otherwise."
+    first_element_of_kind(document.borrow().first_child(), ElementKind::Title)
+}
+
+/// https://html.spec.whatwg.org/multipage/dom.html#document.title
+pub fn document_title(document: &Rc<RefCell<Node>>) -> String {
+    // "2. Otherwise, let value be the child text content of the title element, or the empty
+    // string if the title element is null."
+    let mut value = String::new();
+    if let Some(title) = title_element(document) {
+        let mut child = title.borrow().first_child();
+        while let Some(c) = child {
+            if let NodeKind::Text(ref s) = c.borrow().kind() {
+                value.push_str(s);
+            }
+            child = c.borrow().next_sibling();
+        }
+    }
+
+    // "3. Strip and collapse ASCII whitespace in value."
+    value
+        .split_ascii_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// https://html.spec.whatwg.org/multipage/dom.html#document.title
+pub fn set_document_title(document: &Rc<RefCell<Node>>, title: String) {
+    // "3. Otherwise, if the document element is in the HTML namespace:"
+    let html = match first_child_of_kind(document, ElementKind::Html) {
+        Some(html) => html,
+        None => return,
+    };
+
+    let element = match title_element(document) {
+        // "2. If the title element is non-null, let element be the title element."
+        Some(element) => element,
+        None => {
+            // "1. If the title element is null and the head element is null, then return."
+            let head = match first_child_of_kind(&html, ElementKind::Head) {
+                Some(head) => head,
+                None => return,
+            };
+            // "3. Otherwise:
+            //   1. Let element be the result of creating an element given the document element's
+            //   node document, "title", and the HTML namespace.
+            //   2. Append element to the head element."
+            let element = Rc::new(RefCell::new(Node::new(NodeKind::Element(Element::new(
+                "title",
+                Vec::new(),
+            )))));
+            insert(&element, &head, None);
+            element
+        }
+    };
+
+    // "4. String replace all with the given value within element."
+    set_text_content(&element, title);
+}
+
+/// Returns the first base element that has an href attribute in the document, in tree order.
+/// https://html.spec.whatwg.org/multipage/urls-and-fetching.html#document-base-url
+pub fn base_element(document: &Rc<RefCell<Node>>) -> Option<Rc<RefCell<Node>>> {
+    first_element_matching(document.borrow().first_child(), &|e| {
+        e.local_name() == "base" && e.get_attribute("href").is_some()
+    })
+}
+
+/// Returns the first meta element in the refresh state, i.e. whose http-equiv attribute is an
+/// ASCII case-insensitive match for "refresh", in tree order.
+/// https://html.spec.whatwg.org/multipage/semantics.html#attr-meta-http-equiv-refresh
+pub fn refresh_meta_element(document: &Rc<RefCell<Node>>) -> Option<Rc<RefCell<Node>>> {
+    first_element_matching(document.borrow().first_child(), &|e| {
+        e.local_name() == "meta"
+            && e.get_attribute("http-equiv")
+                .is_some_and(|v| v.eq_ignore_ascii_case("refresh"))
+    })
+}
+
+/// Appends the elements of `kind` among `node` and its following siblings and their descendants
+/// to `result`, in tree order.
+fn collect_elements_of_kind(
+    node: Option<Rc<RefCell<Node>>>,
+    kind: ElementKind,
+    result: &mut Vec<Rc<RefCell<Node>>>,
+) {
+    let mut node = node;
+    while let Some(n) = node {
+        if n.borrow().element_kind() == Some(kind) {
+            result.push(n.clone());
+        }
+        collect_elements_of_kind(n.borrow().first_child(), kind, result);
+        node = n.borrow().next_sibling();
+    }
+}
+
+/// Returns the iframe elements in the document, in tree order. Each of them has a child
+/// navigable.
+/// https://html.spec.whatwg.org/multipage/iframe-embed-object.html#the-iframe-element
+pub fn iframe_elements(document: &Rc<RefCell<Node>>) -> Vec<Rc<RefCell<Node>>> {
+    let mut result = Vec::new();
+    collect_elements_of_kind(
+        document.borrow().first_child(),
+        ElementKind::Iframe,
+        &mut result,
+    );
+    result
+}
+
+/// Parses `selectors` for a DOM API which takes a selector.
+/// https://dom.spec.whatwg.org/#scope-match-a-selectors-string
+fn parse_selectors(selectors: &str) -> Result<Selector, DomException> {
+    // "1. Let s be the result of parse a selector selectors.
+    // 2. If s is failure, then throw a "SyntaxError" DOMException."
+    let t = CssTokenizer::new(selectors.to_string());
+    CssParser::new(Weak::new(), t)
+        .parse_selector()
+        .ok_or(DomException::SyntaxError)
+}
+
+/// Returns the first descendant element of `node` which `selectors` matches, in tree order.
+/// https://dom.spec.whatwg.org/#dom-parentnode-queryselector
+pub fn query_selector(
+    node: &Rc<RefCell<Node>>,
+    selectors: &str,
+) -> Result<Option<Rc<RefCell<Node>>>, DomException> {
+    // "The querySelector(selectors) method steps are to return the first result of running scope-
+    // match a selectors string selectors against this, if the result is not an empty list;
+    // otherwise null."
+    let selector = parse_selectors(selectors)?;
+    Ok(descendants(node)
+        .into_iter()
+        .find(|n| matches_selector(&selector, &n.borrow())))
+}
+
+/// Returns all the descendant elements of `node` which `selectors` matches, in tree order.
+/// https://dom.spec.whatwg.org/#dom-parentnode-queryselectorall
+pub fn query_selector_all(
+    node: &Rc<RefCell<Node>>,
+    selectors: &str,
+) -> Result<Vec<Rc<RefCell<Node>>>, DomException> {
+    // "The querySelectorAll(selectors) method steps are to return the static result of running
+    // scope-match a selectors string selectors against this."
+    let selector = parse_selectors(selectors)?;
+    Ok(descendants(node)
+        .into_iter()
+        .filter(|n| matches_selector(&selector, &n.borrow()))
+        .collect())
+}
+
+/// https://dom.spec.whatwg.org/#dom-element-matches
+pub fn element_matches(element: &Rc<RefCell<Node>>, selectors: &str) -> Result<bool, DomException> {
+    // "1. Let s be the result of parse a selector from selectors.
+    // 2. If s is failure, then throw a "SyntaxError" DOMException.
+    // 3. If the result of match a selector against an element, using s, this, and scoping root
+    // this, returns success, then return true; otherwise, return false."
+    let selector = parse_selectors(selectors)?;
+    Ok(matches_selector(&selector, &element.borrow()))
+}
+
+/// https://dom.spec.whatwg.org/#dom-element-closest
+pub fn closest(
+    element: &Rc<RefCell<Node>>,
+    selectors: &str,
+) -> Result<Option<Rc<RefCell<Node>>>, DomException> {
+    // "1. Let s be the result of parse a selector from selectors.
+    // 2. If s is failure, then throw a "SyntaxError" DOMException.
+    // 3. Let elements be this's inclusive ancestors that are elements, in reverse tree order.
+    // 4. For each element in elements, if match a selector against an element, using s, element,
+    // and scoping root this, returns success, return element.
+    // 5. Return null."
+    let selector = parse_selectors(selectors)?;
+    let mut node = Some(element.clone());
+    while let Some(n) = node {
+        if matches_selector(&selector, &n.borrow()) {
+            return Ok(Some(n.clone()));
+        }
+        node = n.borrow().parent().upgrade();
+    }
+    Ok(None)
+}
+
+/// Returns the children of `node` which are elements, in tree order.
+/// https://dom.spec.whatwg.org/#dom-parentnode-children
+pub fn element_children(node: &Rc<RefCell<Node>>) -> Vec<Rc<RefCell<Node>>> {
+    children(node)
+        .into_iter()
+        .filter(|c| c.borrow().get_element().is_some())
+        .collect()
+}
+
+/// Returns the descendant elements of `root` whose qualified name is `qualified_name`, in tree
+/// order.
+/// https://dom.spec.whatwg.org/#concept-getelementsbytagname
+pub fn get_elements_by_tag_name(
+    root: &Rc<RefCell<Node>>,
+    qualified_name: &str,
+) -> Vec<Rc<RefCell<Node>>> {
+    // "1. If qualifiedName is U+002A (*), return a HTMLCollection rooted at root, whose filter
+    // matches only descendant elements.
+    // 2. Otherwise, if root's node document is an HTML document, return a HTMLCollection rooted
+    // at root, whose filter matches the following descendant elements:
+    //   * Whose namespace is the HTML namespace and whose qualified name is qualifiedName, in
+    //   ASCII lowercase."
+    let name = qualified_name.to_ascii_lowercase();
+    descendants(root)
+        .into_iter()
+        .filter(|n| match n.borrow().get_element() {
+            Some(e) => name == "*" || e.local_name() == name,
+            None => false,
+        })
+        .collect()
+}
+
+/// Returns the descendant elements of `root` which have all the classes in `class_names`, in tree
+/// order.
+/// https://dom.spec.whatwg.org/#concept-getelementsbyclassname
+pub fn get_elements_by_class_name(
+    root: &Rc<RefCell<Node>>,
+    class_names: &str,
+) -> Vec<Rc<RefCell<Node>>> {
+    // "1. Let classes be the result of running the ordered set parser on classNames.
+    // 2. If classes is the empty set, return an empty HTMLCollection.
+    // 3. Return a HTMLCollection rooted at root, whose filter matches descendant elements that
+    // have all their classes in classes."
+    let classes: Vec<&str> = class_names.split_ascii_whitespace().collect();
+    if classes.is_empty() {
+        return Vec::new();
+    }
+    descendants(root)
+        .into_iter()
+        .filter(|n| {
+            classes.iter().all(|class| {
+                matches_selector(&Selector::ClassSelector(class.to_string()), &n.borrow())
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::test_util::create_document;
+    use alloc::vec;
+
+    fn ids(nodes: &[Rc<RefCell<Node>>]) -> Vec<String> {
+        nodes
+            .iter()
+            .map(|n| {
+                n.borrow()
+                    .get_element()
+                    .and_then(|e| e.get_attribute("id"))
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_query_selector() {
+        let document = create_document(
+            "<div id=a class=\"x y\"><p id=b class=y>1</p></div><p id=c class=\"z\">2</p>",
+        );
+        assert_eq!(
+            vec!["b".to_string(), "c".to_string()],
+            ids(&query_selector_all(&document, "P").unwrap())
+        );
+        assert_eq!(
+            vec!["a".to_string(), "b".to_string()],
+            ids(&query_selector_all(&document, ".y").unwrap())
+        );
+        let c = query_selector(&document, "#c").unwrap().unwrap();
+        assert_eq!(vec!["c".to_string()], ids(&[c.clone()]));
+        assert!(query_selector(&document, "#none").unwrap().is_none());
+
+        // Only descendants are matched.
+        let a = query_selector(&document, "div").unwrap().unwrap();
+        assert_eq!(
+            vec!["b".to_string()],
+            ids(&query_selector_all(&a, ".y").unwrap())
+        );
+
+        // Compound selectors, descendant combinators and selector lists.
+        assert_eq!(
+            vec!["b".to_string()],
+            ids(&query_selector_all(&document, "p.y").unwrap())
+        );
+        assert_eq!(
+            vec!["b".to_string()],
+            ids(&query_selector_all(&document, "div p").unwrap())
+        );
+        assert_eq!(
+            vec!["a".to_string(), "c".to_string()],
+            ids(&query_selector_all(&document, "#c, .x").unwrap())
+        );
+
+        assert_eq!(
+            Err(DomException::SyntaxError),
+            query_selector(&document, "div > p")
+        );
+        assert_eq!(
+            Err(DomException::SyntaxError),
+            query_selector_all(&document, "")
+        );
+    }
+
+    #[test]
+    fn test_matches_and_closest() {
+        let document = create_document("<div id=a class=x><p id=b class=y>1</p></div>");
+        let b = query_selector(&document, "#b").unwrap().unwrap();
+        assert_eq!(Ok(true), element_matches(&b, ".y"));
+        assert_eq!(Ok(false), element_matches(&b, ".x"));
+
+        // An element itself is the first candidate of closest().
+        assert_eq!(
+            vec!["b".to_string()],
+            ids(&[closest(&b, "p").unwrap().unwrap()])
+        );
+        assert_eq!(
+            vec!["a".to_string()],
+            ids(&[closest(&b, ".x").unwrap().unwrap()])
+        );
+        assert!(closest(&b, "span").unwrap().is_none());
+        assert_eq!(Err(DomException::SyntaxError), closest(&b, "a:hover"));
+    }
+
+    #[test]
+    fn test_get_elements() {
+        let document = create_document(
+            "<ul id=a><li id=b class=\"x y\">1</li>t<li id=c class=y>2</li></ul><LI id=d class=x></LI>",
+        );
+        let ul = get_element_by_id(Some(document.clone()), &"a".to_string()).unwrap();
+
+        assert_eq!(
+            vec!["b".to_string(), "c".to_string(), "d".to_string()],
+            ids(&get_elements_by_tag_name(&document, "LI"))
+        );
+        assert_eq!(
+            vec!["b".to_string(), "c".to_string()],
+            ids(&get_elements_by_tag_name(&ul, "*"))
+        );
+        assert_eq!(
+            vec!["b".to_string(), "d".to_string()],
+            ids(&get_elements_by_class_name(&document, "x"))
+        );
+        assert_eq!(
+            vec!["b".to_string()],
+            ids(&get_elements_by_class_name(&document, " y  x "))
+        );
+        assert!(get_elements_by_class_name(&document, " ").is_empty());
+
+        // The text node between the list items is a child node but not an element child.
+        assert_eq!(3, children(&ul).len());
+        assert_eq!(
+            vec!["b".to_string(), "c".to_string()],
+            ids(&element_children(&ul))
+        );
+    }
+}
@@ -0,0 +1,111 @@
+//! This module implements collections of nodes, which are HTMLCollection and NodeList.
+//! https://dom.spec.whatwg.org/#collections
+
+use crate::renderer::dom::api::element_children;
+use crate::renderer::dom::api::get_elements_by_class_name;
+use crate::renderer::dom::api::get_elements_by_tag_name;
+use crate::renderer::dom::mutation::children;
+use crate::renderer::dom::node::Node;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+/// The nodes which a collection represents.
+/// https://dom.spec.whatwg.org/#concept-collection
+#[derive(Debug, Clone)]
+pub enum CollectionFilter {
+    /// https://dom.spec.whatwg.org/#dom-parentnode-children
+    Children,
+    /// https://dom.spec.whatwg.org/#dom-node-childnodes
+    ChildNodes,
+    /// https://dom.spec.whatwg.org/#concept-getelementsbytagname
+    TagName(String),
+    /// https://dom.spec.whatwg.org/#concept-getelementsbyclassname
+    ClassNames(String),
+    /// A static NodeList, which doesn't reflect changes to the tree. e.g. the result of
+    /// `querySelectorAll()`.
+    Static(Vec<Rc<RefCell<Node>>>),
+}
+
+/// "A collection is an object that represents a list of nodes. A collection can be either live
+/// or static. Unless otherwise stated, a collection must be live."
+/// https://dom.spec.whatwg.org/#concept-collection
+#[derive(Debug, Clone)]
+pub struct Collection {
+    /// https://dom.spec.whatwg.org/#concept-collection-root
+    root: Rc<RefCell<Node>>,
+    filter: CollectionFilter,
+}
+
+impl Collection {
+    pub fn new(root: Rc<RefCell<Node>>, filter: CollectionFilter) -> Self {
+        Self { root, filter }
+    }
+
+    /// Returns the nodes in this collection, in tree order. A live collection computes them from
+    /// the current tree every time.
+    pub fn nodes(&self) -> Vec<Rc<RefCell<Node>>> {
+        match &self.filter {
+            CollectionFilter::Children => element_children(&self.root),
+            CollectionFilter::ChildNodes => children(&self.root),
+            CollectionFilter::TagName(name) => get_elements_by_tag_name(&self.root, name),
+            CollectionFilter::ClassNames(names) => get_elements_by_class_name(&self.root, names),
+            CollectionFilter::Static(nodes) => nodes.clone(),
+        }
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-htmlcollection-length
+    pub fn length(&self) -> usize {
+        self.nodes().len()
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-htmlcollection-item
+    pub fn item(&self, index: usize) -> Option<Rc<RefCell<Node>>> {
+        // "The item(index) method steps are to return the indexth element in the collection. If
+        // there is no indexth element in the collection, then the method must return null."
+        self.nodes().get(index).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::dom::mutation::append_child;
+    use crate::renderer::dom::mutation::remove_child;
+    use crate::renderer::dom::node::Element;
+    use crate::renderer::dom::node::NodeKind;
+    use crate::renderer::test_util::create_window;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_live_collection() {
+        let window = create_window("<ul><li>a</li><li>b</li></ul>");
+        let document = RefCell::borrow(&window).document();
+
+        let items = Collection::new(
+            document.clone(),
+            CollectionFilter::TagName("li".to_string()),
+        );
+        let snapshot = Collection::new(document.clone(), CollectionFilter::Static(items.nodes()));
+        assert_eq!(2, items.length());
+
+        let ul = get_elements_by_tag_name(&document, "ul")[0].clone();
+        let li = Rc::new(RefCell::new(Node::new(NodeKind::Element(Element::new(
+            "li",
+            Vec::new(),
+        )))));
+        append_child(&ul, &li).unwrap();
+        assert_eq!(3, items.length());
+        assert!(Rc::ptr_eq(&li, &items.item(2).unwrap()));
+        assert!(items.item(3).is_none());
+
+        let first = items.item(0).unwrap();
+        remove_child(&ul, &first).unwrap();
+        assert_eq!(2, items.length());
+
+        // A static collection is not changed.
+        assert_eq!(2, snapshot.length());
+        assert!(Rc::ptr_eq(&first, &snapshot.item(0).unwrap()));
+    }
+}
@@ -2,16 +2,18 @@
 //! DOM Living Standard: https://dom.spec.whatwg.org/#events
 //! UI Events W3C Working Draft: https://www.w3.org/TR/uievents/
 
-use crate::renderer::dom::node::NodeKind;
-use alloc::boxed::Box;
+use crate::renderer::dom::node::Node;
+use crate::renderer::dom::window::Window;
+use alloc::rc::Rc;
 use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::RefCell;
 
 /// https://dom.spec.whatwg.org/#callbackdef-eventlistener
-pub type EventListenerCallback = fn(e: Event);
+pub type EventListenerCallback = fn(event: &mut Event);
 
 /// https://dom.spec.whatwg.org/#concept-event-listener
 #[derive(Debug, Clone)]
-#[allow(dead_code)]
 pub struct EventListener {
     event_type: String,
     callback: EventListenerCallback,
@@ -30,48 +32,724 @@ impl EventListener {
     pub fn event_type(&self) -> String {
         self.event_type.clone()
     }
+
+    pub fn callback(&self) -> EventListenerCallback {
+        self.callback
+    }
+
+    pub fn capture(&self) -> bool {
+        self.capture
+    }
+
+    /// Returns true if `self` and `other` have the same type, callback and capture. An event
+    /// target doesn't have two such listeners.
+    pub fn is_same(&self, other: &EventListener) -> bool {
+        self.event_type == other.event_type
+            && self.callback as usize == other.callback as usize
+            && self.capture == other.capture
+    }
 }
 
 /// https://dom.spec.whatwg.org/#interface-eventtarget
+///
+/// Dispatching needs the tree around the target, so it's done by `dispatch_event()` in this
+/// module instead of a method of this trait.
 pub trait EventTarget {
     /// https://dom.spec.whatwg.org/#dom-eventtarget-addeventlistener
-    fn add_event_listener(&mut self, event_type: String, callback: EventListenerCallback);
+    fn add_event_listener(
+        &mut self,
+        event_type: String,
+        callback: EventListenerCallback,
+        capture: bool,
+    );
     /// https://dom.spec.whatwg.org/#dom-eventtarget-removeeventlistener
-    fn remove_event_listener(&mut self, event_type: String, callback: EventListenerCallback);
-    /// https://dom.spec.whatwg.org/#dom-eventtarget-dispatchevent
-    fn dispatch_event(&mut self, event: Event) -> bool;
+    fn remove_event_listener(
+        &mut self,
+        event_type: String,
+        callback: EventListenerCallback,
+        capture: bool,
+    );
+    /// https://dom.spec.whatwg.org/#eventtarget-event-listener-list
+    fn event_listeners(&self) -> Vec<EventListener>;
+}
 
-    /// Used for compare 2 event targets.
-    fn target_kind(&self) -> NodeKind;
+/// https://dom.spec.whatwg.org/#dom-event-eventphase
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventPhase {
+    None = 0,
+    Capturing = 1,
+    AtTarget = 2,
+    Bubbling = 3,
 }
 
-/// https://dom.spec.whatwg.org/#interface-event
-/// https://w3c.github.io/uievents/#uievent
-pub enum Event {
+/// The interface of an event and the attributes specific to it.
+#[derive(Debug, Clone)]
+pub enum EventInterface {
+    /// https://dom.spec.whatwg.org/#interface-event
+    Event,
     /// https://w3c.github.io/uievents/#idl-mouseevent
     MouseEvent(MouseEvent),
+    /// https://w3c.github.io/uievents/#idl-keyboardevent
+    KeyboardEvent(KeyboardEvent),
+    /// https://w3c.github.io/uievents/#idl-focusevent
+    FocusEvent(FocusEvent),
+    /// https://html.spec.whatwg.org/multipage/nav-history-apis.html#popstateevent
+    PopStateEvent(PopStateEvent),
 }
 
-/// https://w3c.github.io/uievents/#idl-mouseevent
-#[allow(dead_code)]
-pub struct MouseEvent {
+/// https://dom.spec.whatwg.org/#interface-event
+#[derive(Debug, Clone)]
+pub struct Event {
     event_type: String,
-    pub target: Box<dyn EventTarget>,
-    screen_x: i32,
-    screen_y: i32,
+    interface: EventInterface,
+    /// https://dom.spec.whatwg.org/#event-target
+    target: Option<Rc<RefCell<Node>>>,
+    /// https://dom.spec.whatwg.org/#dom-event-currenttarget
+    current_target: Option<Rc<RefCell<Node>>>,
+    event_phase: EventPhase,
+    bubbles: bool,
+    cancelable: bool,
+    /// https://dom.spec.whatwg.org/#stop-propagation-flag
+    stop_propagation: bool,
+    /// https://dom.spec.whatwg.org/#stop-immediate-propagation-flag
+    stop_immediate_propagation: bool,
+    /// https://dom.spec.whatwg.org/#canceled-flag
+    canceled: bool,
+    /// https://dom.spec.whatwg.org/#dispatch-flag
+    dispatch: bool,
 }
 
-impl MouseEvent {
-    pub fn new(event_type: String, target: Box<dyn EventTarget>) -> Self {
+impl Event {
+    /// https://dom.spec.whatwg.org/#concept-event-create
+    pub fn new(event_type: &str, bubbles: bool, cancelable: bool) -> Self {
         Self {
-            event_type,
-            target,
-            screen_x: 0,
-            screen_y: 0,
+            event_type: event_type.into(),
+            interface: EventInterface::Event,
+            target: None,
+            current_target: None,
+            event_phase: EventPhase::None,
+            bubbles,
+            cancelable,
+            stop_propagation: false,
+            stop_immediate_propagation: false,
+            canceled: false,
+            dispatch: false,
         }
     }
 
+    /// Creates a MouseEvent. "click", "mousedown" and "mouseup" events bubble and are
+    /// cancelable.
+    /// https://w3c.github.io/uievents/#events-mouse-types
+    pub fn new_mouse_event(event_type: &str, mouse_event: MouseEvent) -> Self {
+        let mut event = Self::new(event_type, true, true);
+        event.interface = EventInterface::MouseEvent(mouse_event);
+        event
+    }
+
+    /// Creates a KeyboardEvent. "keydown", "keypress" and "keyup" events bubble and are
+    /// cancelable.
+    /// https://w3c.github.io/uievents/#events-keyboard-types
+    pub fn new_keyboard_event(event_type: &str, keyboard_event: KeyboardEvent) -> Self {
+        let mut event = Self::new(event_type, true, true);
+        event.interface = EventInterface::KeyboardEvent(keyboard_event);
+        event
+    }
+
+    /// Creates a FocusEvent. "focusin" and "focusout" events bubble while "focus" and "blur"
+    /// events don't. None of them is cancelable.
+    /// https://w3c.github.io/uievents/#events-focus-types
+    pub fn new_focus_event(event_type: &str, focus_event: FocusEvent) -> Self {
+        let bubbles = event_type == "focusin" || event_type == "focusout";
+        let mut event = Self::new(event_type, bubbles, false);
+        event.interface = EventInterface::FocusEvent(focus_event);
+        event
+    }
+
+    /// Creates a PopStateEvent, which doesn't bubble and isn't cancelable.
+    /// https://html.spec.whatwg.org/multipage/browsing-the-web.html#update-document-for-history-step-application
+    pub fn new_popstate_event(popstate_event: PopStateEvent) -> Self {
+        let mut event = Self::new("popstate", false, false);
+        event.interface = EventInterface::PopStateEvent(popstate_event);
+        event
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-event-type
     pub fn event_type(&self) -> String {
         self.event_type.clone()
     }
+
+    pub fn interface(&self) -> &EventInterface {
+        &self.interface
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-event-target
+    pub fn target(&self) -> Option<Rc<RefCell<Node>>> {
+        self.target.clone()
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-event-currenttarget
+    pub fn current_target(&self) -> Option<Rc<RefCell<Node>>> {
+        self.current_target.clone()
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-event-eventphase
+    pub fn event_phase(&self) -> EventPhase {
+        self.event_phase
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-event-bubbles
+    pub fn bubbles(&self) -> bool {
+        self.bubbles
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-event-cancelable
+    pub fn cancelable(&self) -> bool {
+        self.cancelable
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-event-stoppropagation
+    pub fn stop_propagation(&mut self) {
+        // "The stopPropagation() method steps are to set this’s stop propagation flag."
+        self.stop_propagation = true;
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-event-stopimmediatepropagation
+    pub fn stop_immediate_propagation(&mut self) {
+        // "The stopImmediatePropagation() method steps are to set this’s stop propagation flag
+        // and this’s stop immediate propagation flag."
+        self.stop_propagation = true;
+        self.stop_immediate_propagation = true;
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-event-preventdefault
+    pub fn prevent_default(&mut self) {
+        // "The preventDefault() method steps are to set the canceled flag given this."
+        // https://dom.spec.whatwg.org/#set-the-canceled-flag
+        // "To set the canceled flag, given an event event, if event’s cancelable attribute value
+        // is true and event’s in passive listener flag is unset, then set event’s canceled flag."
+        if self.cancelable {
+            self.canceled = true;
+        }
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-event-defaultprevented
+    pub fn default_prevented(&self) -> bool {
+        self.canceled
+    }
+}
+
+/// https://w3c.github.io/uievents/#idl-mouseevent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MouseEvent {
+    screen_x: i64,
+    screen_y: i64,
+    client_x: i64,
+    client_y: i64,
+    /// https://w3c.github.io/uievents/#dom-mouseevent-button
+    button: i16,
+}
+
+impl MouseEvent {
+    pub fn new(screen_x: i64, screen_y: i64, client_x: i64, client_y: i64, button: i16) -> Self {
+        Self {
+            screen_x,
+            screen_y,
+            client_x,
+            client_y,
+            button,
+        }
+    }
+
+    pub fn screen_x(&self) -> i64 {
+        self.screen_x
+    }
+
+    pub fn screen_y(&self) -> i64 {
+        self.screen_y
+    }
+
+    pub fn client_x(&self) -> i64 {
+        self.client_x
+    }
+
+    pub fn client_y(&self) -> i64 {
+        self.client_y
+    }
+
+    pub fn button(&self) -> i16 {
+        self.button
+    }
+}
+
+/// https://w3c.github.io/uievents/#idl-keyboardevent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyboardEvent {
+    /// The key attribute value, which is a printed character such as "a", or a named key such as
+    /// "Enter", "Tab" and "Backspace".
+    /// https://w3c.github.io/uievents/#dom-keyboardevent-key
+    /// https://w3c.github.io/uievents-key/#named-key-attribute-values
+    key: String,
+    /// https://w3c.github.io/uievents/#dom-keyboardevent-shiftkey
+    shift_key: bool,
+}
+
+impl KeyboardEvent {
+    pub fn new(key: &str, shift_key: bool) -> Self {
+        Self {
+            key: key.into(),
+            shift_key,
+        }
+    }
+
+    pub fn key(&self) -> String {
+        self.key.clone()
+    }
+
+    pub fn shift_key(&self) -> bool {
+        self.shift_key
+    }
+
+    /// Returns true if the key produces a character value, which is the case of a key attribute
+    /// value of one character.
+    /// https://w3c.github.io/uievents/#character-value
+    pub fn is_character(&self) -> bool {
+        self.key.chars().count() == 1
+    }
+}
+
+/// https://w3c.github.io/uievents/#idl-focusevent
+#[derive(Debug, Clone)]
+pub struct FocusEvent {
+    /// The element losing the focus for "focus" and "focusin" events, and the one gaining the
+    /// focus for "blur" and "focusout" events.
+    /// https://w3c.github.io/uievents/#dom-focusevent-relatedtarget
+    related_target: Option<Rc<RefCell<Node>>>,
+}
+
+impl FocusEvent {
+    pub fn new(related_target: Option<Rc<RefCell<Node>>>) -> Self {
+        Self { related_target }
+    }
+
+    pub fn related_target(&self) -> Option<Rc<RefCell<Node>>> {
+        self.related_target.clone()
+    }
+}
+
+/// https://html.spec.whatwg.org/multipage/nav-history-apis.html#popstateevent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PopStateEvent {
+    /// The state of the session history entry which the history is traversed to.
+    /// https://html.spec.whatwg.org/multipage/nav-history-apis.html#dom-popstateevent-state
+    state: Option<String>,
+}
+
+impl PopStateEvent {
+    pub fn new(state: Option<String>) -> Self {
+        Self { state }
+    }
+
+    pub fn state(&self) -> Option<String> {
+        self.state.clone()
+    }
+}
+
+/// The phase in which listeners are invoked.
+/// https://dom.spec.whatwg.org/#concept-event-listener-invoke
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InvokePhase {
+    Capturing,
+    Bubbling,
+}
+
+/// Dispatches `event` to `target` and returns false if the event is canceled.
+/// https://dom.spec.whatwg.org/#concept-event-dispatch
+///
+/// Shadow trees, related targets and touch targets are not supported, and the path ends at the
+/// document. An event targeted at Window is dispatched by `dispatch_event_to_window()`.
+pub fn dispatch_event(target: &Rc<RefCell<Node>>, event: &mut Event) -> bool {
+    // "1. Set event’s dispatch flag."
+    event.dispatch = true;
+
+    // "2. Let targetOverride be target, if legacy target override flag is not given, and target’s
+    // associated Document otherwise."
+    // "5.1. Set event’s target to targetOverride."
+    event.target = Some(target.clone());
+
+    // "5.4. Let isActivationEvent be true, if event is a MouseEvent object and event’s type
+    // attribute is "click"; otherwise false."
+    let is_activation_event =
+        matches!(event.interface, EventInterface::MouseEvent(_)) && event.event_type == "click";
+
+    // "5.5. If isActivationEvent is true and target has activation behavior, then set
+    // activationTarget to target."
+    let mut activation_target = None;
+    if is_activation_event && target.borrow().activation_behavior().is_some() {
+        activation_target = Some(target.clone());
+    }
+
+    // "5.6. Let slottable be target, if target is a slottable and is assigned, and null
+    // otherwise."
+    // "5.8. Append to an event path with event, target, targetOverride, relatedTarget,
+    // touchTargets, and false."
+    let mut path = Vec::from([target.clone()]);
+
+    // "5.10. Let parent be the result of invoking target’s get the parent with event."
+    let mut parent = target.borrow().parent().upgrade();
+    // "5.11. While parent is non-null:"
+    while let Some(p) = parent {
+        // "5.11.7. Otherwise:"
+        // "5.11.7.1. If isActivationEvent is true, event’s bubbles attribute is true,
+        // activationTarget is null, and parent has activation behavior, then set
+        // activationTarget to parent."
+        if is_activation_event
+            && event.bubbles
+            && activation_target.is_none()
+            && p.borrow().activation_behavior().is_some()
+        {
+            activation_target = Some(p.clone());
+        }
+        // "5.11.7.2. Append to an event path with event, parent, null, relatedTarget,
+        // touchTargets, and slot-in-closed-tree."
+        path.push(p.clone());
+        // "5.11.8. If parent is non-null, then set parent to the result of invoking parent’s
+        // get the parent with event."
+        parent = p.borrow().parent().upgrade();
+    }
+
+    // "5.13. For each struct in event’s path, in reverse order:"
+    for node in path.iter().rev() {
+        // "5.13.1. If struct’s shadow-adjusted target is non-null, then set event’s eventPhase
+        // attribute to AT_TARGET."
+        // "5.13.2. Otherwise, set event’s eventPhase attribute to CAPTURING_PHASE."
+        event.event_phase = if Rc::ptr_eq(node, target) {
+            EventPhase::AtTarget
+        } else {
+            EventPhase::Capturing
+        };
+        // "5.13.3. Invoke with struct, event, "capturing", and legacyOutputDidListenersThrowFlag
+        // if given."
+        invoke(node, event, InvokePhase::Capturing);
+    }
+
+    // "5.14. For each struct in event’s path:"
+    for node in path.iter() {
+        // "5.14.1. If struct’s shadow-adjusted target is non-null, then set event’s eventPhase
+        // attribute to AT_TARGET."
+        if Rc::ptr_eq(node, target) {
+            event.event_phase = EventPhase::AtTarget;
+        } else {
+            // "5.14.2.1. If event’s bubbles attribute is false, then continue."
+            if !event.bubbles {
+                continue;
+            }
+            // "5.14.2.2. Set event’s eventPhase attribute to BUBBLING_PHASE."
+            event.event_phase = EventPhase::Bubbling;
+        }
+        // "5.14.3. Invoke with struct, event, "bubbling", and legacyOutputDidListenersThrowFlag
+        // if given."
+        invoke(node, event, InvokePhase::Bubbling);
+    }
+
+    // "6. Set event’s eventPhase attribute to NONE."
+    event.event_phase = EventPhase::None;
+    // "7. Set event’s currentTarget attribute to null."
+    event.current_target = None;
+    // "8. Set event’s path to the empty list."
+
+    // "11. If activationTarget is non-null, then:"
+    if let Some(activation_target) = activation_target {
+        // "11.1. If event’s canceled flag is unset, then run activationTarget’s activation
+        // behavior with event."
+        // "11.2. Otherwise, if activationTarget has legacy-canceled-activation behavior, then
+        // run activationTarget’s legacy-canceled-activation behavior."
+        let activation_behavior = activation_target.borrow().activation_behavior();
+        if let Some(activation_behavior) = activation_behavior {
+            if !event.canceled {
+                activation_behavior(activation_target.clone(), event);
+            }
+        }
+    }
+
+    // "10. Unset event’s dispatch flag, stop propagation flag, and stop immediate propagation
+    // flag."
+    event.dispatch = false;
+    event.stop_propagation = false;
+    event.stop_immediate_propagation = false;
+
+    // "12. Return false if event’s canceled flag is set; otherwise true."
+    !event.canceled
+}
+
+/// Dispatches `event` to `window` and returns false if the event is canceled. Window has no
+/// parent, so the listeners of the window are the only ones invoked. The target and the current
+/// target of the event stay null because they hold nodes in this browser.
+/// https://dom.spec.whatwg.org/#concept-event-dispatch
+pub fn dispatch_event_to_window(window: &Rc<RefCell<Window>>, event: &mut Event) -> bool {
+    // "1. Set event’s dispatch flag."
+    event.dispatch = true;
+
+    // "5.13.1. If struct’s shadow-adjusted target is non-null, then set event’s eventPhase
+    // attribute to AT_TARGET."
+    event.event_phase = EventPhase::AtTarget;
+    for phase in [InvokePhase::Capturing, InvokePhase::Bubbling] {
+        // "3. If event’s stop propagation flag is set, then return."
+        if !event.stop_propagation {
+            inner_invoke(window, event, phase);
+        }
+    }
+
+    // "6. Set event’s eventPhase attribute to NONE."
+    event.event_phase = EventPhase::None;
+
+    // "10. Unset event’s dispatch flag, stop propagation flag, and stop immediate propagation
+    // flag."
+    event.dispatch = false;
+    event.stop_propagation = false;
+    event.stop_immediate_propagation = false;
+
+    // "12. Return false if event’s canceled flag is set; otherwise true."
+    !event.canceled
+}
+
+/// https://dom.spec.whatwg.org/#concept-event-listener-invoke
+fn invoke(node: &Rc<RefCell<Node>>, event: &mut Event, phase: InvokePhase) {
+    // "3. If event’s stop propagation flag is set, then return."
+    if event.stop_propagation {
+        return;
+    }
+
+    // "4. Initialize event’s currentTarget attribute to struct’s invocation target."
+    event.current_target = Some(node.clone());
+
+    inner_invoke(node, event, phase);
+}
+
+/// https://dom.spec.whatwg.org/#concept-event-listener-inner-invoke
+fn inner_invoke<T: EventTarget>(target: &Rc<RefCell<T>>, event: &mut Event, phase: InvokePhase) {
+    // "5. Let listeners be a clone of event’s currentTarget attribute value’s event listener
+    // list."
+    // "NOTE: This avoids event listeners added after this point from being run. Note that
+    // removal still has an effect due to the removed field."
+    let listeners = target.borrow().event_listeners();
+
+    // "2. For each listener of listeners, whose removed is false:"
+    for listener in listeners {
+        if !target
+            .borrow()
+            .event_listeners()
+            .iter()
+            .any(|l| l.is_same(&listener))
+        {
+            continue;
+        }
+
+        // "2.1. If event’s type attribute value is not listener’s type, then continue."
+        if event.event_type != listener.event_type {
+            continue;
+        }
+
+        // "2.3. If phase is "capturing" and listener’s capture is false, then continue."
+        // "2.4. If phase is "bubbling" and listener’s capture is true, then continue."
+        match phase {
+            InvokePhase::Capturing if !listener.capture => continue,
+            InvokePhase::Bubbling if listener.capture => continue,
+            _ => {}
+        }
+
+        // "2.10. Call a user object’s operation with listener’s callback, "handleEvent", « event
+        // », and event’s currentTarget attribute value."
+        (listener.callback)(event);
+
+        // "2.13. If event’s stop immediate propagation flag is set, then break."
+        if event.stop_immediate_propagation {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::dom::api::get_element_by_id;
+    use crate::renderer::test_util::create_document;
+    use alloc::string::ToString;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    // Callbacks are plain functions, so they record what they see in statics. Each test uses its
+    // own statics because tests run in parallel.
+    static LOG: [AtomicUsize; 8] = [
+        AtomicUsize::new(0),
+        AtomicUsize::new(0),
+        AtomicUsize::new(0),
+        AtomicUsize::new(0),
+        AtomicUsize::new(0),
+        AtomicUsize::new(0),
+        AtomicUsize::new(0),
+        AtomicUsize::new(0),
+    ];
+    static LOG_LENGTH: AtomicUsize = AtomicUsize::new(0);
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    /// Records the current target as the number of its ancestors and the phase, e.g. (2,
+    /// Capturing) for the <body> element.
+    fn record(event: &mut Event) {
+        let mut depth = 0;
+        let mut node = event.current_target().unwrap();
+        while let Some(parent) = node.clone().borrow().parent().upgrade() {
+            depth += 1;
+            node = parent;
+        }
+        let i = LOG_LENGTH.fetch_add(1, Ordering::SeqCst);
+        LOG[i].store(depth * 4 + event.event_phase() as usize, Ordering::SeqCst);
+    }
+
+    fn take_log() -> Vec<(usize, EventPhase)> {
+        let length = LOG_LENGTH.swap(0, Ordering::SeqCst);
+        LOG[..length]
+            .iter()
+            .map(|entry| {
+                let value = entry.load(Ordering::SeqCst);
+                let phase = match value % 4 {
+                    1 => EventPhase::Capturing,
+                    2 => EventPhase::AtTarget,
+                    3 => EventPhase::Bubbling,
+                    _ => EventPhase::None,
+                };
+                (value / 4, phase)
+            })
+            .collect()
+    }
+
+    fn stop(event: &mut Event) {
+        record(event);
+        event.stop_propagation();
+    }
+
+    fn stop_immediate(event: &mut Event) {
+        event.stop_immediate_propagation();
+    }
+
+    fn prevent(event: &mut Event) {
+        event.prevent_default();
+    }
+
+    fn count(_event: &mut Event) {
+        COUNT.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn click() -> Event {
+        Event::new_mouse_event("click", MouseEvent::new(0, 0, 0, 0, 0))
+    }
+
+    #[test]
+    fn test_dispatch_phases() {
+        let document = create_document("<div id=outer><p id=inner>text</p></div>");
+        let outer = get_element_by_id(Some(document.clone()), &"outer".to_string()).unwrap();
+        let inner = get_element_by_id(Some(document.clone()), &"inner".to_string()).unwrap();
+
+        document
+            .borrow_mut()
+            .add_event_listener("click".to_string(), record, true);
+        outer
+            .borrow_mut()
+            .add_event_listener("click".to_string(), record, false);
+        outer
+            .borrow_mut()
+            .add_event_listener("click".to_string(), record, true);
+        inner
+            .borrow_mut()
+            .add_event_listener("click".to_string(), record, false);
+        inner
+            .borrow_mut()
+            .add_event_listener("click".to_string(), record, true);
+        // The same listener is not added twice.
+        inner
+            .borrow_mut()
+            .add_event_listener("click".to_string(), record, true);
+        // A listener for another type is not invoked.
+        inner
+            .borrow_mut()
+            .add_event_listener("keydown".to_string(), record, false);
+
+        let mut event = click();
+        assert!(dispatch_event(&inner, &mut event));
+        assert_eq!(
+            Vec::from([
+                (0, EventPhase::Capturing),
+                (3, EventPhase::Capturing),
+                (4, EventPhase::AtTarget),
+                (4, EventPhase::AtTarget),
+                (3, EventPhase::Bubbling),
+            ]),
+            take_log()
+        );
+        assert!(Rc::ptr_eq(&inner, &event.target().unwrap()));
+        assert!(event.current_target().is_none());
+        assert_eq!(EventPhase::None, event.event_phase());
+
+        // An event which doesn't bubble is only captured.
+        assert!(dispatch_event(
+            &inner,
+            &mut Event::new("click", false, false)
+        ));
+        assert_eq!(
+            Vec::from([
+                (0, EventPhase::Capturing),
+                (3, EventPhase::Capturing),
+                (4, EventPhase::AtTarget),
+                (4, EventPhase::AtTarget),
+            ]),
+            take_log()
+        );
+
+        inner
+            .borrow_mut()
+            .remove_event_listener("click".to_string(), record, true);
+        outer
+            .borrow_mut()
+            .remove_event_listener("click".to_string(), record, true);
+        outer
+            .borrow_mut()
+            .add_event_listener("click".to_string(), stop, true);
+        assert!(dispatch_event(&inner, &mut click()));
+        assert_eq!(
+            Vec::from([(0, EventPhase::Capturing), (3, EventPhase::Capturing)]),
+            take_log()
+        );
+    }
+
+    #[test]
+    fn test_stop_immediate_propagation_and_prevent_default() {
+        let document = create_document("<a id=link href=/next><b id=bold>link</b></a>");
+        let link = get_element_by_id(Some(document.clone()), &"link".to_string()).unwrap();
+        let bold = get_element_by_id(Some(document.clone()), &"bold".to_string()).unwrap();
+
+        bold.borrow_mut()
+            .add_event_listener("click".to_string(), count, false);
+        bold.borrow_mut()
+            .add_event_listener("click".to_string(), stop_immediate, false);
+        bold.borrow_mut()
+            .add_event_listener("click".to_string(), prevent, false);
+        link.borrow_mut()
+            .add_event_listener("click".to_string(), count, false);
+
+        // The listeners after `stop_immediate` and the ones on the ancestors are not invoked.
+        COUNT.store(0, Ordering::SeqCst);
+        assert!(dispatch_event(&bold, &mut click()));
+        assert_eq!(1, COUNT.load(Ordering::SeqCst));
+
+        bold.borrow_mut()
+            .remove_event_listener("click".to_string(), stop_immediate, false);
+        COUNT.store(0, Ordering::SeqCst);
+        let mut event = click();
+        assert!(!dispatch_event(&bold, &mut event));
+        assert!(event.default_prevented());
+        assert_eq!(2, COUNT.load(Ordering::SeqCst));
+
+        // An event which is not cancelable is not canceled.
+        let mut event = Event::new("click", true, false);
+        assert!(dispatch_event(&bold, &mut event));
+        assert!(!event.default_prevented());
+    }
 }
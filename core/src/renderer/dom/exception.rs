@@ -0,0 +1,30 @@
+//! This module implements DOMException.
+//! https://webidl.spec.whatwg.org/#idl-DOMException
+
+use core::fmt::Display;
+use core::fmt::Formatter;
+
+/// An error thrown by a DOM API.
+/// https://webidl.spec.whatwg.org/#idl-DOMException-error-names
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DomException {
+    /// "The operation would yield an incorrect node tree."
+    HierarchyRequestError,
+    /// "The object can not be found here."
+    NotFoundError,
+    /// "The string did not match the expected pattern."
+    SyntaxError,
+    /// "The operation is insecure."
+    SecurityError,
+}
+
+impl Display for DomException {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        match self {
+            DomException::HierarchyRequestError => write!(f, "HierarchyRequestError"),
+            DomException::NotFoundError => write!(f, "NotFoundError"),
+            DomException::SyntaxError => write!(f, "SyntaxError"),
+            DomException::SecurityError => write!(f, "SecurityError"),
+        }
+    }
+}
@@ -0,0 +1,318 @@
+//! This module implements focus management of a document: which elements can be focused, the
+//! sequential focus navigation order with the Tab key, and the focus update steps which fire
+//! focus events.
+//! https://html.spec.whatwg.org/multipage/interaction.html#focus
+
+use crate::renderer::dom::event::dispatch_event;
+use crate::renderer::dom::event::Event;
+use crate::renderer::dom::event::FocusEvent;
+use crate::renderer::dom::form::input_type;
+use crate::renderer::dom::form::is_disabled;
+use crate::renderer::dom::form::is_form_control;
+use crate::renderer::dom::mutation::descendants;
+use crate::renderer::dom::mutation::tree_root;
+use crate::renderer::dom::node::Element;
+use crate::renderer::dom::node::ElementKind;
+use crate::renderer::dom::node::Node;
+use alloc::rc::{Rc, Weak};
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+/// Returns the value of the `tabindex` attribute of `element`, or None if it's absent or can't
+/// be parsed as an integer.
+/// https://html.spec.whatwg.org/multipage/interaction.html#attr-tabindex
+pub fn tabindex(element: &Element) -> Option<i64> {
+    let value = element.get_attribute("tabindex")?;
+
+    // https://html.spec.whatwg.org/multipage/common-microsyntaxes.html#rules-for-parsing-integers
+    // "4. Skip ASCII whitespace within input given position."
+    let input = value.trim_start_matches(|c: char| c.is_ascii_whitespace());
+    // "6. If the character indicated by position is a U+002D HYPHEN-MINUS character (-): Let
+    // sign be "negative"."
+    // "7. Otherwise, if the character indicated by position is a U+002B PLUS SIGN character
+    // (+): Advance position to the next character."
+    let (negative, input) = match input.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, input.strip_prefix('+').unwrap_or(input)),
+    };
+    // "9. If the character indicated by position is not an ASCII digit, then return an error."
+    // "10. Collect a sequence of code points that are ASCII digits from input given position,
+    // and interpret the resulting sequence as a base-ten integer."
+    let digits: &str = match input.find(|c: char| !c.is_ascii_digit()) {
+        Some(end) => &input[..end],
+        None => input,
+    };
+    let value = digits.parse::<i64>().ok()?;
+    Some(if negative { -value } else { value })
+}
+
+/// Returns true if the user agent determines that `element` is focusable without the
+/// `tabindex` attribute: a link and a form control which is not disabled or hidden.
+/// https://html.spec.whatwg.org/multipage/interaction.html#tabindex-value
+fn is_focusable_by_default(element: &Element) -> bool {
+    match element.kind() {
+        ElementKind::A => element.get_attribute("href").is_some(),
+        _ if is_form_control(element) => !is_disabled(element) && input_type(element) != "hidden",
+        _ => false,
+    }
+}
+
+/// Returns true if `node` is a focusable area.
+/// https://html.spec.whatwg.org/multipage/interaction.html#focusable-area
+pub fn is_focusable_area(node: &Node) -> bool {
+    match node.get_element() {
+        Some(element) => {
+            if is_form_control(&element) && is_disabled(&element) {
+                return false;
+            }
+            tabindex(&element).is_some() || is_focusable_by_default(&element)
+        }
+        None => false,
+    }
+}
+
+/// Returns the nearest inclusive ancestor of `node` that is a focusable area. A click on a node
+/// focuses it.
+/// https://html.spec.whatwg.org/multipage/interaction.html#focus-fixup-rule
+pub fn focusable_inclusive_ancestor(node: &Rc<RefCell<Node>>) -> Option<Rc<RefCell<Node>>> {
+    let mut current = Some(node.clone());
+    while let Some(n) = current {
+        if is_focusable_area(&n.borrow()) {
+            return Some(n);
+        }
+        current = n.borrow().parent().upgrade();
+    }
+    None
+}
+
+/// Returns the elements that a user can move the focus to with the Tab key, in order.
+/// "Elements whose tabindex value is greater than zero" come first in the ascending order of
+/// the value, followed by the ones whose value is zero or which are focusable by default, in
+/// tree order. Elements with a negative value are skipped.
+/// https://html.spec.whatwg.org/multipage/interaction.html#sequential-focus-navigation-order
+pub fn sequential_navigation_order(document: &Rc<RefCell<Node>>) -> Vec<Rc<RefCell<Node>>> {
+    let mut positive = Vec::new();
+    let mut rest = Vec::new();
+    for node in descendants(document) {
+        if !is_focusable_area(&node.borrow()) {
+            continue;
+        }
+        let element = match node.borrow().get_element() {
+            Some(e) => e,
+            None => continue,
+        };
+        match tabindex(&element) {
+            Some(i) if i < 0 => {}
+            Some(i) if i > 0 => positive.push((i, node.clone())),
+            _ => rest.push(node.clone()),
+        }
+    }
+    // The sort is stable, so elements with the same value stay in tree order.
+    positive.sort_by_key(|(i, _)| *i);
+
+    let mut order: Vec<Rc<RefCell<Node>>> = positive.into_iter().map(|(_, n)| n).collect();
+    order.extend(rest);
+    order
+}
+
+/// Returns the element that the focus moves to from the focused element of `document`, or the
+/// first (or last when `forward` is false) one if nothing is focused. The focus moves back to
+/// the first element after the last one.
+/// https://html.spec.whatwg.org/multipage/interaction.html#sequential-navigation-search-algorithm
+pub fn next_focusable_area(
+    document: &Rc<RefCell<Node>>,
+    forward: bool,
+) -> Option<Rc<RefCell<Node>>> {
+    let order = sequential_navigation_order(document);
+    if order.is_empty() {
+        return None;
+    }
+
+    let focused = document.borrow().focused_area().upgrade();
+    let position = focused.and_then(|f| order.iter().position(|n| Rc::ptr_eq(n, &f)));
+    let next = match (position, forward) {
+        (Some(i), true) => (i + 1) % order.len(),
+        (Some(i), false) => (i + order.len() - 1) % order.len(),
+        (None, true) => 0,
+        (None, false) => order.len() - 1,
+    };
+    Some(order[next].clone())
+}
+
+/// Returns true if `node` is the focused area of its document.
+/// https://html.spec.whatwg.org/multipage/interaction.html#focused
+pub fn is_focused(node: &Rc<RefCell<Node>>) -> bool {
+    match tree_root(node).borrow().focused_area().upgrade() {
+        Some(focused) => Rc::ptr_eq(&focused, node),
+        None => false,
+    }
+}
+
+/// Returns true if `node` or one of its ancestors is focused. The content of a focused element
+/// is painted with a focus indicator.
+pub fn is_in_focused_element(node: &Rc<RefCell<Node>>) -> bool {
+    let mut current = Some(node.clone());
+    while let Some(n) = current {
+        if is_focused(&n) {
+            return true;
+        }
+        current = n.borrow().parent().upgrade();
+    }
+    false
+}
+
+/// Moves the focus of `document` to `new_focus`, or unfocuses the focused element if it's
+/// None. "blur" and "focusout" events are fired at the element that loses the focus, and then
+/// "focus" and "focusin" events are fired at the element that gets the focus.
+/// https://html.spec.whatwg.org/multipage/interaction.html#focusing-steps
+/// https://html.spec.whatwg.org/multipage/interaction.html#focus-update-steps
+pub fn focus(document: &Rc<RefCell<Node>>, new_focus: Option<Rc<RefCell<Node>>>) {
+    let old_focus = document.borrow().focused_area().upgrade();
+
+    // "4. If new focus target is the currently focused area of a top-level traversable, then
+    // return."
+    match (&old_focus, &new_focus) {
+        (Some(old), Some(new)) if Rc::ptr_eq(old, new) => return,
+        (None, None) => return,
+        _ => {}
+    }
+
+    // "1. If the entry is an element, fire a focus event named blur at entry, with related blur
+    // target as the related target."
+    if let Some(old) = &old_focus {
+        for event_type in ["blur", "focusout"] {
+            let mut event = Event::new_focus_event(event_type, FocusEvent::new(new_focus.clone()));
+            dispatch_event(old, &mut event);
+        }
+    }
+
+    // "Designate new focus target as the focused area of the document."
+    let focused_area = match &new_focus {
+        Some(n) => Rc::downgrade(n),
+        None => Weak::new(),
+    };
+    document.borrow_mut().set_focused_area(focused_area);
+
+    // "2. If the entry is an element, fire a focus event named focus at entry, with related
+    // focus target as the related target."
+    if let Some(new) = &new_focus {
+        for event_type in ["focus", "focusin"] {
+            let mut event = Event::new_focus_event(event_type, FocusEvent::new(old_focus.clone()));
+            dispatch_event(new, &mut event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::dom::api::get_element_by_id;
+    use crate::renderer::dom::event::EventTarget;
+    use crate::renderer::test_util::create_document;
+    use alloc::string::String;
+    use alloc::string::ToString;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    static BLUR: AtomicUsize = AtomicUsize::new(0);
+    static FOCUS: AtomicUsize = AtomicUsize::new(0);
+
+    fn blur(_event: &mut Event) {
+        BLUR.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn focused(_event: &mut Event) {
+        FOCUS.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn ids(nodes: &[Rc<RefCell<Node>>]) -> Vec<String> {
+        nodes
+            .iter()
+            .filter_map(|n| n.borrow().get_element()?.get_attribute("id"))
+            .collect()
+    }
+
+    #[test]
+    fn test_tabindex() {
+        let document = create_document(
+            "<p id=a tabindex=' 12x'></p><p id=b tabindex=-1></p><p id=c tabindex=+0></p><p id=d tabindex=x></p>",
+        );
+        let value = |id: &str| {
+            let node = get_element_by_id(Some(document.clone()), &id.to_string()).unwrap();
+            let element = node.borrow().get_element().unwrap();
+            tabindex(&element)
+        };
+        assert_eq!(Some(12), value("a"));
+        assert_eq!(Some(-1), value("b"));
+        assert_eq!(Some(0), value("c"));
+        assert_eq!(None, value("d"));
+    }
+
+    #[test]
+    fn test_sequential_navigation_order() {
+        let document = create_document(
+            "<a id=a href=/>a</a><a id=b>b</a><input id=c><input id=d type=hidden><button id=e \
+             disabled>e</button><p id=f tabindex=2>f</p><p id=g tabindex=-1>g</p><textarea id=h \
+             tabindex=1></textarea><span id=i tabindex=0>i</span><p id=j tabindex=2>j</p>",
+        );
+        let order = sequential_navigation_order(&document);
+        assert_eq!(
+            Vec::from(["h", "f", "j", "a", "c", "i"]),
+            ids(&order)
+                .iter()
+                .map(|s| s.as_str())
+                .collect::<Vec<&str>>()
+        );
+
+        // An element with a negative tabindex can be focused by a click.
+        let g = get_element_by_id(Some(document.clone()), &"g".to_string()).unwrap();
+        let text = g.borrow().first_child().unwrap();
+        assert!(Rc::ptr_eq(
+            &g,
+            &focusable_inclusive_ancestor(&text).unwrap()
+        ));
+
+        let next = next_focusable_area(&document, true).unwrap();
+        assert_eq!(Vec::from(["h".to_string()]), ids(&[next.clone()]));
+        focus(&document, Some(next));
+        let next = next_focusable_area(&document, true).unwrap();
+        assert_eq!(Vec::from(["f".to_string()]), ids(&[next]));
+        // The focus moves back to the last element from the first one.
+        let previous = next_focusable_area(&document, false).unwrap();
+        assert_eq!(Vec::from(["i".to_string()]), ids(&[previous]));
+    }
+
+    #[test]
+    fn test_focus_events() {
+        let document = create_document("<input id=a><input id=b>");
+        let a = get_element_by_id(Some(document.clone()), &"a".to_string()).unwrap();
+        let b = get_element_by_id(Some(document.clone()), &"b".to_string()).unwrap();
+        a.borrow_mut()
+            .add_event_listener("blur".to_string(), blur, false);
+        a.borrow_mut()
+            .add_event_listener("focus".to_string(), focused, false);
+        // "focusin" bubbles to the document while "focus" doesn't.
+        document
+            .borrow_mut()
+            .add_event_listener("focusin".to_string(), focused, false);
+        document
+            .borrow_mut()
+            .add_event_listener("focus".to_string(), focused, false);
+
+        focus(&document, Some(a.clone()));
+        assert!(is_focused(&a));
+        assert_eq!(2, FOCUS.load(Ordering::SeqCst));
+
+        // Focusing the focused element again doesn't fire events.
+        focus(&document, Some(a.clone()));
+        assert_eq!(2, FOCUS.load(Ordering::SeqCst));
+
+        focus(&document, Some(b.clone()));
+        assert!(!is_focused(&a));
+        assert!(is_focused(&b));
+        assert_eq!(1, BLUR.load(Ordering::SeqCst));
+        assert_eq!(3, FOCUS.load(Ordering::SeqCst));
+
+        focus(&document, None);
+        assert!(document.borrow().focused_area().upgrade().is_none());
+    }
+}
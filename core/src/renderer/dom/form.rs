@@ -0,0 +1,769 @@
+//! This is a helper module for forms and form controls.
+//! The state edited by a user (values, checkedness and selectedness) is kept on the element
+//! nodes, and the form submission builds an HTTP request to navigate to.
+//! https://html.spec.whatwg.org/multipage/forms.html
+//! https://html.spec.whatwg.org/multipage/form-control-infrastructure.html
+
+use crate::http::HttpRequest;
+use crate::renderer::dom::mutation::descendant_text_content;
+use crate::renderer::dom::mutation::descendants;
+use crate::renderer::dom::mutation::tree_root;
+use crate::renderer::dom::node::Element;
+use crate::renderer::dom::node::ElementKind;
+use crate::renderer::dom::node::Node;
+use crate::renderer::dom::node::NodeKind;
+use crate::url::search_params::serialize_urlencoded;
+use crate::url::search_params::UrlSearchParams;
+use crate::url::Url;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+/// Returns the state of the `type` attribute of an input element. A missing or unknown value
+/// is the Text state.
+/// https://html.spec.whatwg.org/multipage/input.html#attr-input-type
+pub fn input_type(element: &Element) -> String {
+    let t = match element.get_attribute("type") {
+        Some(t) => t.to_ascii_lowercase(),
+        None => return "text".to_string(),
+    };
+    match t.as_str() {
+        "hidden" | "text" | "search" | "tel" | "url" | "email" | "password" | "date" | "month"
+        | "week" | "time" | "datetime-local" | "number" | "range" | "color" | "checkbox"
+        | "radio" | "file" | "submit" | "image" | "reset" | "button" => t,
+        _ => "text".to_string(),
+    }
+}
+
+/// Returns true if `element` is rendered as a form control, whose content is decided by its
+/// state instead of its children.
+/// https://html.spec.whatwg.org/multipage/rendering.html#form-controls
+pub fn is_form_control(element: &Element) -> bool {
+    matches!(
+        element.kind(),
+        ElementKind::Input | ElementKind::Textarea | ElementKind::Select | ElementKind::Button
+    )
+}
+
+/// Returns true if a user can type text into `element`.
+pub fn is_text_field(element: &Element) -> bool {
+    match element.kind() {
+        ElementKind::Textarea => true,
+        ElementKind::Input => blocks_implicit_submission(element),
+        _ => false,
+    }
+}
+
+/// Returns true if `element` is an input element whose type is one of the states that block
+/// implicit submission.
+/// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#field-that-blocks-implicit-submission
+fn blocks_implicit_submission(element: &Element) -> bool {
+    element.kind() == ElementKind::Input
+        && matches!(
+            input_type(element).as_str(),
+            "text"
+                | "search"
+                | "url"
+                | "tel"
+                | "email"
+                | "password"
+                | "date"
+                | "month"
+                | "week"
+                | "time"
+                | "datetime-local"
+                | "number"
+        )
+}
+
+/// https://html.spec.whatwg.org/multipage/forms.html#concept-submit-button
+pub fn is_submit_button(element: &Element) -> bool {
+    match element.kind() {
+        // "The missing value default and invalid value default are the Submit Button state."
+        ElementKind::Button => !matches!(
+            element
+                .get_attribute("type")
+                .map(|t| t.to_ascii_lowercase())
+                .as_deref(),
+            Some("reset") | Some("button")
+        ),
+        ElementKind::Input => matches!(input_type(element).as_str(), "submit" | "image"),
+        _ => false,
+    }
+}
+
+/// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#concept-fe-disabled
+pub fn is_disabled(element: &Element) -> bool {
+    element.get_attribute("disabled").is_some()
+}
+
+/// Strips leading and trailing ASCII whitespace and collapses ASCII whitespace.
+/// https://infra.spec.whatwg.org/#strip-and-collapse-ascii-whitespace
+fn strip_and_collapse_whitespace(s: &str) -> String {
+    s.split_ascii_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// Returns the value of a form control or an option element.
+/// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#concept-fe-value
+pub fn value(node: &Rc<RefCell<Node>>) -> String {
+    let element = match node.borrow().get_element() {
+        Some(e) => e,
+        None => return String::new(),
+    };
+
+    match element.kind() {
+        // https://html.spec.whatwg.org/multipage/input.html#dom-input-value
+        ElementKind::Input => {
+            if let Some(v) = element.dirty_value() {
+                return v;
+            }
+            match element.get_attribute("value") {
+                Some(v) => v,
+                // "default/on: On getting, if the element has a value content attribute, return
+                // that attribute's value; otherwise, return the string "on"."
+                None if matches!(input_type(&element).as_str(), "checkbox" | "radio") => {
+                    "on".to_string()
+                }
+                None => String::new(),
+            }
+        }
+        // "The raw value of a textarea element must be initially the empty string." and its
+        // default value is the child text content.
+        // https://html.spec.whatwg.org/multipage/form-elements.html#concept-textarea-raw-value
+        ElementKind::Textarea => match element.dirty_value() {
+            Some(v) => v,
+            None => {
+                let mut content = String::new();
+                let mut child = node.borrow().first_child();
+                while let Some(c) = child {
+                    if let NodeKind::Text(t) = c.borrow().kind() {
+                        content.push_str(&t);
+                    }
+                    child = c.borrow().next_sibling();
+                }
+                content
+            }
+        },
+        // "The value IDL attribute, on getting, must return the value of the first option
+        // element in the list of options in tree order that has its selectedness set to true,
+        // if any. If there isn't one, then it must return the empty string."
+        // https://html.spec.whatwg.org/multipage/form-elements.html#dom-select-value
+        ElementKind::Select => match selected_option(node) {
+            Some(option) => value(&option),
+            None => String::new(),
+        },
+        // "The value of an option element is the value of the value content attribute, if
+        // there is one, or, if there is not, the value of the element's text IDL attribute."
+        // https://html.spec.whatwg.org/multipage/form-elements.html#concept-option-value
+        ElementKind::Option => match element.get_attribute("value") {
+            Some(v) => v,
+            None => strip_and_collapse_whitespace(&descendant_text_content(node)),
+        },
+        ElementKind::Button => element.get_attribute("value").unwrap_or_default(),
+        _ => String::new(),
+    }
+}
+
+/// Returns the label of an option element or a button element shown to a user.
+/// https://html.spec.whatwg.org/multipage/form-elements.html#concept-option-label
+pub fn label(node: &Rc<RefCell<Node>>) -> String {
+    let element = match node.borrow().get_element() {
+        Some(e) => e,
+        None => return String::new(),
+    };
+    // "The label IDL attribute, on getting, if there is a label content attribute, must
+    // return that attribute's value; otherwise, it must return the element's label."
+    if element.kind() == ElementKind::Option {
+        if let Some(label) = element.get_attribute("label") {
+            return label;
+        }
+    }
+    strip_and_collapse_whitespace(&descendant_text_content(node))
+}
+
+/// Sets the value of a text field edited by a user.
+/// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#concept-fe-mutable
+pub fn set_value(node: &Rc<RefCell<Node>>, value: String) {
+    let mut n = node.borrow_mut();
+    let element = match n.get_element_mut() {
+        Some(e) => e,
+        None => return,
+    };
+    let value = if element.kind() == ElementKind::Input {
+        // "The value sanitization algorithm is as follows: Strip newlines from the value."
+        // https://html.spec.whatwg.org/multipage/input.html#text-(type=text)-state-and-search-state-(type=search)
+        value.replace(['\n', '\r'], "")
+    } else {
+        value
+    };
+    element.set_value(value);
+}
+
+/// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#concept-fe-checked
+pub fn checkedness(node: &Rc<RefCell<Node>>) -> bool {
+    match node.borrow().get_element() {
+        Some(e) => match e.dirty_checkedness() {
+            Some(checked) => checked,
+            // "The checked content attribute is a boolean attribute that gives the default
+            // checkedness of the input element."
+            None => e.get_attribute("checked").is_some(),
+        },
+        None => false,
+    }
+}
+
+/// Sets the checkedness of a checkbox or a radio button. Checking a radio button unchecks the
+/// other radio buttons in the same group.
+/// https://html.spec.whatwg.org/multipage/input.html#radio-button-group
+pub fn set_checkedness(node: &Rc<RefCell<Node>>, checked: bool) {
+    let element = match node.borrow().get_element() {
+        Some(e) => e,
+        None => return,
+    };
+
+    if checked && input_type(&element) == "radio" {
+        let name = element.get_attribute("name").unwrap_or_default();
+        // "The radio button group that contains an input element a also contains all the other
+        // input elements b that fulfill all of the following conditions: ... Both a and b have
+        // the same form owner, or they both have no form owner. ... They both have a name
+        // attribute, their name attributes are not empty, and the value of a's name attribute
+        // equals the value of b's name attribute."
+        if !name.is_empty() {
+            let owner = form_owner(node);
+            let root = match &owner {
+                Some(form) => form.clone(),
+                None => tree_root(node),
+            };
+            for other in descendants(&root) {
+                if Rc::ptr_eq(&other, node) {
+                    continue;
+                }
+                let other_element = match other.borrow().get_element() {
+                    Some(e) => e,
+                    None => continue,
+                };
+                if other_element.kind() != ElementKind::Input
+                    || input_type(&other_element) != "radio"
+                    || other_element.get_attribute("name") != Some(name.clone())
+                {
+                    continue;
+                }
+                let same_owner = match (&owner, form_owner(&other)) {
+                    (Some(a), Some(b)) => Rc::ptr_eq(a, &b),
+                    (None, None) => true,
+                    _ => false,
+                };
+                if same_owner {
+                    if let Some(e) = other.borrow_mut().get_element_mut() {
+                        e.set_checkedness(false);
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(e) = node.borrow_mut().get_element_mut() {
+        e.set_checkedness(checked);
+    }
+}
+
+/// https://html.spec.whatwg.org/multipage/form-elements.html#concept-option-selectedness
+fn selectedness(option: &Rc<RefCell<Node>>) -> bool {
+    match option.borrow().get_element() {
+        Some(e) => match e.dirty_selectedness() {
+            Some(selected) => selected,
+            // "The selected content attribute is a boolean attribute. It represents the default
+            // selectedness of the element."
+            None => e.get_attribute("selected").is_some(),
+        },
+        None => false,
+    }
+}
+
+/// Returns the list of options of a select element.
+/// https://html.spec.whatwg.org/multipage/form-elements.html#concept-select-option-list
+pub fn options(select: &Rc<RefCell<Node>>) -> Vec<Rc<RefCell<Node>>> {
+    descendants(select)
+        .into_iter()
+        .filter(|n| n.borrow().element_kind() == Some(ElementKind::Option))
+        .collect()
+}
+
+/// Returns the selected option of a select element whose multiple attribute is absent.
+/// https://html.spec.whatwg.org/multipage/form-elements.html#selectedness-setting-algorithm
+pub fn selected_option(select: &Rc<RefCell<Node>>) -> Option<Rc<RefCell<Node>>> {
+    let options = options(select);
+    // "If two or more option elements in the select element's list of options have their
+    // selectedness set to true, set the selectedness of all but the last option element with
+    // its selectedness set to true, in tree order, to false."
+    if let Some(option) = options.iter().rev().find(|o| selectedness(o)) {
+        return Some(option.clone());
+    }
+    // "If nodes are inserted or nodes are removed causing the list of options to gain or lose
+    // one or more option elements, or if an option element in the list of options asks for a
+    // reset, then, if the select element's multiple attribute is absent, the user agent must
+    // set the selectedness of the first option element in the list of options in tree order
+    // that is not disabled, if any, to true."
+    options
+        .iter()
+        .find(|o| !o.borrow().get_element().is_some_and(|e| is_disabled(&e)))
+        .cloned()
+}
+
+/// Selects `option` in its select element and unselects the other options.
+pub fn select_option(select: &Rc<RefCell<Node>>, option: &Rc<RefCell<Node>>) {
+    for o in options(select) {
+        let selected = Rc::ptr_eq(&o, option);
+        if let Some(e) = o.borrow_mut().get_element_mut() {
+            e.set_selectedness(selected);
+        }
+    }
+}
+
+/// Selects the option after the selected option, or the first option if the last option is
+/// selected. This is how a user changes the selection with a keyboard.
+pub fn select_next_option(select: &Rc<RefCell<Node>>) {
+    let options = options(select);
+    if options.is_empty() {
+        return;
+    }
+    let next = match selected_option(select) {
+        Some(selected) => match options.iter().position(|o| Rc::ptr_eq(o, &selected)) {
+            Some(i) => (i + 1) % options.len(),
+            None => 0,
+        },
+        None => 0,
+    };
+    select_option(select, &options[next]);
+}
+
+/// Returns the form owner of a form-associated element, which is the nearest ancestor form
+/// element. The form element pointer of the parser and the `form` attribute are not supported.
+/// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#form-owner
+pub fn form_owner(node: &Rc<RefCell<Node>>) -> Option<Rc<RefCell<Node>>> {
+    let mut parent = node.borrow().parent().upgrade();
+    while let Some(p) = parent {
+        if p.borrow().element_kind() == Some(ElementKind::Form) {
+            return Some(p);
+        }
+        parent = p.borrow().parent().upgrade();
+    }
+    None
+}
+
+/// Returns the elements whose form owner is `form` in tree order.
+/// https://html.spec.whatwg.org/multipage/forms.html#dom-form-elements
+fn form_elements(form: &Rc<RefCell<Node>>) -> Vec<Rc<RefCell<Node>>> {
+    descendants(form)
+        .into_iter()
+        .filter(|n| {
+            n.borrow()
+                .get_element()
+                .is_some_and(|e| is_form_control(&e))
+                && form_owner(n).is_some_and(|owner| Rc::ptr_eq(&owner, form))
+        })
+        .collect()
+}
+
+/// Returns the name-value pairs submitted by `form`. `submitter` is the submit button that
+/// submits the form, if any.
+/// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#constructing-the-form-data-set
+pub fn construct_entry_list(
+    form: &Rc<RefCell<Node>>,
+    submitter: Option<&Rc<RefCell<Node>>>,
+) -> Vec<(String, String)> {
+    let mut entry_list = Vec::new();
+
+    // "5. For each element field in controls, in tree order:"
+    for field in form_elements(form) {
+        let element = match field.borrow().get_element() {
+            Some(e) => e,
+            None => continue,
+        };
+
+        // "5.1. If any of the following are true:
+        //   field is disabled;
+        //   field is a button but it is not submitter;
+        //   field is an input element whose type attribute is in the Checkbox state and whose
+        //   checkedness is false;
+        //   field is an input element whose type attribute is in the Radio Button state and
+        //   whose checkedness is false,
+        // then continue."
+        if is_disabled(&element) {
+            continue;
+        }
+        let is_button = element.kind() == ElementKind::Button
+            || (element.kind() == ElementKind::Input
+                && matches!(
+                    input_type(&element).as_str(),
+                    "submit" | "image" | "reset" | "button"
+                ));
+        if is_button && !submitter.is_some_and(|s| Rc::ptr_eq(s, &field)) {
+            continue;
+        }
+        let t = input_type(&element);
+        if element.kind() == ElementKind::Input
+            && (t == "checkbox" || t == "radio")
+            && !checkedness(&field)
+        {
+            continue;
+        }
+        // "5.2. If the field element is an input element whose type attribute is in the Image
+        // Button state, then: ... Continue." The coordinates are not supported. Files are not
+        // supported either.
+        if element.kind() == ElementKind::Input && (t == "image" || t == "file") {
+            continue;
+        }
+
+        // "5.4. If either the field element does not have a name attribute specified, or its
+        // name attribute's value is the empty string, skip to the next field."
+        let name = match element.get_attribute("name") {
+            Some(name) if !name.is_empty() => name,
+            _ => continue,
+        };
+
+        match element.kind() {
+            // "5.6. If the field element is a select element, then for each option element in
+            // the select element's list of options whose selectedness is true and that is not
+            // disabled, create an entry with name and the value of the option element, and
+            // append it to entry list."
+            ElementKind::Select => {
+                if let Some(option) = selected_option(&field) {
+                    if !option
+                        .borrow()
+                        .get_element()
+                        .is_some_and(|e| is_disabled(&e))
+                    {
+                        entry_list.push((name, value(&option)));
+                    }
+                }
+            }
+            // "5.11. Otherwise, create an entry with name and the value of the field element,
+            // and append it to entry list."
+            //
+            // "To convert to a list of name-value pairs an entry list entry list, run these
+            // steps: ... Replace every occurrence of U+000D (CR) not followed by U+000A (LF),
+            // and every occurrence of U+000A (LF) not preceded by U+000D (CR), in name and value,
+            // by a string consisting of U+000D (CR) and U+000A (LF)."
+            // https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#convert-to-a-list-of-name-value-pairs
+            _ => entry_list.push((
+                name,
+                value(&field)
+                    .replace("\r\n", "\n")
+                    .replace('\r', "\n")
+                    .replace('\n', "\r\n"),
+            )),
+        }
+    }
+
+    entry_list
+}
+
+/// Returns the value of an attribute of the submitter that overrides the form's one, e.g.
+/// `formaction` of a submit button overrides `action` of the form.
+/// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#form-submission-attributes
+fn submission_attribute(
+    form: &Rc<RefCell<Node>>,
+    submitter: Option<&Rc<RefCell<Node>>>,
+    name: &str,
+) -> Option<String> {
+    if let Some(s) = submitter {
+        if let Some(value) = s
+            .borrow()
+            .get_element()
+            .and_then(|e| e.get_attribute(&("form".to_string() + name)))
+        {
+            return Some(value);
+        }
+    }
+    form.borrow()
+        .get_element()
+        .and_then(|e| e.get_attribute(name))
+}
+
+/// Submits `form` and returns the request to navigate to. `base_url` is the document base URL
+/// of the document that the form is in. Returns None if the action URL is not supported.
+/// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#form-submission-algorithm
+pub fn submit(
+    form: &Rc<RefCell<Node>>,
+    submitter: Option<&Rc<RefCell<Node>>>,
+    base_url: &str,
+) -> Option<HttpRequest> {
+    // "6. Let entry list be the result of constructing the entry list with form, submitter,
+    // and encoding."
+    let entry_list = construct_entry_list(form, submitter);
+
+    // "11. Let method be the submitter element's method."
+    // "The method and formmethod content attributes are enumerated attributes ... The missing
+    // value default and the invalid value default for these attributes are the GET state."
+    let method = submission_attribute(form, submitter, "method")
+        .map(|m| m.to_ascii_lowercase())
+        .unwrap_or_default();
+
+    // "13. Let action be the submitter element's action."
+    // "14. If action is the empty string, let action be the URL of the form document."
+    // "15. Let parsed action be the result of encoding-parsing a URL given action, relative to
+    // the submitter's node document. If parsed action is failure, then return."
+    let action = submission_attribute(form, submitter, "action").unwrap_or_default();
+    let action = match Url::new(base_url.to_string()).parse() {
+        Ok(base) => base.resolve(&action),
+        Err(_) => action,
+    };
+    // "19. Let scheme be the scheme of parsed action."
+    // Only the HTTP scheme is supported.
+    let mut parsed_action = Url::new(action).parse().ok()?;
+
+    // "20. Let enctype be the submitter element's enctype."
+    // Only application/x-www-form-urlencoded is supported, which is also the missing value
+    // default and the invalid value default of the enctype attribute.
+
+    // "24. Switch on scheme: http, https
+    //   GET: Mutate action URL
+    //   POST: Submit as entity body"
+    if method == "post" {
+        // https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#submit-body
+        // "2. Switch on enctype: application/x-www-form-urlencoded
+        //   Let pairs be the result of converting to a list of name-value pairs with entry list.
+        //   Let body be the result of running the application/x-www-form-urlencoded serializer
+        //   with pairs and encoding.
+        //   Set body to the result of encoding body.
+        //   Let mimeType be `application/x-www-form-urlencoded`."
+        Some(HttpRequest::post(
+            parsed_action.href(),
+            "application/x-www-form-urlencoded".to_string(),
+            serialize_urlencoded(&entry_list),
+        ))
+    } else {
+        // https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#submit-mutate-action
+        // "1. Let pairs be the result of converting to a list of name-value pairs with entry
+        // list.
+        // 2. Let query be the result of running the application/x-www-form-urlencoded
+        // serializer with pairs and encoding.
+        // 3. Set parsed action's query component to query."
+        let mut params = UrlSearchParams::new("");
+        for (name, value) in entry_list {
+            params.append(name, value);
+        }
+        parsed_action.set_search_params(&params);
+        Some(HttpRequest::get(parsed_action.href()))
+    }
+}
+
+/// Submits the form owner of a text field when a user presses the enter key in it.
+/// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#implicit-submission
+pub fn implicit_submission(node: &Rc<RefCell<Node>>, base_url: &str) -> Option<HttpRequest> {
+    let form = form_owner(node)?;
+    let elements = form_elements(&form);
+
+    // "A form element's default button is the first submit button in tree order whose form
+    // owner is that form element."
+    let default_button = elements.iter().find(|n| {
+        n.borrow()
+            .get_element()
+            .is_some_and(|e| is_submit_button(&e))
+    });
+    if let Some(button) = default_button {
+        // "If the user agent supports letting the user submit a form implicitly (for example,
+        // on some platforms hitting the "enter" key while a text control is focused implicitly
+        // submits the form), then doing so for a form, whose default button has activation
+        // behavior and is not disabled, must cause the user agent to fire a click event at that
+        // default button."
+        if button
+            .borrow()
+            .get_element()
+            .is_some_and(|e| is_disabled(&e))
+        {
+            return None;
+        }
+        return submit(&form, Some(button), base_url);
+    }
+
+    // "If the form has no submit button, then the implicit submission mechanism must perform
+    // the following steps:
+    //   1. If the form has more than one field that blocks implicit submission, then return.
+    //   2. Submit the form element from the form element itself with userInvolvement set to
+    //   "activation"."
+    let blocking_fields = elements
+        .iter()
+        .filter(|n| {
+            n.borrow()
+                .get_element()
+                .is_some_and(|e| blocks_implicit_submission(&e))
+        })
+        .count();
+    if blocking_fields > 1 {
+        return None;
+    }
+    submit(&form, None, base_url)
+}
+
+/// Runs the activation behavior of a form control, e.g. when it's clicked or a user presses
+/// the enter key on it. Returns the request to navigate to if the form is submitted.
+/// https://html.spec.whatwg.org/multipage/input.html#the-input-element:activation-behaviour
+/// https://html.spec.whatwg.org/multipage/form-elements.html#the-button-element:activation-behaviour
+pub fn activate(node: &Rc<RefCell<Node>>, base_url: &str) -> Option<HttpRequest> {
+    let element = node.borrow().get_element()?;
+    if is_disabled(&element) {
+        return None;
+    }
+
+    if element.kind() == ElementKind::Input {
+        match input_type(&element).as_str() {
+            // "The legacy-pre-activation behavior is to set the element's checkedness to its
+            // opposite value (i.e. true if it is false, false if it is true)."
+            "checkbox" => {
+                set_checkedness(node, !checkedness(node));
+                return None;
+            }
+            // "The legacy-pre-activation behavior is to set the element's checkedness to true."
+            "radio" => {
+                set_checkedness(node, true);
+                return None;
+            }
+            _ => {}
+        }
+    }
+
+    // "If element is a submit button: 1. If element does not have a form owner, then return.
+    // 2. Submit element's form owner from element."
+    if is_submit_button(&element) {
+        let form = form_owner(node)?;
+        return submit(&form, Some(node), base_url);
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::dom::api::get_target_element_node;
+    use crate::renderer::test_util::create_document;
+    use alloc::vec;
+
+    fn element_by_name(document: &Rc<RefCell<Node>>, name: &str) -> Rc<RefCell<Node>> {
+        descendants(document)
+            .into_iter()
+            .find(|n| {
+                n.borrow()
+                    .get_element()
+                    .is_some_and(|e| e.get_attribute("name") == Some(name.to_string()))
+            })
+            .expect("failed to find an element")
+    }
+
+    #[test]
+    fn test_get_submission() {
+        let document = create_document(
+            "<form action=\"/search\"><input name=q value=\"saba browser\"><input type=hidden name=lang value=ja><input type=submit name=go value=Go></form>",
+        );
+        let form = get_target_element_node(Some(document.clone()), ElementKind::Form)
+            .expect("failed to find a form");
+
+        assert_eq!(
+            Some(HttpRequest::get(
+                "http://example.com/search?q=saba+browser&lang=ja".to_string()
+            )),
+            submit(&form, None, "http://example.com/index.html?x=1")
+        );
+
+        // The submit button is included only when it submits the form.
+        let go = element_by_name(&document, "go");
+        assert_eq!(
+            Some(HttpRequest::get(
+                "http://example.com/search?q=saba+browser&lang=ja&go=Go".to_string()
+            )),
+            activate(&go, "http://example.com/index.html")
+        );
+    }
+
+    #[test]
+    fn test_post_submission() {
+        let document = create_document(
+            "<form method=POST action=\"login\"><input name=user><input type=password name=pass><textarea name=memo>a\nb</textarea><button>Log in</button></form>",
+        );
+        let user = element_by_name(&document, "user");
+        let pass = element_by_name(&document, "pass");
+        set_value(&user, "saba".to_string());
+        set_value(&pass, "p&ss\nword".to_string());
+
+        assert_eq!(
+            Some(HttpRequest::post(
+                "http://example.com/admin/login".to_string(),
+                "application/x-www-form-urlencoded".to_string(),
+                "user=saba&pass=p%26ssword&memo=a%0D%0Ab".to_string(),
+            )),
+            implicit_submission(&user, "http://example.com/admin/index.html")
+        );
+    }
+
+    #[test]
+    fn test_checkbox_and_radio() {
+        let document = create_document(
+            "<form><input type=checkbox name=a checked><input type=checkbox name=b value=yes><input type=radio name=r value=1 checked><input type=radio name=r value=2></form>",
+        );
+        let form = get_target_element_node(Some(document.clone()), ElementKind::Form)
+            .expect("failed to find a form");
+        assert_eq!(
+            vec![
+                ("a".to_string(), "on".to_string()),
+                ("r".to_string(), "1".to_string())
+            ],
+            construct_entry_list(&form, None)
+        );
+
+        let a = element_by_name(&document, "a");
+        let b = element_by_name(&document, "b");
+        activate(&a, "");
+        activate(&b, "");
+        let second_radio = descendants(&form)
+            .into_iter()
+            .find(|n| {
+                n.borrow()
+                    .get_element()
+                    .is_some_and(|e| e.get_attribute("value") == Some("2".to_string()))
+            })
+            .expect("failed to find a radio button");
+        activate(&second_radio, "");
+        assert_eq!(
+            vec![
+                ("b".to_string(), "yes".to_string()),
+                ("r".to_string(), "2".to_string())
+            ],
+            construct_entry_list(&form, None)
+        );
+    }
+
+    #[test]
+    fn test_select() {
+        let document = create_document(
+            "<form><select name=s><option>one<option value=2 selected>two<option> three </select></form>",
+        );
+        let form = get_target_element_node(Some(document.clone()), ElementKind::Form)
+            .expect("failed to find a form");
+        let select = element_by_name(&document, "s");
+        assert_eq!(3, options(&select).len());
+        assert_eq!("2".to_string(), value(&select));
+
+        select_next_option(&select);
+        assert_eq!("three".to_string(), value(&select));
+        select_next_option(&select);
+        assert_eq!(
+            vec![("s".to_string(), "one".to_string())],
+            construct_entry_list(&form, None)
+        );
+    }
+
+    #[test]
+    fn test_implicit_submission_blocked() {
+        let document = create_document("<form><input name=a><input name=b></form><input name=c>");
+        let a = element_by_name(&document, "a");
+        let c = element_by_name(&document, "c");
+        // A form with two text fields and no submit button is not submitted implicitly.
+        assert_eq!(None, implicit_submission(&a, "http://example.com/"));
+        // A text field without a form owner submits nothing.
+        assert_eq!(None, implicit_submission(&c, "http://example.com/"));
+    }
+}
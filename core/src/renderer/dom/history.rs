@@ -0,0 +1,185 @@
+//! This module implements the History interface on the session history of the page which holds
+//! the window.
+//! https://html.spec.whatwg.org/multipage/nav-history-apis.html#the-history-interface
+
+use crate::renderer::dom::exception::DomException;
+use crate::renderer::dom::window::Navigation;
+use crate::renderer::dom::window::Window;
+use crate::renderer::session_history::HistoryHandling;
+use crate::url::Url;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::string::ToString;
+use core::cell::RefCell;
+
+/// https://html.spec.whatwg.org/multipage/nav-history-apis.html#dom-history-length
+pub fn length(window: &Rc<RefCell<Window>>) -> usize {
+    window.borrow().session_history().borrow().length()
+}
+
+/// https://html.spec.whatwg.org/multipage/nav-history-apis.html#dom-history-state
+pub fn state(window: &Rc<RefCell<Window>>) -> Option<String> {
+    window
+        .borrow()
+        .session_history()
+        .borrow()
+        .current_entry()
+        .and_then(|entry| entry.state())
+}
+
+/// https://html.spec.whatwg.org/multipage/nav-history-apis.html#dom-history-go
+pub fn go(window: &Rc<RefCell<Window>>, delta: i64) {
+    // "3. If delta is 0, then reload document's node navigable, and return."
+    if delta == 0 {
+        let url = window.borrow().url();
+        window.borrow_mut().navigate(
+            Navigation::new(url, String::new()).with_history_handling(HistoryHandling::Reload),
+        );
+        return;
+    }
+
+    // "4. Traverse the history by a delta given document's node navigable's traversable
+    // navigable, delta, and with sourceDocument set to document."
+    window.borrow_mut().traverse_history(delta);
+}
+
+/// https://html.spec.whatwg.org/multipage/nav-history-apis.html#dom-history-back
+pub fn back(window: &Rc<RefCell<Window>>) {
+    // "3. Traverse the history by a delta with −1, and document."
+    window.borrow_mut().traverse_history(-1);
+}
+
+/// https://html.spec.whatwg.org/multipage/nav-history-apis.html#dom-history-forward
+pub fn forward(window: &Rc<RefCell<Window>>) {
+    // "3. Traverse the history by a delta with +1, and document."
+    window.borrow_mut().traverse_history(1);
+}
+
+/// https://html.spec.whatwg.org/multipage/nav-history-apis.html#dom-history-pushstate
+pub fn push_state(
+    window: &Rc<RefCell<Window>>,
+    data: Option<String>,
+    url: Option<String>,
+) -> Result<(), DomException> {
+    // "1. Run the shared history push/replace state steps given this, data, url, and "push"."
+    shared_history_push_replace_state_steps(window, data, url, HistoryHandling::Push)
+}
+
+/// https://html.spec.whatwg.org/multipage/nav-history-apis.html#dom-history-replacestate
+pub fn replace_state(
+    window: &Rc<RefCell<Window>>,
+    data: Option<String>,
+    url: Option<String>,
+) -> Result<(), DomException> {
+    // "1. Run the shared history push/replace state steps given this, data, url, and
+    // "replace"."
+    shared_history_push_replace_state_steps(window, data, url, HistoryHandling::Replace)
+}
+
+/// https://html.spec.whatwg.org/multipage/nav-history-apis.html#shared-history-push/replace-state-steps
+fn shared_history_push_replace_state_steps(
+    window: &Rc<RefCell<Window>>,
+    data: Option<String>,
+    url: Option<String>,
+    history_handling: HistoryHandling,
+) -> Result<(), DomException> {
+    // "5. Let newURL be document's URL."
+    let document_url = window.borrow().url();
+    let mut new_url = document_url.clone();
+
+    // "6. If url is not null or the empty string, then:"
+    if let Some(url) = url.filter(|url| !url.is_empty()) {
+        // "6.1. Set newURL to the result of encoding-parsing a URL given url, relative to the
+        // relevant settings object of history.
+        // 6.2. If newURL is failure, then throw a "SecurityError" DOMException."
+        let base = parse(&window.borrow().base_url())?;
+        new_url = base.resolve(&url);
+
+        // "6.3. If document cannot have its URL rewritten to newURL, then throw a
+        // "SecurityError" DOMException."
+        if !can_have_url_rewritten(&document_url, &new_url)? {
+            return Err(DomException::SecurityError);
+        }
+    }
+
+    // "10. Run the URL and history update steps given document and newURL, with serializedData
+    // set to serializedData and historyHandling set to historyHandling."
+    // https://html.spec.whatwg.org/multipage/browsing-the-web.html#url-and-history-update-steps
+    // "3. Let historyEntry be a new session history entry, with URL newURL, serialized state
+    // serializedData, document state activeEntry's document state, ...
+    // 5. If historyHandling is "push", then ... increment document's history object's index.
+    // 6. If historyHandling is "replace", then ... set historyEntry's step to activeEntry's step.
+    // 7. Set document's URL to newURL."
+    window
+        .borrow()
+        .session_history()
+        .borrow_mut()
+        .add_same_document_entry(new_url.clone(), data, history_handling);
+    window.borrow_mut().set_url(new_url);
+    Ok(())
+}
+
+/// Parses `url`. A URL which can't be parsed is a security error for the History API.
+fn parse(url: &str) -> Result<Url, DomException> {
+    Url::new(url.to_string())
+        .parse()
+        .map_err(|_| DomException::SecurityError)
+}
+
+/// https://html.spec.whatwg.org/multipage/nav-history-apis.html#can-have-its-url-rewritten
+fn can_have_url_rewritten(document_url: &str, target_url: &str) -> Result<bool, DomException> {
+    // "2. If targetURL and documentURL differ in their scheme, username, password, host, or port
+    // components, then return false.
+    // 3. If targetURL's scheme is an HTTP(S) scheme, then return true."
+    let document_url = parse(document_url)?;
+    let target_url = parse(target_url)?;
+    Ok(document_url.host() == target_url.host() && document_url.port() == target_url.port())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::test_util::create_window;
+
+    #[test]
+    fn test_push_and_replace_state() {
+        let window = create_window("");
+        window
+            .borrow_mut()
+            .set_url("http://example.com/a/index.html".to_string());
+        window
+            .borrow()
+            .session_history()
+            .borrow_mut()
+            .add_document_entry(window.borrow().url(), HistoryHandling::Push);
+
+        push_state(&window, Some("1".to_string()), Some("page1".to_string())).unwrap();
+        assert_eq!("http://example.com/a/page1", window.borrow().url());
+        assert_eq!(Some("1".to_string()), state(&window));
+        assert_eq!(2, length(&window));
+
+        // A null or empty URL keeps the document URL.
+        replace_state(&window, Some("2".to_string()), None).unwrap();
+        assert_eq!("http://example.com/a/page1", window.borrow().url());
+        assert_eq!(Some("2".to_string()), state(&window));
+        assert_eq!(2, length(&window));
+
+        // The URL can't be rewritten to another origin.
+        assert_eq!(
+            Err(DomException::SecurityError),
+            push_state(&window, None, Some("http://example.org/".to_string()))
+        );
+        assert_eq!(2, length(&window));
+
+        back(&window);
+        assert_eq!(Some(-1), window.borrow_mut().take_traversal());
+        go(&window, 0);
+        assert_eq!(
+            Some(HistoryHandling::Reload),
+            window
+                .borrow_mut()
+                .take_navigation()
+                .map(|n| n.history_handling())
+        );
+    }
+}
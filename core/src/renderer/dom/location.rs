@@ -0,0 +1,230 @@
+//! This module implements the Location interface. The attributes are read from the URL of the
+//! document, and setting them navigates the window.
+//! https://html.spec.whatwg.org/multipage/nav-history-apis.html#the-location-interface
+
+use crate::renderer::dom::exception::DomException;
+use crate::renderer::dom::window::Navigation;
+use crate::renderer::dom::window::Window;
+use crate::renderer::session_history::HistoryHandling;
+use crate::url::Url;
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::string::ToString;
+use core::cell::RefCell;
+
+fn parse(url: &str) -> Result<Url, DomException> {
+    Url::new(url.to_string())
+        .parse()
+        .map_err(|_| DomException::SyntaxError)
+}
+
+/// https://html.spec.whatwg.org/multipage/nav-history-apis.html#dom-location-href
+pub fn href(url: &str) -> String {
+    // "2. Return this's url, serialized."
+    url.to_string()
+}
+
+/// https://html.spec.whatwg.org/multipage/nav-history-apis.html#dom-location-pathname
+pub fn pathname(url: &str) -> String {
+    // "2. Return the result of URL path serializing this Location object's url."
+    match parse(url) {
+        Ok(url) => "/".to_string() + &url.path(),
+        Err(_) => String::new(),
+    }
+}
+
+/// https://html.spec.whatwg.org/multipage/nav-history-apis.html#dom-location-search
+pub fn search(url: &str) -> String {
+    // "2. If this's url's query is either null or the empty string, return the empty string.
+    // 3. Return "?", followed by this's url's query."
+    match parse(url) {
+        Ok(url) => url.search(),
+        Err(_) => String::new(),
+    }
+}
+
+/// https://html.spec.whatwg.org/multipage/nav-history-apis.html#dom-location-hash
+pub fn hash(url: &str) -> String {
+    // "2. If this's url's fragment is either null or the empty string, return the empty string.
+    // 3. Return "#", followed by this's url's fragment."
+    match parse(url) {
+        Ok(url) => url.hash(),
+        Err(_) => String::new(),
+    }
+}
+
+/// https://html.spec.whatwg.org/multipage/nav-history-apis.html#location-object-navigate
+fn navigate(window: &Rc<RefCell<Window>>, url: String, history_handling: HistoryHandling) {
+    // "3. If location's relevant Document is not yet completely loaded, and the incumbent global
+    // object does not have transient activation, then set historyHandling to "replace"."
+    // This step is not supported, so scripts which run while the document is loaded push an
+    // entry.
+    // "4. Navigate navigable to url using sourceDocument, with exceptionsEnabled set to true and
+    // historyHandling set to historyHandling."
+    window
+        .borrow_mut()
+        .navigate(Navigation::new(url, String::new()).with_history_handling(history_handling));
+}
+
+/// Parses `url` relative to the document base URL.
+/// https://html.spec.whatwg.org/multipage/urls-and-fetching.html#encoding-parsing-a-url
+fn parse_relative(window: &Rc<RefCell<Window>>, url: &str) -> Result<String, DomException> {
+    let base = parse(&window.borrow().base_url())?;
+    let resolved = base.resolve(url);
+    parse(&resolved)?;
+    Ok(resolved)
+}
+
+/// https://html.spec.whatwg.org/multipage/nav-history-apis.html#dom-location-href
+pub fn set_href(window: &Rc<RefCell<Window>>, value: &str) -> Result<(), DomException> {
+    // "2. Let url be the result of encoding-parsing a URL given the given value, relative to the
+    // entry settings object.
+    // 3. If url is failure, then throw a "SyntaxError" DOMException.
+    // 4. Location-object navigate this to url."
+    let url = parse_relative(window, value)?;
+    navigate(window, url, HistoryHandling::Push);
+    Ok(())
+}
+
+/// https://html.spec.whatwg.org/multipage/nav-history-apis.html#dom-location-pathname
+pub fn set_pathname(window: &Rc<RefCell<Window>>, value: &str) -> Result<(), DomException> {
+    // "3. Let copyURL be a copy of this's url.
+    // 5. Set copyURL's path to the empty list.
+    // 6. Basic URL parse the given value, with copyURL as url and path start state as state
+    // override."
+    let copy_url = parse(&window.borrow().url())?;
+    let path = "/".to_string() + value.trim_start_matches('/') + &copy_url.search();
+    let new_url = copy_url.resolve(&(path + &copy_url.hash()));
+    // "7. Location-object navigate this to copyURL."
+    navigate(window, new_url, HistoryHandling::Push);
+    Ok(())
+}
+
+/// https://html.spec.whatwg.org/multipage/nav-history-apis.html#dom-location-search
+pub fn set_search(window: &Rc<RefCell<Window>>, value: &str) -> Result<(), DomException> {
+    // "3. Let copyURL be a copy of this's url.
+    // 4. If the given value is the empty string, set copyURL's query to null.
+    // 5. Otherwise, run these substeps:
+    //   1. Let input be the given value with a single leading "?" removed, if any.
+    //   3. Set copyURL's query to the empty string.
+    //   4. Basic URL parse input, with null, encoding, copyURL as url, and query state as state
+    //   override."
+    let copy_url = parse(&window.borrow().url())?;
+    let input = value.strip_prefix('?').unwrap_or(value);
+    let new_url = copy_url.resolve(&format!("?{}{}", input, copy_url.hash()));
+    // "6. Location-object navigate this to copyURL."
+    navigate(window, new_url, HistoryHandling::Push);
+    Ok(())
+}
+
+/// https://html.spec.whatwg.org/multipage/nav-history-apis.html#dom-location-hash
+pub fn set_hash(window: &Rc<RefCell<Window>>, value: &str) -> Result<(), DomException> {
+    // "3. Let copyURL be a copy of this's url.
+    // 4. Let thisURLFragment be copyURL's fragment if it is non-null; otherwise the empty
+    // string.
+    // 5. Let input be the given value with a single leading "#" removed, if any.
+    // 6. Set copyURL's fragment to the empty string.
+    // 7. Basic URL parse input, with copyURL as url and fragment state as state override."
+    let mut copy_url = parse(&window.borrow().url())?;
+    let this_url_fragment = copy_url.fragment();
+    let input = value.strip_prefix('#').unwrap_or(value);
+    copy_url.set_fragment(input.to_string());
+    // "8. If copyURL's fragment is thisURLFragment, then return."
+    if copy_url.fragment() == this_url_fragment {
+        return Ok(());
+    }
+    // "9. Location-object navigate this to copyURL."
+    navigate(window, copy_url.href(), HistoryHandling::Push);
+    Ok(())
+}
+
+/// https://html.spec.whatwg.org/multipage/nav-history-apis.html#dom-location-assign
+pub fn assign(window: &Rc<RefCell<Window>>, url: &str) -> Result<(), DomException> {
+    // "3. Let urlRecord be the result of encoding-parsing a URL given url, relative to the entry
+    // settings object.
+    // 4. If urlRecord is failure, then throw a "SyntaxError" DOMException.
+    // 5. Location-object navigate this to urlRecord."
+    let url = parse_relative(window, url)?;
+    navigate(window, url, HistoryHandling::Push);
+    Ok(())
+}
+
+/// https://html.spec.whatwg.org/multipage/nav-history-apis.html#dom-location-replace
+pub fn replace(window: &Rc<RefCell<Window>>, url: &str) -> Result<(), DomException> {
+    // "4. Location-object navigate this to urlRecord given "replace"."
+    let url = parse_relative(window, url)?;
+    navigate(window, url, HistoryHandling::Replace);
+    Ok(())
+}
+
+/// https://html.spec.whatwg.org/multipage/nav-history-apis.html#dom-location-reload
+pub fn reload(window: &Rc<RefCell<Window>>) {
+    // "3. Reload this's relevant global object's navigable."
+    let url = window.borrow().url();
+    navigate(window, url, HistoryHandling::Reload);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::test_util::create_window;
+
+    #[test]
+    fn test_location() {
+        let url = "http://example.com:8000/a/b.html?q=1#top";
+        assert_eq!(url, href(url));
+        assert_eq!("/a/b.html", pathname(url));
+        assert_eq!("?q=1", search(url));
+        assert_eq!("#top", hash(url));
+        assert_eq!("/", pathname("http://example.com"));
+        assert_eq!("", search("http://example.com/#"));
+        assert_eq!("", hash("http://example.com/#"));
+
+        let window = create_window("");
+        window.borrow_mut().set_url(url.to_string());
+        let navigation = |window: &Rc<RefCell<Window>>| {
+            let navigation = window.borrow_mut().take_navigation().expect("a navigation");
+            (navigation.url(), navigation.history_handling())
+        };
+
+        set_hash(&window, "bottom").unwrap();
+        assert_eq!(
+            (
+                "http://example.com:8000/a/b.html?q=1#bottom".to_string(),
+                HistoryHandling::Push
+            ),
+            navigation(&window)
+        );
+        // Setting the same fragment doesn't navigate.
+        set_hash(&window, "#top").unwrap();
+        assert_eq!(None, window.borrow_mut().take_navigation());
+
+        set_search(&window, "?q=2").unwrap();
+        assert_eq!(
+            "http://example.com:8000/a/b.html?q=2#top",
+            navigation(&window).0
+        );
+        set_pathname(&window, "c").unwrap();
+        assert_eq!("http://example.com:8000/c?q=1#top", navigation(&window).0);
+        set_href(&window, "../d.html").unwrap();
+        assert_eq!("http://example.com:8000/d.html", navigation(&window).0);
+        replace(&window, "e.html").unwrap();
+        assert_eq!(
+            (
+                "http://example.com:8000/a/e.html".to_string(),
+                HistoryHandling::Replace
+            ),
+            navigation(&window)
+        );
+        reload(&window);
+        assert_eq!(
+            (url.to_string(), HistoryHandling::Reload),
+            navigation(&window)
+        );
+        assert_eq!(
+            Err(DomException::SyntaxError),
+            assign(&window, "ftp://example.com")
+        );
+    }
+}
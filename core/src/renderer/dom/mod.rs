@@ -3,6 +3,14 @@
 
 pub mod activation_behavior;
 pub mod api;
+pub mod collection;
 pub mod event;
+pub mod exception;
+pub mod focus;
+pub mod form;
+pub mod history;
+pub mod location;
+pub mod mutation;
 pub mod node;
+pub mod refresh;
 pub mod window;
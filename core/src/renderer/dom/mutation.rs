@@ -0,0 +1,667 @@
+//! This module implements the mutation algorithms of a node tree, which keep the parent, child
+//! and sibling pointers of all the affected nodes consistent.
+//! https://dom.spec.whatwg.org/#mutation-algorithms
+
+use crate::renderer::dom::exception::DomException;
+use crate::renderer::dom::node::Node;
+use crate::renderer::dom::node::NodeKind;
+use alloc::rc::{Rc, Weak};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+fn is_document(node: &Rc<RefCell<Node>>) -> bool {
+    matches!(node.borrow().kind(), NodeKind::Document)
+}
+
+fn is_doctype(node: &Rc<RefCell<Node>>) -> bool {
+    matches!(node.borrow().kind(), NodeKind::DocumentType(_))
+}
+
+fn is_element(node: &Rc<RefCell<Node>>) -> bool {
+    matches!(node.borrow().kind(), NodeKind::Element(_))
+}
+
+fn is_text(node: &Rc<RefCell<Node>>) -> bool {
+    matches!(node.borrow().kind(), NodeKind::Text(_))
+}
+
+/// Returns the children of `parent` in tree order.
+pub fn children(parent: &Rc<RefCell<Node>>) -> Vec<Rc<RefCell<Node>>> {
+    let mut children = Vec::new();
+    let mut child = parent.borrow().first_child();
+    while let Some(c) = child {
+        child = c.borrow().next_sibling();
+        children.push(c);
+    }
+    children
+}
+
+/// Returns the descendants of `node` in tree order.
+/// https://dom.spec.whatwg.org/#concept-tree-order
+pub fn descendants(node: &Rc<RefCell<Node>>) -> Vec<Rc<RefCell<Node>>> {
+    let mut result = Vec::new();
+    for child in children(node) {
+        result.push(child.clone());
+        result.extend(descendants(&child));
+    }
+    result
+}
+
+/// Returns the root of the tree that `node` is in.
+/// https://dom.spec.whatwg.org/#concept-tree-root
+pub fn tree_root(node: &Rc<RefCell<Node>>) -> Rc<RefCell<Node>> {
+    let mut root = node.clone();
+    loop {
+        let parent = root.borrow().parent().upgrade();
+        match parent {
+            Some(p) => root = p,
+            None => return root,
+        }
+    }
+}
+
+/// https://dom.spec.whatwg.org/#concept-tree-inclusive-ancestor
+fn is_inclusive_ancestor(node: &Rc<RefCell<Node>>, of: &Rc<RefCell<Node>>) -> bool {
+    let mut current = Some(of.clone());
+    while let Some(c) = current {
+        if Rc::ptr_eq(&c, node) {
+            return true;
+        }
+        current = c.borrow().parent().upgrade();
+    }
+    false
+}
+
+fn is_child_of(child: &Rc<RefCell<Node>>, parent: &Rc<RefCell<Node>>) -> bool {
+    match child.borrow().parent().upgrade() {
+        Some(p) => Rc::ptr_eq(&p, parent),
+        None => false,
+    }
+}
+
+/// Returns true if a child of `parent` other than `except` satisfies `predicate`.
+fn has_child(
+    parent: &Rc<RefCell<Node>>,
+    except: Option<&Rc<RefCell<Node>>>,
+    predicate: fn(&Rc<RefCell<Node>>) -> bool,
+) -> bool {
+    children(parent)
+        .iter()
+        .any(|c| predicate(c) && !except.is_some_and(|e| Rc::ptr_eq(c, e)))
+}
+
+/// Returns true if a sibling preceding `child` satisfies `predicate`.
+fn has_preceding_sibling(
+    child: &Rc<RefCell<Node>>,
+    predicate: fn(&Rc<RefCell<Node>>) -> bool,
+) -> bool {
+    let mut sibling = child.borrow().previous_sibling().upgrade();
+    while let Some(s) = sibling {
+        if predicate(&s) {
+            return true;
+        }
+        sibling = s.borrow().previous_sibling().upgrade();
+    }
+    false
+}
+
+/// Returns true if a sibling following `child` satisfies `predicate`.
+fn has_following_sibling(
+    child: &Rc<RefCell<Node>>,
+    predicate: fn(&Rc<RefCell<Node>>) -> bool,
+) -> bool {
+    let mut sibling = child.borrow().next_sibling();
+    while let Some(s) = sibling {
+        if predicate(&s) {
+            return true;
+        }
+        sibling = s.borrow().next_sibling();
+    }
+    false
+}
+
+/// Runs the steps 1-5 of both ensuring pre-insertion validity and replacing a child. `child` is
+/// the reference child for the former and the replaced child for the latter.
+fn ensure_hierarchy(
+    node: &Rc<RefCell<Node>>,
+    parent: &Rc<RefCell<Node>>,
+    child: Option<&Rc<RefCell<Node>>>,
+) -> Result<(), DomException> {
+    // "1. If parent is not a Document, DocumentFragment, or Element node, then throw a
+    // "HierarchyRequestError" DOMException."
+    if !is_document(parent) && !is_element(parent) {
+        return Err(DomException::HierarchyRequestError);
+    }
+
+    // "2. If node is a host-including inclusive ancestor of parent, then throw a
+    // "HierarchyRequestError" DOMException."
+    if is_inclusive_ancestor(node, parent) {
+        return Err(DomException::HierarchyRequestError);
+    }
+
+    // "3. If child is non-null and its parent is not parent, then throw a "NotFoundError"
+    // DOMException."
+    if let Some(child) = child {
+        if !is_child_of(child, parent) {
+            return Err(DomException::NotFoundError);
+        }
+    }
+
+    // "4. If node is not a DocumentFragment, DocumentType, Element, or CharacterData node, then
+    // throw a "HierarchyRequestError" DOMException."
+    if is_document(node) {
+        return Err(DomException::HierarchyRequestError);
+    }
+
+    // "5. If either node is a Text node and parent is a document, or node is a doctype and parent
+    // is not a document, then throw a "HierarchyRequestError" DOMException."
+    if (is_text(node) && is_document(parent)) || (is_doctype(node) && !is_document(parent)) {
+        return Err(DomException::HierarchyRequestError);
+    }
+
+    Ok(())
+}
+
+/// https://dom.spec.whatwg.org/#concept-node-ensure-pre-insertion-validity
+fn ensure_pre_insertion_validity(
+    node: &Rc<RefCell<Node>>,
+    parent: &Rc<RefCell<Node>>,
+    child: Option<&Rc<RefCell<Node>>>,
+) -> Result<(), DomException> {
+    ensure_hierarchy(node, parent, child)?;
+
+    // "6. If parent is a document, and any of the statements below, switched on the interface
+    // node implements, are true, then throw a "HierarchyRequestError" DOMException."
+    if !is_document(parent) {
+        return Ok(());
+    }
+    let invalid = if is_element(node) {
+        // "parent has an element child, child is a doctype, or child is non-null and a doctype
+        // is following child."
+        has_child(parent, None, is_element)
+            || child.is_some_and(|c| is_doctype(c) || has_following_sibling(c, is_doctype))
+    } else if is_doctype(node) {
+        // "parent has a doctype child, child is non-null and an element is preceding child, or
+        // child is null and parent has an element child."
+        has_child(parent, None, is_doctype)
+            || match child {
+                Some(c) => has_preceding_sibling(c, is_element),
+                None => has_child(parent, None, is_element),
+            }
+    } else {
+        false
+    };
+    if invalid {
+        return Err(DomException::HierarchyRequestError);
+    }
+
+    Ok(())
+}
+
+/// Inserts `node` into `parent` before `child`, or at the end of the children of `parent` if
+/// `child` is None. `node` is removed from its old parent first. This doesn't check the
+/// hierarchy rules.
+/// https://dom.spec.whatwg.org/#concept-node-insert
+pub fn insert(
+    node: &Rc<RefCell<Node>>,
+    parent: &Rc<RefCell<Node>>,
+    child: Option<&Rc<RefCell<Node>>>,
+) {
+    // https://dom.spec.whatwg.org/#concept-node-adopt
+    // "2. If node's parent is non-null, then remove node."
+    remove(node);
+
+    let previous = match child {
+        Some(c) => c.borrow().previous_sibling().upgrade(),
+        None => parent.borrow().last_child().upgrade(),
+    };
+
+    match previous {
+        Some(ref p) => {
+            p.borrow_mut().set_next_sibling(Some(node.clone()));
+            node.borrow_mut().set_previous_sibling(Rc::downgrade(p));
+        }
+        None => parent.borrow_mut().set_first_child(Some(node.clone())),
+    }
+    match child {
+        Some(c) => {
+            node.borrow_mut().set_next_sibling(Some(c.clone()));
+            c.borrow_mut().set_previous_sibling(Rc::downgrade(node));
+        }
+        None => parent.borrow_mut().set_last_child(Rc::downgrade(node)),
+    }
+    node.borrow_mut().set_parent(Rc::downgrade(parent));
+}
+
+/// Removes `node` from its parent. Nothing happens if `node` doesn't have a parent.
+/// https://dom.spec.whatwg.org/#concept-node-remove
+pub fn remove(node: &Rc<RefCell<Node>>) {
+    let parent = match node.borrow().parent().upgrade() {
+        Some(p) => p,
+        None => return,
+    };
+    let previous = node.borrow().previous_sibling().upgrade();
+    let next = node.borrow().next_sibling();
+
+    match previous {
+        Some(ref p) => p.borrow_mut().set_next_sibling(next.clone()),
+        None => parent.borrow_mut().set_first_child(next.clone()),
+    }
+    let previous = match previous {
+        Some(ref p) => Rc::downgrade(p),
+        None => Weak::new(),
+    };
+    match next {
+        Some(ref n) => n.borrow_mut().set_previous_sibling(previous),
+        None => parent.borrow_mut().set_last_child(previous),
+    }
+
+    let mut n = node.borrow_mut();
+    n.set_parent(Weak::new());
+    n.set_previous_sibling(Weak::new());
+    n.set_next_sibling(None);
+}
+
+/// https://dom.spec.whatwg.org/#concept-node-pre-insert
+pub fn insert_before(
+    parent: &Rc<RefCell<Node>>,
+    node: &Rc<RefCell<Node>>,
+    child: Option<&Rc<RefCell<Node>>>,
+) -> Result<Rc<RefCell<Node>>, DomException> {
+    // "1. Ensure pre-insertion validity of node into parent before child."
+    ensure_pre_insertion_validity(node, parent, child)?;
+
+    // "2. Let referenceChild be child.
+    // 3. If referenceChild is node, then set referenceChild to node's next sibling."
+    let reference_child = match child {
+        Some(c) if Rc::ptr_eq(c, node) => node.borrow().next_sibling(),
+        _ => child.cloned(),
+    };
+
+    // "4. Insert node into parent before referenceChild.
+    // 5. Return node."
+    insert(node, parent, reference_child.as_ref());
+    Ok(node.clone())
+}
+
+/// https://dom.spec.whatwg.org/#concept-node-append
+pub fn append_child(
+    parent: &Rc<RefCell<Node>>,
+    node: &Rc<RefCell<Node>>,
+) -> Result<Rc<RefCell<Node>>, DomException> {
+    // "To append a node to a parent, pre-insert node into parent before null."
+    insert_before(parent, node, None)
+}
+
+/// https://dom.spec.whatwg.org/#concept-node-pre-remove
+pub fn remove_child(
+    parent: &Rc<RefCell<Node>>,
+    child: &Rc<RefCell<Node>>,
+) -> Result<Rc<RefCell<Node>>, DomException> {
+    // "1. If child's parent is not parent, then throw a "NotFoundError" DOMException.
+    // 2. Remove child.
+    // 3. Return child."
+    if !is_child_of(child, parent) {
+        return Err(DomException::NotFoundError);
+    }
+    remove(child);
+    Ok(child.clone())
+}
+
+/// Replaces `child` with `node` within `parent`.
+/// https://dom.spec.whatwg.org/#concept-node-replace
+pub fn replace_child(
+    parent: &Rc<RefCell<Node>>,
+    node: &Rc<RefCell<Node>>,
+    child: &Rc<RefCell<Node>>,
+) -> Result<Rc<RefCell<Node>>, DomException> {
+    ensure_hierarchy(node, parent, Some(child))?;
+
+    // "6. If parent is a document, and any of the statements below, switched on the interface
+    // node implements, are true, then throw a "HierarchyRequestError" DOMException."
+    if is_document(parent) {
+        let invalid = if is_element(node) {
+            // "parent has an element child that is not child or a doctype is following child."
+            has_child(parent, Some(child), is_element) || has_following_sibling(child, is_doctype)
+        } else if is_doctype(node) {
+            // "parent has a doctype child that is not child, or an element is preceding child."
+            has_child(parent, Some(child), is_doctype) || has_preceding_sibling(child, is_element)
+        } else {
+            false
+        };
+        if invalid {
+            return Err(DomException::HierarchyRequestError);
+        }
+    }
+
+    // "7. Let referenceChild be child's next sibling.
+    // 8. If referenceChild is node, then set referenceChild to node's next sibling."
+    let mut reference_child = child.borrow().next_sibling();
+    if reference_child
+        .as_ref()
+        .is_some_and(|r| Rc::ptr_eq(r, node))
+    {
+        reference_child = node.borrow().next_sibling();
+    }
+
+    // "11. If child's parent is non-null, then: ... Remove child with the suppress observers
+    // flag set.
+    // 13. Insert node into parent before referenceChild with the suppress observers flag set.
+    // 15. Return child."
+    if !Rc::ptr_eq(child, node) {
+        remove(child);
+    }
+    insert(node, parent, reference_child.as_ref());
+    Ok(child.clone())
+}
+
+/// Replaces all the children of `parent` with `nodes`.
+/// https://dom.spec.whatwg.org/#concept-node-replace-all
+pub fn replace_all(parent: &Rc<RefCell<Node>>, nodes: Vec<Rc<RefCell<Node>>>) {
+    // "3. Remove all parent's children, in tree order, with the suppress observers flag set."
+    for child in children(parent) {
+        remove(&child);
+    }
+
+    // "4. If node is non-null, then insert node into parent before null with the suppress
+    // observers flag set."
+    for node in nodes {
+        insert(&node, parent, None);
+    }
+}
+
+/// Returns a copy of `node` without a parent. The children of `node` are copied too if `deep`
+/// is true.
+/// https://dom.spec.whatwg.org/#concept-node-clone
+pub fn clone_node(node: &Rc<RefCell<Node>>, deep: bool) -> Rc<RefCell<Node>> {
+    // "2. If node is an element, then: ... Let copy be the result of creating an element ...
+    // For each attribute of node's attribute list: ... Append copyAttribute to copy.
+    // 3. Otherwise, let copy be a node that implements the same interfaces as node, and fulfills
+    // these additional requirements, switching on the interface node implements: ..."
+    let copy = {
+        let n = node.borrow();
+        let mut copy = Node::new(n.kind());
+        copy.set_document_mode(n.document_mode());
+        Rc::new(RefCell::new(copy))
+    };
+
+    // "6. If the clone children flag is set, then for each child child of node, in tree order:
+    // append the result of cloning child with document and the clone children flag set, to
+    // copy."
+    if deep {
+        for child in children(node) {
+            insert(&clone_node(&child, true), &copy, None);
+        }
+    }
+
+    copy
+}
+
+/// Returns the concatenation of the data of all the Text node descendants of `node`, in tree
+/// order.
+/// https://dom.spec.whatwg.org/#concept-descendant-text-content
+pub fn descendant_text_content(node: &Rc<RefCell<Node>>) -> String {
+    descendants(node)
+        .iter()
+        .filter_map(|n| match n.borrow().kind() {
+            NodeKind::Text(s) => Some(s),
+            _ => None,
+        })
+        .collect()
+}
+
+/// https://dom.spec.whatwg.org/#dom-node-textcontent
+pub fn text_content(node: &Rc<RefCell<Node>>) -> Option<String> {
+    // "The textContent getter steps are to return the following, switching on the interface
+    // this implements:
+    //   DocumentFragment, Element: The descendant text content of this.
+    //   CharacterData: This's data.
+    //   Otherwise: Null."
+    match node.borrow().kind() {
+        NodeKind::Element(_) => Some(descendant_text_content(node)),
+        NodeKind::Text(s) | NodeKind::Comment(s) => Some(s),
+        NodeKind::Document | NodeKind::DocumentType(_) => None,
+    }
+}
+
+/// https://dom.spec.whatwg.org/#dom-node-textcontent
+pub fn set_text_content(node: &Rc<RefCell<Node>>, value: String) {
+    // "The textContent setter steps are to, if the given value is null, act as if it was the
+    // empty string instead, and then do as described below, switching on the interface this
+    // implements:"
+    let kind = node.borrow().kind();
+    match kind {
+        // "DocumentFragment, Element: String replace all with the given value within this."
+        // https://dom.spec.whatwg.org/#string-replace-all
+        NodeKind::Element(_) => {
+            let nodes = if value.is_empty() {
+                Vec::new()
+            } else {
+                Vec::from([Rc::new(RefCell::new(Node::new(NodeKind::Text(value))))])
+            };
+            replace_all(node, nodes);
+        }
+        // "CharacterData: Replace data with node this, offset 0, count this's length, and data
+        // the given value."
+        NodeKind::Text(_) => node.borrow_mut().kind = NodeKind::Text(value),
+        NodeKind::Comment(_) => node.borrow_mut().kind = NodeKind::Comment(value),
+        // "Otherwise: Do nothing."
+        NodeKind::Document | NodeKind::DocumentType(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::dom::node::DocumentType;
+    use crate::renderer::dom::node::Element;
+    use crate::renderer::html::html_builder::dom_to_html;
+    use alloc::string::ToString;
+
+    fn element(name: &str) -> Rc<RefCell<Node>> {
+        Rc::new(RefCell::new(Node::new(NodeKind::Element(Element::new(
+            name,
+            Vec::new(),
+        )))))
+    }
+
+    fn text(data: &str) -> Rc<RefCell<Node>> {
+        Rc::new(RefCell::new(Node::new(NodeKind::Text(data.to_string()))))
+    }
+
+    /// Asserts that the pointers of `parent` and its children are consistent with each other.
+    fn assert_links(parent: &Rc<RefCell<Node>>) {
+        let children = children(parent);
+        match children.last() {
+            Some(last) => assert!(Rc::ptr_eq(
+                last,
+                &parent.borrow().last_child().upgrade().unwrap()
+            )),
+            None => assert!(parent.borrow().last_child().upgrade().is_none()),
+        }
+        for (i, child) in children.iter().enumerate() {
+            assert!(is_child_of(child, parent));
+            match child.borrow().previous_sibling().upgrade() {
+                Some(p) => assert!(Rc::ptr_eq(&p, &children[i - 1])),
+                None => assert_eq!(0, i),
+            }
+        }
+    }
+
+    #[test]
+    fn test_append_insert_remove() {
+        let div = element("div");
+        let a = element("a");
+        let b = text("b");
+        let c = element("p");
+
+        append_child(&div, &a).unwrap();
+        append_child(&div, &c).unwrap();
+        insert_before(&div, &b, Some(&c)).unwrap();
+        assert_eq!(
+            "<div><a></a>b<p></p></div>".to_string(),
+            dom_to_html(&Some(div.clone()))
+        );
+        assert_links(&div);
+
+        // Appending a node which is already a child moves it.
+        append_child(&div, &a).unwrap();
+        assert_eq!(
+            "<div>b<p></p><a></a></div>".to_string(),
+            dom_to_html(&Some(div.clone()))
+        );
+        assert_links(&div);
+
+        // Inserting a node before itself keeps the order.
+        insert_before(&div, &c, Some(&c)).unwrap();
+        assert_links(&div);
+
+        assert!(Rc::ptr_eq(&a, &remove_child(&div, &a).unwrap()));
+        assert!(a.borrow().parent().upgrade().is_none());
+        assert_eq!(Err(DomException::NotFoundError), remove_child(&div, &a));
+        assert_eq!(
+            "<div>b<p></p></div>".to_string(),
+            dom_to_html(&Some(div.clone()))
+        );
+        assert_links(&div);
+
+        // Moving a node to another parent removes it from the old parent.
+        append_child(&a, &c).unwrap();
+        assert_links(&div);
+        assert_links(&a);
+        assert_eq!("<div>b</div>".to_string(), dom_to_html(&Some(div.clone())));
+    }
+
+    #[test]
+    fn test_replace_child() {
+        let div = element("div");
+        let a = element("a");
+        let b = text("b");
+        let c = element("p");
+        append_child(&div, &a).unwrap();
+        append_child(&div, &b).unwrap();
+
+        assert!(Rc::ptr_eq(&a, &replace_child(&div, &c, &a).unwrap()));
+        assert_eq!(
+            "<div><p></p>b</div>".to_string(),
+            dom_to_html(&Some(div.clone()))
+        );
+        assert_links(&div);
+
+        // Replacing a child with its previous sibling.
+        replace_child(&div, &c, &b).unwrap();
+        assert_eq!(
+            "<div><p></p></div>".to_string(),
+            dom_to_html(&Some(div.clone()))
+        );
+        assert_links(&div);
+
+        assert_eq!(
+            Err(DomException::NotFoundError),
+            replace_child(&div, &c, &b)
+        );
+    }
+
+    #[test]
+    fn test_hierarchy_request_error() {
+        let document = Rc::new(RefCell::new(Node::new(NodeKind::Document)));
+        let html = element("html");
+        let body = element("body");
+        append_child(&document, &html).unwrap();
+        append_child(&html, &body).unwrap();
+
+        // A node can't be inserted into itself or its descendant.
+        assert_eq!(
+            Err(DomException::HierarchyRequestError),
+            append_child(&body, &html)
+        );
+        assert_eq!(
+            Err(DomException::HierarchyRequestError),
+            append_child(&body, &body)
+        );
+        // A text node can't have a child.
+        assert_eq!(
+            Err(DomException::HierarchyRequestError),
+            append_child(&text("a"), &element("b"))
+        );
+        // A document has at most one element and no text.
+        assert_eq!(
+            Err(DomException::HierarchyRequestError),
+            append_child(&document, &element("p"))
+        );
+        assert_eq!(
+            Err(DomException::HierarchyRequestError),
+            append_child(&document, &text("a"))
+        );
+        // A doctype must precede the document element.
+        let doctype = Rc::new(RefCell::new(Node::new(NodeKind::DocumentType(
+            DocumentType::new("html".to_string(), String::new(), String::new()),
+        ))));
+        assert_eq!(
+            Err(DomException::HierarchyRequestError),
+            append_child(&document, &doctype)
+        );
+        assert_eq!(
+            Err(DomException::HierarchyRequestError),
+            append_child(&body, &doctype)
+        );
+        insert_before(&document, &doctype, Some(&html)).unwrap();
+        assert_links(&document);
+        // The document element can be replaced with another element.
+        replace_child(&document, &element("html"), &html).unwrap();
+        assert_links(&document);
+        assert_eq!(
+            Err(DomException::HierarchyRequestError),
+            replace_child(&document, &element("p"), &doctype)
+        );
+    }
+
+    #[test]
+    fn test_clone_node() {
+        let div = element("div");
+        let p = element("p");
+        append_child(&div, &p).unwrap();
+        append_child(&p, &text("a")).unwrap();
+        append_child(&div, &text("b")).unwrap();
+
+        let shallow = clone_node(&div, false);
+        assert_eq!("<div></div>".to_string(), dom_to_html(&Some(shallow)));
+
+        let deep = clone_node(&div, true);
+        assert_eq!(
+            "<div><p>a</p>b</div>".to_string(),
+            dom_to_html(&Some(deep.clone()))
+        );
+        assert_links(&deep);
+        // The copy shares no nodes with the original.
+        assert!(!Rc::ptr_eq(&deep.borrow().first_child().unwrap(), &p));
+    }
+
+    #[test]
+    fn test_text_content() {
+        let div = element("div");
+        let p = element("p");
+        append_child(&div, &text("a")).unwrap();
+        append_child(&div, &p).unwrap();
+        append_child(&p, &text("b")).unwrap();
+        assert_eq!(Some("ab".to_string()), text_content(&div));
+        assert_eq!(
+            None,
+            text_content(&Rc::new(RefCell::new(Node::new(NodeKind::Document))))
+        );
+
+        set_text_content(&div, "c".to_string());
+        assert_eq!("<div>c</div>".to_string(), dom_to_html(&Some(div.clone())));
+        assert_links(&div);
+        assert!(p.borrow().parent().upgrade().is_none());
+
+        let c = div.borrow().first_child().unwrap();
+        set_text_content(&c, "d".to_string());
+        assert_eq!(Some("d".to_string()), text_content(&div));
+
+        set_text_content(&div, String::new());
+        assert!(div.borrow().first_child().is_none());
+        assert_links(&div);
+    }
+}
@@ -5,12 +5,12 @@
 
 use crate::renderer::dom::activation_behavior::get_activation_behavior;
 use crate::renderer::dom::activation_behavior::ActivationBehavior;
-use crate::renderer::dom::event::Event;
 use crate::renderer::dom::event::EventListener;
 use crate::renderer::dom::event::EventListenerCallback;
 use crate::renderer::dom::event::EventTarget;
 use crate::renderer::dom::window::Window;
 use crate::renderer::html::attribute::Attribute;
+use crate::renderer::source::SourceRange;
 use alloc::format;
 use alloc::rc::{Rc, Weak};
 use alloc::string::String;
@@ -34,6 +34,16 @@ pub struct Node {
     events: Vec<EventListener>,
     /// https://dom.spec.whatwg.org/#eventtarget-activation-behavior
     activation_behavior: Option<ActivationBehavior>,
+    /// https://dom.spec.whatwg.org/#concept-document-mode
+    /// This is meaningful only when `kind` is NodeKind::Document.
+    document_mode: DocumentMode,
+    /// https://html.spec.whatwg.org/multipage/interaction.html#focused-area-of-the-document
+    /// This is meaningful only when `kind` is NodeKind::Document.
+    focused_area: Weak<RefCell<Node>>,
+    /// The range of the markup which produced this node, from the start of its start tag to the
+    /// end of its end tag. None if the node is not created by the HTML parser from a token, e.g.
+    /// an implied element.
+    source_range: Option<SourceRange>,
 }
 
 impl PartialEq for Node {
@@ -55,6 +65,9 @@ impl Node {
             next_sibling: None,
             events: Vec::new(),
             activation_behavior: get_activation_behavior(&kind),
+            document_mode: DocumentMode::NoQuirks,
+            focused_area: Weak::new(),
+            source_range: None,
         }
     }
 
@@ -62,10 +75,21 @@ impl Node {
         self.kind.clone()
     }
 
+    /// https://dom.spec.whatwg.org/#eventtarget-activation-behavior
+    pub fn activation_behavior(&self) -> Option<ActivationBehavior> {
+        self.activation_behavior
+    }
+
     pub fn set_window(&mut self, window: Weak<RefCell<Window>>) {
         self.window = window;
     }
 
+    /// Returns the window of the document. This is meaningful only when `kind` is
+    /// NodeKind::Document.
+    pub fn window(&self) -> Weak<RefCell<Window>> {
+        self.window.clone()
+    }
+
     pub fn get_window(&self) -> Weak<RefCell<Window>> {
         if self.window.upgrade().is_some() {
             return self.window.clone();
@@ -93,18 +117,62 @@ impl Node {
 
     pub fn get_element(&self) -> Option<Element> {
         match self.kind {
-            NodeKind::Document | NodeKind::Text(_) => None,
+            NodeKind::Document
+            | NodeKind::DocumentType(_)
+            | NodeKind::Comment(_)
+            | NodeKind::Text(_) => None,
             NodeKind::Element(ref e) => Some(e.clone()),
         }
     }
 
+    pub fn get_element_mut(&mut self) -> Option<&mut Element> {
+        match self.kind {
+            NodeKind::Document
+            | NodeKind::DocumentType(_)
+            | NodeKind::Comment(_)
+            | NodeKind::Text(_) => None,
+            NodeKind::Element(ref mut e) => Some(e),
+        }
+    }
+
     pub fn element_kind(&self) -> Option<ElementKind> {
         match self.kind {
-            NodeKind::Document | NodeKind::Text(_) => None,
+            NodeKind::Document
+            | NodeKind::DocumentType(_)
+            | NodeKind::Comment(_)
+            | NodeKind::Text(_) => None,
             NodeKind::Element(ref e) => Some(e.kind()),
         }
     }
 
+    /// https://dom.spec.whatwg.org/#concept-document-mode
+    pub fn set_document_mode(&mut self, mode: DocumentMode) {
+        self.document_mode = mode;
+    }
+
+    /// https://dom.spec.whatwg.org/#concept-document-mode
+    pub fn document_mode(&self) -> DocumentMode {
+        self.document_mode
+    }
+
+    /// https://html.spec.whatwg.org/multipage/interaction.html#focused-area-of-the-document
+    pub fn set_focused_area(&mut self, focused_area: Weak<RefCell<Node>>) {
+        self.focused_area = focused_area;
+    }
+
+    /// https://html.spec.whatwg.org/multipage/interaction.html#focused-area-of-the-document
+    pub fn focused_area(&self) -> Weak<RefCell<Node>> {
+        self.focused_area.clone()
+    }
+
+    pub fn set_source_range(&mut self, source_range: Option<SourceRange>) {
+        self.source_range = source_range;
+    }
+
+    pub fn source_range(&self) -> Option<SourceRange> {
+        self.source_range
+    }
+
     pub fn set_parent(&mut self, parent: Weak<RefCell<Node>>) {
         self.parent = parent;
     }
@@ -148,64 +216,39 @@ impl Node {
 
 /// https://dom.spec.whatwg.org/#interface-eventtarget
 impl EventTarget for Node {
-    fn target_kind(&self) -> NodeKind {
-        self.kind()
-    }
-
     /// https://dom.spec.whatwg.org/#dom-eventtarget-addeventlistener
-    fn add_event_listener(&mut self, event_type: String, callback: EventListenerCallback) {
-        for e in &self.events {
-            if e.event_type() == event_type {
-                // Do not add a new EventListener if the same event type already exists.
-                return;
-            }
+    fn add_event_listener(
+        &mut self,
+        event_type: String,
+        callback: EventListenerCallback,
+        capture: bool,
+    ) {
+        // https://dom.spec.whatwg.org/#add-an-event-listener
+        // "4. If eventTarget’s event listener list does not contain an event listener whose type
+        // is listener’s type, callback is listener’s callback, and capture is listener’s capture,
+        // then append listener to eventTarget’s event listener list."
+        let listener = EventListener::new(event_type, callback, capture);
+        if !self.events.iter().any(|e| e.is_same(&listener)) {
+            self.events.push(listener);
         }
-        self.events
-            .push(EventListener::new(event_type, callback, false));
     }
 
     /// https://dom.spec.whatwg.org/#dom-eventtarget-removeeventlistener
-    fn remove_event_listener(&mut self, event_type: String, _callback: EventListenerCallback) {
-        if let Some(index) = self
-            .events
-            .iter()
-            .position(|e| e.event_type() == event_type)
-        {
-            self.events.remove(index);
-        }
-    }
-
-    /// https://dom.spec.whatwg.org/#dom-eventtarget-dispatchevent
-    fn dispatch_event(&mut self, event: Event) -> bool {
-        // https://dom.spec.whatwg.org/#concept-event-dispatch
-        let mut activation_target: Option<Self> = None;
-        match &event {
-            // "5.4. Let isActivationEvent be true, if event is a MouseEvent object and event’s
-            // type attribute is "click"; otherwise false."
-            Event::MouseEvent(mouse_event) => {
-                // "5. If target is not relatedTarget or target is event’s relatedTarget, then:"
-                //
-                // "5.5. If isActivationEvent is true and target has activation behavior, then set
-                // activationTarget to target."
-                if self.target_kind() == mouse_event.target.target_kind()
-                    && mouse_event.event_type() == "click"
-                {
-                    activation_target = Some(self.clone());
-                }
-            }
-        }
-
-        // "11. If activationTarget is non-null, then:"
-        if let Some(target) = activation_target {
-            if let Some(activation_behavior) = target.activation_behavior {
-                // "11.1. If event’s canceled flag is unset, then run activationTarget’s activation behavior
-                // with event."
-                // "11.2. Otherwise, if activationTarget has legacy-canceled-activation behavior, then run
-                // activationTarget’s legacy-canceled-activation behavior."
-                activation_behavior(Rc::new(RefCell::new(self.clone())), event);
-            }
-        }
-        true
+    fn remove_event_listener(
+        &mut self,
+        event_type: String,
+        callback: EventListenerCallback,
+        capture: bool,
+    ) {
+        // https://dom.spec.whatwg.org/#remove-an-event-listener
+        // "2. Set listener’s removed to true and remove listener from eventTarget’s event
+        // listener list."
+        let listener = EventListener::new(event_type, callback, capture);
+        self.events.retain(|e| !e.is_same(&listener));
+    }
+
+    fn event_listeners(&self) -> Vec<EventListener> {
+        self.events.clone()
     }
 }
 
@@ -213,8 +256,12 @@ impl EventTarget for Node {
 pub enum NodeKind {
     /// https://dom.spec.whatwg.org/#interface-document
     Document,
+    /// https://dom.spec.whatwg.org/#interface-documenttype
+    DocumentType(DocumentType),
     /// https://dom.spec.whatwg.org/#interface-element
     Element(Element),
+    /// https://dom.spec.whatwg.org/#interface-comment
+    Comment(String),
     /// https://dom.spec.whatwg.org/#interface-text
     Text(String),
 }
@@ -223,26 +270,94 @@ impl PartialEq for NodeKind {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (Self::Document, Self::Document) => true,
-            (Self::Element(e1), Self::Element(e2)) => e1.kind == e2.kind,
+            (Self::DocumentType(d1), Self::DocumentType(d2)) => d1 == d2,
+            (Self::Element(e1), Self::Element(e2)) => {
+                e1.kind == e2.kind && e1.local_name == e2.local_name
+            }
+            (Self::Comment(c1), Self::Comment(c2)) => c1 == c2,
             (Self::Text(t1), Self::Text(t2)) => t1 == t2,
             _ => false,
         }
     }
 }
 
+/// https://dom.spec.whatwg.org/#concept-document-mode
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DocumentMode {
+    /// "no-quirks"
+    NoQuirks,
+    /// "quirks"
+    Quirks,
+    /// "limited-quirks"
+    LimitedQuirks,
+}
+
+/// https://dom.spec.whatwg.org/#interface-documenttype
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentType {
+    name: String,
+    public_id: String,
+    system_id: String,
+}
+
+impl DocumentType {
+    pub fn new(name: String, public_id: String, system_id: String) -> Self {
+        Self {
+            name,
+            public_id,
+            system_id,
+        }
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-documenttype-name
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-documenttype-publicid
+    pub fn public_id(&self) -> String {
+        self.public_id.clone()
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-documenttype-systemid
+    pub fn system_id(&self) -> String {
+        self.system_id.clone()
+    }
+}
+
 /// https://dom.spec.whatwg.org/#interface-element
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Element {
     kind: ElementKind,
+    /// https://dom.spec.whatwg.org/#concept-element-local-name
+    local_name: String,
     attributes: Vec<Attribute>,
+    /// The value of a form control edited by the user. None means that the dirty value flag is
+    /// false and the default value is used.
+    /// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#concept-fe-value
+    /// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#concept-fe-dirty
+    value: Option<String>,
+    /// The checkedness of an input element changed by the user. None means that the dirty
+    /// checkedness flag is false and the `checked` attribute is used.
+    /// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#concept-fe-checked
+    checkedness: Option<bool>,
+    /// The selectedness of an option element changed by the user. None means that the dirtiness
+    /// is false and the `selected` attribute is used.
+    /// https://html.spec.whatwg.org/multipage/form-elements.html#concept-option-selectedness
+    selectedness: Option<bool>,
 }
 
 impl Element {
+    /// Creates an element. An element whose name is not supported specifically (e.g. custom
+    /// elements) becomes ElementKind::Unknown and keeps its name in `local_name`.
     pub fn new(element_name: &str, attributes: Vec<Attribute>) -> Self {
         Self {
-            kind: ElementKind::from_str(element_name)
-                .expect("failed to convert string to ElementKind"),
+            kind: ElementKind::from_str(element_name).unwrap_or(ElementKind::Unknown),
+            local_name: String::from(element_name),
             attributes,
+            value: None,
+            checkedness: None,
+            selectedness: None,
         }
     }
 
@@ -250,6 +365,11 @@ impl Element {
         self.kind
     }
 
+    /// https://dom.spec.whatwg.org/#dom-element-localname
+    pub fn local_name(&self) -> String {
+        self.local_name.clone()
+    }
+
     pub fn attributes(&self) -> Vec<Attribute> {
         self.attributes.clone()
     }
@@ -264,18 +384,66 @@ impl Element {
         None
     }
 
+    /// Returns the value set by the user, or None if the value is not dirty.
+    /// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#concept-fe-dirty
+    pub fn dirty_value(&self) -> Option<String> {
+        self.value.clone()
+    }
+
+    /// Sets the value and the dirty value flag to true.
+    pub fn set_value(&mut self, value: String) {
+        self.value = Some(value);
+    }
+
+    /// Returns the checkedness set by the user, or None if the dirty checkedness flag is false.
+    pub fn dirty_checkedness(&self) -> Option<bool> {
+        self.checkedness
+    }
+
+    /// Sets the checkedness and the dirty checkedness flag to true.
+    pub fn set_checkedness(&mut self, checkedness: bool) {
+        self.checkedness = Some(checkedness);
+    }
+
+    /// Returns the selectedness set by the user, or None if the dirtiness is false.
+    pub fn dirty_selectedness(&self) -> Option<bool> {
+        self.selectedness
+    }
+
+    /// Sets the selectedness and the dirtiness to true.
+    pub fn set_selectedness(&mut self, selectedness: bool) {
+        self.selectedness = Some(selectedness);
+    }
+
     /// return true if this element is a block element
     pub fn is_block_element(&self) -> bool {
         match self.kind {
             // https://developer.mozilla.org/en-US/docs/Web/HTML/Block-level_elements#elements
+            // https://html.spec.whatwg.org/multipage/rendering.html#flow-content-3
             ElementKind::Body
             | ElementKind::H1
             | ElementKind::H2
+            | ElementKind::H3
+            | ElementKind::H4
+            | ElementKind::H5
+            | ElementKind::H6
+            | ElementKind::Section
+            | ElementKind::Article
+            | ElementKind::Nav
+            | ElementKind::Header
+            | ElementKind::Footer
             | ElementKind::P
+            | ElementKind::Hr
             | ElementKind::Pre
+            | ElementKind::Blockquote
+            | ElementKind::Ol
             | ElementKind::Ul
             | ElementKind::Li
-            | ElementKind::Div => true,
+            | ElementKind::Dl
+            | ElementKind::Dt
+            | ElementKind::Dd
+            | ElementKind::Div
+            | ElementKind::Form => true,
             // https://developer.mozilla.org/en-US/docs/Web/HTML/Inline_elements#list_of_inline_elements
             _ => false,
         }
@@ -316,23 +484,105 @@ pub enum ElementKind {
     Script,
     /// https://html.spec.whatwg.org/multipage/sections.html#the-body-element
     Body,
+    /// https://html.spec.whatwg.org/multipage/semantics.html#the-title-element
+    Title,
     /// https://html.spec.whatwg.org/multipage/sections.html#the-h1,-h2,-h3,-h4,-h5,-and-h6-elements
     H1,
     H2,
+    H3,
+    H4,
+    H5,
+    H6,
+    /// https://html.spec.whatwg.org/multipage/sections.html#the-section-element
+    Section,
+    /// https://html.spec.whatwg.org/multipage/sections.html#the-article-element
+    Article,
+    /// https://html.spec.whatwg.org/multipage/sections.html#the-nav-element
+    Nav,
+    /// https://html.spec.whatwg.org/multipage/sections.html#the-header-element
+    Header,
+    /// https://html.spec.whatwg.org/multipage/sections.html#the-footer-element
+    Footer,
     /// https://html.spec.whatwg.org/multipage/grouping-content.html#the-p-element
     P,
+    /// https://html.spec.whatwg.org/multipage/grouping-content.html#the-hr-element
+    Hr,
     /// https://html.spec.whatwg.org/multipage/grouping-content.html#the-pre-element
     Pre,
+    /// https://html.spec.whatwg.org/multipage/grouping-content.html#the-blockquote-element
+    Blockquote,
+    /// https://html.spec.whatwg.org/multipage/grouping-content.html#the-ol-element
+    Ol,
     /// https://html.spec.whatwg.org/multipage/grouping-content.html#the-ul-element
     Ul,
     /// https://html.spec.whatwg.org/multipage/grouping-content.html#the-li-element
     Li,
+    /// https://html.spec.whatwg.org/multipage/grouping-content.html#the-dl-element
+    Dl,
+    /// https://html.spec.whatwg.org/multipage/grouping-content.html#the-dt-element
+    Dt,
+    /// https://html.spec.whatwg.org/multipage/grouping-content.html#the-dd-element
+    Dd,
     /// https://html.spec.whatwg.org/multipage/grouping-content.html#the-div-element
     Div,
+    /// https://html.spec.whatwg.org/multipage/tables.html#the-table-element
+    Table,
+    /// https://html.spec.whatwg.org/multipage/tables.html#the-caption-element
+    Caption,
+    /// https://html.spec.whatwg.org/multipage/tables.html#the-colgroup-element
+    Colgroup,
+    /// https://html.spec.whatwg.org/multipage/tables.html#the-col-element
+    Col,
+    /// https://html.spec.whatwg.org/multipage/tables.html#the-tbody-element
+    Tbody,
+    /// https://html.spec.whatwg.org/multipage/tables.html#the-thead-element
+    Thead,
+    /// https://html.spec.whatwg.org/multipage/tables.html#the-tfoot-element
+    Tfoot,
+    /// https://html.spec.whatwg.org/multipage/tables.html#the-tr-element
+    Tr,
+    /// https://html.spec.whatwg.org/multipage/tables.html#the-td-element
+    Td,
+    /// https://html.spec.whatwg.org/multipage/tables.html#the-th-element
+    Th,
+    /// https://html.spec.whatwg.org/multipage/forms.html#the-form-element
+    Form,
+    /// https://html.spec.whatwg.org/multipage/input.html#the-input-element
+    Input,
+    /// https://html.spec.whatwg.org/multipage/form-elements.html#the-textarea-element
+    Textarea,
+    /// https://html.spec.whatwg.org/multipage/form-elements.html#the-select-element
+    Select,
+    /// https://html.spec.whatwg.org/multipage/form-elements.html#the-optgroup-element
+    Optgroup,
+    /// https://html.spec.whatwg.org/multipage/form-elements.html#the-option-element
+    Option,
+    /// https://html.spec.whatwg.org/multipage/form-elements.html#the-button-element
+    Button,
     /// https://html.spec.whatwg.org/multipage/text-level-semantics.html#the-a-element
     A,
+    /// https://html.spec.whatwg.org/multipage/text-level-semantics.html#the-em-element
+    Em,
+    /// https://html.spec.whatwg.org/multipage/text-level-semantics.html#the-strong-element
+    Strong,
+    /// https://html.spec.whatwg.org/multipage/text-level-semantics.html#the-code-element
+    Code,
+    /// https://html.spec.whatwg.org/multipage/text-level-semantics.html#the-i-element
+    I,
+    /// https://html.spec.whatwg.org/multipage/text-level-semantics.html#the-b-element
+    B,
+    /// https://html.spec.whatwg.org/multipage/text-level-semantics.html#the-span-element
+    Span,
+    /// https://html.spec.whatwg.org/multipage/text-level-semantics.html#the-br-element
+    Br,
     /// https://html.spec.whatwg.org/multipage/embedded-content.html#the-img-element
     IMG,
+    /// https://html.spec.whatwg.org/multipage/iframe-embed-object.html#the-iframe-element
+    Iframe,
+    /// An element that doesn't have a specific kind such as a custom element or an element this
+    /// browser doesn't support. Its tag name is stored in `Element::local_name`.
+    /// https://html.spec.whatwg.org/multipage/dom.html#htmlunknownelement
+    Unknown,
 }
 
 impl Display for ElementKind {
@@ -343,15 +593,57 @@ impl Display for ElementKind {
             ElementKind::Style => "style",
             ElementKind::Script => "script",
             ElementKind::Body => "body",
+            ElementKind::Title => "title",
             ElementKind::H1 => "h1",
             ElementKind::H2 => "h2",
+            ElementKind::H3 => "h3",
+            ElementKind::H4 => "h4",
+            ElementKind::H5 => "h5",
+            ElementKind::H6 => "h6",
+            ElementKind::Section => "section",
+            ElementKind::Article => "article",
+            ElementKind::Nav => "nav",
+            ElementKind::Header => "header",
+            ElementKind::Footer => "footer",
             ElementKind::P => "p",
+            ElementKind::Hr => "hr",
             ElementKind::Pre => "pre",
+            ElementKind::Blockquote => "blockquote",
+            ElementKind::Ol => "ol",
             ElementKind::Ul => "ul",
             ElementKind::Li => "li",
+            ElementKind::Dl => "dl",
+            ElementKind::Dt => "dt",
+            ElementKind::Dd => "dd",
             ElementKind::Div => "div",
+            ElementKind::Table => "table",
+            ElementKind::Caption => "caption",
+            ElementKind::Colgroup => "colgroup",
+            ElementKind::Col => "col",
+            ElementKind::Tbody => "tbody",
+            ElementKind::Thead => "thead",
+            ElementKind::Tfoot => "tfoot",
+            ElementKind::Tr => "tr",
+            ElementKind::Td => "td",
+            ElementKind::Th => "th",
+            ElementKind::Form => "form",
+            ElementKind::Input => "input",
+            ElementKind::Textarea => "textarea",
+            ElementKind::Select => "select",
+            ElementKind::Optgroup => "optgroup",
+            ElementKind::Option => "option",
+            ElementKind::Button => "button",
             ElementKind::A => "a",
+            ElementKind::Em => "em",
+            ElementKind::Strong => "strong",
+            ElementKind::Code => "code",
+            ElementKind::I => "i",
+            ElementKind::B => "b",
+            ElementKind::Span => "span",
+            ElementKind::Br => "br",
             ElementKind::IMG => "img",
+            ElementKind::Iframe => "iframe",
+            ElementKind::Unknown => "unknown",
         };
         write!(f, "{}", s)
     }
@@ -367,15 +659,56 @@ impl FromStr for ElementKind {
             "style" => Ok(ElementKind::Style),
             "script" => Ok(ElementKind::Script),
             "body" => Ok(ElementKind::Body),
+            "title" => Ok(ElementKind::Title),
             "h1" => Ok(ElementKind::H1),
             "h2" => Ok(ElementKind::H2),
+            "h3" => Ok(ElementKind::H3),
+            "h4" => Ok(ElementKind::H4),
+            "h5" => Ok(ElementKind::H5),
+            "h6" => Ok(ElementKind::H6),
+            "section" => Ok(ElementKind::Section),
+            "article" => Ok(ElementKind::Article),
+            "nav" => Ok(ElementKind::Nav),
+            "header" => Ok(ElementKind::Header),
+            "footer" => Ok(ElementKind::Footer),
             "p" => Ok(ElementKind::P),
+            "hr" => Ok(ElementKind::Hr),
             "pre" => Ok(ElementKind::Pre),
+            "blockquote" => Ok(ElementKind::Blockquote),
+            "ol" => Ok(ElementKind::Ol),
             "ul" => Ok(ElementKind::Ul),
             "li" => Ok(ElementKind::Li),
+            "dl" => Ok(ElementKind::Dl),
+            "dt" => Ok(ElementKind::Dt),
+            "dd" => Ok(ElementKind::Dd),
             "div" => Ok(ElementKind::Div),
+            "table" => Ok(ElementKind::Table),
+            "caption" => Ok(ElementKind::Caption),
+            "colgroup" => Ok(ElementKind::Colgroup),
+            "col" => Ok(ElementKind::Col),
+            "tbody" => Ok(ElementKind::Tbody),
+            "thead" => Ok(ElementKind::Thead),
+            "tfoot" => Ok(ElementKind::Tfoot),
+            "tr" => Ok(ElementKind::Tr),
+            "td" => Ok(ElementKind::Td),
+            "th" => Ok(ElementKind::Th),
+            "form" => Ok(ElementKind::Form),
+            "input" => Ok(ElementKind::Input),
+            "textarea" => Ok(ElementKind::Textarea),
+            "select" => Ok(ElementKind::Select),
+            "optgroup" => Ok(ElementKind::Optgroup),
+            "option" => Ok(ElementKind::Option),
+            "button" => Ok(ElementKind::Button),
             "a" => Ok(ElementKind::A),
+            "em" => Ok(ElementKind::Em),
+            "strong" => Ok(ElementKind::Strong),
+            "code" => Ok(ElementKind::Code),
+            "i" => Ok(ElementKind::I),
+            "b" => Ok(ElementKind::B),
+            "span" => Ok(ElementKind::Span),
+            "br" => Ok(ElementKind::Br),
             "img" => Ok(ElementKind::IMG),
+            "iframe" => Ok(ElementKind::Iframe),
             _ => Err(format!("unimplemented element name {:?}", s)),
         }
     }
@@ -410,6 +743,33 @@ mod tests {
         assert_eq!(n1, n2);
     }
 
+    #[test]
+    fn test_comment_nodes() {
+        let n1 = Node::new(NodeKind::Comment("comment".to_string()));
+        let n2 = Node::new(NodeKind::Comment("comment".to_string()));
+        let n3 = Node::new(NodeKind::Text("comment".to_string()));
+
+        assert_eq!(n1, n2);
+        assert_ne!(n1, n3);
+    }
+
+    #[test]
+    fn test_document_type_nodes() {
+        let n1 = Node::new(NodeKind::DocumentType(DocumentType::new(
+            "html".to_string(),
+            "".to_string(),
+            "".to_string(),
+        )));
+        let n2 = Node::new(NodeKind::DocumentType(DocumentType::new(
+            "html".to_string(),
+            "".to_string(),
+            "".to_string(),
+        )));
+
+        assert_eq!(n1, n2);
+        assert_eq!(DocumentMode::NoQuirks, n1.document_mode());
+    }
+
     #[test]
     fn test_different_nodes() {
         let n1 = Node::new(NodeKind::Document);
@@ -418,6 +778,20 @@ mod tests {
         assert_ne!(n1, n2);
     }
 
+    #[test]
+    fn test_unknown_elements() {
+        let e1 = Element::new("my-element", Vec::new());
+        assert_eq!(ElementKind::Unknown, e1.kind());
+        assert_eq!("my-element".to_string(), e1.local_name());
+
+        let n1 = Node::new(NodeKind::Element(e1));
+        let n2 = Node::new(NodeKind::Element(Element::new("my-element", Vec::new())));
+        let n3 = Node::new(NodeKind::Element(Element::new("table", Vec::new())));
+
+        assert_eq!(n1, n2);
+        assert_ne!(n1, n3);
+    }
+
     #[test]
     fn test_different_elements() {
         let n1 = Node::new(NodeKind::Element(Element::new("html", Vec::new())));
@@ -0,0 +1,271 @@
+//! This module implements a declarative refresh by `<meta http-equiv="refresh">`.
+//! https://html.spec.whatwg.org/multipage/semantics.html#attr-meta-http-equiv-refresh
+
+use crate::renderer::dom::api::refresh_meta_element;
+use crate::renderer::dom::node::Node;
+use crate::url::Url;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+/// A navigation scheduled by a declarative refresh.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Refresh {
+    /// The number of seconds to wait before navigating.
+    delay: u64,
+    /// The URL to navigate to.
+    url: String,
+}
+
+impl Refresh {
+    pub fn new(delay: u64, url: String) -> Self {
+        Self { delay, url }
+    }
+
+    pub fn delay(&self) -> u64 {
+        self.delay
+    }
+
+    pub fn url(&self) -> String {
+        self.url.clone()
+    }
+}
+
+/// Returns the refresh declared by the first meta element in the refresh state in `document`.
+/// `document_url` is the URL of the document and `base_url` is its document base URL.
+/// https://html.spec.whatwg.org/multipage/semantics.html#attr-meta-http-equiv-refresh
+pub fn meta_refresh(
+    document: &Rc<RefCell<Node>>,
+    document_url: &str,
+    base_url: &str,
+) -> Option<Refresh> {
+    // "1. If the meta element has no content attribute, or if that attribute's value is the
+    // empty string, then return.
+    // 2. Let input be the value of the element's content attribute."
+    let meta = refresh_meta_element(document)?;
+    let input = meta.borrow().get_element()?.get_attribute("content")?;
+    if input.is_empty() {
+        return None;
+    }
+
+    // "3. Run the shared declarative refresh steps with the meta element's node document, input,
+    // and the meta element."
+    parse_refresh(&input, document_url, base_url)
+}
+
+/// Parses `input` and returns the refresh to schedule. Returns None if `input` is invalid.
+/// https://html.spec.whatwg.org/multipage/browsing-the-web.html#shared-declarative-refresh-steps
+pub fn parse_refresh(input: &str, document_url: &str, base_url: &str) -> Option<Refresh> {
+    let input: Vec<char> = input.chars().collect();
+
+    // "2. Let position point at the start of input."
+    let mut position = 0;
+
+    let skip_whitespace = |position: &mut usize| {
+        while *position < input.len() && input[*position].is_ascii_whitespace() {
+            *position += 1;
+        }
+    };
+
+    // "3. Skip ASCII whitespace within input given position."
+    skip_whitespace(&mut position);
+
+    // "4. Let time be 0."
+    let mut time = 0;
+
+    // "5. Collect a sequence of code points that are ASCII digits from input given position, and
+    // let the result be timeString."
+    let start = position;
+    while position < input.len() && input[position].is_ascii_digit() {
+        position += 1;
+    }
+    let time_string: String = input[start..position].iter().collect();
+
+    if time_string.is_empty() {
+        // "6. If timeString is the empty string, then:
+        //   1. If the code point in input pointed to by position is not U+002E (.), then return."
+        if input.get(position) != Some(&'.') {
+            return None;
+        }
+    } else {
+        // "7. Otherwise, set time to the result of parsing timeString using the rules for parsing
+        // non-negative integers."
+        time = time_string.parse::<u64>().unwrap_or(u64::MAX);
+    }
+
+    // "8. Collect a sequence of code points that are ASCII digits and U+002E FULL STOP
+    // characters (.) from input given position. Ignore any collected characters."
+    while position < input.len() && (input[position].is_ascii_digit() || input[position] == '.') {
+        position += 1;
+    }
+
+    // "9. Let urlRecord be document's URL."
+    let mut url = document_url.to_string();
+
+    // "10. If position is not past the end of input, then:"
+    if position < input.len() {
+        // "1. If the code point in input pointed to by position is not U+003B (;), U+002C (,), or
+        // ASCII whitespace, then return."
+        let c = input[position];
+        if c != ';' && c != ',' && !c.is_ascii_whitespace() {
+            return None;
+        }
+        // "2. Skip ASCII whitespace within input given position."
+        skip_whitespace(&mut position);
+        // "3. If the code point in input pointed to by position is U+003B (;) or U+002C (,), then
+        // advance position to the next code point."
+        if matches!(input.get(position), Some(';') | Some(',')) {
+            position += 1;
+        }
+        // "4. Skip ASCII whitespace within input given position."
+        skip_whitespace(&mut position);
+    }
+
+    // "11. If position is not past the end of input, then:"
+    if position < input.len() {
+        // "1. Let urlString be the substring of input from the code point at position to the end
+        // of the string."
+        let mut url_start = position;
+
+        // "2. If the code point in input pointed to by position is U+0055 (U) or U+0075 (u), then
+        // advance position to the next code point. Otherwise, jump to the step labeled skip
+        // quotes.
+        // 3. If the code point in input pointed to by position is U+0052 (R) or U+0072 (r), then
+        // advance position to the next code point. Otherwise, jump to the step labeled parse.
+        // 4. If the code point in input pointed to by position is U+004C (L) or U+006C (l), then
+        // advance position to the next code point. Otherwise, jump to the step labeled parse.
+        // 5. Skip ASCII whitespace within input given position.
+        // 6. If the code point in input pointed to by position is U+003D (=), then advance
+        // position to the next code point. Otherwise, jump to the step labeled parse.
+        // 7. Skip ASCII whitespace within input given position."
+        let mut skip_quotes = true;
+        let mut p = position;
+        if matches!(input.get(p), Some('U') | Some('u')) {
+            p += 1;
+            if matches!(input.get(p), Some('R') | Some('r'))
+                && matches!(input.get(p + 1), Some('L') | Some('l'))
+            {
+                p += 2;
+                skip_whitespace(&mut p);
+                if input.get(p) == Some(&'=') {
+                    p += 1;
+                    skip_whitespace(&mut p);
+                    position = p;
+                } else {
+                    skip_quotes = false;
+                }
+            } else {
+                skip_quotes = false;
+            }
+        }
+
+        let mut quote = None;
+        if skip_quotes {
+            // "8. Skip quotes: If the code point in input pointed to by position is U+0027 (') or
+            // U+0022 ("), then let quote be that code point, and advance position to the next
+            // code point. Otherwise, let quote be the empty string."
+            if let Some(c @ ('\'' | '"')) = input.get(position) {
+                quote = Some(*c);
+                position += 1;
+            }
+            // "9. Set urlString to the substring of input from the code point at position to the
+            // end of the string."
+            url_start = position;
+        }
+
+        // "10. If quote is not the empty string, and there is a code point in urlString equal to
+        // quote, then truncate urlString at that code point, so that it and all subsequent code
+        // points are removed."
+        let mut url_string: String = input[url_start..].iter().collect();
+        if let Some(q) = quote {
+            if let Some(index) = url_string.find(q) {
+                url_string.truncate(index);
+            }
+        }
+
+        // "11. Parse: Set urlRecord to the result of encoding-parsing a URL given urlString,
+        // relative to document.
+        // 12. If urlRecord is failure, then return."
+        let resolved = match Url::new(base_url.to_string()).parse() {
+            Ok(base) => base.resolve(&url_string),
+            Err(_) => url_string,
+        };
+        url = Url::new(resolved).parse().ok()?.href();
+    }
+
+    Some(Refresh::new(time, url))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::test_util::create_window;
+
+    const DOCUMENT_URL: &str = "http://example.com/dir/page.html";
+
+    fn refresh(input: &str) -> Option<Refresh> {
+        parse_refresh(input, DOCUMENT_URL, DOCUMENT_URL)
+    }
+
+    #[test]
+    fn test_parse_refresh() {
+        assert_eq!(
+            Some(Refresh::new(5, DOCUMENT_URL.to_string())),
+            refresh(" 5 ")
+        );
+        assert_eq!(
+            Some(Refresh::new(
+                0,
+                "http://example.com/dir/next.html".to_string()
+            )),
+            refresh("0; url=next.html")
+        );
+        assert_eq!(
+            Some(Refresh::new(3, "http://example.com/a".to_string())),
+            refresh("3.5, URL = '/a' ignored")
+        );
+        assert_eq!(
+            Some(Refresh::new(1, "http://example.org".to_string())),
+            refresh("1;\"http://example.org/\"")
+        );
+        // A URL which doesn't start with "url=" is used as it is.
+        assert_eq!(
+            Some(Refresh::new(
+                0,
+                "http://example.com/dir/urgent.html".to_string()
+            )),
+            refresh("0;urgent.html")
+        );
+        assert_eq!(
+            Some(Refresh::new(0, DOCUMENT_URL.to_string())),
+            refresh(".5")
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_refresh() {
+        assert_eq!(None, refresh(""));
+        assert_eq!(None, refresh("url=next.html"));
+        assert_eq!(None, refresh("5x; url=next.html"));
+        assert_eq!(None, refresh("0; url=https://example.com/"));
+    }
+
+    #[test]
+    fn test_meta_refresh() {
+        let window = create_window("<html><head><base href=\"/base/\"><meta http-equiv=\"Refresh\" content=\"2; url=next.html\"><meta http-equiv=refresh content=0></head></html>");
+        window.borrow_mut().set_url(DOCUMENT_URL.to_string());
+        let base_url = window.borrow().base_url();
+        assert_eq!("http://example.com/base/".to_string(), base_url);
+
+        let document = window.borrow().document();
+        assert_eq!(
+            Some(Refresh::new(
+                2,
+                "http://example.com/base/next.html".to_string()
+            )),
+            meta_refresh(&document, DOCUMENT_URL, &base_url)
+        );
+    }
+}
@@ -2,11 +2,20 @@
 //! https://html.spec.whatwg.org/multipage/nav-history-apis.html#window
 
 use crate::browser::Browser;
+use crate::renderer::dom::api::base_element;
+use crate::renderer::dom::event::EventListener;
+use crate::renderer::dom::event::EventListenerCallback;
+use crate::renderer::dom::event::EventTarget;
 use crate::renderer::dom::node::Node;
 use crate::renderer::dom::node::NodeKind;
 use crate::renderer::page::Page;
+use crate::renderer::session_history::HistoryHandling;
+use crate::renderer::session_history::SessionHistory;
+use crate::url::Url;
 use alloc::rc::Rc;
 use alloc::rc::Weak;
+use alloc::string::String;
+use alloc::vec::Vec;
 use core::cell::RefCell;
 
 /// https://html.spec.whatwg.org/multipage/nav-history-apis.html#window
@@ -14,30 +23,182 @@ use core::cell::RefCell;
 /// https://source.chromium.org/chromium/chromium/src/+/main:third_party/blink/renderer/core/frame/dom_window.h
 #[derive(Debug, Clone)]
 pub struct Window {
-    _browser: Weak<RefCell<Browser>>,
+    browser: Weak<RefCell<Browser>>,
     _page: Weak<RefCell<Page>>,
     document: Rc<RefCell<Node>>,
-    // TODO: support location
-    //_location: Location,
+    /// https://dom.spec.whatwg.org/#concept-document-url
+    url: String,
+    /// The navigation requested by following a hyperlink or by the Location object.
+    navigation: Option<Navigation>,
+    /// The session history of the page which holds this window. It's shared with the page so
+    /// that the History API reads and updates it while the page runs scripts.
+    session_history: Rc<RefCell<SessionHistory>>,
+    /// The delta of the history traversal requested by the History API.
+    /// https://html.spec.whatwg.org/multipage/browsing-the-web.html#traverse-the-history-by-a-delta
+    traversal: Option<i64>,
+    /// https://dom.spec.whatwg.org/#eventtarget-event-listener-list
+    events: Vec<EventListener>,
 }
 
 impl Window {
     pub fn new(browser: Weak<RefCell<Browser>>) -> Self {
-        let window = Self {
-            _browser: browser,
+        Self {
+            browser,
             _page: Weak::new(),
             document: Rc::new(RefCell::new(Node::new(NodeKind::Document))),
-        };
-
-        window
-            .document
-            .borrow_mut()
-            .set_window(Rc::downgrade(&Rc::new(RefCell::new(window.clone()))));
+            url: String::new(),
+            navigation: None,
+            session_history: Rc::new(RefCell::new(SessionHistory::new())),
+            traversal: None,
+            events: Vec::new(),
+        }
+    }
 
-        window
+    pub fn browser(&self) -> Weak<RefCell<Browser>> {
+        self.browser.clone()
     }
 
     pub fn document(&self) -> Rc<RefCell<Node>> {
         self.document.clone()
     }
+
+    pub fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    pub fn set_url(&mut self, url: String) {
+        self.url = url;
+    }
+
+    /// Returns the URL that relative URLs in the document are resolved against.
+    /// https://html.spec.whatwg.org/multipage/urls-and-fetching.html#document-base-url
+    pub fn base_url(&self) -> String {
+        // "1. If there is no base element that has an href attribute in the Document, then return
+        // the Document's fallback base URL."
+        // The fallback base URL is the document's URL because about:blank and srcdoc documents
+        // are not supported.
+        let href = match base_element(&self.document)
+            .and_then(|base| base.borrow().get_element())
+            .and_then(|e| e.get_attribute("href"))
+        {
+            Some(href) => href,
+            None => return self.url.clone(),
+        };
+
+        // "2. Otherwise, return the frozen base URL of the first base element in the Document
+        // that has an href attribute, in tree order."
+        // https://html.spec.whatwg.org/multipage/semantics.html#set-the-frozen-base-url
+        // "2. Let urlRecord be the result of parsing the value of element's href content
+        // attribute with document's fallback base URL, and document's character encoding.
+        // 3. If any of the following are true:
+        //   - urlRecord is failure;
+        //   - urlRecord's scheme is "data" or "javascript"; or
+        //   ...
+        // then set urlRecord to fallbackBaseURL."
+        // Only the HTTP scheme is supported, so any other scheme is a failure.
+        let resolved = match Url::new(self.url.clone()).parse() {
+            Ok(fallback) => fallback.resolve(&href),
+            Err(_) => href,
+        };
+        match Url::new(resolved).parse() {
+            Ok(url) => url.href(),
+            Err(_) => self.url.clone(),
+        }
+    }
+
+    /// Requests the page which holds this window to start `navigation` after the current task.
+    pub fn navigate(&mut self, navigation: Navigation) {
+        self.navigation = Some(navigation);
+    }
+
+    pub fn take_navigation(&mut self) -> Option<Navigation> {
+        self.navigation.take()
+    }
+
+    /// https://html.spec.whatwg.org/multipage/nav-history-apis.html#window-bc
+    pub fn session_history(&self) -> Rc<RefCell<SessionHistory>> {
+        self.session_history.clone()
+    }
+
+    pub fn set_session_history(&mut self, session_history: Rc<RefCell<SessionHistory>>) {
+        self.session_history = session_history;
+    }
+
+    /// Requests the page which holds this window to traverse the session history by `delta`
+    /// after the current task.
+    pub fn traverse_history(&mut self, delta: i64) {
+        self.traversal = Some(delta);
+    }
+
+    pub fn take_traversal(&mut self) -> Option<i64> {
+        self.traversal.take()
+    }
+}
+
+impl EventTarget for Window {
+    /// https://dom.spec.whatwg.org/#dom-eventtarget-addeventlistener
+    fn add_event_listener(
+        &mut self,
+        event_type: String,
+        callback: EventListenerCallback,
+        capture: bool,
+    ) {
+        let listener = EventListener::new(event_type, callback, capture);
+        if !self.events.iter().any(|e| e.is_same(&listener)) {
+            self.events.push(listener);
+        }
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-eventtarget-removeeventlistener
+    fn remove_event_listener(
+        &mut self,
+        event_type: String,
+        callback: EventListenerCallback,
+        capture: bool,
+    ) {
+        let listener = EventListener::new(event_type, callback, capture);
+        self.events.retain(|e| !e.is_same(&listener));
+    }
+
+    fn event_listeners(&self) -> Vec<EventListener> {
+        self.events.clone()
+    }
+}
+
+/// A navigation requested by following a hyperlink or by the Location object. The target is the
+/// name of the navigable to navigate, e.g. "_self", "_top" or the name of an iframe.
+/// https://html.spec.whatwg.org/multipage/browsing-the-web.html#navigate
+/// https://html.spec.whatwg.org/multipage/document-sequences.html#valid-navigable-target-name-or-keyword
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Navigation {
+    url: String,
+    target: String,
+    history_handling: HistoryHandling,
+}
+
+impl Navigation {
+    pub fn new(url: String, target: String) -> Self {
+        Self {
+            url,
+            target,
+            history_handling: HistoryHandling::Push,
+        }
+    }
+
+    pub fn with_history_handling(mut self, history_handling: HistoryHandling) -> Self {
+        self.history_handling = history_handling;
+        self
+    }
+
+    pub fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    pub fn target(&self) -> String {
+        self.target.clone()
+    }
+
+    pub fn history_handling(&self) -> HistoryHandling {
+        self.history_handling
+    }
 }
@@ -1,53 +1,189 @@
 //! This is a helper function to construct HTML string from DOM tree.
+//!
+//! https://html.spec.whatwg.org/multipage/parsing.html#serialising-html-fragments
 
-use crate::alloc::string::ToString;
 use crate::renderer::dom::node::Node;
 use crate::renderer::dom::node::NodeKind;
 use alloc::rc::Rc;
 use alloc::string::String;
 use core::cell::RefCell;
 
+/// https://html.spec.whatwg.org/multipage/syntax.html#void-elements
+const VOID_ELEMENTS: [&str; 18] = [
+    "area", "base", "basefont", "bgsound", "br", "col", "embed", "frame", "hr", "img", "input",
+    "keygen", "link", "meta", "param", "source", "track", "wbr",
+];
+
+/// Elements whose text children are serialized without escaping.
+const RAW_TEXT_PARENTS: [&str; 7] = [
+    "style",
+    "script",
+    "xmp",
+    "iframe",
+    "noembed",
+    "noframes",
+    "plaintext",
+];
+
+/// Serializes `root` and its following siblings.
 pub fn dom_to_html(root: &Option<Rc<RefCell<Node>>>) -> String {
     let mut html = String::new();
-    dom_to_html_internal(root, &mut html);
+    let mut node = root.clone();
+    while let Some(n) = node {
+        serialize_node_internal(&n, &mut html);
+        node = n.borrow().next_sibling();
+    }
+    html
+}
+
+/// Serializes the children of `node`. This is the value of `innerHTML`.
+/// https://html.spec.whatwg.org/multipage/parsing.html#html-fragment-serialisation-algorithm
+pub fn serialize_children(node: &Rc<RefCell<Node>>) -> String {
+    // "4. Let s be a string, and initialize it to the empty string.
+    // 6. For each child node of the node, in tree order, run the following steps:"
+    dom_to_html(&node.borrow().first_child())
+}
+
+/// Serializes `node` itself including its children. This is the value of `outerHTML`.
+/// https://w3c.github.io/DOM-Parsing/#dom-element-outerhtml
+pub fn serialize_node(node: &Rc<RefCell<Node>>) -> String {
+    let mut html = String::new();
+    serialize_node_internal(node, &mut html);
     html
 }
 
-fn dom_to_html_internal(node: &Option<Rc<RefCell<Node>>>, html: &mut String) {
-    match node {
-        Some(n) => {
-            // open tag
-            match n.borrow().kind() {
-                NodeKind::Document => {}
-                NodeKind::Element(ref e) => {
-                    html.push('<');
-                    html.push_str(&e.kind().to_string());
-                    for attr in e.attributes() {
-                        html.push(' ');
-                        html.push_str(&attr.name());
-                        html.push('=');
-                        html.push_str(&attr.value());
-                    }
-                    html.push('>');
-                }
-                NodeKind::Text(ref s) => html.push_str(s),
+fn serialize_node_internal(node: &Rc<RefCell<Node>>, html: &mut String) {
+    // "2. Append the appropriate string from the following list to s:"
+    match node.borrow().kind() {
+        NodeKind::Document => {
+            html.push_str(&serialize_children(node));
+        }
+        // "If current node is a DocumentType
+        //   Append the literal string "<!DOCTYPE" (U+003C LESS-THAN SIGN, U+0021 EXCLAMATION MARK,
+        //   U+0044 LATIN CAPITAL LETTER D, U+004F LATIN CAPITAL LETTER O, U+0043 LATIN CAPITAL
+        //   LETTER C, U+0054 LATIN CAPITAL LETTER T, U+0059 LATIN CAPITAL LETTER Y, U+0050 LATIN
+        //   CAPITAL LETTER P, U+0045 LATIN CAPITAL LETTER E), followed by a space (U+0020 SPACE),
+        //   followed by the value of current node's name IDL attribute, followed by the literal
+        //   string ">" (U+003E GREATER-THAN SIGN)."
+        NodeKind::DocumentType(ref d) => {
+            html.push_str("<!DOCTYPE ");
+            html.push_str(&d.name());
+            html.push('>');
+        }
+        // "If current node is an Element
+        //   Let tagname be current node's qualified name.
+        //   Append a U+003C LESS-THAN SIGN character (<), followed by tagname.
+        //   For each attribute that the element has, append a U+0020 SPACE character, the
+        //   attribute's serialized name as described below, a U+003D EQUALS SIGN character (=), a
+        //   U+0022 QUOTATION MARK character ("), the attribute's value, escaped as described below
+        //   in attribute mode, and a second U+0022 QUOTATION MARK character (").
+        //   Append a U+003E GREATER-THAN SIGN character (>).
+        //   If current node serializes as void, then continue on to the next child node at this
+        //   point.
+        //   Append the value of running the HTML fragment serialization algorithm on the current
+        //   node element, followed by a U+003C LESS-THAN SIGN character (<), a U+002F SOLIDUS
+        //   character (/), tagname again, and finally a U+003E GREATER-THAN SIGN character (>)."
+        NodeKind::Element(ref e) => {
+            let tag_name = e.local_name();
+            html.push('<');
+            html.push_str(&tag_name);
+            for attr in e.attributes() {
+                html.push(' ');
+                html.push_str(&attr.name());
+                html.push_str("=\"");
+                html.push_str(&escape_string(&attr.value(), true));
+                html.push('"');
+            }
+            html.push('>');
+
+            if VOID_ELEMENTS.contains(&tag_name.as_str()) {
+                return;
             }
 
-            dom_to_html_internal(&n.borrow().first_child(), html);
-
-            // close tag
-            match n.borrow().kind() {
-                NodeKind::Document => {}
-                NodeKind::Element(ref e) => {
-                    html.push_str("</");
-                    html.push_str(&e.kind().to_string());
-                    html.push('>');
-                }
-                NodeKind::Text(_s) => {}
+            html.push_str(&serialize_children(node));
+            html.push_str("</");
+            html.push_str(&tag_name);
+            html.push('>');
+        }
+        // "If current node is a Text node
+        //   If the parent of current node is a style, script, xmp, iframe, noembed, noframes, or
+        //   plaintext element, or if the parent of current node is a noscript element and
+        //   scripting is enabled for the node, then append the value of current node's data IDL
+        //   attribute literally.
+        //   Otherwise, append the value of current node's data IDL attribute, escaped as
+        //   described below."
+        NodeKind::Text(ref s) => {
+            let raw = match node.borrow().parent().upgrade() {
+                Some(parent) => match parent.borrow().get_element() {
+                    Some(e) => RAW_TEXT_PARENTS.contains(&e.local_name().as_str()),
+                    None => false,
+                },
+                None => false,
+            };
+            if raw {
+                html.push_str(s);
+            } else {
+                html.push_str(&escape_string(s, false));
             }
+        }
+        // "If current node is a Comment
+        //   Append the literal string "<!--" (U+003C LESS-THAN SIGN, U+0021 EXCLAMATION MARK,
+        //   U+002D HYPHEN-MINUS, U+002D HYPHEN-MINUS), followed by the value of current node's
+        //   data IDL attribute, followed by the literal string "-->" (U+002D HYPHEN-MINUS,
+        //   U+002D HYPHEN-MINUS, U+003E GREATER-THAN SIGN)."
+        NodeKind::Comment(ref s) => {
+            html.push_str("<!--");
+            html.push_str(s);
+            html.push_str("-->");
+        }
+    }
+}
 
-            dom_to_html_internal(&n.borrow().next_sibling(), html);
+/// https://html.spec.whatwg.org/multipage/parsing.html#escapingString
+fn escape_string(s: &str, attribute_mode: bool) -> String {
+    let mut escaped = String::new();
+    for c in s.chars() {
+        match c {
+            // "1. Replace any occurrence of the "&" character by the string "&amp;"."
+            '&' => escaped.push_str("&amp;"),
+            // "2. Replace any occurrences of the U+00A0 NO-BREAK SPACE character by the string
+            // "&nbsp;"."
+            '\u{A0}' => escaped.push_str("&nbsp;"),
+            // "3. Replace any occurrences of the "<" character by the string "&lt;".
+            // 4. Replace any occurrences of the ">" character by the string "&gt;"."
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            // "5. If the algorithm was invoked in the attribute mode, then replace any occurrences
+            // of the """ character by the string "&quot;"."
+            '"' if attribute_mode => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
         }
-        None => (),
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::dom::api::get_element_by_id;
+    use crate::renderer::test_util::create_window;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_serialize() {
+        let window = create_window("<!doctype html><p id=target class=\"a b\" title='\"x\"'>1 &lt; 2 &amp;&amp; a&nbsp;b<br><img src=a.png><!--c--></p><script>if (1 < 2) {}</script>");
+        let document = window.borrow().document();
+
+        assert_eq!(
+            "<!DOCTYPE html><html><head></head><body><p id=\"target\" class=\"a b\" title=\"&quot;x&quot;\">1 &lt; 2 &amp;&amp; a&nbsp;b<br><img src=\"a.png\"><!--c--></p><script>if (1 < 2) {}</script></body></html>".to_string(),
+            serialize_node(&document)
+        );
+
+        let target =
+            get_element_by_id(Some(document), &"target".to_string()).expect("target should exist");
+        assert_eq!(
+            "1 &lt; 2 &amp;&amp; a&nbsp;b<br><img src=\"a.png\"><!--c-->".to_string(),
+            serialize_children(&target)
+        );
     }
 }
@@ -1,4 +1,5 @@
 pub mod attribute;
+pub mod entities;
 pub mod html_builder;
 pub mod parser;
 pub mod token;
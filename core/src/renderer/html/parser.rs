@@ -2,6 +2,9 @@
 //! https://html.spec.whatwg.org/multipage/parsing.html#tree-construction
 
 use crate::browser::Browser;
+use crate::renderer::dom::mutation::{insert, remove};
+use crate::renderer::dom::node::DocumentMode;
+use crate::renderer::dom::node::DocumentType;
 use crate::renderer::dom::node::Element;
 use crate::renderer::dom::node::ElementKind;
 use crate::renderer::dom::node::Node;
@@ -9,7 +12,8 @@ use crate::renderer::dom::node::NodeKind;
 use crate::renderer::dom::window::Window;
 use crate::renderer::html::attribute::Attribute;
 use crate::renderer::html::token::{HtmlToken, HtmlTokenizer, State};
-use crate::utils::console_warning;
+use crate::renderer::source::SourceRange;
+use crate::utils::console_debug;
 use alloc::format;
 use alloc::rc::{Rc, Weak};
 use alloc::string::String;
@@ -27,10 +31,28 @@ pub enum InsertionMode {
     AfterHead,
     InBody,
     Text,
+    InTable,
+    InTableText,
+    InCaption,
+    InColumnGroup,
+    InTableBody,
+    InRow,
+    InCell,
+    InSelect,
     AfterBody,
     AfterAfterBody,
 }
 
+/// An entry in the list of active formatting elements.
+/// https://html.spec.whatwg.org/multipage/parsing.html#list-of-active-formatting-elements
+#[derive(Debug, Clone)]
+enum ActiveFormattingElement {
+    /// A marker inserted when entering a td, th or caption element to prevent formatting from
+    /// "leaking" into it.
+    Marker,
+    Element(Rc<RefCell<Node>>),
+}
+
 #[derive(Debug, Clone)]
 pub struct HtmlParser {
     browser: Weak<RefCell<Browser>>,
@@ -40,21 +62,109 @@ pub struct HtmlParser {
     original_insertion_mode: InsertionMode,
     /// https://html.spec.whatwg.org/multipage/parsing.html#the-stack-of-open-elements
     stack_of_open_elements: Vec<Rc<RefCell<Node>>>,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#list-of-active-formatting-elements
+    active_formatting_elements: Vec<ActiveFormattingElement>,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#form-element-pointer
+    form_element_pointer: Option<Rc<RefCell<Node>>>,
+    /// The context element given to the fragment parsing algorithm. None unless the parser parses
+    /// a fragment.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#concept-frag-parse-context
+    context_element: Option<Rc<RefCell<Node>>>,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#foster-parent
+    foster_parenting: bool,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#concept-pending-table-char-tokens
+    pending_table_character_tokens: Vec<char>,
     t: HtmlTokenizer,
+    /// The token being processed.
+    current_token: Option<HtmlToken>,
+    /// Elements which may be closed by a recent end tag, with the range of the end tag. The range
+    /// of an element is extended to the end tag once it's popped from the stack of open elements.
+    /// The number is how many tokens have been fetched since the end tag.
+    pending_end_tags: Vec<(Rc<RefCell<Node>>, SourceRange, usize)>,
 }
 
 impl HtmlParser {
     pub fn new(browser: Weak<RefCell<Browser>>, t: HtmlTokenizer) -> Self {
+        let window = Rc::new(RefCell::new(Window::new(browser.clone())));
+        window
+            .borrow()
+            .document()
+            .borrow_mut()
+            .set_window(Rc::downgrade(&window));
+
         Self {
-            browser: browser.clone(),
-            window: Rc::new(RefCell::new(Window::new(browser))),
+            browser,
+            window,
             mode: InsertionMode::Initial,
             original_insertion_mode: InsertionMode::Initial,
             stack_of_open_elements: Vec::new(),
+            active_formatting_elements: Vec::new(),
+            form_element_pointer: None,
+            context_element: None,
+            foster_parenting: false,
+            pending_table_character_tokens: Vec::new(),
             t,
+            current_token: None,
+            pending_end_tags: Vec::new(),
+        }
+    }
+
+    /// Fetches a next token from the tokenizer, and records the source range of elements closed
+    /// by previous end tags.
+    fn next_token(&mut self) -> Option<HtmlToken> {
+        self.close_pending_end_tags();
+
+        let token = self.t.next();
+        if let Some(HtmlToken::EndTag { ref tag }) = token {
+            let closed = self.stack_of_open_elements.iter().rev().find(|node| {
+                node.borrow()
+                    .get_element()
+                    .is_some_and(|e| e.local_name() == *tag)
+            });
+            if let Some(node) = closed {
+                self.pending_end_tags
+                    .push((node.clone(), self.t.token_range(), 0));
+            }
+        }
+        self.current_token = token.clone();
+        token
+    }
+
+    /// Extends the source range of elements to their end tags if the elements have been popped
+    /// from the stack of open elements. An end tag which doesn't close an element soon is ignored.
+    fn close_pending_end_tags(&mut self) {
+        let pending_end_tags = core::mem::take(&mut self.pending_end_tags);
+        for (node, end_tag_range, age) in pending_end_tags {
+            if !self
+                .stack_of_open_elements
+                .iter()
+                .any(|n| Rc::ptr_eq(n, &node))
+            {
+                let range = node.borrow().source_range();
+                if let Some(range) = range {
+                    node.borrow_mut()
+                        .set_source_range(Some(range.extend_to(&end_tag_range)));
+                }
+            } else if age < 2 {
+                self.pending_end_tags.push((node, end_tag_range, age + 1));
+            }
+        }
+    }
+
+    /// Returns the source range of the current token if it's a start tag whose tag name is `tag`.
+    fn start_tag_range(&self, tag: &str) -> Option<SourceRange> {
+        match self.current_token {
+            Some(HtmlToken::StartTag { tag: ref t, .. }) if t == tag => Some(self.t.token_range()),
+            _ => None,
         }
     }
 
+    /// Reports a tree construction error at the current position of the tokenizer.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parse-errors
+    fn parse_error(&self, code: &str) {
+        self.t.parse_error(code);
+    }
+
     /// Creates a char node.
     fn create_char(&self, c: char) -> Node {
         let mut s = String::new();
@@ -67,17 +177,19 @@ impl HtmlParser {
         Node::new(NodeKind::Element(Element::new(tag, attributes)))
     }
 
-    /// Creates an element node for the token and insert it to the appropriate place for inserting
-    /// a node. Put the new node in the stack of open elements.
-    /// https://html.spec.whatwg.org/multipage/parsing.html#insert-a-foreign-element
-    fn insert_element(&mut self, tag: &str, attributes: Vec<Attribute>) {
-        let window = self.window.borrow();
-        let current = match self.stack_of_open_elements.last() {
+    /// Returns the current node, which is the bottommost node in the stack of open elements, or
+    /// the Document if the stack is empty.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#current-node
+    fn current_node(&self) -> Rc<RefCell<Node>> {
+        match self.stack_of_open_elements.last() {
             Some(n) => n.clone(),
-            None => window.document(),
-        };
+            None => self.window.borrow().document(),
+        }
+    }
 
-        let node = Rc::new(RefCell::new(self.create_element(tag, attributes)));
+    /// Appends `node` to the end of the children of `parent`.
+    fn append_child(parent: &Rc<RefCell<Node>>, node: &Rc<RefCell<Node>>) {
+        let current = parent;
 
         if current.borrow().first_child().is_some() {
             let mut last_sibling = current.borrow().first_child();
@@ -106,26 +218,133 @@ impl HtmlParser {
             current.borrow_mut().set_first_child(Some(node.clone()));
         }
 
-        current.borrow_mut().set_last_child(Rc::downgrade(&node));
-        node.borrow_mut().set_parent(Rc::downgrade(&current));
+        current.borrow_mut().set_last_child(Rc::downgrade(node));
+        node.borrow_mut().set_parent(Rc::downgrade(current));
+    }
+
+    /// Inserts `node` into `parent` immediately before `child`. If `child` is None, `node` is
+    /// appended to the end of the children of `parent`.
+    fn insert_before(
+        parent: &Rc<RefCell<Node>>,
+        node: &Rc<RefCell<Node>>,
+        child: Option<&Rc<RefCell<Node>>>,
+    ) {
+        insert(node, parent, child);
+    }
+
+    /// Removes `node` from its parent. Nothing happens if `node` doesn't have a parent.
+    /// https://dom.spec.whatwg.org/#concept-node-remove
+    fn remove_from_parent(node: &Rc<RefCell<Node>>) {
+        remove(node);
+    }
+
+    /// Returns the parent and the reference child (a new node is inserted before it, or appended
+    /// if it's None) of the appropriate place for inserting a node.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#appropriate-place-for-inserting-a-node
+    fn appropriate_place_for_inserting(
+        &self,
+        override_target: Option<Rc<RefCell<Node>>>,
+    ) -> (Rc<RefCell<Node>>, Option<Rc<RefCell<Node>>>) {
+        // "1. If there was an override target specified, then let target be the override target.
+        // Otherwise, let target be the current node."
+        let target = match override_target {
+            Some(t) => t,
+            None => self.current_node(),
+        };
+
+        // "2. Determine the adjusted insertion location using the first matching steps from the
+        // following list:
+        //   If foster parenting is enabled and target is a table, tbody, tfoot, thead, or tr
+        //   element"
+        let is_table_part = matches!(
+            target.borrow().element_kind(),
+            Some(ElementKind::Table)
+                | Some(ElementKind::Tbody)
+                | Some(ElementKind::Tfoot)
+                | Some(ElementKind::Thead)
+                | Some(ElementKind::Tr)
+        );
+        if !self.foster_parenting || !is_table_part {
+            //   "Otherwise: Let adjusted insertion location be inside target, after its last
+            //   child (if any)."
+            return (target, None);
+        }
+
+        // "1. Let last table be the last table element in the stack of open elements, if any."
+        let last_table = self
+            .stack_of_open_elements
+            .iter()
+            .rposition(|n| n.borrow().element_kind() == Some(ElementKind::Table));
+        match last_table {
+            Some(i) => {
+                let table = self.stack_of_open_elements[i].clone();
+                // "4. If last table has a parent node, then let adjusted insertion location be
+                // inside last table's parent node, immediately before last table, and abort these
+                // steps."
+                if let Some(parent) = table.borrow().parent().upgrade() {
+                    return (parent, Some(table.clone()));
+                }
+                // "5. Let previous element be the element immediately above last table in the
+                // stack of open elements."
+                // "6. Let adjusted insertion location be inside previous element, after its last
+                // child (if any)."
+                (
+                    self.stack_of_open_elements[i.saturating_sub(1)].clone(),
+                    None,
+                )
+            }
+            // "3. If there is no last table, then let adjusted insertion location be inside the
+            // first element in the stack of open elements (the html element), after its last
+            // child (if any), and abort these steps. (fragment case)"
+            None => match self.stack_of_open_elements.first() {
+                Some(html) => (html.clone(), None),
+                None => (target, None),
+            },
+        }
+    }
 
+    /// Creates an element node for the token and insert it to the appropriate place for inserting
+    /// a node. Put the new node in the stack of open elements.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#insert-a-foreign-element
+    fn insert_element(&mut self, tag: &str, attributes: Vec<Attribute>) {
+        let (parent, child) = self.appropriate_place_for_inserting(None);
+        let node = Rc::new(RefCell::new(self.create_element(tag, attributes)));
+        node.borrow_mut()
+            .set_source_range(self.start_tag_range(tag));
+        Self::insert_before(&parent, &node, child.as_ref());
         self.stack_of_open_elements.push(node);
     }
 
     /// https://html.spec.whatwg.org/multipage/parsing.html#insert-a-character
     fn insert_char(&mut self, c: char) {
-        let current = match self.stack_of_open_elements.last() {
-            Some(n) => n.clone(),
-            None => return,
-        };
-
-        // When the current node is Text, add a character to the current node.
-        // Do not access by current.borrow().kind(), otherwise, you can't add a next char to a
-        // correct node.
-        if let NodeKind::Text(ref mut s) = current.borrow_mut().kind {
-            s.push(c);
+        if self.stack_of_open_elements.is_empty() {
             return;
         }
+        let (parent, child) = self.appropriate_place_for_inserting(None);
+
+        // "If there is a Text node immediately before the adjusted insertion location, then
+        // append data to that Text node's data."
+        // Do not access by kind(), otherwise, you can't add a next char to a correct node.
+        let previous = match child {
+            Some(ref c) => c.borrow().previous_sibling().upgrade(),
+            None => parent.borrow().last_child().upgrade(),
+        };
+        // Characters in the pending table character tokens are inserted after the token which
+        // produced them, so their ranges are unknown.
+        let range = match self.current_token {
+            Some(HtmlToken::Char(_)) => Some(self.t.token_range()),
+            _ => None,
+        };
+        if let Some(last) = previous {
+            let mut last = last.borrow_mut();
+            if let NodeKind::Text(ref mut s) = last.kind {
+                s.push(c);
+                if let (Some(current), Some(range)) = (last.source_range(), range) {
+                    last.set_source_range(Some(current.extend_to(&range)));
+                }
+                return;
+            }
+        }
 
         // do not create a Text node if new char is '\n' or ' '
         if c == '\n' || c == ' ' {
@@ -133,23 +352,24 @@ impl HtmlParser {
         }
 
         let node = Rc::new(RefCell::new(self.create_char(c)));
+        node.borrow_mut().set_source_range(range);
+        Self::insert_before(&parent, &node, child.as_ref());
+    }
 
-        if current.borrow().first_child().is_some() {
-            // TODO: Probably impossible to reach here. `first_child` of the current node is always None.
-            current
-                .borrow()
-                .first_child()
-                .unwrap()
-                .borrow_mut()
-                .set_next_sibling(Some(node.clone()));
-        } else {
-            current.borrow_mut().set_first_child(Some(node.clone()));
-        }
-
-        current.borrow_mut().set_last_child(Rc::downgrade(&node));
-        node.borrow_mut().set_parent(Rc::downgrade(&current));
-
-        self.stack_of_open_elements.push(node);
+    /// Inserts a Comment node with `data`. If `parent` is None, the comment is inserted to the
+    /// current node.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#insert-a-comment
+    fn insert_comment(&mut self, data: &str, parent: Option<Rc<RefCell<Node>>>) {
+        let parent = match parent {
+            Some(p) => p,
+            None => self.current_node(),
+        };
+        let node = Rc::new(RefCell::new(Node::new(NodeKind::Comment(String::from(
+            data,
+        )))));
+        node.borrow_mut()
+            .set_source_range(Some(self.t.token_range()));
+        Self::append_child(&parent, &node);
     }
 
     /// Returns true if the current node's kind is same as NodeKind::Element::<element_kind>.
@@ -187,6 +407,41 @@ impl HtmlParser {
         }
     }
 
+    /// Runs the steps for "any other end tag" in the "in body" insertion mode. Pops nodes until
+    /// an element whose tag name is `tag` is popped, unless an element in the special category
+    /// comes first.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inbody
+    fn close_element_with_tag_name(&mut self, tag: &str) {
+        // "1. Initialize node to be the current node (the bottommost node of the stack)."
+        for i in (0..self.stack_of_open_elements.len()).rev() {
+            let local_name = match self.stack_of_open_elements[i].borrow().get_element() {
+                Some(e) => e.local_name(),
+                None => continue,
+            };
+
+            // "2. Loop: If node is an HTML element with the same tag name as the token, then:
+            //   1. Generate implied end tags, except for HTML elements with the same tag name as
+            //      the token.
+            //   2. If node is not the current node, then this is a parse error.
+            //   3. Pop all the nodes from the current node up to node, including node, then stop
+            //      these steps."
+            if local_name == tag {
+                self.stack_of_open_elements.truncate(i);
+                return;
+            }
+
+            // "3. Otherwise, if node is in the special category, then this is a parse error;
+            // ignore the token, and return."
+            if is_special(&local_name) {
+                self.parse_error("unexpected-end-tag");
+                return;
+            }
+
+            // "4. Set node to the previous entry in the stack of open elements."
+            // "5. Return to the step labeled loop."
+        }
+    }
+
     /// Returns true if the stack of open elements has NodeKind::Element::<element_kind> node.
     fn contain_in_stack(&mut self, element_kind: ElementKind) -> bool {
         for i in (0..self.stack_of_open_elements.len()).rev() {
@@ -198,105 +453,1073 @@ impl HtmlParser {
         false
     }
 
-    /// https://html.spec.whatwg.org/multipage/parsing.html#tree-construction
-    pub fn construct_tree(&mut self) -> Rc<RefCell<Window>> {
-        let mut token = self.t.next();
+    /// Returns true if the stack of open elements has `element_kind` in the specific scope whose
+    /// element types are the ones `is_boundary` returns true for.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#has-an-element-in-the-specific-scope
+    fn has_element_in_specific_scope(
+        &self,
+        element_kind: ElementKind,
+        is_boundary: fn(&str) -> bool,
+    ) -> bool {
+        // "1. Initialize node to be the current node (the bottommost node of the stack)."
+        for node in self.stack_of_open_elements.iter().rev() {
+            let element = match node.borrow().get_element() {
+                Some(e) => e,
+                None => continue,
+            };
+            // "2. If node is the target node, terminate in a match state."
+            if element.kind() == element_kind {
+                return true;
+            }
+            // "3. Otherwise, if node is one of the element types in list, terminate in a failure
+            // state."
+            if is_boundary(&element.local_name()) {
+                return false;
+            }
+            // "4. Otherwise, set node to the previous entry in the stack of open elements and
+            // return to step 2."
+        }
 
-        while token.is_some() {
-            match self.mode {
-                // https://html.spec.whatwg.org/multipage/parsing.html#the-initial-insertion-mode
-                InsertionMode::Initial => {
-                    // Ignore <!doctype html>.
-                    // <!doctype html> is generated as a Char token.
-                    if let Some(HtmlToken::Char(_)) = token {
-                        token = self.t.next();
-                        continue;
+        false
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#has-an-element-in-scope
+    fn has_element_in_scope(&self, element_kind: ElementKind) -> bool {
+        self.has_element_in_specific_scope(element_kind, is_scope_boundary)
+    }
+
+    /// Returns true if `node` itself is in scope. This is used for elements that don't have their
+    /// own ElementKind such as formatting elements.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#has-an-element-in-scope
+    fn has_node_in_scope(&self, node: &Rc<RefCell<Node>>) -> bool {
+        for n in self.stack_of_open_elements.iter().rev() {
+            if Rc::ptr_eq(n, node) {
+                return true;
+            }
+            if n.borrow()
+                .get_element()
+                .is_some_and(|e| is_scope_boundary(&e.local_name()))
+            {
+                return false;
+            }
+        }
+        false
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#has-an-element-in-list-item-scope
+    fn has_element_in_list_item_scope(&self, element_kind: ElementKind) -> bool {
+        self.has_element_in_specific_scope(element_kind, |name| {
+            is_scope_boundary(name) || name == "ol" || name == "ul"
+        })
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#has-an-element-in-button-scope
+    fn has_element_in_button_scope(&self, element_kind: ElementKind) -> bool {
+        self.has_element_in_specific_scope(element_kind, |name| {
+            is_scope_boundary(name) || name == "button"
+        })
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#has-an-element-in-table-scope
+    fn has_element_in_table_scope(&self, element_kind: ElementKind) -> bool {
+        self.has_element_in_specific_scope(element_kind, |name| {
+            name == "html" || name == "table" || name == "template"
+        })
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#has-an-element-in-select-scope
+    fn has_element_in_select_scope(&self, element_kind: ElementKind) -> bool {
+        // "the list of element types consisting of the following, but consisting of all element
+        // types except the following: optgroup in the HTML namespace, option in the HTML
+        // namespace"
+        self.has_element_in_specific_scope(element_kind, |name| {
+            name != "optgroup" && name != "option"
+        })
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#generate-implied-end-tags
+    fn generate_implied_end_tags(&mut self) {
+        self.generate_implied_end_tags_except(None);
+    }
+
+    /// Generates implied end tags, except for elements whose tag name is `exception`.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#generate-implied-end-tags
+    fn generate_implied_end_tags_except(&mut self, exception: Option<&str>) {
+        // "while the current node is a dd element, a dt element, an li element, an optgroup
+        // element, an option element, a p element, an rb element, an rp element, an rt element, or
+        // an rtc element, the UA must pop the current node off the stack of open elements."
+        //
+        // "If a step requires the UA to generate implied end tags but lists an element to exclude
+        // from the process, then the UA must perform the above steps as if that element was not
+        // in the above list."
+        loop {
+            let local_name = match self.current_node().borrow().get_element() {
+                Some(e) => e.local_name(),
+                None => return,
+            };
+            if exception == Some(local_name.as_str()) {
+                return;
+            }
+            if !matches!(
+                local_name.as_str(),
+                "dd" | "dt" | "li" | "optgroup" | "option" | "p" | "rb" | "rp" | "rt" | "rtc"
+            ) {
+                return;
+            }
+            self.stack_of_open_elements.pop();
+        }
+    }
+
+    /// Pops elements until the current node is one of `element_kinds` or the html element.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#clear-the-stack-back-to-a-table-context
+    /// https://html.spec.whatwg.org/multipage/parsing.html#clear-the-stack-back-to-a-table-body-context
+    /// https://html.spec.whatwg.org/multipage/parsing.html#clear-the-stack-back-to-a-table-row-context
+    fn clear_stack_back_to(&mut self, element_kinds: &[ElementKind]) {
+        while let Some(kind) = self.current_node().borrow().element_kind() {
+            if kind == ElementKind::Html || element_kinds.contains(&kind) {
+                return;
+            }
+            self.stack_of_open_elements.pop();
+        }
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#close-the-cell
+    fn close_cell(&mut self) {
+        // "1. Generate implied end tags."
+        self.generate_implied_end_tags();
+        // "3. Pop elements from the stack of open elements stack until a td element or a th
+        // element has been popped from the stack."
+        while let Some(n) = self.stack_of_open_elements.pop() {
+            let kind = n.borrow().element_kind();
+            if kind == Some(ElementKind::Td) || kind == Some(ElementKind::Th) {
+                break;
+            }
+        }
+        // "4. Clear the list of active formatting elements up to the last marker."
+        self.clear_active_formatting_elements_to_last_marker();
+        // "5. Switch the insertion mode to "in row"."
+        self.mode = InsertionMode::InRow;
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#close-a-p-element
+    fn close_p_element(&mut self) {
+        // "Generate implied end tags, except for p elements. If the current node is not a p
+        // element, then this is a parse error. Pop elements from the stack of open elements until
+        // a p element has been popped from the stack."
+        self.generate_implied_end_tags_except(Some("p"));
+        self.pop_until(ElementKind::P);
+    }
+
+    /// Returns the index of `node` in the stack of open elements.
+    fn position_in_stack(&self, node: &Rc<RefCell<Node>>) -> Option<usize> {
+        self.stack_of_open_elements
+            .iter()
+            .position(|n| Rc::ptr_eq(n, node))
+    }
+
+    /// Returns the index of `node` in the list of active formatting elements.
+    fn position_in_active_formatting_elements(&self, node: &Rc<RefCell<Node>>) -> Option<usize> {
+        self.active_formatting_elements
+            .iter()
+            .position(|entry| match entry {
+                ActiveFormattingElement::Element(n) => Rc::ptr_eq(n, node),
+                ActiveFormattingElement::Marker => false,
+            })
+    }
+
+    /// Returns the last element whose tag name is `tag` between the end of the list of active
+    /// formatting elements and the last marker on the list (or the start of the list if there is
+    /// no marker on the list).
+    fn active_formatting_element_after_last_marker(&self, tag: &str) -> Option<Rc<RefCell<Node>>> {
+        for entry in self.active_formatting_elements.iter().rev() {
+            match entry {
+                ActiveFormattingElement::Marker => return None,
+                ActiveFormattingElement::Element(n) => {
+                    if n.borrow()
+                        .get_element()
+                        .is_some_and(|e| e.local_name() == tag)
+                    {
+                        return Some(n.clone());
                     }
+                }
+            }
+        }
+        None
+    }
 
-                    self.mode = InsertionMode::BeforeHtml;
-                    continue;
+    /// Pushes `node` onto the list of active formatting elements.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#push-onto-the-list-of-active-formatting-elements
+    fn push_active_formatting_element(&mut self, node: Rc<RefCell<Node>>) {
+        // "1. If there are already three elements in the list of active formatting elements after
+        // the last marker, if any, or anywhere in the list if there are no markers, that have the
+        // same tag name, namespace, and attributes as element, then remove the earliest such
+        // element from the list of active formatting elements. For these purposes, the attributes
+        // must be compared as they were when the elements were created by the parser; two
+        // elements have the same attributes if all their parsed attributes can be paired such
+        // that the two attributes in each pair have identical names, namespaces, and values (the
+        // order of the attributes does not matter)."
+        let element = match node.borrow().get_element() {
+            Some(e) => e,
+            None => return,
+        };
+        let mut same_elements = Vec::new();
+        for (i, entry) in self.active_formatting_elements.iter().enumerate().rev() {
+            let n = match entry {
+                ActiveFormattingElement::Marker => break,
+                ActiveFormattingElement::Element(n) => n,
+            };
+            if let Some(e) = n.borrow().get_element() {
+                let attributes = e.attributes();
+                if e.local_name() == element.local_name()
+                    && attributes.len() == element.attributes().len()
+                    && attributes.iter().all(|a| element.attributes().contains(a))
+                {
+                    same_elements.push(i);
                 }
+            }
+        }
+        if same_elements.len() >= 3 {
+            if let Some(earliest) = same_elements.last() {
+                self.active_formatting_elements.remove(*earliest);
+            }
+        }
 
-                // https://html.spec.whatwg.org/multipage/parsing.html#the-before-html-insertion-mode
-                InsertionMode::BeforeHtml => {
-                    match token {
-                        Some(HtmlToken::Char(c)) => {
-                            if c == ' ' || c == '\n' {
-                                token = self.t.next();
-                                continue;
-                            }
-                        }
-                        Some(HtmlToken::StartTag {
-                            ref tag,
-                            self_closing: _,
-                            ref attributes,
-                        }) => {
-                            // A start tag whose tag name is "html"
-                            // Create an element for the token in the HTML namespace, with the Document
-                            // as the intended parent. Append it to the Document object. Put this
-                            // element in the stack of open elements.
-                            if tag == "html" {
-                                self.insert_element(tag, attributes.to_vec());
-                                self.mode = InsertionMode::BeforeHead;
-                                token = self.t.next();
-                                continue;
-                            }
-                        }
-                        Some(HtmlToken::EndTag { ref tag }) => {
-                            // Any other end tag
-                            // Parse error. Ignore the token.
-                            if tag != "head" || tag != "body" || tag != "html" || tag != "br" {
-                                // Ignore the token.
-                                token = self.t.next();
-                                continue;
-                            }
-                        }
-                        Some(HtmlToken::Eof) | None => {
-                            return self.window.clone();
-                        }
-                    }
-                    self.insert_element("html", Vec::new());
-                    self.mode = InsertionMode::BeforeHead;
-                    continue;
-                } // end of InsertionMode::BeforeHtml
+        // "2. Add element to the list of active formatting elements."
+        self.active_formatting_elements
+            .push(ActiveFormattingElement::Element(node));
+    }
 
-                // https://html.spec.whatwg.org/multipage/parsing.html#the-before-head-insertion-mode
-                InsertionMode::BeforeHead => {
-                    match token {
-                        Some(HtmlToken::Char(c)) => {
-                            if c == ' ' || c == '\n' {
-                                token = self.t.next();
-                                continue;
-                            }
-                        }
-                        Some(HtmlToken::StartTag {
-                            ref tag,
-                            self_closing: _,
-                            ref attributes,
-                        }) => {
-                            if tag == "head" {
-                                self.insert_element(tag, attributes.to_vec());
-                                self.mode = InsertionMode::InHead;
-                                token = self.t.next();
-                                continue;
-                            }
-                        }
-                        Some(HtmlToken::Eof) | None => {
-                            return self.window.clone();
-                        }
-                        _ => {}
+    /// Inserts a marker at the end of the list of active formatting elements.
+    fn insert_marker(&mut self) {
+        self.active_formatting_elements
+            .push(ActiveFormattingElement::Marker);
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#clear-the-list-of-active-formatting-elements-up-to-the-last-marker
+    fn clear_active_formatting_elements_to_last_marker(&mut self) {
+        // "1. Let entry be the last (most recently added) entry in the list of active formatting
+        // elements.
+        // 2. Remove entry from the list of active formatting elements.
+        // 3. If entry was a marker, then stop the algorithm at this point. The list has been
+        // cleared up to the last marker.
+        // 4. Go to step 1."
+        while let Some(entry) = self.active_formatting_elements.pop() {
+            if let ActiveFormattingElement::Marker = entry {
+                return;
+            }
+        }
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#reconstruct-the-active-formatting-elements
+    fn reconstruct_active_formatting_elements(&mut self) {
+        // "1. If there are no entries in the list of active formatting elements, then there is
+        // nothing to reconstruct; stop this algorithm."
+        //
+        // "2. If the last (most recently added) entry in the list of active formatting elements
+        // is a marker, or if it is an element that is in the stack of open elements, then there
+        // is nothing to reconstruct; stop this algorithm."
+        let is_open = |parser: &Self, entry: &ActiveFormattingElement| match entry {
+            ActiveFormattingElement::Marker => true,
+            ActiveFormattingElement::Element(n) => parser.position_in_stack(n).is_some(),
+        };
+        let mut i = match self.active_formatting_elements.last() {
+            Some(entry) if !is_open(self, entry) => self.active_formatting_elements.len() - 1,
+            _ => return,
+        };
+
+        // "3. Let entry be the last (most recently added) element in the list of active
+        // formatting elements.
+        // 4. Rewind: If there are no entries before entry in the list of active formatting
+        // elements, then jump to the step labeled create.
+        // 5. Let entry be the entry one earlier than entry in the list of active formatting
+        // elements.
+        // 6. If entry is neither a marker nor an element that is also in the stack of open
+        // elements, go to the step labeled rewind."
+        while i > 0 && !is_open(self, &self.active_formatting_elements[i - 1]) {
+            i -= 1;
+        }
+
+        // "7. Advance: Let entry be the element one later than entry in the list of active
+        // formatting elements.
+        // 8. Create: Insert an HTML element for the token for which the element entry was
+        // created, to obtain new element.
+        // 9. Replace the entry for entry in the list with an entry for new element.
+        // 10. If the entry for new element in the list of active formatting elements is not the
+        // last entry in the list, return to the step labeled advance."
+        for j in i..self.active_formatting_elements.len() {
+            let entry = match &self.active_formatting_elements[j] {
+                ActiveFormattingElement::Element(n) => Some(n.clone()),
+                ActiveFormattingElement::Marker => None,
+            };
+            if let Some(entry) = entry {
+                let e = entry
+                    .borrow()
+                    .get_element()
+                    .expect("a formatting element should be an element");
+                self.insert_element(&e.local_name(), e.attributes());
+                // The new element is created for the same token as the entry.
+                self.current_node()
+                    .borrow_mut()
+                    .set_source_range(entry.borrow().source_range());
+                self.active_formatting_elements[j] =
+                    ActiveFormattingElement::Element(self.current_node());
+            }
+        }
+    }
+
+    /// Creates a new element for the same token as `node`.
+    fn clone_element(&self, node: &Rc<RefCell<Node>>) -> Rc<RefCell<Node>> {
+        let element = node
+            .borrow()
+            .get_element()
+            .expect("a formatting element should be an element");
+        let clone = self.create_element(&element.local_name(), element.attributes());
+        let clone = Rc::new(RefCell::new(clone));
+        clone
+            .borrow_mut()
+            .set_source_range(node.borrow().source_range());
+        clone
+    }
+
+    /// Runs the adoption agency algorithm for an end tag whose tag name is `subject`. Returns
+    /// false if the token should be processed as "any other end tag" instead.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#adoption-agency-algorithm
+    fn run_adoption_agency_algorithm(&mut self, subject: &str) -> bool {
+        // "2. If the current node is an HTML element whose tag name is subject, and the current
+        // node is not in the list of active formatting elements, then pop the current node off
+        // the stack of open elements and return."
+        let current = self.current_node();
+        if current
+            .borrow()
+            .get_element()
+            .is_some_and(|e| e.local_name() == subject)
+            && self
+                .position_in_active_formatting_elements(&current)
+                .is_none()
+        {
+            self.stack_of_open_elements.pop();
+            return true;
+        }
+
+        // "3. Let outer loop counter be 0.
+        // 4. While true:
+        //   1. If outer loop counter is greater than or equal to 8, then return.
+        //   2. Increment outer loop counter by 1."
+        for _ in 0..8 {
+            // "3. Let formatting element be the last element in the list of active formatting
+            // elements that: is between the end of the list and the last marker in the list, if
+            // any, or the start of the list otherwise, and has the tag name subject.
+            // If there is no such element, then return and instead act as described in the "any
+            // other end tag" entry above."
+            let formatting_element = match self.active_formatting_element_after_last_marker(subject)
+            {
+                Some(e) => e,
+                None => return false,
+            };
+
+            // "4. If formatting element is not in the stack of open elements, then this is a
+            // parse error; remove the element from the list, and return."
+            let formatting_element_index = match self.position_in_stack(&formatting_element) {
+                Some(i) => i,
+                None => {
+                    if let Some(i) =
+                        self.position_in_active_formatting_elements(&formatting_element)
+                    {
+                        self.active_formatting_elements.remove(i);
                     }
-                    self.insert_element("head", Vec::new());
-                    self.mode = InsertionMode::InHead;
-                    continue;
-                } // end of InsertionMode::BeforeHead
+                    return true;
+                }
+            };
+
+            // "5. If formatting element is in the stack of open elements, but the element is not
+            // in scope, then this is a parse error; return."
+            if !self.has_node_in_scope(&formatting_element) {
+                return true;
+            }
+
+            // "6. If formatting element is not the current node, this is a parse error. (But do
+            // not return.)"
+            //
+            // "7. Let furthest block be the topmost node in the stack of open elements that is
+            // lower in the stack than formatting element, and is an element in the special
+            // category. There might not be one."
+            let furthest_block = self.stack_of_open_elements[formatting_element_index + 1..]
+                .iter()
+                .find(|n| {
+                    n.borrow()
+                        .get_element()
+                        .is_some_and(|e| is_special(&e.local_name()))
+                })
+                .cloned();
+
+            // "8. If there is no furthest block, then the UA must first pop all the nodes from the
+            // bottom of the stack of open elements, from the current node up to and including
+            // formatting element, then remove formatting element from the list of active
+            // formatting elements, and finally return."
+            let furthest_block = match furthest_block {
+                Some(b) => b,
+                None => {
+                    self.stack_of_open_elements
+                        .truncate(formatting_element_index);
+                    if let Some(i) =
+                        self.position_in_active_formatting_elements(&formatting_element)
+                    {
+                        self.active_formatting_elements.remove(i);
+                    }
+                    return true;
+                }
+            };
+
+            // "9. Let common ancestor be the element immediately above formatting element in the
+            // stack of open elements."
+            let common_ancestor = self.stack_of_open_elements[formatting_element_index - 1].clone();
+
+            // "10. Let a bookmark note the position of formatting element in the list of active
+            // formatting elements relative to the elements on either side of it in the list."
+            let mut bookmark = self
+                .position_in_active_formatting_elements(&formatting_element)
+                .expect("formatting element should be in the list");
+
+            // "11. Let node and last node be furthest block."
+            let mut node_index = self
+                .position_in_stack(&furthest_block)
+                .expect("furthest block should be in the stack");
+            let mut last_node = furthest_block.clone();
+
+            // "12. Let inner loop counter be 0.
+            // 13. While true:"
+            let mut inner_loop_counter = 0;
+            loop {
+                // "1. Increment inner loop counter by 1.
+                // 2. Let node be the element immediately above node in the stack of open
+                // elements, or if node is no longer in the stack of open elements (e.g. because
+                // it got removed by this algorithm), the element that was immediately above node
+                // in the stack of open elements before node was removed."
+                inner_loop_counter += 1;
+                node_index -= 1;
+                let node = self.stack_of_open_elements[node_index].clone();
+
+                // "3. If node is formatting element, then break."
+                if Rc::ptr_eq(&node, &formatting_element) {
+                    break;
+                }
+
+                // "4. If inner loop counter is greater than 3 and node is in the list of active
+                // formatting elements, then remove node from the list of active formatting
+                // elements."
+                let mut list_index = self.position_in_active_formatting_elements(&node);
+                if inner_loop_counter > 3 {
+                    if let Some(i) = list_index {
+                        self.active_formatting_elements.remove(i);
+                        if i < bookmark {
+                            bookmark -= 1;
+                        }
+                        list_index = None;
+                    }
+                }
+
+                // "5. If node is not in the list of active formatting elements, then remove node
+                // from the stack of open elements and continue."
+                let list_index = match list_index {
+                    Some(i) => i,
+                    None => {
+                        self.stack_of_open_elements.remove(node_index);
+                        continue;
+                    }
+                };
+
+                // "6. Create an element for the token for which the element node was created, in
+                // the HTML namespace, with common ancestor as the intended parent; replace the
+                // entry for node in the list of active formatting elements with an entry for the
+                // new element, replace the entry for node in the stack of open elements with an
+                // entry for the new element, and let node be the new element."
+                let node = self.clone_element(&node);
+                self.active_formatting_elements[list_index] =
+                    ActiveFormattingElement::Element(node.clone());
+                self.stack_of_open_elements[node_index] = node.clone();
+
+                // "7. If last node is furthest block, then move the aforementioned bookmark to be
+                // immediately after the new node in the list of active formatting elements."
+                if Rc::ptr_eq(&last_node, &furthest_block) {
+                    bookmark = list_index + 1;
+                }
+
+                // "8. Append last node to node."
+                Self::remove_from_parent(&last_node);
+                Self::append_child(&node, &last_node);
+
+                // "9. Set last node to node."
+                last_node = node;
+            }
+
+            // "14. Insert whatever last node ended up being in the appropriate place for
+            // inserting a node, but using common ancestor as the override target."
+            Self::remove_from_parent(&last_node);
+            let (parent, child) = self.appropriate_place_for_inserting(Some(common_ancestor));
+            Self::insert_before(&parent, &last_node, child.as_ref());
+
+            // "15. Create an element for the token for which formatting element was created, in
+            // the HTML namespace, with furthest block as the intended parent."
+            let new_element = self.clone_element(&formatting_element);
+
+            // "16. Take all of the child nodes of furthest block and append them to the element
+            // created in the last step."
+            let mut child = furthest_block.borrow().first_child();
+            while let Some(c) = child {
+                child = c.borrow().next_sibling();
+                Self::remove_from_parent(&c);
+                Self::append_child(&new_element, &c);
+            }
+
+            // "17. Append that new element to furthest block."
+            Self::append_child(&furthest_block, &new_element);
+
+            // "18. Remove formatting element from the list of active formatting elements, and
+            // insert the new element into the list of active formatting elements at the position
+            // of the aforementioned bookmark."
+            if let Some(i) = self.position_in_active_formatting_elements(&formatting_element) {
+                self.active_formatting_elements.remove(i);
+                if i < bookmark {
+                    bookmark -= 1;
+                }
+            }
+            self.active_formatting_elements.insert(
+                bookmark,
+                ActiveFormattingElement::Element(new_element.clone()),
+            );
+
+            // "19. Remove formatting element from the stack of open elements, and insert the new
+            // element into the stack of open elements immediately below the position of furthest
+            // block in that stack."
+            if let Some(i) = self.position_in_stack(&formatting_element) {
+                self.stack_of_open_elements.remove(i);
+            }
+            let furthest_block_index = self
+                .position_in_stack(&furthest_block)
+                .expect("furthest block should be in the stack");
+            self.stack_of_open_elements
+                .insert(furthest_block_index + 1, new_element);
+        }
+        true
+    }
+
+    /// Runs the steps shared by a "table" end tag and table-related start tags in the "in table
+    /// body" insertion mode. Returns false if the token should be ignored; otherwise, the token
+    /// should be reprocessed in the "in table" insertion mode.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-intbody
+    fn close_table_body(&mut self) -> bool {
+        // "If the stack of open elements does not have a tbody, thead, or tfoot element in table
+        // scope, this is a parse error; ignore the token."
+        if !self.has_element_in_table_scope(ElementKind::Tbody)
+            && !self.has_element_in_table_scope(ElementKind::Thead)
+            && !self.has_element_in_table_scope(ElementKind::Tfoot)
+        {
+            return false;
+        }
+        // "Otherwise:
+        //   Clear the stack back to a table body context.
+        //   Pop the current node from the stack of open elements. Switch the insertion mode to
+        //   "in table".
+        //   Reprocess the token."
+        self.clear_stack_back_to(&TABLE_BODY_CONTEXT);
+        self.stack_of_open_elements.pop();
+        self.mode = InsertionMode::InTable;
+        true
+    }
+
+    /// Runs the steps for a "tr" end tag in the "in row" insertion mode. Returns false if the
+    /// token should be ignored; otherwise, the current insertion mode becomes "in table body".
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-intr
+    fn close_table_row(&mut self) -> bool {
+        // "If the stack of open elements does not have a tr element in table scope, this is a
+        // parse error; ignore the token."
+        if !self.has_element_in_table_scope(ElementKind::Tr) {
+            return false;
+        }
+        // "Otherwise:
+        //   Clear the stack back to a table row context.
+        //   Pop the current node (which will be a tr element) from the stack of open elements.
+        //   Switch the insertion mode to "in table body"."
+        self.clear_stack_back_to(&TABLE_ROW_CONTEXT);
+        self.stack_of_open_elements.pop();
+        self.mode = InsertionMode::InTableBody;
+        true
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#reset-the-insertion-mode-appropriately
+    fn reset_insertion_mode_appropriately(&mut self) {
+        // "3. Loop: If node is the first node in the stack of open elements, then set last to
+        // true."
+        for i in (0..self.stack_of_open_elements.len()).rev() {
+            let last = i == 0;
+            // "If node is the first node in the stack of open elements ... and the parser was
+            // created as part of the HTML fragment parsing algorithm (fragment case), set node to
+            // the context element passed to that algorithm."
+            let node = match self.context_element {
+                Some(ref context) if last => context.clone(),
+                _ => self.stack_of_open_elements[i].clone(),
+            };
+            let mode = match node.borrow().element_kind() {
+                // "4. If node is a select element, run these substeps: ... Done: Switch the
+                // insertion mode to "in select" and return."
+                Some(ElementKind::Select) => InsertionMode::InSelect,
+                // "5. If node is a td or th element and last is false, then switch the insertion
+                // mode to "in cell" and return."
+                Some(ElementKind::Td) | Some(ElementKind::Th) if !last => InsertionMode::InCell,
+                Some(ElementKind::Tr) => InsertionMode::InRow,
+                Some(ElementKind::Tbody) | Some(ElementKind::Thead) | Some(ElementKind::Tfoot) => {
+                    InsertionMode::InTableBody
+                }
+                Some(ElementKind::Caption) => InsertionMode::InCaption,
+                Some(ElementKind::Colgroup) => InsertionMode::InColumnGroup,
+                Some(ElementKind::Table) => InsertionMode::InTable,
+                Some(ElementKind::Head) if !last => InsertionMode::InHead,
+                Some(ElementKind::Body) => InsertionMode::InBody,
+                // "15. If node is an html element, run these substeps: If the head element
+                // pointer is null, switch the insertion mode to "before head" and return.
+                // Otherwise, switch the insertion mode to "after head" and return."
+                Some(ElementKind::Html) => {
+                    let mut child = node.borrow().first_child();
+                    let mut has_head = false;
+                    while let Some(c) = child {
+                        if c.borrow().element_kind() == Some(ElementKind::Head) {
+                            has_head = true;
+                        }
+                        child = c.borrow().next_sibling();
+                    }
+                    if has_head {
+                        InsertionMode::AfterHead
+                    } else {
+                        InsertionMode::BeforeHead
+                    }
+                }
+                // "16. If last is true, then switch the insertion mode to "in body" and return."
+                _ if last => InsertionMode::InBody,
+                // "17. Let node now be the node before node in the stack of open elements."
+                _ => continue,
+            };
+            self.mode = mode;
+            return;
+        }
+        self.mode = InsertionMode::InBody;
+    }
+
+    /// Returns the insertion mode whose rules are used to process `token`. Some tokens in the
+    /// table-related insertion modes are processed "using the rules for" another insertion mode
+    /// without changing the current insertion mode.
+    fn mode_for_token(&self, token: &Option<HtmlToken>) -> InsertionMode {
+        match self.mode {
+            // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-intable
+            InsertionMode::InTable => self.table_mode_for_token(token),
+            // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-incaption
+            InsertionMode::InCaption => {
+                if is_start_tag(token, &TABLE_PART_START_TAGS)
+                    || is_end_tag(
+                        token,
+                        &[
+                            "caption", "table", "body", "col", "colgroup", "html", "tbody", "td",
+                            "tfoot", "th", "thead", "tr",
+                        ],
+                    )
+                {
+                    InsertionMode::InCaption
+                } else {
+                    InsertionMode::InBody
+                }
+            }
+            // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-intbody
+            InsertionMode::InTableBody => {
+                if is_start_tag(token, &TABLE_PART_START_TAGS)
+                    || is_end_tag(
+                        token,
+                        &[
+                            "tbody", "tfoot", "thead", "table", "body", "caption", "col",
+                            "colgroup", "html", "td", "th", "tr",
+                        ],
+                    )
+                {
+                    InsertionMode::InTableBody
+                } else {
+                    self.table_mode_for_token(token)
+                }
+            }
+            // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-intr
+            InsertionMode::InRow => {
+                if is_start_tag(token, &TABLE_PART_START_TAGS)
+                    || is_end_tag(
+                        token,
+                        &[
+                            "tr", "table", "tbody", "tfoot", "thead", "body", "caption", "col",
+                            "colgroup", "html", "td", "th",
+                        ],
+                    )
+                {
+                    InsertionMode::InRow
+                } else {
+                    self.table_mode_for_token(token)
+                }
+            }
+            // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-intd
+            InsertionMode::InCell => {
+                if is_start_tag(token, &TABLE_PART_START_TAGS)
+                    || is_end_tag(
+                        token,
+                        &[
+                            "td", "th", "body", "caption", "col", "colgroup", "html", "table",
+                            "tbody", "tfoot", "thead", "tr",
+                        ],
+                    )
+                {
+                    InsertionMode::InCell
+                } else {
+                    InsertionMode::InBody
+                }
+            }
+            _ => self.mode,
+        }
+    }
+
+    /// Returns the insertion mode whose rules are used to process `token` in the "in table"
+    /// insertion mode.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-intable
+    fn table_mode_for_token(&self, token: &Option<HtmlToken>) -> InsertionMode {
+        match token {
+            // "A character token, if the current node is table, tbody, template, tfoot, thead, or
+            // tr element"
+            Some(HtmlToken::Char(_)) => match self.current_node().borrow().element_kind() {
+                Some(ElementKind::Table)
+                | Some(ElementKind::Tbody)
+                | Some(ElementKind::Tfoot)
+                | Some(ElementKind::Thead)
+                | Some(ElementKind::Tr) => InsertionMode::InTable,
+                _ => InsertionMode::InBody,
+            },
+            Some(HtmlToken::Comment(_)) | Some(HtmlToken::Doctype { .. }) => InsertionMode::InTable,
+            Some(HtmlToken::StartTag {
+                ref tag,
+                ref attributes,
+                ..
+            }) => match tag.as_str() {
+                "caption" | "colgroup" | "col" | "tbody" | "tfoot" | "thead" | "td" | "th"
+                | "tr" | "table" | "form" => InsertionMode::InTable,
+                // "A start tag whose tag name is one of: "style", "script", "template"
+                // Process the token using the rules for the "in head" insertion mode."
+                "style" | "script" => InsertionMode::InHead,
+                // "A start tag whose tag name is "input"
+                // If the token does not have an attribute with the name "type", or if it does,
+                // but that attribute's value is not an ASCII case-insensitive match for the string
+                // "hidden", then: act as described in the "anything else" entry below."
+                "input"
+                    if attributes.iter().any(|a| {
+                        a.name() == "type" && a.value().eq_ignore_ascii_case("hidden")
+                    }) =>
+                {
+                    InsertionMode::InTable
+                }
+                _ => InsertionMode::InBody,
+            },
+            Some(HtmlToken::EndTag { ref tag }) => match tag.as_str() {
+                "table" | "body" | "caption" | "col" | "colgroup" | "html" | "tbody" | "td"
+                | "tfoot" | "th" | "thead" | "tr" => InsertionMode::InTable,
+                _ => InsertionMode::InBody,
+            },
+            // "An end-of-file token
+            // Process the token using the rules for the "in body" insertion mode."
+            Some(HtmlToken::Eof) | None => InsertionMode::InBody,
+        }
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#tree-construction
+    /// Parses `markup` as the children of `context` and returns the parsed nodes. The nodes don't
+    /// have a parent yet.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-html-fragments
+    pub fn parse_fragment(
+        browser: Weak<RefCell<Browser>>,
+        context: &Rc<RefCell<Node>>,
+        markup: String,
+    ) -> Vec<Rc<RefCell<Node>>> {
+        // "1. Create a new Document node, and mark it as being an HTML document.
+        // 3. Create a new HTML parser, and associate it with the just created Document node."
+        let mut t = HtmlTokenizer::new(browser.clone(), markup);
+
+        // "4. Set the state of the HTML parser's tokenization stage as follows, switching on the
+        // context element:
+        //   title, textarea: Switch the tokenizer to the RCDATA state.
+        //   style, xmp, iframe, noembed, noframes: Switch the tokenizer to the RAWTEXT state.
+        //   script: Switch the tokenizer to the script data state.
+        //   Any other element: Leave the tokenizer in the data state."
+        let context_name = context
+            .borrow()
+            .get_element()
+            .map(|e| e.local_name())
+            .unwrap_or_default();
+        match context_name.as_str() {
+            "title" | "textarea" => t.switch_context(State::Rcdata),
+            "style" | "xmp" | "iframe" | "noembed" | "noframes" => t.switch_context(State::Rawtext),
+            "script" => t.switch_context(State::ScriptData),
+            _ => {}
+        }
+
+        let mut parser = HtmlParser::new(browser, t);
+
+        // "5. Let root be the result of creating an element given this new Document, "html", and
+        // the HTML namespace.
+        // 6. Append root to this new Document.
+        // 7. Set up the HTML parser's stack of open elements so that it contains just the single
+        // element root."
+        let document = parser.window.borrow().document();
+        let root = Rc::new(RefCell::new(parser.create_element("html", Vec::new())));
+        Self::append_child(&document, &root);
+        parser.stack_of_open_elements.push(root.clone());
+
+        // "9. Reset the parser's insertion mode appropriately."
+        parser.context_element = Some(context.clone());
+        parser.reset_insertion_mode_appropriately();
+
+        // "10. Set the HTML parser's form element pointer to the nearest node to the context
+        // element that is a form element (going straight up the ancestor chain, and including
+        // the element itself), if there is such an element."
+        let mut node = Some(context.clone());
+        while let Some(n) = node {
+            if n.borrow().element_kind() == Some(ElementKind::Form) {
+                parser.form_element_pointer = Some(n);
+                break;
+            }
+            node = n.borrow().parent().upgrade();
+        }
+
+        // "12. Start the parser and let it run until it has consumed all the characters just
+        // inserted into the input stream."
+        parser.construct_tree();
+
+        // "13. Return root's children, in tree order."
+        let mut children = Vec::new();
+        let mut child = root.borrow().first_child();
+        while let Some(c) = child {
+            child = c.borrow().next_sibling();
+            Self::remove_from_parent(&c);
+            children.push(c);
+        }
+        children
+    }
+
+    pub fn construct_tree(&mut self) -> Rc<RefCell<Window>> {
+        let mut token = self.next_token();
+
+        while token.is_some() {
+            let mode = self.mode_for_token(&token);
+            // "Anything else: Parse error. Enable foster parenting, process the token using the
+            // rules for the "in body" insertion mode, and then disable foster parenting."
+            self.foster_parenting = mode == InsertionMode::InBody
+                && matches!(
+                    self.mode,
+                    InsertionMode::InTable | InsertionMode::InTableBody | InsertionMode::InRow
+                );
+
+            match mode {
+                // https://html.spec.whatwg.org/multipage/parsing.html#the-initial-insertion-mode
+                InsertionMode::Initial => {
+                    match token {
+                        // A character token that is one of U+0009 CHARACTER TABULATION, U+000A
+                        // LINE FEED (LF), U+000C FORM FEED (FF), U+000D CARRIAGE RETURN (CR), or
+                        // U+0020 SPACE
+                        // Ignore the token.
+                        Some(HtmlToken::Char(c)) if c.is_ascii_whitespace() => {
+                            token = self.next_token();
+                            continue;
+                        }
+                        // A comment token
+                        // Insert a comment as the last child of the Document object.
+                        Some(HtmlToken::Comment(ref data)) => {
+                            let document = self.window.borrow().document();
+                            self.insert_comment(data, Some(document));
+                            token = self.next_token();
+                            continue;
+                        }
+                        // A DOCTYPE token
+                        Some(HtmlToken::Doctype {
+                            ref name,
+                            ref public_identifier,
+                            ref system_identifier,
+                            force_quirks,
+                        }) => {
+                            // "Append a DocumentType node to the Document node, with its name set
+                            // to the name given in the DOCTYPE token, or the empty string if the
+                            // name was missing; its public ID set to the public identifier given
+                            // in the DOCTYPE token, or the empty string if the public identifier
+                            // was missing; and its system ID set to the system identifier given in
+                            // the DOCTYPE token, or the empty string if the system identifier was
+                            // missing."
+                            let document = self.window.borrow().document();
+                            let node = Rc::new(RefCell::new(Node::new(NodeKind::DocumentType(
+                                DocumentType::new(
+                                    name.clone().unwrap_or_default(),
+                                    public_identifier.clone().unwrap_or_default(),
+                                    system_identifier.clone().unwrap_or_default(),
+                                ),
+                            ))));
+                            node.borrow_mut()
+                                .set_source_range(Some(self.t.token_range()));
+                            Self::append_child(&document, &node);
+
+                            // "Then, if the document is not an iframe srcdoc document, and the
+                            // parser cannot change the mode flag is false, and the DOCTYPE token
+                            // matches one of the conditions in the following list, then set the
+                            // Document to quirks mode: ..."
+                            let mode = document_mode_for_doctype(
+                                name,
+                                public_identifier,
+                                system_identifier,
+                                force_quirks,
+                            );
+                            document.borrow_mut().set_document_mode(mode);
+
+                            self.mode = InsertionMode::BeforeHtml;
+                            token = self.next_token();
+                            continue;
+                        }
+                        _ => {}
+                    }
+
+                    // Anything else
+                    // "If the document is not an iframe srcdoc document, then this is a parse
+                    // error; if the parser cannot change the mode flag is false, set the
+                    // Document to quirks mode."
+                    self.window
+                        .borrow()
+                        .document()
+                        .borrow_mut()
+                        .set_document_mode(DocumentMode::Quirks);
+                    // "In any case, switch the insertion mode to "before html", then reprocess
+                    // the token."
+                    self.mode = InsertionMode::BeforeHtml;
+                    continue;
+                }
+
+                // https://html.spec.whatwg.org/multipage/parsing.html#the-before-html-insertion-mode
+                InsertionMode::BeforeHtml => {
+                    match token {
+                        // A DOCTYPE token
+                        // Parse error. Ignore the token.
+                        Some(HtmlToken::Doctype { .. }) => {
+                            self.parse_error("unexpected-doctype");
+                            token = self.next_token();
+                            continue;
+                        }
+                        // A comment token
+                        // Insert a comment as the last child of the Document object.
+                        Some(HtmlToken::Comment(ref data)) => {
+                            let document = self.window.borrow().document();
+                            self.insert_comment(data, Some(document));
+                            token = self.next_token();
+                            continue;
+                        }
+                        Some(HtmlToken::Char(c)) => {
+                            if c == ' ' || c == '\n' {
+                                token = self.next_token();
+                                continue;
+                            }
+                        }
+                        Some(HtmlToken::StartTag {
+                            ref tag,
+                            self_closing: _,
+                            ref attributes,
+                        }) => {
+                            // A start tag whose tag name is "html"
+                            // Create an element for the token in the HTML namespace, with the Document
+                            // as the intended parent. Append it to the Document object. Put this
+                            // element in the stack of open elements.
+                            if tag == "html" {
+                                self.insert_element(tag, attributes.to_vec());
+                                self.mode = InsertionMode::BeforeHead;
+                                token = self.next_token();
+                                continue;
+                            }
+                        }
+                        Some(HtmlToken::EndTag { ref tag }) => {
+                            // Any other end tag
+                            // Parse error. Ignore the token.
+                            if tag != "head" || tag != "body" || tag != "html" || tag != "br" {
+                                self.parse_error("unexpected-end-tag");
+                                // Ignore the token.
+                                token = self.next_token();
+                                continue;
+                            }
+                        }
+                        Some(HtmlToken::Eof) | None => {
+                            return self.window.clone();
+                        }
+                    }
+                    self.insert_element("html", Vec::new());
+                    self.mode = InsertionMode::BeforeHead;
+                    continue;
+                } // end of InsertionMode::BeforeHtml
+
+                // https://html.spec.whatwg.org/multipage/parsing.html#the-before-head-insertion-mode
+                InsertionMode::BeforeHead => {
+                    match token {
+                        // A comment token
+                        // Insert a comment.
+                        Some(HtmlToken::Comment(ref data)) => {
+                            self.insert_comment(data, None);
+                            token = self.next_token();
+                            continue;
+                        }
+                        // A DOCTYPE token
+                        // Parse error. Ignore the token.
+                        Some(HtmlToken::Doctype { .. }) => {
+                            self.parse_error("unexpected-doctype");
+                            token = self.next_token();
+                            continue;
+                        }
+                        Some(HtmlToken::Char(c)) => {
+                            if c == ' ' || c == '\n' {
+                                token = self.next_token();
+                                continue;
+                            }
+                        }
+                        Some(HtmlToken::StartTag {
+                            ref tag,
+                            self_closing: _,
+                            ref attributes,
+                        }) => {
+                            if tag == "head" {
+                                self.insert_element(tag, attributes.to_vec());
+                                self.mode = InsertionMode::InHead;
+                                token = self.next_token();
+                                continue;
+                            }
+                        }
+                        Some(HtmlToken::Eof) | None => {
+                            return self.window.clone();
+                        }
+                        _ => {}
+                    }
+                    self.insert_element("head", Vec::new());
+                    self.mode = InsertionMode::InHead;
+                    continue;
+                } // end of InsertionMode::BeforeHead
 
                 // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inhead
                 InsertionMode::InHead => {
                     match token {
+                        // A comment token
+                        // Insert a comment.
+                        Some(HtmlToken::Comment(ref data)) => {
+                            self.insert_comment(data, None);
+                            token = self.next_token();
+                            continue;
+                        }
+                        // A DOCTYPE token
+                        // Parse error. Ignore the token.
+                        Some(HtmlToken::Doctype { .. }) => {
+                            self.parse_error("unexpected-doctype");
+                            token = self.next_token();
+                            continue;
+                        }
                         Some(HtmlToken::Char(c)) => {
                             if c == ' ' || c == '\n' {
-                                token = self.t.next();
+                                token = self.next_token();
                                 continue;
                             }
                         }
@@ -305,11 +1528,24 @@ impl HtmlParser {
                             self_closing: _,
                             ref attributes,
                         }) => {
+                            // A start tag whose tag name is one of: "noframes", "style"
+                            // Follow the generic raw text element parsing algorithm.
                             if tag == "style" {
                                 self.insert_element(tag, attributes.to_vec());
+                                self.t.switch_context(State::Rawtext);
+                                self.original_insertion_mode = self.mode;
+                                self.mode = InsertionMode::Text;
+                                token = self.next_token();
+                                continue;
+                            }
+                            // A start tag whose tag name is "title"
+                            // Follow the generic RCDATA element parsing algorithm.
+                            if tag == "title" {
+                                self.insert_element(tag, attributes.to_vec());
+                                self.t.switch_context(State::Rcdata);
                                 self.original_insertion_mode = self.mode;
                                 self.mode = InsertionMode::Text;
-                                token = self.t.next();
+                                token = self.next_token();
                                 continue;
                             }
                             if tag == "script" {
@@ -324,7 +1560,7 @@ impl HtmlParser {
                                 self.original_insertion_mode = self.mode;
                                 // "10. Switch the insertion mode to "text"."
                                 self.mode = InsertionMode::Text;
-                                token = self.t.next();
+                                token = self.next_token();
                                 continue;
                             }
 
@@ -336,7 +1572,33 @@ impl HtmlParser {
                                 self.mode = InsertionMode::AfterHead;
                                 continue;
                             }
-                            if let Ok(_element_kind) = ElementKind::from_str(tag) {
+                            // A start tag whose tag name is one of: "base", "basefont",
+                            // "bgsound", "link"
+                            // A start tag whose tag name is "meta"
+                            // Insert an HTML element for the token. Immediately pop the current
+                            // node off the stack of open elements.
+                            if tag == "base"
+                                || tag == "basefont"
+                                || tag == "bgsound"
+                                || tag == "link"
+                                || tag == "meta"
+                            {
+                                self.insert_element(tag, attributes.to_vec());
+                                self.stack_of_open_elements.pop();
+                                token = self.next_token();
+                                continue;
+                            }
+                            // Anything else
+                            // Pop the current node (which will be the head element) off the stack
+                            // of open elements. Switch the insertion mode to "after head".
+                            // Reprocess the token.
+                            //
+                            // Unsupported elements that belong to <head> are ignored below.
+                            if tag != "head"
+                                && tag != "noscript"
+                                && tag != "noframes"
+                                && tag != "template"
+                            {
                                 self.pop_until(ElementKind::Head);
                                 self.mode = InsertionMode::AfterHead;
                                 continue;
@@ -345,7 +1607,7 @@ impl HtmlParser {
                         Some(HtmlToken::EndTag { ref tag }) => {
                             if tag == "head" {
                                 self.mode = InsertionMode::AfterHead;
-                                token = self.t.next();
+                                token = self.next_token();
                                 self.pop_until(ElementKind::Head);
                                 continue;
                             }
@@ -355,17 +1617,31 @@ impl HtmlParser {
                         }
                     }
                     // Ignore unsupported tags like <meta> and <title>.
-                    token = self.t.next();
+                    token = self.next_token();
                     continue;
                 } // end of InsertionMode::InHead
 
                 // https://html.spec.whatwg.org/multipage/parsing.html#the-after-head-insertion-mode
                 InsertionMode::AfterHead => {
                     match token {
+                        // A comment token
+                        // Insert a comment.
+                        Some(HtmlToken::Comment(ref data)) => {
+                            self.insert_comment(data, None);
+                            token = self.next_token();
+                            continue;
+                        }
+                        // A DOCTYPE token
+                        // Parse error. Ignore the token.
+                        Some(HtmlToken::Doctype { .. }) => {
+                            self.parse_error("unexpected-doctype");
+                            token = self.next_token();
+                            continue;
+                        }
                         Some(HtmlToken::Char(c)) => {
                             if c == ' ' || c == '\n' {
                                 self.insert_char(c);
-                                token = self.t.next();
+                                token = self.next_token();
                                 continue;
                             }
                         }
@@ -376,7 +1652,7 @@ impl HtmlParser {
                         }) => {
                             if tag == "body" {
                                 self.insert_element(tag, attributes.to_vec());
-                                token = self.t.next();
+                                token = self.next_token();
                                 self.mode = InsertionMode::InBody;
                                 continue;
                             }
@@ -394,83 +1670,275 @@ impl HtmlParser {
                 // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inbody
                 InsertionMode::InBody => {
                     match token {
+                        // A comment token
+                        // Insert a comment.
+                        Some(HtmlToken::Comment(ref data)) => {
+                            self.insert_comment(data, None);
+                            token = self.next_token();
+                            continue;
+                        }
+                        // A DOCTYPE token
+                        // Parse error. Ignore the token.
+                        Some(HtmlToken::Doctype { .. }) => {
+                            self.parse_error("unexpected-doctype");
+                            token = self.next_token();
+                            continue;
+                        }
                         Some(HtmlToken::StartTag {
                             ref tag,
                             self_closing,
                             ref attributes,
                         }) => {
                             match tag.as_str() {
-                                // A start tag whose tag name is one of: "base", "basefont",
-                                // "bgsound", "link", "meta", "noframes", "script", "style",
-                                // "template", "title"
-                                "script" | "style" => {
-                                    // Process the token using the rules for the "in head" insertion mode.
-                                    //
-                                    // https://html.spec.whatwg.org/multipage/parsing.html#parsing-html-fragments
-                                    // Switch the tokenizer to the script data state.
-                                    self.insert_element(tag, attributes.to_vec());
-                                    self.t.switch_context(State::ScriptData);
-                                    self.original_insertion_mode = self.mode;
-                                    self.mode = InsertionMode::Text;
-                                    token = self.t.next();
+                                // A start tag whose tag name is "html"
+                                // A start tag whose tag name is "body"
+                                // Parse error. The attributes are not merged into the existing
+                                // element.
+                                //
+                                // A start tag whose tag name is "head"
+                                // Parse error. Ignore the token.
+                                "html" | "body" | "head" => {
+                                    self.parse_error("unexpected-start-tag");
+                                    token = self.next_token();
                                     continue;
                                 }
-                                // A start tag whose tag name is one of: "address", "article",
-                                // "aside", "blockquote", "center", "details", "dialog", "dir",
-                                // "div", "dl", "fieldset", "figcaption", "figure", "footer",
-                                // "header", "hgroup", "main", "menu", "nav", "ol", "p", "section",
-                                // "summary", "ul"
-                                "div" | "p" | "ul" => {
-                                    // If the stack of open elements has a p element in button
-                                    // scope, then close a p element.
-                                    if self.contain_in_stack(ElementKind::P) {
-                                        self.pop_until(ElementKind::P);
-                                    }
-                                    // Insert an HTML element for the token.
-                                    self.insert_element(tag, attributes.to_vec());
-                                    token = self.t.next();
+                                // A start tag whose tag name is one of: "caption", "col",
+                                // "colgroup", "frame", "head", "tbody", "td", "tfoot", "th",
+                                // "thead", "tr"
+                                // Parse error. Ignore the token.
+                                "caption" | "col" | "colgroup" | "frame" | "tbody" | "td"
+                                | "tfoot" | "th" | "thead" | "tr" => {
+                                    self.parse_error("unexpected-start-tag");
+                                    token = self.next_token();
                                     continue;
                                 }
-                                // A start tag whose tag name is one of: "h1", "h2", "h3", "h4",
-                                // "h5", "h6"
-                                "h1" | "h2" => {
+                                // A start tag whose tag name is "form"
+                                "form" => {
+                                    // If the form element pointer is not null, and there is no
+                                    // template element on the stack of open elements, then this
+                                    // is a parse error; ignore the token.
+                                    if self.form_element_pointer.is_some() {
+                                        token = self.next_token();
+                                        continue;
+                                    }
+                                    // Otherwise:
+                                    //
                                     // If the stack of open elements has a p element in button
                                     // scope, then close a p element.
-                                    if self.contain_in_stack(ElementKind::P) {
-                                        self.pop_until(ElementKind::P);
+                                    if self.has_element_in_button_scope(ElementKind::P) {
+                                        self.close_p_element();
                                     }
-                                    // If the current node is an HTML element whose tag name is one
-                                    // of "h1", "h2", "h3", "h4", "h5", or "h6", then this is a
-                                    // parse error; pop the current node off the stack of open
-                                    // elements.
-                                    //
-                                    // Insert an HTML element for the token.
+                                    // Insert an HTML element for the token, and, if there is no
+                                    // template element on the stack of open elements, set the
+                                    // form element pointer to point to the element created.
                                     self.insert_element(tag, attributes.to_vec());
-                                    token = self.t.next();
+                                    self.form_element_pointer = Some(self.current_node());
+                                    token = self.next_token();
                                     continue;
                                 }
-                                // A start tag whose tag name is one of: "pre", "listing"
-                                "pre" => {
-                                    // If the stack of open elements has a p element in button
-                                    // scope, then close a p element.
-                                    if self.contain_in_stack(ElementKind::P) {
-                                        self.pop_until(ElementKind::P);
+                                // A start tag whose tag name is "button"
+                                "button" => {
+                                    // 1. If the stack of open elements has a button element in
+                                    // scope, then run these substeps:
+                                    // 1-1. Parse error.
+                                    // 1-2. Generate implied end tags.
+                                    // 1-3. Pop elements from the stack of open elements until a
+                                    // button element has been popped from the stack.
+                                    if self.has_element_in_scope(ElementKind::Button) {
+                                        self.generate_implied_end_tags();
+                                        self.pop_until(ElementKind::Button);
                                     }
-                                    // Insert an HTML element for the token.
-                                    //
-                                    // If the next token is a U+000A LINE FEED (LF) character
-                                    // token, then ignore that token and move on to the next one.
-                                    // (Newlines at the start of pre blocks are ignored as an
-                                    // authoring convenience.)
+                                    // 2. Reconstruct the active formatting elements, if any.
+                                    self.reconstruct_active_formatting_elements();
+                                    // 3. Insert an HTML element for the token.
                                     //
-                                    // Set the frameset-ok flag to "not ok".
+                                    // 4. Set the frameset-ok flag to "not ok".
                                     self.insert_element(tag, attributes.to_vec());
-                                    token = self.t.next();
+                                    token = self.next_token();
                                     continue;
                                 }
-                                // A start tag whose tag name is "li"
-                                "li" => {
-                                    // Run these steps:
+                                // A start tag whose tag name is "textarea"
+                                "textarea" => {
+                                    // 1. Insert an HTML element for the token.
+                                    self.insert_element(tag, attributes.to_vec());
+                                    // 3. Switch the tokenizer to the RCDATA state.
+                                    self.t.switch_context(State::Rcdata);
+                                    // 4. Let the original insertion mode be the current insertion
+                                    // mode.
+                                    //
+                                    // 5. Set the frameset-ok flag to "not ok".
+                                    //
+                                    // 6. Switch the insertion mode to "text".
+                                    self.original_insertion_mode = self.mode;
+                                    self.mode = InsertionMode::Text;
+                                    // 2. If the next token is a U+000A LINE FEED (LF) character
+                                    // token, then ignore that token and move on to the next one.
+                                    // (Newlines at the start of textarea elements are ignored as
+                                    // an authoring convenience.)
+                                    token = self.next_token();
+                                    if token == Some(HtmlToken::Char('\n')) {
+                                        token = self.next_token();
+                                    }
+                                    continue;
+                                }
+                                // A start tag whose tag name is "select"
+                                "select" => {
+                                    // Reconstruct the active formatting elements, if any.
+                                    self.reconstruct_active_formatting_elements();
+                                    // Insert an HTML element for the token.
+                                    //
+                                    // Set the frameset-ok flag to "not ok".
+                                    self.insert_element(tag, attributes.to_vec());
+                                    // If the insertion mode is one of "in table", "in caption",
+                                    // "in table body", "in row", or "in cell", then switch the
+                                    // insertion mode to "in select in table". Otherwise, switch
+                                    // the insertion mode to "in select".
+                                    // The "in select in table" insertion mode is not supported, so
+                                    // the "in select" insertion mode is used in a table too.
+                                    self.mode = InsertionMode::InSelect;
+                                    token = self.next_token();
+                                    continue;
+                                }
+                                // A start tag whose tag name is one of: "optgroup", "option"
+                                "optgroup" | "option" => {
+                                    // If the current node is an option element, then pop the
+                                    // current node off the stack of open elements.
+                                    self.pop_current_node(ElementKind::Option);
+                                    // Reconstruct the active formatting elements, if any.
+                                    self.reconstruct_active_formatting_elements();
+                                    // Insert an HTML element for the token.
+                                    self.insert_element(tag, attributes.to_vec());
+                                    token = self.next_token();
+                                    continue;
+                                }
+                                // A start tag whose tag name is "table"
+                                "table" => {
+                                    // If the Document is not set to quirks mode, and the stack of
+                                    // open elements has a p element in button scope, then close a
+                                    // p element.
+                                    let document_mode =
+                                        self.window.borrow().document().borrow().document_mode();
+                                    if document_mode != DocumentMode::Quirks
+                                        && self.has_element_in_button_scope(ElementKind::P)
+                                    {
+                                        self.close_p_element();
+                                    }
+                                    // Insert an HTML element for the token.
+                                    //
+                                    // Set the frameset-ok flag to "not ok".
+                                    //
+                                    // Switch the insertion mode to "in table".
+                                    self.insert_element(tag, attributes.to_vec());
+                                    self.mode = InsertionMode::InTable;
+                                    token = self.next_token();
+                                    continue;
+                                }
+                                // A start tag whose tag name is one of: "base", "basefont",
+                                // "bgsound", "link", "meta", "noframes", "script", "style",
+                                // "template", "title"
+                                "base" | "basefont" | "bgsound" | "link" | "meta" => {
+                                    // Process the token using the rules for the "in head"
+                                    // insertion mode.
+                                    self.insert_element(tag, attributes.to_vec());
+                                    self.stack_of_open_elements.pop();
+                                    token = self.next_token();
+                                    continue;
+                                }
+                                "title" => {
+                                    // Process the token using the rules for the "in head"
+                                    // insertion mode. Follow the generic RCDATA element parsing
+                                    // algorithm.
+                                    self.insert_element(tag, attributes.to_vec());
+                                    self.t.switch_context(State::Rcdata);
+                                    self.original_insertion_mode = self.mode;
+                                    self.mode = InsertionMode::Text;
+                                    token = self.next_token();
+                                    continue;
+                                }
+                                "script" => {
+                                    // Process the token using the rules for the "in head" insertion mode.
+                                    //
+                                    // https://html.spec.whatwg.org/multipage/parsing.html#parsing-html-fragments
+                                    // Switch the tokenizer to the script data state.
+                                    self.insert_element(tag, attributes.to_vec());
+                                    self.t.switch_context(State::ScriptData);
+                                    self.original_insertion_mode = self.mode;
+                                    self.mode = InsertionMode::Text;
+                                    token = self.next_token();
+                                    continue;
+                                }
+                                "style" => {
+                                    // Process the token using the rules for the "in head" insertion
+                                    // mode. Follow the generic raw text element parsing algorithm.
+                                    self.insert_element(tag, attributes.to_vec());
+                                    self.t.switch_context(State::Rawtext);
+                                    self.original_insertion_mode = self.mode;
+                                    self.mode = InsertionMode::Text;
+                                    token = self.next_token();
+                                    continue;
+                                }
+                                // A start tag whose tag name is one of: "address", "article",
+                                // "aside", "blockquote", "center", "details", "dialog", "dir",
+                                // "div", "dl", "fieldset", "figcaption", "figure", "footer",
+                                // "header", "hgroup", "main", "menu", "nav", "ol", "p", "section",
+                                // "summary", "ul"
+                                "div" | "p" | "ul" | "ol" | "blockquote" | "section"
+                                | "article" | "nav" | "header" | "footer" | "dl" => {
+                                    // If the stack of open elements has a p element in button
+                                    // scope, then close a p element.
+                                    if self.has_element_in_button_scope(ElementKind::P) {
+                                        self.close_p_element();
+                                    }
+                                    // Insert an HTML element for the token.
+                                    self.insert_element(tag, attributes.to_vec());
+                                    token = self.next_token();
+                                    continue;
+                                }
+                                // A start tag whose tag name is one of: "h1", "h2", "h3", "h4",
+                                // "h5", "h6"
+                                "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                                    // If the stack of open elements has a p element in button
+                                    // scope, then close a p element.
+                                    if self.has_element_in_button_scope(ElementKind::P) {
+                                        self.close_p_element();
+                                    }
+                                    // If the current node is an HTML element whose tag name is one
+                                    // of "h1", "h2", "h3", "h4", "h5", or "h6", then this is a
+                                    // parse error; pop the current node off the stack of open
+                                    // elements.
+                                    if let Some(kind) = self.current_node().borrow().element_kind()
+                                    {
+                                        if is_heading(kind) {
+                                            self.stack_of_open_elements.pop();
+                                        }
+                                    }
+                                    // Insert an HTML element for the token.
+                                    self.insert_element(tag, attributes.to_vec());
+                                    token = self.next_token();
+                                    continue;
+                                }
+                                // A start tag whose tag name is one of: "pre", "listing"
+                                "pre" => {
+                                    // If the stack of open elements has a p element in button
+                                    // scope, then close a p element.
+                                    if self.has_element_in_button_scope(ElementKind::P) {
+                                        self.close_p_element();
+                                    }
+                                    // Insert an HTML element for the token.
+                                    //
+                                    // If the next token is a U+000A LINE FEED (LF) character
+                                    // token, then ignore that token and move on to the next one.
+                                    // (Newlines at the start of pre blocks are ignored as an
+                                    // authoring convenience.)
+                                    //
+                                    // Set the frameset-ok flag to "not ok".
+                                    self.insert_element(tag, attributes.to_vec());
+                                    token = self.next_token();
+                                    continue;
+                                }
+                                // A start tag whose tag name is "li"
+                                "li" => {
+                                    // Run these steps:
                                     //
                                     // 1. Set the frameset-ok flag to "not ok".
                                     //
@@ -491,14 +1959,86 @@ impl HtmlParser {
                                     // 5. Otherwise, set node to the previous entry in the stack of
                                     // open elements and return to the step labeled loop.
                                     //
+                                    for i in (0..self.stack_of_open_elements.len()).rev() {
+                                        let local_name = match self.stack_of_open_elements[i]
+                                            .borrow()
+                                            .get_element()
+                                        {
+                                            Some(e) => e.local_name(),
+                                            None => break,
+                                        };
+                                        if local_name == "li" {
+                                            self.generate_implied_end_tags_except(Some("li"));
+                                            self.pop_until(ElementKind::Li);
+                                            break;
+                                        }
+                                        if is_special(&local_name)
+                                            && local_name != "address"
+                                            && local_name != "div"
+                                            && local_name != "p"
+                                        {
+                                            break;
+                                        }
+                                    }
+                                    // 6. Done: If the stack of open elements has a p element in
+                                    // button scope, then close a p element.
+                                    if self.has_element_in_button_scope(ElementKind::P) {
+                                        self.close_p_element();
+                                    }
+                                    // 7. Finally, insert an HTML element for the token.
+                                    self.insert_element(tag, attributes.to_vec());
+                                    token = self.next_token();
+                                    continue;
+                                }
+                                // A start tag whose tag name is one of: "dd", "dt"
+                                "dd" | "dt" => {
+                                    // 1. Set the frameset-ok flag to "not ok".
+                                    //
+                                    // 2. Initialize node to be the current node (the bottommost
+                                    // node of the stack).
+                                    //
+                                    // 3. Loop: If node is a dd or dt element, then generate
+                                    // implied end tags, except for dd or dt elements, and pop
+                                    // elements from the stack of open elements until a dd or dt
+                                    // element has been popped from the stack. Jump to done.
+                                    //
+                                    // 4. If node is in the special category, but is not an
+                                    // address, div, or p element, then jump to done.
+                                    //
+                                    // 5. Otherwise, set node to the previous entry in the stack
+                                    // of open elements and return to the step labeled loop.
+                                    for i in (0..self.stack_of_open_elements.len()).rev() {
+                                        let kind =
+                                            self.stack_of_open_elements[i].borrow().element_kind();
+                                        if kind == Some(ElementKind::Dd)
+                                            || kind == Some(ElementKind::Dt)
+                                        {
+                                            self.stack_of_open_elements.truncate(i);
+                                            break;
+                                        }
+                                        let local_name = match self.stack_of_open_elements[i]
+                                            .borrow()
+                                            .get_element()
+                                        {
+                                            Some(e) => e.local_name(),
+                                            None => break,
+                                        };
+                                        if is_special(&local_name)
+                                            && local_name != "address"
+                                            && local_name != "div"
+                                            && local_name != "p"
+                                        {
+                                            break;
+                                        }
+                                    }
                                     // 6. Done: If the stack of open elements has a p element in
                                     // button scope, then close a p element.
-                                    if self.contain_in_stack(ElementKind::P) {
-                                        self.pop_until(ElementKind::P);
+                                    if self.has_element_in_button_scope(ElementKind::P) {
+                                        self.close_p_element();
                                     }
                                     // 7. Finally, insert an HTML element for the token.
                                     self.insert_element(tag, attributes.to_vec());
-                                    token = self.t.next();
+                                    token = self.next_token();
                                     continue;
                                 }
                                 // A start tag whose tag name is "a"
@@ -513,17 +2053,72 @@ impl HtmlParser {
                                     // already remove it (it might not have if the element is not
                                     // in table scope).
                                     //
+                                    if let Some(a) =
+                                        self.active_formatting_element_after_last_marker("a")
+                                    {
+                                        self.run_adoption_agency_algorithm("a");
+                                        if let Some(i) =
+                                            self.position_in_active_formatting_elements(&a)
+                                        {
+                                            self.active_formatting_elements.remove(i);
+                                        }
+                                        if let Some(i) = self.position_in_stack(&a) {
+                                            self.stack_of_open_elements.remove(i);
+                                        }
+                                    }
                                     // Reconstruct the active formatting elements, if any.
-                                    //
+                                    self.reconstruct_active_formatting_elements();
+                                    // Insert an HTML element for the token. Push onto the list of
+                                    // active formatting elements that element.
+                                    self.insert_element(tag, attributes.to_vec());
+                                    self.push_active_formatting_element(self.current_node());
+                                    token = self.next_token();
+                                    continue;
+                                }
+                                // A start tag whose tag name is one of: "b", "big", "code", "em",
+                                // "font", "i", "s", "small", "strike", "strong", "tt", "u"
+                                "b" | "big" | "code" | "em" | "font" | "i" | "s" | "small"
+                                | "strike" | "strong" | "tt" | "u" => {
+                                    // Reconstruct the active formatting elements, if any.
+                                    self.reconstruct_active_formatting_elements();
+                                    // Insert an HTML element for the token. Push onto the list of
+                                    // active formatting elements that element.
+                                    self.insert_element(tag, attributes.to_vec());
+                                    self.push_active_formatting_element(self.current_node());
+                                    token = self.next_token();
+                                    continue;
+                                }
+                                // A start tag whose tag name is "nobr"
+                                "nobr" => {
+                                    // Reconstruct the active formatting elements, if any.
+                                    self.reconstruct_active_formatting_elements();
+                                    // If the stack of open elements has a nobr element in scope,
+                                    // then this is a parse error; run the adoption agency
+                                    // algorithm for the token, then once again reconstruct the
+                                    // active formatting elements, if any.
+                                    let nobr = self.stack_of_open_elements.iter().rev().find(|n| {
+                                        n.borrow()
+                                            .get_element()
+                                            .is_some_and(|e| e.local_name() == "nobr")
+                                    });
+                                    if nobr.is_some_and(|n| self.has_node_in_scope(n)) {
+                                        self.run_adoption_agency_algorithm("nobr");
+                                        self.reconstruct_active_formatting_elements();
+                                    }
                                     // Insert an HTML element for the token. Push onto the list of
                                     // active formatting elements that element.
                                     self.insert_element(tag, attributes.to_vec());
-                                    token = self.t.next();
+                                    self.push_active_formatting_element(self.current_node());
+                                    token = self.next_token();
                                     continue;
                                 }
                                 // A start tag whose tag name is one of: "area", "br", "embed", "img", "keygen", "wbr"
-                                "img" => {
+                                // A start tag whose tag name is "input"
+                                // A start tag whose tag name is one of: "param", "source", "track"
+                                "area" | "br" | "embed" | "img" | "keygen" | "wbr" | "input"
+                                | "param" | "source" | "track" => {
                                     // Reconstruct the active formatting elements, if any.
+                                    self.reconstruct_active_formatting_elements();
 
                                     // Insert an HTML element for the token. Immediately pop the current node off the stack of open elements.
 
@@ -532,18 +2127,44 @@ impl HtmlParser {
                                     // Set the frameset-ok flag to "not ok".
 
                                     self.insert_element(tag, attributes.to_vec());
-                                    if self_closing {
-                                        self.stack_of_open_elements.pop();
+                                    self.stack_of_open_elements.pop();
+                                    token = self.next_token();
+                                    continue;
+                                }
+                                // A start tag whose tag name is "hr"
+                                "hr" => {
+                                    // If the stack of open elements has a p element in button
+                                    // scope, then close a p element.
+                                    if self.has_element_in_button_scope(ElementKind::P) {
+                                        self.close_p_element();
                                     }
-                                    token = self.t.next();
+                                    // Insert an HTML element for the token. Immediately pop the
+                                    // current node off the stack of open elements.
+                                    self.insert_element(tag, attributes.to_vec());
+                                    self.stack_of_open_elements.pop();
+                                    token = self.next_token();
                                     continue;
                                 }
+                                // Any other start tag
                                 _ => {
-                                    console_warning(
-                                        &self.browser,
-                                        format!("unsupported start tag in InBody {:?}", tag),
-                                    );
-                                    token = self.t.next();
+                                    // Reconstruct the active formatting elements, if any.
+                                    self.reconstruct_active_formatting_elements();
+                                    // Insert an HTML element for the token.
+                                    // Note: This element will be an ordinary element.
+                                    if ElementKind::from_str(tag).is_err() {
+                                        console_debug(
+                                            &self.browser,
+                                            format!("insert an unknown element {:?}", tag),
+                                        );
+                                    }
+                                    if self_closing {
+                                        self.parse_error(
+                                            "non-void-html-element-start-tag-with-trailing-solidus",
+                                        );
+                                    }
+                                    self.insert_element(tag, attributes.to_vec());
+                                    token = self.next_token();
+                                    continue;
                                 }
                             }
                         }
@@ -552,9 +2173,10 @@ impl HtmlParser {
                                 // An end tag whose tag name is "body"
                                 "body" => {
                                     self.mode = InsertionMode::AfterBody;
-                                    token = self.t.next();
+                                    token = self.next_token();
                                     if !self.contain_in_stack(ElementKind::Body) {
                                         // Parse error. Ignore the token.
+                                        self.parse_error("unexpected-end-tag");
                                         continue;
                                     }
                                     self.pop_until(ElementKind::Body);
@@ -568,7 +2190,7 @@ impl HtmlParser {
                                         self.mode = InsertionMode::AfterBody;
                                         assert!(self.pop_current_node(ElementKind::Html));
                                     } else {
-                                        token = self.t.next();
+                                        token = self.next_token();
                                     }
                                     continue;
                                 }
@@ -577,65 +2199,143 @@ impl HtmlParser {
                                 // "dir", "div", "dl", "fieldset", "figcaption", "figure",
                                 // "footer", "header", "hgroup", "listing", "main", "menu", "nav",
                                 // "ol", "pre", "section", "summary", "ul"
-                                "div" | "pre" | "ul" => {
+                                "div" | "pre" | "ul" | "ol" | "blockquote" | "section"
+                                | "article" | "nav" | "header" | "footer" | "dl" | "button" => {
                                     let element_kind = ElementKind::from_str(tag)
                                         .expect("failed to convert string to ElementKind");
-                                    token = self.t.next();
-                                    self.pop_until(element_kind);
+                                    // If the stack of open elements does not have an element in
+                                    // scope that is an HTML element with the same tag name as that
+                                    // of the token, then this is a parse error; ignore the token.
+                                    if self.has_element_in_scope(element_kind) {
+                                        self.pop_until(element_kind);
+                                    } else {
+                                        self.parse_error("unexpected-end-tag");
+                                    }
+                                    token = self.next_token();
+                                    continue;
+                                }
+                                // An end tag whose tag name is "form"
+                                "form" => {
+                                    token = self.next_token();
+                                    // 1. Let node be the element that the form element pointer is
+                                    // set to, or null if it is not set to an element.
+                                    //
+                                    // 2. Set the form element pointer to null.
+                                    let node = match self.form_element_pointer.take() {
+                                        Some(node) => node,
+                                        None => continue,
+                                    };
+                                    // 3. If node is null or if the stack of open elements does not
+                                    // have node in scope, then this is a parse error; return and
+                                    // ignore the token.
+                                    if !self.has_element_in_scope(ElementKind::Form) {
+                                        continue;
+                                    }
+                                    // 4. Generate implied end tags.
+                                    self.generate_implied_end_tags();
+                                    // 5. If the current node is not node, then this is a parse
+                                    // error.
+                                    //
+                                    // 6. Remove node from the stack of open elements.
+                                    self.stack_of_open_elements
+                                        .retain(|n| !Rc::ptr_eq(n, &node));
                                     continue;
                                 }
                                 // An end tag whose tag name is "p"
                                 "p" => {
-                                    let element_kind = ElementKind::from_str(tag)
-                                        .expect("failed to convert string to ElementKind");
-                                    token = self.t.next();
-                                    self.pop_until(element_kind);
+                                    // If the stack of open elements does not have a p element in
+                                    // button scope, then this is a parse error; insert an HTML
+                                    // element for a "p" start tag token with no attributes.
+                                    if !self.has_element_in_button_scope(ElementKind::P) {
+                                        self.insert_element("p", Vec::new());
+                                    }
+                                    // Close a p element.
+                                    token = self.next_token();
+                                    self.close_p_element();
                                     continue;
                                 }
                                 // An end tag whose tag name is "li"
-                                "li" => {
-                                    let element_kind = ElementKind::from_str(tag)
+                                // An end tag whose tag name is one of: "dd", "dt"
+                                "li" | "dd" | "dt" => {
+                                    let tag = tag.clone();
+                                    let element_kind = ElementKind::from_str(&tag)
                                         .expect("failed to convert string to ElementKind");
-                                    token = self.t.next();
-                                    self.pop_until(element_kind);
+                                    token = self.next_token();
+                                    // If the stack of open elements does not have an li element
+                                    // in list item scope, then this is a parse error; ignore the
+                                    // token.
+                                    let in_scope = if element_kind == ElementKind::Li {
+                                        self.has_element_in_list_item_scope(element_kind)
+                                    } else {
+                                        self.has_element_in_scope(element_kind)
+                                    };
+                                    // Otherwise, run these steps:
+                                    // 1. Generate implied end tags, except for li elements (dd or
+                                    // dt elements for a "dd" or "dt" end tag).
+                                    // 2. If the current node is not an li element, then this is a
+                                    // parse error.
+                                    // 3. Pop elements from the stack of open elements until an li
+                                    // element has been popped from the stack.
+                                    if in_scope {
+                                        self.generate_implied_end_tags_except(Some(&tag));
+                                        self.pop_until(element_kind);
+                                    }
                                     continue;
                                 }
                                 // An end tag whose tag name is one of: "h1", "h2", "h3", "h4",
                                 // "h5", "h6"
-                                "h1" | "h2" => {
-                                    let element_kind = ElementKind::from_str(tag)
-                                        .expect("failed to convert string to ElementKind");
-                                    token = self.t.next();
-                                    self.pop_until(element_kind);
+                                "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                                    token = self.next_token();
+                                    // If the stack of open elements does not have an element in
+                                    // scope that is an HTML element and whose tag name is one of
+                                    // "h1", "h2", "h3", "h4", "h5", or "h6", then this is a parse
+                                    // error; ignore the token.
+                                    let has_heading = self
+                                        .stack_of_open_elements
+                                        .iter()
+                                        .any(|n| n.borrow().element_kind().is_some_and(is_heading));
+                                    if !has_heading {
+                                        continue;
+                                    }
+                                    // Pop elements from the stack of open elements until an HTML
+                                    // element whose tag name is one of "h1", "h2", "h3", "h4",
+                                    // "h5", or "h6" has been popped from the stack.
+                                    while let Some(n) = self.stack_of_open_elements.pop() {
+                                        if n.borrow().element_kind().is_some_and(is_heading) {
+                                            break;
+                                        }
+                                    }
                                     continue;
                                 }
                                 // An end tag whose tag name is one of: "a", "b", "big", "code",
                                 // "em", "font", "i", "nobr", "s", "small", "strike", "strong",
                                 // "tt", "u"
-                                "a" => {
+                                "a" | "b" | "big" | "code" | "em" | "font" | "i" | "nobr" | "s"
+                                | "small" | "strike" | "strong" | "tt" | "u" => {
                                     // Run the adoption agency algorithm for the token.
-                                    let element_kind = ElementKind::from_str(tag)
-                                        .expect("failed to convert string to ElementKind");
-                                    token = self.t.next();
-                                    self.pop_until(element_kind);
+                                    let tag = tag.clone();
+                                    token = self.next_token();
+                                    if !self.run_adoption_agency_algorithm(&tag) {
+                                        self.close_element_with_tag_name(&tag);
+                                    }
                                     continue;
                                 }
+                                // Any other end tag
                                 _ => {
-                                    console_warning(
-                                        &self.browser,
-                                        format!("unsupported end tag InBody {:?}", tag),
-                                    );
-                                    token = self.t.next();
+                                    self.close_element_with_tag_name(tag);
+                                    token = self.next_token();
+                                    continue;
                                 }
                             }
                         }
                         // Any other character token
                         Some(HtmlToken::Char(c)) => {
-                            // TODO: Reconstruct the active formatting elements, if any.
+                            // Reconstruct the active formatting elements, if any.
+                            self.reconstruct_active_formatting_elements();
                             // Insert the token's character.
                             // TODO: Set the frameset-ok flag to "not ok".
                             self.insert_char(c);
-                            token = self.t.next();
+                            token = self.next_token();
                             continue;
                         }
                         Some(HtmlToken::Eof) | None => {
@@ -650,23 +2350,31 @@ impl HtmlParser {
                         Some(HtmlToken::Eof) | None => {
                             return self.window.clone();
                         }
+                        // An end tag whose tag name is "script"
+                        // Any other end tag
+                        // Pop the current node off the stack of open elements. Switch the
+                        // insertion mode to the original insertion mode.
+                        //
+                        // The tokenizer emits only the end tag of the current node in this mode,
+                        // so any other end tag is a parse error and ignored.
                         Some(HtmlToken::EndTag { ref tag }) => {
-                            if tag == "style" {
-                                self.pop_until(ElementKind::Style);
-                                self.mode = self.original_insertion_mode;
-                                token = self.t.next();
-                                continue;
-                            }
-                            if tag == "script" {
-                                self.pop_until(ElementKind::Script);
+                            let current_tag = self
+                                .current_node()
+                                .borrow()
+                                .get_element()
+                                .map(|e| e.local_name());
+                            if current_tag.as_ref() == Some(tag) {
+                                self.stack_of_open_elements.pop();
                                 self.mode = self.original_insertion_mode;
-                                token = self.t.next();
-                                continue;
+                            } else {
+                                self.parse_error("unexpected-end-tag");
                             }
+                            token = self.next_token();
+                            continue;
                         }
                         Some(HtmlToken::Char(c)) => {
                             self.insert_char(c);
-                            token = self.t.next();
+                            token = self.next_token();
                             continue;
                         }
                         _ => {}
@@ -675,61 +2383,1140 @@ impl HtmlParser {
                     self.mode = self.original_insertion_mode;
                 } // end of InsertionMode::Text
 
-                // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-afterbody
-                InsertionMode::AfterBody => {
+                // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-intable
+                InsertionMode::InTable => {
                     match token {
-                        Some(HtmlToken::Char(_c)) => {
-                            // Not align with the spec.
-                            // TODO: Process the token using the rules for the "in body" insertion
-                            // mode.
-                            token = self.t.next();
+                        // A character token, if the current node is table, tbody, template,
+                        // tfoot, thead, or tr element
+                        Some(HtmlToken::Char(_)) => {
+                            // Let the pending table character tokens be an empty list of tokens.
+                            // Let the original insertion mode be the current insertion mode.
+                            // Switch the insertion mode to "in table text" and reprocess the
+                            // token.
+                            self.pending_table_character_tokens = Vec::new();
+                            self.original_insertion_mode = self.mode;
+                            self.mode = InsertionMode::InTableText;
+                            continue;
+                        }
+                        // A comment token
+                        // Insert a comment.
+                        Some(HtmlToken::Comment(ref data)) => {
+                            self.insert_comment(data, None);
+                            token = self.next_token();
+                            continue;
+                        }
+                        // A DOCTYPE token
+                        // Parse error. Ignore the token.
+                        Some(HtmlToken::Doctype { .. }) => {
+                            self.parse_error("unexpected-doctype");
+                            token = self.next_token();
                             continue;
                         }
+                        Some(HtmlToken::StartTag {
+                            ref tag,
+                            self_closing: _,
+                            ref attributes,
+                        }) => match tag.as_str() {
+                            // A start tag whose tag name is "caption"
+                            "caption" => {
+                                // Clear the stack back to a table context.
+                                //
+                                // Insert a marker at the end of the list of active formatting
+                                // elements.
+                                //
+                                // Insert an HTML element for the token, then switch the insertion
+                                // mode to "in caption".
+                                self.clear_stack_back_to(&[ElementKind::Table]);
+                                self.insert_marker();
+                                self.insert_element(tag, attributes.to_vec());
+                                self.mode = InsertionMode::InCaption;
+                                token = self.next_token();
+                                continue;
+                            }
+                            // A start tag whose tag name is "colgroup"
+                            "colgroup" => {
+                                // Clear the stack back to a table context.
+                                //
+                                // Insert an HTML element for the token, then switch the insertion
+                                // mode to "in column group".
+                                self.clear_stack_back_to(&[ElementKind::Table]);
+                                self.insert_element(tag, attributes.to_vec());
+                                self.mode = InsertionMode::InColumnGroup;
+                                token = self.next_token();
+                                continue;
+                            }
+                            // A start tag whose tag name is "col"
+                            "col" => {
+                                // Clear the stack back to a table context.
+                                //
+                                // Insert an HTML element for a "colgroup" start tag token with no
+                                // attributes, then switch the insertion mode to "in column group".
+                                //
+                                // Reprocess the current token.
+                                self.clear_stack_back_to(&[ElementKind::Table]);
+                                self.insert_element("colgroup", Vec::new());
+                                self.mode = InsertionMode::InColumnGroup;
+                                continue;
+                            }
+                            // A start tag whose tag name is one of: "tbody", "tfoot", "thead"
+                            "tbody" | "tfoot" | "thead" => {
+                                // Clear the stack back to a table context.
+                                //
+                                // Insert an HTML element for the token, then switch the insertion
+                                // mode to "in table body".
+                                self.clear_stack_back_to(&[ElementKind::Table]);
+                                self.insert_element(tag, attributes.to_vec());
+                                self.mode = InsertionMode::InTableBody;
+                                token = self.next_token();
+                                continue;
+                            }
+                            // A start tag whose tag name is one of: "td", "th", "tr"
+                            "td" | "th" | "tr" => {
+                                // Clear the stack back to a table context.
+                                //
+                                // Insert an HTML element for a "tbody" start tag token with no
+                                // attributes, then switch the insertion mode to "in table body".
+                                //
+                                // Reprocess the current token.
+                                self.clear_stack_back_to(&[ElementKind::Table]);
+                                self.insert_element("tbody", Vec::new());
+                                self.mode = InsertionMode::InTableBody;
+                                continue;
+                            }
+                            // A start tag whose tag name is "table"
+                            "table" => {
+                                // Parse error.
+                                //
+                                // If the stack of open elements does not have a table element in
+                                // table scope, ignore the token.
+                                if !self.has_element_in_table_scope(ElementKind::Table) {
+                                    token = self.next_token();
+                                    continue;
+                                }
+                                // Otherwise:
+                                //
+                                // Pop elements from this stack until a table element has been
+                                // popped from the stack.
+                                //
+                                // Reset the insertion mode appropriately.
+                                //
+                                // Reprocess the token.
+                                self.pop_until(ElementKind::Table);
+                                self.reset_insertion_mode_appropriately();
+                                continue;
+                            }
+                            // A start tag whose tag name is "form"
+                            "form" => {
+                                // Parse error.
+                                //
+                                // If there is a template element on the stack of open elements,
+                                // or if the form element pointer is not null, ignore the token.
+                                //
+                                // Otherwise:
+                                //
+                                // Insert an HTML element for the token, and set the form element
+                                // pointer to point to the element created.
+                                //
+                                // Pop that form element off the stack of open elements.
+                                if self.form_element_pointer.is_none() {
+                                    self.insert_element(tag, attributes.to_vec());
+                                    self.form_element_pointer = self.stack_of_open_elements.pop();
+                                }
+                                token = self.next_token();
+                                continue;
+                            }
+                            // A start tag whose tag name is "input" (with a type attribute whose
+                            // value is "hidden")
+                            _ => {
+                                // Parse error.
+                                //
+                                // Insert an HTML element for the token.
+                                //
+                                // Pop that input element off the stack of open elements.
+                                self.insert_element(tag, attributes.to_vec());
+                                self.stack_of_open_elements.pop();
+                                token = self.next_token();
+                                continue;
+                            }
+                        },
                         Some(HtmlToken::EndTag { ref tag }) => {
-                            if tag == "html" {
-                                self.mode = InsertionMode::AfterAfterBody;
-                                token = self.t.next();
+                            // An end tag whose tag name is "table"
+                            if tag == "table" {
+                                token = self.next_token();
+                                // If the stack of open elements does not have a table element in
+                                // table scope, this is a parse error; ignore the token.
+                                if !self.has_element_in_table_scope(ElementKind::Table) {
+                                    continue;
+                                }
+                                // Otherwise:
+                                //
+                                // Pop elements from this stack until a table element has been
+                                // popped from the stack.
+                                //
+                                // Reset the insertion mode appropriately.
+                                self.pop_until(ElementKind::Table);
+                                self.reset_insertion_mode_appropriately();
                                 continue;
                             }
+                            // An end tag whose tag name is one of: "body", "caption", "col",
+                            // "colgroup", "html", "tbody", "td", "tfoot", "th", "thead", "tr"
+                            // Parse error. Ignore the token.
+                            self.parse_error("unexpected-end-tag");
+                            token = self.next_token();
+                            continue;
                         }
                         Some(HtmlToken::Eof) | None => {
                             return self.window.clone();
                         }
-                        _ => {}
                     }
+                } // end of InsertionMode::InTable
 
-                    self.mode = InsertionMode::InBody;
-                } // end of InsertionMode::AfterBody
-
-                // https://html.spec.whatwg.org/multipage/parsing.html#the-after-after-body-insertion-mode
-                InsertionMode::AfterAfterBody => {
+                // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-intabletext
+                InsertionMode::InTableText => {
                     match token {
-                        Some(HtmlToken::Char(_c)) => {
-                            // Not align with the spec.
-                            // TODO: Process the token using the rules for the "in body" insertion
-                            // mode.
-                            token = self.t.next();
+                        // A character token that is U+0000 NULL
+                        // Parse error. Ignore the token.
+                        //
+                        // Any other character token
+                        // Append the character token to the pending table character tokens list.
+                        Some(HtmlToken::Char(c)) => {
+                            if c != '\0' {
+                                self.pending_table_character_tokens.push(c);
+                            }
+                            token = self.next_token();
                             continue;
                         }
-                        Some(HtmlToken::Eof) | None => {
-                            return self.window.clone();
+                        _ => {
+                            // Anything else
+                            //
+                            // If any of the tokens in the pending table character tokens list are
+                            // character tokens that are not ASCII whitespace, then this is a parse
+                            // error: reinsert the character tokens in the pending table character
+                            // tokens list using the rules given in the "anything else" entry in
+                            // the "in table" insertion mode.
+                            //
+                            // Otherwise, insert the characters given by the pending table
+                            // character tokens list.
+                            let chars = core::mem::take(&mut self.pending_table_character_tokens);
+                            self.foster_parenting = chars.iter().any(|c| !c.is_ascii_whitespace());
+                            for c in chars {
+                                self.insert_char(c);
+                            }
+                            self.foster_parenting = false;
+
+                            // Switch the insertion mode to the original insertion mode and
+                            // reprocess the token.
+                            self.mode = self.original_insertion_mode;
+                            continue;
                         }
-                        _ => {}
                     }
+                } // end of InsertionMode::InTableText
 
-                    self.mode = InsertionMode::InBody;
-                } // end of InsertionMode::AfterAfterBody
-            } // end of match self.mode {}
-        } // end of while token.is_some {}
+                // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-incaption
+                InsertionMode::InCaption => {
+                    // An end tag whose tag name is "caption"
+                    if is_end_tag(&token, &["caption"]) {
+                        token = self.next_token();
+                        // If the stack of open elements does not have a caption element in table
+                        // scope, this is a parse error; ignore the token.
+                        if !self.has_element_in_table_scope(ElementKind::Caption) {
+                            continue;
+                        }
+                        // Otherwise:
+                        //
+                        // Generate implied end tags.
+                        //
+                        // Pop elements from this stack until a caption element has been popped
+                        // from the stack.
+                        //
+                        // Clear the list of active formatting elements up to the last marker.
+                        //
+                        // Switch the insertion mode to "in table".
+                        self.generate_implied_end_tags();
+                        self.pop_until(ElementKind::Caption);
+                        self.clear_active_formatting_elements_to_last_marker();
+                        self.mode = InsertionMode::InTable;
+                        continue;
+                    }
 
-        self.window.clone()
-    }
-}
+                    // A start tag whose tag name is one of: "caption", "col", "colgroup",
+                    // "tbody", "td", "tfoot", "th", "thead", "tr"
+                    // An end tag whose tag name is "table"
+                    if is_start_tag(&token, &TABLE_PART_START_TAGS)
+                        || is_end_tag(&token, &["table"])
+                    {
+                        // If the stack of open elements does not have a caption element in table
+                        // scope, this is a parse error; ignore the token.
+                        if !self.has_element_in_table_scope(ElementKind::Caption) {
+                            token = self.next_token();
+                            continue;
+                        }
+                        // Otherwise:
+                        //
+                        // Generate implied end tags. Pop elements from this stack until a caption
+                        // element has been popped from the stack. Switch the insertion mode to
+                        // "in table".
+                        //
+                        // Reprocess the token.
+                        self.generate_implied_end_tags();
+                        self.pop_until(ElementKind::Caption);
+                        self.clear_active_formatting_elements_to_last_marker();
+                        self.mode = InsertionMode::InTable;
+                        continue;
+                    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+                    // An end tag whose tag name is one of: "body", "col", "colgroup", "html",
+                    // "tbody", "td", "tfoot", "th", "thead", "tr"
+                    // Parse error. Ignore the token.
+                    self.parse_error("unexpected-end-tag");
+                    token = self.next_token();
+                    continue;
+                } // end of InsertionMode::InCaption
+
+                // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-incolgroup
+                InsertionMode::InColumnGroup => {
+                    match token {
+                        // A character token that is one of U+0009 CHARACTER TABULATION, U+000A
+                        // LINE FEED (LF), U+000C FORM FEED (FF), U+000D CARRIAGE RETURN (CR), or
+                        // U+0020 SPACE
+                        // Insert the character.
+                        Some(HtmlToken::Char(c)) if c.is_ascii_whitespace() => {
+                            self.insert_char(c);
+                            token = self.next_token();
+                            continue;
+                        }
+                        // A comment token
+                        // Insert a comment.
+                        Some(HtmlToken::Comment(ref data)) => {
+                            self.insert_comment(data, None);
+                            token = self.next_token();
+                            continue;
+                        }
+                        // A DOCTYPE token
+                        // Parse error. Ignore the token.
+                        Some(HtmlToken::Doctype { .. }) => {
+                            self.parse_error("unexpected-doctype");
+                            token = self.next_token();
+                            continue;
+                        }
+                        // A start tag whose tag name is "col"
+                        Some(HtmlToken::StartTag {
+                            ref tag,
+                            self_closing: _,
+                            ref attributes,
+                        }) if tag == "col" => {
+                            // Insert an HTML element for the token. Immediately pop the current
+                            // node off the stack of open elements.
+                            self.insert_element(tag, attributes.to_vec());
+                            self.stack_of_open_elements.pop();
+                            token = self.next_token();
+                            continue;
+                        }
+                        // An end tag whose tag name is "colgroup"
+                        Some(HtmlToken::EndTag { ref tag }) if tag == "colgroup" => {
+                            token = self.next_token();
+                            // If the current node is not a colgroup element, then this is a parse
+                            // error; ignore the token.
+                            if self.pop_current_node(ElementKind::Colgroup) {
+                                // Otherwise, pop the current node from the stack of open
+                                // elements. Switch the insertion mode to "in table".
+                                self.mode = InsertionMode::InTable;
+                            }
+                            continue;
+                        }
+                        // An end tag whose tag name is "col"
+                        // Parse error. Ignore the token.
+                        Some(HtmlToken::EndTag { ref tag }) if tag == "col" => {
+                            self.parse_error("unexpected-end-tag");
+                            token = self.next_token();
+                            continue;
+                        }
+                        Some(HtmlToken::Eof) | None => {
+                            return self.window.clone();
+                        }
+                        _ => {
+                            // Anything else
+                            //
+                            // If the current node is not a colgroup element, then this is a parse
+                            // error; ignore the token.
+                            if !self.pop_current_node(ElementKind::Colgroup) {
+                                token = self.next_token();
+                                continue;
+                            }
+                            // Otherwise, pop the current node from the stack of open elements.
+                            //
+                            // Switch the insertion mode to "in table".
+                            //
+                            // Reprocess the token.
+                            self.mode = InsertionMode::InTable;
+                            continue;
+                        }
+                    }
+                } // end of InsertionMode::InColumnGroup
+
+                // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-intbody
+                InsertionMode::InTableBody => {
+                    match token {
+                        Some(HtmlToken::StartTag {
+                            ref tag,
+                            self_closing: _,
+                            ref attributes,
+                        }) => match tag.as_str() {
+                            // A start tag whose tag name is "tr"
+                            "tr" => {
+                                // Clear the stack back to a table body context.
+                                //
+                                // Insert an HTML element for the token, then switch the insertion
+                                // mode to "in row".
+                                self.clear_stack_back_to(&TABLE_BODY_CONTEXT);
+                                self.insert_element(tag, attributes.to_vec());
+                                self.mode = InsertionMode::InRow;
+                                token = self.next_token();
+                                continue;
+                            }
+                            // A start tag whose tag name is one of: "th", "td"
+                            "th" | "td" => {
+                                // Parse error.
+                                //
+                                // Clear the stack back to a table body context.
+                                //
+                                // Insert an HTML element for a "tr" start tag token with no
+                                // attributes, then switch the insertion mode to "in row".
+                                //
+                                // Reprocess the current token.
+                                self.clear_stack_back_to(&TABLE_BODY_CONTEXT);
+                                self.insert_element("tr", Vec::new());
+                                self.mode = InsertionMode::InRow;
+                                continue;
+                            }
+                            // A start tag whose tag name is one of: "caption", "col", "colgroup",
+                            // "tbody", "tfoot", "thead"
+                            _ => {
+                                if !self.close_table_body() {
+                                    token = self.next_token();
+                                }
+                                continue;
+                            }
+                        },
+                        Some(HtmlToken::EndTag { ref tag }) => match tag.as_str() {
+                            // An end tag whose tag name is one of: "tbody", "tfoot", "thead"
+                            "tbody" | "tfoot" | "thead" => {
+                                let element_kind = ElementKind::from_str(tag)
+                                    .expect("failed to convert string to ElementKind");
+                                token = self.next_token();
+                                // If the stack of open elements does not have an element in table
+                                // scope that is an HTML element with the same tag name as the
+                                // token, this is a parse error; ignore the token.
+                                if !self.has_element_in_table_scope(element_kind) {
+                                    continue;
+                                }
+                                // Otherwise:
+                                //
+                                // Clear the stack back to a table body context.
+                                //
+                                // Pop the current node from the stack of open elements. Switch the
+                                // insertion mode to "in table".
+                                self.clear_stack_back_to(&TABLE_BODY_CONTEXT);
+                                self.stack_of_open_elements.pop();
+                                self.mode = InsertionMode::InTable;
+                                continue;
+                            }
+                            // An end tag whose tag name is "table"
+                            "table" => {
+                                if !self.close_table_body() {
+                                    token = self.next_token();
+                                }
+                                continue;
+                            }
+                            // An end tag whose tag name is one of: "body", "caption", "col",
+                            // "colgroup", "html", "td", "th", "tr"
+                            // Parse error. Ignore the token.
+                            _ => {
+                                self.parse_error("unexpected-end-tag");
+                                token = self.next_token();
+                                continue;
+                            }
+                        },
+                        _ => {
+                            token = self.next_token();
+                            continue;
+                        }
+                    }
+                } // end of InsertionMode::InTableBody
+
+                // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-intr
+                InsertionMode::InRow => {
+                    match token {
+                        Some(HtmlToken::StartTag {
+                            ref tag,
+                            self_closing: _,
+                            ref attributes,
+                        }) => match tag.as_str() {
+                            // A start tag whose tag name is one of: "th", "td"
+                            "th" | "td" => {
+                                // Clear the stack back to a table row context.
+                                //
+                                // Insert an HTML element for the token, then switch the insertion
+                                // mode to "in cell".
+                                //
+                                // Insert a marker at the end of the list of active formatting
+                                // elements.
+                                self.clear_stack_back_to(&TABLE_ROW_CONTEXT);
+                                self.insert_element(tag, attributes.to_vec());
+                                self.mode = InsertionMode::InCell;
+                                self.insert_marker();
+                                token = self.next_token();
+                                continue;
+                            }
+                            // A start tag whose tag name is one of: "caption", "col", "colgroup",
+                            // "tbody", "tfoot", "thead", "tr"
+                            _ => {
+                                if !self.close_table_row() {
+                                    token = self.next_token();
+                                }
+                                continue;
+                            }
+                        },
+                        Some(HtmlToken::EndTag { ref tag }) => match tag.as_str() {
+                            // An end tag whose tag name is "tr"
+                            "tr" => {
+                                self.close_table_row();
+                                token = self.next_token();
+                                continue;
+                            }
+                            // An end tag whose tag name is "table"
+                            "table" => {
+                                if !self.close_table_row() {
+                                    token = self.next_token();
+                                }
+                                continue;
+                            }
+                            // An end tag whose tag name is one of: "tbody", "tfoot", "thead"
+                            "tbody" | "tfoot" | "thead" => {
+                                let element_kind = ElementKind::from_str(tag)
+                                    .expect("failed to convert string to ElementKind");
+                                // If the stack of open elements does not have an element in table
+                                // scope that is an HTML element with the same tag name as the
+                                // token, this is a parse error; ignore the token.
+                                if !self.has_element_in_table_scope(element_kind)
+                                    || !self.close_table_row()
+                                {
+                                    token = self.next_token();
+                                }
+                                continue;
+                            }
+                            // An end tag whose tag name is one of: "body", "caption", "col",
+                            // "colgroup", "html", "td", "th"
+                            // Parse error. Ignore the token.
+                            _ => {
+                                self.parse_error("unexpected-end-tag");
+                                token = self.next_token();
+                                continue;
+                            }
+                        },
+                        _ => {
+                            token = self.next_token();
+                            continue;
+                        }
+                    }
+                } // end of InsertionMode::InRow
+
+                // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-intd
+                InsertionMode::InCell => {
+                    match token {
+                        Some(HtmlToken::EndTag { ref tag }) => match tag.as_str() {
+                            // An end tag whose tag name is one of: "td", "th"
+                            "td" | "th" => {
+                                let element_kind = ElementKind::from_str(tag)
+                                    .expect("failed to convert string to ElementKind");
+                                token = self.next_token();
+                                // If the stack of open elements does not have an element in table
+                                // scope that is an HTML element with the same tag name as that of
+                                // the token, then this is a parse error; ignore the token.
+                                if !self.has_element_in_table_scope(element_kind) {
+                                    continue;
+                                }
+                                // Otherwise:
+                                //
+                                // Generate implied end tags.
+                                //
+                                // Pop elements from the stack of open elements stack until an
+                                // HTML element with the same tag name as the token has been
+                                // popped from the stack.
+                                //
+                                // Clear the list of active formatting elements up to the last
+                                // marker.
+                                //
+                                // Switch the insertion mode to "in row".
+                                self.generate_implied_end_tags();
+                                self.pop_until(element_kind);
+                                self.clear_active_formatting_elements_to_last_marker();
+                                self.mode = InsertionMode::InRow;
+                                continue;
+                            }
+                            // An end tag whose tag name is one of: "table", "tbody", "tfoot",
+                            // "thead", "tr"
+                            "table" | "tbody" | "tfoot" | "thead" | "tr" => {
+                                let element_kind = ElementKind::from_str(tag)
+                                    .expect("failed to convert string to ElementKind");
+                                // If the stack of open elements does not have an element in table
+                                // scope that is an HTML element with the same tag name as that of
+                                // the token, this is a parse error; ignore the token.
+                                if !self.has_element_in_table_scope(element_kind) {
+                                    token = self.next_token();
+                                    continue;
+                                }
+                                // Otherwise, close the cell and reprocess the token.
+                                self.close_cell();
+                                continue;
+                            }
+                            // An end tag whose tag name is one of: "body", "caption", "col",
+                            // "colgroup", "html"
+                            // Parse error. Ignore the token.
+                            _ => {
+                                self.parse_error("unexpected-end-tag");
+                                token = self.next_token();
+                                continue;
+                            }
+                        },
+                        // A start tag whose tag name is one of: "caption", "col", "colgroup",
+                        // "tbody", "td", "tfoot", "th", "thead", "tr"
+                        _ => {
+                            // Assert: The stack of open elements has a td or th element in table
+                            // scope.
+                            if !self.has_element_in_table_scope(ElementKind::Td)
+                                && !self.has_element_in_table_scope(ElementKind::Th)
+                            {
+                                token = self.next_token();
+                                continue;
+                            }
+                            // Close the cell and reprocess the token.
+                            self.close_cell();
+                            continue;
+                        }
+                    }
+                } // end of InsertionMode::InCell
+
+                // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inselect
+                InsertionMode::InSelect => {
+                    match token {
+                        // Any other character token
+                        // Insert the token's character.
+                        Some(HtmlToken::Char(c)) => {
+                            self.insert_char(c);
+                            token = self.next_token();
+                            continue;
+                        }
+                        // A comment token
+                        // Insert a comment.
+                        Some(HtmlToken::Comment(ref data)) => {
+                            self.insert_comment(data, None);
+                            token = self.next_token();
+                            continue;
+                        }
+                        Some(HtmlToken::StartTag {
+                            ref tag,
+                            ref attributes,
+                            ..
+                        }) => match tag.as_str() {
+                            // A start tag whose tag name is "option"
+                            "option" => {
+                                // If the current node is an option element, pop that node from
+                                // the stack of open elements.
+                                self.pop_current_node(ElementKind::Option);
+                                // Insert an HTML element for the token.
+                                self.insert_element(tag, attributes.to_vec());
+                                token = self.next_token();
+                                continue;
+                            }
+                            // A start tag whose tag name is "optgroup"
+                            "optgroup" => {
+                                // If the current node is an option element, pop that node from
+                                // the stack of open elements.
+                                //
+                                // If the current node is an optgroup element, pop that node from
+                                // the stack of open elements.
+                                self.pop_current_node(ElementKind::Option);
+                                self.pop_current_node(ElementKind::Optgroup);
+                                // Insert an HTML element for the token.
+                                self.insert_element(tag, attributes.to_vec());
+                                token = self.next_token();
+                                continue;
+                            }
+                            // A start tag whose tag name is "select"
+                            // A start tag whose tag name is one of: "input", "keygen", "textarea"
+                            "select" | "input" | "keygen" | "textarea" => {
+                                // Parse error.
+                                //
+                                // If the stack of open elements does not have a select element
+                                // in select scope, ignore the token.
+                                if !self.has_element_in_select_scope(ElementKind::Select) {
+                                    token = self.next_token();
+                                    continue;
+                                }
+                                // Pop elements from the stack of open elements until a select
+                                // element has been popped from the stack.
+                                //
+                                // Reset the insertion mode appropriately.
+                                self.pop_until(ElementKind::Select);
+                                self.reset_insertion_mode_appropriately();
+                                // It just gets treated like an end tag for "select", or the
+                                // token is reprocessed.
+                                if tag == "select" {
+                                    token = self.next_token();
+                                }
+                                continue;
+                            }
+                            // Anything else
+                            // Parse error. Ignore the token.
+                            _ => {
+                                self.parse_error("unexpected-start-tag");
+                                token = self.next_token();
+                                continue;
+                            }
+                        },
+                        Some(HtmlToken::EndTag { ref tag }) => match tag.as_str() {
+                            // An end tag whose tag name is "optgroup"
+                            "optgroup" => {
+                                // First, if the current node is an option element, and the node
+                                // immediately before it in the stack of open elements is an
+                                // optgroup element, then pop the current node from the stack of
+                                // open elements.
+                                let len = self.stack_of_open_elements.len();
+                                if len >= 2
+                                    && self.current_node().borrow().element_kind()
+                                        == Some(ElementKind::Option)
+                                    && self.stack_of_open_elements[len - 2].borrow().element_kind()
+                                        == Some(ElementKind::Optgroup)
+                                {
+                                    self.stack_of_open_elements.pop();
+                                }
+                                // If the current node is an optgroup element, then pop that node
+                                // from the stack of open elements. Otherwise, this is a parse
+                                // error; ignore the token.
+                                self.pop_current_node(ElementKind::Optgroup);
+                                token = self.next_token();
+                                continue;
+                            }
+                            // An end tag whose tag name is "option"
+                            "option" => {
+                                // If the current node is an option element, then pop that node
+                                // from the stack of open elements. Otherwise, this is a parse
+                                // error; ignore the token.
+                                self.pop_current_node(ElementKind::Option);
+                                token = self.next_token();
+                                continue;
+                            }
+                            // An end tag whose tag name is "select"
+                            "select" => {
+                                token = self.next_token();
+                                // If the stack of open elements does not have a select element
+                                // in select scope, this is a parse error; ignore the token.
+                                if !self.has_element_in_select_scope(ElementKind::Select) {
+                                    continue;
+                                }
+                                // Otherwise:
+                                //
+                                // Pop elements from the stack of open elements until a select
+                                // element has been popped from the stack.
+                                //
+                                // Reset the insertion mode appropriately.
+                                self.pop_until(ElementKind::Select);
+                                self.reset_insertion_mode_appropriately();
+                                continue;
+                            }
+                            // Anything else
+                            // Parse error. Ignore the token.
+                            _ => {
+                                self.parse_error("unexpected-end-tag");
+                                token = self.next_token();
+                                continue;
+                            }
+                        },
+                        // A DOCTYPE token
+                        // Parse error. Ignore the token.
+                        Some(HtmlToken::Doctype { .. }) => {
+                            self.parse_error("unexpected-doctype");
+                            token = self.next_token();
+                            continue;
+                        }
+                        // An end-of-file token
+                        // Process the token using the rules for the "in body" insertion mode.
+                        Some(HtmlToken::Eof) | None => {
+                            return self.window.clone();
+                        }
+                    }
+                } // end of InsertionMode::InSelect
+
+                // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-afterbody
+                InsertionMode::AfterBody => {
+                    match token {
+                        // A comment token
+                        // Insert a comment as the last child of the first element in the stack
+                        // of open elements (the html element).
+                        Some(HtmlToken::Comment(ref data)) => {
+                            let html = self.stack_of_open_elements.first().cloned();
+                            self.insert_comment(data, html);
+                            token = self.next_token();
+                            continue;
+                        }
+                        // A DOCTYPE token
+                        // Parse error. Ignore the token.
+                        Some(HtmlToken::Doctype { .. }) => {
+                            self.parse_error("unexpected-doctype");
+                            token = self.next_token();
+                            continue;
+                        }
+                        Some(HtmlToken::Char(_c)) => {
+                            // Not align with the spec.
+                            // TODO: Process the token using the rules for the "in body" insertion
+                            // mode.
+                            token = self.next_token();
+                            continue;
+                        }
+                        Some(HtmlToken::EndTag { ref tag }) => {
+                            if tag == "html" {
+                                self.mode = InsertionMode::AfterAfterBody;
+                                token = self.next_token();
+                                continue;
+                            }
+                        }
+                        Some(HtmlToken::Eof) | None => {
+                            return self.window.clone();
+                        }
+                        _ => {}
+                    }
+
+                    self.mode = InsertionMode::InBody;
+                } // end of InsertionMode::AfterBody
+
+                // https://html.spec.whatwg.org/multipage/parsing.html#the-after-after-body-insertion-mode
+                InsertionMode::AfterAfterBody => {
+                    match token {
+                        // A comment token
+                        // Insert a comment as the last child of the Document object.
+                        Some(HtmlToken::Comment(ref data)) => {
+                            let document = self.window.borrow().document();
+                            self.insert_comment(data, Some(document));
+                            token = self.next_token();
+                            continue;
+                        }
+                        // A DOCTYPE token
+                        // Process the token using the rules for the "in body" insertion mode.
+                        Some(HtmlToken::Doctype { .. }) => {
+                            self.parse_error("unexpected-doctype");
+                            token = self.next_token();
+                            continue;
+                        }
+                        Some(HtmlToken::Char(_c)) => {
+                            // Not align with the spec.
+                            // TODO: Process the token using the rules for the "in body" insertion
+                            // mode.
+                            token = self.next_token();
+                            continue;
+                        }
+                        Some(HtmlToken::Eof) | None => {
+                            return self.window.clone();
+                        }
+                        _ => {}
+                    }
+
+                    self.mode = InsertionMode::InBody;
+                } // end of InsertionMode::AfterAfterBody
+            } // end of match mode {}
+        } // end of while token.is_some {}
+
+        self.window.clone()
+    }
+}
+
+/// Start tags that close the current caption, row or cell in the table-related insertion modes.
+const TABLE_PART_START_TAGS: [&str; 9] = [
+    "caption", "col", "colgroup", "tbody", "td", "tfoot", "th", "thead", "tr",
+];
+
+/// https://html.spec.whatwg.org/multipage/parsing.html#clear-the-stack-back-to-a-table-body-context
+const TABLE_BODY_CONTEXT: [ElementKind; 3] =
+    [ElementKind::Tbody, ElementKind::Tfoot, ElementKind::Thead];
+
+/// https://html.spec.whatwg.org/multipage/parsing.html#clear-the-stack-back-to-a-table-row-context
+const TABLE_ROW_CONTEXT: [ElementKind; 1] = [ElementKind::Tr];
+
+/// Returns true if `token` is a start tag whose tag name is one of `tags`.
+fn is_start_tag(token: &Option<HtmlToken>, tags: &[&str]) -> bool {
+    match token {
+        Some(HtmlToken::StartTag { tag, .. }) => tags.contains(&tag.as_str()),
+        _ => false,
+    }
+}
+
+/// Returns true if `token` is an end tag whose tag name is one of `tags`.
+fn is_end_tag(token: &Option<HtmlToken>, tags: &[&str]) -> bool {
+    match token {
+        Some(HtmlToken::EndTag { tag }) => tags.contains(&tag.as_str()),
+        _ => false,
+    }
+}
+
+/// Returns true if an element whose tag name is `local_name` is one of the element types of the
+/// "has an element in scope" list.
+/// https://html.spec.whatwg.org/multipage/parsing.html#has-an-element-in-scope
+fn is_scope_boundary(local_name: &str) -> bool {
+    matches!(
+        local_name,
+        "applet" | "caption" | "html" | "table" | "td" | "th" | "marquee" | "object" | "template"
+    )
+}
+
+/// Returns true if `kind` is one of h1, h2, h3, h4, h5 and h6.
+fn is_heading(kind: ElementKind) -> bool {
+    matches!(
+        kind,
+        ElementKind::H1
+            | ElementKind::H2
+            | ElementKind::H3
+            | ElementKind::H4
+            | ElementKind::H5
+            | ElementKind::H6
+    )
+}
+
+/// Returns true if an element whose tag name is `local_name` is in the special category.
+/// https://html.spec.whatwg.org/multipage/parsing.html#special
+fn is_special(local_name: &str) -> bool {
+    matches!(
+        local_name,
+        "address"
+            | "applet"
+            | "area"
+            | "article"
+            | "aside"
+            | "base"
+            | "basefont"
+            | "bgsound"
+            | "blockquote"
+            | "body"
+            | "br"
+            | "button"
+            | "caption"
+            | "center"
+            | "col"
+            | "colgroup"
+            | "dd"
+            | "details"
+            | "dir"
+            | "div"
+            | "dl"
+            | "dt"
+            | "embed"
+            | "fieldset"
+            | "figcaption"
+            | "figure"
+            | "footer"
+            | "form"
+            | "frame"
+            | "frameset"
+            | "h1"
+            | "h2"
+            | "h3"
+            | "h4"
+            | "h5"
+            | "h6"
+            | "head"
+            | "header"
+            | "hgroup"
+            | "hr"
+            | "html"
+            | "iframe"
+            | "img"
+            | "input"
+            | "keygen"
+            | "li"
+            | "link"
+            | "listing"
+            | "main"
+            | "marquee"
+            | "menu"
+            | "meta"
+            | "nav"
+            | "noembed"
+            | "noframes"
+            | "noscript"
+            | "object"
+            | "ol"
+            | "p"
+            | "param"
+            | "plaintext"
+            | "pre"
+            | "script"
+            | "search"
+            | "section"
+            | "select"
+            | "source"
+            | "style"
+            | "summary"
+            | "table"
+            | "tbody"
+            | "td"
+            | "template"
+            | "textarea"
+            | "tfoot"
+            | "th"
+            | "thead"
+            | "title"
+            | "tr"
+            | "track"
+            | "ul"
+            | "wbr"
+            | "xmp"
+    )
+}
+
+/// The public identifiers that set the Document to quirks mode when a DOCTYPE's public identifier
+/// starts with one of them.
+/// https://html.spec.whatwg.org/multipage/parsing.html#the-initial-insertion-mode
+const QUIRKS_PUBLIC_IDENTIFIER_PREFIXES: [&str; 55] = [
+    "+//silmaril//dtd html pro v0r11 19970101//",
+    "-//as//dtd html 3.0 aswedit + extensions//",
+    "-//advasoft ltd//dtd html 3.0 aswedit + extensions//",
+    "-//ietf//dtd html 2.0 level 1//",
+    "-//ietf//dtd html 2.0 level 2//",
+    "-//ietf//dtd html 2.0 strict level 1//",
+    "-//ietf//dtd html 2.0 strict level 2//",
+    "-//ietf//dtd html 2.0 strict//",
+    "-//ietf//dtd html 2.0//",
+    "-//ietf//dtd html 2.1e//",
+    "-//ietf//dtd html 3.0//",
+    "-//ietf//dtd html 3.2 final//",
+    "-//ietf//dtd html 3.2//",
+    "-//ietf//dtd html 3//",
+    "-//ietf//dtd html level 0//",
+    "-//ietf//dtd html level 1//",
+    "-//ietf//dtd html level 2//",
+    "-//ietf//dtd html level 3//",
+    "-//ietf//dtd html strict level 0//",
+    "-//ietf//dtd html strict level 1//",
+    "-//ietf//dtd html strict level 2//",
+    "-//ietf//dtd html strict level 3//",
+    "-//ietf//dtd html strict//",
+    "-//ietf//dtd html//",
+    "-//metrius//dtd metrius presentational//",
+    "-//microsoft//dtd internet explorer 2.0 html strict//",
+    "-//microsoft//dtd internet explorer 2.0 html//",
+    "-//microsoft//dtd internet explorer 2.0 tables//",
+    "-//microsoft//dtd internet explorer 3.0 html strict//",
+    "-//microsoft//dtd internet explorer 3.0 html//",
+    "-//microsoft//dtd internet explorer 3.0 tables//",
+    "-//netscape comm. corp.//dtd html//",
+    "-//netscape comm. corp.//dtd strict html//",
+    "-//o'reilly and associates//dtd html 2.0//",
+    "-//o'reilly and associates//dtd html extended 1.0//",
+    "-//o'reilly and associates//dtd html extended relaxed 1.0//",
+    "-//sq//dtd html 2.0 hotmetal + extensions//",
+    "-//softquad software//dtd hotmetal pro 6.0::19990601::extensions to html 4.0//",
+    "-//softquad//dtd hotmetal pro 4.0::19971010::extensions to html 4.0//",
+    "-//spyglass//dtd html 2.0 extended//",
+    "-//sun microsystems corp.//dtd hotjava html//",
+    "-//sun microsystems corp.//dtd hotjava strict html//",
+    "-//w3c//dtd html 3 1995-03-24//",
+    "-//w3c//dtd html 3.2 draft//",
+    "-//w3c//dtd html 3.2 final//",
+    "-//w3c//dtd html 3.2//",
+    "-//w3c//dtd html 3.2s draft//",
+    "-//w3c//dtd html 4.0 frameset//",
+    "-//w3c//dtd html 4.0 transitional//",
+    "-//w3c//dtd html experimental 19960712//",
+    "-//w3c//dtd html experimental 970421//",
+    "-//w3c//dtd w3 html//",
+    "-//w3o//dtd w3 html 3.0//",
+    "-//webtechs//dtd mozilla html 2.0//",
+    "-//webtechs//dtd mozilla html//",
+];
+
+/// Returns the document mode determined by a DOCTYPE token.
+/// https://html.spec.whatwg.org/multipage/parsing.html#the-initial-insertion-mode
+fn document_mode_for_doctype(
+    name: &Option<String>,
+    public_identifier: &Option<String>,
+    system_identifier: &Option<String>,
+    force_quirks: bool,
+) -> DocumentMode {
+    // The public identifier and the system identifier are compared ASCII case-insensitively.
+    let public_id = public_identifier.as_ref().map(|s| s.to_ascii_lowercase());
+    let system_id = system_identifier.as_ref().map(|s| s.to_ascii_lowercase());
+    let public_starts_with = |prefix: &str| match public_id {
+        Some(ref id) => id.starts_with(prefix),
+        None => false,
+    };
+
+    // "The force-quirks flag is set to on."
+    // "The name is not "html"."
+    if force_quirks || name.as_deref() != Some("html") {
+        return DocumentMode::Quirks;
+    }
+
+    // "The public identifier is set to: "-//W3O//DTD W3 HTML Strict 3.0//EN//""
+    // "The public identifier is set to: "-/W3C/DTD HTML 4.0 Transitional/EN""
+    // "The public identifier is set to: "HTML""
+    if let Some(ref id) = public_id {
+        if id == "-//w3o//dtd w3 html strict 3.0//en//"
+            || id == "-/w3c/dtd html 4.0 transitional/en"
+            || id == "html"
+        {
+            return DocumentMode::Quirks;
+        }
+    }
+
+    // "The system identifier is set to:
+    // "http://www.ibm.com/data/dtd/v11/ibmxhtml1-transitional.dtd""
+    if system_id.as_deref() == Some("http://www.ibm.com/data/dtd/v11/ibmxhtml1-transitional.dtd") {
+        return DocumentMode::Quirks;
+    }
+
+    // "The public identifier starts with: ..."
+    if QUIRKS_PUBLIC_IDENTIFIER_PREFIXES
+        .iter()
+        .any(|prefix| public_starts_with(prefix))
+    {
+        return DocumentMode::Quirks;
+    }
+
+    // "The system identifier is missing and the public identifier starts with:
+    // "-//W3C//DTD HTML 4.01 Frameset//""
+    // "The system identifier is missing and the public identifier starts with:
+    // "-//W3C//DTD HTML 4.01 Transitional//""
+    let html401_frameset_or_transitional = public_starts_with("-//w3c//dtd html 4.01 frameset//")
+        || public_starts_with("-//w3c//dtd html 4.01 transitional//");
+    if system_id.is_none() && html401_frameset_or_transitional {
+        return DocumentMode::Quirks;
+    }
+
+    // "Otherwise, if the document is not an iframe srcdoc document, and the parser cannot change
+    // the mode flag is false, and the DOCTYPE token matches one of the conditions in the
+    // following list, then then set the Document to limited-quirks mode:"
+    // "The public identifier starts with: "-//W3C//DTD XHTML 1.0 Frameset//""
+    // "The public identifier starts with: "-//W3C//DTD XHTML 1.0 Transitional//""
+    // "The system identifier is not missing and the public identifier starts with:
+    // "-//W3C//DTD HTML 4.01 Frameset//""
+    // "The system identifier is not missing and the public identifier starts with:
+    // "-//W3C//DTD HTML 4.01 Transitional//""
+    if public_starts_with("-//w3c//dtd xhtml 1.0 frameset//")
+        || public_starts_with("-//w3c//dtd xhtml 1.0 transitional//")
+        || (system_id.is_some() && html401_frameset_or_transitional)
+    {
+        return DocumentMode::LimitedQuirks;
+    }
+
+    DocumentMode::NoQuirks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
     use crate::alloc::string::ToString;
+    use crate::renderer::dom::api::get_element_by_id;
+    use crate::renderer::html::html_builder::dom_to_html;
+    use crate::utils::convert_dom_to_string;
     use alloc::vec;
 
     #[test]
@@ -963,4 +3750,622 @@ mod tests {
             p
         );
     }
+
+    #[test]
+    fn test_doctype() {
+        let browser = Browser::new();
+        let html = "<!DOCTYPE html><html><head></head><body></body></html>".to_string();
+        let t = HtmlTokenizer::new(Rc::downgrade(&browser), html);
+        let window = HtmlParser::new(Rc::downgrade(&browser), t).construct_tree();
+        let document = window.borrow().document();
+        assert_eq!(DocumentMode::NoQuirks, document.borrow().document_mode());
+
+        let doctype = document
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of document");
+        assert_eq!(
+            Rc::new(RefCell::new(Node::new(NodeKind::DocumentType(
+                DocumentType::new("html".to_string(), "".to_string(), "".to_string())
+            )))),
+            doctype
+        );
+        let html = doctype
+            .borrow()
+            .next_sibling()
+            .expect("failed to get a next sibling of doctype");
+        assert_eq!(
+            Rc::new(RefCell::new(Node::new(NodeKind::Element(Element::new(
+                "html",
+                Vec::new()
+            ))))),
+            html
+        );
+    }
+
+    #[test]
+    fn test_document_mode() {
+        let browser = Browser::new();
+        let cases = [
+            ("<p>a</p>", DocumentMode::Quirks),
+            ("<!DOCTYPE html>", DocumentMode::NoQuirks),
+            ("<!DOCTYPE foo>", DocumentMode::Quirks),
+            (
+                "<!DOCTYPE html PUBLIC \"-//W3C//DTD HTML 4.01 Transitional//EN\">",
+                DocumentMode::Quirks,
+            ),
+            (
+                "<!DOCTYPE html PUBLIC \"-//W3C//DTD HTML 4.01 Transitional//EN\" \"http://www.w3.org/TR/html4/loose.dtd\">",
+                DocumentMode::LimitedQuirks,
+            ),
+            (
+                "<!DOCTYPE html PUBLIC \"-//W3C//DTD XHTML 1.0 Strict//EN\" \"http://www.w3.org/TR/xhtml1/DTD/xhtml1-strict.dtd\">",
+                DocumentMode::NoQuirks,
+            ),
+        ];
+
+        for (html, expected) in cases {
+            let t = HtmlTokenizer::new(Rc::downgrade(&browser), html.to_string());
+            let window = HtmlParser::new(Rc::downgrade(&browser), t).construct_tree();
+            let document = window.borrow().document();
+            assert_eq!(expected, document.borrow().document_mode(), "{}", html);
+        }
+    }
+
+    #[test]
+    fn test_comment() {
+        let browser = Browser::new();
+        let html = "<!--a--><html><body><p>x<!--b--></p>y</body></html><!--c-->".to_string();
+        let t = HtmlTokenizer::new(Rc::downgrade(&browser), html);
+        let window = HtmlParser::new(Rc::downgrade(&browser), t).construct_tree();
+        let document = window.borrow().document();
+
+        let comment_a = document
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of document");
+        assert_eq!(
+            Rc::new(RefCell::new(Node::new(NodeKind::Comment("a".to_string())))),
+            comment_a
+        );
+
+        let html = comment_a
+            .borrow()
+            .next_sibling()
+            .expect("failed to get a next sibling of comment");
+        let comment_c = html
+            .borrow()
+            .next_sibling()
+            .expect("failed to get a next sibling of html");
+        assert_eq!(
+            Rc::new(RefCell::new(Node::new(NodeKind::Comment("c".to_string())))),
+            comment_c
+        );
+
+        let body = html
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of html")
+            .borrow()
+            .next_sibling()
+            .expect("failed to get a next sibling of head");
+        let p = body
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of body");
+        let text = p
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of p");
+        assert_eq!(
+            Rc::new(RefCell::new(Node::new(NodeKind::Text("x".to_string())))),
+            text
+        );
+        let comment_b = text
+            .borrow()
+            .next_sibling()
+            .expect("failed to get a next sibling of text");
+        assert_eq!(
+            Rc::new(RefCell::new(Node::new(NodeKind::Comment("b".to_string())))),
+            comment_b
+        );
+        let text = p
+            .borrow()
+            .next_sibling()
+            .expect("failed to get a next sibling of p");
+        assert_eq!(
+            Rc::new(RefCell::new(Node::new(NodeKind::Text("y".to_string())))),
+            text
+        );
+    }
+
+    #[test]
+    fn test_unknown_elements() {
+        let browser = Browser::new();
+        let html = "<body><my-element><span>a</span></div></my-element>b</body>".to_string();
+        let t = HtmlTokenizer::new(Rc::downgrade(&browser), html);
+        let window = HtmlParser::new(Rc::downgrade(&browser), t).construct_tree();
+        let document = window.borrow().document();
+
+        let body = document
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of document")
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of html")
+            .borrow()
+            .next_sibling()
+            .expect("failed to get a next sibling of head");
+        let custom = body
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of body");
+        assert_eq!(
+            Rc::new(RefCell::new(Node::new(NodeKind::Element(Element::new(
+                "my-element",
+                Vec::new()
+            ))))),
+            custom
+        );
+        let span = custom
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of my-element");
+        assert_eq!(
+            Rc::new(RefCell::new(Node::new(NodeKind::Element(Element::new(
+                "span",
+                Vec::new()
+            ))))),
+            span
+        );
+        let text = custom
+            .borrow()
+            .next_sibling()
+            .expect("failed to get a next sibling of my-element");
+        assert_eq!(
+            Rc::new(RefCell::new(Node::new(NodeKind::Text("b".to_string())))),
+            text
+        );
+    }
+
+    #[test]
+    fn test_void_elements() {
+        let browser = Browser::new();
+        let html = "<body><p>a<br>b<img src=x>c</p><meta charset=utf-8></body>".to_string();
+        let t = HtmlTokenizer::new(Rc::downgrade(&browser), html);
+        let window = HtmlParser::new(Rc::downgrade(&browser), t).construct_tree();
+        let document = window.borrow().document();
+
+        let p = document
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of document")
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of html")
+            .borrow()
+            .next_sibling()
+            .expect("failed to get a next sibling of head")
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of body");
+
+        let mut children = Vec::new();
+        let mut child = p.borrow().first_child();
+        while let Some(c) = child {
+            assert!(c.borrow().first_child().is_none());
+            children.push(c.borrow().kind());
+            child = c.borrow().next_sibling();
+        }
+        assert_eq!(5, children.len());
+        assert_eq!(NodeKind::Text("c".to_string()), children[4]);
+
+        let meta = p
+            .borrow()
+            .next_sibling()
+            .expect("failed to get a next sibling of p");
+        assert_eq!(
+            Rc::new(RefCell::new(Node::new(NodeKind::Element(Element::new(
+                "meta",
+                Vec::new()
+            ))))),
+            meta
+        );
+    }
+
+    #[test]
+    fn test_headings_and_sections() {
+        let browser = Browser::new();
+        let html = "<section><h3>a<h4>b</h4></section><dl><dt>c<dd>d</dl>".to_string();
+        let t = HtmlTokenizer::new(Rc::downgrade(&browser), html);
+        let window = HtmlParser::new(Rc::downgrade(&browser), t).construct_tree();
+        let document = window.borrow().document();
+
+        let section = document
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of document")
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of html")
+            .borrow()
+            .next_sibling()
+            .expect("failed to get a next sibling of head")
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of body");
+        assert_eq!(Some(ElementKind::Section), section.borrow().element_kind());
+
+        // <h4> closes <h3> because headings can't be nested.
+        let h3 = section
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of section");
+        assert_eq!(Some(ElementKind::H3), h3.borrow().element_kind());
+        let h4 = h3
+            .borrow()
+            .next_sibling()
+            .expect("failed to get a next sibling of h3");
+        assert_eq!(Some(ElementKind::H4), h4.borrow().element_kind());
+
+        // <dd> closes <dt>.
+        let dl = section
+            .borrow()
+            .next_sibling()
+            .expect("failed to get a next sibling of section");
+        let dt = dl
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of dl");
+        assert_eq!(Some(ElementKind::Dt), dt.borrow().element_kind());
+        let dd = dt
+            .borrow()
+            .next_sibling()
+            .expect("failed to get a next sibling of dt");
+        assert_eq!(Some(ElementKind::Dd), dd.borrow().element_kind());
+    }
+
+    #[test]
+    fn test_title() {
+        let browser = Browser::new();
+        let html = "<html><head><title>Hello</title></head><body></body></html>".to_string();
+        let t = HtmlTokenizer::new(Rc::downgrade(&browser), html);
+        let window = HtmlParser::new(Rc::downgrade(&browser), t).construct_tree();
+        let document = window.borrow().document();
+
+        let title = document
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of document")
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of html")
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of head");
+        assert_eq!(Some(ElementKind::Title), title.borrow().element_kind());
+        assert_eq!(
+            Rc::new(RefCell::new(Node::new(NodeKind::Text("Hello".to_string())))),
+            title
+                .borrow()
+                .first_child()
+                .expect("failed to get a first child of title")
+        );
+    }
+
+    #[test]
+    fn test_table() {
+        let browser = Browser::new();
+        let html = "<table><caption>c</caption><col><tr><th>h<td>d</table>".to_string();
+        let t = HtmlTokenizer::new(Rc::downgrade(&browser), html);
+        let window = HtmlParser::new(Rc::downgrade(&browser), t).construct_tree();
+        let document = window.borrow().document();
+
+        let body = document
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of document")
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of html")
+            .borrow()
+            .next_sibling()
+            .expect("failed to get a next sibling of head");
+        // tbody, tr and colgroup are implied, and a cell is closed by the next cell.
+        assert_eq!(
+            "<table><caption>c</caption><colgroup><col></colgroup><tbody><tr><th>h</th><td>d</td></tr></tbody></table>".to_string(),
+            dom_to_html(&body.borrow().first_child())
+        );
+    }
+
+    #[test]
+    fn test_foster_parenting() {
+        let browser = Browser::new();
+        let html = "<table>a<tr><td>b</td></tr><div>c</div></table>".to_string();
+        let t = HtmlTokenizer::new(Rc::downgrade(&browser), html);
+        let window = HtmlParser::new(Rc::downgrade(&browser), t).construct_tree();
+        let document = window.borrow().document();
+
+        let body = document
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of document")
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of html")
+            .borrow()
+            .next_sibling()
+            .expect("failed to get a next sibling of head");
+        // Non-table content in a table is moved before the table.
+        assert_eq!(
+            "a<div>c</div><table><tbody><tr><td>b</td></tr></tbody></table>".to_string(),
+            dom_to_html(&body.borrow().first_child())
+        );
+    }
+
+    #[test]
+    fn test_misnested_formatting_elements() {
+        let browser = Browser::new();
+        let html = "<a><p>x</a>y</p><b>1<i>2</b>3</i>".to_string();
+        let t = HtmlTokenizer::new(Rc::downgrade(&browser), html);
+        let window = HtmlParser::new(Rc::downgrade(&browser), t).construct_tree();
+        let document = window.borrow().document();
+
+        let body = document
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of document")
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of html")
+            .borrow()
+            .next_sibling()
+            .expect("failed to get a next sibling of head");
+        // The adoption agency algorithm moves the content of a misnested formatting element into a
+        // clone of it.
+        assert_eq!(
+            "<a></a><p><a>x</a>y</p><b>1<i>2</i></b><i>3</i>".to_string(),
+            dom_to_html(&body.borrow().first_child())
+        );
+    }
+
+    #[test]
+    fn test_reconstruct_formatting_elements() {
+        let browser = Browser::new();
+        let html = "<p><b>x<p>y</p><table><td><b>z</td></table>".to_string();
+        let t = HtmlTokenizer::new(Rc::downgrade(&browser), html);
+        let window = HtmlParser::new(Rc::downgrade(&browser), t).construct_tree();
+        let document = window.borrow().document();
+
+        let body = document
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of document")
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of html")
+            .borrow()
+            .next_sibling()
+            .expect("failed to get a next sibling of head");
+        // An unclosed formatting element is reconstructed in the following paragraph, but not
+        // across a marker for a table cell.
+        assert_eq!(
+            "<p><b>x</b></p><p><b>y</b></p><table><tbody><tr><td><b>z</b></td></tr></tbody></table>".to_string(),
+            dom_to_html(&body.borrow().first_child())
+        );
+    }
+
+    #[test]
+    fn test_implied_end_tags() {
+        let browser = Browser::new();
+        let html = "<p>a<div>b</div><ul><li>c<p>d<li>e</ul>".to_string();
+        let t = HtmlTokenizer::new(Rc::downgrade(&browser), html);
+        let window = HtmlParser::new(Rc::downgrade(&browser), t).construct_tree();
+        let document = window.borrow().document();
+
+        let body = document
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of document")
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of html")
+            .borrow()
+            .next_sibling()
+            .expect("failed to get a next sibling of head");
+        // A p element is closed by a block and an li element is closed by the next li element.
+        assert_eq!(
+            "<p>a</p><div>b</div><ul><li>c<p>d</p></li><li>e</li></ul>".to_string(),
+            dom_to_html(&body.borrow().first_child())
+        );
+    }
+
+    #[test]
+    fn test_parse_fragment() {
+        let browser = Browser::new();
+        let html = "<table><tr id=row><td>a</td></tr></table><div id=block></div>".to_string();
+        let t = HtmlTokenizer::new(Rc::downgrade(&browser), html);
+        let window = HtmlParser::new(Rc::downgrade(&browser), t).construct_tree();
+        let document = window.borrow().document();
+
+        // A fragment is parsed in the insertion mode for the context element.
+        let block = get_element_by_id(Some(document.clone()), &"block".to_string())
+            .expect("block should exist");
+        let nodes =
+            HtmlParser::parse_fragment(Rc::downgrade(&browser), &block, "<p>x<li>y".to_string());
+        assert_eq!(2, nodes.len());
+        assert_eq!("<p>x</p>".to_string(), dom_to_html(&Some(nodes[0].clone())));
+
+        let row = get_element_by_id(Some(document), &"row".to_string()).expect("row should exist");
+        let nodes =
+            HtmlParser::parse_fragment(Rc::downgrade(&browser), &row, "<td>b<td>c".to_string());
+        assert_eq!(2, nodes.len());
+        assert_eq!(Some(ElementKind::Td), nodes[1].borrow().element_kind());
+        assert!(nodes[0].borrow().parent().upgrade().is_none());
+    }
+
+    #[test]
+    fn test_form_controls() {
+        let browser = Browser::new();
+        let html = "<form><button>a<button>b</button><select><option>x<option>y<div>z</div></select><textarea>\n<b>t</textarea></form>".to_string();
+        let t = HtmlTokenizer::new(Rc::downgrade(&browser), html);
+        let window = HtmlParser::new(Rc::downgrade(&browser), t).construct_tree();
+        let document = window.borrow().document();
+
+        let body = document
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of document")
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of html")
+            .borrow()
+            .next_sibling()
+            .expect("failed to get a next sibling of head");
+        // A button closes an open button, an option closes the previous option, tags in a select
+        // are ignored, and a textarea has raw text.
+        assert_eq!(
+            "<form><button>a</button><button>b</button><select><option>x</option><option>yz</option></select><textarea>&lt;b&gt;t</textarea></form>".to_string(),
+            dom_to_html(&body.borrow().first_child())
+        );
+    }
+
+    #[test]
+    fn test_table_scope() {
+        let browser = Browser::new();
+        let html = "<p><table><td>a<div>b</div></table>".to_string();
+        let t = HtmlTokenizer::new(Rc::downgrade(&browser), html);
+        let window = HtmlParser::new(Rc::downgrade(&browser), t).construct_tree();
+        let document = window.borrow().document();
+
+        let body = document
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of document")
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of html")
+            .borrow()
+            .next_sibling()
+            .expect("failed to get a next sibling of head");
+        // In quirks mode, a table doesn't close a p element, and a div in a cell doesn't either.
+        assert_eq!(
+            "<p><table><tbody><tr><td>a<div>b</div></td></tr></tbody></table></p>".to_string(),
+            dom_to_html(&body.borrow().first_child())
+        );
+    }
+
+    #[test]
+    fn test_title_rcdata() {
+        let browser = Browser::new();
+        let html = "<title>a<b>&amp;</b></title><p>x</p>".to_string();
+        let t = HtmlTokenizer::new(Rc::downgrade(&browser), html);
+        let window = HtmlParser::new(Rc::downgrade(&browser), t).construct_tree();
+        let document = window.borrow().document();
+
+        // The content of a title element is text, and character references are consumed.
+        assert_eq!(
+            "<html><head><title>a&lt;b&gt;&amp;&lt;/b&gt;</title></head><body><p>x</p></body></html>"
+                .to_string(),
+            dom_to_html(&document.borrow().first_child())
+        );
+    }
+
+    #[test]
+    fn test_style_rawtext() {
+        let parse = |html: &str| {
+            let browser = Browser::new();
+            let t = HtmlTokenizer::new(Rc::downgrade(&browser), html.to_string());
+            let window = HtmlParser::new(Rc::downgrade(&browser), t).construct_tree();
+            let document = window.borrow().document();
+            let html = dom_to_html(&document.borrow().first_child());
+            html
+        };
+
+        // Only the end tag of the style element ends its content.
+        assert_eq!(
+            "<html><head><style>a</p>b</style></head></html>".to_string(),
+            parse("<style>a</p>b</style>")
+        );
+        assert_eq!(
+            "<html><head><style></script></style></head></html>".to_string(),
+            parse("<style></script>")
+        );
+        assert_eq!(
+            "<html><head></head><body><p>&amp;#<style></title></style></p></body></html>"
+                .to_string(),
+            parse("</div><p>&#<style></title>")
+        );
+    }
+
+    #[test]
+    fn test_source_range() {
+        let browser = Browser::new();
+        let html = "<!doctype html><p id=a>hi <b>x</b>\n<!--c--></p><i>y".to_string();
+        let t = HtmlTokenizer::new(Rc::downgrade(&browser), html.clone());
+        let window = HtmlParser::new(Rc::downgrade(&browser), t).construct_tree();
+        let document = window.borrow().document();
+        let source = |node: &Rc<RefCell<Node>>| {
+            node.borrow()
+                .source_range()
+                .map(|r| html[r.start()..r.end()].to_string())
+        };
+
+        // Elements span from their start tags to their end tags, and implied elements have no
+        // range.
+        let doctype = document
+            .borrow()
+            .first_child()
+            .expect("doctype should exist");
+        assert_eq!(Some("<!doctype html>".to_string()), source(&doctype));
+        let html_element = doctype.borrow().next_sibling().expect("html should exist");
+        assert_eq!(None, source(&html_element));
+        let p =
+            get_element_by_id(Some(document.clone()), &"a".to_string()).expect("p should exist");
+        assert_eq!(
+            Some("<p id=a>hi <b>x</b>\n<!--c--></p>".to_string()),
+            source(&p)
+        );
+        let text = p.borrow().first_child().expect("text should exist");
+        assert_eq!(Some("hi ".to_string()), source(&text));
+        let b = text.borrow().next_sibling().expect("b should exist");
+        assert_eq!(Some("<b>x</b>".to_string()), source(&b));
+        let comment = b.borrow().next_sibling().expect("comment should exist");
+        assert_eq!(Some("<!--c-->".to_string()), source(&comment));
+        // An element which is not closed spans only its start tag.
+        let i = p.borrow().next_sibling().expect("i should exist");
+        assert_eq!(Some("<i>".to_string()), source(&i));
+
+        let dom = convert_dom_to_string(&Some(p), true);
+        assert!(dom.contains(") @ 1:16-2:13 [15..47]\n"));
+        assert!(dom.contains("Comment(\"c\") @ 2:1-2:9 [35..43]\n"));
+        assert!(!convert_dom_to_string(&Some(b), false).contains(" @ "));
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let browser = Browser::new();
+        let html = "<!DOCTYPE html><p>a</p>\n<!DOCTYPE x></div><span/>".to_string();
+        let t = HtmlTokenizer::new(Rc::downgrade(&browser), html);
+        HtmlParser::new(Rc::downgrade(&browser), t).construct_tree();
+
+        let errors: Vec<(String, String)> = browser
+            .borrow()
+            .diagnostics()
+            .iter()
+            .map(|d| (d.code(), d.position().to_string()))
+            .collect();
+        assert_eq!(
+            vec![
+                ("unexpected-doctype".to_string(), "2:12".to_string()),
+                ("unexpected-end-tag".to_string(), "2:18".to_string()),
+                (
+                    "non-void-html-element-start-tag-with-trailing-solidus".to_string(),
+                    "2:25".to_string()
+                ),
+            ],
+            errors
+        );
+    }
 }
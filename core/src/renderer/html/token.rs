@@ -2,9 +2,18 @@
 //! https://html.spec.whatwg.org/multipage/parsing.html#tokenization
 
 use crate::browser::Browser;
+use crate::log::Diagnostic;
+use crate::log::SourceKind;
 use crate::renderer::html::attribute::Attribute;
+use crate::renderer::html::entities::{lookup_named_character_reference, LONGEST_NAME_LENGTH};
+use crate::renderer::source::LineMap;
+use crate::renderer::source::SourcePosition;
+use crate::renderer::source::SourceRange;
+use crate::utils::console_diagnostic;
+use alloc::collections::VecDeque;
 use alloc::rc::Weak;
 use alloc::string::String;
+use alloc::string::ToString;
 use alloc::vec::Vec;
 use core::assert;
 use core::cell::RefCell;
@@ -38,6 +47,22 @@ pub enum State {
     AfterAttributeValueQuoted,
     /// https://html.spec.whatwg.org/multipage/parsing.html#self-closing-start-tag-state
     SelfClosingStartTag,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#rcdata-state
+    Rcdata,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#rcdata-less-than-sign-state
+    RcdataLessThanSign,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#rcdata-end-tag-open-state
+    RcdataEndTagOpen,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#rcdata-end-tag-name-state
+    RcdataEndTagName,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#rawtext-state
+    Rawtext,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#rawtext-less-than-sign-state
+    RawtextLessThanSign,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#rawtext-end-tag-open-state
+    RawtextEndTagOpen,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#rawtext-end-tag-name-state
+    RawtextEndTagName,
     /// https://html.spec.whatwg.org/multipage/parsing.html#script-data-state
     ScriptData,
     /// https://html.spec.whatwg.org/multipage/parsing.html#script-data-less-than-sign-state
@@ -46,8 +71,84 @@ pub enum State {
     ScriptDataEndTagOpen,
     /// https://html.spec.whatwg.org/multipage/parsing.html#script-data-end-tag-name-state
     ScriptDataEndTagName,
-    /// https://html.spec.whatwg.org/multipage/parsing.html#temporary-buffer
-    TemporaryBuffer,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#character-reference-state
+    CharacterReference,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#named-character-reference-state
+    NamedCharacterReference,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#ambiguous-ampersand-state
+    AmbiguousAmpersand,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#numeric-character-reference-state
+    NumericCharacterReference,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#hexadecimal-character-reference-start-state
+    HexadecimalCharacterReferenceStart,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#decimal-character-reference-start-state
+    DecimalCharacterReferenceStart,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#hexadecimal-character-reference-state
+    HexadecimalCharacterReference,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#decimal-character-reference-state
+    DecimalCharacterReference,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#markup-declaration-open-state
+    MarkupDeclarationOpen,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#bogus-comment-state
+    BogusComment,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#comment-start-state
+    CommentStart,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#comment-start-dash-state
+    CommentStartDash,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#comment-state
+    Comment,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#comment-less-than-sign-state
+    CommentLessThanSign,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#comment-less-than-sign-bang-state
+    CommentLessThanSignBang,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#comment-less-than-sign-bang-dash-state
+    CommentLessThanSignBangDash,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#comment-less-than-sign-bang-dash-dash-state
+    CommentLessThanSignBangDashDash,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#comment-end-dash-state
+    CommentEndDash,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#comment-end-state
+    CommentEnd,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#comment-end-bang-state
+    CommentEndBang,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#doctype-state
+    Doctype,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#before-doctype-name-state
+    BeforeDoctypeName,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#doctype-name-state
+    DoctypeName,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#after-doctype-name-state
+    AfterDoctypeName,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#after-doctype-public-keyword-state
+    AfterDoctypePublicKeyword,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#before-doctype-public-identifier-state
+    BeforeDoctypePublicIdentifier,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#doctype-public-identifier-(double-quoted)-state
+    DoctypePublicIdentifierDoubleQuoted,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#doctype-public-identifier-(single-quoted)-state
+    DoctypePublicIdentifierSingleQuoted,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#after-doctype-public-identifier-state
+    AfterDoctypePublicIdentifier,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#between-doctype-public-and-system-identifiers-state
+    BetweenDoctypePublicAndSystemIdentifiers,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#after-doctype-system-keyword-state
+    AfterDoctypeSystemKeyword,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#before-doctype-system-identifier-state
+    BeforeDoctypeSystemIdentifier,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#doctype-system-identifier-(double-quoted)-state
+    DoctypeSystemIdentifierDoubleQuoted,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#doctype-system-identifier-(single-quoted)-state
+    DoctypeSystemIdentifierSingleQuoted,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#after-doctype-system-identifier-state
+    AfterDoctypeSystemIdentifier,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#bogus-doctype-state
+    BogusDoctype,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#cdata-section-state
+    CdataSection,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#cdata-section-bracket-state
+    CdataSectionBracket,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#cdata-section-end-state
+    CdataSectionEnd,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -64,6 +165,20 @@ pub enum HtmlToken {
     },
     // "foo"
     Char(char),
+    // <!-- foo -->
+    Comment(String),
+    // <!DOCTYPE foo>
+    // https://html.spec.whatwg.org/multipage/parsing.html#tokenization
+    // "DOCTYPE tokens have a name, a public identifier, a system identifier, and a force-quirks
+    // flag. When a DOCTYPE token is created, its name, public identifier, and system identifier
+    // must be marked as missing (which is a distinct state from the empty string), and the
+    // force-quirks flag must be set to off (its other state is on)."
+    Doctype {
+        name: Option<String>,
+        public_identifier: Option<String>,
+        system_identifier: Option<String>,
+        force_quirks: bool,
+    },
     Eof,
 }
 
@@ -78,29 +193,62 @@ pub struct HtmlTokenizer {
     latest_token: Option<HtmlToken>,
     input: Vec<char>,
     buf: String,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#return-state
+    return_state: State,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#character-reference-code
+    character_reference_code: u32,
+    /// Characters which are already tokenized but not emitted yet. A character reference can
+    /// produce multiple characters at once.
+    pending_chars: VecDeque<char>,
+    /// True if the adjusted current node is an element in a foreign namespace (e.g. SVG or
+    /// MathML). CDATA sections are allowed only in foreign content.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#markup-declaration-open-state
+    in_foreign_content: bool,
+    /// The tag name of the last start tag emitted from this tokenizer. This is used to check
+    /// whether an end tag token is an appropriate end tag token.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#appropriate-end-tag-token
+    last_start_tag: Option<String>,
+    /// The URL of the document, which is reported with parse errors.
+    url: String,
+    line_map: LineMap,
+    /// The range of the input which produced the last emitted token.
+    token_range: SourceRange,
 }
 
 impl HtmlTokenizer {
     pub fn new(browser: Weak<RefCell<Browser>>, html: String) -> Self {
         let preprocessed_html = Self::preprocess(&html);
+        let input: Vec<char> = preprocessed_html.chars().collect();
         Self {
             browser: browser,
             state: State::Data,
             pos: 0,
             reconsume: false,
             latest_token: None,
-            input: preprocessed_html.chars().collect(),
+            line_map: LineMap::new(&input),
+            token_range: SourceRange::default(),
+            input,
             buf: String::new(),
+            return_state: State::Data,
+            character_reference_code: 0,
+            pending_chars: VecDeque::new(),
+            in_foreign_content: false,
+            last_start_tag: None,
+            url: String::new(),
         }
     }
 
-    fn preprocess(raw: &String) -> String {
+    /// Normalizes newlines in `raw`. Positions and ranges reported by this tokenizer are in the
+    /// preprocessed input.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#preprocessing-the-input-stream
+    pub fn preprocess(raw: &String) -> String {
         return raw.replace("\r\n", "\n");
     }
 
-    /// Consumes a next input character.
+    /// Consumes a next input character. Returns U+0000 NULL and moves the position beyond the end
+    /// of input if there is no more input, so that `is_eof` becomes true.
     fn consume_next_input(&mut self) -> char {
-        let c = self.input[self.pos];
+        let c = self.input.get(self.pos).copied().unwrap_or('\0');
         self.pos += 1;
         c
     }
@@ -109,7 +257,7 @@ impl HtmlTokenizer {
     /// `reconsume_input` is called.
     fn reconsume_input(&mut self) -> char {
         self.reconsume = false;
-        self.input[self.pos - 1]
+        self.input.get(self.pos - 1).copied().unwrap_or('\0')
     }
 
     /// Creates a StartTag or EndTag token.
@@ -146,6 +294,10 @@ impl HtmlTokenizer {
     fn start_new_attribute(&mut self) {
         assert!(self.latest_token.is_some());
 
+        if let Some(HtmlToken::EndTag { .. }) = self.latest_token {
+            self.parse_error("end-tag-with-attributes");
+        }
+
         if let Some(t) = self.latest_token.as_mut() {
             match t {
                 HtmlToken::StartTag {
@@ -155,7 +307,9 @@ impl HtmlTokenizer {
                 } => {
                     attributes.push(Attribute::new());
                 }
-                _ => panic!("`latest_token` should be either StartTag"),
+                // end-tag-with-attributes parse error. Attributes in an end tag are ignored.
+                HtmlToken::EndTag { tag: _ } => {}
+                _ => panic!("`latest_token` should be either StartTag or EndTag"),
             }
         }
     }
@@ -176,7 +330,8 @@ impl HtmlTokenizer {
 
                     attributes[len - 1].add_char(c, is_name);
                 }
-                _ => panic!("`latest_token` should be either StartTag"),
+                HtmlToken::EndTag { tag: _ } => {}
+                _ => panic!("`latest_token` should be either StartTag or EndTag"),
             }
         }
     }
@@ -185,6 +340,10 @@ impl HtmlTokenizer {
     fn set_self_closing_flag(&mut self) {
         assert!(self.latest_token.is_some());
 
+        if let Some(HtmlToken::EndTag { .. }) = self.latest_token {
+            self.parse_error("end-tag-with-trailing-solidus");
+        }
+
         if let Some(t) = self.latest_token.as_mut() {
             match t {
                 HtmlToken::StartTag {
@@ -192,41 +351,374 @@ impl HtmlTokenizer {
                     ref mut self_closing,
                     attributes: _,
                 } => *self_closing = true,
-                _ => panic!("`latest_token` should be either StartTag"),
+                // end-tag-with-trailing-solidus parse error.
+                HtmlToken::EndTag { tag: _ } => {}
+                _ => panic!("`latest_token` should be either StartTag or EndTag"),
+            }
+        }
+    }
+
+    /// Creates a Comment token with `data`.
+    fn create_comment(&mut self, data: &str) {
+        self.latest_token = Some(HtmlToken::Comment(String::from(data)));
+    }
+
+    /// Appends a string to the data of the Comment token in `latest_token`.
+    fn append_comment(&mut self, s: &str) {
+        assert!(self.latest_token.is_some());
+
+        if let Some(t) = self.latest_token.as_mut() {
+            match t {
+                HtmlToken::Comment(ref mut data) => data.push_str(s),
+                _ => panic!("`latest_token` should be Comment"),
+            }
+        }
+    }
+
+    /// Creates a DOCTYPE token whose name, public identifier and system identifier are missing.
+    fn create_doctype(&mut self) {
+        self.latest_token = Some(HtmlToken::Doctype {
+            name: None,
+            public_identifier: None,
+            system_identifier: None,
+            force_quirks: false,
+        });
+    }
+
+    /// Sets the force-quirks flag of the DOCTYPE token in `latest_token` to on.
+    fn set_force_quirks_flag(&mut self) {
+        assert!(self.latest_token.is_some());
+
+        if let Some(t) = self.latest_token.as_mut() {
+            match t {
+                HtmlToken::Doctype {
+                    ref mut force_quirks,
+                    ..
+                } => *force_quirks = true,
+                _ => panic!("`latest_token` should be Doctype"),
+            }
+        }
+    }
+
+    /// Appends a char to the name of the DOCTYPE token in `latest_token`.
+    fn append_doctype_name(&mut self, c: char) {
+        assert!(self.latest_token.is_some());
+
+        if let Some(t) = self.latest_token.as_mut() {
+            match t {
+                HtmlToken::Doctype { ref mut name, .. } => {
+                    name.get_or_insert_with(String::new).push(c)
+                }
+                _ => panic!("`latest_token` should be Doctype"),
+            }
+        }
+    }
+
+    /// Sets the public identifier or the system identifier of the DOCTYPE token in
+    /// `latest_token` to the empty string.
+    fn start_doctype_identifier(&mut self, is_public: bool) {
+        assert!(self.latest_token.is_some());
+
+        if let Some(t) = self.latest_token.as_mut() {
+            match t {
+                HtmlToken::Doctype {
+                    ref mut public_identifier,
+                    ref mut system_identifier,
+                    ..
+                } => {
+                    if is_public {
+                        *public_identifier = Some(String::new());
+                    } else {
+                        *system_identifier = Some(String::new());
+                    }
+                }
+                _ => panic!("`latest_token` should be Doctype"),
             }
         }
     }
 
+    /// Appends a char to the public identifier or the system identifier of the DOCTYPE token in
+    /// `latest_token`.
+    fn append_doctype_identifier(&mut self, c: char, is_public: bool) {
+        assert!(self.latest_token.is_some());
+
+        if let Some(t) = self.latest_token.as_mut() {
+            match t {
+                HtmlToken::Doctype {
+                    ref mut public_identifier,
+                    ref mut system_identifier,
+                    ..
+                } => {
+                    let identifier = if is_public {
+                        public_identifier
+                    } else {
+                        system_identifier
+                    };
+                    identifier.get_or_insert_with(String::new).push(c);
+                }
+                _ => panic!("`latest_token` should be Doctype"),
+            }
+        }
+    }
+
+    /// Returns true and consumes characters if the next characters from the current input
+    /// character match `s`. The current input character is already consumed.
+    fn consume_if_matches(&mut self, s: &str, case_insensitive: bool) -> bool {
+        let start = self.pos - 1;
+        let len = s.chars().count();
+        if start + len > self.input.len() {
+            return false;
+        }
+
+        let matched = self.input[start..start + len]
+            .iter()
+            .zip(s.chars())
+            .all(|(a, b)| {
+                if case_insensitive {
+                    a.eq_ignore_ascii_case(&b)
+                } else {
+                    *a == b
+                }
+            });
+
+        if matched {
+            self.pos = start + len;
+        }
+        matched
+    }
+
     /// Returns `latest_token` and makes it to None.
     fn take_latest_token(&mut self) -> Option<HtmlToken> {
         assert!(self.latest_token.is_some());
 
-        let t = self.latest_token.as_ref().cloned();
-        self.latest_token = None;
+        let mut t = self.latest_token.take();
         assert!(self.latest_token.is_none());
 
+        // "When the user agent leaves the attribute name state (and before emitting the tag token,
+        // if appropriate), the complete attribute's name must be compared to the other attributes
+        // on the same token; if there is already an attribute on the token with the exact same
+        // name, then this is a duplicate-attribute parse error and the new attribute must be
+        // removed from the token."
+        // https://html.spec.whatwg.org/multipage/parsing.html#attribute-name-state
+        if let Some(HtmlToken::StartTag {
+            ref mut attributes, ..
+        }) = t
+        {
+            let len = attributes.len();
+            let mut names: Vec<String> = Vec::new();
+            attributes.retain(|attr| {
+                if names.contains(&attr.name()) {
+                    return false;
+                }
+                names.push(attr.name());
+                true
+            });
+            if attributes.len() != len {
+                self.parse_error("duplicate-attribute");
+            }
+        }
+
+        if let Some(HtmlToken::StartTag { ref tag, .. }) = t {
+            self.last_start_tag = Some(tag.clone());
+        }
+
         t
     }
 
+    /// Returns true if the latest token is an end tag whose tag name matches the tag name of the
+    /// last start tag emitted from this tokenizer.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#appropriate-end-tag-token
+    fn is_appropriate_end_tag_token(&self) -> bool {
+        match (&self.latest_token, &self.last_start_tag) {
+            (Some(HtmlToken::EndTag { tag }), Some(last_start_tag)) => tag == last_start_tag,
+            _ => false,
+        }
+    }
+
     /// Returns true if the current position is larger than the length of input.
     fn is_eof(&self) -> bool {
         self.pos > self.input.len()
     }
 
+    /// Returns true if the return state is one of attribute value states.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#charref-in-attribute
+    fn is_consumed_as_part_of_attribute(&self) -> bool {
+        self.return_state == State::AttributeValueDoubleQuoted
+            || self.return_state == State::AttributeValueSingleQuoted
+            || self.return_state == State::AttributeValueUnquoted
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#flush-code-points-consumed-as-a-character-reference
+    fn flush_code_points_consumed_as_character_reference(&mut self) {
+        let chars: Vec<char> = self.buf.chars().collect();
+        self.buf = String::new();
+
+        for c in chars {
+            // "If the character reference was consumed as part of an attribute, then append each
+            // character to the current attribute's value. Otherwise, emit each character as a
+            // character token."
+            if self.is_consumed_as_part_of_attribute() {
+                self.append_attribute(c, /*is_name*/ false);
+            } else {
+                self.pending_chars.push_back(c);
+            }
+        }
+    }
+
+    /// Consumes the maximum number of characters which match one of the names in the named
+    /// character references table, starting from the current input character. Returns the
+    /// matched name and the characters it refers to.
+    fn consume_named_character_reference(&mut self) -> Option<(String, &'static str)> {
+        // The current input character is already consumed.
+        let start = self.pos - 1;
+        let max_len = core::cmp::min(LONGEST_NAME_LENGTH, self.input.len() - start);
+
+        for len in (1..=max_len).rev() {
+            let name: String = self.input[start..start + len].iter().collect();
+            if let Some(chars) = lookup_named_character_reference(&name) {
+                self.pos = start + len;
+                return Some((name, chars));
+            }
+        }
+
+        None
+    }
+
+    /// Runs the numeric character reference end state. This state doesn't consume any character,
+    /// so it's run as a part of the hexadecimal/decimal character reference states.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#numeric-character-reference-end-state
+    fn numeric_character_reference_end(&mut self) {
+        let code = self.character_reference_code;
+        if code == 0 {
+            self.parse_error("null-character-reference");
+        } else if code > 0x10FFFF {
+            self.parse_error("character-reference-outside-unicode-range");
+        } else if (0xD800..=0xDFFF).contains(&code) {
+            self.parse_error("surrogate-character-reference");
+        } else if (0xFDD0..=0xFDEF).contains(&code) || code & 0xFFFE == 0xFFFE {
+            // "If the number is a noncharacter, then this is a
+            // noncharacter-character-reference parse error."
+            self.parse_error("noncharacter-character-reference");
+        } else if code == 0x0D
+            || ((code < 0x20 || (0x7F..=0x9F).contains(&code))
+                && !matches!(code, 0x09 | 0x0A | 0x0C | 0x20))
+        {
+            // "If the number is 0x0D, or a control that's not ASCII whitespace, then this is a
+            // control-character-reference parse error."
+            self.parse_error("control-character-reference");
+        }
+
+        let c = numeric_character_reference_code(code);
+
+        // "Set the temporary buffer to the empty string. Append a code point equal to the
+        // character reference code to the temporary buffer. Flush code points consumed as a
+        // character reference. Switch to the return state."
+        self.buf = String::new();
+        self.buf.push(c);
+        self.flush_code_points_consumed_as_character_reference();
+        self.state = self.return_state.clone();
+    }
+
+    /// Sets whether the adjusted current node is an element in a foreign namespace. This is used
+    /// to decide whether "<![CDATA[" starts a CDATA section or a bogus comment.
+    pub fn set_in_foreign_content(&mut self, in_foreign_content: bool) {
+        self.in_foreign_content = in_foreign_content;
+    }
+
     /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-html-fragments
     pub fn switch_context(&mut self, state: State) {
         self.state = state;
     }
+
+    /// Sets the tag name of the last start tag emitted from this tokenizer. This is used to start
+    /// tokenizing from the middle of a document, e.g. in the RCDATA state.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#appropriate-end-tag-token
+    pub fn set_last_start_tag(&mut self, tag: String) {
+        self.last_start_tag = Some(tag);
+    }
+
+    pub fn set_url(&mut self, url: String) {
+        self.url = url;
+    }
+
+    /// Returns the position of the current input character.
+    pub fn position(&self) -> SourcePosition {
+        self.line_map.position(self.pos.saturating_sub(1))
+    }
+
+    /// Returns the range of the input which produced the last emitted token.
+    pub fn token_range(&self) -> SourceRange {
+        self.token_range
+    }
+
+    /// Reports a parse error at the current input character to the browser console.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parse-errors
+    pub fn parse_error(&self, code: &str) {
+        console_diagnostic(
+            &self.browser,
+            Diagnostic::new(
+                SourceKind::Html,
+                code.to_string(),
+                self.url.clone(),
+                self.position(),
+            ),
+        );
+    }
 }
 
-impl Iterator for HtmlTokenizer {
-    type Item = HtmlToken;
+/// Returns the character for the character reference code, replacing the code which is not
+/// allowed.
+/// https://html.spec.whatwg.org/multipage/parsing.html#numeric-character-reference-end-state
+fn numeric_character_reference_code(code: u32) -> char {
+    // "If the number is 0x00, then this is a null-character-reference parse error. Set the
+    // character reference code to 0xFFFD."
+    // "If the number is greater than 0x10FFFF, then this is a
+    // character-reference-outside-unicode-range parse error. Set the character reference code to
+    // 0xFFFD."
+    // "If the number is a surrogate, then this is a surrogate-character-reference parse error. Set
+    // the character reference code to 0xFFFD."
+    let code = match code {
+        0x80 => 0x20AC,
+        0x82 => 0x201A,
+        0x83 => 0x0192,
+        0x84 => 0x201E,
+        0x85 => 0x2026,
+        0x86 => 0x2020,
+        0x87 => 0x2021,
+        0x88 => 0x02C6,
+        0x89 => 0x2030,
+        0x8A => 0x0160,
+        0x8B => 0x2039,
+        0x8C => 0x0152,
+        0x8E => 0x017D,
+        0x91 => 0x2018,
+        0x92 => 0x2019,
+        0x93 => 0x201C,
+        0x94 => 0x201D,
+        0x95 => 0x2022,
+        0x96 => 0x2013,
+        0x97 => 0x2014,
+        0x98 => 0x02DC,
+        0x99 => 0x2122,
+        0x9A => 0x0161,
+        0x9B => 0x203A,
+        0x9C => 0x0153,
+        0x9E => 0x017E,
+        0x9F => 0x0178,
+        _ => code,
+    };
+
+    // U+0000 is a valid `char`, so check it explicitly.
+    if code == 0 {
+        return '\u{FFFD}';
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.pos >= self.input.len() {
-            return None;
-        }
+    char::from_u32(code).unwrap_or('\u{FFFD}')
+}
 
+impl HtmlTokenizer {
+    /// Consumes input characters until a token is emitted.
+    fn consume_token(&mut self) -> Option<HtmlToken> {
         loop {
             let c = match self.reconsume {
                 true => self.reconsume_input(),
@@ -236,6 +728,12 @@ impl Iterator for HtmlTokenizer {
             match self.state {
                 // https://html.spec.whatwg.org/multipage/parsing.html#data-state
                 State::Data => {
+                    if c == '&' {
+                        self.return_state = State::Data;
+                        self.state = State::CharacterReference;
+                        continue;
+                    }
+
                     if c == '<' {
                         self.state = State::TagOpen;
                         continue;
@@ -245,10 +743,20 @@ impl Iterator for HtmlTokenizer {
                         return Some(HtmlToken::Eof);
                     }
 
+                    if c == '\0' {
+                        // unexpected-null-character parse error.
+                        self.parse_error("unexpected-null-character");
+                    }
+
                     return Some(HtmlToken::Char(c));
                 }
                 // https://html.spec.whatwg.org/multipage/parsing.html#tag-open-state
                 State::TagOpen => {
+                    if c == '!' {
+                        self.state = State::MarkupDeclarationOpen;
+                        continue;
+                    }
+
                     if c == '/' {
                         self.state = State::EndTagOpen;
                         continue;
@@ -261,18 +769,42 @@ impl Iterator for HtmlTokenizer {
                         continue;
                     }
 
+                    if c == '?' {
+                        // unexpected-question-mark-instead-of-tag-name parse error.
+                        self.parse_error("unexpected-question-mark-instead-of-tag-name");
+                        self.create_comment("");
+                        self.reconsume = true;
+                        self.state = State::BogusComment;
+                        continue;
+                    }
+
                     if self.is_eof() {
-                        return Some(HtmlToken::Eof);
+                        // "This is an eof-before-tag-name parse error. Emit a U+003C LESS-THAN
+                        // SIGN character token and an end-of-file token."
+                        self.parse_error("eof-before-tag-name");
+                        self.reconsume = true;
+                        self.state = State::Data;
+                        return Some(HtmlToken::Char('<'));
                     }
 
+                    // "This is an invalid-first-character-of-tag-name parse error. Emit a U+003C
+                    // LESS-THAN SIGN character token. Reconsume in the data state."
+                    self.parse_error("invalid-first-character-of-tag-name");
                     self.reconsume = true;
                     self.state = State::Data;
+                    return Some(HtmlToken::Char('<'));
                 }
                 // https://html.spec.whatwg.org/multipage/parsing.html#end-tag-open-state
                 State::EndTagOpen => {
                     if self.is_eof() {
-                        // invalid parse error.
-                        return Some(HtmlToken::Eof);
+                        // "This is an eof-before-tag-name parse error. Emit a U+003C LESS-THAN
+                        // SIGN character token, a U+002F SOLIDUS character token and an
+                        // end-of-file token."
+                        self.parse_error("eof-before-tag-name");
+                        self.pending_chars.push_back('/');
+                        self.reconsume = true;
+                        self.state = State::Data;
+                        return Some(HtmlToken::Char('<'));
                     }
 
                     if c.is_ascii_alphabetic() {
@@ -281,6 +813,19 @@ impl Iterator for HtmlTokenizer {
                         self.create_tag(false);
                         continue;
                     }
+
+                    if c == '>' {
+                        // missing-end-tag-name parse error.
+                        self.parse_error("missing-end-tag-name");
+                        self.state = State::Data;
+                        continue;
+                    }
+
+                    // invalid-first-character-of-tag-name parse error.
+                    self.parse_error("invalid-first-character-of-tag-name");
+                    self.create_comment("");
+                    self.reconsume = true;
+                    self.state = State::BogusComment;
                 }
                 // https://html.spec.whatwg.org/multipage/parsing.html#tag-name-state
                 State::TagName => {
@@ -305,7 +850,8 @@ impl Iterator for HtmlTokenizer {
                     }
 
                     if self.is_eof() {
-                        // invalid parse error.
+                        // eof-in-tag parse error.
+                        self.parse_error("eof-in-tag");
                         return Some(HtmlToken::Eof);
                     }
 
@@ -313,12 +859,29 @@ impl Iterator for HtmlTokenizer {
                 }
                 // https://html.spec.whatwg.org/multipage/parsing.html#before-attribute-name-state
                 State::BeforeAttributeName => {
+                    if c.is_ascii_whitespace() {
+                        // Ignore the character.
+                        continue;
+                    }
+
                     if c == '/' || c == '>' || self.is_eof() {
                         self.reconsume = true;
                         self.state = State::AfterAttributeName;
                         continue;
                     }
 
+                    if c == '=' {
+                        // "This is an unexpected-equals-sign-before-attribute-name parse error.
+                        // Start a new attribute in the current tag token. Set that attribute's
+                        // name to the current input character, and its value to the empty string.
+                        // Switch to the attribute name state."
+                        self.parse_error("unexpected-equals-sign-before-attribute-name");
+                        self.start_new_attribute();
+                        self.append_attribute(c, /*is_name*/ true);
+                        self.state = State::AttributeName;
+                        continue;
+                    }
+
                     self.reconsume = true;
                     self.state = State::AttributeName;
                     self.start_new_attribute();
@@ -341,6 +904,11 @@ impl Iterator for HtmlTokenizer {
                         continue;
                     }
 
+                    if c == '"' || c == '\'' || c == '<' {
+                        // unexpected-character-in-attribute-name parse error.
+                        self.parse_error("unexpected-character-in-attribute-name");
+                    }
+
                     self.append_attribute(c, /*is_name*/ true);
                 }
                 // https://html.spec.whatwg.org/multipage/parsing.html#after-attribute-name-state
@@ -366,6 +934,8 @@ impl Iterator for HtmlTokenizer {
                     }
 
                     if self.is_eof() {
+                        // eof-in-tag parse error.
+                        self.parse_error("eof-in-tag");
                         return Some(HtmlToken::Eof);
                     }
 
@@ -390,6 +960,13 @@ impl Iterator for HtmlTokenizer {
                         continue;
                     }
 
+                    if c == '>' {
+                        // missing-attribute-value parse error.
+                        self.parse_error("missing-attribute-value");
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
                     self.reconsume = true;
                     self.state = State::AttributeValueUnquoted;
                 }
@@ -400,7 +977,15 @@ impl Iterator for HtmlTokenizer {
                         continue;
                     }
 
+                    if c == '&' {
+                        self.return_state = State::AttributeValueDoubleQuoted;
+                        self.state = State::CharacterReference;
+                        continue;
+                    }
+
                     if self.is_eof() {
+                        // eof-in-tag parse error.
+                        self.parse_error("eof-in-tag");
                         return Some(HtmlToken::Eof);
                     }
 
@@ -413,7 +998,15 @@ impl Iterator for HtmlTokenizer {
                         continue;
                     }
 
+                    if c == '&' {
+                        self.return_state = State::AttributeValueSingleQuoted;
+                        self.state = State::CharacterReference;
+                        continue;
+                    }
+
                     if self.is_eof() {
+                        // eof-in-tag parse error.
+                        self.parse_error("eof-in-tag");
                         return Some(HtmlToken::Eof);
                     }
 
@@ -426,15 +1019,28 @@ impl Iterator for HtmlTokenizer {
                         continue;
                     }
 
+                    if c == '&' {
+                        self.return_state = State::AttributeValueUnquoted;
+                        self.state = State::CharacterReference;
+                        continue;
+                    }
+
                     if c == '>' {
                         self.state = State::Data;
                         return self.take_latest_token();
                     }
 
                     if self.is_eof() {
+                        // eof-in-tag parse error.
+                        self.parse_error("eof-in-tag");
                         return Some(HtmlToken::Eof);
                     }
 
+                    if c == '"' || c == '\'' || c == '<' || c == '=' || c == '`' {
+                        // unexpected-character-in-unquoted-attribute-value parse error.
+                        self.parse_error("unexpected-character-in-unquoted-attribute-value");
+                    }
+
                     self.append_attribute(c, /*is_name*/ false);
                 }
                 // https://html.spec.whatwg.org/multipage/parsing.html#after-attribute-value-(quoted)-state
@@ -455,9 +1061,13 @@ impl Iterator for HtmlTokenizer {
                     }
 
                     if self.is_eof() {
+                        // eof-in-tag parse error.
+                        self.parse_error("eof-in-tag");
                         return Some(HtmlToken::Eof);
                     }
 
+                    // missing-whitespace-between-attributes parse error.
+                    self.parse_error("missing-whitespace-between-attributes");
                     self.reconsume = true;
                     self.state = State::BeforeAttributeName;
                 }
@@ -470,14 +1080,26 @@ impl Iterator for HtmlTokenizer {
                     }
 
                     if self.is_eof() {
-                        // invalid parse error.
+                        // eof-in-tag parse error.
+                        self.parse_error("eof-in-tag");
                         return Some(HtmlToken::Eof);
                     }
+
+                    // unexpected-solidus-in-tag parse error.
+                    self.parse_error("unexpected-solidus-in-tag");
+                    self.reconsume = true;
+                    self.state = State::BeforeAttributeName;
                 }
-                // https://html.spec.whatwg.org/multipage/parsing.html#script-data-state
-                State::ScriptData => {
+                // https://html.spec.whatwg.org/multipage/parsing.html#rcdata-state
+                State::Rcdata => {
+                    if c == '&' {
+                        self.return_state = State::Rcdata;
+                        self.state = State::CharacterReference;
+                        continue;
+                    }
+
                     if c == '<' {
-                        self.state = State::ScriptDataLessThanSign;
+                        self.state = State::RcdataLessThanSign;
                         continue;
                     }
 
@@ -485,103 +1107,1086 @@ impl Iterator for HtmlTokenizer {
                         return Some(HtmlToken::Eof);
                     }
 
+                    if c == '\0' {
+                        // unexpected-null-character parse error.
+                        self.parse_error("unexpected-null-character");
+                        return Some(HtmlToken::Char('\u{FFFD}'));
+                    }
+
                     return Some(HtmlToken::Char(c));
                 }
-                // https://html.spec.whatwg.org/multipage/parsing.html#script-data-less-than-sign-state
-                State::ScriptDataLessThanSign => {
+                // https://html.spec.whatwg.org/multipage/parsing.html#rcdata-less-than-sign-state
+                State::RcdataLessThanSign => {
                     if c == '/' {
-                        // "Set the temporary buffer to the empty string."
+                        // "Set the temporary buffer to the empty string. Switch to the RCDATA end
+                        // tag open state."
                         self.buf = String::new();
-                        self.state = State::ScriptDataEndTagOpen;
+                        self.state = State::RcdataEndTagOpen;
                         continue;
                     }
 
+                    // "Emit a U+003C LESS-THAN SIGN character token. Reconsume in the RCDATA
+                    // state."
                     self.reconsume = true;
-                    self.state = State::ScriptData;
+                    self.state = State::Rcdata;
                     return Some(HtmlToken::Char('<'));
                 }
-                // https://html.spec.whatwg.org/multipage/parsing.html#script-data-end-tag-open-state
-                State::ScriptDataEndTagOpen => {
+                // https://html.spec.whatwg.org/multipage/parsing.html#rcdata-end-tag-open-state
+                State::RcdataEndTagOpen => {
                     if c.is_ascii_alphabetic() {
-                        self.reconsume = true;
-                        self.state = State::ScriptDataEndTagName;
+                        // "Create a new end tag token, set its tag name to the empty string.
+                        // Reconsume in the RCDATA end tag name state."
                         self.create_tag(false);
+                        self.reconsume = true;
+                        self.state = State::RcdataEndTagName;
                         continue;
                     }
 
-                    self.reconsume = true;
-                    self.state = State::ScriptData;
-                    // TODO: emit '<' and '/'
                     // "Emit a U+003C LESS-THAN SIGN character token and a U+002F SOLIDUS character
-                    // token. Reconsume in the script data state."
+                    // token. Reconsume in the RCDATA state."
+                    self.pending_chars.push_back('/');
+                    self.reconsume = true;
+                    self.state = State::Rcdata;
                     return Some(HtmlToken::Char('<'));
                 }
-                // https://html.spec.whatwg.org/multipage/parsing.html#script-data-end-tag-name-state
-                State::ScriptDataEndTagName => {
-                    if c == '>' {
+                // https://html.spec.whatwg.org/multipage/parsing.html#rcdata-end-tag-name-state
+                State::RcdataEndTagName => {
+                    if c.is_ascii_whitespace() && self.is_appropriate_end_tag_token() {
+                        self.state = State::BeforeAttributeName;
+                        continue;
+                    }
+
+                    if c == '/' && self.is_appropriate_end_tag_token() {
+                        self.state = State::SelfClosingStartTag;
+                        continue;
+                    }
+
+                    if c == '>' && self.is_appropriate_end_tag_token() {
                         self.state = State::Data;
                         return self.take_latest_token();
                     }
 
                     if c.is_ascii_alphabetic() {
-                        self.buf.push(c);
                         self.append_tag_name(c.to_ascii_lowercase());
+                        self.buf.push(c);
                         continue;
                     }
 
                     // "Emit a U+003C LESS-THAN SIGN character token, a U+002F SOLIDUS character
                     // token, and a character token for each of the characters in the temporary
-                    // buffer (in the order they were added to the buffer). Reconsume in the script
-                    // data state."
-                    self.state = State::TemporaryBuffer;
-                    self.buf = String::from("</") + &self.buf;
-                    self.buf.push(c);
-                    continue;
-                }
-                // https://html.spec.whatwg.org/multipage/parsing.html#temporary-buffer
-                State::TemporaryBuffer => {
+                    // buffer (in the order they were added to the buffer). Reconsume in the RCDATA
+                    // state."
+                    self.latest_token = None;
+                    self.pending_chars.push_back('/');
+                    let chars: Vec<char> = self.buf.chars().collect();
+                    self.pending_chars.extend(chars);
+                    self.buf = String::new();
                     self.reconsume = true;
-
-                    if self.buf.chars().count() == 0 {
-                        self.state = State::ScriptData;
+                    self.state = State::Rcdata;
+                    return Some(HtmlToken::Char('<'));
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#rawtext-state
+                State::Rawtext => {
+                    if c == '<' {
+                        self.state = State::RawtextLessThanSign;
                         continue;
                     }
 
-                    // remove the first char
-                    let c = self
-                        .buf
-                        .chars()
-                        .nth(0)
-                        .expect("self.buf should have at least 1 char");
-                    self.buf.remove(0);
+                    if self.is_eof() {
+                        return Some(HtmlToken::Eof);
+                    }
+
+                    if c == '\0' {
+                        // unexpected-null-character parse error.
+                        self.parse_error("unexpected-null-character");
+                        return Some(HtmlToken::Char('\u{FFFD}'));
+                    }
+
                     return Some(HtmlToken::Char(c));
                 }
-            } // end of `match self.state`
-        } // end of `loop`
-    }
-}
+                // https://html.spec.whatwg.org/multipage/parsing.html#rawtext-less-than-sign-state
+                State::RawtextLessThanSign => {
+                    if c == '/' {
+                        // "Set the temporary buffer to the empty string. Switch to the RAWTEXT end
+                        // tag open state."
+                        self.buf = String::new();
+                        self.state = State::RawtextEndTagOpen;
+                        continue;
+                    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::alloc::string::ToString;
-    use alloc::rc::Rc;
-    use alloc::vec;
+                    // "Emit a U+003C LESS-THAN SIGN character token. Reconsume in the RAWTEXT
+                    // state."
+                    self.reconsume = true;
+                    self.state = State::Rawtext;
+                    return Some(HtmlToken::Char('<'));
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#rawtext-end-tag-open-state
+                State::RawtextEndTagOpen => {
+                    if c.is_ascii_alphabetic() {
+                        // "Create a new end tag token, set its tag name to the empty string.
+                        // Reconsume in the RAWTEXT end tag name state."
+                        self.create_tag(false);
+                        self.reconsume = true;
+                        self.state = State::RawtextEndTagName;
+                        continue;
+                    }
 
-    #[test]
-    fn test_empty() {
-        let browser = Browser::new();
-        let html = "".to_string();
-        let mut tokenizer = HtmlTokenizer::new(Rc::downgrade(&browser), html);
-        assert!(tokenizer.next().is_none());
-    }
+                    // "Emit a U+003C LESS-THAN SIGN character token and a U+002F SOLIDUS character
+                    // token. Reconsume in the RAWTEXT state."
+                    self.pending_chars.push_back('/');
+                    self.reconsume = true;
+                    self.state = State::Rawtext;
+                    return Some(HtmlToken::Char('<'));
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#rawtext-end-tag-name-state
+                State::RawtextEndTagName => {
+                    if c.is_ascii_whitespace() && self.is_appropriate_end_tag_token() {
+                        self.state = State::BeforeAttributeName;
+                        continue;
+                    }
 
-    #[test]
-    fn test_start_and_end_tag() {
-        let browser = Browser::new();
-        let html = "<body></body>".to_string();
-        let mut tokenizer = HtmlTokenizer::new(Rc::downgrade(&browser), html);
-        let expected = [
+                    if c == '/' && self.is_appropriate_end_tag_token() {
+                        self.state = State::SelfClosingStartTag;
+                        continue;
+                    }
+
+                    if c == '>' && self.is_appropriate_end_tag_token() {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    if c.is_ascii_alphabetic() {
+                        self.append_tag_name(c.to_ascii_lowercase());
+                        self.buf.push(c);
+                        continue;
+                    }
+
+                    // "Emit a U+003C LESS-THAN SIGN character token, a U+002F SOLIDUS character
+                    // token, and a character token for each of the characters in the temporary
+                    // buffer (in the order they were added to the buffer). Reconsume in the RAWTEXT
+                    // state."
+                    self.latest_token = None;
+                    self.pending_chars.push_back('/');
+                    let chars: Vec<char> = self.buf.chars().collect();
+                    self.pending_chars.extend(chars);
+                    self.buf = String::new();
+                    self.reconsume = true;
+                    self.state = State::Rawtext;
+                    return Some(HtmlToken::Char('<'));
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#script-data-state
+                State::ScriptData => {
+                    if c == '<' {
+                        self.state = State::ScriptDataLessThanSign;
+                        continue;
+                    }
+
+                    if self.is_eof() {
+                        return Some(HtmlToken::Eof);
+                    }
+
+                    return Some(HtmlToken::Char(c));
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#script-data-less-than-sign-state
+                State::ScriptDataLessThanSign => {
+                    if c == '/' {
+                        // "Set the temporary buffer to the empty string."
+                        self.buf = String::new();
+                        self.state = State::ScriptDataEndTagOpen;
+                        continue;
+                    }
+
+                    self.reconsume = true;
+                    self.state = State::ScriptData;
+                    return Some(HtmlToken::Char('<'));
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#script-data-end-tag-open-state
+                State::ScriptDataEndTagOpen => {
+                    if c.is_ascii_alphabetic() {
+                        // "Create a new end tag token, set its tag name to the empty string.
+                        // Reconsume in the script data end tag name state."
+                        self.create_tag(false);
+                        self.reconsume = true;
+                        self.state = State::ScriptDataEndTagName;
+                        continue;
+                    }
+
+                    // "Emit a U+003C LESS-THAN SIGN character token and a U+002F SOLIDUS character
+                    // token. Reconsume in the script data state."
+                    self.pending_chars.push_back('/');
+                    self.reconsume = true;
+                    self.state = State::ScriptData;
+                    return Some(HtmlToken::Char('<'));
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#script-data-end-tag-name-state
+                State::ScriptDataEndTagName => {
+                    if c.is_ascii_whitespace() && self.is_appropriate_end_tag_token() {
+                        self.state = State::BeforeAttributeName;
+                        continue;
+                    }
+
+                    if c == '/' && self.is_appropriate_end_tag_token() {
+                        self.state = State::SelfClosingStartTag;
+                        continue;
+                    }
+
+                    if c == '>' && self.is_appropriate_end_tag_token() {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    if c.is_ascii_alphabetic() {
+                        self.append_tag_name(c.to_ascii_lowercase());
+                        self.buf.push(c);
+                        continue;
+                    }
+
+                    // "Emit a U+003C LESS-THAN SIGN character token, a U+002F SOLIDUS character
+                    // token, and a character token for each of the characters in the temporary
+                    // buffer (in the order they were added to the buffer). Reconsume in the script
+                    // data state."
+                    self.latest_token = None;
+                    self.pending_chars.push_back('/');
+                    let chars: Vec<char> = self.buf.chars().collect();
+                    self.pending_chars.extend(chars);
+                    self.buf = String::new();
+                    self.reconsume = true;
+                    self.state = State::ScriptData;
+                    return Some(HtmlToken::Char('<'));
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#character-reference-state
+                State::CharacterReference => {
+                    // "Set the temporary buffer to the empty string. Append a U+0026 AMPERSAND (&)
+                    // character to the temporary buffer."
+                    self.buf = String::from("&");
+
+                    if c.is_ascii_alphanumeric() {
+                        self.reconsume = true;
+                        self.state = State::NamedCharacterReference;
+                        continue;
+                    }
+
+                    if c == '#' {
+                        self.buf.push(c);
+                        self.state = State::NumericCharacterReference;
+                        continue;
+                    }
+
+                    self.flush_code_points_consumed_as_character_reference();
+                    self.reconsume = true;
+                    self.state = self.return_state.clone();
+                    if let Some(c) = self.pending_chars.pop_front() {
+                        return Some(HtmlToken::Char(c));
+                    }
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#named-character-reference-state
+                State::NamedCharacterReference => {
+                    match self.consume_named_character_reference() {
+                        Some((name, chars)) => {
+                            self.buf.push_str(&name);
+
+                            let next = self.input.get(self.pos).copied();
+                            // "If the character reference was consumed as part of an attribute,
+                            // and the last character matched is not a U+003B SEMICOLON character
+                            // (;), and the next input character is either a U+003D EQUALS SIGN
+                            // character (=) or an ASCII alphanumeric, then, for historical
+                            // reasons, flush code points consumed as a character reference and
+                            // switch to the return state."
+                            if self.is_consumed_as_part_of_attribute()
+                                && !name.ends_with(';')
+                                && next.map_or(false, |n| n == '=' || n.is_ascii_alphanumeric())
+                            {
+                                self.flush_code_points_consumed_as_character_reference();
+                                self.state = self.return_state.clone();
+                                continue;
+                            }
+
+                            // If the last character matched is not ';', this is a
+                            // missing-semicolon-after-character-reference parse error.
+                            if !name.ends_with(';') {
+                                self.parse_error("missing-semicolon-after-character-reference");
+                            }
+                            self.buf = String::from(chars);
+                            self.flush_code_points_consumed_as_character_reference();
+                            self.state = self.return_state.clone();
+                        }
+                        None => {
+                            self.flush_code_points_consumed_as_character_reference();
+                            self.reconsume = true;
+                            self.state = State::AmbiguousAmpersand;
+                        }
+                    }
+
+                    if let Some(c) = self.pending_chars.pop_front() {
+                        return Some(HtmlToken::Char(c));
+                    }
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#ambiguous-ampersand-state
+                State::AmbiguousAmpersand => {
+                    if c.is_ascii_alphanumeric() {
+                        if self.is_consumed_as_part_of_attribute() {
+                            self.append_attribute(c, /*is_name*/ false);
+                            continue;
+                        }
+                        return Some(HtmlToken::Char(c));
+                    }
+
+                    // If the current input character is ';', this is an
+                    // unknown-named-character-reference parse error.
+                    if c == ';' {
+                        self.parse_error("unknown-named-character-reference");
+                    }
+                    self.reconsume = true;
+                    self.state = self.return_state.clone();
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#numeric-character-reference-state
+                State::NumericCharacterReference => {
+                    self.character_reference_code = 0;
+
+                    if c == 'x' || c == 'X' {
+                        self.buf.push(c);
+                        self.state = State::HexadecimalCharacterReferenceStart;
+                        continue;
+                    }
+
+                    self.reconsume = true;
+                    self.state = State::DecimalCharacterReferenceStart;
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#hexadecimal-character-reference-start-state
+                // https://html.spec.whatwg.org/multipage/parsing.html#decimal-character-reference-start-state
+                State::HexadecimalCharacterReferenceStart
+                | State::DecimalCharacterReferenceStart => {
+                    let is_hex = self.state == State::HexadecimalCharacterReferenceStart;
+                    if c.is_ascii_digit() || (is_hex && c.is_ascii_hexdigit()) {
+                        self.reconsume = true;
+                        self.state = if is_hex {
+                            State::HexadecimalCharacterReference
+                        } else {
+                            State::DecimalCharacterReference
+                        };
+                        continue;
+                    }
+
+                    // absence-of-digits-in-numeric-character-reference parse error.
+                    self.parse_error("absence-of-digits-in-numeric-character-reference");
+                    self.flush_code_points_consumed_as_character_reference();
+                    self.reconsume = true;
+                    self.state = self.return_state.clone();
+                    if let Some(c) = self.pending_chars.pop_front() {
+                        return Some(HtmlToken::Char(c));
+                    }
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#hexadecimal-character-reference-state
+                // https://html.spec.whatwg.org/multipage/parsing.html#decimal-character-reference-state
+                State::HexadecimalCharacterReference | State::DecimalCharacterReference => {
+                    let radix = if self.state == State::HexadecimalCharacterReference {
+                        16
+                    } else {
+                        10
+                    };
+
+                    if let Some(digit) = c.to_digit(radix) {
+                        // Saturate the code to avoid an overflow. A code larger than 0x10FFFF is
+                        // replaced with U+FFFD in the numeric character reference end state
+                        // anyway.
+                        self.character_reference_code = self
+                            .character_reference_code
+                            .saturating_mul(radix)
+                            .saturating_add(digit);
+                        continue;
+                    }
+
+                    if c != ';' {
+                        // missing-semicolon-after-character-reference parse error.
+                        self.parse_error("missing-semicolon-after-character-reference");
+                        self.reconsume = true;
+                    }
+
+                    self.numeric_character_reference_end();
+                    if let Some(c) = self.pending_chars.pop_front() {
+                        return Some(HtmlToken::Char(c));
+                    }
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#markup-declaration-open-state
+                State::MarkupDeclarationOpen => {
+                    // "If the next few characters are:"
+                    // "Two U+002D HYPHEN-MINUS characters (-)
+                    // Consume those two characters, create a comment token whose data is the
+                    // empty string, and switch to the comment start state."
+                    if self.consume_if_matches("--", false) {
+                        self.create_comment("");
+                        self.state = State::CommentStart;
+                        continue;
+                    }
+
+                    // "ASCII case-insensitive match for the word "DOCTYPE"
+                    // Consume those characters and switch to the DOCTYPE state."
+                    if self.consume_if_matches("DOCTYPE", true) {
+                        self.state = State::Doctype;
+                        continue;
+                    }
+
+                    // "The string "[CDATA[" (the five uppercase letters "CDATA" with a U+005B LEFT
+                    // SQUARE BRACKET character before and after)
+                    // Consume those characters. If there is an adjusted current node and it is
+                    // not an element in the HTML namespace, then switch to the CDATA section
+                    // state. Otherwise, this is a cdata-in-html-content parse error. Create a
+                    // comment token whose data is the "[CDATA[" string. Switch to the bogus
+                    // comment state."
+                    if self.consume_if_matches("[CDATA[", false) {
+                        if self.in_foreign_content {
+                            self.state = State::CdataSection;
+                        } else {
+                            self.parse_error("cdata-in-html-content");
+                            self.create_comment("[CDATA[");
+                            self.state = State::BogusComment;
+                        }
+                        continue;
+                    }
+
+                    // "Anything else
+                    // This is an incorrectly-opened-comment parse error. Create a comment token
+                    // whose data is the empty string. Switch to the bogus comment state (don't
+                    // consume anything in the current state)."
+                    self.parse_error("incorrectly-opened-comment");
+                    self.create_comment("");
+                    self.reconsume = true;
+                    self.state = State::BogusComment;
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#bogus-comment-state
+                State::BogusComment => {
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    if self.is_eof() {
+                        return self.take_latest_token();
+                    }
+
+                    if c == '\0' {
+                        // unexpected-null-character parse error.
+                        self.parse_error("unexpected-null-character");
+                        self.append_comment("\u{FFFD}");
+                        continue;
+                    }
+
+                    self.append_comment(c.encode_utf8(&mut [0; 4]));
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#comment-start-state
+                State::CommentStart => {
+                    if c == '-' {
+                        self.state = State::CommentStartDash;
+                        continue;
+                    }
+
+                    if c == '>' {
+                        // abrupt-closing-of-empty-comment parse error.
+                        self.parse_error("abrupt-closing-of-empty-comment");
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    self.reconsume = true;
+                    self.state = State::Comment;
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#comment-start-dash-state
+                State::CommentStartDash => {
+                    if c == '-' {
+                        self.state = State::CommentEnd;
+                        continue;
+                    }
+
+                    if c == '>' {
+                        // abrupt-closing-of-empty-comment parse error.
+                        self.parse_error("abrupt-closing-of-empty-comment");
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    if self.is_eof() {
+                        // eof-in-comment parse error.
+                        self.parse_error("eof-in-comment");
+                        return self.take_latest_token();
+                    }
+
+                    self.append_comment("-");
+                    self.reconsume = true;
+                    self.state = State::Comment;
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#comment-state
+                State::Comment => {
+                    if self.is_eof() {
+                        // eof-in-comment parse error.
+                        self.parse_error("eof-in-comment");
+                        return self.take_latest_token();
+                    }
+
+                    if c == '<' {
+                        self.append_comment("<");
+                        self.state = State::CommentLessThanSign;
+                        continue;
+                    }
+
+                    if c == '-' {
+                        self.state = State::CommentEndDash;
+                        continue;
+                    }
+
+                    if c == '\0' {
+                        // unexpected-null-character parse error.
+                        self.parse_error("unexpected-null-character");
+                        self.append_comment("\u{FFFD}");
+                        continue;
+                    }
+
+                    self.append_comment(c.encode_utf8(&mut [0; 4]));
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#comment-less-than-sign-state
+                State::CommentLessThanSign => {
+                    if c == '!' {
+                        self.append_comment("!");
+                        self.state = State::CommentLessThanSignBang;
+                        continue;
+                    }
+
+                    if c == '<' {
+                        self.append_comment("<");
+                        continue;
+                    }
+
+                    self.reconsume = true;
+                    self.state = State::Comment;
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#comment-less-than-sign-bang-state
+                State::CommentLessThanSignBang => {
+                    if c == '-' {
+                        self.state = State::CommentLessThanSignBangDash;
+                        continue;
+                    }
+
+                    self.reconsume = true;
+                    self.state = State::Comment;
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#comment-less-than-sign-bang-dash-state
+                State::CommentLessThanSignBangDash => {
+                    if c == '-' {
+                        self.state = State::CommentLessThanSignBangDashDash;
+                        continue;
+                    }
+
+                    self.reconsume = true;
+                    self.state = State::CommentEndDash;
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#comment-less-than-sign-bang-dash-dash-state
+                State::CommentLessThanSignBangDashDash => {
+                    // If the current input character is not '>' nor EOF, this is a
+                    // nested-comment parse error.
+                    if c != '>' && !self.is_eof() {
+                        self.parse_error("nested-comment");
+                    }
+                    self.reconsume = true;
+                    self.state = State::CommentEnd;
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#comment-end-dash-state
+                State::CommentEndDash => {
+                    if self.is_eof() {
+                        // eof-in-comment parse error.
+                        self.parse_error("eof-in-comment");
+                        return self.take_latest_token();
+                    }
+
+                    if c == '-' {
+                        self.state = State::CommentEnd;
+                        continue;
+                    }
+
+                    self.append_comment("-");
+                    self.reconsume = true;
+                    self.state = State::Comment;
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#comment-end-state
+                State::CommentEnd => {
+                    if self.is_eof() {
+                        // eof-in-comment parse error.
+                        self.parse_error("eof-in-comment");
+                        return self.take_latest_token();
+                    }
+
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    if c == '!' {
+                        self.state = State::CommentEndBang;
+                        continue;
+                    }
+
+                    if c == '-' {
+                        self.append_comment("-");
+                        continue;
+                    }
+
+                    self.append_comment("--");
+                    self.reconsume = true;
+                    self.state = State::Comment;
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#comment-end-bang-state
+                State::CommentEndBang => {
+                    if self.is_eof() {
+                        // eof-in-comment parse error.
+                        self.parse_error("eof-in-comment");
+                        return self.take_latest_token();
+                    }
+
+                    if c == '-' {
+                        self.append_comment("--!");
+                        self.state = State::CommentEndDash;
+                        continue;
+                    }
+
+                    if c == '>' {
+                        // incorrectly-closed-comment parse error.
+                        self.parse_error("incorrectly-closed-comment");
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    self.append_comment("--!");
+                    self.reconsume = true;
+                    self.state = State::Comment;
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#doctype-state
+                State::Doctype => {
+                    if self.is_eof() {
+                        // eof-in-doctype parse error.
+                        self.parse_error("eof-in-doctype");
+                        self.create_doctype();
+                        self.set_force_quirks_flag();
+                        return self.take_latest_token();
+                    }
+
+                    if c.is_ascii_whitespace() {
+                        self.state = State::BeforeDoctypeName;
+                        continue;
+                    }
+
+                    // If the current input character is not '>', this is a
+                    // missing-whitespace-before-doctype-name parse error.
+                    if c != '>' {
+                        self.parse_error("missing-whitespace-before-doctype-name");
+                    }
+                    self.reconsume = true;
+                    self.state = State::BeforeDoctypeName;
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#before-doctype-name-state
+                State::BeforeDoctypeName => {
+                    if self.is_eof() {
+                        // eof-in-doctype parse error.
+                        self.parse_error("eof-in-doctype");
+                        self.create_doctype();
+                        self.set_force_quirks_flag();
+                        return self.take_latest_token();
+                    }
+
+                    if c.is_ascii_whitespace() {
+                        // Ignore the character.
+                        continue;
+                    }
+
+                    if c == '>' {
+                        // missing-doctype-name parse error.
+                        self.parse_error("missing-doctype-name");
+                        self.create_doctype();
+                        self.set_force_quirks_flag();
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    self.create_doctype();
+                    if c == '\0' {
+                        // unexpected-null-character parse error.
+                        self.parse_error("unexpected-null-character");
+                        self.append_doctype_name('\u{FFFD}');
+                    } else {
+                        self.append_doctype_name(c.to_ascii_lowercase());
+                    }
+                    self.state = State::DoctypeName;
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#doctype-name-state
+                State::DoctypeName => {
+                    if self.is_eof() {
+                        // eof-in-doctype parse error.
+                        self.parse_error("eof-in-doctype");
+                        self.set_force_quirks_flag();
+                        return self.take_latest_token();
+                    }
+
+                    if c.is_ascii_whitespace() {
+                        self.state = State::AfterDoctypeName;
+                        continue;
+                    }
+
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    if c == '\0' {
+                        // unexpected-null-character parse error.
+                        self.parse_error("unexpected-null-character");
+                        self.append_doctype_name('\u{FFFD}');
+                        continue;
+                    }
+
+                    self.append_doctype_name(c.to_ascii_lowercase());
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#after-doctype-name-state
+                State::AfterDoctypeName => {
+                    if self.is_eof() {
+                        // eof-in-doctype parse error.
+                        self.parse_error("eof-in-doctype");
+                        self.set_force_quirks_flag();
+                        return self.take_latest_token();
+                    }
+
+                    if c.is_ascii_whitespace() {
+                        // Ignore the character.
+                        continue;
+                    }
+
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    // "If the six characters starting from the current input character are an
+                    // ASCII case-insensitive match for the word "PUBLIC", then consume those
+                    // characters and switch to the after DOCTYPE public keyword state."
+                    if self.consume_if_matches("PUBLIC", true) {
+                        self.state = State::AfterDoctypePublicKeyword;
+                        continue;
+                    }
+
+                    // "Otherwise, if the six characters starting from the current input
+                    // character are an ASCII case-insensitive match for the word "SYSTEM", then
+                    // consume those characters and switch to the after DOCTYPE system keyword
+                    // state."
+                    if self.consume_if_matches("SYSTEM", true) {
+                        self.state = State::AfterDoctypeSystemKeyword;
+                        continue;
+                    }
+
+                    // invalid-character-sequence-after-doctype-name parse error.
+                    self.parse_error("invalid-character-sequence-after-doctype-name");
+                    self.set_force_quirks_flag();
+                    self.reconsume = true;
+                    self.state = State::BogusDoctype;
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#after-doctype-public-keyword-state
+                // https://html.spec.whatwg.org/multipage/parsing.html#before-doctype-public-identifier-state
+                // https://html.spec.whatwg.org/multipage/parsing.html#after-doctype-system-keyword-state
+                // https://html.spec.whatwg.org/multipage/parsing.html#before-doctype-system-identifier-state
+                State::AfterDoctypePublicKeyword
+                | State::BeforeDoctypePublicIdentifier
+                | State::AfterDoctypeSystemKeyword
+                | State::BeforeDoctypeSystemIdentifier => {
+                    let is_public = self.state == State::AfterDoctypePublicKeyword
+                        || self.state == State::BeforeDoctypePublicIdentifier;
+
+                    if self.is_eof() {
+                        // eof-in-doctype parse error.
+                        self.parse_error("eof-in-doctype");
+                        self.set_force_quirks_flag();
+                        return self.take_latest_token();
+                    }
+
+                    if c.is_ascii_whitespace() {
+                        // The whitespace after a keyword switches to the before identifier
+                        // state. Otherwise, ignore the character.
+                        if self.state == State::AfterDoctypePublicKeyword {
+                            self.state = State::BeforeDoctypePublicIdentifier;
+                        } else if self.state == State::AfterDoctypeSystemKeyword {
+                            self.state = State::BeforeDoctypeSystemIdentifier;
+                        }
+                        continue;
+                    }
+
+                    // If the current state is an after keyword state, a quotation mark is a
+                    // missing-whitespace-after-doctype-public/system-keyword parse error.
+                    if c == '"' || c == '\'' {
+                        if self.state == State::AfterDoctypePublicKeyword {
+                            self.parse_error("missing-whitespace-after-doctype-public-keyword");
+                        } else if self.state == State::AfterDoctypeSystemKeyword {
+                            self.parse_error("missing-whitespace-after-doctype-system-keyword");
+                        }
+                        self.start_doctype_identifier(is_public);
+                        self.state = match (is_public, c == '"') {
+                            (true, true) => State::DoctypePublicIdentifierDoubleQuoted,
+                            (true, false) => State::DoctypePublicIdentifierSingleQuoted,
+                            (false, true) => State::DoctypeSystemIdentifierDoubleQuoted,
+                            (false, false) => State::DoctypeSystemIdentifierSingleQuoted,
+                        };
+                        continue;
+                    }
+
+                    if c == '>' {
+                        // missing-doctype-public/system-identifier parse error.
+                        self.parse_error(if is_public {
+                            "missing-doctype-public-identifier"
+                        } else {
+                            "missing-doctype-system-identifier"
+                        });
+                        self.set_force_quirks_flag();
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    // missing-quote-before-doctype-public/system-identifier parse error.
+                    self.parse_error(if is_public {
+                        "missing-quote-before-doctype-public-identifier"
+                    } else {
+                        "missing-quote-before-doctype-system-identifier"
+                    });
+                    self.set_force_quirks_flag();
+                    self.reconsume = true;
+                    self.state = State::BogusDoctype;
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#doctype-public-identifier-(double-quoted)-state
+                // https://html.spec.whatwg.org/multipage/parsing.html#doctype-public-identifier-(single-quoted)-state
+                // https://html.spec.whatwg.org/multipage/parsing.html#doctype-system-identifier-(double-quoted)-state
+                // https://html.spec.whatwg.org/multipage/parsing.html#doctype-system-identifier-(single-quoted)-state
+                State::DoctypePublicIdentifierDoubleQuoted
+                | State::DoctypePublicIdentifierSingleQuoted
+                | State::DoctypeSystemIdentifierDoubleQuoted
+                | State::DoctypeSystemIdentifierSingleQuoted => {
+                    let (is_public, quote) = match self.state {
+                        State::DoctypePublicIdentifierDoubleQuoted => (true, '"'),
+                        State::DoctypePublicIdentifierSingleQuoted => (true, '\''),
+                        State::DoctypeSystemIdentifierDoubleQuoted => (false, '"'),
+                        _ => (false, '\''),
+                    };
+
+                    if self.is_eof() {
+                        // eof-in-doctype parse error.
+                        self.parse_error("eof-in-doctype");
+                        self.set_force_quirks_flag();
+                        return self.take_latest_token();
+                    }
+
+                    if c == quote {
+                        self.state = if is_public {
+                            State::AfterDoctypePublicIdentifier
+                        } else {
+                            State::AfterDoctypeSystemIdentifier
+                        };
+                        continue;
+                    }
+
+                    if c == '>' {
+                        // abrupt-doctype-public/system-identifier parse error.
+                        self.parse_error(if is_public {
+                            "abrupt-doctype-public-identifier"
+                        } else {
+                            "abrupt-doctype-system-identifier"
+                        });
+                        self.set_force_quirks_flag();
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    if c == '\0' {
+                        // unexpected-null-character parse error.
+                        self.parse_error("unexpected-null-character");
+                        self.append_doctype_identifier('\u{FFFD}', is_public);
+                        continue;
+                    }
+
+                    self.append_doctype_identifier(c, is_public);
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#after-doctype-public-identifier-state
+                // https://html.spec.whatwg.org/multipage/parsing.html#between-doctype-public-and-system-identifiers-state
+                State::AfterDoctypePublicIdentifier
+                | State::BetweenDoctypePublicAndSystemIdentifiers => {
+                    if self.is_eof() {
+                        // eof-in-doctype parse error.
+                        self.parse_error("eof-in-doctype");
+                        self.set_force_quirks_flag();
+                        return self.take_latest_token();
+                    }
+
+                    if c.is_ascii_whitespace() {
+                        self.state = State::BetweenDoctypePublicAndSystemIdentifiers;
+                        continue;
+                    }
+
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    // If the current state is the after DOCTYPE public identifier state, a
+                    // quotation mark is a
+                    // missing-whitespace-between-doctype-public-and-system-identifiers parse
+                    // error.
+                    if c == '"' || c == '\'' {
+                        if self.state == State::AfterDoctypePublicIdentifier {
+                            self.parse_error(
+                                "missing-whitespace-between-doctype-public-and-system-identifiers",
+                            );
+                        }
+                        self.start_doctype_identifier(/*is_public*/ false);
+                        self.state = if c == '"' {
+                            State::DoctypeSystemIdentifierDoubleQuoted
+                        } else {
+                            State::DoctypeSystemIdentifierSingleQuoted
+                        };
+                        continue;
+                    }
+
+                    // missing-quote-before-doctype-system-identifier parse error.
+                    self.parse_error("missing-quote-before-doctype-system-identifier");
+                    self.set_force_quirks_flag();
+                    self.reconsume = true;
+                    self.state = State::BogusDoctype;
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#after-doctype-system-identifier-state
+                State::AfterDoctypeSystemIdentifier => {
+                    if self.is_eof() {
+                        // eof-in-doctype parse error.
+                        self.parse_error("eof-in-doctype");
+                        self.set_force_quirks_flag();
+                        return self.take_latest_token();
+                    }
+
+                    if c.is_ascii_whitespace() {
+                        // Ignore the character.
+                        continue;
+                    }
+
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    // unexpected-character-after-doctype-system-identifier parse error. This does
+                    // not set the force-quirks flag.
+                    self.parse_error("unexpected-character-after-doctype-system-identifier");
+                    self.reconsume = true;
+                    self.state = State::BogusDoctype;
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#bogus-doctype-state
+                State::BogusDoctype => {
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    if self.is_eof() {
+                        return self.take_latest_token();
+                    }
+
+                    // Ignore the character.
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#cdata-section-state
+                State::CdataSection => {
+                    if c == ']' {
+                        self.state = State::CdataSectionBracket;
+                        continue;
+                    }
+
+                    if self.is_eof() {
+                        // eof-in-cdata parse error.
+                        self.parse_error("eof-in-cdata");
+                        return Some(HtmlToken::Eof);
+                    }
+
+                    return Some(HtmlToken::Char(c));
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#cdata-section-bracket-state
+                State::CdataSectionBracket => {
+                    if c == ']' {
+                        self.state = State::CdataSectionEnd;
+                        continue;
+                    }
+
+                    self.reconsume = true;
+                    self.state = State::CdataSection;
+                    return Some(HtmlToken::Char(']'));
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#cdata-section-end-state
+                State::CdataSectionEnd => {
+                    if c == ']' {
+                        return Some(HtmlToken::Char(']'));
+                    }
+
+                    if c == '>' {
+                        self.state = State::Data;
+                        continue;
+                    }
+
+                    self.reconsume = true;
+                    self.state = State::CdataSection;
+                    self.pending_chars.push_back(']');
+                    return Some(HtmlToken::Char(']'));
+                }
+            } // end of `match self.state`
+        } // end of `loop`
+    }
+}
+
+impl Iterator for HtmlTokenizer {
+    type Item = HtmlToken;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Characters produced at once share the range of the input which produced them.
+        if let Some(c) = self.pending_chars.pop_front() {
+            return Some(HtmlToken::Char(c));
+        }
+
+        if self.pos >= self.input.len() && !self.reconsume {
+            return None;
+        }
+
+        // The character to be reconsumed is the first character of the next token.
+        let start = match self.reconsume {
+            true => self.pos - 1,
+            false => self.pos,
+        };
+        let token = self.consume_token();
+        let end = match self.reconsume {
+            true => self.pos - 1,
+            false => self.pos,
+        };
+        let len = self.input.len();
+        self.token_range = self.line_map.range(start.min(len), end.min(len));
+
+        token
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc::string::ToString;
+    use alloc::rc::Rc;
+    use alloc::vec;
+
+    #[test]
+    fn test_empty() {
+        let browser = Browser::new();
+        let html = "".to_string();
+        let mut tokenizer = HtmlTokenizer::new(Rc::downgrade(&browser), html);
+        assert!(tokenizer.next().is_none());
+    }
+
+    #[test]
+    fn test_start_and_end_tag() {
+        let browser = Browser::new();
+        let html = "<body></body>".to_string();
+        let mut tokenizer = HtmlTokenizer::new(Rc::downgrade(&browser), html);
+        let expected = [
             HtmlToken::StartTag {
                 tag: "body".to_string(),
                 self_closing: false,
@@ -679,4 +2284,330 @@ mod tests {
             assert_eq!(Some(e), tokenizer.next());
         }
     }
+
+    fn chars(tokenizer: &mut HtmlTokenizer) -> String {
+        let mut result = String::new();
+        for token in tokenizer {
+            match token {
+                HtmlToken::Char(c) => result.push(c),
+                _ => break,
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn test_named_character_reference() {
+        let browser = Browser::new();
+        let html = "&lt;p&gt; &amp;&nbsp;&NotEqualTilde;".to_string();
+        let mut tokenizer = HtmlTokenizer::new(Rc::downgrade(&browser), html);
+        assert_eq!(
+            "<p> &\u{a0}\u{2242}\u{338}".to_string(),
+            chars(&mut tokenizer)
+        );
+    }
+
+    #[test]
+    fn test_numeric_character_reference() {
+        let browser = Browser::new();
+        let html = "&#x41;&#66;&#X63&#0;&#x110000;&#128;&#xD800;".to_string();
+        let mut tokenizer = HtmlTokenizer::new(Rc::downgrade(&browser), html);
+        assert_eq!(
+            "ABc\u{fffd}\u{fffd}\u{20ac}\u{fffd}".to_string(),
+            chars(&mut tokenizer)
+        );
+    }
+
+    #[test]
+    fn test_invalid_character_reference() {
+        let browser = Browser::new();
+        let html = "& &# &#x; &foo; &notit; &copy".to_string();
+        let mut tokenizer = HtmlTokenizer::new(Rc::downgrade(&browser), html);
+        assert_eq!(
+            "& &# &#x; &foo; \u{ac}it; \u{a9}".to_string(),
+            chars(&mut tokenizer)
+        );
+    }
+
+    #[test]
+    fn test_character_reference_in_attribute() {
+        let browser = Browser::new();
+        let html =
+            "<a href=\"?a=1&amp;b=2&copy=3&lt\" title='&quot;x&quot;' id=a&gt;b>".to_string();
+        let mut tokenizer = HtmlTokenizer::new(Rc::downgrade(&browser), html);
+        match tokenizer.next() {
+            Some(HtmlToken::StartTag { attributes, .. }) => {
+                assert_eq!("?a=1&b=2&copy=3<".to_string(), attributes[0].value());
+                assert_eq!("\"x\"".to_string(), attributes[1].value());
+                assert_eq!("a>b".to_string(), attributes[2].value());
+            }
+            t => panic!("expected a start tag but got {:?}", t),
+        }
+    }
+
+    #[test]
+    fn test_comment() {
+        let browser = Browser::new();
+        let html = "<!-- a -- b --><!----><!--><!-x-><?php ?></ x>".to_string();
+        let mut tokenizer = HtmlTokenizer::new(Rc::downgrade(&browser), html);
+        let expected = [
+            HtmlToken::Comment(" a -- b ".to_string()),
+            HtmlToken::Comment("".to_string()),
+            HtmlToken::Comment("".to_string()),
+            HtmlToken::Comment("-x-".to_string()),
+            HtmlToken::Comment("?php ?".to_string()),
+            HtmlToken::Comment(" x".to_string()),
+        ];
+        for e in expected {
+            assert_eq!(Some(e), tokenizer.next());
+        }
+        assert!(tokenizer.next().is_none());
+    }
+
+    #[test]
+    fn test_doctype() {
+        let browser = Browser::new();
+        let html = "<!DOCTYPE html><!doctype HTML PUBLIC \"-//W3C//DTD HTML 4.01//EN\" 'http://www.w3.org/TR/html4/strict.dtd'><!DOCTYPE>".to_string();
+        let mut tokenizer = HtmlTokenizer::new(Rc::downgrade(&browser), html);
+        let expected = [
+            HtmlToken::Doctype {
+                name: Some("html".to_string()),
+                public_identifier: None,
+                system_identifier: None,
+                force_quirks: false,
+            },
+            HtmlToken::Doctype {
+                name: Some("html".to_string()),
+                public_identifier: Some("-//W3C//DTD HTML 4.01//EN".to_string()),
+                system_identifier: Some("http://www.w3.org/TR/html4/strict.dtd".to_string()),
+                force_quirks: false,
+            },
+            HtmlToken::Doctype {
+                name: None,
+                public_identifier: None,
+                system_identifier: None,
+                force_quirks: true,
+            },
+        ];
+        for e in expected {
+            assert_eq!(Some(e), tokenizer.next());
+        }
+    }
+
+    #[test]
+    fn test_cdata() {
+        let browser = Browser::new();
+        let html = "<![CDATA[a]]b]]]>".to_string();
+        let mut tokenizer = HtmlTokenizer::new(Rc::downgrade(&browser), html.clone());
+        assert_eq!(
+            Some(HtmlToken::Comment("[CDATA[a]]b]]]".to_string())),
+            tokenizer.next()
+        );
+
+        let mut tokenizer = HtmlTokenizer::new(Rc::downgrade(&browser), html);
+        tokenizer.set_in_foreign_content(true);
+        assert_eq!("a]]b]".to_string(), chars(&mut tokenizer));
+    }
+
+    #[test]
+    fn test_rcdata() {
+        let browser = Browser::new();
+        let html = "<title>a<b>&amp;</p></ti</title x>c".to_string();
+        let mut tokenizer = HtmlTokenizer::new(Rc::downgrade(&browser), html);
+        assert_eq!(
+            Some(HtmlToken::StartTag {
+                tag: "title".to_string(),
+                self_closing: false,
+                attributes: Vec::new(),
+            }),
+            tokenizer.next()
+        );
+
+        // Only the end tag of the last start tag ends the RCDATA state, and character references
+        // are still consumed.
+        tokenizer.switch_context(State::Rcdata);
+        let mut text = String::new();
+        let end_tag = loop {
+            match tokenizer.next() {
+                Some(HtmlToken::Char(c)) => text.push(c),
+                t => break t,
+            }
+        };
+        assert_eq!("a<b>&</p></ti".to_string(), text);
+        assert_eq!(
+            Some(HtmlToken::EndTag {
+                tag: "title".to_string()
+            }),
+            end_tag
+        );
+        assert_eq!(Some(HtmlToken::Char('c')), tokenizer.next());
+    }
+
+    #[test]
+    fn test_script_data() {
+        let browser = Browser::new();
+        let html = "<script>var s = \"<b>1</b></ x\";</SCRIPT >c".to_string();
+        let mut tokenizer = HtmlTokenizer::new(Rc::downgrade(&browser), html);
+        assert_eq!(
+            Some(HtmlToken::StartTag {
+                tag: "script".to_string(),
+                self_closing: false,
+                attributes: Vec::new(),
+            }),
+            tokenizer.next()
+        );
+
+        // Only the end tag of the last start tag ends the script data state.
+        tokenizer.switch_context(State::ScriptData);
+        let mut text = String::new();
+        let end_tag = loop {
+            match tokenizer.next() {
+                Some(HtmlToken::Char(c)) => text.push(c),
+                t => break t,
+            }
+        };
+        assert_eq!("var s = \"<b>1</b></ x\";".to_string(), text);
+        assert_eq!(
+            Some(HtmlToken::EndTag {
+                tag: "script".to_string()
+            }),
+            end_tag
+        );
+        assert_eq!(Some(HtmlToken::Char('c')), tokenizer.next());
+    }
+
+    #[test]
+    fn test_rawtext() {
+        let browser = Browser::new();
+        let html = "<style>a</p>&amp;</script></style>c".to_string();
+        let mut tokenizer = HtmlTokenizer::new(Rc::downgrade(&browser), html);
+        assert_eq!(
+            Some(HtmlToken::StartTag {
+                tag: "style".to_string(),
+                self_closing: false,
+                attributes: Vec::new(),
+            }),
+            tokenizer.next()
+        );
+
+        // Only the end tag of the last start tag ends the RAWTEXT state, and a character reference
+        // is not consumed.
+        tokenizer.switch_context(State::Rawtext);
+        let mut text = String::new();
+        let end_tag = loop {
+            match tokenizer.next() {
+                Some(HtmlToken::Char(c)) => text.push(c),
+                t => break t,
+            }
+        };
+        assert_eq!("a</p>&amp;</script>".to_string(), text);
+        assert_eq!(
+            Some(HtmlToken::EndTag {
+                tag: "style".to_string()
+            }),
+            end_tag
+        );
+        assert_eq!(Some(HtmlToken::Char('c')), tokenizer.next());
+    }
+
+    #[test]
+    fn test_parse_error_position() {
+        let browser = Browser::new();
+        let html = "<p>\n  <a href=x\"y\">&#0;</a></b/c>\n<div".to_string();
+        let mut tokenizer = HtmlTokenizer::new(Rc::downgrade(&browser), html);
+        tokenizer.set_url("http://example.com/index.html".to_string());
+        while tokenizer.next().is_some() {}
+
+        let errors: Vec<(String, String)> = browser
+            .borrow()
+            .diagnostics()
+            .iter()
+            .map(|d| (d.code(), d.position().to_string()))
+            .collect();
+        assert_eq!(
+            vec![
+                (
+                    "unexpected-character-in-unquoted-attribute-value".to_string(),
+                    "2:12".to_string()
+                ),
+                (
+                    "unexpected-character-in-unquoted-attribute-value".to_string(),
+                    "2:14".to_string()
+                ),
+                ("null-character-reference".to_string(), "2:19".to_string()),
+                ("unexpected-solidus-in-tag".to_string(), "2:28".to_string()),
+                ("end-tag-with-attributes".to_string(), "2:28".to_string()),
+                ("eof-in-tag".to_string(), "3:5".to_string()),
+            ],
+            errors
+        );
+        assert_eq!(
+            "http://example.com/index.html".to_string(),
+            browser.borrow().diagnostics()[0].url()
+        );
+    }
+
+    #[test]
+    fn test_token_range() {
+        let browser = Browser::new();
+        let html = "<p class=a>x&lt;\r\n<!-- c --></p>".to_string();
+        let source = HtmlTokenizer::preprocess(&html);
+        let mut tokenizer = HtmlTokenizer::new(Rc::downgrade(&browser), html);
+
+        let mut ranges = Vec::new();
+        while tokenizer.next().is_some() {
+            let range = tokenizer.token_range();
+            ranges.push((
+                source[range.start()..range.end()].to_string(),
+                range.start_position().to_string(),
+            ));
+        }
+        assert_eq!(
+            vec![
+                ("<p class=a>".to_string(), "1:1".to_string()),
+                ("x".to_string(), "1:12".to_string()),
+                ("&lt;".to_string(), "1:13".to_string()),
+                ("\n".to_string(), "1:17".to_string()),
+                ("<!-- c -->".to_string(), "2:1".to_string()),
+                ("</p>".to_string(), "2:11".to_string()),
+            ],
+            ranges
+        );
+    }
+
+    #[test]
+    fn test_invalid_tag_open() {
+        let browser = Browser::new();
+        let html = "a < b <a x=1 x=2 / y>".to_string();
+        let tokenizer = HtmlTokenizer::new(Rc::downgrade(&browser), html);
+
+        // A duplicate attribute is removed and a solidus in a tag is ignored.
+        let mut x = Attribute::new();
+        x.add_char('x', true);
+        x.add_char('1', false);
+        let mut y = Attribute::new();
+        y.add_char('y', true);
+        let mut expected: Vec<HtmlToken> = "a < b ".chars().map(HtmlToken::Char).collect();
+        expected.push(HtmlToken::StartTag {
+            tag: "a".to_string(),
+            self_closing: false,
+            attributes: vec![x, y],
+        });
+        assert_eq!(expected, tokenizer.collect::<Vec<HtmlToken>>());
+
+        let codes: Vec<String> = browser
+            .borrow()
+            .diagnostics()
+            .iter()
+            .map(|d| d.code())
+            .collect();
+        assert_eq!(
+            vec![
+                "invalid-first-character-of-tag-name".to_string(),
+                "unexpected-solidus-in-tag".to_string(),
+                "duplicate-attribute".to_string(),
+            ],
+            codes
+        );
+    }
 }
@@ -1,13 +1,16 @@
 //! https://github.com/estree/estree
 //! https://astexplorer.net/
 
+use crate::log::Diagnostic;
+use crate::log::SourceKind;
 use crate::renderer::js::token::JsLexer;
 use crate::renderer::js::token::Token;
+use crate::renderer::source::SourcePosition;
 use alloc::rc::Rc;
 use alloc::string::String;
+use alloc::string::ToString;
 use alloc::vec;
 use alloc::vec::Vec;
-use core::iter::Peekable;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Program {
@@ -49,6 +52,12 @@ pub enum Node {
         params: Vec<Option<Rc<Node>>>,
         body: Option<Rc<Node>>,
     },
+    /// https://github.com/estree/estree/blob/master/es2015.md#forofstatement
+    ForOfStatement {
+        left: Option<Rc<Node>>,
+        right: Option<Rc<Node>>,
+        body: Option<Rc<Node>>,
+    },
     /// https://github.com/estree/estree/blob/master/es5.md#variabledeclaration
     VariableDeclaration { declarations: Vec<Option<Rc<Node>>> },
     /// https://github.com/estree/estree/blob/master/es5.md#variabledeclarator
@@ -72,12 +81,20 @@ pub enum Node {
     MemberExpression {
         object: Option<Rc<Node>>,
         property: Option<Rc<Node>>,
+        /// True if the property is an expression such as `object[property]`, and false if it's
+        /// an identifier such as `object.property`.
+        computed: bool,
     },
     /// https://github.com/estree/estree/blob/master/es5.md#callexpression
     CallExpression {
         callee: Option<Rc<Node>>,
         arguments: Vec<Option<Rc<Node>>>,
     },
+    /// https://github.com/estree/estree/blob/master/es5.md#newexpression
+    NewExpression {
+        callee: Option<Rc<Node>>,
+        arguments: Vec<Option<Rc<Node>>>,
+    },
     /// https://github.com/estree/estree/blob/master/es5.md#identifier
     /// https://262.ecma-international.org/12.0/#prod-Identifier
     Identifier(String),
@@ -134,6 +151,14 @@ impl Node {
         Some(Rc::new(Node::FunctionDeclaration { id, params, body }))
     }
 
+    pub fn new_for_of_statement(
+        left: Option<Rc<Self>>,
+        right: Option<Rc<Self>>,
+        body: Option<Rc<Self>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::ForOfStatement { left, right, body }))
+    }
+
     pub fn new_variable_declarator(
         id: Option<Rc<Self>>,
         init: Option<Rc<Self>>,
@@ -148,8 +173,13 @@ impl Node {
     pub fn new_member_expression(
         object: Option<Rc<Self>>,
         property: Option<Rc<Self>>,
+        computed: bool,
     ) -> Option<Rc<Self>> {
-        Some(Rc::new(Node::MemberExpression { object, property }))
+        Some(Rc::new(Node::MemberExpression {
+            object,
+            property,
+            computed,
+        }))
     }
 
     pub fn new_call_expression(
@@ -159,6 +189,13 @@ impl Node {
         Some(Rc::new(Node::CallExpression { callee, arguments }))
     }
 
+    pub fn new_new_expression(
+        callee: Option<Rc<Self>>,
+        arguments: Vec<Option<Rc<Self>>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::NewExpression { callee, arguments }))
+    }
+
     pub fn new_identifier(name: String) -> Option<Rc<Self>> {
         Some(Rc::new(Node::Identifier(name)))
     }
@@ -174,12 +211,52 @@ impl Node {
 
 #[derive(Debug)]
 pub struct JsParser {
-    t: Peekable<JsLexer>,
+    t: JsLexer,
+    /// The URL of the script, which is reported with syntax errors.
+    url: String,
+    /// The position of the start of the script in the document at `url`.
+    start_position: SourcePosition,
+    diagnostics: Vec<Diagnostic>,
 }
 
 impl JsParser {
     pub fn new(t: JsLexer) -> Self {
-        Self { t: t.peekable() }
+        Self {
+            t,
+            url: String::new(),
+            start_position: SourcePosition::default(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    pub fn set_url(&mut self, url: String) {
+        self.url = url;
+    }
+
+    /// Sets the position of the start of the script in the document, for a script embedded in a
+    /// script element. Syntax errors are reported at positions in the document.
+    pub fn set_start_position(&mut self, position: SourcePosition) {
+        self.start_position = position;
+    }
+
+    /// Returns the syntax errors found by `parse_ast`. The script must not be run if there is any
+    /// error.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.diagnostics.clone()
+    }
+
+    /// Records a syntax error at the current token.
+    fn syntax_error(&mut self, code: &str) {
+        self.syntax_error_at(code, self.t.position());
+    }
+
+    fn syntax_error_at(&mut self, code: &str, position: SourcePosition) {
+        self.diagnostics.push(Diagnostic::new(
+            SourceKind::Js,
+            code.to_string(),
+            self.url.clone(),
+            position.offset_by(self.start_position),
+        ));
     }
 
     /// Literal ::= ( <DECIMAL_LITERAL> | <HEX_INTEGER_LITERAL> | <STRING_LITERAL> |
@@ -194,7 +271,10 @@ impl JsParser {
     fn primary_expression(&mut self) -> Option<Rc<Node>> {
         let t = match self.t.next() {
             Some(token) => token,
-            None => return None,
+            None => {
+                self.syntax_error("unexpected-eof");
+                return None;
+            }
         };
 
         match t {
@@ -202,7 +282,24 @@ impl JsParser {
             // Literal
             Token::Number(value) => Node::new_numeric_literal(value),
             Token::StringLiteral(value) => Node::new_string_literal(value),
-            _ => None,
+            _ => {
+                self.syntax_error("unexpected-token");
+                None
+            }
+        }
+    }
+
+    /// AllocationExpression ::= ( "new" MemberExpression ( ( Arguments ( MemberExpressionPart )* )* ) )
+    fn allocation_expression(&mut self) -> Option<Rc<Node>> {
+        let callee = self.member_expression();
+
+        match self.t.peek() {
+            Some(Token::Punctuator('(')) => {
+                // consume '('
+                assert!(self.t.next().is_some());
+                Node::new_new_expression(callee, self.arguments())
+            }
+            _ => Node::new_new_expression(callee, Vec::new()),
         }
     }
 
@@ -211,24 +308,47 @@ impl JsParser {
     /// MemberExpression ::= ( ( FunctionExpression | PrimaryExpression ) ( MemberExpressionPart)* )
     ///                    | AllocationExpression
     fn member_expression(&mut self) -> Option<Rc<Node>> {
-        let expr = self.primary_expression();
+        if let Some(Token::Keyword(keyword)) = self.t.peek() {
+            if keyword == "new" {
+                // consume "new"
+                assert!(self.t.next().is_some());
+                return self.allocation_expression();
+            }
+        }
 
-        let t = match self.t.peek() {
-            Some(token) => token,
-            None => return expr,
-        };
+        let mut expr = self.primary_expression();
+        while let Some(member) = self.member_expression_part(expr.clone()) {
+            expr = member;
+        }
+        expr
+    }
 
-        match t {
-            Token::Punctuator(c) => {
-                if c == &'.' {
-                    // consume '.'
-                    assert!(self.t.next().is_some());
-                    return Node::new_member_expression(expr, self.identifier());
+    /// MemberExpressionPart ::= ( "[" Expression "]" ) | ( "." Identifier )
+    ///
+    /// Returns a member expression of `object` if the next token starts MemberExpressionPart.
+    fn member_expression_part(&mut self, object: Option<Rc<Node>>) -> Option<Option<Rc<Node>>> {
+        match self.t.peek() {
+            Some(Token::Punctuator('.')) => {
+                // consume '.'
+                assert!(self.t.next().is_some());
+                Some(Node::new_member_expression(
+                    object,
+                    self.identifier(),
+                    false,
+                ))
+            }
+            Some(Token::Punctuator('[')) => {
+                // consume '['
+                assert!(self.t.next().is_some());
+                let property = self.assignment_expression();
+                match self.t.next() {
+                    Some(Token::Punctuator(']')) => {}
+                    Some(_) => self.syntax_error("expected-close-bracket"),
+                    None => self.syntax_error("unexpected-eof"),
                 }
-
-                expr
+                Some(Node::new_member_expression(object, property, true))
             }
-            _ => expr,
+            _ => None,
         }
     }
 
@@ -238,27 +358,25 @@ impl JsParser {
     /// Arguments ::= "(" ( ArgumentList )? ")"
     /// CallExpression ::= MemberExpression Arguments ( CallExpressionPart )*
     ///
+    /// CallExpressionPart ::= Arguments | ( "[" Expression "]" ) | ( "." Identifier )
+    ///
     /// LeftHandSideExpression ::= CallExpression | MemberExpression
     fn left_hand_side_expression(&mut self) -> Option<Rc<Node>> {
-        let expr = self.member_expression();
+        let mut expr = self.member_expression();
 
-        let t = match self.t.peek() {
-            Some(token) => token,
-            None => return expr,
-        };
-
-        match t {
-            Token::Punctuator(c) => {
-                if c == &'(' {
-                    // consume '('
-                    assert!(self.t.next().is_some());
-                    return Node::new_call_expression(expr, self.arguments());
-                }
+        loop {
+            if let Some(Token::Punctuator('(')) = self.t.peek() {
+                // consume '('
+                assert!(self.t.next().is_some());
+                expr = Node::new_call_expression(expr, self.arguments());
+                continue;
+            }
 
-                // return MemberExpression
-                expr
+            match self.member_expression_part(expr.clone()) {
+                Some(member) => expr = member,
+                // return CallExpression or MemberExpression
+                None => return expr,
             }
-            _ => expr,
         }
     }
 
@@ -335,24 +453,29 @@ impl JsParser {
     fn identifier(&mut self) -> Option<Rc<Node>> {
         let t = match self.t.next() {
             Some(token) => token,
-            None => return None,
+            None => {
+                self.syntax_error("unexpected-eof");
+                return None;
+            }
         };
 
         match t {
             Token::Identifier(name) => Node::new_identifier(name),
-            _ => None,
+            _ => {
+                self.syntax_error("expected-identifier");
+                None
+            }
         }
     }
 
     /// Initialiser ::= "=" AssignmentExpression
     fn initialiser(&mut self) -> Option<Rc<Node>> {
-        let t = match self.t.next() {
-            Some(token) => token,
-            None => return None,
-        };
-
-        match t {
-            Token::Punctuator('=') => self.assignment_expression(),
+        match self.t.peek() {
+            Some(Token::Punctuator('=')) => {
+                // consume '='
+                assert!(self.t.next().is_some());
+                self.assignment_expression()
+            }
             _ => None,
         }
     }
@@ -381,7 +504,7 @@ impl JsParser {
     /// ExpressionStatement ::= Expression ( ";" )?
     /// ReturnStatement ::= "return" ( Expression )? ( ";" )?
     ///
-    /// Statement ::= ExpressionStatement | VariableStatement | ReturnStatement
+    /// Statement ::= ExpressionStatement | VariableStatement | ReturnStatement | IterationStatement
     fn statement(&mut self) -> Option<Rc<Node>> {
         let t = match self.t.peek() {
             Some(t) => t,
@@ -395,13 +518,18 @@ impl JsParser {
                     assert!(self.t.next().is_some());
 
                     self.variable_declaration()
+                } else if keyword == "for" {
+                    // consume "for"
+                    assert!(self.t.next().is_some());
+
+                    return self.for_of_statement();
                 } else if keyword == "return" {
                     // consume "return"
                     assert!(self.t.next().is_some());
 
                     Node::new_return_statement(self.assignment_expression())
                 } else {
-                    None
+                    Node::new_expression_statement(self.assignment_expression())
                 }
             }
             _ => Node::new_expression_statement(self.assignment_expression()),
@@ -417,29 +545,89 @@ impl JsParser {
         node
     }
 
+    /// https://262.ecma-international.org/12.0/#prod-ForInOfStatement
+    ///
+    /// IterationStatement ::= "for" "(" ( "var" )? Identifier "of" AssignmentExpression ")"
+    ///                        Statement
+    fn for_of_statement(&mut self) -> Option<Rc<Node>> {
+        match self.t.next() {
+            Some(Token::Punctuator('(')) => {}
+            _ => {
+                self.syntax_error("expected-open-paren");
+                return None;
+            }
+        }
+
+        let left = match self.t.peek() {
+            Some(Token::Keyword(keyword)) if keyword == "var" => {
+                // consume "var"
+                assert!(self.t.next().is_some());
+                let declarator = Node::new_variable_declarator(self.identifier(), None);
+                Node::new_variable_declaration(vec![declarator])
+            }
+            _ => self.identifier(),
+        };
+
+        match self.t.next() {
+            Some(Token::Identifier(of)) if of == "of" => {}
+            _ => {
+                self.syntax_error("expected-of");
+                return None;
+            }
+        }
+        let right = self.assignment_expression();
+
+        match self.t.next() {
+            Some(Token::Punctuator(')')) => {}
+            _ => {
+                self.syntax_error("expected-close-paren");
+                return None;
+            }
+        }
+
+        // A block has the same syntax as FunctionBody.
+        let body = match self.t.peek() {
+            Some(Token::Punctuator('{')) => self.function_body(),
+            _ => self.statement(),
+        };
+        Node::new_for_of_statement(left, right, body)
+    }
+
     /// FunctionBody ::= "{" ( SourceElements )? "}"
     fn function_body(&mut self) -> Option<Rc<Node>> {
         // consume '{'
         match self.t.next() {
-            Some(t) => match t {
-                Token::Punctuator(c) => assert!(c == '{'),
-                _ => unimplemented!("function should have open curly blacket but got {:?}", t),
-            },
-            None => unimplemented!("function should have open curly blacket but got None"),
+            Some(Token::Punctuator('{')) => {}
+            Some(_) => {
+                self.syntax_error("expected-open-curly");
+                return None;
+            }
+            None => {
+                self.syntax_error("unexpected-eof");
+                return None;
+            }
         }
 
         let mut body = Vec::new();
         loop {
             // loop until hits '}'
-            if let Some(Token::Punctuator(c)) = self.t.peek() {
-                if c == &'}' {
+            match self.t.peek() {
+                Some(Token::Punctuator('}')) => {
                     // consume '}'
                     assert!(self.t.next().is_some());
                     return Node::new_block_statement(body);
                 }
+                Some(_) => {}
+                None => {
+                    self.syntax_error("unexpected-eof");
+                    return Node::new_block_statement(body);
+                }
             }
 
-            body.push(self.source_element());
+            match self.source_element() {
+                Some(node) => body.push(Some(node)),
+                None => self.skip_unexpected_token(),
+            }
         }
     }
 
@@ -462,11 +650,17 @@ impl JsParser {
                         if c == &',' {
                             // consume ','
                             assert!(self.t.next().is_some());
+                            continue;
                         }
+                        arguments.push(self.assignment_expression());
                     }
                     _ => arguments.push(self.assignment_expression()),
                 },
-                None => return arguments,
+                // The input ends before ')'.
+                None => {
+                    self.syntax_error("unexpected-eof");
+                    return arguments;
+                }
             }
         }
     }
@@ -477,11 +671,15 @@ impl JsParser {
 
         // consume '('
         match self.t.next() {
-            Some(t) => match t {
-                Token::Punctuator(c) => assert!(c == '(', "expect ( but got {:?}", c),
-                _ => unimplemented!("function should have `(` but got {:?}", t),
-            },
-            None => unimplemented!("function should have `(` but got None"),
+            Some(Token::Punctuator('(')) => {}
+            Some(_) => {
+                self.syntax_error("expected-open-paren");
+                return params;
+            }
+            None => {
+                self.syntax_error("unexpected-eof");
+                return params;
+            }
         }
 
         loop {
@@ -497,7 +695,12 @@ impl JsParser {
                         if c == &',' {
                             // consume ','
                             assert!(self.t.next().is_some());
+                            continue;
                         }
+                        // The parameter list ends at an unexpected punctuator such as '{'.
+                        let position = self.t.next_position();
+                        self.syntax_error_at("expected-identifier", position);
+                        return params;
                     }
                     _ => {
                         params.push(self.identifier());
@@ -554,12 +757,30 @@ impl JsParser {
             match node {
                 Some(n) => body.push(n),
                 None => {
+                    if self.t.peek().is_some() {
+                        self.skip_unexpected_token();
+                        continue;
+                    }
+
+                    // Report the errors found by the lexer as well, in source order.
+                    for (code, position) in self.t.take_errors() {
+                        self.syntax_error_at(code, position);
+                    }
+                    self.diagnostics.sort_by_key(|d| d.position());
+
                     program.set_body(body);
                     return program;
                 }
             }
         }
     }
+
+    /// Consumes a token which can't start a source element and reports it.
+    fn skip_unexpected_token(&mut self) {
+        if self.t.next().is_some() {
+            self.syntax_error("unexpected-token");
+        }
+    }
 }
 
 #[cfg(test)]
@@ -693,6 +914,7 @@ mod tests {
                 left: Some(Rc::new(Node::MemberExpression {
                     object: Some(Rc::new(Node::Identifier("foo".to_string()))),
                     property: Some(Rc::new(Node::Identifier("innerHTML".to_string()))),
+                    computed: false,
                 })),
                 right: Some(Rc::new(Node::StringLiteral("<h1>dynamic</h1>".to_string()))),
             },
@@ -798,6 +1020,7 @@ mod tests {
                 callee: Some(Rc::new(Node::MemberExpression {
                     object: Some(Rc::new(Node::Identifier("document".to_string()))),
                     property: Some(Rc::new(Node::Identifier("getElementById".to_string()))),
+                    computed: false,
                 })),
                 arguments: [Some(Rc::new(Node::StringLiteral("target".to_string())))].to_vec(),
             },
@@ -805,4 +1028,127 @@ mod tests {
         expected.set_body(body);
         assert_eq!(expected, parser.parse_ast());
     }
+
+    #[test]
+    fn test_new_expression() {
+        let input = "var params = new URLSearchParams(\"a=1\");".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let mut expected = Program::new();
+        let mut body = Vec::new();
+        body.push(Rc::new(Node::VariableDeclaration {
+            declarations: [Some(Rc::new(Node::VariableDeclarator {
+                id: Some(Rc::new(Node::Identifier("params".to_string()))),
+                init: Some(Rc::new(Node::NewExpression {
+                    callee: Some(Rc::new(Node::Identifier("URLSearchParams".to_string()))),
+                    arguments: [Some(Rc::new(Node::StringLiteral("a=1".to_string())))].to_vec(),
+                })),
+            }))]
+            .to_vec(),
+        }));
+        expected.set_body(body);
+        assert_eq!(expected, parser.parse_ast());
+    }
+
+    #[test]
+    fn test_member_and_call_chain() {
+        let input = "a.b(1)[i].c;".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let mut expected = Program::new();
+        let mut body = Vec::new();
+        let call = Some(Rc::new(Node::CallExpression {
+            callee: Some(Rc::new(Node::MemberExpression {
+                object: Some(Rc::new(Node::Identifier("a".to_string()))),
+                property: Some(Rc::new(Node::Identifier("b".to_string()))),
+                computed: false,
+            })),
+            arguments: [Some(Rc::new(Node::NumericLiteral(1)))].to_vec(),
+        }));
+        body.push(Rc::new(Node::ExpressionStatement(Some(Rc::new(
+            Node::MemberExpression {
+                object: Some(Rc::new(Node::MemberExpression {
+                    object: call,
+                    property: Some(Rc::new(Node::Identifier("i".to_string()))),
+                    computed: true,
+                })),
+                property: Some(Rc::new(Node::Identifier("c".to_string()))),
+                computed: false,
+            },
+        )))));
+        expected.set_body(body);
+        assert_eq!(expected, parser.parse_ast());
+        assert!(parser.diagnostics().is_empty());
+    }
+
+    #[test]
+    fn test_for_of_statement() {
+        let input = "for (var item of items) { n = n + 1; }".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let mut expected = Program::new();
+        let mut body = Vec::new();
+        body.push(Rc::new(Node::ForOfStatement {
+            left: Some(Rc::new(Node::VariableDeclaration {
+                declarations: [Some(Rc::new(Node::VariableDeclarator {
+                    id: Some(Rc::new(Node::Identifier("item".to_string()))),
+                    init: None,
+                }))]
+                .to_vec(),
+            })),
+            right: Some(Rc::new(Node::Identifier("items".to_string()))),
+            body: Some(Rc::new(Node::BlockStatement {
+                body: [Some(Rc::new(Node::ExpressionStatement(Some(Rc::new(
+                    Node::AssignmentExpression {
+                        operator: '=',
+                        left: Some(Rc::new(Node::Identifier("n".to_string()))),
+                        right: Some(Rc::new(Node::BinaryExpression {
+                            operator: '+',
+                            left: Some(Rc::new(Node::Identifier("n".to_string()))),
+                            right: Some(Rc::new(Node::NumericLiteral(1))),
+                        })),
+                    },
+                )))))]
+                .to_vec(),
+            })),
+        }));
+        expected.set_body(body);
+        assert_eq!(expected, parser.parse_ast());
+        assert!(parser.diagnostics().is_empty());
+    }
+
+    #[test]
+    fn test_syntax_error() {
+        let input = "var a = 1;\nfunction f( {\n  a = 2 * 3;\n}".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        parser.set_url("http://example.com/".to_string());
+        parser.parse_ast();
+
+        let errors: Vec<(String, String)> = parser
+            .diagnostics()
+            .iter()
+            .map(|d| (d.code(), d.position().to_string()))
+            .collect();
+        assert_eq!(
+            [
+                ("expected-identifier".to_string(), "2:13".to_string()),
+                ("unexpected-character".to_string(), "3:9".to_string()),
+            ]
+            .to_vec(),
+            errors
+        );
+    }
+
+    #[test]
+    fn test_unexpected_eof() {
+        for input in ["a.b.c(", "if (", "1 +", "f(1,"] {
+            let lexer = JsLexer::new(input.to_string());
+            let mut parser = JsParser::new(lexer);
+            parser.parse_ast();
+
+            let errors: Vec<String> = parser.diagnostics().iter().map(|d| d.code()).collect();
+            assert_eq!(vec!["unexpected-eof".to_string()], errors, "{}", input);
+        }
+    }
 }
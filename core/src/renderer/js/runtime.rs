@@ -1,12 +1,34 @@
+use crate::renderer::dom::api::closest;
+use crate::renderer::dom::api::document_title;
+use crate::renderer::dom::api::element_matches;
 use crate::renderer::dom::api::get_element_by_id;
+use crate::renderer::dom::api::query_selector;
+use crate::renderer::dom::api::query_selector_all;
+use crate::renderer::dom::api::set_document_title;
+use crate::renderer::dom::collection::Collection;
+use crate::renderer::dom::collection::CollectionFilter;
+use crate::renderer::dom::history;
+use crate::renderer::dom::location;
+use crate::renderer::dom::mutation::{
+    append_child, clone_node, insert_before, remove_child, replace_all, replace_child,
+    set_text_content, text_content,
+};
+use crate::renderer::dom::node::Element as DomElement;
 use crate::renderer::dom::node::Node as DomNode;
 use crate::renderer::dom::node::NodeKind as DomNodeKind;
+use crate::renderer::dom::window::Window;
+use crate::renderer::html::html_builder::serialize_children;
+use crate::renderer::html::html_builder::serialize_node;
+use crate::renderer::html::parser::HtmlParser;
 use crate::renderer::js::ast::Node;
 use crate::renderer::js::ast::Program;
+use crate::url::search_params::UrlSearchParams;
+use crate::utils::console_error;
 use alloc::format;
-use alloc::rc::Rc;
+use alloc::rc::{Rc, Weak};
 use alloc::string::String;
 use alloc::string::ToString;
+use alloc::vec;
 use alloc::vec::Vec;
 use core::borrow::Borrow;
 use core::cell::RefCell;
@@ -18,29 +40,66 @@ use core::ops::Sub;
 #[derive(Debug, Clone)]
 /// https://262.ecma-international.org/13.0/#sec-ecmascript-language-types
 pub enum RuntimeValue {
+    /// https://tc39.es/ecma262/#sec-ecmascript-language-types-null-type
+    Null,
     /// https://tc39.es/ecma262/#sec-numeric-types
     Number(u64),
+    /// https://tc39.es/ecma262/#sec-ecmascript-language-types-boolean-type
+    Boolean(bool),
     /// https://tc39.es/ecma262/#sec-ecmascript-language-types-string-type
     StringLiteral(String),
+    /// An array created by the runtime, such as the result of an iterator. Scripts can't create
+    /// arrays yet.
+    /// https://tc39.es/ecma262/#sec-array-objects
+    Array(Rc<Vec<RuntimeValue>>),
     /// https://dom.spec.whatwg.org/#interface-htmlcollection
     /// https://dom.spec.whatwg.org/#element
     HtmlElement {
         object: Rc<RefCell<DomNode>>,
         property: Option<String>,
     },
+    /// https://url.spec.whatwg.org/#interface-urlsearchparams
+    UrlSearchParams {
+        object: Rc<RefCell<UrlSearchParams>>,
+        property: Option<String>,
+    },
+    /// https://dom.spec.whatwg.org/#interface-htmlcollection
+    /// https://dom.spec.whatwg.org/#interface-nodelist
+    Collection {
+        object: Rc<Collection>,
+        property: Option<String>,
+    },
 }
 
 impl Display for RuntimeValue {
     fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
         let s = match self {
+            RuntimeValue::Null => "null".to_string(),
             RuntimeValue::Number(value) => format!("{}", value),
+            RuntimeValue::Boolean(value) => format!("{}", value),
             RuntimeValue::StringLiteral(value) => value.to_string(),
+            // https://tc39.es/ecma262/#sec-array.prototype.join
+            RuntimeValue::Array(values) => values
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<String>>()
+                .join(","),
             RuntimeValue::HtmlElement {
                 object,
                 property: _,
             } => {
                 format!("HtmlElement: {:#?}", object)
             }
+            RuntimeValue::UrlSearchParams {
+                object,
+                property: _,
+            } => RefCell::borrow(object).to_string(),
+            RuntimeValue::Collection {
+                object,
+                property: _,
+            } => {
+                format!("Collection: {:#?}", object.nodes())
+            }
         };
         write!(f, "{}", s)
     }
@@ -49,18 +108,32 @@ impl Display for RuntimeValue {
 impl PartialEq for RuntimeValue {
     fn eq(&self, other: &Self) -> bool {
         match self {
+            RuntimeValue::Null => matches!(other, RuntimeValue::Null),
             RuntimeValue::Number(v1) => match other {
                 RuntimeValue::Number(v2) => v1 == v2,
                 _ => false,
             },
+            RuntimeValue::Boolean(v1) => match other {
+                RuntimeValue::Boolean(v2) => v1 == v2,
+                _ => false,
+            },
             RuntimeValue::StringLiteral(v1) => match other {
                 RuntimeValue::StringLiteral(v2) => v1 == v2,
                 _ => false,
             },
+            RuntimeValue::Array(_) => false,
             RuntimeValue::HtmlElement {
                 object: _,
                 property: _,
             } => false,
+            RuntimeValue::UrlSearchParams {
+                object: _,
+                property: _,
+            } => false,
+            RuntimeValue::Collection {
+                object: _,
+                property: _,
+            } => false,
         }
     }
 }
@@ -92,6 +165,41 @@ impl Sub<RuntimeValue> for RuntimeValue {
     }
 }
 
+/// https://webidl.spec.whatwg.org/#default-iterator-object-kind
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IterationKind {
+    Key,
+    Value,
+    KeyAndValue,
+}
+
+/// Returns the results of iterating over the value pairs of `params` as an array. An iterator
+/// object is not supported, so the value pairs are fixed when the iteration starts.
+/// https://webidl.spec.whatwg.org/#es-iterator-prototype-object
+fn iterate(params: &UrlSearchParams, kind: IterationKind) -> RuntimeValue {
+    // https://url.spec.whatwg.org/#interface-urlsearchparams
+    // "The value pairs to iterate over are this's list's tuples with the key being the name and
+    // the value being the value."
+    // https://webidl.spec.whatwg.org/#iterator-result
+    // "2. If kind is "key", let result be key.
+    // 3. Else if kind is "value", let result be value.
+    // 4. Else, let result be ! ArrayCreate(2). ... CreateDataProperty(result, "0", key) ...
+    // CreateDataProperty(result, "1", value)."
+    let results = params
+        .iter()
+        .map(|(name, value)| {
+            let key = RuntimeValue::StringLiteral(name.clone());
+            let value = RuntimeValue::StringLiteral(value.clone());
+            match kind {
+                IterationKind::Key => key,
+                IterationKind::Value => value,
+                IterationKind::KeyAndValue => RuntimeValue::Array(Rc::new(vec![key, value])),
+            }
+        })
+        .collect();
+    RuntimeValue::Array(Rc::new(results))
+}
+
 type VariableMap = Vec<(String, Option<RuntimeValue>)>;
 
 /// https://262.ecma-international.org/12.0/#sec-environment-records
@@ -123,7 +231,15 @@ impl Environment {
         }
     }
 
+    /// Adds a variable to this environment. A variable declared again, e.g. by `var` in a loop,
+    /// is overwritten.
     fn add_variable(&mut self, name: String, value: Option<RuntimeValue>) {
+        for variable in &mut self.variables {
+            if variable.0 == name {
+                variable.1 = value;
+                return;
+            }
+        }
         self.variables.push((name, value));
     }
 
@@ -155,7 +271,7 @@ impl Function {
 #[derive(Debug, Clone)]
 pub struct JsRuntime {
     dom_root: Rc<RefCell<DomNode>>,
-    dom_modified: bool,
+    url: String,
     functions: Vec<Function>,
     env: Rc<RefCell<Environment>>,
 }
@@ -173,7 +289,7 @@ impl JsRuntime {
 
         Self {
             dom_root,
-            dom_modified: false,
+            url: String::new(),
             functions: Vec::new(),
             env: Rc::new(RefCell::new(env)),
         }
@@ -183,8 +299,117 @@ impl JsRuntime {
         self.dom_root.clone()
     }
 
-    pub fn dom_modified(&self) -> bool {
-        self.dom_modified
+    /// Sets the URL of the document which this runtime executes scripts for.
+    pub fn set_url(&mut self, url: String) {
+        self.url = url;
+    }
+
+    /// Returns the window of the document. A document which is not created by the HTML parser
+    /// doesn't have a window.
+    fn window(&self) -> Option<Rc<RefCell<Window>>> {
+        RefCell::borrow(&self.dom_root).window().upgrade()
+    }
+
+    /// Returns the URL of the document, which the History API may have changed.
+    fn document_url(&self) -> String {
+        match self.window() {
+            Some(window) => RefCell::borrow(&window).url(),
+            None => self.url.clone(),
+        }
+    }
+
+    /// Logs an error which a script throws to the console of the browser.
+    fn report_error(&self, log: String) {
+        if let Some(window) = self.window() {
+            console_error(&RefCell::borrow(&window).browser(), log);
+        }
+    }
+
+    /// Evaluates the `index`-th argument as a string. It's None if the argument is null, undefined
+    /// or missing.
+    fn string_argument(
+        &mut self,
+        arguments: &[Option<Rc<Node>>],
+        index: usize,
+        env: Rc<RefCell<Environment>>,
+    ) -> Option<String> {
+        match self.eval(arguments.get(index)?, env)? {
+            RuntimeValue::Null => None,
+            value => Some(value.to_string()),
+        }
+    }
+
+    /// Calls a method of the Location object or the History object. Returns None if `func` is
+    /// not one of them, and Some(None) since the methods return undefined. An exception is
+    /// ignored because there is no way to catch it yet.
+    /// https://html.spec.whatwg.org/multipage/nav-history-apis.html#the-location-interface
+    /// https://html.spec.whatwg.org/multipage/nav-history-apis.html#the-history-interface
+    fn call_location_and_history_api(
+        &mut self,
+        func: &RuntimeValue,
+        arguments: &[Option<Rc<Node>>],
+        env: Rc<RefCell<Environment>>,
+    ) -> Option<Option<RuntimeValue>> {
+        let name = match func {
+            RuntimeValue::StringLiteral(name)
+                if name.starts_with("location.") || name.starts_with("history.") =>
+            {
+                name.clone()
+            }
+            _ => return None,
+        };
+        // The methods do nothing for a document without a window.
+        let window = match self.window() {
+            Some(window) => window,
+            None => return Some(None),
+        };
+
+        let _ = match name.as_str() {
+            "location.assign" => {
+                let url = self.string_argument(arguments, 0, env).unwrap_or_default();
+                location::assign(&window, &url)
+            }
+            "location.replace" => {
+                let url = self.string_argument(arguments, 0, env).unwrap_or_default();
+                location::replace(&window, &url)
+            }
+            "location.reload" => {
+                location::reload(&window);
+                Ok(())
+            }
+            "history.back" => {
+                history::back(&window);
+                Ok(())
+            }
+            "history.forward" => {
+                history::forward(&window);
+                Ok(())
+            }
+            "history.go" => {
+                // https://webidl.spec.whatwg.org/#es-long
+                // A negative number can't be written yet, but a string such as "-1" is converted
+                // to a number.
+                let delta = match arguments.first().and_then(|arg| self.eval(arg, env)) {
+                    Some(RuntimeValue::Number(n)) => n as i64,
+                    Some(RuntimeValue::StringLiteral(s)) => s.trim().parse().unwrap_or(0),
+                    _ => 0,
+                };
+                history::go(&window, delta);
+                Ok(())
+            }
+            "history.pushState" | "history.replaceState" => {
+                // The second argument is unused for historical reasons.
+                let data = self.string_argument(arguments, 0, env.clone());
+                let url = self.string_argument(arguments, 2, env);
+                if name == "history.pushState" {
+                    history::push_state(&window, data, url)
+                } else {
+                    history::replace_state(&window, data, url)
+                }
+            }
+            _ => return None,
+        };
+        Some(None)
     }
 
     /// https://developer.mozilla.org/en-US/docs/Web/API
@@ -208,11 +433,23 @@ impl JsRuntime {
             }
         }
 
-        if let RuntimeValue::HtmlElement {
-            object: _,
-            property,
-        } = func
-        {
+        if let Some(result) = self.call_location_and_history_api(func, arguments, env.clone()) {
+            return (true, result);
+        }
+
+        if let RuntimeValue::HtmlElement { object, property } = func {
+            if let Some(result) = self.call_node_api(object, property, arguments, env.clone()) {
+                return (true, result);
+            }
+
+            if let Some(result) = self.call_selector_api(object, property, arguments, env.clone()) {
+                return (true, result);
+            }
+            if let Some(result) = self.call_collection_api(object, property, arguments, env.clone())
+            {
+                return (true, result);
+            }
+
             if property == &Some("getElementById".to_string()) {
                 let arg = match self.eval(&arguments[0], env.clone()) {
                     Some(a) => a,
@@ -233,9 +470,349 @@ impl JsRuntime {
             }
         }
 
+        // https://dom.spec.whatwg.org/#dom-htmlcollection-item
+        if let RuntimeValue::Collection { object, property } = func {
+            if property.as_deref() != Some("item") {
+                return (false, None);
+            }
+            let index = match arguments.first() {
+                Some(arg) => match self.eval(arg, env.clone()) {
+                    Some(RuntimeValue::Number(n)) => n as usize,
+                    _ => 0,
+                },
+                None => 0,
+            };
+            return (
+                true,
+                object.item(index).map(|node| RuntimeValue::HtmlElement {
+                    object: node,
+                    property: None,
+                }),
+            );
+        }
+
+        if let RuntimeValue::UrlSearchParams { object, property } = func {
+            let mut args = Vec::new();
+            for argument in arguments {
+                match self.eval(argument, env.clone()) {
+                    Some(a) => args.push(a.to_string()),
+                    None => args.push("undefined".to_string()),
+                }
+            }
+            let arg = |i: usize| args.get(i).cloned().unwrap_or_default();
+
+            let result = match property.as_deref() {
+                Some("get") => RefCell::borrow(object)
+                    .get(&arg(0))
+                    .map(RuntimeValue::StringLiteral),
+                Some("has") => Some(RuntimeValue::Boolean(RefCell::borrow(object).has(&arg(0)))),
+                Some("set") => {
+                    object.borrow_mut().set(arg(0), arg(1));
+                    None
+                }
+                Some("append") => {
+                    object.borrow_mut().append(arg(0), arg(1));
+                    None
+                }
+                Some("delete") => {
+                    object.borrow_mut().delete(&arg(0));
+                    None
+                }
+                Some("toString") => Some(RuntimeValue::StringLiteral(
+                    RefCell::borrow(object).to_string(),
+                )),
+                // https://webidl.spec.whatwg.org/#js-iterable-entries
+                Some("entries") => Some(iterate(
+                    &RefCell::borrow(object),
+                    IterationKind::KeyAndValue,
+                )),
+                // https://webidl.spec.whatwg.org/#js-iterable-keys
+                Some("keys") => Some(iterate(&RefCell::borrow(object), IterationKind::Key)),
+                // https://webidl.spec.whatwg.org/#js-iterable-values
+                Some("values") => Some(iterate(&RefCell::borrow(object), IterationKind::Value)),
+                _ => return (false, None),
+            };
+            return (true, result);
+        }
+
         (false, None)
     }
 
+    /// Evaluates the `index`-th argument and returns the DOM node if it's a node.
+    fn node_argument(
+        &mut self,
+        arguments: &[Option<Rc<Node>>],
+        index: usize,
+        env: Rc<RefCell<Environment>>,
+    ) -> Option<Rc<RefCell<DomNode>>> {
+        match self.eval(arguments.get(index)?, env) {
+            Some(RuntimeValue::HtmlElement {
+                object,
+                property: None,
+            }) => Some(object),
+            _ => None,
+        }
+    }
+
+    /// Calls a method of the Node interface and the node creation methods of the Document
+    /// interface. Returns None if `property` is not one of them, and Some(None) if the method
+    /// returns null or throws an exception, which scripts can't catch yet.
+    /// https://dom.spec.whatwg.org/#interface-node
+    fn call_node_api(
+        &mut self,
+        object: &Rc<RefCell<DomNode>>,
+        property: &Option<String>,
+        arguments: &[Option<Rc<Node>>],
+        env: Rc<RefCell<Environment>>,
+    ) -> Option<Option<RuntimeValue>> {
+        let result = match property.as_deref()? {
+            // https://dom.spec.whatwg.org/#dom-document-createelement
+            "createElement" if RefCell::borrow(object).kind() == DomNodeKind::Document => {
+                // "2. If this is an HTML document, then set localName to localName in ASCII
+                // lowercase."
+                let local_name = match self.eval(arguments.first()?, env) {
+                    Some(name) => name.to_string().to_ascii_lowercase(),
+                    None => return Some(None),
+                };
+                Ok(Rc::new(RefCell::new(DomNode::new(DomNodeKind::Element(
+                    DomElement::new(&local_name, Vec::new()),
+                )))))
+            }
+            // https://dom.spec.whatwg.org/#dom-document-createtextnode
+            "createTextNode" if RefCell::borrow(object).kind() == DomNodeKind::Document => {
+                let data = match self.eval(arguments.first()?, env) {
+                    Some(data) => data.to_string(),
+                    None => return Some(None),
+                };
+                Ok(Rc::new(RefCell::new(DomNode::new(DomNodeKind::Text(data)))))
+            }
+            // https://dom.spec.whatwg.org/#dom-node-appendchild
+            "appendChild" => match self.node_argument(arguments, 0, env) {
+                Some(node) => append_child(object, &node),
+                None => return Some(None),
+            },
+            // https://dom.spec.whatwg.org/#dom-node-insertbefore
+            "insertBefore" => {
+                let node = match self.node_argument(arguments, 0, env.clone()) {
+                    Some(node) => node,
+                    None => return Some(None),
+                };
+                // The child is null unless it's a node.
+                let child = self.node_argument(arguments, 1, env);
+                insert_before(object, &node, child.as_ref())
+            }
+            // https://dom.spec.whatwg.org/#dom-node-removechild
+            "removeChild" => match self.node_argument(arguments, 0, env) {
+                Some(child) => remove_child(object, &child),
+                None => return Some(None),
+            },
+            // https://dom.spec.whatwg.org/#dom-node-replacechild
+            "replaceChild" => {
+                match (
+                    self.node_argument(arguments, 0, env.clone()),
+                    self.node_argument(arguments, 1, env),
+                ) {
+                    (Some(node), Some(child)) => replace_child(object, &node, &child),
+                    _ => return Some(None),
+                }
+            }
+            // https://dom.spec.whatwg.org/#dom-node-clonenode
+            "cloneNode" => {
+                // There is no boolean value yet, so `true` is evaluated as an identifier.
+                let deep = match arguments.first() {
+                    Some(arg) => match self.eval(arg, env) {
+                        Some(RuntimeValue::StringLiteral(s)) => s == "true",
+                        Some(RuntimeValue::Number(n)) => n != 0,
+                        _ => false,
+                    },
+                    None => false,
+                };
+                Ok(clone_node(object, deep))
+            }
+            _ => return None,
+        };
+
+        Some(result.ok().map(|node| RuntimeValue::HtmlElement {
+            object: node,
+            property: None,
+        }))
+    }
+
+    /// Calls a method which takes a selector. Returns None if `property` is not one of them, and
+    /// Some(None) if the method returns null or throws an exception.
+    /// https://dom.spec.whatwg.org/#interface-parentnode
+    fn call_selector_api(
+        &mut self,
+        object: &Rc<RefCell<DomNode>>,
+        property: &Option<String>,
+        arguments: &[Option<Rc<Node>>],
+        env: Rc<RefCell<Environment>>,
+    ) -> Option<Option<RuntimeValue>> {
+        let property = property.as_deref()?;
+        if !matches!(
+            property,
+            "querySelector" | "querySelectorAll" | "matches" | "closest"
+        ) {
+            return None;
+        }
+        let selectors = match self.eval(arguments.first()?, env) {
+            Some(selectors) => selectors.to_string(),
+            None => return Some(None),
+        };
+        let element = |node: Rc<RefCell<DomNode>>| RuntimeValue::HtmlElement {
+            object: node,
+            property: None,
+        };
+
+        let result = match property {
+            // https://dom.spec.whatwg.org/#dom-parentnode-queryselector
+            "querySelector" => query_selector(object, &selectors).map(|node| node.map(element)),
+            // https://dom.spec.whatwg.org/#dom-parentnode-queryselectorall
+            "querySelectorAll" => query_selector_all(object, &selectors).map(|nodes| {
+                Some(RuntimeValue::Collection {
+                    object: Rc::new(Collection::new(
+                        object.clone(),
+                        CollectionFilter::Static(nodes),
+                    )),
+                    property: None,
+                })
+            }),
+            // https://dom.spec.whatwg.org/#dom-element-matches
+            "matches" => element_matches(object, &selectors)
+                .map(|matched| Some(RuntimeValue::Boolean(matched))),
+            // https://dom.spec.whatwg.org/#dom-element-closest
+            _ => closest(object, &selectors).map(|node| node.map(element)),
+        };
+        match result {
+            Ok(value) => Some(value),
+            // There is no way to catch an exception yet, so it's reported as an uncaught one.
+            Err(e) => {
+                self.report_error(format!(
+                    "Uncaught {}: {}(): '{}' is not a valid selector",
+                    e, property, selectors
+                ));
+                Some(None)
+            }
+        }
+    }
+
+    /// Calls a method which returns a live HTMLCollection. Returns None if `property` is not one
+    /// of them.
+    fn call_collection_api(
+        &mut self,
+        object: &Rc<RefCell<DomNode>>,
+        property: &Option<String>,
+        arguments: &[Option<Rc<Node>>],
+        env: Rc<RefCell<Environment>>,
+    ) -> Option<Option<RuntimeValue>> {
+        let property = property.as_deref()?;
+        if !matches!(property, "getElementsByTagName" | "getElementsByClassName") {
+            return None;
+        }
+        let arg = match self.eval(arguments.first()?, env) {
+            Some(arg) => arg.to_string(),
+            None => return Some(None),
+        };
+
+        let filter = match property {
+            // https://dom.spec.whatwg.org/#dom-document-getelementsbytagname
+            "getElementsByTagName" => CollectionFilter::TagName(arg),
+            // https://dom.spec.whatwg.org/#dom-document-getelementsbyclassname
+            _ => CollectionFilter::ClassNames(arg),
+        };
+        Some(Some(RuntimeValue::Collection {
+            object: Rc::new(Collection::new(object.clone(), filter)),
+            property: None,
+        }))
+    }
+
+    /// Returns the value of a tree traversal property of `object`. Returns None if `property` is
+    /// not one of them, and Some(None) if the property is null.
+    /// https://dom.spec.whatwg.org/#interface-node
+    fn traversal_property(
+        &self,
+        object: &Rc<RefCell<DomNode>>,
+        property: &str,
+    ) -> Option<Option<RuntimeValue>> {
+        let collection = |filter| {
+            Some(Some(RuntimeValue::Collection {
+                object: Rc::new(Collection::new(object.clone(), filter)),
+                property: None,
+            }))
+        };
+        let node = match property {
+            // https://dom.spec.whatwg.org/#dom-parentnode-children
+            "children" => return collection(CollectionFilter::Children),
+            // https://dom.spec.whatwg.org/#dom-node-childnodes
+            "childNodes" => return collection(CollectionFilter::ChildNodes),
+            // https://dom.spec.whatwg.org/#dom-node-parentnode
+            "parentNode" => RefCell::borrow(object).parent().upgrade(),
+            // https://dom.spec.whatwg.org/#dom-node-nextsibling
+            "nextSibling" => RefCell::borrow(object).next_sibling(),
+            _ => return None,
+        };
+        Some(node.map(|node| RuntimeValue::HtmlElement {
+            object: node,
+            property: None,
+        }))
+    }
+
+    /// https://developer.mozilla.org/en-US/docs/Web/API/Location
+    fn location(&self, property: &str) -> Option<RuntimeValue> {
+        let url = self.document_url();
+        let value = match property {
+            "href" => location::href(&url),
+            "pathname" => location::pathname(&url),
+            "search" => location::search(&url),
+            "hash" => location::hash(&url),
+            _ => return None,
+        };
+        Some(RuntimeValue::StringLiteral(value))
+    }
+
+    /// Sets a property of the Location object, which navigates the window.
+    fn set_location(&self, property: &str, value: &str) {
+        let window = match self.window() {
+            Some(window) => window,
+            None => return,
+        };
+        // An exception is ignored because there is no way to catch it yet.
+        let _ = match property {
+            "href" => location::set_href(&window, value),
+            "pathname" => location::set_pathname(&window, value),
+            "search" => location::set_search(&window, value),
+            "hash" => location::set_hash(&window, value),
+            _ => Ok(()),
+        };
+    }
+
+    /// https://developer.mozilla.org/en-US/docs/Web/API/History
+    fn history(&self, property: &str) -> Option<Option<RuntimeValue>> {
+        let window = self.window()?;
+        match property {
+            "length" => Some(Some(RuntimeValue::Number(history::length(&window) as u64))),
+            "state" => Some(history::state(&window).map(RuntimeValue::StringLiteral)),
+            _ => None,
+        }
+    }
+
+    /// Returns the property name of a member expression. A computed property is evaluated, and an
+    /// identifier is the name itself.
+    /// https://tc39.es/ecma262/#sec-property-accessors-runtime-semantics-evaluation
+    fn member_property(
+        &mut self,
+        property: &Option<Rc<Node>>,
+        computed: bool,
+        env: Rc<RefCell<Environment>>,
+    ) -> Option<RuntimeValue> {
+        match property.as_deref() {
+            Some(Node::Identifier(name)) if !computed => {
+                Some(RuntimeValue::StringLiteral(name.to_string()))
+            }
+            _ => self.eval(property, env),
+        }
+    }
+
     fn eval(
         &mut self,
         node: &Option<Rc<Node>>,
@@ -267,6 +844,57 @@ impl JsRuntime {
                 };
                 None
             }
+            // https://tc39.es/ecma262/#sec-runtime-semantics-forin-div-ofbodyevaluation-lhs-stmt-iterator-lhskind-labelset
+            Node::ForOfStatement { left, right, body } => {
+                let name = match left.as_deref() {
+                    Some(Node::Identifier(name)) => name.to_string(),
+                    Some(Node::VariableDeclaration { declarations }) => {
+                        match declarations.first().and_then(|d| d.as_deref()) {
+                            Some(Node::VariableDeclarator { id, init: _ }) => match id.as_deref() {
+                                Some(Node::Identifier(name)) => name.to_string(),
+                                _ => return None,
+                            },
+                            _ => return None,
+                        }
+                    }
+                    _ => return None,
+                };
+
+                // The values are fixed before the loop even if the object is live, so that the body
+                // can modify the tree or the list.
+                let values = match self.eval(right, env.clone()) {
+                    Some(RuntimeValue::Collection {
+                        object,
+                        property: None,
+                    }) => object
+                        .nodes()
+                        .into_iter()
+                        .map(|node| RuntimeValue::HtmlElement {
+                            object: node,
+                            property: None,
+                        })
+                        .collect(),
+                    // https://webidl.spec.whatwg.org/#js-iterable
+                    // "If the interface has any of the following: an iterable declaration ... then
+                    // a %Symbol.iterator% property must exist ... whose value is the same function
+                    // object as the value of the entries property."
+                    Some(RuntimeValue::UrlSearchParams {
+                        object,
+                        property: None,
+                    }) => match iterate(&RefCell::borrow(&object), IterationKind::KeyAndValue) {
+                        RuntimeValue::Array(values) => values.to_vec(),
+                        _ => return None,
+                    },
+                    Some(RuntimeValue::Array(values)) => values.to_vec(),
+                    _ => return None,
+                };
+
+                for value in values {
+                    env.borrow_mut().add_variable(name.clone(), Some(value));
+                    self.eval(body, env.clone());
+                }
+                None
+            }
             Node::VariableDeclaration { declarations } => {
                 for declaration in declarations {
                     self.eval(declaration, env.clone());
@@ -323,10 +951,44 @@ impl JsRuntime {
                     }
                 }
 
-                // If the left value is HtmlElement, update DOM.
-                let left_value = match self.eval(&left, env.clone()) {
-                    Some(value) => value,
-                    None => return None,
+                // If the left value is HtmlElement, update DOM. A member expression is evaluated
+                // as a reference here so that getters such as `innerHTML` are not called.
+                let left_value = match left.as_deref() {
+                    Some(Node::MemberExpression {
+                        object,
+                        property,
+                        computed,
+                    }) => {
+                        match (
+                            self.eval(object, env.clone()),
+                            self.member_property(property, *computed, env.clone()),
+                        ) {
+                            (
+                                Some(RuntimeValue::HtmlElement {
+                                    object,
+                                    property: None,
+                                }),
+                                Some(property),
+                            ) => RuntimeValue::HtmlElement {
+                                object,
+                                property: Some(property.to_string()),
+                            },
+                            // this is the implementation of `location.href = "foobar";`
+                            (Some(RuntimeValue::StringLiteral(name)), Some(property))
+                                if name == "location" =>
+                            {
+                                if let Some(value) = self.eval(right, env.clone()) {
+                                    self.set_location(&property.to_string(), &value.to_string());
+                                }
+                                return None;
+                            }
+                            _ => return None,
+                        }
+                    }
+                    _ => match self.eval(&left, env.clone()) {
+                        Some(value) => value,
+                        None => return None,
+                    },
                 };
                 let right_value = match self.eval(&right, env.clone()) {
                     Some(value) => value,
@@ -339,24 +1001,29 @@ impl JsRuntime {
                             // this is the implementation of
                             // `document.getElementById("target").textContent = "foobar";`
                             if p == "textContent" {
-                                // Not necessary to set dom_modified=true because only text
-                                // content is changed.
-                                object
-                                    .borrow_mut()
-                                    .set_first_child(Some(Rc::new(RefCell::new(DomNode::new(
-                                        DomNodeKind::Text(right_value.to_string()),
-                                    )))));
+                                set_text_content(&object, right_value.to_string());
                             }
                             // this is the implementation of
-                            // `document.getElementById("target").innerHTML = "foobar";`
-                            // Currently, an assignment value should be a text like "foobar".
+                            // `document.getElementById("target").innerHTML = "<b>foobar</b>";`
+                            // https://html.spec.whatwg.org/multipage/dynamic-markup-insertion.html#dom-element-innerhtml
                             if p == "innerHTML" {
-                                self.dom_modified = true;
-                                object
-                                    .borrow_mut()
-                                    .set_first_child(Some(Rc::new(RefCell::new(DomNode::new(
-                                        DomNodeKind::Text(right_value.to_string()),
-                                    )))));
+                                // "1. Let context be this.
+                                // 3. Let fragment be the result of invoking the fragment parsing
+                                // algorithm steps with context and compliantString.
+                                // 5. Replace all with fragment within context."
+                                let fragment = HtmlParser::parse_fragment(
+                                    Weak::new(),
+                                    &object,
+                                    right_value.to_string(),
+                                );
+                                replace_all(&object, fragment);
+                            }
+                            // this is the implementation of `document.title = "foobar";`
+                            // https://html.spec.whatwg.org/multipage/dom.html#document.title
+                            if p == "title"
+                                && RefCell::borrow(&object).kind() == DomNodeKind::Document
+                            {
+                                set_document_title(&object, right_value.to_string());
                             }
                         }
                     }
@@ -364,12 +1031,16 @@ impl JsRuntime {
                 }
                 None
             }
-            Node::MemberExpression { object, property } => {
+            Node::MemberExpression {
+                object,
+                property,
+                computed,
+            } => {
                 let object_value = match self.eval(&object, env.clone()) {
                     Some(value) => value,
                     None => return None,
                 };
-                let property_value = match self.eval(&property, env.clone()) {
+                let property_value = match self.member_property(property, *computed, env.clone()) {
                     Some(value) => value,
                     // return RuntimeValue in `object` because of no `property`
                     None => return Some(object_value),
@@ -378,7 +1049,38 @@ impl JsRuntime {
                 match object_value {
                     // return html element for DOM manipulation
                     RuntimeValue::HtmlElement { object, property } => {
-                        assert!(property.is_none());
+                        // A property of an unknown property is undefined.
+                        if property.is_some() {
+                            return None;
+                        }
+
+                        if let Some(value) =
+                            self.traversal_property(&object, &property_value.to_string())
+                        {
+                            return value;
+                        }
+
+                        match property_value.to_string().as_str() {
+                            // https://html.spec.whatwg.org/multipage/dynamic-markup-insertion.html#dom-element-innerhtml
+                            "innerHTML" => {
+                                return Some(RuntimeValue::StringLiteral(serialize_children(
+                                    &object,
+                                )))
+                            }
+                            // https://html.spec.whatwg.org/multipage/dynamic-markup-insertion.html#dom-element-outerhtml
+                            "outerHTML" => {
+                                return Some(RuntimeValue::StringLiteral(serialize_node(&object)))
+                            }
+                            // https://dom.spec.whatwg.org/#dom-node-textcontent
+                            "textContent" => {
+                                return text_content(&object).map(RuntimeValue::StringLiteral)
+                            }
+                            // https://html.spec.whatwg.org/multipage/dom.html#document.title
+                            "title" if RefCell::borrow(&object).kind() == DomNodeKind::Document => {
+                                return Some(RuntimeValue::StringLiteral(document_title(&object)))
+                            }
+                            _ => {}
+                        }
 
                         // set `property` to the HtmlElement value.
                         Some(RuntimeValue::HtmlElement {
@@ -386,26 +1088,74 @@ impl JsRuntime {
                             property: Some(property_value.to_string()),
                         })
                     }
+                    RuntimeValue::Collection { object, property } => {
+                        if property.is_some() {
+                            return None;
+                        }
+
+                        // https://dom.spec.whatwg.org/#dom-htmlcollection-length
+                        if property_value == RuntimeValue::StringLiteral("length".to_string()) {
+                            return Some(RuntimeValue::Number(object.length() as u64));
+                        }
+
+                        // An indexed property such as `collection[0]`.
+                        if let RuntimeValue::Number(index) = property_value {
+                            return object.item(index as usize).map(|node| {
+                                RuntimeValue::HtmlElement {
+                                    object: node,
+                                    property: None,
+                                }
+                            });
+                        }
+
+                        Some(RuntimeValue::Collection {
+                            object,
+                            property: Some(property_value.to_string()),
+                        })
+                    }
+                    RuntimeValue::Array(values) => {
+                        // https://tc39.es/ecma262/#sec-properties-of-array-instances-length
+                        if property_value == RuntimeValue::StringLiteral("length".to_string()) {
+                            return Some(RuntimeValue::Number(values.len() as u64));
+                        }
+
+                        // An indexed property such as `array[0]`.
+                        match property_value {
+                            RuntimeValue::Number(index) => values.get(index as usize).cloned(),
+                            _ => None,
+                        }
+                    }
+                    RuntimeValue::UrlSearchParams { object, property } => {
+                        assert!(property.is_none());
+
+                        if property_value == RuntimeValue::StringLiteral("size".to_string()) {
+                            return Some(RuntimeValue::Number(
+                                RefCell::borrow(&object).size() as u64
+                            ));
+                        }
+
+                        Some(RuntimeValue::UrlSearchParams {
+                            object,
+                            property: Some(property_value.to_string()),
+                        })
+                    }
                     _ => {
-                        /*
-                        TODO: support window.location.href.
-                        // dom_root.window().location()
+                        let property_name = property_value.to_string();
+                        if object_value == RuntimeValue::StringLiteral("window".to_string())
+                            && (property_name == "location" || property_name == "history")
+                        {
+                            return Some(property_value);
+                        }
                         if object_value == RuntimeValue::StringLiteral("location".to_string()) {
-                            if property_value == RuntimeValue::StringLiteral("href".to_string()) {
-                                //println!("[location.href] {:?}", self.url);
-                                return Some(RuntimeValue::StringLiteral(self.url.clone()));
+                            if let Some(value) = self.location(&property_name) {
+                                return Some(value);
                             }
-
-                            if property_value == RuntimeValue::StringLiteral("hash".to_string()) {
-                                let hash = match self.url.find('#') {
-                                    Some(i) => self.url[i..].to_string(),
-                                    None => "".to_string(),
-                                };
-                                //println!("[location.hash] {:?}", hash);
-                                return Some(RuntimeValue::StringLiteral(hash.clone()));
+                        }
+                        if object_value == RuntimeValue::StringLiteral("history".to_string()) {
+                            if let Some(value) = self.history(&property_name) {
+                                return value;
                             }
                         }
-                        */
 
                         // return a concatenated string such as "console.log"
                         Some(
@@ -443,7 +1193,16 @@ impl JsRuntime {
 
                     match f {
                         Some(f) => f,
-                        None => unimplemented!("function {:?} doesn't exist", callee),
+                        None => {
+                            // https://tc39.es/ecma262/#sec-getvalue
+                            // "If IsUnresolvableReference(V) is true, throw a ReferenceError
+                            // exception."
+                            self.report_error(format!(
+                                "Uncaught ReferenceError: {} is not defined",
+                                callee_value
+                            ));
+                            return None;
+                        }
                     }
                 };
 
@@ -462,11 +1221,36 @@ impl JsRuntime {
                 // call function with arguments
                 self.eval(&function.body.clone(), new_env.clone())
             }
+            Node::NewExpression { callee, arguments } => {
+                let callee_value = match self.eval(callee, env.clone()) {
+                    Some(value) => value,
+                    None => return None,
+                };
+
+                // https://url.spec.whatwg.org/#dom-urlsearchparams-urlsearchparams
+                if callee_value == RuntimeValue::StringLiteral("URLSearchParams".to_string()) {
+                    let init = match arguments.first() {
+                        Some(arg) => match self.eval(arg, env.clone()) {
+                            Some(value) => value.to_string(),
+                            None => String::new(),
+                        },
+                        None => String::new(),
+                    };
+                    return Some(RuntimeValue::UrlSearchParams {
+                        object: Rc::new(RefCell::new(UrlSearchParams::new(&init))),
+                        property: None,
+                    });
+                }
+
+                None
+            }
             Node::Identifier(name) => {
                 match env.borrow_mut().get_variable(name.to_string()) {
                     Some(v) => {
                         return Some(v);
                     }
+                    // https://tc39.es/ecma262/#sec-null-literals
+                    None if name == "null" => Some(RuntimeValue::Null),
                     // first time to evaluate this identifier
                     None => Some(RuntimeValue::StringLiteral(name.to_string())),
                 }
@@ -486,8 +1270,13 @@ impl JsRuntime {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::browser::Browser;
+    use crate::renderer::html::html_builder::dom_to_html;
+    use crate::renderer::html::token::HtmlTokenizer;
     use crate::renderer::js::ast::JsParser;
     use crate::renderer::js::token::JsLexer;
+    use crate::renderer::session_history::HistoryHandling;
+    use crate::renderer::test_util::create_window;
 
     #[test]
     fn test_num() {
@@ -689,4 +1478,396 @@ mod tests {
             i += 1;
         }
     }
+
+    #[test]
+    fn test_undefined_function() {
+        let browser = Browser::new();
+        let t = HtmlTokenizer::new(Rc::downgrade(&browser), "".to_string());
+        let window = HtmlParser::new(Rc::downgrade(&browser), t).construct_tree();
+        let dom = RefCell::borrow(&window).document();
+
+        let input = "foo();".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast();
+        assert!(parser.diagnostics().is_empty());
+        let mut runtime = JsRuntime::new(dom);
+        runtime.execute(&ast);
+
+        let logs: Vec<String> = RefCell::borrow(&browser)
+            .logs()
+            .iter()
+            .map(|log| log.to_string())
+            .collect();
+        assert_eq!(
+            vec!["Error: Uncaught ReferenceError: foo is not defined".to_string()],
+            logs
+        );
+    }
+
+    #[test]
+    fn test_invalid_selector() {
+        let browser = Browser::new();
+        let html = "<p class=a>x</p>".to_string();
+        let t = HtmlTokenizer::new(Rc::downgrade(&browser), html);
+        let window = HtmlParser::new(Rc::downgrade(&browser), t).construct_tree();
+        let dom = RefCell::borrow(&window).document();
+
+        let input = "var p = document.querySelector(\"p\"); var matched = p.matches(\"p > a\"); var unmatched = p.matches(\"div p\");".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast();
+        assert!(parser.diagnostics().is_empty());
+        let mut runtime = JsRuntime::new(dom);
+        runtime.execute(&ast);
+
+        let env = RefCell::borrow(&runtime.env);
+        assert_eq!(None, env.get_variable("matched".to_string()));
+        assert_eq!(
+            Some(RuntimeValue::Boolean(false)),
+            env.get_variable("unmatched".to_string())
+        );
+        let logs: Vec<String> = RefCell::borrow(&browser)
+            .logs()
+            .iter()
+            .map(|log| log.to_string())
+            .collect();
+        assert_eq!(
+            vec![
+                "Error: Uncaught SyntaxError: matches(): 'p > a' is not a valid selector"
+                    .to_string()
+            ],
+            logs
+        );
+    }
+
+    #[test]
+    fn test_url_search_params() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let input = "var params = new URLSearchParams(location.search); params.get(\"q\"); params.set(\"q\", \"b c\"); params.append(\"n\", 1); params.size; params.toString()".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast();
+        let mut runtime = JsRuntime::new(dom);
+        runtime.set_url("http://example.com/index.html?q=a&lang=ja".to_string());
+        let expected = [
+            None,
+            Some(RuntimeValue::StringLiteral("a".to_string())),
+            None,
+            None,
+            Some(RuntimeValue::Number(3)),
+            Some(RuntimeValue::StringLiteral("q=b+c&lang=ja&n=1".to_string())),
+        ];
+        let mut i = 0;
+
+        for node in ast.body() {
+            let result = runtime.eval(&Some(node.clone()), runtime.env.clone());
+            assert_eq!(expected[i], result);
+            i += 1;
+        }
+    }
+
+    #[test]
+    fn test_url_search_params_iteration() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let input = "var params = new URLSearchParams(\"a=1&b=2&a=3\"); var has = params.has(\"b\"); var missing = params.has(\"c\"); var pairs = \"\"; for (var entry of params) { pairs = pairs + entry[0] + \"=\" + entry[1] + \";\"; params.delete(\"b\"); } var keys = \"\"; for (var key of params.keys()) { keys = keys + key; } var values = params.values(); var count = values.length; var entries = params.entries(); var last = entries[1][1];"
+            .to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast();
+        assert!(parser.diagnostics().is_empty());
+        let mut runtime = JsRuntime::new(dom);
+        runtime.execute(&ast);
+
+        let env = RefCell::borrow(&runtime.env);
+        let value = |name: &str| env.get_variable(name.to_string());
+        assert_eq!(Some(RuntimeValue::Boolean(true)), value("has"));
+        assert_eq!(Some(RuntimeValue::Boolean(false)), value("missing"));
+        // The loop iterates over the value pairs at the start of the loop.
+        assert_eq!(
+            Some(RuntimeValue::StringLiteral("a=1;b=2;a=3;".to_string())),
+            value("pairs")
+        );
+        assert_eq!(
+            Some(RuntimeValue::StringLiteral("aa".to_string())),
+            value("keys")
+        );
+        assert_eq!(Some(RuntimeValue::Number(2)), value("count"));
+        assert_eq!(
+            "a,1,a,3".to_string(),
+            value("entries").expect("entries").to_string()
+        );
+        assert_eq!(
+            Some(RuntimeValue::StringLiteral("3".to_string())),
+            value("last")
+        );
+    }
+
+    #[test]
+    fn test_location_search() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let input = "location.search".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast();
+        let mut runtime = JsRuntime::new(dom);
+        runtime.set_url("http://example.com/?a=1".to_string());
+        let expected = [Some(RuntimeValue::StringLiteral("?a=1".to_string()))];
+        let mut i = 0;
+
+        for node in ast.body() {
+            let result = runtime.eval(&Some(node.clone()), runtime.env.clone());
+            assert_eq!(expected[i], result);
+            i += 1;
+        }
+    }
+
+    #[test]
+    fn test_inner_html() {
+        let window = create_window("<p id=target>a</p>");
+        let dom = RefCell::borrow(&window).document();
+
+        let input = "var a=1; var target=document.getElementById(\"target\"); target.innerHTML=\"<b>x</b>y\"; a=a+1;"
+            .to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast();
+        let mut runtime = JsRuntime::new(dom.clone());
+        runtime.execute(&ast);
+
+        // The children of the target are replaced with the parsed nodes in place, and the
+        // variable keeps its value.
+        let target =
+            get_element_by_id(Some(dom), &"target".to_string()).expect("target should exist");
+        assert_eq!(
+            "<b>x</b>y".to_string(),
+            dom_to_html(&RefCell::borrow(&target).first_child())
+        );
+        assert_eq!(
+            Some(RuntimeValue::Number(2)),
+            RefCell::borrow(&runtime.env).get_variable("a".to_string())
+        );
+    }
+
+    #[test]
+    fn test_inner_html_and_outer_html_getters() {
+        let window = create_window("<p id=target title=\"a b\">x<br></p>");
+        let dom = RefCell::borrow(&window).document();
+
+        let input = "var target=document.getElementById(\"target\"); var inner=target.innerHTML; var outer=target.outerHTML;"
+            .to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast();
+        let mut runtime = JsRuntime::new(dom);
+        runtime.execute(&ast);
+
+        assert_eq!(
+            Some(RuntimeValue::StringLiteral("x<br>".to_string())),
+            RefCell::borrow(&runtime.env).get_variable("inner".to_string())
+        );
+        assert_eq!(
+            Some(RuntimeValue::StringLiteral(
+                "<p id=\"target\" title=\"a b\">x<br></p>".to_string()
+            )),
+            RefCell::borrow(&runtime.env).get_variable("outer".to_string())
+        );
+    }
+
+    #[test]
+    fn test_document_title() {
+        let window = create_window("<title> a\n b </title><p>x</p>");
+        let dom = RefCell::borrow(&window).document();
+
+        let input = "var before=document.title; document.title=\"c<d\"; var after=document.title;"
+            .to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast();
+        let mut runtime = JsRuntime::new(dom.clone());
+        runtime.execute(&ast);
+
+        assert_eq!(
+            Some(RuntimeValue::StringLiteral("a b".to_string())),
+            RefCell::borrow(&runtime.env).get_variable("before".to_string())
+        );
+        assert_eq!(
+            Some(RuntimeValue::StringLiteral("c<d".to_string())),
+            RefCell::borrow(&runtime.env).get_variable("after".to_string())
+        );
+        assert_eq!(
+            "<html><head><title>c&lt;d</title></head><body><p>x</p></body></html>".to_string(),
+            dom_to_html(&RefCell::borrow(&dom).first_child())
+        );
+    }
+
+    #[test]
+    fn test_set_document_title_without_title_element() {
+        let window = create_window("<p>x</p>");
+        let dom = RefCell::borrow(&window).document();
+
+        let input = "document.title=\"new\";".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast();
+        let mut runtime = JsRuntime::new(dom.clone());
+        runtime.execute(&ast);
+
+        // A title element is appended to the head element.
+        assert_eq!(
+            "<html><head><title>new</title></head><body><p>x</p></body></html>".to_string(),
+            dom_to_html(&RefCell::borrow(&dom).first_child())
+        );
+    }
+
+    #[test]
+    fn test_node_mutation() {
+        let window = create_window("<div id=target><p id=first>a</p><p id=second>b</p></div>");
+        let dom = RefCell::borrow(&window).document();
+
+        let input = "var target=document.getElementById(\"target\"); var first=document.getElementById(\"first\"); var second=document.getElementById(\"second\"); var span=document.createElement(\"SPAN\"); span.appendChild(document.createTextNode(\"c\")); target.insertBefore(span, first); target.appendChild(first); target.replaceChild(first.cloneNode(true), second); target.removeChild(first); var text=target.textContent; first.textContent=\"x\"; target.appendChild(first);"
+            .to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast();
+        let mut runtime = JsRuntime::new(dom.clone());
+        runtime.execute(&ast);
+
+        let target =
+            get_element_by_id(Some(dom), &"target".to_string()).expect("target should exist");
+        assert_eq!(
+            "<span>c</span><p id=\"first\">a</p><p id=\"first\">x</p>".to_string(),
+            dom_to_html(&RefCell::borrow(&target).first_child())
+        );
+        assert_eq!(
+            Some(RuntimeValue::StringLiteral("ca".to_string())),
+            RefCell::borrow(&runtime.env).get_variable("text".to_string())
+        );
+        // The last child is updated by the mutations.
+        let last = RefCell::borrow(&target).last_child().upgrade().unwrap();
+        assert_eq!(Some("x".to_string()), text_content(&last));
+    }
+
+    #[test]
+    fn test_query_selector() {
+        let window = create_window("<ul><li class=item>a</li><li class=\"item last\">b</li></ul>");
+        let dom = RefCell::borrow(&window).document();
+
+        let input = "var items=document.querySelectorAll(\".item\"); var count=items.length; var last=document.querySelector(\".last\"); last.textContent=\"c\"; var matched=last.matches(\"li\"); var first=items.item(0); var list=first.closest(\"ul\"); var html=list.innerHTML; var missing=document.querySelector(\"p\");"
+            .to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast();
+        let mut runtime = JsRuntime::new(dom);
+        runtime.execute(&ast);
+
+        let env = RefCell::borrow(&runtime.env);
+        assert_eq!(
+            Some(RuntimeValue::Number(2)),
+            env.get_variable("count".to_string())
+        );
+        assert_eq!(
+            Some(RuntimeValue::Boolean(true)),
+            env.get_variable("matched".to_string())
+        );
+        assert_eq!(
+            Some(RuntimeValue::StringLiteral(
+                "<li class=\"item\">a</li><li class=\"item last\">c</li>".to_string()
+            )),
+            env.get_variable("html".to_string())
+        );
+        assert_eq!(None, env.get_variable("missing".to_string()));
+    }
+
+    #[test]
+    fn test_collections() {
+        let window = create_window(
+            "<ul id=list><li class=item>a</li>x<li class=\"item done\">b</li></ul><li>c</li>",
+        );
+        let dom = RefCell::borrow(&window).document();
+
+        let input = "var list=document.getElementById(\"list\"); var items=list.getElementsByTagName(\"li\"); var all=document.getElementsByTagName(\"LI\").length; var done=document.getElementsByClassName(\"done item\")[0].textContent; var text=\"\"; for (var item of items) { text = text + item.textContent; list.appendChild(item.cloneNode(1)); } var count=items.length; var nodes=list.childNodes.length; var second=list.children[1].textContent; var next=list.children.item(0).nextSibling.nextSibling.textContent; var parent=items[0].parentNode.parentNode.children.length; var none=items[9];"
+            .to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast();
+        assert!(parser.diagnostics().is_empty());
+        let mut runtime = JsRuntime::new(dom);
+        runtime.execute(&ast);
+
+        let env = RefCell::borrow(&runtime.env);
+        let value = |name: &str| env.get_variable(name.to_string());
+        assert_eq!(Some(RuntimeValue::Number(3)), value("all"));
+        assert_eq!(
+            Some(RuntimeValue::StringLiteral("b".to_string())),
+            value("done")
+        );
+        // The loop iterates over the items at the start of the loop, and the live collection
+        // has the appended copies after the loop.
+        assert_eq!(
+            Some(RuntimeValue::StringLiteral("ab".to_string())),
+            value("text")
+        );
+        assert_eq!(Some(RuntimeValue::Number(4)), value("count"));
+        assert_eq!(Some(RuntimeValue::Number(5)), value("nodes"));
+        assert_eq!(
+            Some(RuntimeValue::StringLiteral("b".to_string())),
+            value("second")
+        );
+        assert_eq!(
+            Some(RuntimeValue::StringLiteral("b".to_string())),
+            value("next")
+        );
+        // The body element has the list and the last item.
+        assert_eq!(Some(RuntimeValue::Number(2)), value("parent"));
+        assert_eq!(None, value("none"));
+    }
+    #[test]
+    fn test_location_and_history() {
+        let window = create_window("");
+        let url = "http://example.com/a/index.html?q=1#top".to_string();
+        window.borrow_mut().set_url(url.clone());
+        let session_history = RefCell::borrow(&window).session_history();
+        session_history
+            .borrow_mut()
+            .add_document_entry(url, HistoryHandling::Push);
+        let dom = RefCell::borrow(&window).document();
+
+        let input = "var href=location.href; var pathname=window.location.pathname; var hash=location.hash; history.pushState(\"s\", \"\", \"b.html\"); var state=history.state; var length=history.length; var search=location.search; history.replaceState(null, \"\", \"null\"); var replaced=location.pathname; var replacedState=history.state; history.go(\"-1\"); location.hash=\"x\";"
+            .to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast();
+        assert!(parser.diagnostics().is_empty());
+        let mut runtime = JsRuntime::new(dom);
+        runtime.execute(&ast);
+
+        let env = RefCell::borrow(&runtime.env);
+        let value = |name: &str| env.get_variable(name.to_string());
+        let string = |s: &str| Some(RuntimeValue::StringLiteral(s.to_string()));
+        assert_eq!(
+            string("http://example.com/a/index.html?q=1#top"),
+            value("href")
+        );
+        assert_eq!(string("/a/index.html"), value("pathname"));
+        assert_eq!(string("#top"), value("hash"));
+        assert_eq!(string("s"), value("state"));
+        assert_eq!(Some(RuntimeValue::Number(2)), value("length"));
+        // pushState() changes the URL of the document without loading it.
+        assert_eq!(string(""), value("search"));
+        // A null state is not the string "null", which is a relative URL.
+        assert_eq!(string("/a/null"), value("replaced"));
+        assert_eq!(None, value("replacedState"));
+        assert_eq!(
+            "http://example.com/a/null".to_string(),
+            RefCell::borrow(&window).url()
+        );
+
+        // The traversal and the navigation are started by the page after the script.
+        assert_eq!(Some(-1), window.borrow_mut().take_traversal());
+        assert_eq!(
+            Some("http://example.com/a/null#x".to_string()),
+            window.borrow_mut().take_navigation().map(|n| n.url())
+        );
+    }
 }
@@ -1,10 +1,12 @@
 //! https://262.ecma-international.org/12.0/#sec-ecmascript-language-lexical-grammar
 
+use crate::renderer::source::LineMap;
+use crate::renderer::source::SourcePosition;
 use alloc::string::String;
 use alloc::string::ToString;
 use alloc::vec::Vec;
 
-static RESERVED_WORDS: [&str; 3] = ["var", "function", "return"];
+static RESERVED_WORDS: [&str; 5] = ["var", "function", "return", "new", "for"];
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Token {
@@ -24,16 +26,68 @@ pub enum Token {
 pub struct JsLexer {
     pos: usize,
     input: Vec<char>,
+    line_map: LineMap,
+    /// The index of the first character of the token being consumed.
+    token_start: usize,
+    /// The position of the current token, which is the last token returned by `next`.
+    position: SourcePosition,
+    /// The next token and its position if it's already consumed by `peek`.
+    peeked: Option<(Option<Token>, SourcePosition)>,
+    /// The syntax errors found so far and their positions.
+    errors: Vec<(&'static str, SourcePosition)>,
 }
 
 impl JsLexer {
     pub fn new(js: String) -> Self {
+        let input: Vec<char> = js.chars().collect();
         Self {
             pos: 0,
-            input: js.chars().collect(),
+            line_map: LineMap::new(&input),
+            input,
+            token_start: 0,
+            position: SourcePosition::default(),
+            peeked: None,
+            errors: Vec::new(),
         }
     }
 
+    /// Returns the position of the current token.
+    pub fn position(&self) -> SourcePosition {
+        self.position
+    }
+
+    /// Returns the next token without consuming it.
+    pub fn peek(&mut self) -> Option<&Token> {
+        if self.peeked.is_none() {
+            let token = self.consume_token();
+            let position = self.line_map.position(self.token_start);
+            self.peeked = Some((token, position));
+        }
+
+        match &self.peeked {
+            Some((token, _)) => token.as_ref(),
+            None => None,
+        }
+    }
+
+    /// Returns the position of the next token.
+    pub fn next_position(&mut self) -> SourcePosition {
+        self.peek();
+        match &self.peeked {
+            Some((_, position)) => *position,
+            None => self.position,
+        }
+    }
+
+    /// Returns the syntax errors found so far and clears them.
+    pub fn take_errors(&mut self) -> Vec<(&'static str, SourcePosition)> {
+        core::mem::take(&mut self.errors)
+    }
+
+    fn error(&mut self, code: &'static str, index: usize) {
+        self.errors.push((code, self.line_map.position(index)));
+    }
+
     fn consume_number(&mut self) -> u64 {
         let mut num = 0;
 
@@ -62,6 +116,7 @@ impl JsLexer {
 
         loop {
             if self.pos >= self.input.len() {
+                self.error("unterminated-string-literal", self.token_start);
                 return result;
             }
 
@@ -94,6 +149,17 @@ impl JsLexer {
     }
 
     fn contains(&self, keyword: &str) -> bool {
+        if self.pos + keyword.len() > self.input.len() {
+            return false;
+        }
+
+        // A keyword must not be followed by an identifier part. e.g. "newValue" is an identifier.
+        if let Some(c) = self.input.get(self.pos + keyword.len()) {
+            if c.is_ascii_alphanumeric() || c == &'$' || c == &'_' {
+                return false;
+            }
+        }
+
         for i in 0..keyword.len() {
             if keyword
                 .chars()
@@ -117,25 +183,30 @@ impl JsLexer {
 
         None
     }
-}
-
-impl Iterator for JsLexer {
-    type Item = Token;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.pos >= self.input.len() {
-            return None;
-        }
 
-        // skip a white space and a new line
-        while self.input[self.pos] == ' ' || self.input[self.pos] == '\n' {
-            self.pos += 1;
+    fn consume_token(&mut self) -> Option<Token> {
+        loop {
+            // skip white spaces and line terminators
+            while self.pos < self.input.len()
+                && matches!(self.input[self.pos], ' ' | '\t' | '\n' | '\r')
+            {
+                self.pos += 1;
+            }
 
+            self.token_start = self.pos;
             if self.pos >= self.input.len() {
                 return None;
             }
+
+            if let Some(token) = self.consume_token_at_current_position() {
+                return Some(token);
+            }
         }
+    }
 
+    /// Consumes a token starting from the current position. Returns None and skips the character
+    /// if it can't start a token.
+    fn consume_token_at_current_position(&mut self) -> Option<Token> {
         if let Some(keyword) = self.check_reserved_word() {
             self.pos += keyword.len();
             let token = Some(Token::Keyword(keyword));
@@ -145,7 +216,7 @@ impl Iterator for JsLexer {
         let c = self.input[self.pos];
 
         let token = match c {
-            '+' | '-' | ';' | '=' | '(' | ')' | '{' | '}' | ',' | '.' => {
+            '+' | '-' | ';' | '=' | '(' | ')' | '{' | '}' | '[' | ']' | ',' | '.' => {
                 let t = Token::Punctuator(c);
                 self.pos += 1;
                 t
@@ -154,13 +225,34 @@ impl Iterator for JsLexer {
             '0'..='9' => Token::Number(self.consume_number()),
             // https://262.ecma-international.org/12.0/#prod-IdentifierStart
             'a'..='z' | 'A'..='Z' | '_' | '$' => Token::Identifier(self.consume_identifier()),
-            _ => unimplemented!("char {:?} is not supported yet", c),
+            // TODO: support other punctuators and literals.
+            _ => {
+                self.error("unexpected-character", self.pos);
+                self.pos += 1;
+                return None;
+            }
         };
 
         Some(token)
     }
 }
 
+impl Iterator for JsLexer {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (token, position) = match self.peeked.take() {
+            Some(peeked) => peeked,
+            None => {
+                let token = self.consume_token();
+                (token, self.line_map.position(self.token_start))
+            }
+        };
+        self.position = position;
+        token
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -482,4 +574,30 @@ mod tests {
         }
         assert!(lexer.peek().is_none());
     }
+
+    #[test]
+    fn test_new_expression() {
+        let input = "var newParams = new URLSearchParams(location.search);".to_string();
+        let mut lexer = JsLexer::new(input).peekable();
+        let expected = [
+            Token::Keyword("var".to_string()),
+            Token::Identifier("newParams".to_string()),
+            Token::Punctuator('='),
+            Token::Keyword("new".to_string()),
+            Token::Identifier("URLSearchParams".to_string()),
+            Token::Punctuator('('),
+            Token::Identifier("location".to_string()),
+            Token::Punctuator('.'),
+            Token::Identifier("search".to_string()),
+            Token::Punctuator(')'),
+            Token::Punctuator(';'),
+        ]
+        .to_vec();
+        let mut i = 0;
+        while lexer.peek().is_some() {
+            assert_eq!(Some(expected[i].clone()), lexer.next());
+            i += 1;
+        }
+        assert!(lexer.peek().is_none());
+    }
 }
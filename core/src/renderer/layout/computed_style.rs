@@ -3,6 +3,7 @@
 //! https://developer.mozilla.org/en-US/docs/Learn/CSS/Building_blocks/Cascade_and_inheritance
 
 use crate::error::Error;
+use crate::renderer::dom::node::Element;
 use crate::renderer::dom::node::ElementKind;
 use crate::renderer::dom::node::Node;
 use crate::renderer::dom::node::NodeKind;
@@ -10,13 +11,18 @@ use crate::renderer::layout::color::*;
 use alloc::format;
 use alloc::rc::Rc;
 use core::cell::RefCell;
+use core::str::FromStr;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ComputedStyle {
     background_color: Option<Color>,
+    border_color: Option<Color>,
+    border_width: Option<f64>,
     color: Option<Color>,
     display: Option<DisplayType>,
     font_size: Option<FontSize>,
+    font_style: Option<FontStyle>,
+    font_weight: Option<FontWeight>,
     height: Option<f64>,
     margin: Option<BoxInfo>,
     padding: Option<BoxInfo>,
@@ -52,9 +58,13 @@ impl ComputedStyle {
         // It may be better to handle cascading, defaulting and inheritance here.
         Self {
             background_color: None,
+            border_color: None,
+            border_width: None,
             color: None,
             display: None,
             font_size: None,
+            font_style: None,
+            font_weight: None,
             height: None,
             margin: None,
             padding: None,
@@ -70,7 +80,8 @@ impl ComputedStyle {
     pub fn defaulting(&mut self, node: &Rc<RefCell<Node>>, parent_style: Option<ComputedStyle>) {
         // If the parent exists and a CSS property doesn't have a default value, inherit the value.
         if let Some(parent_style) = parent_style {
-            // currently, only inherit `background_color`, `color`, `font_size` and `text_decoration`.
+            // currently, only inherit `background_color`, `color`, `font_size`, `font_style`,
+            // `font_weight` and `text_decoration`.
             if self.background_color.is_none() && parent_style.background_color() != Color::white()
             {
                 self.background_color = Some(parent_style.background_color());
@@ -81,6 +92,12 @@ impl ComputedStyle {
             if self.font_size.is_none() && parent_style.font_size() != FontSize::Medium {
                 self.font_size = Some(parent_style.font_size());
             }
+            if self.font_style.is_none() && parent_style.font_style() != FontStyle::Normal {
+                self.font_style = Some(parent_style.font_style());
+            }
+            if self.font_weight.is_none() && parent_style.font_weight() != FontWeight::Normal {
+                self.font_weight = Some(parent_style.font_weight());
+            }
             if self.text_decoration.is_none()
                 && parent_style.text_decoration() != TextDecoration::None
             {
@@ -89,7 +106,14 @@ impl ComputedStyle {
         }
 
         if self.background_color.is_none() {
-            self.background_color = Some(Color::white());
+            self.background_color = Some(default_background_color(node));
+        }
+        if self.border_color.is_none() {
+            // Borders are drawn in gray, which is close to the color of an `inset` border.
+            self.border_color = Some(Color::from_name("gray").unwrap_or(Color::black()));
+        }
+        if self.border_width.is_none() {
+            self.border_width = Some(default_border_width(node));
         }
         if self.color.is_none() {
             self.color = Some(Color::black());
@@ -100,17 +124,20 @@ impl ComputedStyle {
         if self.font_size.is_none() {
             self.font_size = Some(FontSize::default(node));
         }
+        if self.font_style.is_none() {
+            self.font_style = Some(FontStyle::default(node));
+        }
+        if self.font_weight.is_none() {
+            self.font_weight = Some(FontWeight::default(node));
+        }
         if self.height.is_none() {
-            // check the default value for height
-            self.height = Some(0.0);
+            self.height = Some(default_height(node));
         }
         if self.margin.is_none() {
-            // check the default value for margin
-            self.margin = Some(BoxInfo::new(0.0, 0.0, 0.0, 0.0));
+            self.margin = Some(BoxInfo::default_margin(node));
         }
         if self.padding.is_none() {
-            // check the default value for padding
-            self.padding = Some(BoxInfo::new(0.0, 0.0, 0.0, 0.0));
+            self.padding = Some(BoxInfo::default_padding(node));
         }
         if self.text_decoration.is_none() {
             self.text_decoration = Some(TextDecoration::default(node));
@@ -134,6 +161,26 @@ impl ComputedStyle {
             .expect("failed to access CSS property: background_color")
     }
 
+    pub fn set_border_color(&mut self, color: Color) {
+        self.border_color = Some(color);
+    }
+
+    pub fn border_color(&self) -> Color {
+        self.border_color
+            .clone()
+            .expect("failed to access CSS property: border_color")
+    }
+
+    pub fn set_border_width(&mut self, width: f64) {
+        self.border_width = Some(width);
+    }
+
+    /// Returns the width of all four borders.
+    pub fn border_width(&self) -> f64 {
+        self.border_width
+            .expect("failed to access CSS property: border_width")
+    }
+
     pub fn set_color(&mut self, color: Color) {
         self.color = Some(color);
     }
@@ -177,6 +224,15 @@ impl ComputedStyle {
         self.margin.expect("failed to access CSS property: margin")
     }
 
+    /// Returns the cascaded margin if it exists. Otherwise, returns the default margin for
+    /// `node`. This is used to update one side of the margin before defaulting.
+    pub fn margin_or_default(&self, node: &Rc<RefCell<Node>>) -> BoxInfo {
+        match self.margin {
+            Some(margin) => margin,
+            None => BoxInfo::default_margin(node),
+        }
+    }
+
     pub fn set_padding(&mut self, padding: BoxInfo) {
         self.padding = Some(padding);
     }
@@ -191,6 +247,24 @@ impl ComputedStyle {
             .expect("failed to access CSS property: font_size")
     }
 
+    pub fn set_font_style(&mut self, font_style: FontStyle) {
+        self.font_style = Some(font_style);
+    }
+
+    pub fn font_style(&self) -> FontStyle {
+        self.font_style
+            .expect("failed to access CSS property: font_style")
+    }
+
+    pub fn set_font_weight(&mut self, font_weight: FontWeight) {
+        self.font_weight = Some(font_weight);
+    }
+
+    pub fn font_weight(&self) -> FontWeight {
+        self.font_weight
+            .expect("failed to access CSS property: font_weight")
+    }
+
     pub fn white_space(&self) -> WhiteSpace {
         self.white_space
             .expect("failed to access CSS property: white_space")
@@ -242,6 +316,15 @@ pub enum DisplayType {
     Inline,
     /// https://www.w3.org/TR/css-display-3/#valdef-display-none
     DisplayNone,
+    /// https://www.w3.org/TR/css-display-3/#valdef-display-table
+    Table,
+    /// https://www.w3.org/TR/css-display-3/#valdef-display-table-row-group
+    /// `table-header-group` and `table-footer-group` are treated as `table-row-group`.
+    TableRowGroup,
+    /// https://www.w3.org/TR/css-display-3/#valdef-display-table-row
+    TableRow,
+    /// https://www.w3.org/TR/css-display-3/#valdef-display-table-cell
+    TableCell,
 }
 
 impl DisplayType {
@@ -249,9 +332,67 @@ impl DisplayType {
         match &node.borrow().kind() {
             NodeKind::Document => DisplayType::Block,
             NodeKind::Element(e) => {
-                if e.kind() == ElementKind::Script || e.kind() == ElementKind::Style {
+                // https://html.spec.whatwg.org/multipage/rendering.html#hidden-elements
+                // "area, base, basefont, datalist, head, link, meta, noembed,
+                // noframes, param, rp, script, style, template, title {
+                //   display: none;
+                // }"
+                if e.kind() == ElementKind::Head
+                    || e.kind() == ElementKind::Title
+                    || e.kind() == ElementKind::Script
+                    || e.kind() == ElementKind::Style
+                {
+                    return DisplayType::DisplayNone;
+                }
+                if e.kind() == ElementKind::Unknown
+                    && matches!(
+                        e.local_name().as_str(),
+                        "area"
+                            | "base"
+                            | "basefont"
+                            | "datalist"
+                            | "link"
+                            | "meta"
+                            | "noembed"
+                            | "noframes"
+                            | "param"
+                            | "rp"
+                            | "template"
+                    )
+                {
                     return DisplayType::DisplayNone;
                 }
+                // https://html.spec.whatwg.org/multipage/rendering.html#hidden-elements
+                // "input[type=hidden i] { display: none !important; }"
+                if e.kind() == ElementKind::Input
+                    && e.get_attribute("type")
+                        .is_some_and(|t| t.eq_ignore_ascii_case("hidden"))
+                {
+                    return DisplayType::DisplayNone;
+                }
+
+                // https://html.spec.whatwg.org/multipage/rendering.html#tables-2
+                // "table { display: table; }
+                //  caption { display: table-caption; }
+                //  colgroup, colgroup[hidden] { display: table-column-group; }
+                //  col, col[hidden] { display: table-column; }
+                //  thead, thead[hidden] { display: table-header-group; }
+                //  tbody, tbody[hidden] { display: table-row-group; }
+                //  tfoot, tfoot[hidden] { display: table-footer-group; }
+                //  tr, tr[hidden] { display: table-row; }
+                //  td, th { display: table-cell; }"
+                // A caption is laid out as a block at the top of the table, and columns are not
+                // rendered because they don't have any content.
+                match e.kind() {
+                    ElementKind::Table => return DisplayType::Table,
+                    ElementKind::Colgroup | ElementKind::Col => return DisplayType::DisplayNone,
+                    ElementKind::Thead | ElementKind::Tbody | ElementKind::Tfoot => {
+                        return DisplayType::TableRowGroup
+                    }
+                    ElementKind::Tr => return DisplayType::TableRow,
+                    ElementKind::Td | ElementKind::Th => return DisplayType::TableCell,
+                    _ => {}
+                }
 
                 if e.is_block_element() {
                     DisplayType::Block
@@ -260,6 +401,8 @@ impl DisplayType {
                 }
             }
             NodeKind::Text(_) => DisplayType::Inline,
+            // Comments and DOCTYPEs are never rendered.
+            NodeKind::DocumentType(_) | NodeKind::Comment(_) => DisplayType::DisplayNone,
         }
     }
 
@@ -268,6 +411,12 @@ impl DisplayType {
             "block" => Ok(Self::Block),
             "inline" => Ok(Self::Inline),
             "none" => Ok(Self::DisplayNone),
+            "table" => Ok(Self::Table),
+            "table-row-group" | "table-header-group" | "table-footer-group" => {
+                Ok(Self::TableRowGroup)
+            }
+            "table-row" => Ok(Self::TableRow),
+            "table-cell" => Ok(Self::TableCell),
             _ => Err(Error::UnexpectedInput(format!(
                 "display {:?} is not supported yet",
                 s
@@ -294,6 +443,82 @@ impl BoxInfo {
         }
     }
 
+    /// Returns the default margin of an element. `em` values in the spec are converted to
+    /// pixels with the element's default font size.
+    /// https://html.spec.whatwg.org/multipage/rendering.html#the-page
+    /// https://html.spec.whatwg.org/multipage/rendering.html#flow-content-3
+    /// https://html.spec.whatwg.org/multipage/rendering.html#sections-and-headings
+    /// https://html.spec.whatwg.org/multipage/rendering.html#lists
+    fn default_margin(node: &Rc<RefCell<Node>>) -> Self {
+        let (top_and_bottom, left_and_right) = match &node.borrow().kind() {
+            NodeKind::Element(element) => match element.kind() {
+                // "body { margin: 8px; }"
+                ElementKind::Body => (8.0, 8.0),
+                // "blockquote, figure, listing, p, plaintext, pre, xmp {
+                //   margin-block: 1em;
+                // }"
+                // "blockquote, figure { margin-inline: 40px; }"
+                ElementKind::Blockquote => (16.0, 40.0),
+                ElementKind::P | ElementKind::Pre => (16.0, 0.0),
+                // "dir, dd, dl, dt, menu, ol, ul { display: block; }"
+                // "dir, dl, menu, ol, ul { margin-block: 1em; }"
+                ElementKind::Dl | ElementKind::Ol | ElementKind::Ul => (16.0, 0.0),
+                // "dd { margin-inline-start: 40px; }"
+                ElementKind::Dd => return Self::new(0.0, 0.0, 40.0, 0.0),
+                // "h1 { margin-block: 0.67em; font-size: 2.00em; font-weight: bold; }"
+                ElementKind::H1 => (21.0, 0.0),
+                // "h2 { margin-block: 0.83em; font-size: 1.50em; font-weight: bold; }"
+                ElementKind::H2 => (20.0, 0.0),
+                // "h3 { margin-block: 1.00em; font-size: 1.17em; font-weight: bold; }"
+                ElementKind::H3 => (19.0, 0.0),
+                // "h4 { margin-block: 1.33em; font-size: 1.00em; font-weight: bold; }"
+                ElementKind::H4 => (21.0, 0.0),
+                // "h5 { margin-block: 1.67em; font-size: 0.83em; font-weight: bold; }"
+                ElementKind::H5 => (22.0, 0.0),
+                // "h6 { margin-block: 2.33em; font-size: 0.67em; font-weight: bold; }"
+                ElementKind::H6 => (25.0, 0.0),
+                // "hr { margin-block: 0.5em; margin-inline: auto; }"
+                ElementKind::Hr => (8.0, 0.0),
+                // "form { display: block; margin-block-end: 1em; }"
+                ElementKind::Form => return Self::new(0.0, 0.0, 0.0, 16.0),
+                _ => (0.0, 0.0),
+            },
+            _ => (0.0, 0.0),
+        };
+
+        Self::new(
+            top_and_bottom,
+            left_and_right,
+            left_and_right,
+            top_and_bottom,
+        )
+    }
+
+    /// Returns the default padding of an element.
+    /// https://html.spec.whatwg.org/multipage/rendering.html#lists
+    /// https://html.spec.whatwg.org/multipage/rendering.html#tables-2
+    fn default_padding(node: &Rc<RefCell<Node>>) -> Self {
+        match &node.borrow().kind() {
+            NodeKind::Element(element) => match element.kind() {
+                // "dir, menu, ol, ul { padding-inline-start: 40px; }"
+                ElementKind::Ol | ElementKind::Ul => Self::new(0.0, 0.0, 40.0, 0.0),
+                // "td, th { padding: 1px; }"
+                // "When a table element has a cellpadding attribute, its td and th descendants
+                // that are not also descendants of another table element are expected to have
+                // their padding properties set to the value of the attribute."
+                ElementKind::Td | ElementKind::Th => {
+                    let padding = ancestor_table(node)
+                        .and_then(|table| table.get_attribute("cellpadding"))
+                        .and_then(|value| value.trim().parse::<u32>().ok())
+                        .map_or(1.0, |value| value as f64);
+                    Self::new(padding, padding, padding, padding)
+                }
+                _ => Self::new(0.0, 0.0, 0.0, 0.0),
+            },
+            _ => Self::new(0.0, 0.0, 0.0, 0.0),
+        }
+    }
+
     pub fn top(&self) -> f64 {
         self.top
     }
@@ -311,26 +536,207 @@ impl BoxInfo {
     }
 }
 
+/// Returns the default background color. A horizontal rule is painted as a gray box because
+/// borders are not supported.
+/// https://html.spec.whatwg.org/multipage/rendering.html#the-hr-element-2
+fn default_background_color(node: &Rc<RefCell<Node>>) -> Color {
+    match &node.borrow().kind() {
+        NodeKind::Element(element) if element.kind() == ElementKind::Hr => {
+            Color::from_name("gray").unwrap_or(Color::black())
+        }
+        _ => Color::white(),
+    }
+}
+
+/// Returns the nearest table element that contains `node`.
+fn ancestor_table(node: &Rc<RefCell<Node>>) -> Option<Element> {
+    let mut parent = node.borrow().parent().upgrade();
+    while let Some(p) = parent {
+        if let Some(element) = p.borrow().get_element() {
+            if element.kind() == ElementKind::Table {
+                return Some(element);
+            }
+        }
+        parent = p.borrow().parent().upgrade();
+    }
+    None
+}
+
+/// Returns the width of borders specified by the `border` attribute of a table element.
+/// https://html.spec.whatwg.org/multipage/rendering.html#tables-2
+fn table_border_width(table: &Element) -> f64 {
+    match table.get_attribute("border") {
+        // "When a table element has a border attribute whose value, when parsed using the rules
+        // for parsing non-negative integers, is found to be a number greater than zero, the user
+        // agent is expected to use the parsed value for all four pixel length properties
+        // 'border-top-width', 'border-right-width', 'border-bottom-width', 'border-left-width'."
+        //
+        // "If the attribute is present but parsing the attribute's value using the rules for
+        // parsing non-negative integers generates an error, a default value of 1px is expected to
+        // be used for that property instead."
+        Some(value) => value
+            .trim()
+            .parse::<u32>()
+            .map_or(1.0, |value| value as f64),
+        None => 0.0,
+    }
+}
+
+/// Returns the default width of borders. Only tables with a `border` attribute, their cells and
+/// iframes have borders by default.
+/// https://html.spec.whatwg.org/multipage/rendering.html#tables-2
+fn default_border_width(node: &Rc<RefCell<Node>>) -> f64 {
+    match &node.borrow().kind() {
+        NodeKind::Element(element) => match element.kind() {
+            // "iframe { border: 2px inset; }"
+            // https://html.spec.whatwg.org/multipage/rendering.html#the-iframe-element-2
+            ElementKind::Iframe => 2.0,
+            ElementKind::Table => table_border_width(element),
+            // "table[border] > tr > td, table[border] > tr > th, ... {
+            //   border-width: 1px;
+            //   border-style: inset;
+            // }"
+            ElementKind::Td | ElementKind::Th => match ancestor_table(node) {
+                Some(table) if table_border_width(&table) > 0.0 => 1.0,
+                _ => 0.0,
+            },
+            _ => 0.0,
+        },
+        _ => 0.0,
+    }
+}
+
+/// Returns the default height. 0.0 means that the height is decided by the contents.
+/// https://html.spec.whatwg.org/multipage/rendering.html#the-hr-element-2
+fn default_height(node: &Rc<RefCell<Node>>) -> f64 {
+    match &node.borrow().kind() {
+        // "hr { border-style: inset; border-width: 1px; }" makes a 2px-high rule.
+        NodeKind::Element(element) if element.kind() == ElementKind::Hr => 2.0,
+        _ => 0.0,
+    }
+}
+
 /// https://www.w3.org/TR/css-fonts-4/#absolute-size-mapping
 /// https://docs.gtk.org/Pango/pango_markup.html align with pango markup syntax
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum FontSize {
+    XSmall,
+    Small,
     Medium,
+    Large,
     XLarge,
     XXLarge,
 }
 
 impl FontSize {
+    /// https://html.spec.whatwg.org/multipage/rendering.html#sections-and-headings
     fn default(node: &Rc<RefCell<Node>>) -> Self {
         match &node.borrow().kind() {
             NodeKind::Element(element) => match element.kind() {
                 ElementKind::H1 => FontSize::XXLarge,
                 ElementKind::H2 => FontSize::XLarge,
+                ElementKind::H3 => FontSize::Large,
+                ElementKind::H5 => FontSize::Small,
+                ElementKind::H6 => FontSize::XSmall,
                 _ => FontSize::Medium,
             },
             _ => FontSize::Medium,
         }
     }
+
+    /// Returns the scale of a character compared to the medium size. Text smaller than medium
+    /// uses the medium size because the UI has only fixed-size fonts.
+    pub fn ratio(&self) -> i64 {
+        match self {
+            FontSize::XSmall | FontSize::Small | FontSize::Medium | FontSize::Large => 1,
+            FontSize::XLarge => 2,
+            FontSize::XXLarge => 3,
+        }
+    }
+}
+
+/// https://www.w3.org/TR/css-fonts-4/#font-style-prop
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FontStyle {
+    Normal,
+    Italic,
+}
+
+impl FontStyle {
+    /// https://html.spec.whatwg.org/multipage/rendering.html#phrasing-content-3
+    /// "address, cite, dfn, em, i, var { font-style: italic; }"
+    fn default(node: &Rc<RefCell<Node>>) -> Self {
+        match &node.borrow().kind() {
+            NodeKind::Element(element) => match element.kind() {
+                ElementKind::Em | ElementKind::I => FontStyle::Italic,
+                _ => FontStyle::Normal,
+            },
+            _ => FontStyle::Normal,
+        }
+    }
+}
+
+impl FromStr for FontStyle {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "normal" => Ok(Self::Normal),
+            "italic" | "oblique" => Ok(Self::Italic),
+            _ => Err(Error::UnexpectedInput(format!(
+                "font-style {:?} is not supported yet",
+                s
+            ))),
+        }
+    }
+}
+
+/// https://www.w3.org/TR/css-fonts-4/#font-weight-prop
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FontWeight {
+    Normal,
+    Bold,
+}
+
+impl FontWeight {
+    /// https://html.spec.whatwg.org/multipage/rendering.html#phrasing-content-3
+    /// "b, strong { font-weight: bolder; }"
+    /// https://html.spec.whatwg.org/multipage/rendering.html#sections-and-headings
+    /// https://html.spec.whatwg.org/multipage/rendering.html#tables-2
+    /// "th { font-weight: bold; }"
+    fn default(node: &Rc<RefCell<Node>>) -> Self {
+        match &node.borrow().kind() {
+            NodeKind::Element(element) => match element.kind() {
+                ElementKind::B
+                | ElementKind::Strong
+                | ElementKind::H1
+                | ElementKind::H2
+                | ElementKind::H3
+                | ElementKind::H4
+                | ElementKind::H5
+                | ElementKind::H6
+                | ElementKind::Dt
+                | ElementKind::Th => FontWeight::Bold,
+                _ => FontWeight::Normal,
+            },
+            _ => FontWeight::Normal,
+        }
+    }
+}
+
+impl FromStr for FontWeight {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "normal" => Ok(Self::Normal),
+            "bold" | "bolder" => Ok(Self::Bold),
+            _ => Err(Error::UnexpectedInput(format!(
+                "font-weight {:?} is not supported yet",
+                s
+            ))),
+        }
+    }
 }
 
 /// https://w3c.github.io/csswg-drafts/css-text-decor/#text-decoration-property
@@ -364,6 +770,7 @@ impl WhiteSpace {
         match &node.borrow().kind() {
             NodeKind::Element(element) => match element.kind() {
                 ElementKind::P => WhiteSpace::Normal,
+                // "listing, plaintext, pre, xmp { white-space: pre; }"
                 ElementKind::Pre => WhiteSpace::Pre,
                 _ => WhiteSpace::Normal,
             },
@@ -11,6 +11,9 @@ use crate::renderer::css::cssom::ComponentValue;
 use crate::renderer::css::cssom::Declaration;
 use crate::renderer::css::cssom::Selector;
 use crate::renderer::css::cssom::StyleSheet;
+use crate::renderer::css::selector::matches_selector;
+use crate::renderer::dom::focus;
+use crate::renderer::dom::form;
 use crate::renderer::dom::node::ElementKind;
 use crate::renderer::dom::node::Node;
 use crate::renderer::dom::node::NodeKind;
@@ -18,9 +21,11 @@ use crate::renderer::layout::color::Color;
 use crate::renderer::layout::computed_style::BoxInfo;
 use crate::renderer::layout::computed_style::ComputedStyle;
 use crate::renderer::layout::computed_style::DisplayType;
-use crate::renderer::layout::computed_style::FontSize;
+use crate::renderer::layout::computed_style::FontStyle;
+use crate::renderer::layout::computed_style::FontWeight;
 use crate::renderer::layout::layout_point::LayoutPoint;
 use crate::renderer::layout::layout_size::LayoutSize;
+use crate::renderer::source::SourceRange;
 use crate::utils::console_error;
 use crate::utils::console_warning;
 use alloc::format;
@@ -29,6 +34,7 @@ use alloc::string::String;
 use alloc::vec;
 use alloc::vec::Vec;
 use core::cell::RefCell;
+use core::str::FromStr;
 
 /// This is used when { word-break: normal; } in CSS.
 /// https://drafts.csswg.org/css-text/#word-break-property
@@ -42,21 +48,40 @@ fn find_index_for_line_break(line: String, max_index: usize) -> usize {
 }
 
 /// https://drafts.csswg.org/css-text/#word-break-property
-fn split_text(line: String, char_width: i64) -> Vec<String> {
+fn split_text(line: String, char_width: i64, max_width: i64) -> Vec<String> {
     let mut result: Vec<String> = vec![];
-    if line.len() as i64 * char_width > (WINDOW_WIDTH + WINDOW_PADDING) {
-        let s = line.split_at(find_index_for_line_break(
-            line.clone(),
-            ((WINDOW_WIDTH + WINDOW_PADDING) / char_width) as usize,
-        ));
+    if line.len() as i64 * char_width > max_width {
+        // Put at least one character in a line.
+        let max_index = core::cmp::max(max_width / char_width, 1);
+        let s = line.split_at(find_index_for_line_break(line.clone(), max_index as usize));
         result.push(s.0.to_string());
-        result.extend(split_text(s.1.trim().to_string(), char_width))
+        result.extend(split_text(s.1.trim().to_string(), char_width, max_width))
     } else {
         result.push(line);
     }
     result
 }
 
+/// Collapses a sequence of white spaces into a single space and removes leading and trailing
+/// white spaces.
+/// https://drafts.csswg.org/css-text/#white-space-phase-1
+fn collapse_white_space(text: &str) -> String {
+    text.replace('\n', " ")
+        .split(' ')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Pads `line` with underscores to draw a text field of `width` characters.
+fn pad_line(line: &str, width: usize) -> String {
+    let len = line.chars().count();
+    if len >= width {
+        return line.to_string();
+    }
+    format!("{}{}", line, "_".repeat(width - len))
+}
+
 pub fn create_layout_object(
     browser: Weak<RefCell<Browser>>,
     node: &Option<Rc<RefCell<Node>>>,
@@ -103,6 +128,39 @@ pub enum LayoutObjectKind {
     Block,
     Inline,
     Text,
+    /// A forced line break created by a <br> element. The following content starts on a new
+    /// line like a block, while the line break itself is placed at the end of the current line.
+    /// https://html.spec.whatwg.org/multipage/rendering.html#phrasing-content-3
+    LineBreak,
+    /// A table wrapper box. Its row groups, rows and cells are laid out by the table layout
+    /// algorithm in the `table` module.
+    /// https://www.w3.org/TR/CSS22/tables.html
+    Table,
+    TableRowGroup,
+    TableRow,
+    TableCell,
+    /// A replaced box for an input, textarea, select or button element. Its content is the
+    /// state of the control instead of its children, and it's drawn as text.
+    /// https://html.spec.whatwg.org/multipage/rendering.html#form-controls
+    FormControl,
+    /// A replaced box for an iframe element. Its content is the document of the child frame,
+    /// which is laid out and painted by the child frame's page.
+    /// https://html.spec.whatwg.org/multipage/rendering.html#the-iframe-element-2
+    Iframe,
+}
+
+impl LayoutObjectKind {
+    /// Returns true if the object is placed on its own line in the normal flow.
+    pub fn is_block_level(&self) -> bool {
+        matches!(
+            self,
+            LayoutObjectKind::Block
+                | LayoutObjectKind::Table
+                | LayoutObjectKind::TableRowGroup
+                | LayoutObjectKind::TableRow
+                | LayoutObjectKind::TableCell
+        )
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -164,17 +222,37 @@ impl LayoutObject {
     pub fn update_kind(&mut self) {
         match self.node_kind() {
             NodeKind::Document => panic!("should not create a layout object for a Document node"),
-            NodeKind::Element(_) => {
+            NodeKind::Element(e) => {
+                if e.kind() == ElementKind::Br {
+                    self.kind = LayoutObjectKind::LineBreak;
+                    return;
+                }
+                if form::is_form_control(&e) {
+                    self.kind = LayoutObjectKind::FormControl;
+                    return;
+                }
+                if e.kind() == ElementKind::Iframe {
+                    self.kind = LayoutObjectKind::Iframe;
+                    return;
+                }
+
                 let display = self.style.display();
                 match display {
                     DisplayType::Block => self.kind = LayoutObjectKind::Block,
                     DisplayType::Inline => self.kind = LayoutObjectKind::Inline,
+                    DisplayType::Table => self.kind = LayoutObjectKind::Table,
+                    DisplayType::TableRowGroup => self.kind = LayoutObjectKind::TableRowGroup,
+                    DisplayType::TableRow => self.kind = LayoutObjectKind::TableRow,
+                    DisplayType::TableCell => self.kind = LayoutObjectKind::TableCell,
                     DisplayType::DisplayNone => {
                         panic!("should not create a layout object for display:none")
                     }
                 }
             }
             NodeKind::Text(_) => self.kind = LayoutObjectKind::Text,
+            NodeKind::DocumentType(_) | NodeKind::Comment(_) => {
+                panic!("should not create a layout object for a DocumentType or Comment node")
+            }
         }
     }
 
@@ -186,6 +264,11 @@ impl LayoutObject {
         self.node.borrow().kind()
     }
 
+    /// Returns the range of the markup which produced the node of this layout object.
+    pub fn source_range(&self) -> Option<SourceRange> {
+        self.node.borrow().source_range()
+    }
+
     pub fn set_first_child(&mut self, first_child: Option<Rc<RefCell<LayoutObject>>>) {
         self.first_child = first_child;
     }
@@ -206,14 +289,241 @@ impl LayoutObject {
         self.style.clone()
     }
 
+    pub fn set_point(&mut self, point: LayoutPoint) {
+        self.point = point;
+    }
+
     pub fn point(&self) -> LayoutPoint {
         self.point
     }
 
+    pub fn set_size(&mut self, size: LayoutSize) {
+        self.size = size;
+    }
+
     pub fn size(&self) -> LayoutSize {
         self.size
     }
 
+    /// Returns the top-left point of the content box, which is inside the padding and borders.
+    pub fn content_point(&self) -> LayoutPoint {
+        let border = self.style.border_width() as i64;
+        LayoutPoint::new(
+            self.point.x() + self.style.padding_left() as i64 + border,
+            self.point.y() + self.style.padding_top() as i64 + border,
+        )
+    }
+
+    /// Returns the size of the content box, which is inside the padding and borders.
+    pub fn content_size(&self) -> LayoutSize {
+        let border = self.style.border_width() as i64;
+        LayoutSize::new(
+            self.size.width()
+                - self.style.padding_left() as i64
+                - self.style.padding_right() as i64
+                - border * 2,
+            self.size.height(),
+        )
+    }
+
+    /// Returns the top-left point and the size of the viewport of an iframe, which is inside its
+    /// borders.
+    pub fn iframe_viewport(&self) -> (LayoutPoint, LayoutSize) {
+        let border = self.style.border_width() as i64;
+        (
+            LayoutPoint::new(self.point.x() + border, self.point.y() + border),
+            LayoutSize::new(
+                self.size.width() - border * 2,
+                self.size.height() - border * 2,
+            ),
+        )
+    }
+
+    /// Returns the size of an iframe including its borders. The width and height attributes map
+    /// to the size of the viewport, which is 300x150 by default.
+    /// https://html.spec.whatwg.org/multipage/rendering.html#attributes-for-embedded-content-and-images
+    /// https://www.w3.org/TR/CSS22/visudet.html#inline-replaced-width
+    fn iframe_size(&self) -> LayoutSize {
+        let element = self.node.borrow().get_element();
+        let dimension = |name: &str, default: i64| -> i64 {
+            match element
+                .as_ref()
+                .and_then(|e| e.get_attribute(name))
+                .map(|v| v.trim().parse::<i64>())
+            {
+                Some(Ok(n)) if n >= 0 => n,
+                _ => default,
+            }
+        };
+        let border = self.style.border_width() as i64;
+        LayoutSize::new(
+            dimension("width", 300) + border * 2,
+            dimension("height", 150) + border * 2,
+        )
+    }
+
+    /// Returns the lines of text that represent the state of a form control.
+    /// - A text field shows its value padded to its `size` (or `cols` and `rows` for a
+    ///   textarea), with a caret when it's focused.
+    /// - A checkbox and a radio button show their checkedness.
+    /// - A button and a select element show their labels.
+    /// https://html.spec.whatwg.org/multipage/rendering.html#form-controls
+    fn form_control_lines(&self) -> Vec<String> {
+        let element = match self.node.borrow().get_element() {
+            Some(e) => e,
+            None => return Vec::new(),
+        };
+        let numeric_attribute = |name: &str, default: usize| -> usize {
+            match element
+                .get_attribute(name)
+                .map(|v| v.trim().parse::<usize>())
+            {
+                Some(Ok(n)) if n > 0 => n,
+                _ => default,
+            }
+        };
+        let caret = if focus::is_focused(&self.node) {
+            "|"
+        } else {
+            ""
+        };
+
+        match element.kind() {
+            ElementKind::Input => {
+                let value = form::value(&self.node);
+                match form::input_type(&element).as_str() {
+                    "checkbox" => {
+                        let mark = if form::checkedness(&self.node) {
+                            "[x]"
+                        } else {
+                            "[ ]"
+                        };
+                        vec![mark.to_string()]
+                    }
+                    "radio" => {
+                        let mark = if form::checkedness(&self.node) {
+                            "(*)"
+                        } else {
+                            "( )"
+                        };
+                        vec![mark.to_string()]
+                    }
+                    "submit" | "reset" | "button" | "image" => {
+                        let label = match element.get_attribute("value") {
+                            Some(v) => v,
+                            None if form::input_type(&element) == "reset" => "Reset".to_string(),
+                            None => "Submit".to_string(),
+                        };
+                        vec![format!("[{}]", label)]
+                    }
+                    t => {
+                        // "The size attribute gives the number of characters that, in a visual
+                        // rendering, the user agent is to allow the user to see while editing the
+                        // element's value." The default value is 20.
+                        let size = numeric_attribute("size", 20);
+                        let text = if t == "password" {
+                            "*".repeat(value.chars().count())
+                        } else {
+                            value
+                        };
+                        vec![pad_line(&format!("{}{}", text, caret), size)]
+                    }
+                }
+            }
+            ElementKind::Textarea => {
+                // The default values of `cols` and `rows` are 20 and 2.
+                let cols = numeric_attribute("cols", 20);
+                let rows = numeric_attribute("rows", 2);
+                let value = format!("{}{}", form::value(&self.node), caret);
+                let mut lines: Vec<String> =
+                    value.split('\n').map(|line| pad_line(line, cols)).collect();
+                while lines.len() < rows {
+                    lines.push(pad_line("", cols));
+                }
+                lines
+            }
+            ElementKind::Select => {
+                let label = match form::selected_option(&self.node) {
+                    Some(option) => form::label(&option),
+                    None => String::new(),
+                };
+                vec![format!("[{} v]", label)]
+            }
+            ElementKind::Button => {
+                vec![format!("[{}]", form::label(&self.node))]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Returns the width that the content takes when it's laid out without any line breaks
+    /// other than forced ones. This is used to decide the widths of table columns.
+    /// https://www.w3.org/TR/css-sizing-3/#max-content
+    pub fn max_content_width(&self) -> i64 {
+        if self.style.width() != 0.0 {
+            return self.style.width() as i64;
+        }
+
+        match self.kind {
+            LayoutObjectKind::Text => match self.node_kind() {
+                NodeKind::Text(t) => {
+                    CHAR_WIDTH
+                        * self.style.font_size().ratio()
+                        * collapse_white_space(&t).len() as i64
+                }
+                _ => 0,
+            },
+            LayoutObjectKind::LineBreak => 0,
+            LayoutObjectKind::FormControl => {
+                let max_len = self
+                    .form_control_lines()
+                    .iter()
+                    .map(|line| line.chars().count())
+                    .max()
+                    .unwrap_or(0);
+                CHAR_WIDTH * self.style.font_size().ratio() * max_len as i64
+            }
+            LayoutObjectKind::Iframe => self.iframe_size().width(),
+            _ => {
+                // Inline-level children on the same line are summed up, and the widest line or
+                // block-level child is the width of the content. Cells in a row are placed side
+                // by side.
+                let mut max_width = 0;
+                let mut line_width = 0;
+                let mut child = self.first_child();
+                while let Some(c) = child {
+                    let kind = c.borrow().kind();
+                    let width = c.borrow().max_content_width();
+                    if kind.is_block_level() && self.kind != LayoutObjectKind::TableRow {
+                        max_width = core::cmp::max(max_width, line_width);
+                        line_width = 0;
+                        let style = c.borrow().style();
+                        max_width = core::cmp::max(
+                            max_width,
+                            width + style.margin_left() as i64 + style.margin_right() as i64,
+                        );
+                    } else if kind == LayoutObjectKind::LineBreak {
+                        max_width = core::cmp::max(max_width, line_width);
+                        line_width = 0;
+                    } else {
+                        line_width += width;
+                    }
+                    child = c.borrow().next_sibling();
+                }
+                max_width = core::cmp::max(max_width, line_width);
+
+                if self.kind == LayoutObjectKind::Inline {
+                    max_width
+                } else {
+                    max_width
+                        + self.style.padding_left() as i64
+                        + self.style.padding_right() as i64
+                        + self.style.border_width() as i64 * 2
+                }
+            }
+        }
+    }
+
     /// https://www.w3.org/TR/css-cascade-4/#cascading
     /// Cascading yields the cascaded value. It takes takes an unordered list of declared values
     /// and outputs a single cascaded value for a property.
@@ -305,30 +615,60 @@ impl LayoutObject {
                 }
                 "margin-top" => {
                     if let ComponentValue::Number(value) = declaration.value {
-                        let m = self.style.margin();
+                        let m = self.style.margin_or_default(&self.node);
                         self.style
-                            .set_margin(BoxInfo::new(value, m.right(), m.bottom(), m.left()));
+                            .set_margin(BoxInfo::new(value, m.right(), m.left(), m.bottom()));
                     }
                 }
                 "margin-right" => {
                     if let ComponentValue::Number(value) = declaration.value {
-                        let m = self.style.margin();
+                        let m = self.style.margin_or_default(&self.node);
                         self.style
-                            .set_margin(BoxInfo::new(m.top(), value, m.bottom(), m.left()));
+                            .set_margin(BoxInfo::new(m.top(), value, m.left(), m.bottom()));
                     }
                 }
                 "margin-bottom" => {
                     if let ComponentValue::Number(value) = declaration.value {
-                        let m = self.style.margin();
+                        let m = self.style.margin_or_default(&self.node);
                         self.style
-                            .set_margin(BoxInfo::new(m.top(), m.right(), value, m.left()));
+                            .set_margin(BoxInfo::new(m.top(), m.right(), m.left(), value));
                     }
                 }
                 "margin-left" => {
                     if let ComponentValue::Number(value) = declaration.value {
-                        let m = self.style.margin();
+                        let m = self.style.margin_or_default(&self.node);
                         self.style
-                            .set_margin(BoxInfo::new(m.top(), m.right(), m.bottom(), value));
+                            .set_margin(BoxInfo::new(m.top(), m.right(), value, m.bottom()));
+                    }
+                }
+                "font-style" => {
+                    if let ComponentValue::Ident(value) = declaration.value {
+                        match FontStyle::from_str(&value) {
+                            Ok(font_style) => self.style.set_font_style(font_style),
+                            Err(e) => console_error(&self.browser, format!("{:?}", e)),
+                        }
+                    }
+                }
+                "font-weight" => {
+                    if let ComponentValue::Ident(value) = declaration.value {
+                        match FontWeight::from_str(&value) {
+                            Ok(font_weight) => self.style.set_font_weight(font_weight),
+                            Err(e) => console_error(&self.browser, format!("{:?}", e)),
+                        }
+                    }
+                }
+                "border-width" | "border" => {
+                    // TODO: support the style and the color in the `border` shorthand.
+                    if let ComponentValue::Number(value) = declaration.value {
+                        self.style.set_border_width(value);
+                    }
+                }
+                "border-color" => {
+                    if let ComponentValue::Ident(value) = &declaration.value {
+                        match Color::from_name(value) {
+                            Ok(color) => self.style.set_border_color(color),
+                            Err(e) => console_error(&self.browser, format!("{:?}", e)),
+                        }
                     }
                 }
                 // TODO: support padding
@@ -373,17 +713,30 @@ impl LayoutObject {
         }
 
         match self.kind() {
-            LayoutObjectKind::Block => {
+            // Table parts outside of a table are laid out as blocks.
+            LayoutObjectKind::Block
+            | LayoutObjectKind::TableRowGroup
+            | LayoutObjectKind::TableRow
+            | LayoutObjectKind::TableCell => {
                 // For a block element, consider the parent's width.
                 // TODO: add content_size to LayoutSize?
-                size.set_width(
-                    parent_size.width()
-                        - self.style.padding_left() as i64
-                        - self.style.padding_right() as i64,
-                );
+                if !is_width_set {
+                    size.set_width(
+                        parent_size.width()
+                            - self.style.margin_left() as i64
+                            - self.style.margin_right() as i64,
+                    );
+                }
+
+                if is_height_set {
+                    self.size = size;
+                    return;
+                }
 
                 // For height, sum up the height of all children next to the block element.
-                let mut height = 0;
+                // TODO: support margin collapsing.
+                // https://www.w3.org/TR/CSS2/box.html#collapsing-margins
+                let mut height = self.style.padding_top() as i64 + self.style.border_width() as i64;
                 let mut child = self.first_child();
                 let mut previous_child_kind = LayoutObjectKind::Block;
                 while child.is_some() {
@@ -392,17 +745,26 @@ impl LayoutObject {
                         None => panic!("first child should exist"),
                     };
 
-                    if previous_child_kind == LayoutObjectKind::Block
-                        || c.borrow().kind() == LayoutObjectKind::Block
+                    if previous_child_kind.is_block_level()
+                        || previous_child_kind == LayoutObjectKind::LineBreak
+                        || c.borrow().kind().is_block_level()
                     {
                         height += c.borrow().size.height();
                     }
+                    if c.borrow().kind().is_block_level() {
+                        height += c.borrow().style.margin_top() as i64
+                            + c.borrow().style.margin_bottom() as i64;
+                    }
 
                     previous_child_kind = c.borrow().kind();
                     child = c.borrow().next_sibling();
                 }
-                size.set_height(height);
+                size.set_height(
+                    height + self.style.padding_bottom() as i64 + self.style.border_width() as i64,
+                );
             }
+            // The size of a table is decided by the table layout algorithm.
+            LayoutObjectKind::Table => return,
             LayoutObjectKind::Inline => {
                 // Sum up the width and height of all children directly under this element.
                 let mut width = 0;
@@ -423,22 +785,45 @@ impl LayoutObject {
                 size.set_width(width);
                 size.set_height(height);
             }
+            LayoutObjectKind::LineBreak => {
+                // A line break has the height of an empty line.
+                size.set_height(CHAR_HEIGHT_WITH_PADDING * self.style.font_size().ratio());
+            }
+            LayoutObjectKind::FormControl => {
+                let ratio = self.style.font_size().ratio();
+                let lines = self.form_control_lines();
+                if !is_width_set {
+                    size.set_width(self.max_content_width());
+                }
+                if !is_height_set {
+                    size.set_height(CHAR_HEIGHT_WITH_PADDING * ratio * lines.len() as i64);
+                }
+            }
+            LayoutObjectKind::Iframe => {
+                let iframe_size = self.iframe_size();
+                if !is_width_set {
+                    size.set_width(iframe_size.width());
+                }
+                if !is_height_set {
+                    size.set_height(iframe_size.height());
+                }
+            }
             LayoutObjectKind::Text => {
                 if let NodeKind::Text(t) = self.node_kind() {
-                    let ratio = match self.style.font_size() {
-                        FontSize::Medium => 1,
-                        FontSize::XLarge => 2,
-                        FontSize::XXLarge => 3,
+                    let ratio = self.style.font_size().ratio();
+                    let width = CHAR_WIDTH * ratio * collapse_white_space(&t).len() as i64;
+                    // The text wraps at the width of the containing block.
+                    let max_width = if parent_size.width() > 0 {
+                        parent_size.width()
+                    } else {
+                        CONTENT_AREA_WIDTH
                     };
-                    let width = CHAR_WIDTH * ratio * t.len() as i64;
-                    if width > CONTENT_AREA_WIDTH {
+                    if width > max_width {
                         // The text is multiple lines.
-                        size.set_width(CONTENT_AREA_WIDTH);
-                        let line_num = if width.wrapping_rem(CONTENT_AREA_WIDTH) == 0 {
-                            width.wrapping_div(CONTENT_AREA_WIDTH)
-                        } else {
-                            width.wrapping_div(CONTENT_AREA_WIDTH) + 1
-                        };
+                        size.set_width(max_width);
+                        let line_num =
+                            split_text(collapse_white_space(&t), CHAR_WIDTH * ratio, max_width)
+                                .len() as i64;
                         size.set_height(CHAR_HEIGHT_WITH_PADDING * ratio * line_num);
                     } else {
                         // The text is signle line.
@@ -462,22 +847,53 @@ impl LayoutObject {
         previous_sibling_kind: LayoutObjectKind,
         previous_sibling_point: Option<LayoutPoint>,
         previous_sibling_size: Option<LayoutSize>,
+        previous_sibling_margin_bottom: f64,
     ) {
         let mut point = LayoutPoint::new(0, 0);
 
         match (self.kind(), previous_sibling_kind) {
             // If a current node or a sibling node is a block element, grow along the Y-axis direction.
-            (LayoutObjectKind::Block, _) | (_, LayoutObjectKind::Block) => {
+            (kind, previous_kind) if kind.is_block_level() || previous_kind.is_block_level() => {
                 if let (Some(size), Some(pos)) = (previous_sibling_size, previous_sibling_point) {
                     // TODO: consider padding of the previous sibling.
-                    point.set_y(pos.y() + size.height() + self.style.margin_top() as i64);
+                    point.set_y(
+                        pos.y()
+                            + size.height()
+                            + previous_sibling_margin_bottom as i64
+                            + self.style.margin_top() as i64,
+                    );
+                } else {
+                    point.set_y(parent_point.y() + self.style.margin_top() as i64);
+                }
+                point.set_x(parent_point.x() + self.style.margin_left() as i64);
+            }
+            // The content after a line break starts on a new line.
+            (_, LayoutObjectKind::LineBreak) => {
+                if let (Some(size), Some(pos)) = (previous_sibling_size, previous_sibling_point) {
+                    point.set_y(pos.y() + size.height());
                 } else {
                     point.set_y(parent_point.y());
                 }
                 point.set_x(parent_point.x());
             }
+            // A line break is placed at the end of the current line.
+            (LayoutObjectKind::LineBreak, _) => {
+                if let (Some(size), Some(pos)) = (previous_sibling_size, previous_sibling_point) {
+                    point.set_x(pos.x() + size.width());
+                    point.set_y(pos.y());
+                } else {
+                    point.set_x(parent_point.x());
+                    point.set_y(parent_point.y());
+                }
+            }
             // If both a current node and a sibling node are inline elements, grow along the X-axis direction.
-            (LayoutObjectKind::Inline, LayoutObjectKind::Inline) => {
+            // A form control and an iframe are placed next to the previous content on the same
+            // line.
+            (LayoutObjectKind::Inline, LayoutObjectKind::Inline)
+            | (LayoutObjectKind::FormControl, _)
+            | (_, LayoutObjectKind::FormControl)
+            | (LayoutObjectKind::Iframe, _)
+            | (_, LayoutObjectKind::Iframe) => {
                 if let (Some(size), Some(pos)) = (previous_sibling_size, previous_sibling_point) {
                     // TODO: consider padding of the previous sibling.
                     point.set_x(pos.x() + size.width() + self.style.margin_left() as i64);
@@ -497,34 +913,47 @@ impl LayoutObject {
     }
 
     pub fn is_node_selected(&self, selector: &Selector) -> bool {
-        match &self.node_kind() {
-            NodeKind::Element(e) => match selector {
-                Selector::TypeSelector(type_name) => {
-                    if e.kind().to_string() == *type_name {
-                        return true;
-                    }
-                    false
-                }
-                Selector::ClassSelector(class_name) => {
-                    for attr in &e.attributes() {
-                        if attr.name() == "class" && attr.value() == *class_name {
-                            return true;
-                        }
-                    }
-                    false
-                }
-                Selector::IdSelector(id_name) => {
-                    for attr in &e.attributes() {
-                        if attr.name() == "id" && attr.value() == *id_name {
-                            return true;
-                        }
-                    }
-                    false
-                }
-                Selector::UnknownSelector => false,
-            },
-            _ => false,
+        matches_selector(selector, &self.node.borrow())
+    }
+
+    /// Returns four rectangles for the top, bottom, left and right borders.
+    /// https://www.w3.org/TR/css-backgrounds-3/#borders
+    fn paint_borders(&self) -> Vec<DisplayItem> {
+        let width = self.style.border_width() as i64;
+        if width <= 0 {
+            return vec![];
+        }
+
+        let mut style = self.style();
+        style.set_background_color(self.style.border_color());
+        let (x, y) = (self.point.x(), self.point.y());
+        let (w, h) = (self.size.width(), self.size.height());
+        [
+            (x, y, w, width),
+            (x, y + h - width, w, width),
+            (x, y, width, h),
+            (x + w - width, y, width, h),
+        ]
+        .iter()
+        .map(|(x, y, w, h)| DisplayItem::Rect {
+            style: style.clone(),
+            layout_point: LayoutPoint::new(*x, *y),
+            layout_size: LayoutSize::new(*w, *h),
+        })
+        .collect()
+    }
+
+    /// Returns the style to paint this object with. The focused element and its content are
+    /// highlighted as a focus indicator, which shows where the key input goes.
+    /// https://www.w3.org/TR/selectors-4/#the-focus-pseudo
+    fn paint_style(&self) -> ComputedStyle {
+        let mut style = self.style();
+        if focus::is_in_focused_element(&self.node) {
+            style.set_background_color(
+                Color::from_name("lightgray").expect("lightgray should be a valid color"),
+            );
         }
+        style
     }
 
     /// https://source.chromium.org/chromium/chromium/src/+/main:third_party/blink/renderer/core/layout/layout_object.h;drc=0e9a0b6e9bb6ec59521977eec805f5d0bca833e0;bpv=1;bpt=1;l=2377
@@ -534,13 +963,19 @@ impl LayoutObject {
         }
 
         match self.kind {
-            LayoutObjectKind::Block => {
+            LayoutObjectKind::Block
+            | LayoutObjectKind::Table
+            | LayoutObjectKind::TableRowGroup
+            | LayoutObjectKind::TableRow
+            | LayoutObjectKind::TableCell => {
                 if let NodeKind::Element(_e) = self.node_kind() {
-                    return vec![DisplayItem::Rect {
-                        style: self.style(),
+                    let mut v = vec![DisplayItem::Rect {
+                        style: self.paint_style(),
                         layout_point: self.point(),
                         layout_size: self.size(),
                     }];
+                    v.extend(self.paint_borders());
+                    return v;
                 }
             }
             LayoutObjectKind::Inline => {
@@ -558,27 +993,51 @@ impl LayoutObject {
                     }
                 }
             }
+            // A line break paints nothing. It moves the following content to the next line.
+            LayoutObjectKind::LineBreak => {}
+            // The content of an iframe is painted by the child frame. See `Page::paint_tree`.
+            LayoutObjectKind::Iframe => {
+                let mut v = vec![DisplayItem::Rect {
+                    style: self.style(),
+                    layout_point: self.point(),
+                    layout_size: self.size(),
+                }];
+                v.extend(self.paint_borders());
+                return v;
+            }
+            LayoutObjectKind::FormControl => {
+                let style = self.paint_style();
+                let mut v = vec![DisplayItem::Rect {
+                    style: style.clone(),
+                    layout_point: self.point(),
+                    layout_size: self.size(),
+                }];
+
+                let ratio = self.style.font_size().ratio();
+                for (i, line) in self.form_control_lines().into_iter().enumerate() {
+                    v.push(DisplayItem::Text {
+                        text: line,
+                        style: style.clone(),
+                        layout_point: LayoutPoint::new(
+                            self.point().x(),
+                            self.point().y() + CHAR_HEIGHT_WITH_PADDING * ratio * i as i64,
+                        ),
+                    });
+                }
+                return v;
+            }
             LayoutObjectKind::Text => {
                 if let NodeKind::Text(t) = self.node_kind() {
                     let mut v = vec![];
 
-                    let ratio = match self.style.font_size() {
-                        FontSize::Medium => 1,
-                        FontSize::XLarge => 2,
-                        FontSize::XXLarge => 3,
-                    };
-                    let plain_text = t
-                        .replace("\n", " ")
-                        .split(' ')
-                        .filter(|s| !s.is_empty())
-                        .collect::<Vec<_>>()
-                        .join(" ");
-                    let lines = split_text(plain_text, CHAR_WIDTH * ratio);
+                    let ratio = self.style.font_size().ratio();
+                    let plain_text = collapse_white_space(&t);
+                    let lines = split_text(plain_text, CHAR_WIDTH * ratio, self.size().width());
                     let mut i = 0;
                     for line in lines {
                         let item = DisplayItem::Text {
                             text: line,
-                            style: self.style(),
+                            style: self.paint_style(),
                             layout_point: LayoutPoint::new(
                                 self.point().x(),
                                 self.point().y() + CHAR_HEIGHT_WITH_PADDING * i,
@@ -14,6 +14,9 @@ use crate::renderer::layout::layout_object::LayoutObject;
 use crate::renderer::layout::layout_object::LayoutObjectKind;
 use crate::renderer::layout::layout_point::LayoutPoint;
 use crate::renderer::layout::layout_size::LayoutSize;
+use crate::renderer::layout::table::layout_table;
+use crate::renderer::layout::table::place_table;
+use crate::renderer::source::SourceRange;
 use alloc::rc::{Rc, Weak};
 use alloc::vec::Vec;
 use core::cell::RefCell;
@@ -40,7 +43,20 @@ fn build_layout_tree(
     }
 
     if let Some(n) = target_node {
-        let original_first_child = n.borrow().first_child();
+        // The children of a form control are not rendered as boxes. Its content is painted
+        // from its state instead. The children of an iframe are not rendered either, because its
+        // content is the document of the child frame.
+        let original_first_child = match &layout_object {
+            Some(obj)
+                if matches!(
+                    obj.borrow().kind(),
+                    LayoutObjectKind::FormControl | LayoutObjectKind::Iframe
+                ) =>
+            {
+                None
+            }
+            _ => n.borrow().first_child(),
+        };
         let original_next_sibling = n.borrow().next_sibling();
         let mut first_child = build_layout_tree(
             browser.clone(),
@@ -117,6 +133,9 @@ fn build_layout_tree(
 #[derive(Debug, Clone)]
 pub struct LayoutView {
     root: Option<Rc<RefCell<LayoutObject>>>,
+    /// The width of the viewport, which is the width of the content area for a top-level page and
+    /// the width of the iframe for a child frame.
+    viewport_width: i64,
 }
 
 impl LayoutView {
@@ -124,6 +143,15 @@ impl LayoutView {
         browser: Weak<RefCell<Browser>>,
         root: Rc<RefCell<Node>>,
         cssom: &StyleSheet,
+    ) -> Self {
+        Self::with_viewport_width(browser, root, cssom, CONTENT_AREA_WIDTH)
+    }
+
+    pub fn with_viewport_width(
+        browser: Weak<RefCell<Browser>>,
+        root: Rc<RefCell<Node>>,
+        cssom: &StyleSheet,
+        viewport_width: i64,
     ) -> Self {
         // A layout object should be created for a flow content.
         // https://html.spec.whatwg.org/multipage/dom.html#flow-content-2
@@ -131,6 +159,7 @@ impl LayoutView {
 
         let mut tree = Self {
             root: build_layout_tree(browser, &body_root, &None, cssom),
+            viewport_width,
         };
 
         tree.update_layout();
@@ -138,15 +167,32 @@ impl LayoutView {
         tree
     }
 
-    fn calculate_node_size(node: &Option<Rc<RefCell<LayoutObject>>>, parent_size: LayoutSize) {
+    pub(crate) fn calculate_node_size(
+        node: &Option<Rc<RefCell<LayoutObject>>>,
+        parent_size: LayoutSize,
+    ) {
         if let Some(n) = node {
-            // For block elements, we should layout the size before calling children.
-            if n.borrow().kind() == LayoutObjectKind::Block {
-                n.borrow_mut().compute_size(parent_size);
-            }
+            let kind = n.borrow().kind();
+            if kind == LayoutObjectKind::Table {
+                // A table decides the sizes of its rows and cells by itself.
+                layout_table(n, parent_size);
+            } else {
+                // For block elements, we should layout the size before calling children.
+                if kind.is_block_level() {
+                    n.borrow_mut().compute_size(parent_size);
+                }
 
-            let first_child = n.borrow().first_child();
-            Self::calculate_node_size(&first_child, n.borrow().size());
+                // Children of a block element are laid out in its content box, which is inside
+                // the padding and borders. Children of an inline element are laid out in the
+                // same containing block as the inline element.
+                let content_size = if kind.is_block_level() {
+                    n.borrow().content_size()
+                } else {
+                    parent_size
+                };
+                let first_child = n.borrow().first_child();
+                Self::calculate_node_size(&first_child, content_size);
+            }
 
             let next_sibling = n.borrow().next_sibling();
             Self::calculate_node_size(&next_sibling, parent_size);
@@ -157,12 +203,13 @@ impl LayoutView {
         }
     }
 
-    fn calculate_node_position(
+    pub(crate) fn calculate_node_position(
         node: &Option<Rc<RefCell<LayoutObject>>>,
         parent_point: LayoutPoint,
         previous_sibling_kind: LayoutObjectKind,
         previous_sibling_point: Option<LayoutPoint>,
         previous_sibling_size: Option<LayoutSize>,
+        previous_sibling_margin_bottom: f64,
     ) {
         if let Some(n) = node {
             n.borrow_mut().compute_position(
@@ -170,31 +217,47 @@ impl LayoutView {
                 previous_sibling_kind,
                 previous_sibling_point,
                 previous_sibling_size,
+                previous_sibling_margin_bottom,
             );
 
-            let first_child = n.borrow().first_child();
-            Self::calculate_node_position(
-                &first_child,
-                n.borrow().point(),
-                LayoutObjectKind::Block,
-                None,
-                None,
-            );
+            if n.borrow().kind() == LayoutObjectKind::Table {
+                place_table(n);
+            } else {
+                // Children are placed in the content box, which is inside the padding and
+                // borders.
+                let content_point = n.borrow().content_point();
+                let first_child = n.borrow().first_child();
+                Self::calculate_node_position(
+                    &first_child,
+                    content_point,
+                    LayoutObjectKind::Block,
+                    None,
+                    None,
+                    0.0,
+                );
+            }
 
+            let style = n.borrow().style();
             let next_sibling = n.borrow().next_sibling();
+            let margin_bottom = if n.borrow().kind().is_block_level() {
+                style.margin_bottom()
+            } else {
+                0.0
+            };
             Self::calculate_node_position(
                 &next_sibling,
                 parent_point,
                 n.borrow().kind(),
                 Some(n.borrow().point()),
                 Some(n.borrow().size()),
+                margin_bottom,
             );
         }
     }
 
     /// Calculate the layout point.
     fn update_layout(&mut self) {
-        Self::calculate_node_size(&self.root, LayoutSize::new(CONTENT_AREA_WIDTH, 0));
+        Self::calculate_node_size(&self.root, LayoutSize::new(self.viewport_width, 0));
 
         Self::calculate_node_position(
             &self.root,
@@ -202,6 +265,7 @@ impl LayoutView {
             LayoutObjectKind::Block,
             None,
             None,
+            0.0,
         );
     }
 
@@ -269,12 +333,52 @@ impl LayoutView {
     pub fn find_node_by_position(&self, position: (i64, i64)) -> Option<Rc<RefCell<LayoutObject>>> {
         Self::find_node_by_position_internal(&self.root(), position)
     }
+
+    fn find_layout_object_internal(
+        object: &Option<Rc<RefCell<LayoutObject>>>,
+        node: &Rc<RefCell<Node>>,
+    ) -> Option<Rc<RefCell<LayoutObject>>> {
+        let o = object.as_ref()?;
+        if Rc::ptr_eq(&o.borrow().node(), node) {
+            return Some(o.clone());
+        }
+        let first_child = o.borrow().first_child();
+        if let Some(found) = Self::find_layout_object_internal(&first_child, node) {
+            return Some(found);
+        }
+        let next_sibling = o.borrow().next_sibling();
+        Self::find_layout_object_internal(&next_sibling, node)
+    }
+
+    /// Returns the LayoutObject created for `node`. None if `node` is not rendered.
+    pub fn find_layout_object(
+        &self,
+        node: &Rc<RefCell<Node>>,
+    ) -> Option<Rc<RefCell<LayoutObject>>> {
+        Self::find_layout_object_internal(&self.root, node)
+    }
+
+    /// Returns the range of the markup which produced the content placed on `position`. If the
+    /// node placed on `position` isn't produced from the markup directly, e.g. an implied element,
+    /// the range of the nearest ancestor which has one is returned.
+    pub fn find_source_range_by_position(&self, position: (i64, i64)) -> Option<SourceRange> {
+        let mut node = Some(self.find_node_by_position(position)?.borrow().node());
+        while let Some(n) = node {
+            if let Some(range) = n.borrow().source_range() {
+                return Some(range);
+            }
+            node = n.borrow().parent().upgrade();
+        }
+        None
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::alloc::string::ToString;
+    use crate::constants::CHAR_HEIGHT_WITH_PADDING;
+    use crate::constants::CHAR_WIDTH;
     use crate::renderer::css::cssom::CssParser;
     use crate::renderer::css::token::CssTokenizer;
     use crate::renderer::dom::api::get_style_content;
@@ -282,7 +386,12 @@ mod tests {
     use crate::renderer::dom::node::NodeKind;
     use crate::renderer::html::parser::HtmlParser;
     use crate::renderer::html::token::HtmlTokenizer;
+    use crate::renderer::layout::color::Color;
+    use crate::renderer::layout::computed_style::FontSize;
+    use crate::renderer::layout::computed_style::FontStyle;
+    use crate::renderer::layout::computed_style::FontWeight;
     use alloc::string::String;
+    use alloc::vec;
 
     fn create_layout_view(html: String) -> LayoutView {
         let browser = Browser::new();
@@ -454,4 +563,280 @@ mod tests {
             .next_sibling()
             .is_none());
     }
+
+    #[test]
+    fn test_complex_selectors() {
+        let html = "<html><head><style>p.hidden, div p { display: none; }</style></head><body><p class=hidden>a</p><div><p>b</p></div><p>c</p></body></html>"
+            .to_string();
+        let layout_view = create_layout_view(html);
+
+        // The first p and the p in the div are not rendered.
+        let root = layout_view.root();
+        let div = root.expect("root should exist").borrow().first_child();
+        assert_eq!(
+            NodeKind::Element(Element::new("div", Vec::new())),
+            div.clone()
+                .expect("div node should exist")
+                .borrow()
+                .node_kind()
+        );
+        assert!(div
+            .clone()
+            .expect("div node should exist")
+            .borrow()
+            .first_child()
+            .is_none());
+        let p = div.expect("div node should exist").borrow().next_sibling();
+        assert_eq!(
+            NodeKind::Element(Element::new("p", Vec::new())),
+            p.expect("p node should exist").borrow().node_kind()
+        );
+    }
+
+    #[test]
+    fn test_unknown_element_type_selector() {
+        let html = "<html><head><style>my-card{display:block;}</style></head><body><my-card>text</my-card></body></html>"
+            .to_string();
+        let layout_view = create_layout_view(html);
+
+        let root = layout_view.root();
+        let card = root.expect("root should exist").borrow().first_child();
+        assert_eq!(
+            LayoutObjectKind::Block,
+            card.clone()
+                .expect("my-card node should exist")
+                .borrow()
+                .kind()
+        );
+        assert_eq!(
+            NodeKind::Element(Element::new("my-card", Vec::new())),
+            card.clone()
+                .expect("my-card node should exist")
+                .borrow()
+                .node_kind()
+        );
+        let text = card
+            .expect("my-card node should exist")
+            .borrow()
+            .first_child();
+        assert_eq!(
+            LayoutObjectKind::Text,
+            text.expect("text node should exist").borrow().kind()
+        );
+    }
+
+    #[test]
+    fn test_line_break() {
+        let html = "<html><head></head><body>a<br>b<br><br>c</body></html>".to_string();
+        let layout_view = create_layout_view(html);
+
+        let texts: Vec<(String, i64)> = layout_view
+            .paint()
+            .into_iter()
+            .filter_map(|item| match item {
+                DisplayItem::Text {
+                    text, layout_point, ..
+                } => Some((text, layout_point.y())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(3, texts.len());
+        // "b" is on the next line of "a", and an empty line exists between "b" and "c".
+        assert!(texts[0].1 < texts[1].1);
+        assert_eq!(texts[1].1 - texts[0].1, (texts[2].1 - texts[1].1) / 2);
+    }
+
+    #[test]
+    fn test_horizontal_rule() {
+        let html = "<html><head></head><body><p>a</p><hr><p>b</p></body></html>".to_string();
+        let layout_view = create_layout_view(html);
+
+        let rules: Vec<DisplayItem> = layout_view
+            .paint()
+            .into_iter()
+            .filter(|item| match item {
+                DisplayItem::Rect { style, .. } => style.background_color() != Color::white(),
+                _ => false,
+            })
+            .collect();
+        assert_eq!(1, rules.len());
+        if let DisplayItem::Rect { layout_size, .. } = &rules[0] {
+            assert_eq!(2, layout_size.height());
+            assert!(layout_size.width() > 0);
+        }
+    }
+
+    #[test]
+    fn test_default_style() {
+        let html = "<html><head><title>t</title></head><body><h3><em>a</em></h3><blockquote>b</blockquote></body></html>".to_string();
+        let layout_view = create_layout_view(html);
+
+        let root = layout_view.root().expect("root should exist");
+        let h3 = root.borrow().first_child().expect("h3 should exist");
+        assert_eq!(LayoutObjectKind::Block, h3.borrow().kind());
+        assert_eq!(FontWeight::Bold, h3.borrow().style().font_weight());
+        assert_eq!(FontSize::Large, h3.borrow().style().font_size());
+
+        let em = h3.borrow().first_child().expect("em should exist");
+        assert_eq!(LayoutObjectKind::Inline, em.borrow().kind());
+        assert_eq!(FontStyle::Italic, em.borrow().style().font_style());
+        // Inherited from <h3>.
+        assert_eq!(FontWeight::Bold, em.borrow().style().font_weight());
+
+        let blockquote = h3.borrow().next_sibling().expect("blockquote should exist");
+        assert_eq!(40.0, blockquote.borrow().style().margin_left());
+        assert_eq!(
+            root.borrow().point().x() + 40,
+            blockquote.borrow().point().x()
+        );
+        assert!(
+            blockquote.borrow().point().y()
+                >= h3.borrow().point().y() + h3.borrow().size().height() + 16
+        );
+    }
+
+    /// Returns the texts painted by `layout_view` with their points.
+    fn painted_texts(layout_view: &LayoutView) -> Vec<(String, LayoutPoint)> {
+        layout_view
+            .paint()
+            .into_iter()
+            .filter_map(|item| match item {
+                DisplayItem::Text {
+                    text, layout_point, ..
+                } => Some((text, layout_point)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_table() {
+        let html = "<table><tr><td>a</td><td>bbb</td></tr><tr><td>cc</td><td>d</td></tr></table>"
+            .to_string();
+        let layout_view = create_layout_view(html);
+
+        let root = layout_view.root().expect("root should exist");
+        let table = root.borrow().first_child().expect("table should exist");
+        assert_eq!(LayoutObjectKind::Table, table.borrow().kind());
+
+        let texts = painted_texts(&layout_view);
+        assert_eq!(4, texts.len());
+        let (a, bbb, cc, d) = (texts[0].1, texts[1].1, texts[2].1, texts[3].1);
+        // Cells in the same column have the same x, and the column is as wide as the widest
+        // cell.
+        assert_eq!(a.x(), cc.x());
+        assert_eq!(bbb.x(), d.x());
+        assert!(bbb.x() >= cc.x() + 2 * CHAR_WIDTH);
+        // Cells in the same row have the same y.
+        assert_eq!(a.y(), bbb.y());
+        assert_eq!(cc.y(), d.y());
+        assert!(cc.y() >= a.y() + CHAR_HEIGHT_WITH_PADDING);
+        // The table is as wide as its contents.
+        assert!(table.borrow().size().width() < CONTENT_AREA_WIDTH);
+    }
+
+    #[test]
+    fn test_table_span() {
+        let html = "<table><tr><td colspan=2>aaaaaaaa</td><td rowspan=2>b</td></tr><tr><td>c</td><td>d</td></tr></table>"
+            .to_string();
+        let layout_view = create_layout_view(html);
+
+        let texts = painted_texts(&layout_view);
+        assert_eq!(4, texts.len());
+        let (a, b, c, d) = (texts[0].1, texts[1].1, texts[2].1, texts[3].1);
+        assert_eq!(a.x(), c.x());
+        // "d" is in the second column because "aaaaaaaa" spans two columns, and "b" is in the
+        // third column.
+        assert!(d.x() > c.x());
+        assert!(b.x() >= a.x() + 8 * CHAR_WIDTH);
+        assert!(c.y() > a.y());
+    }
+
+    #[test]
+    fn test_table_border() {
+        let html = "<table border=1 cellpadding=3><tr><td>a</td></tr></table>".to_string();
+        let layout_view = create_layout_view(html);
+
+        let root = layout_view.root().expect("root should exist");
+        let table = root.borrow().first_child().expect("table should exist");
+        assert_eq!(1.0, table.borrow().style().border_width());
+
+        // The table and the cell have four borders each.
+        let gray = Color::from_name("gray").expect("gray should exist");
+        let borders = layout_view
+            .paint()
+            .into_iter()
+            .filter(|item| match item {
+                DisplayItem::Rect { style, .. } => style.background_color() == gray,
+                _ => false,
+            })
+            .count();
+        assert_eq!(8, borders);
+
+        // The text is inside the table border (1px), the border spacing (2px), the cell border
+        // (1px) and the cell padding (3px).
+        let texts = painted_texts(&layout_view);
+        assert_eq!(table.borrow().point().x() + 7, texts[0].1.x());
+        assert_eq!(table.borrow().point().y() + 7, texts[0].1.y());
+    }
+
+    #[test]
+    fn test_form_controls() {
+        let html = "<form><input name=q value=saba size=6><input type=hidden name=h><input type=checkbox checked><select><option>a<option selected>b</select><textarea rows=2 cols=3>x</textarea><button>Go</button></form>".to_string();
+        let layout_view = create_layout_view(html);
+
+        // A hidden input is not rendered, and the content of each control is its state.
+        let texts: Vec<String> = painted_texts(&layout_view)
+            .into_iter()
+            .map(|(text, _)| text)
+            .collect();
+        assert_eq!(vec!["saba__", "[x]", "[b v]", "x__", "___", "[Go]"], texts);
+
+        // The controls are placed on the same line.
+        let points: Vec<LayoutPoint> = painted_texts(&layout_view)
+            .into_iter()
+            .map(|(_, point)| point)
+            .collect();
+        assert_eq!(points[0].y(), points[1].y());
+        assert_eq!(points[0].x() + 6 * CHAR_WIDTH, points[1].x());
+        assert_eq!(points[3].y() + CHAR_HEIGHT_WITH_PADDING, points[4].y());
+    }
+
+    #[test]
+    fn test_find_source_range_by_position() {
+        let html = "<p>first</p>\n<p>second <a href=x>link</a></p>".to_string();
+        let layout_view = create_layout_view(html.clone());
+        let source_at = |position: (i64, i64)| {
+            let range = layout_view
+                .find_source_range_by_position(position)
+                .expect("a source range should exist");
+            (
+                html[range.start()..range.end()].to_string(),
+                range.start_position().to_string(),
+            )
+        };
+
+        let texts = painted_texts(&layout_view);
+        let point = |i: usize| (texts[i].1.x() + 1, texts[i].1.y() + 1);
+        assert_eq!(
+            ("first".to_string(), "1:4".to_string()),
+            source_at(point(0))
+        );
+        assert_eq!(
+            ("link".to_string(), "2:21".to_string()),
+            source_at(point(2))
+        );
+
+        // The body element is implied, so nothing in the markup produced it.
+        let body = layout_view.root().expect("root should exist");
+        assert_eq!(None, body.borrow().source_range());
+        let p = body.borrow().first_child().expect("p should exist");
+        assert_eq!(
+            "<p>first</p>".to_string(),
+            p.borrow()
+                .source_range()
+                .map(|r| html[r.start()..r.end()].to_string())
+                .expect("p should have a source range")
+        );
+    }
 }
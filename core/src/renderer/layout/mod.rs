@@ -4,3 +4,4 @@ pub mod layout_object;
 pub mod layout_point;
 pub mod layout_size;
 pub mod layout_view;
+pub mod table;
@@ -0,0 +1,541 @@
+//! https://www.w3.org/TR/CSS22/tables.html
+//! https://html.spec.whatwg.org/multipage/tables.html#table-processing-model
+//!
+//! The size pass of the table layout decides the sizes of all table parts and their points
+//! relative to the table. The position pass converts the relative points to absolute ones once
+//! the point of the table is decided.
+
+use crate::renderer::dom::node::Node;
+use crate::renderer::layout::layout_object::LayoutObject;
+use crate::renderer::layout::layout_object::LayoutObjectKind;
+use crate::renderer::layout::layout_point::LayoutPoint;
+use crate::renderer::layout::layout_size::LayoutSize;
+use crate::renderer::layout::layout_view::LayoutView;
+use alloc::rc::Rc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::cmp::max;
+
+/// https://html.spec.whatwg.org/multipage/tables.html#concept-cell
+#[derive(Debug, Clone)]
+struct Cell {
+    object: Rc<RefCell<LayoutObject>>,
+    row: usize,
+    column: usize,
+    rowspan: usize,
+    colspan: usize,
+}
+
+/// https://html.spec.whatwg.org/multipage/tables.html#concept-table
+#[derive(Debug, Clone)]
+struct Table {
+    /// Children that are not rows or row groups, such as captions. They are laid out as blocks
+    /// at the top of the table.
+    captions: Vec<Rc<RefCell<LayoutObject>>>,
+    /// Row groups and the range of the rows in each group.
+    row_groups: Vec<(Rc<RefCell<LayoutObject>>, usize, usize)>,
+    rows: Vec<Rc<RefCell<LayoutObject>>>,
+    cells: Vec<Cell>,
+    column_count: usize,
+}
+
+/// Returns the value of the attribute `name` parsed by the rules for parsing non-negative
+/// integers.
+/// https://html.spec.whatwg.org/multipage/common-microsyntaxes.html#rules-for-parsing-non-negative-integers
+fn integer_attribute(node: &Rc<RefCell<Node>>, name: &str) -> Option<usize> {
+    node.borrow()
+        .get_element()?
+        .get_attribute(name)?
+        .trim()
+        .parse::<usize>()
+        .ok()
+}
+
+/// Returns the children of `object` whose kind is `kind`.
+fn children_of_kind(
+    object: &Rc<RefCell<LayoutObject>>,
+    kind: LayoutObjectKind,
+) -> Vec<Rc<RefCell<LayoutObject>>> {
+    let mut children = Vec::new();
+    let mut child = object.borrow().first_child();
+    while let Some(c) = child {
+        if c.borrow().kind() == kind {
+            children.push(c.clone());
+        }
+        child = c.borrow().next_sibling();
+    }
+    children
+}
+
+impl Table {
+    /// https://html.spec.whatwg.org/multipage/tables.html#forming-a-table
+    fn new(table: &Rc<RefCell<LayoutObject>>) -> Self {
+        let mut captions = Vec::new();
+        let mut row_groups = Vec::new();
+        let mut rows = Vec::new();
+
+        let mut child = table.borrow().first_child();
+        while let Some(c) = child {
+            match c.borrow().kind() {
+                LayoutObjectKind::TableRowGroup => {
+                    let start = rows.len();
+                    rows.extend(children_of_kind(&c, LayoutObjectKind::TableRow));
+                    row_groups.push((c.clone(), start, rows.len()));
+                }
+                LayoutObjectKind::TableRow => rows.push(c.clone()),
+                _ => captions.push(c.clone()),
+            }
+            child = c.borrow().next_sibling();
+        }
+
+        // The index after the last row in the row group that each row belongs to.
+        let mut group_ends: Vec<usize> = vec![rows.len(); rows.len()];
+        for (_, start, end) in &row_groups {
+            for group_end in group_ends.iter_mut().take(*end).skip(*start) {
+                *group_end = *end;
+            }
+        }
+
+        // https://html.spec.whatwg.org/multipage/tables.html#algorithm-for-processing-rows
+        let mut cells = Vec::new();
+        let mut slots: Vec<Vec<bool>> = vec![Vec::new(); rows.len()];
+        for (row, r) in rows.iter().enumerate() {
+            let mut column = 0;
+            for object in children_of_kind(r, LayoutObjectKind::TableCell) {
+                // "While xcurrent is less than xwidth and the slot with coordinate (xcurrent,
+                // ycurrent) already has a cell assigned to it, increase xcurrent by 1."
+                while slots[row].get(column) == Some(&true) {
+                    column += 1;
+                }
+
+                let node = object.borrow().node();
+                // "If the current cell has a colspan attribute, then parse that attribute's value,
+                // and let colspan be the result. If parsing that value failed, or returned zero,
+                // or if the attribute is absent, then let colspan be 1, instead. If colspan is
+                // greater than 1000, let it be 1000 instead."
+                let colspan = match integer_attribute(&node, "colspan") {
+                    Some(0) | None => 1,
+                    Some(n) => n.min(1000),
+                };
+                // "If the current cell has a rowspan attribute, then parse that attribute's value,
+                // and let rowspan be the result. If parsing that value failed or if the attribute
+                // is absent, then let rowspan be 1, instead. If rowspan is greater than 65534, let
+                // it be 65534 instead."
+                // "If rowspan is zero and the td element's node document is not set to quirks
+                // mode, then let cell grows downward be true, and set rowspan to 1."
+                // A cell that grows downward spans the rest of the row group.
+                let group_end = group_ends[row];
+                let rowspan = match integer_attribute(&node, "rowspan") {
+                    Some(0) => group_end - row,
+                    Some(n) => n.min(65534).min(group_end - row),
+                    None => 1,
+                };
+
+                for slot_row in slots.iter_mut().skip(row).take(rowspan) {
+                    if slot_row.len() < column + colspan {
+                        slot_row.resize(column + colspan, false);
+                    }
+                    for slot in slot_row.iter_mut().skip(column).take(colspan) {
+                        *slot = true;
+                    }
+                }
+
+                cells.push(Cell {
+                    object,
+                    row,
+                    column,
+                    rowspan,
+                    colspan,
+                });
+                column += colspan;
+            }
+        }
+
+        let column_count = slots.iter().map(|s| s.len()).max().unwrap_or(0);
+
+        Self {
+            captions,
+            row_groups,
+            rows,
+            cells,
+            column_count,
+        }
+    }
+}
+
+/// Returns the width of the spacing between cells.
+/// https://html.spec.whatwg.org/multipage/rendering.html#tables-2
+fn border_spacing(table: &Rc<RefCell<LayoutObject>>) -> i64 {
+    // "table { border-spacing: 2px; }"
+    // "When a table element has a cellspacing attribute whose value, when parsed using the rules
+    // for parsing non-negative integers, is found to be a number greater than zero, the user
+    // agent is expected to use the parsed value as a pixel length for a presentational hint for
+    // the 'border-spacing' property on the element."
+    match integer_attribute(&table.borrow().node(), "cellspacing") {
+        Some(n) if n > 0 => n as i64,
+        _ => 2,
+    }
+}
+
+/// Decides the size of `object` with the `width` and lays out its children.
+fn layout_box(object: &Rc<RefCell<LayoutObject>>, width: i64) {
+    let size = LayoutSize::new(width, 0);
+    object.borrow_mut().compute_size(size);
+    let content_size = object.borrow().content_size();
+    let first_child = object.borrow().first_child();
+    LayoutView::calculate_node_size(&first_child, content_size);
+    object.borrow_mut().compute_size(size);
+}
+
+/// Returns the widths of columns based on the max-content widths of cells.
+/// https://www.w3.org/TR/CSS22/tables.html#auto-table-layout
+fn column_widths(table: &Table, spacing: i64) -> Vec<i64> {
+    let mut columns = vec![0; table.column_count];
+
+    // "Calculate the minimum content width (MCW) of each cell: ... For each column, determine a
+    // maximum and minimum column width from the cells that span only that column."
+    for cell in table.cells.iter().filter(|c| c.colspan == 1) {
+        let width = cell.object.borrow().max_content_width();
+        columns[cell.column] = max(columns[cell.column], width);
+    }
+
+    // "For each cell that spans more than one column, increase the minimum widths of the columns
+    // it spans so that together, they are at least as wide as the cell."
+    for cell in table.cells.iter().filter(|c| c.colspan > 1) {
+        let width = cell.object.borrow().max_content_width();
+        let spanned = &mut columns[cell.column..cell.column + cell.colspan];
+        let current = spanned.iter().sum::<i64>() + spacing * (cell.colspan as i64 - 1);
+        if width > current {
+            let extra = width - current;
+            let count = spanned.len() as i64;
+            for (i, column) in spanned.iter_mut().enumerate() {
+                *column += extra / count + if (i as i64) < extra % count { 1 } else { 0 };
+            }
+        }
+    }
+
+    columns
+}
+
+/// Lays out the captions, row groups, rows and cells in `table`, and decides the size of the
+/// table. The points of the table parts are relative to the table until `place_table` is called.
+/// https://www.w3.org/TR/CSS22/tables.html#width-layout
+pub fn layout_table(table_object: &Rc<RefCell<LayoutObject>>, parent_size: LayoutSize) {
+    let table = Table::new(table_object);
+    let style = table_object.borrow().style();
+    let border = style.border_width() as i64;
+    let spacing = border_spacing(table_object);
+    let gaps = spacing * (table.column_count as i64 + 1) + border * 2;
+
+    // The table is as wide as its contents unless it's wider than the containing block or its
+    // width is specified.
+    let mut columns = column_widths(&table, spacing);
+    let total: i64 = columns.iter().sum();
+    let available = if style.width() != 0.0 {
+        style.width() as i64 - gaps
+    } else {
+        parent_size.width() - style.margin_left() as i64 - style.margin_right() as i64 - gaps
+    };
+    if total > available && total > 0 {
+        let available = max(available, 0);
+        for column in columns.iter_mut() {
+            *column = *column * available / total;
+        }
+    } else if style.width() != 0.0 && !columns.is_empty() {
+        let extra = (available - total) / columns.len() as i64;
+        for column in columns.iter_mut() {
+            *column += extra;
+        }
+    }
+    let width = columns.iter().sum::<i64>() + gaps;
+
+    // Captions are placed above the rows.
+    let mut y = 0;
+    for caption in &table.captions {
+        layout_box(caption, width);
+        caption.borrow_mut().set_point(LayoutPoint::new(0, y));
+        y += caption.borrow().size().height();
+    }
+    let grid_top = y + border;
+
+    // Lay out the contents of cells with the widths of the columns they span.
+    let mut column_x = Vec::with_capacity(columns.len());
+    let mut x = border + spacing;
+    for column in &columns {
+        column_x.push(x);
+        x += column + spacing;
+    }
+    for cell in &table.cells {
+        let cell_width = columns[cell.column..cell.column + cell.colspan]
+            .iter()
+            .sum::<i64>()
+            + spacing * (cell.colspan as i64 - 1);
+        layout_box(&cell.object, cell_width);
+    }
+
+    // https://www.w3.org/TR/CSS22/tables.html#height-layout
+    // "The height of a 'table-row' element's box is calculated once the user agent has all the
+    // cells in the row available: it is the maximum of the row's computed 'height', the computed
+    // 'height' of each cell in the row, and the minimum height (MIN) required by the cells."
+    let mut heights = vec![0; table.rows.len()];
+    for (row, r) in table.rows.iter().enumerate() {
+        heights[row] = r.borrow().style().height() as i64;
+    }
+    for cell in table.cells.iter().filter(|c| c.rowspan == 1) {
+        heights[cell.row] = max(heights[cell.row], cell.object.borrow().size().height());
+    }
+    // A cell that spans multiple rows makes the last row taller if the rows are not tall enough.
+    for cell in table.cells.iter().filter(|c| c.rowspan > 1) {
+        let spanned = heights[cell.row..cell.row + cell.rowspan]
+            .iter()
+            .sum::<i64>()
+            + spacing * (cell.rowspan as i64 - 1);
+        let height = cell.object.borrow().size().height();
+        if height > spanned {
+            heights[cell.row + cell.rowspan - 1] += height - spanned;
+        }
+    }
+
+    let mut row_y = Vec::with_capacity(heights.len());
+    let mut y = grid_top + spacing;
+    for height in &heights {
+        row_y.push(y);
+        y += height + spacing;
+    }
+    let mut height = y + border;
+    if style.height() as i64 > height {
+        height = style.height() as i64;
+    }
+
+    // Cells are stretched to the height of the rows they span.
+    for cell in &table.cells {
+        let cell_height = heights[cell.row..cell.row + cell.rowspan]
+            .iter()
+            .sum::<i64>()
+            + spacing * (cell.rowspan as i64 - 1);
+        let cell_width = cell.object.borrow().size().width();
+        let mut c = cell.object.borrow_mut();
+        c.set_size(LayoutSize::new(cell_width, cell_height));
+        c.set_point(LayoutPoint::new(column_x[cell.column], row_y[cell.row]));
+    }
+
+    let inner_width = width - border * 2;
+    for (row, r) in table.rows.iter().enumerate() {
+        let mut r = r.borrow_mut();
+        r.set_size(LayoutSize::new(inner_width, heights[row]));
+        r.set_point(LayoutPoint::new(border, row_y[row]));
+    }
+    for (group, start, end) in &table.row_groups {
+        let (top, bottom) = if start < end {
+            (row_y[*start], row_y[end - 1] + heights[end - 1])
+        } else {
+            (grid_top, grid_top)
+        };
+        let mut g = group.borrow_mut();
+        g.set_size(LayoutSize::new(inner_width, bottom - top));
+        g.set_point(LayoutPoint::new(border, top));
+    }
+
+    table_object
+        .borrow_mut()
+        .set_size(LayoutSize::new(width, height));
+}
+
+/// Moves the table parts laid out by `layout_table` to the absolute points, and decides the
+/// positions of the contents of captions and cells.
+pub fn place_table(table_object: &Rc<RefCell<LayoutObject>>) {
+    let table = Table::new(table_object);
+    let origin = table_object.borrow().point();
+
+    let mut objects = table.captions.clone();
+    objects.extend(table.row_groups.iter().map(|(g, _, _)| g.clone()));
+    objects.extend(table.rows.iter().cloned());
+    objects.extend(table.cells.iter().map(|c| c.object.clone()));
+    for object in &objects {
+        let point = object.borrow().point();
+        object.borrow_mut().set_point(LayoutPoint::new(
+            origin.x() + point.x(),
+            origin.y() + point.y(),
+        ));
+    }
+
+    let mut boxes = table.captions.clone();
+    boxes.extend(table.cells.iter().map(|c| c.object.clone()));
+    for object in &boxes {
+        let content_point = object.borrow().content_point();
+        let first_child = object.borrow().first_child();
+        LayoutView::calculate_node_position(
+            &first_child,
+            content_point,
+            LayoutObjectKind::Block,
+            None,
+            None,
+            0.0,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::browser::Browser;
+    use crate::constants::CHAR_WIDTH;
+    use crate::renderer::css::cssom::CssParser;
+    use crate::renderer::css::token::CssTokenizer;
+    use crate::renderer::dom::api::get_style_content;
+    use crate::renderer::html::parser::HtmlParser;
+    use crate::renderer::html::token::HtmlTokenizer;
+    use alloc::string::String;
+    use alloc::string::ToString;
+
+    fn create_layout_view(html: String) -> LayoutView {
+        let browser = Browser::new();
+        let t = HtmlTokenizer::new(Rc::downgrade(&browser), html);
+        let window = HtmlParser::new(Rc::downgrade(&browser), t).construct_tree();
+        let dom = window.borrow().document();
+        let style = get_style_content(dom.clone());
+        let css_tokenizer = CssTokenizer::new(style);
+        let cssom = CssParser::new(Rc::downgrade(&browser), css_tokenizer).parse_stylesheet();
+        LayoutView::new(Rc::downgrade(&browser), dom, &cssom)
+    }
+
+    /// Returns the table, which is the first child of the body, and the cells in it.
+    fn table_and_cells(layout_view: &LayoutView) -> (Rc<RefCell<LayoutObject>>, Vec<Cell>) {
+        let root = layout_view.root().expect("root should exist");
+        let table = root.borrow().first_child().expect("table should exist");
+        assert_eq!(LayoutObjectKind::Table, table.borrow().kind());
+        let cells = Table::new(&table).cells;
+        (table, cells)
+    }
+
+    /// Returns the point of `cell` relative to `table` and the size of it.
+    fn cell_box(table: &Rc<RefCell<LayoutObject>>, cell: &Cell) -> (i64, i64, i64, i64) {
+        let origin = table.borrow().point();
+        let object = cell.object.borrow();
+        (
+            object.point().x() - origin.x(),
+            object.point().y() - origin.y(),
+            object.size().width(),
+            object.size().height(),
+        )
+    }
+
+    #[test]
+    fn test_column_widths() {
+        let html = "<table><tr><td>a</td><td>bbb</td></tr><tr><td>cc</td><td>d</td></tr></table>"
+            .to_string();
+        let layout_view = create_layout_view(html);
+        let (table, cells) = table_and_cells(&layout_view);
+
+        // Each column is as wide as the widest cell in it. A cell has 1px padding on each side,
+        // and the cells are separated by 2px border spacing.
+        let first = 2 * CHAR_WIDTH + 2;
+        let second = 3 * CHAR_WIDTH + 2;
+        let boxes: Vec<(i64, i64)> = cells
+            .iter()
+            .map(|c| {
+                let (x, _, width, _) = cell_box(&table, c);
+                (x, width)
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                (2, first),
+                (2 + first + 2, second),
+                (2, first),
+                (2 + first + 2, second)
+            ],
+            boxes
+        );
+        assert_eq!(first + second + 3 * 2, table.borrow().size().width());
+    }
+
+    #[test]
+    fn test_column_widths_with_table_width() {
+        let html = "<html><head><style>table { width: 100; }</style></head><body><table><tr><td>a</td><td>bbb</td></tr></table></body></html>"
+            .to_string();
+        let layout_view = create_layout_view(html);
+        let (table, cells) = table_and_cells(&layout_view);
+
+        // The extra width is distributed to the columns equally.
+        let extra = (100 - 3 * 2 - (CHAR_WIDTH + 2) - (3 * CHAR_WIDTH + 2)) / 2;
+        let widths: Vec<i64> = cells.iter().map(|c| cell_box(&table, c).2).collect();
+        assert_eq!(
+            vec![CHAR_WIDTH + 2 + extra, 3 * CHAR_WIDTH + 2 + extra],
+            widths
+        );
+        assert_eq!(100, table.borrow().size().width());
+    }
+
+    #[test]
+    fn test_colspan_and_rowspan() {
+        let html = "<table><tr><td colspan=2>aaaaaaaa</td><td rowspan=2>b</td></tr><tr><td>c</td><td>d</td></tr></table>"
+            .to_string();
+        let layout_view = create_layout_view(html);
+        let (table, cells) = table_and_cells(&layout_view);
+
+        // (row, column, rowspan, colspan) of each cell. "d" is in the second column because
+        // "aaaaaaaa" spans two columns, and "b" occupies the third column of both rows.
+        let slots: Vec<(usize, usize, usize, usize)> = cells
+            .iter()
+            .map(|c| (c.row, c.column, c.rowspan, c.colspan))
+            .collect();
+        assert_eq!(
+            vec![(0, 0, 1, 2), (0, 2, 2, 1), (1, 0, 1, 1), (1, 1, 1, 1)],
+            slots
+        );
+
+        let (a, b, c, d) = (
+            cell_box(&table, &cells[0]),
+            cell_box(&table, &cells[1]),
+            cell_box(&table, &cells[2]),
+            cell_box(&table, &cells[3]),
+        );
+        // The width of "aaaaaaaa" is distributed to the two columns it spans.
+        let column = (8 * CHAR_WIDTH + 2 - 2) / 2;
+        assert_eq!((2, column), (c.0, c.2));
+        assert_eq!((2 + column + 2, column), (d.0, d.2));
+        assert_eq!((2, column * 2 + 2), (a.0, a.2));
+        assert_eq!(2 + (column + 2) * 2, b.0);
+        // "b" is as tall as the two rows and the spacing between them.
+        assert_eq!(a.1, b.1);
+        assert_eq!(a.1 + a.3 + 2, c.1);
+        assert_eq!(a.3 + 2 + c.3, b.3);
+    }
+
+    #[test]
+    fn test_rowspan_zero() {
+        let html =
+            "<table><tr><td rowspan=0>a</td><td>b</td></tr><tr><td>c</td></tr><tr><td>d</td></tr></table>"
+                .to_string();
+        let layout_view = create_layout_view(html);
+        let (_, cells) = table_and_cells(&layout_view);
+
+        // A cell whose rowspan is 0 spans the rest of the row group.
+        let slots: Vec<(usize, usize, usize, usize)> = cells
+            .iter()
+            .map(|c| (c.row, c.column, c.rowspan, c.colspan))
+            .collect();
+        assert_eq!(
+            vec![(0, 0, 3, 1), (0, 1, 1, 1), (1, 1, 1, 1), (2, 1, 1, 1)],
+            slots
+        );
+    }
+
+    #[test]
+    fn test_cell_padding() {
+        let html = "<table cellpadding=5><tr><td>a</td><td>b</td></tr></table>".to_string();
+        let layout_view = create_layout_view(html);
+        let (table, cells) = table_and_cells(&layout_view);
+
+        // The padding is added to the size of each cell, and the content is inside it.
+        let (x, y, width, _) = cell_box(&table, &cells[0]);
+        assert_eq!((2, 2, CHAR_WIDTH + 10), (x, y, width));
+        assert_eq!(2 + width + 2, cell_box(&table, &cells[1]).0);
+        let cell = cells[0].object.borrow();
+        let text = cell.first_child().expect("text should exist");
+        assert_eq!(cell.point().x() + 5, text.borrow().point().x());
+        assert_eq!(cell.point().y() + 5, text.borrow().point().y());
+    }
+}
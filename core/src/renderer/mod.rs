@@ -4,3 +4,7 @@ pub mod html;
 pub mod js;
 pub mod layout;
 pub mod page;
+pub mod session_history;
+pub mod source;
+#[cfg(test)]
+pub mod test_util;
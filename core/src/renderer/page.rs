@@ -1,29 +1,51 @@
 //! This is corresponding to a page.
 //!
-//! In Chromium, a page can contain multiple frames (iframe, etc.), but this browser has one frame per page. This module implements a subset of Page and Frame.
+//! In Chromium, a page can contain multiple frames (iframe, etc.). In this browser, a page has one
+//! frame, and each iframe in it has a child frame which is held by its own page. This module
+//! implements a subset of Page and Frame.
 //! https://source.chromium.org/chromium/chromium/src/+/main:third_party/blink/renderer/core/page/page.h
 //! https://source.chromium.org/chromium/chromium/src/+/main:third_party/blink/renderer/core/frame/frame.h
 //! https://source.chromium.org/chromium/chromium/src/+/main:third_party/blink/renderer/core/frame/local_frame.h
 
 use crate::alloc::string::ToString;
 use crate::browser::Browser;
+use crate::constants::CONTENT_AREA_WIDTH;
 use crate::display_item::DisplayItem;
+use crate::http::HttpRequest;
 use crate::http::HttpResponse;
 use crate::renderer::css::cssom::CssParser;
 use crate::renderer::css::cssom::StyleSheet;
 use crate::renderer::css::token::CssTokenizer;
-use crate::renderer::dom::api::{get_js_content, get_style_content};
+use crate::renderer::dom::api::{document_title, get_elements_by_tag_name, iframe_elements};
+use crate::renderer::dom::event::dispatch_event;
+use crate::renderer::dom::event::dispatch_event_to_window;
+use crate::renderer::dom::event::Event;
+use crate::renderer::dom::event::KeyboardEvent;
+use crate::renderer::dom::event::MouseEvent;
+use crate::renderer::dom::event::PopStateEvent;
+use crate::renderer::dom::focus;
+use crate::renderer::dom::form;
 use crate::renderer::dom::node::ElementKind;
+use crate::renderer::dom::node::Node;
 use crate::renderer::dom::node::NodeKind;
+use crate::renderer::dom::refresh::meta_refresh;
+use crate::renderer::dom::refresh::Refresh;
 use crate::renderer::dom::window::Window;
-use crate::renderer::html::html_builder::dom_to_html;
+use crate::renderer::html::html_builder::serialize_node;
 use crate::renderer::html::parser::HtmlParser;
 use crate::renderer::html::token::HtmlTokenizer;
 use crate::renderer::js::ast::JsParser;
 use crate::renderer::js::runtime::JsRuntime;
 use crate::renderer::js::token::JsLexer;
+use crate::renderer::layout::layout_object::LayoutObjectKind;
 use crate::renderer::layout::layout_view::LayoutView;
+use crate::renderer::session_history::HistoryHandling;
+use crate::renderer::session_history::SessionHistory;
+use crate::renderer::source::SourcePosition;
+use crate::renderer::source::SourceRange;
+use crate::url::Url;
 use crate::utils::console_debug;
+use crate::utils::console_diagnostic;
 use crate::utils::convert_dom_to_string;
 use crate::utils::convert_layout_tree_to_string;
 use alloc::format;
@@ -47,34 +69,97 @@ impl Subresource {
     }
 }
 
+/// The maximum depth of nested frames. A document which embeds itself would nest forever
+/// otherwise.
+const MAX_FRAME_DEPTH: usize = 3;
+
+/// A child navigable created for an iframe element.
+/// https://html.spec.whatwg.org/multipage/document-sequences.html#child-navigable
+#[derive(Debug, Clone)]
+struct ChildFrame {
+    /// The iframe element which contains this frame.
+    /// https://html.spec.whatwg.org/multipage/document-sequences.html#nav-container
+    container: Rc<RefCell<Node>>,
+    /// The request to load the document of this frame.
+    request: HttpRequest,
+    /// True if `request` has been handed to a UI.
+    requested: bool,
+    /// The page which holds the document of this frame. None until the response is received.
+    page: Option<Rc<RefCell<Page>>>,
+}
+
+impl ChildFrame {
+    fn new(container: Rc<RefCell<Node>>, request: HttpRequest) -> Self {
+        Self {
+            container,
+            request,
+            requested: false,
+            page: None,
+        }
+    }
+
+    /// Starts loading the document of `request` into this frame.
+    fn navigate(&mut self, request: HttpRequest) {
+        self.request = request;
+        self.requested = false;
+        self.page = None;
+    }
+}
+
 /// Represents a page.
 #[derive(Debug, Clone)]
 pub struct Page {
     browser: Weak<RefCell<Browser>>,
+    /// The URL of the document in this page.
+    url: String,
     /// https://source.chromium.org/chromium/chromium/src/+/main:third_party/blink/renderer/core/frame/frame.h;drc=ac83a5a2d3c04763d86ce16d92f3904cc9566d3a;bpv=1;bpt=1;l=505
     frame: Option<Rc<RefCell<Window>>>,
     style: Option<StyleSheet>,
     layout_view: Option<LayoutView>,
     subresources: Vec<Subresource>,
     display_items: Vec<DisplayItem>,
-    modified: bool,
+    /// The navigation scheduled by `<meta http-equiv="refresh">` in the document.
+    refresh: Option<Refresh>,
+    /// The markup of the document. Source ranges of nodes point into this.
+    source: String,
+    /// The child frames for the iframe elements in the document.
+    child_frames: Vec<ChildFrame>,
+    /// The number of frames this page is nested in. 0 for a top-level page.
+    depth: usize,
+    /// The width of the viewport, which is the width of the iframe for a child frame.
+    viewport_width: i64,
+    /// The session history of this page. The window of the document shares it for the History
+    /// API.
+    session_history: Rc<RefCell<SessionHistory>>,
+    /// How the navigation which loads the next document into this page changes the session
+    /// history.
+    history_handling: HistoryHandling,
 }
 
 impl Page {
     pub fn new() -> Self {
         Self {
             browser: Weak::new(),
+            url: String::new(),
             frame: None,
             style: None,
             layout_view: None,
             subresources: Vec::new(),
             display_items: Vec::new(),
-            modified: false,
+            refresh: None,
+            source: String::new(),
+            child_frames: Vec::new(),
+            depth: 0,
+            viewport_width: CONTENT_AREA_WIDTH,
+            session_history: Rc::new(RefCell::new(SessionHistory::new())),
+            history_handling: HistoryHandling::Push,
         }
     }
 
-    /// Called when this page is clicked.
-    pub fn clicked(&self, position: (i64, i64)) -> Option<String> {
+    /// Called when this page is clicked. Returns the request to load into this page when a
+    /// submit button is clicked, or a link is clicked in a child frame. A link in a top-level
+    /// page requests the navigation from the browser.
+    pub fn clicked(&mut self, position: (i64, i64)) -> Option<HttpRequest> {
         let view = match &self.layout_view {
             Some(v) => v,
             None => return None,
@@ -86,64 +171,692 @@ impl Page {
                 format!("cliecked node {:?}", n.borrow().node_kind()),
             );
 
-            if let Some(parent) = n.borrow().parent().upgrade() {
-                if let NodeKind::Element(e) = parent.borrow().node().borrow().kind() {
-                    if e.kind() == ElementKind::A {
-                        return e.get_attribute("href");
+            // A click on an iframe is routed to the child frame. A navigation in the child frame
+            // loads the new document into the frame.
+            if n.borrow().kind() == LayoutObjectKind::Iframe {
+                let node = n.borrow().node();
+                let (point, _) = n.borrow().iframe_viewport();
+                let frame = self
+                    .child_frames
+                    .iter_mut()
+                    .find(|f| Rc::ptr_eq(&f.container, &node));
+                if let Some(frame) = frame {
+                    if let Some(page) = frame.page.clone() {
+                        let position_in_frame = (position.0 - point.x(), position.1 - point.y());
+                        if let Some(request) = page.borrow_mut().clicked(position_in_frame) {
+                            frame.navigate(request);
+                        }
+                        self.paint_tree();
                     }
                 }
+                return None;
+            }
+
+            // The click is dispatched to the element, or to the parent element of a text.
+            // https://w3c.github.io/uievents/#event-type-click
+            let mut target = n.borrow().node();
+            let is_text = matches!(target.borrow().kind(), NodeKind::Text(_));
+            if is_text {
+                if let Some(parent) = target.clone().borrow().parent().upgrade() {
+                    target = parent;
+                }
             }
+
+            // Pressing the mouse button on a focusable area focuses it, and elsewhere unfocuses
+            // the focused element.
+            // https://html.spec.whatwg.org/multipage/interaction.html#focus-processing-model
+            self.focus(focus::focusable_inclusive_ancestor(&target));
+
+            let mut event = Event::new_mouse_event(
+                "click",
+                MouseEvent::new(position.0, position.1, position.0, position.1, 0),
+            );
+            // A listener which cancels the event prevents the default actions below.
+            if !dispatch_event(&target, &mut event) {
+                self.update_rendering();
+                return None;
+            }
+
+            // Following a hyperlink is requested by the activation behavior of the link.
+            let navigation = self.take_window_navigation();
+
+            // A click on a form control runs its activation behavior.
+            if n.borrow().kind() == LayoutObjectKind::FormControl {
+                let node = n.borrow().node();
+                let request = form::activate(&node, &self.base_url());
+                self.update_rendering();
+                return request.or(navigation);
+            }
+            return navigation;
         }
 
         console_debug(&self.browser, "clicked but node not found".to_string());
         None
     }
 
-    /// Called when HTTP response is received.
-    pub fn receive_response(&mut self, response: HttpResponse) {
+    /// Returns the range of the markup which produced the content placed on `position`. A
+    /// view-source display can highlight `&self.source()[range.start()..range.end()]`.
+    pub fn source_range_at(&self, position: (i64, i64)) -> Option<SourceRange> {
+        self.layout_view
+            .as_ref()?
+            .find_source_range_by_position(position)
+    }
+
+    /// Returns the markup of the document in this page, with newlines normalized as the HTML
+    /// tokenizer does.
+    pub fn source(&self) -> String {
+        self.source.clone()
+    }
+
+    /// Called when HTTP response for `url` is received.
+    pub fn receive_response(&mut self, url: String, response: HttpResponse) {
         console_debug(&self.browser, "receive_response start".to_string());
 
+        self.url = url.clone();
+        self.update_session_history(url);
+
         self.create_frame(response.body());
 
+        // Scripts modify the DOM in place, so the document is laid out after they run.
         self.execute_js();
 
-        while self.modified {
-            let dom = match &self.frame {
-                Some(frame) => frame.borrow().document(),
-                None => panic!("frame should exist"),
+        // Scripts may navigate with the Location and History objects. The navigation requested
+        // by a script in a child frame is not supported because the parent page is loading the
+        // child frame.
+        if self.take_window_navigation().is_some() {
+            console_debug(
+                &self.browser,
+                "navigation in a loading child frame is ignored".to_string(),
+            );
+        }
+
+        self.create_child_frames();
+
+        self.set_layout_view();
+
+        self.paint_tree();
+
+        self.refresh = self.declarative_refresh();
+    }
+
+    /// Creates a child frame for each iframe element in the document. The documents of the child
+    /// frames are loaded by a UI through `take_frame_requests` and `receive_frame_response`.
+    /// https://html.spec.whatwg.org/multipage/iframe-embed-object.html#process-the-iframe-attributes
+    fn create_child_frames(&mut self) {
+        self.child_frames = Vec::new();
+        if self.depth >= MAX_FRAME_DEPTH {
+            return;
+        }
+        let dom = match &self.frame {
+            Some(frame) => frame.borrow().document(),
+            None => return,
+        };
+
+        for iframe in iframe_elements(&dom) {
+            // "2. If element has a src attribute specified, and its value is not the empty
+            // string, then:
+            //   1. Let maybeURL be the result of encoding-parsing a URL given that attribute's
+            //   value, relative to element's node document.
+            //   2. If maybeURL is not failure, then set url to maybeURL."
+            // An iframe without a src attribute shows about:blank, which is an empty frame.
+            let src = iframe
+                .borrow()
+                .get_element()
+                .and_then(|e| e.get_attribute("src"));
+            let src = match src {
+                Some(src) if !src.trim().is_empty() => src,
+                _ => continue,
             };
+            let request = HttpRequest::get(self.resolve_url(src.trim()));
+            self.child_frames.push(ChildFrame::new(iframe, request));
+        }
+    }
+
+    /// Returns the requests to load the documents of child frames, including frames nested in
+    /// them, which haven't been returned yet. A UI sends them and passes the responses to
+    /// `receive_frame_response`.
+    pub fn take_frame_requests(&mut self) -> Vec<HttpRequest> {
+        let mut requests = Vec::new();
+        for frame in &mut self.child_frames {
+            match &frame.page {
+                Some(page) => requests.extend(page.borrow_mut().take_frame_requests()),
+                None if !frame.requested => {
+                    frame.requested = true;
+                    requests.push(frame.request.clone());
+                }
+                None => {}
+            }
+        }
+        requests
+    }
 
-            let modified_html = dom_to_html(&Some(dom));
+    /// Called when HTTP response for `url` requested by `take_frame_requests` is received.
+    /// Returns true if a child frame of this page or a frame nested in it was waiting for it.
+    pub fn receive_frame_response(&mut self, url: String, response: HttpResponse) -> bool {
+        let waiting = self
+            .child_frames
+            .iter()
+            .position(|f| f.requested && f.page.is_none() && f.request.url() == url);
 
-            self.create_frame(modified_html);
+        let received = match waiting {
+            Some(i) => {
+                // The document in the child frame is laid out in the viewport of the iframe.
+                let container = self.child_frames[i].container.clone();
+                let viewport_width = self
+                    .layout_view
+                    .as_ref()
+                    .and_then(|view| view.find_layout_object(&container))
+                    .map(|iframe| iframe.borrow().iframe_viewport().1.width())
+                    .unwrap_or(CONTENT_AREA_WIDTH);
 
-            self.modified = false;
+                let mut page = Page::new();
+                page.set_browser(self.browser.clone());
+                page.depth = self.depth + 1;
+                page.viewport_width = viewport_width;
+                page.receive_response(url, response);
+                self.child_frames[i].page = Some(Rc::new(RefCell::new(page)));
+                true
+            }
+            None => self.child_frames.iter().any(|f| match &f.page {
+                Some(page) => page
+                    .borrow_mut()
+                    .receive_frame_response(url.clone(), response.clone()),
+                None => false,
+            }),
+        };
 
-            self.execute_js();
+        if received {
+            self.paint_tree();
         }
+        received
+    }
 
-        self.set_layout_view();
+    /// Adds the entry for the document loaded from `url` to the session history.
+    /// https://html.spec.whatwg.org/multipage/browsing-the-web.html#finalize-a-cross-document-navigation
+    fn update_session_history(&mut self, url: String) {
+        let history_handling =
+            core::mem::replace(&mut self.history_handling, HistoryHandling::Push);
+        let mut session_history = self.session_history.borrow_mut();
+        let current_url = session_history.current_entry().map(|entry| entry.url());
+        match history_handling {
+            HistoryHandling::Push | HistoryHandling::Replace => {
+                // https://html.spec.whatwg.org/multipage/browsing-the-web.html#navigate
+                // "If url equals navigable's active document's URL, and initiatorOriginSnapshot
+                // is same origin with targetNavigable's active document's origin, then set
+                // historyHandling to "replace"."
+                let history_handling = if current_url.as_ref() == Some(&url) {
+                    HistoryHandling::Replace
+                } else {
+                    history_handling
+                };
+                session_history.add_document_entry(url, history_handling);
+            }
+            // The document of the current entry is loaded again.
+            HistoryHandling::Reload | HistoryHandling::Traverse => {
+                if current_url.is_none() {
+                    session_history.add_document_entry(url, HistoryHandling::Push);
+                }
+            }
+        }
+    }
+
+    /// Traverses the session history by `delta`, e.g. -1 for a back button and 1 for a forward
+    /// button of a UI. Returns the request to load the document of the entry if the entry is not
+    /// for the current document. Moving to an entry of the current document fires a popstate
+    /// event at the window instead.
+    /// https://html.spec.whatwg.org/multipage/browsing-the-web.html#traverse-the-history-by-a-delta
+    pub fn traverse_history(&mut self, delta: i64) -> Option<HttpRequest> {
+        match self.traverse_session_history(delta) {
+            Some(request) => Some(request),
+            // A listener of the popstate event may navigate.
+            None => self.take_window_navigation(),
+        }
+    }
+
+    fn traverse_session_history(&mut self, delta: i64) -> Option<HttpRequest> {
+        let (current, target) = {
+            let mut session_history = self.session_history.borrow_mut();
+            let current = session_history.current_entry()?;
+            (current, session_history.traverse(delta)?)
+        };
 
+        // The document of an entry is not kept after another document is loaded, so the document
+        // is loaded again.
+        let frame = match &self.frame {
+            Some(frame) if target.document_state() == current.document_state() => frame.clone(),
+            _ => {
+                self.history_handling = HistoryHandling::Traverse;
+                return Some(HttpRequest::get(target.url()));
+            }
+        };
+
+        // https://html.spec.whatwg.org/multipage/browsing-the-web.html#update-document-for-history-step-application
+        // "5.3. Set document's URL to entry's URL.
+        // 6.4.2. Fire an event named popstate at document's relevant global object, using
+        // PopStateEvent, with the state attribute initialized to document's history object's
+        // state and hasUAVisualTransition initialized to true if a visual transition, to display
+        // a cached rendered state of the latest entry, was done by the user agent."
+        self.url = target.url();
+        frame.borrow_mut().set_url(target.url());
+        let mut event = Event::new_popstate_event(PopStateEvent::new(target.state()));
+        dispatch_event_to_window(&frame, &mut event);
+        self.update_rendering();
+        None
+    }
+
+    /// Returns the navigation scheduled by `<meta http-equiv="refresh">` in the document. A UI
+    /// navigates to its URL after its delay.
+    pub fn refresh(&self) -> Option<Refresh> {
+        self.refresh.clone()
+    }
+
+    fn declarative_refresh(&self) -> Option<Refresh> {
+        let dom = self.frame.as_ref()?.borrow().document();
+        let refresh = meta_refresh(&dom, &self.url, &self.base_url())?;
+
+        // "Perform one or more of the following steps: ... Do nothing."
+        // A user can disable the declarative refresh in the browser setting.
+        let enabled = match self.browser.upgrade() {
+            Some(browser) => browser.borrow().meta_refresh_enabled(),
+            None => true,
+        };
+        if !enabled {
+            console_debug(
+                &self.browser,
+                format!("meta refresh to {} is disabled", refresh.url()),
+            );
+            return None;
+        }
+        Some(refresh)
+    }
+
+    /// Returns the focused element of the document.
+    /// https://html.spec.whatwg.org/multipage/interaction.html#focused-area-of-the-document
+    pub fn focused_element(&self) -> Option<Rc<RefCell<Node>>> {
+        let dom = match &self.frame {
+            Some(frame) => frame.borrow().document(),
+            None => return None,
+        };
+        let focused = dom.borrow().focused_area().upgrade();
+        focused
+    }
+
+    /// Returns the form control that has the focus.
+    pub fn focused_form_control(&self) -> Option<Rc<RefCell<Node>>> {
+        self.focused_element().filter(|node| {
+            node.borrow()
+                .get_element()
+                .is_some_and(|e| form::is_form_control(&e))
+        })
+    }
+
+    /// Moves the focus to the next element in the sequential focus navigation order, or the
+    /// previous one if `forward` is false. The focus moves back to the first element after the
+    /// last one.
+    /// https://html.spec.whatwg.org/multipage/interaction.html#sequential-focus-navigation
+    pub fn focus_next_element(&mut self, forward: bool) {
+        let dom = match &self.frame {
+            Some(frame) => frame.borrow().document(),
+            None => return,
+        };
+        if let Some(next) = focus::next_focusable_area(&dom, forward) {
+            self.focus(Some(next));
+            self.update_rendering();
+        }
+    }
+
+    /// Removes the focus from the focused element.
+    pub fn unfocus(&mut self) {
+        self.focus(None);
+        self.update_rendering();
+    }
+
+    /// Called when a user types `c` while a form control is focused. A character is appended to
+    /// a text field, and a space key toggles a checkbox or changes the option of a select
+    /// element.
+    pub fn insert_char(&mut self, c: char) {
+        let node = match self.focused_form_control() {
+            Some(n) => n,
+            None => return,
+        };
+        let element = match node.borrow().get_element() {
+            Some(e) => e,
+            None => return,
+        };
+
+        if form::is_text_field(&element) {
+            let mut value = form::value(&node);
+            value.push(c);
+            form::set_value(&node, value);
+        } else if c == ' ' {
+            if element.kind() == ElementKind::Select {
+                form::select_next_option(&node);
+            } else if !form::is_submit_button(&element) {
+                form::activate(&node, &self.base_url());
+            }
+        }
+        self.update_rendering();
+    }
+
+    /// Called when a user presses the backspace key while a text field is focused.
+    pub fn delete_char(&mut self) {
+        let node = match self.focused_form_control() {
+            Some(n) => n,
+            None => return,
+        };
+        if !node
+            .borrow()
+            .get_element()
+            .is_some_and(|e| form::is_text_field(&e))
+        {
+            return;
+        }
+
+        let mut value = form::value(&node);
+        value.pop();
+        form::set_value(&node, value);
+        self.update_rendering();
+    }
+
+    /// Called when a user presses the enter key while a form control is focused. Returns the
+    /// request to navigate to if the form is submitted.
+    pub fn activate_focused_control(&mut self) -> Option<HttpRequest> {
+        let node = self.focused_form_control()?;
+        let element = node.borrow().get_element()?;
+
+        let request = match element.kind() {
+            // The enter key inserts a new line in a textarea.
+            ElementKind::Textarea => {
+                let mut value = form::value(&node);
+                value.push('\n');
+                form::set_value(&node, value);
+                None
+            }
+            ElementKind::Select => {
+                form::select_next_option(&node);
+                None
+            }
+            _ if form::is_text_field(&element) => {
+                form::implicit_submission(&node, &self.base_url())
+            }
+            _ => form::activate(&node, &self.base_url()),
+        };
+        self.update_rendering();
+        request
+    }
+
+    /// https://html.spec.whatwg.org/multipage/interaction.html#focusing-steps
+    fn focus(&mut self, node: Option<Rc<RefCell<Node>>>) {
+        let dom = match &self.frame {
+            Some(frame) => frame.borrow().document(),
+            None => return,
+        };
+        focus::focus(&dom, node);
+    }
+
+    /// Called when a user presses a key. `key` is a key attribute value such as "a", "Enter" or
+    /// "Tab". Keyboard events are fired at the focused element, or the body element if nothing
+    /// is focused, and the default action of the key runs unless a listener cancels it. Returns
+    /// the request to navigate to when a link is followed or a form is submitted.
+    /// https://w3c.github.io/uievents/#events-keyboard-event-order
+    pub fn key_pressed(&mut self, key: &str, shift_key: bool) -> Option<HttpRequest> {
+        let dom = match &self.frame {
+            Some(frame) => frame.borrow().document(),
+            None => return None,
+        };
+        // https://w3c.github.io/uievents/#event-type-keydown
+        // "The target of the event is the focused element processing the key event or if no
+        // element focused, then the body element if available, otherwise the root element."
+        let target = match self.focused_element() {
+            Some(focused) => focused,
+            None => match get_elements_by_tag_name(&dom, "body").first() {
+                Some(body) => body.clone(),
+                None => dom.clone(),
+            },
+        };
+        let keyboard_event = KeyboardEvent::new(key, shift_key);
+
+        let mut canceled = !dispatch_event(
+            &target,
+            &mut Event::new_keyboard_event("keydown", keyboard_event.clone()),
+        );
+        // "keypress" is fired only for a key which produces a character value.
+        // https://w3c.github.io/uievents/#event-type-keypress
+        if !canceled && keyboard_event.is_character() {
+            canceled = !dispatch_event(
+                &target,
+                &mut Event::new_keyboard_event("keypress", keyboard_event.clone()),
+            );
+        }
+
+        let request = if canceled {
+            None
+        } else {
+            self.run_key_default_action(&target, &keyboard_event)
+        };
+
+        dispatch_event(
+            &target,
+            &mut Event::new_keyboard_event("keyup", keyboard_event),
+        );
+        self.update_rendering();
+        request
+    }
+
+    /// Starts the navigation requested by the document, e.g. by following a hyperlink or by the
+    /// Location object, or the history traversal requested by the History API. The navigable to
+    /// navigate is chosen by the target of the link:
+    /// - "_self" or no target navigates this page. Returns the request when this page is a child
+    ///   frame so that the parent page loads it into the frame.
+    /// - "_parent", "_top" and "_blank" navigate the top-level page. A new page is not opened
+    ///   because this browser supports only one page.
+    /// - The name of an iframe in this page navigates its child frame, and an unknown name
+    ///   navigates the top-level page.
+    /// https://html.spec.whatwg.org/multipage/document-sequences.html#the-rules-for-choosing-a-navigable
+    fn take_window_navigation(&mut self) -> Option<HttpRequest> {
+        let frame = self.frame.clone()?;
+        // The History API may have changed the URL of the document.
+        self.url = frame.borrow().url();
+
+        let traversal = frame.borrow_mut().take_traversal();
+        if let Some(delta) = traversal {
+            return match self.traverse_session_history(delta) {
+                Some(request) => self.request_navigation(request),
+                None => self.take_window_navigation(),
+            };
+        }
+
+        let navigation = frame.borrow_mut().take_navigation()?;
+        let history_handling = navigation.history_handling();
+        let url = navigation.url();
+        let request = HttpRequest::get(url.clone());
+
+        let target = navigation.target().to_ascii_lowercase();
+        if !target.is_empty() && target != "_self" {
+            let frame = self.child_frames.iter_mut().find(|f| {
+                f.container
+                    .borrow()
+                    .get_element()
+                    .and_then(|e| e.get_attribute("name"))
+                    .is_some_and(|name| name.to_ascii_lowercase() == target)
+            });
+            match frame {
+                Some(frame) if !target.starts_with('_') => frame.navigate(request),
+                _ => self.request_top_level_navigation(request),
+            }
+            return None;
+        }
+
+        // https://html.spec.whatwg.org/multipage/browsing-the-web.html#navigate
+        // "If url equals navigable's active document's URL with exclude fragments set to true,
+        // and url's fragment is non-null, then navigate to a fragment given navigable, url,
+        // historyHandling, userInvolvement, navigationAPIState, and navigationId."
+        let is_fragment_navigation = match (
+            Url::new(url.clone()).parse(),
+            Url::new(self.url.clone()).parse(),
+        ) {
+            (Ok(target), Ok(current)) => {
+                !target.fragment().is_empty() && target.equals(&current, true)
+            }
+            _ => false,
+        };
+        if history_handling != HistoryHandling::Reload && is_fragment_navigation {
+            // https://html.spec.whatwg.org/multipage/browsing-the-web.html#navigate-fragid
+            // The document is not loaded again. Scrolling to the fragment is not supported.
+            // "3. Let historyEntry be a new session history entry, with URL url, document state
+            // navigable's active session history entry's document state, ...
+            // 4. Let entryToReplace be navigable's active session history entry if
+            // historyHandling is "replace", otherwise null."
+            self.session_history.borrow_mut().add_same_document_entry(
+                url.clone(),
+                None,
+                history_handling,
+            );
+            self.url = url.clone();
+            frame.borrow_mut().set_url(url);
+            return None;
+        }
+
+        self.history_handling = history_handling;
+        self.request_navigation(request)
+    }
+
+    /// Requests to load `request` into this page. Returns the request when this page is a child
+    /// frame so that the parent page loads it into the frame.
+    fn request_navigation(&self, request: HttpRequest) -> Option<HttpRequest> {
+        if self.depth > 0 {
+            return Some(request);
+        }
+        self.request_top_level_navigation(request);
+        None
+    }
+
+    fn request_top_level_navigation(&self, request: HttpRequest) {
+        if let Some(browser) = self.browser.upgrade() {
+            browser.borrow_mut().request_navigation(request);
+        }
+    }
+
+    /// Runs the default action of a key.
+    /// - Tab moves the focus, and backward with the shift key.
+    /// - Escape unfocuses the focused element.
+    /// - Enter follows a focused link or activates a focused form control.
+    /// - Backspace and characters edit a focused form control.
+    fn run_key_default_action(
+        &mut self,
+        target: &Rc<RefCell<Node>>,
+        keyboard_event: &KeyboardEvent,
+    ) -> Option<HttpRequest> {
+        match keyboard_event.key().as_str() {
+            "Tab" => {
+                self.focus_next_element(!keyboard_event.shift_key());
+                None
+            }
+            "Escape" => {
+                self.unfocus();
+                None
+            }
+            "Enter" => {
+                let element = target.borrow().get_element()?;
+                if element.kind() == ElementKind::A {
+                    // The enter key on a focused link fires a synthetic click event at it.
+                    // https://html.spec.whatwg.org/multipage/interaction.html#activation-triggering-input-event
+                    let mut event = Event::new_mouse_event("click", MouseEvent::new(0, 0, 0, 0, 0));
+                    dispatch_event(target, &mut event);
+                    return self.take_window_navigation();
+                }
+                self.activate_focused_control()
+            }
+            "Backspace" => {
+                self.delete_char();
+                None
+            }
+            key => {
+                if let Some(c) = key.chars().next().filter(|_| keyboard_event.is_character()) {
+                    self.insert_char(c);
+                }
+                None
+            }
+        }
+    }
+
+    /// Lays out and paints the document again after the state of the document is changed.
+    fn update_rendering(&mut self) {
+        self.set_layout_view();
         self.paint_tree();
     }
 
+    pub fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    /// https://html.spec.whatwg.org/multipage/urls-and-fetching.html#document-base-url
+    pub fn base_url(&self) -> String {
+        match &self.frame {
+            Some(frame) => frame.borrow().base_url(),
+            None => self.url.clone(),
+        }
+    }
+
+    /// Resolves `url` against the document base URL.
+    /// https://html.spec.whatwg.org/multipage/urls-and-fetching.html#encoding-parsing-a-url
+    fn resolve_url(&self, url: &str) -> String {
+        match Url::new(self.base_url()).parse() {
+            Ok(base) => base.resolve(url),
+            Err(_) => url.to_string(),
+        }
+    }
+
+    /// Returns the title of the document in this page.
+    /// https://html.spec.whatwg.org/multipage/dom.html#document.title
+    pub fn title(&self) -> String {
+        match &self.frame {
+            Some(frame) => document_title(&frame.borrow().document()),
+            None => String::new(),
+        }
+    }
+
+    /// Returns the HTML serialization of the document in this page. The result includes the
+    /// changes made by scripts.
+    pub fn serialize_dom(&self) -> String {
+        match &self.frame {
+            Some(frame) => serialize_node(&frame.borrow().document()),
+            None => String::new(),
+        }
+    }
+
     pub fn set_browser(&mut self, browser: Weak<RefCell<Browser>>) {
         self.browser = browser;
     }
 
     fn create_frame(&mut self, html: String) {
-        let html_tokenizer = HtmlTokenizer::new(self.browser.clone(), html);
+        self.source = HtmlTokenizer::preprocess(&html);
+        let mut html_tokenizer = HtmlTokenizer::new(self.browser.clone(), html);
+        html_tokenizer.set_url(self.url.clone());
 
         let frame = HtmlParser::new(self.browser.clone(), html_tokenizer).construct_tree();
+        frame.borrow_mut().set_url(self.url.clone());
+        frame
+            .borrow_mut()
+            .set_session_history(self.session_history.clone());
         let dom = frame.borrow().document();
 
         // for debug.
-        let debug = convert_dom_to_string(&Some(dom.clone()));
+        let debug = convert_dom_to_string(&Some(dom.clone()), false);
         console_debug(&self.browser, debug);
 
-        let style = get_style_content(dom);
-        let css_tokenizer = CssTokenizer::new(style);
-        let cssom = CssParser::new(self.browser.clone(), css_tokenizer).parse_stylesheet();
+        // Each style element is parsed separately, so that a parse error is reported at its
+        // position in the document. The rules are in the order of the style elements in the
+        // document.
+        let mut cssom = StyleSheet::new();
+        for style in get_elements_by_tag_name(&dom, "style") {
+            let (content, start_position) = child_text_content(&style);
+            let css_tokenizer = CssTokenizer::new(content);
+            let mut css_parser = CssParser::new(self.browser.clone(), css_tokenizer);
+            css_parser.set_url(self.url.clone());
+            css_parser.set_start_position(start_position);
+            cssom.rules.extend(css_parser.parse_stylesheet().rules);
+        }
 
         self.frame = Some(frame);
         self.style = Some(cssom);
@@ -160,10 +873,11 @@ impl Page {
             None => return,
         };
 
-        let layout_view = LayoutView::new(self.browser.clone(), dom, &style);
+        let layout_view =
+            LayoutView::with_viewport_width(self.browser.clone(), dom, &style, self.viewport_width);
 
         // for debug.
-        let debug = convert_layout_tree_to_string(&layout_view.root());
+        let debug = convert_layout_tree_to_string(&layout_view.root(), false);
         console_debug(&self.browser, debug);
 
         self.layout_view = Some(layout_view);
@@ -175,16 +889,31 @@ impl Page {
             None => return,
         };
 
-        let js = get_js_content(dom.clone());
-        let lexer = JsLexer::new(js);
-
-        let mut parser = JsParser::new(lexer);
-        let ast = parser.parse_ast();
-
+        // The scripts are run in the order of the script elements in the document, and share the
+        // global environment. The script elements are collected before running the scripts, so a
+        // script element inserted by a script is not run.
+        let scripts = get_elements_by_tag_name(&dom, "script");
         let mut runtime = JsRuntime::new(dom);
-        runtime.execute(&ast);
+        runtime.set_url(self.url.clone());
+        for script in scripts {
+            let (content, start_position) = child_text_content(&script);
+            let lexer = JsLexer::new(content);
+            let mut parser = JsParser::new(lexer);
+            parser.set_url(self.url.clone());
+            parser.set_start_position(start_position);
+            let ast = parser.parse_ast();
 
-        self.modified = runtime.dom_modified();
+            // A script with a syntax error is not run, but the following scripts are.
+            let diagnostics = parser.diagnostics();
+            if !diagnostics.is_empty() {
+                for diagnostic in diagnostics {
+                    console_diagnostic(&self.browser, diagnostic);
+                }
+                continue;
+            }
+
+            runtime.execute(&ast);
+        }
     }
 
     pub fn push_url_for_subresource(&mut self, src: String) {
@@ -214,5 +943,445 @@ impl Page {
         if let Some(layout_view) = &self.layout_view {
             self.display_items = layout_view.paint();
         }
+
+        // Subresources are fetched with the URLs resolved against the document base URL.
+        let display_items = self.display_items.clone();
+        self.display_items = display_items
+            .into_iter()
+            .map(|item| match item {
+                DisplayItem::Img {
+                    src,
+                    style,
+                    layout_point,
+                } => DisplayItem::Img {
+                    src: self.resolve_url(&src),
+                    style,
+                    layout_point,
+                },
+                _ => item,
+            })
+            .collect();
+
+        // The content of each child frame is painted in the viewport of its iframe, and the
+        // part outside of the viewport is clipped.
+        let layout_view = match &self.layout_view {
+            Some(v) => v,
+            None => return,
+        };
+        for frame in &self.child_frames {
+            let (page, iframe) = match (
+                &frame.page,
+                layout_view.find_layout_object(&frame.container),
+            ) {
+                (Some(page), Some(iframe)) => (page, iframe),
+                _ => continue,
+            };
+            let (point, size) = iframe.borrow().iframe_viewport();
+            for item in page.borrow().display_items() {
+                if let Some(item) = item.translate(point).clip(point, size) {
+                    self.display_items.push(item);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the child text content of `element` and the position in the document where it starts.
+/// https://dom.spec.whatwg.org/#concept-child-text-content
+fn child_text_content(element: &Rc<RefCell<Node>>) -> (String, SourcePosition) {
+    // "The child text content of a node node is the concatenation of the data of all the Text node
+    // children of node, in tree order."
+    let mut content = String::new();
+    let mut start_position = None;
+    let mut child = element.borrow().first_child();
+    while let Some(c) = child {
+        if let NodeKind::Text(ref s) = c.borrow().kind() {
+            content.push_str(s);
+            if start_position.is_none() {
+                start_position = c.borrow().source_range().map(|r| r.start_position());
+            }
+        }
+        child = c.borrow().next_sibling();
+    }
+    (content, start_position.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::browser::Browser;
+    use crate::log::SourceKind;
+    use crate::renderer::dom::api::get_element_by_id;
+    use crate::renderer::dom::event::EventInterface;
+    use crate::renderer::dom::event::EventTarget;
+    use crate::renderer::layout::color::Color;
+    use crate::renderer::layout::layout_point::LayoutPoint;
+    use alloc::vec;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    fn response(body: &str) -> HttpResponse {
+        HttpResponse::new(format!(
+            "HTTP/1.1 200 OK\nContent-Type: text/html\n\n{}",
+            body
+        ))
+        .expect("failed to parse http response")
+    }
+
+    fn texts(page: &Rc<RefCell<Page>>) -> Vec<(String, LayoutPoint)> {
+        page.borrow()
+            .display_items()
+            .into_iter()
+            .filter_map(|item| match item {
+                DisplayItem::Text {
+                    text, layout_point, ..
+                } => Some((text, layout_point)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_iframe() {
+        let browser = Browser::new();
+        let page = browser.borrow().current_page();
+        page.borrow_mut().receive_response(
+            "http://example.com/dir/index.html".to_string(),
+            response("<p>top</p><iframe src=child.html width=120 height=100>fallback</iframe>"),
+        );
+
+        // The fallback content is not rendered, and the document of the child frame is loaded
+        // only once.
+        assert_eq!(vec!["top".to_string()], {
+            texts(&page).into_iter().map(|(t, _)| t).collect::<Vec<_>>()
+        });
+        let requests = page.borrow_mut().take_frame_requests();
+        assert_eq!(
+            vec![HttpRequest::get(
+                "http://example.com/dir/child.html".to_string()
+            )],
+            requests
+        );
+        assert!(page.borrow_mut().take_frame_requests().is_empty());
+
+        assert!(page.borrow_mut().receive_frame_response(
+            "http://example.com/dir/child.html".to_string(),
+            response("<a href=next.html>next</a><p>a long line which is clipped</p><p>hidden</p>"),
+        ));
+
+        // The content of the child frame is laid out in the width of the iframe, and the lines
+        // below its height are clipped. The body of the child frame has a margin of 8px, and the
+        // iframe has a border of 2px.
+        let texts = texts(&page);
+        let link = texts
+            .iter()
+            .find(|(t, _)| t == "next")
+            .expect("the link in the iframe should be painted")
+            .1;
+        assert_eq!(
+            vec!["top", "next", "a long line", "which is"],
+            texts.iter().map(|(t, _)| t.as_str()).collect::<Vec<_>>()
+        );
+        let iframe_point = LayoutPoint::new(link.x() - 10, link.y() - 10);
+        for (_, point) in &texts[1..] {
+            assert!(point.x() >= iframe_point.x() + 2 && point.x() < iframe_point.x() + 122);
+            assert!(point.y() >= iframe_point.y() + 2 && point.y() < iframe_point.y() + 102);
+        }
+
+        // A click on the link in the iframe navigates the child frame.
+        assert_eq!(
+            None,
+            page.borrow_mut().clicked((link.x() + 1, link.y() + 1))
+        );
+        assert_eq!(
+            vec![HttpRequest::get(
+                "http://example.com/dir/next.html".to_string()
+            )],
+            page.borrow_mut().take_frame_requests()
+        );
+    }
+
+    #[test]
+    fn test_keyboard_focus() {
+        let browser = Browser::new();
+        let page = browser.borrow().current_page();
+        page.borrow_mut().receive_response(
+            "http://example.com/index.html".to_string(),
+            response("<input name=q><a href=next.html>next</a><p tabindex=1>first</p>"),
+        );
+        let focused_name = |page: &Rc<RefCell<Page>>| {
+            page.borrow()
+                .focused_element()
+                .and_then(|n| n.borrow().get_element())
+                .map(|e| e.local_name())
+        };
+
+        // The element with a positive tabindex comes first.
+        assert_eq!(None, page.borrow_mut().key_pressed("Tab", false));
+        assert_eq!(Some("p".to_string()), focused_name(&page));
+        page.borrow_mut().key_pressed("Tab", false);
+        assert_eq!(Some("input".to_string()), focused_name(&page));
+        page.borrow_mut().key_pressed("a", false);
+        page.borrow_mut().key_pressed("b", false);
+        page.borrow_mut().key_pressed("Backspace", false);
+        let input = page.borrow().focused_form_control().unwrap();
+        assert_eq!("a".to_string(), form::value(&input));
+
+        // The focused link is highlighted, and the enter key follows it. The navigation of the
+        // top-level page is requested from the browser.
+        page.borrow_mut().key_pressed("Tab", false);
+        assert_eq!(Some("a".to_string()), focused_name(&page));
+        assert!(page.borrow().focused_form_control().is_none());
+        let highlighted = page
+            .borrow()
+            .display_items()
+            .into_iter()
+            .any(|item| match item {
+                DisplayItem::Text { text, style, .. } => {
+                    text == "next"
+                        && style.background_color() == Color::from_name("lightgray").unwrap()
+                }
+                _ => false,
+            });
+        assert!(highlighted);
+        assert_eq!(None, page.borrow_mut().key_pressed("Enter", false));
+        assert_eq!(
+            Some(HttpRequest::get("http://example.com/next.html".to_string())),
+            browser.borrow_mut().take_navigation_request()
+        );
+
+        // The shift key moves the focus backward.
+        page.borrow_mut().key_pressed("Tab", true);
+        assert_eq!(Some("input".to_string()), focused_name(&page));
+        page.borrow_mut().key_pressed("Escape", false);
+        assert_eq!(None, focused_name(&page));
+    }
+
+    fn prevent_default(event: &mut Event) {
+        event.prevent_default();
+    }
+
+    #[test]
+    fn test_follow_hyperlink() {
+        let browser = Browser::new();
+        let page = browser.borrow().current_page();
+        page.borrow_mut().receive_response(
+            "http://example.com/dir/index.html".to_string(),
+            response(
+                "<p><a href=next.html><b id=nested>nested</b></a></p><p><a href=#section>fragment</a></p>\
+                 <p><a href=top.html target=_blank>blank</a></p><p><a href=child2.html target=Frame>named</a></p>\
+                 <p><a id=canceled href=canceled.html>canceled</a></p><iframe name=frame src=child.html></iframe>",
+            ),
+        );
+        page.borrow_mut().take_frame_requests();
+        page.borrow_mut().receive_frame_response(
+            "http://example.com/dir/child.html".to_string(),
+            response("<p>child</p>"),
+        );
+        let click = |text: &str| {
+            let point = texts(&page)
+                .into_iter()
+                .find(|(t, _)| t == text)
+                .expect("the text should be painted")
+                .1;
+            page.borrow_mut().clicked((point.x() + 1, point.y() + 1))
+        };
+
+        // A click on an element in a link follows the link.
+        assert_eq!(None, click("nested"));
+        assert_eq!(
+            Some(HttpRequest::get(
+                "http://example.com/dir/next.html".to_string()
+            )),
+            browser.borrow_mut().take_navigation_request()
+        );
+
+        // A link to a fragment of the document doesn't load the document again.
+        assert_eq!(None, click("fragment"));
+        assert_eq!(None, browser.borrow_mut().take_navigation_request());
+        assert_eq!(
+            "http://example.com/dir/index.html#section".to_string(),
+            page.borrow().url()
+        );
+
+        assert_eq!(None, click("blank"));
+        assert_eq!(
+            Some(HttpRequest::get(
+                "http://example.com/dir/top.html".to_string()
+            )),
+            browser.borrow_mut().take_navigation_request()
+        );
+
+        // A link whose target is the name of an iframe navigates the child frame.
+        assert_eq!(None, click("named"));
+        assert_eq!(None, browser.borrow_mut().take_navigation_request());
+        assert_eq!(
+            vec![HttpRequest::get(
+                "http://example.com/dir/child2.html".to_string()
+            )],
+            page.borrow_mut().take_frame_requests()
+        );
+
+        // A listener which cancels the click prevents the navigation.
+        let document = page.borrow().frame.as_ref().unwrap().borrow().document();
+        let link = get_element_by_id(Some(document), &"canceled".to_string()).unwrap();
+        link.borrow_mut()
+            .add_event_listener("click".to_string(), prevent_default, false);
+        assert_eq!(None, click("canceled"));
+        assert_eq!(None, browser.borrow_mut().take_navigation_request());
+    }
+
+    // The state of the last popstate event, which is 0 for null and 1 for "1".
+    static POPSTATE: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+    fn record_popstate(event: &mut Event) {
+        if let EventInterface::PopStateEvent(popstate) = event.interface() {
+            let state = match popstate.state() {
+                Some(state) => state.parse().unwrap_or(usize::MAX),
+                None => 0,
+            };
+            POPSTATE.store(state, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_style_and_script_elements() {
+        let browser = Browser::new();
+        let page = browser.borrow().current_page();
+        page.borrow_mut().receive_response(
+            "http://example.com/index.html".to_string(),
+            response(
+                "<style>p { color: red; }</style>\n<style>\np { color red; }</style>\n<script>var a = 1;</script>\n<script>\nfunction f( {</script>\n<script>document.title = \"ok\";</script>",
+            ),
+        );
+
+        // Each element is parsed separately, and the errors are reported at their positions in
+        // the document.
+        let diagnostics: Vec<(String, String)> = browser
+            .borrow()
+            .diagnostics()
+            .iter()
+            .filter(|d| d.kind() != SourceKind::Html)
+            .map(|d| (d.code(), d.position().to_string()))
+            .collect();
+        assert_eq!(
+            vec![
+                (
+                    "missing-colon-in-declaration".to_string(),
+                    "3:11".to_string()
+                ),
+                ("expected-identifier".to_string(), "6:13".to_string()),
+                ("unexpected-eof".to_string(), "6:13".to_string()),
+            ],
+            diagnostics
+        );
+        assert_eq!(2, page.borrow().style.as_ref().expect("style").rules.len());
+        // Only the script with the syntax error is not run.
+        assert_eq!("ok".to_string(), page.borrow().title());
+    }
+
+    #[test]
+    fn test_session_history() {
+        let browser = Browser::new();
+        let page = browser.borrow().current_page();
+        let length = |page: &Rc<RefCell<Page>>| page.borrow().session_history.borrow().length();
+        page.borrow_mut().receive_response(
+            "http://example.com/index.html".to_string(),
+            response(
+                "<script>history.pushState(\"1\", \"\", \"page1\");</script><a href=#section>fragment</a>",
+            ),
+        );
+        assert_eq!("http://example.com/page1".to_string(), page.borrow().url());
+        assert_eq!(2, length(&page));
+
+        let point = texts(&page)[0].1;
+        assert_eq!(
+            None,
+            page.borrow_mut().clicked((point.x() + 1, point.y() + 1))
+        );
+        assert_eq!(
+            "http://example.com/page1#section".to_string(),
+            page.borrow().url()
+        );
+        assert_eq!(3, length(&page));
+
+        // Moving between the entries of the document fires popstate events at the window.
+        page.borrow()
+            .frame
+            .as_ref()
+            .unwrap()
+            .borrow_mut()
+            .add_event_listener("popstate".to_string(), record_popstate, false);
+        assert_eq!(None, page.borrow_mut().traverse_history(-1));
+        assert_eq!("http://example.com/page1".to_string(), page.borrow().url());
+        assert_eq!(1, POPSTATE.load(Ordering::SeqCst));
+        assert_eq!(None, page.borrow_mut().traverse_history(-1));
+        assert_eq!(
+            "http://example.com/index.html".to_string(),
+            page.borrow().url()
+        );
+        assert_eq!(0, POPSTATE.load(Ordering::SeqCst));
+        assert_eq!(None, page.borrow_mut().traverse_history(-1));
+        assert_eq!(None, browser.borrow_mut().take_navigation_request());
+
+        // A new document removes the entries after the current entry, and location.replace()
+        // replaces the entry of the document.
+        page.borrow_mut().receive_response(
+            "http://example.com/next.html".to_string(),
+            response("<script>location.replace(\"other.html\");</script>"),
+        );
+        assert_eq!(2, length(&page));
+        let request = browser.borrow_mut().take_navigation_request().unwrap();
+        assert_eq!(
+            HttpRequest::get("http://example.com/other.html".to_string()),
+            request
+        );
+        page.borrow_mut()
+            .receive_response(request.url(), response("<p>other</p>"));
+        assert_eq!(2, length(&page));
+
+        // Moving to an entry of another document loads it again without adding an entry.
+        let request = page.borrow_mut().traverse_history(-1).unwrap();
+        assert_eq!(
+            HttpRequest::get("http://example.com/index.html".to_string()),
+            request
+        );
+        page.borrow_mut()
+            .receive_response(request.url(), response("<p>index</p>"));
+        assert_eq!(2, length(&page));
+        assert_eq!(
+            Some(HttpRequest::get(
+                "http://example.com/other.html".to_string()
+            )),
+            page.borrow_mut().traverse_history(1)
+        );
+    }
+
+    #[test]
+    fn test_nested_iframe_depth() {
+        let browser = Browser::new();
+        let page = browser.borrow().current_page();
+        let html = "<iframe src=self.html></iframe>";
+        let url = "http://example.com/self.html".to_string();
+        page.borrow_mut()
+            .receive_response(url.clone(), response(html));
+
+        // A document which embeds itself is nested up to the limit.
+        let mut loaded = 0;
+        loop {
+            let requests = page.borrow_mut().take_frame_requests();
+            if requests.is_empty() {
+                break;
+            }
+            for request in requests {
+                assert!(page
+                    .borrow_mut()
+                    .receive_frame_response(request.url(), response(html)));
+                loaded += 1;
+            }
+        }
+        assert_eq!(MAX_FRAME_DEPTH, loaded);
+        assert!(!page
+            .borrow_mut()
+            .receive_frame_response(url, response(html)));
     }
 }
@@ -0,0 +1,199 @@
+//! This is a session history of a page, which is the list of the documents and the states pushed
+//! by the History API that the page has shown. Back and forward buttons of a UI and the History
+//! API traverse it.
+//! https://html.spec.whatwg.org/multipage/document-sequences.html#tn-session-history-entries
+//! https://html.spec.whatwg.org/multipage/browsing-the-web.html#session-history-entries
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// How a navigation changes the session history.
+/// https://html.spec.whatwg.org/multipage/browsing-the-web.html#navigationhistorybehavior
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryHandling {
+    /// Appends a new entry after the current entry.
+    Push,
+    /// Replaces the current entry.
+    Replace,
+    /// Loads the document of the current entry again.
+    Reload,
+    /// Loads the document of an entry which the history is traversed to. The current entry is
+    /// already changed.
+    Traverse,
+}
+
+/// https://html.spec.whatwg.org/multipage/browsing-the-web.html#session-history-entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionHistoryEntry {
+    /// https://html.spec.whatwg.org/multipage/browsing-the-web.html#she-url
+    url: String,
+    /// The state given to `history.pushState()` or `history.replaceState()`. The state is
+    /// serialized to a string because the values of the JavaScript runtime are strings and
+    /// numbers.
+    /// https://html.spec.whatwg.org/multipage/browsing-the-web.html#she-classic-history-api-state
+    state: Option<String>,
+    /// The identifier of the document state. Entries created by the History API and fragment
+    /// navigations share the document state with the entry they are created from, and moving
+    /// between them doesn't load a document.
+    /// https://html.spec.whatwg.org/multipage/browsing-the-web.html#she-document-state
+    document_state: usize,
+}
+
+impl SessionHistoryEntry {
+    pub fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    pub fn state(&self) -> Option<String> {
+        self.state.clone()
+    }
+
+    pub fn document_state(&self) -> usize {
+        self.document_state
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionHistory {
+    entries: Vec<SessionHistoryEntry>,
+    /// The index of the current session history entry.
+    /// https://html.spec.whatwg.org/multipage/document-sequences.html#nav-current-history-entry
+    current: usize,
+    /// The identifier given to the next document state.
+    next_document_state: usize,
+}
+
+impl SessionHistory {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            current: 0,
+            next_document_state: 0,
+        }
+    }
+
+    /// https://html.spec.whatwg.org/multipage/nav-history-apis.html#dom-history-length
+    pub fn length(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// https://html.spec.whatwg.org/multipage/document-sequences.html#nav-current-history-entry
+    pub fn current_entry(&self) -> Option<SessionHistoryEntry> {
+        self.entries.get(self.current).cloned()
+    }
+
+    /// Adds an entry for a newly loaded document at `url`.
+    pub fn add_document_entry(&mut self, url: String, history_handling: HistoryHandling) {
+        let entry = SessionHistoryEntry {
+            url,
+            state: None,
+            document_state: self.next_document_state,
+        };
+        self.next_document_state += 1;
+        self.add_entry(entry, history_handling);
+    }
+
+    /// Adds an entry which shares the document of the current entry, for a fragment navigation or
+    /// the History API.
+    /// https://html.spec.whatwg.org/multipage/browsing-the-web.html#url-and-history-update-steps
+    pub fn add_same_document_entry(
+        &mut self,
+        url: String,
+        state: Option<String>,
+        history_handling: HistoryHandling,
+    ) {
+        let document_state = match self.current_entry() {
+            Some(entry) => entry.document_state,
+            None => {
+                self.next_document_state += 1;
+                self.next_document_state - 1
+            }
+        };
+        let entry = SessionHistoryEntry {
+            url,
+            state,
+            document_state,
+        };
+        self.add_entry(entry, history_handling);
+    }
+
+    fn add_entry(&mut self, entry: SessionHistoryEntry, history_handling: HistoryHandling) {
+        if history_handling == HistoryHandling::Replace && !self.entries.is_empty() {
+            self.entries[self.current] = entry;
+            return;
+        }
+
+        // https://html.spec.whatwg.org/multipage/browsing-the-web.html#finalize-a-cross-document-navigation
+        // "Remove all the entries in navigable's session history entries after the current
+        // entry" and append the new entry.
+        if !self.entries.is_empty() {
+            self.entries.truncate(self.current + 1);
+        }
+        self.entries.push(entry);
+        self.current = self.entries.len() - 1;
+    }
+
+    /// Makes the entry `delta` entries away from the current entry current, and returns it. Returns
+    /// None if there is no such entry.
+    /// https://html.spec.whatwg.org/multipage/browsing-the-web.html#traverse-the-history-by-a-delta
+    pub fn traverse(&mut self, delta: i64) -> Option<SessionHistoryEntry> {
+        // "4. Let targetStepIndex be currentStepIndex plus delta.
+        // 5. If allSteps[targetStepIndex] does not exist, then abort these steps."
+        let index = self.current as i64 + delta;
+        if index < 0 || index as usize >= self.entries.len() {
+            return None;
+        }
+        self.current = index as usize;
+        self.current_entry()
+    }
+}
+
+impl Default for SessionHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_session_history() {
+        let mut history = SessionHistory::new();
+        assert_eq!(None, history.current_entry());
+        assert_eq!(None, history.traverse(-1));
+
+        history.add_document_entry("http://a.test/1".to_string(), HistoryHandling::Push);
+        history.add_same_document_entry(
+            "http://a.test/1#top".to_string(),
+            None,
+            HistoryHandling::Push,
+        );
+        history.add_document_entry("http://a.test/2".to_string(), HistoryHandling::Push);
+        assert_eq!(3, history.length());
+
+        let entry = history.traverse(-2).expect("the first entry");
+        assert_eq!("http://a.test/1", entry.url());
+        assert_eq!(None, history.traverse(-1));
+        let fragment = history.traverse(1).expect("the fragment entry");
+        assert_eq!(entry.document_state(), fragment.document_state());
+
+        // A new entry removes the entries after the current entry.
+        history.add_same_document_entry(
+            "http://a.test/1?page=2".to_string(),
+            Some("2".to_string()),
+            HistoryHandling::Push,
+        );
+        assert_eq!(3, history.length());
+        assert_eq!(None, history.traverse(1));
+
+        history.add_document_entry("http://a.test/3".to_string(), HistoryHandling::Replace);
+        assert_eq!(3, history.length());
+        let current = history.current_entry().expect("the current entry");
+        assert_eq!("http://a.test/3", current.url());
+        assert_eq!(None, current.state());
+        assert_ne!(entry.document_state(), current.document_state());
+    }
+}
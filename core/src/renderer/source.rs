@@ -0,0 +1,237 @@
+//! This module tracks positions in a source text such as an HTML document, a style sheet or a
+//! script.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+/// A position in a source text. Both the line and the column are 1-based, and the column counts
+/// code points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SourcePosition {
+    line: usize,
+    column: usize,
+}
+
+impl SourcePosition {
+    pub fn new(line: usize, column: usize) -> Self {
+        Self { line, column }
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// Returns this position in a text which is embedded at `start` of another text, such as the
+    /// content of a style element in a document.
+    pub fn offset_by(&self, start: SourcePosition) -> SourcePosition {
+        if self.line == 1 {
+            return Self::new(start.line, start.column + self.column - 1);
+        }
+        Self::new(start.line + self.line - 1, self.column)
+    }
+}
+
+impl Default for SourcePosition {
+    fn default() -> Self {
+        Self::new(1, 1)
+    }
+}
+
+impl fmt::Display for SourcePosition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// A range in a source text. `start` and `end` are byte offsets and `end` is exclusive, so that
+/// `&source[range.start()..range.end()]` is the text in the range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SourceRange {
+    start: usize,
+    end: usize,
+    start_position: SourcePosition,
+    end_position: SourcePosition,
+}
+
+impl SourceRange {
+    pub fn new(
+        start: usize,
+        end: usize,
+        start_position: SourcePosition,
+        end_position: SourcePosition,
+    ) -> Self {
+        Self {
+            start,
+            end,
+            start_position,
+            end_position,
+        }
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    /// Returns the position of the first character in this range.
+    pub fn start_position(&self) -> SourcePosition {
+        self.start_position
+    }
+
+    /// Returns the position just after the last character in this range.
+    pub fn end_position(&self) -> SourcePosition {
+        self.end_position
+    }
+
+    /// Returns a range from the start of this range to the end of `other`.
+    pub fn extend_to(&self, other: &SourceRange) -> SourceRange {
+        if other.end < self.end {
+            return *self;
+        }
+        Self::new(
+            self.start,
+            other.end,
+            self.start_position,
+            other.end_position,
+        )
+    }
+
+    pub fn contains(&self, offset: usize) -> bool {
+        self.start <= offset && offset < self.end
+    }
+}
+
+impl fmt::Display for SourceRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}-{} [{}..{}]",
+            self.start_position, self.end_position, self.start, self.end
+        )
+    }
+}
+
+/// An index of the start of each line in a source text, which maps a code point index in the text
+/// to a line and a column.
+///
+/// Tokenizers don't track a position incrementally because they can jump over several characters
+/// at once (e.g. when they consume a keyword or a named character reference).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineMap {
+    /// The index of the first code point of each line.
+    line_starts: Vec<usize>,
+    /// The byte offset of each code point, and the length of the text in bytes at the end.
+    byte_offsets: Vec<usize>,
+}
+
+impl LineMap {
+    pub fn new(input: &[char]) -> Self {
+        let mut line_starts = Vec::from([0]);
+        let mut byte_offsets = Vec::with_capacity(input.len() + 1);
+        let mut offset = 0;
+        for (i, c) in input.iter().enumerate() {
+            if *c == '\n' {
+                line_starts.push(i + 1);
+            }
+            byte_offsets.push(offset);
+            offset += c.len_utf8();
+        }
+        byte_offsets.push(offset);
+        Self {
+            line_starts,
+            byte_offsets,
+        }
+    }
+
+    /// Returns the byte offset of the code point at `index`.
+    pub fn byte_offset(&self, index: usize) -> usize {
+        match self.byte_offsets.get(index) {
+            Some(offset) => *offset,
+            None => *self.byte_offsets.last().unwrap_or(&0),
+        }
+    }
+
+    /// Returns the range of the code points from `start` to `end` (exclusive).
+    pub fn range(&self, start: usize, end: usize) -> SourceRange {
+        SourceRange::new(
+            self.byte_offset(start),
+            self.byte_offset(end),
+            self.position(start),
+            self.position(end),
+        )
+    }
+
+    /// Returns the position of the code point at `index`.
+    pub fn position(&self, index: usize) -> SourcePosition {
+        let line = match self.line_starts.binary_search(&index) {
+            Ok(line) => line,
+            Err(next_line) => next_line - 1,
+        };
+        SourcePosition::new(line + 1, index - self.line_starts[line] + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_position() {
+        let input: Vec<char> = "ab\n\ncd\n".chars().collect();
+        let map = LineMap::new(&input);
+        assert_eq!(SourcePosition::new(1, 1), map.position(0));
+        assert_eq!(SourcePosition::new(1, 3), map.position(2));
+        assert_eq!(SourcePosition::new(2, 1), map.position(3));
+        assert_eq!(SourcePosition::new(3, 2), map.position(5));
+        // A position past the end of the input is on the last line.
+        assert_eq!(SourcePosition::new(4, 1), map.position(7));
+        assert_eq!(SourcePosition::new(4, 3), map.position(9));
+        assert_eq!("3:2".to_string(), map.position(5).to_string());
+    }
+
+    #[test]
+    fn test_offset_by() {
+        let start = SourcePosition::new(3, 8);
+        assert_eq!(
+            SourcePosition::new(3, 8),
+            SourcePosition::default().offset_by(start)
+        );
+        assert_eq!(
+            SourcePosition::new(3, 10),
+            SourcePosition::new(1, 3).offset_by(start)
+        );
+        assert_eq!(
+            SourcePosition::new(4, 2),
+            SourcePosition::new(2, 2).offset_by(start)
+        );
+    }
+
+    #[test]
+    fn test_range() {
+        let source = "<p>\u{3042}</p>\nb";
+        let input: Vec<char> = source.chars().collect();
+        let map = LineMap::new(&input);
+        // The range of "あ</p>".
+        let range = map.range(3, 8);
+        assert_eq!(3, range.start());
+        assert_eq!(10, range.end());
+        assert_eq!("\u{3042}</p>", &source[range.start()..range.end()]);
+        assert_eq!("1:4-1:9 [3..10]".to_string(), range.to_string());
+
+        let b = map.range(9, 10);
+        assert_eq!(
+            "1:1-2:2 [0..12]".to_string(),
+            map.range(0, 1).extend_to(&b).to_string()
+        );
+        assert!(b.contains(11));
+        assert!(!b.contains(12));
+    }
+}
@@ -0,0 +1,24 @@
+//! This module has the fixtures shared by the unit tests of the renderer.
+
+use crate::browser::Browser;
+use crate::renderer::dom::node::Node;
+use crate::renderer::dom::window::Window;
+use crate::renderer::html::parser::HtmlParser;
+use crate::renderer::html::token::HtmlTokenizer;
+use alloc::rc::Rc;
+use alloc::string::ToString;
+use core::cell::RefCell;
+
+/// Parses `html` and returns the window which holds the document.
+pub fn create_window(html: &str) -> Rc<RefCell<Window>> {
+    let browser = Browser::new();
+    let t = HtmlTokenizer::new(Rc::downgrade(&browser), html.to_string());
+    HtmlParser::new(Rc::downgrade(&browser), t).construct_tree()
+}
+
+/// Parses `html` and returns the document.
+pub fn create_document(html: &str) -> Rc<RefCell<Node>> {
+    let window = create_window(html);
+    let document = RefCell::borrow(&window).document();
+    document
+}
@@ -0,0 +1,500 @@
+//! UTS #46: Unicode IDNA Compatibility Processing
+//! https://www.unicode.org/reports/tr46/
+//!
+//! RFC 3492 - Punycode: https://datatracker.ietf.org/doc/html/rfc3492
+//!
+//! This module converts a host name which contains non-ASCII characters (e.g. "bücher.example")
+//! into the ASCII form which DNS can resolve (e.g. "xn--bcher-kva.example"), and vice versa.
+//! The mapping step implements a subset of the IDNA mapping table: case folding, full-width
+//! forms, label separators and ignored characters. Unicode normalization is not supported.
+
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+/// https://datatracker.ietf.org/doc/html/rfc3492#section-5
+const BASE: u32 = 36;
+const TMIN: u32 = 1;
+const TMAX: u32 = 26;
+const SKEW: u32 = 38;
+const DAMP: u32 = 700;
+const INITIAL_BIAS: u32 = 72;
+const INITIAL_N: u32 = 128;
+
+/// The prefix of a label encoded by Punycode.
+/// https://www.unicode.org/reports/tr46/#Notation
+const ACE_PREFIX: &str = "xn--";
+
+/// https://datatracker.ietf.org/doc/html/rfc3492#section-6.1
+fn adapt(delta: u32, num_points: u32, first_time: bool) -> u32 {
+    let mut delta = if first_time { delta / DAMP } else { delta / 2 };
+    delta += delta / num_points;
+
+    let mut k = 0;
+    while delta > ((BASE - TMIN) * TMAX) / 2 {
+        delta /= BASE - TMIN;
+        k += BASE;
+    }
+
+    k + (((BASE - TMIN + 1) * delta) / (delta + SKEW))
+}
+
+/// Returns the threshold value `t` for the position `k`.
+/// https://datatracker.ietf.org/doc/html/rfc3492#section-6.2
+fn threshold(k: u32, bias: u32) -> u32 {
+    if k <= bias {
+        TMIN
+    } else if k >= bias + TMAX {
+        TMAX
+    } else {
+        k - bias
+    }
+}
+
+/// https://datatracker.ietf.org/doc/html/rfc3492#section-5
+fn encode_digit(d: u32) -> char {
+    match d {
+        0..=25 => (b'a' + d as u8) as char,
+        _ => (b'0' + (d - 26) as u8) as char,
+    }
+}
+
+/// https://datatracker.ietf.org/doc/html/rfc3492#section-5
+fn decode_digit(c: char) -> Option<u32> {
+    match c {
+        'a'..='z' => Some(c as u32 - 'a' as u32),
+        'A'..='Z' => Some(c as u32 - 'A' as u32),
+        '0'..='9' => Some(c as u32 - '0' as u32 + 26),
+        _ => None,
+    }
+}
+
+/// Encodes a Unicode label to Punycode without the "xn--" prefix.
+/// https://datatracker.ietf.org/doc/html/rfc3492#section-6.3
+pub fn punycode_encode(input: &str) -> Result<String, String> {
+    let input: Vec<u32> = input.chars().map(|c| c as u32).collect();
+    let mut output = String::new();
+
+    // "Copy them to the output in order, followed by a delimiter if b > 0."
+    for c in &input {
+        if *c < INITIAL_N {
+            output.push(*c as u8 as char);
+        }
+    }
+    let basic_len = output.len() as u32;
+    let mut handled = basic_len;
+    if basic_len > 0 {
+        output.push('-');
+    }
+
+    let mut n = INITIAL_N;
+    let mut delta: u32 = 0;
+    let mut bias = INITIAL_BIAS;
+
+    while (handled as usize) < input.len() {
+        // "let m = the minimum {non-basic} code point >= n in the input"
+        let m = match input.iter().filter(|c| **c >= n).min() {
+            Some(m) => *m,
+            None => break,
+        };
+
+        delta = (m - n)
+            .checked_mul(handled + 1)
+            .and_then(|d| d.checked_add(delta))
+            .ok_or("punycode overflow".to_string())?;
+        n = m;
+
+        for c in &input {
+            if *c < n {
+                delta = delta
+                    .checked_add(1)
+                    .ok_or("punycode overflow".to_string())?;
+            }
+
+            if *c == n {
+                let mut q = delta;
+                let mut k = BASE;
+                loop {
+                    let t = threshold(k, bias);
+                    if q < t {
+                        break;
+                    }
+                    output.push(encode_digit(t + (q - t) % (BASE - t)));
+                    q = (q - t) / (BASE - t);
+                    k += BASE;
+                }
+                output.push(encode_digit(q));
+                bias = adapt(delta, handled + 1, handled == basic_len);
+                delta = 0;
+                handled += 1;
+            }
+        }
+
+        delta += 1;
+        n += 1;
+    }
+
+    Ok(output)
+}
+
+/// Decodes a Punycode label without the "xn--" prefix to a Unicode string.
+/// https://datatracker.ietf.org/doc/html/rfc3492#section-6.2
+pub fn punycode_decode(input: &str) -> Result<String, String> {
+    let (basic, extended) = match input.rfind('-') {
+        Some(index) => (&input[..index], &input[index + 1..]),
+        None => ("", input),
+    };
+
+    if !basic.is_ascii() {
+        return Err("punycode contains a non-basic code point".to_string());
+    }
+
+    let mut output: Vec<char> = basic.chars().collect();
+    let mut n = INITIAL_N;
+    let mut i: u32 = 0;
+    let mut bias = INITIAL_BIAS;
+    let mut chars = extended.chars().peekable();
+
+    while chars.peek().is_some() {
+        let old_i = i;
+        let mut w: u32 = 1;
+        let mut k = BASE;
+        loop {
+            let digit = match chars.next().and_then(decode_digit) {
+                Some(d) => d,
+                None => return Err("invalid punycode digit".to_string()),
+            };
+            i = digit
+                .checked_mul(w)
+                .and_then(|d| d.checked_add(i))
+                .ok_or("punycode overflow".to_string())?;
+
+            let t = threshold(k, bias);
+            if digit < t {
+                break;
+            }
+            w = w
+                .checked_mul(BASE - t)
+                .ok_or("punycode overflow".to_string())?;
+            k += BASE;
+        }
+
+        let len = output.len() as u32 + 1;
+        bias = adapt(i - old_i, len, old_i == 0);
+        n = n
+            .checked_add(i / len)
+            .ok_or("punycode overflow".to_string())?;
+        i %= len;
+
+        let c = match char::from_u32(n) {
+            Some(c) => c,
+            None => return Err("punycode decodes to an invalid code point".to_string()),
+        };
+        output.insert(i as usize, c);
+        i += 1;
+    }
+
+    Ok(output.into_iter().collect())
+}
+
+/// https://www.unicode.org/reports/tr46/#IDNA_Mapping_Table
+enum Mapping {
+    Valid(char),
+    Mapped(char),
+    Ignored,
+    Disallowed,
+}
+
+/// Looks up the IDNA mapping table for `c`. This covers a subset of the table.
+fn map_char(c: char) -> Mapping {
+    match c {
+        // Forbidden host code points.
+        // https://url.spec.whatwg.org/#forbidden-host-code-point
+        '\u{0}'..='\u{20}'
+        | '#'
+        | '%'
+        | '/'
+        | ':'
+        | '<'
+        | '>'
+        | '?'
+        | '@'
+        | '['
+        | '\\'
+        | ']'
+        | '^'
+        | '|'
+        | '\u{7f}' => Mapping::Disallowed,
+        'A'..='Z' => Mapping::Mapped(c.to_ascii_lowercase()),
+        _ if c.is_ascii() => Mapping::Valid(c),
+        // Soft hyphen, combining grapheme joiner, Mongolian variation selectors, zero width space,
+        // word joiner, variation selectors and zero width no-break space are ignored.
+        '\u{ad}'
+        | '\u{34f}'
+        | '\u{180b}'..='\u{180d}'
+        | '\u{200b}'
+        | '\u{2060}'
+        | '\u{fe00}'..='\u{fe0f}'
+        | '\u{feff}' => Mapping::Ignored,
+        // Ideographic full stop, full-width full stop and half-width ideographic full stop are
+        // label separators.
+        '\u{3002}' | '\u{ff0e}' | '\u{ff61}' => Mapping::Mapped('.'),
+        // Full-width ASCII variants are mapped to ASCII.
+        '\u{ff01}'..='\u{ff5e}' => match char::from_u32(c as u32 - 0xfee0) {
+            Some(ascii) => match map_char(ascii) {
+                Mapping::Valid(m) | Mapping::Mapped(m) => Mapping::Mapped(m),
+                other => other,
+            },
+            None => Mapping::Disallowed,
+        },
+        _ if c.is_control() => Mapping::Disallowed,
+        _ => {
+            // Case folding. A character whose lower case has multiple characters is not supported.
+            let mut lower = c.to_lowercase();
+            match (lower.next(), lower.next()) {
+                (Some(l), None) if l != c => Mapping::Mapped(l),
+                _ => Mapping::Valid(c),
+            }
+        }
+    }
+}
+
+/// https://www.unicode.org/reports/tr46/#ProcessingStepMap
+fn map(domain: &str) -> Result<String, String> {
+    let mut result = String::new();
+    for c in domain.chars() {
+        match map_char(c) {
+            Mapping::Valid(c) | Mapping::Mapped(c) => result.push(c),
+            Mapping::Ignored => {}
+            Mapping::Disallowed => {
+                return Err(alloc::format!("disallowed code point {:?} in host", c));
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Converts a domain name to the ASCII form which is used for DNS lookup.
+/// https://www.unicode.org/reports/tr46/#ToASCII
+pub fn domain_to_ascii(domain: &str) -> Result<String, String> {
+    let mapped = map(domain)?;
+
+    let mut labels = Vec::new();
+    for label in mapped.split('.') {
+        if label.is_ascii() {
+            // https://www.unicode.org/reports/tr46/#Validity_Criteria
+            // A label starting with "xn--" must be a valid Punycode.
+            if let Some(encoded) = label.strip_prefix(ACE_PREFIX) {
+                let decoded = punycode_decode(encoded)?;
+                if decoded.is_ascii() {
+                    return Err(alloc::format!("label {:?} is not a valid A-label", label));
+                }
+            }
+            labels.push(label.to_string());
+            continue;
+        }
+
+        if label.starts_with('-') || label.ends_with('-') {
+            return Err(alloc::format!(
+                "label {:?} starts or ends with a hyphen",
+                label
+            ));
+        }
+
+        labels.push(String::from(ACE_PREFIX) + &punycode_encode(label)?);
+    }
+
+    Ok(labels.join("."))
+}
+
+/// Converts a domain name to the Unicode form. A label which fails to be decoded is kept as it
+/// is.
+/// https://www.unicode.org/reports/tr46/#ToUnicode
+pub fn domain_to_unicode(domain: &str) -> String {
+    domain
+        .split('.')
+        .map(|label| {
+            let lowercase = label.to_ascii_lowercase();
+            match lowercase.strip_prefix(ACE_PREFIX) {
+                Some(encoded) => match punycode_decode(encoded) {
+                    Ok(decoded) => decoded,
+                    Err(_) => label.to_string(),
+                },
+                None => label.to_string(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(".")
+}
+
+/// A simplified Unicode script property.
+/// https://www.unicode.org/reports/tr24/
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Script {
+    /// Digits, hyphens and other characters which are used with any script.
+    Common,
+    Latin,
+    Greek,
+    Cyrillic,
+    Armenian,
+    Hebrew,
+    Arabic,
+    Thai,
+    Hangul,
+    Hiragana,
+    Katakana,
+    Bopomofo,
+    Han,
+    Other,
+}
+
+impl Script {
+    fn from_char(c: char) -> Self {
+        match c as u32 {
+            0x30..=0x39 | 0x2d | 0x5f | 0x30fc => Script::Common,
+            0x41..=0x5a | 0x61..=0x7a | 0xc0..=0x24f | 0x1e00..=0x1eff => Script::Latin,
+            0x370..=0x3ff | 0x1f00..=0x1fff => Script::Greek,
+            0x400..=0x52f => Script::Cyrillic,
+            0x530..=0x58f => Script::Armenian,
+            0x590..=0x5ff => Script::Hebrew,
+            0x600..=0x6ff | 0x750..=0x77f => Script::Arabic,
+            0xe00..=0xe7f => Script::Thai,
+            0x1100..=0x11ff | 0x3130..=0x318f | 0xac00..=0xd7af => Script::Hangul,
+            0x3040..=0x309f => Script::Hiragana,
+            0x30a0..=0x30ff | 0x31f0..=0x31ff | 0xff66..=0xff9f => Script::Katakana,
+            0x3100..=0x312f | 0x31a0..=0x31bf => Script::Bopomofo,
+            0x3400..=0x4dbf | 0x4e00..=0x9fff | 0xf900..=0xfaff | 0x20000..=0x2ffff => Script::Han,
+            _ => Script::Other,
+        }
+    }
+}
+
+/// Returns true if `label` mixes scripts in a way which is not allowed by the "Highly
+/// Restrictive" profile. The combinations of Latin, Han and a script commonly written with Han
+/// (Japanese, Chinese and Korean) are allowed.
+/// https://www.unicode.org/reports/tr39/#Restriction_Level_Detection
+fn is_mixed_script(label: &str) -> bool {
+    let mut scripts: Vec<Script> = Vec::new();
+    for c in label.chars() {
+        let script = Script::from_char(c);
+        if script == Script::Other {
+            // The script of this character is unknown, so it can't be displayed safely.
+            return true;
+        }
+        if script != Script::Common && !scripts.contains(&script) {
+            scripts.push(script);
+        }
+    }
+
+    if scripts.len() <= 1 {
+        return false;
+    }
+
+    let allowed_sets = [
+        [
+            Script::Latin,
+            Script::Han,
+            Script::Hiragana,
+            Script::Katakana,
+        ]
+        .as_slice(),
+        [Script::Latin, Script::Han, Script::Bopomofo].as_slice(),
+        [Script::Latin, Script::Han, Script::Hangul].as_slice(),
+    ];
+
+    !allowed_sets
+        .iter()
+        .any(|set| scripts.iter().all(|s| set.contains(s)))
+}
+
+/// Returns the host to show in the address bar. The Unicode form is used unless a label mixes
+/// scripts, which can be used to spoof another host (e.g. "аpple" with a Cyrillic "а").
+pub fn domain_to_display(domain: &str) -> String {
+    let unicode = domain_to_unicode(domain);
+
+    if unicode
+        .split('.')
+        .any(|label| !label.is_ascii() && is_mixed_script(label))
+    {
+        return domain.to_string();
+    }
+
+    unicode
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_punycode_encode() {
+        assert_eq!(Ok("bcher-kva".to_string()), punycode_encode("bücher"));
+        assert_eq!(Ok("mnchen-3ya".to_string()), punycode_encode("münchen"));
+        assert_eq!(Ok("wgv71a119e".to_string()), punycode_encode("日本語"));
+        assert_eq!(
+            Ok("eckwd4c7cu47r2wf".to_string()),
+            punycode_encode("ドメイン名例")
+        );
+    }
+
+    #[test]
+    fn test_punycode_decode() {
+        assert_eq!(Ok("bücher".to_string()), punycode_decode("bcher-kva"));
+        assert_eq!(Ok("bücher-".to_string()), punycode_decode("bcher--3ya"));
+        assert_eq!(Ok("日本語".to_string()), punycode_decode("wgv71a119e"));
+        assert!(punycode_decode("bcher-k!a").is_err());
+    }
+
+    #[test]
+    fn test_domain_to_ascii() {
+        assert_eq!(
+            Ok("example.com".to_string()),
+            domain_to_ascii("EXAMPLE.com")
+        );
+        assert_eq!(
+            Ok("xn--bcher-kva.example".to_string()),
+            domain_to_ascii("Bücher.example")
+        );
+        assert_eq!(
+            Ok("xn--wgv71a119e.jp".to_string()),
+            domain_to_ascii("日本語。ｊｐ")
+        );
+        assert_eq!(
+            Ok("example.com".to_string()),
+            domain_to_ascii("exam\u{ad}ple.com")
+        );
+    }
+
+    #[test]
+    fn test_domain_to_ascii_invalid() {
+        assert!(domain_to_ascii("exa mple.com").is_err());
+        assert!(domain_to_ascii("-bücher.example").is_err());
+        assert!(domain_to_ascii("xn--abc-.example").is_err());
+    }
+
+    #[test]
+    fn test_domain_to_unicode() {
+        assert_eq!(
+            "bücher.example".to_string(),
+            domain_to_unicode("xn--bcher-kva.example")
+        );
+        assert_eq!("example.com".to_string(), domain_to_unicode("example.com"));
+    }
+
+    #[test]
+    fn test_domain_to_display() {
+        assert_eq!(
+            "bücher.example".to_string(),
+            domain_to_display("xn--bcher-kva.example")
+        );
+        assert_eq!(
+            "ドメイン名例.jp".to_string(),
+            domain_to_display("xn--eckwd4c7cu47r2wf.jp")
+        );
+        // "аpple" with a Cyrillic "а" is shown as ASCII.
+        assert_eq!(
+            "xn--pple-43d.com".to_string(),
+            domain_to_display("xn--pple-43d.com")
+        );
+    }
+}
@@ -0,0 +1,565 @@
+//! RFC 1738 - Uniform Resource Locators (URL): https://datatracker.ietf.org/doc/html/rfc1738
+//! This module only supports HTTP URL scheme defined at RFC 1738 section 3.3.
+//! https://datatracker.ietf.org/doc/html/rfc1738#section-3.3
+
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+pub mod idna;
+pub mod search_params;
+
+use crate::url::search_params::UrlSearchParams;
+
+/// The HTTP URL scheme is used to designate Internet resources accessible using HTTP (HyperText Transfer Protocol).
+/// http://<host>:<port>/<path>?<searchpart>
+/// https://datatracker.ietf.org/doc/html/rfc1738#section-3.3
+#[derive(Debug, Clone, PartialEq)]
+pub struct Url {
+    url: String,
+    host: String,
+    port: String,
+    path: String,
+    searchpart: String,
+    /// https://url.spec.whatwg.org/#concept-url-fragment
+    fragment: String,
+}
+
+impl Url {
+    pub fn new(url: String) -> Self {
+        Self {
+            url,
+            host: "".to_string(),
+            port: "".to_string(),
+            path: "".to_string(),
+            searchpart: "".to_string(),
+            fragment: "".to_string(),
+        }
+    }
+
+    pub fn parse(&mut self) -> Result<Self, String> {
+        if !self.is_http() {
+            return Err("Only HTTP scheme is supported.".to_string());
+        }
+
+        self.host = self.extract_host()?;
+        self.port = self.extract_port();
+        self.path = self.extract_path();
+        self.searchpart = self.extract_searchpart();
+        self.fragment = self.extract_fragment();
+
+        Ok(self.clone())
+    }
+
+    fn is_http(&self) -> bool {
+        self.url.contains("http://")
+    }
+
+    /// Returns the host and the port of the URL.
+    /// https://url.spec.whatwg.org/#authority-state
+    fn authority(&self) -> &str {
+        // "Otherwise, if one of the following is true: c is the EOF code point, U+002F (/), U+003F
+        // (?), or U+0023 (#) ... decrease pointer by buffer's code point length + 1, ... and set
+        // state to host state."
+        let rest = self.url.trim_start_matches("http://");
+        match rest.find(['/', '?', '#']) {
+            Some(index) => &rest[..index],
+            None => rest,
+        }
+    }
+
+    /// Returns the host in the ASCII form. A host which contains non-ASCII characters is
+    /// converted by the IDNA processing.
+    /// https://url.spec.whatwg.org/#concept-host-parser
+    fn extract_host(&self) -> Result<String, String> {
+        let authority = self.authority();
+        let host = if let Some(index) = authority.find(':') {
+            &authority[..index]
+        } else {
+            authority
+        };
+
+        idna::domain_to_ascii(host)
+    }
+
+    fn extract_port(&self) -> String {
+        let authority = self.authority();
+        if let Some(index) = authority.find(':') {
+            authority[index + 1..].to_string()
+        } else {
+            // 80 is the default port number of HTTP scheme.
+            // Default port numbers are defined by Internet Assigned Numbers Authority (IANA).
+            // https://www.iana.org/assignments/service-names-port-numbers/service-names-port-numbers.xhtml
+            "80".to_string()
+        }
+    }
+
+    /// Returns the path, the query and the fragment, which follow the host and the port.
+    fn path_query_and_fragment(&self) -> &str {
+        let rest = self.url.trim_start_matches("http://");
+        &rest[self.authority().len()..]
+    }
+
+    fn extract_path(&self) -> String {
+        let rest = self.path_query_and_fragment();
+        let path = match rest.find(['?', '#']) {
+            Some(index) => &rest[..index],
+            None => rest,
+        };
+        path.strip_prefix('/').unwrap_or(path).to_string()
+    }
+
+    fn extract_searchpart(&self) -> String {
+        // "query state": the query ends at U+0023 (#).
+        let rest = match self.path_query_and_fragment().split_once('#') {
+            Some((r, _)) => r,
+            None => self.path_query_and_fragment(),
+        };
+        match rest.split_once('?') {
+            Some((_, query)) => query.to_string(),
+            None => "".to_string(),
+        }
+    }
+
+    fn extract_fragment(&self) -> String {
+        match self.path_query_and_fragment().split_once('#') {
+            Some((_, fragment)) => fragment.to_string(),
+            None => "".to_string(),
+        }
+    }
+
+    pub fn host(&self) -> String {
+        self.host.clone()
+    }
+
+    pub fn port(&self) -> String {
+        self.port.clone()
+    }
+
+    pub fn path(&self) -> String {
+        self.path.clone()
+    }
+
+    pub fn searchpart(&self) -> String {
+        self.searchpart.clone()
+    }
+
+    pub fn fragment(&self) -> String {
+        self.fragment.clone()
+    }
+
+    /// Returns the fragment with a leading "#", or an empty string if the fragment is empty.
+    /// https://url.spec.whatwg.org/#dom-url-hash
+    pub fn hash(&self) -> String {
+        if self.fragment.is_empty() {
+            return "".to_string();
+        }
+        "#".to_string() + &self.fragment
+    }
+
+    /// Returns the query string with a leading "?", or an empty string if the query is empty.
+    /// https://url.spec.whatwg.org/#dom-url-search
+    pub fn search(&self) -> String {
+        if self.searchpart.is_empty() {
+            return "".to_string();
+        }
+        "?".to_string() + &self.searchpart
+    }
+
+    /// https://url.spec.whatwg.org/#dom-url-searchparams
+    pub fn search_params(&self) -> UrlSearchParams {
+        UrlSearchParams::new(&self.searchpart)
+    }
+
+    /// Replaces the query string with the serialization of `params`.
+    /// https://url.spec.whatwg.org/#concept-urlsearchparams-update
+    pub fn set_search_params(&mut self, params: &UrlSearchParams) {
+        self.searchpart = params.to_string();
+        self.url = self.serialize(&self.host, false);
+    }
+
+    /// https://url.spec.whatwg.org/#dom-url-href
+    pub fn href(&self) -> String {
+        self.serialize(&self.host, false)
+    }
+
+    /// https://url.spec.whatwg.org/#concept-url-fragment
+    pub fn set_fragment(&mut self, fragment: String) {
+        self.fragment = fragment;
+        self.url = self.serialize(&self.host, false);
+    }
+
+    /// https://url.spec.whatwg.org/#concept-url-equals
+    pub fn equals(&self, other: &Url, exclude_fragments: bool) -> bool {
+        // "1. Let serializedA be the result of serializing A, with exclude fragment set to
+        // excludeFragments.
+        // 2. Let serializedB be the result of serializing B, with exclude fragment set to
+        // excludeFragments.
+        // 3. Return true if serializedA is serializedB; otherwise false."
+        self.serialize(&self.host, exclude_fragments)
+            == other.serialize(&other.host, exclude_fragments)
+    }
+
+    /// Returns the URL string that `input` refers to when `input` is parsed with this URL as the
+    /// base URL. An absolute URL is returned as it is.
+    /// https://url.spec.whatwg.org/#concept-basic-url-parser
+    pub fn resolve(&self, input: &str) -> String {
+        let input = input.trim();
+
+        // "scheme state": an input that starts with a scheme is an absolute URL.
+        if let Some((scheme, _)) = input.split_once(':') {
+            if scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+            {
+                return input.to_string();
+            }
+        }
+
+        // "special authority ignore slashes state": an input that starts with "//" has its own
+        // host.
+        if let Some(rest) = input.strip_prefix("//") {
+            return "http://".to_string() + rest;
+        }
+
+        // "fragment state"
+        let (input, fragment) = match input.split_once('#') {
+            Some((i, f)) => (i, f.to_string()),
+            None => (input, "".to_string()),
+        };
+
+        let mut url = self.clone();
+        url.fragment = fragment;
+        // "relative state"
+        if input.is_empty() {
+            // The URL is the same as the base URL except for the fragment.
+        } else if let Some(query) = input.strip_prefix('?') {
+            url.searchpart = query.to_string();
+        } else {
+            let (path, query) = match input.split_once('?') {
+                Some((p, q)) => (p, q),
+                None => (input, ""),
+            };
+            let path = match path.strip_prefix('/') {
+                // "path start state": an absolute path replaces the path of the base URL.
+                Some(p) => p.to_string(),
+                // "Remove url's path's last item, if any." and append the input.
+                None => match self.path.rfind('/') {
+                    Some(index) => self.path[..index + 1].to_string() + path,
+                    None => path.to_string(),
+                },
+            };
+            url.path = remove_dot_segments(&path);
+            url.searchpart = query.to_string();
+        }
+
+        url.serialize(&url.host, false)
+    }
+
+    /// Returns the URL to show in the address bar. The host is shown in the Unicode form unless
+    /// it may be used for spoofing.
+    pub fn display_url(&self) -> String {
+        self.serialize(&idna::domain_to_display(&self.host), false)
+    }
+
+    /// https://url.spec.whatwg.org/#concept-url-serializer
+    fn serialize(&self, host: &str, exclude_fragment: bool) -> String {
+        let mut url = "http://".to_string() + host;
+        if self.port != "80" {
+            url.push(':');
+            url.push_str(&self.port);
+        }
+        if !self.path.is_empty() || !self.searchpart.is_empty() {
+            url.push('/');
+            url.push_str(&self.path);
+        }
+        if !self.searchpart.is_empty() {
+            url.push('?');
+            url.push_str(&self.searchpart);
+        }
+        if !exclude_fragment && !self.fragment.is_empty() {
+            url.push('#');
+            url.push_str(&self.fragment);
+        }
+        url
+    }
+}
+
+/// Removes "." and ".." segments from a path that doesn't start with "/".
+/// https://url.spec.whatwg.org/#single-dot-path-segment
+/// https://url.spec.whatwg.org/#double-dot-path-segment
+fn remove_dot_segments(path: &str) -> String {
+    let segments: Vec<&str> = path.split('/').collect();
+    let mut output: Vec<&str> = Vec::new();
+    for (i, segment) in segments.iter().enumerate() {
+        let is_last = i == segments.len() - 1;
+        match *segment {
+            "." | "%2e" | "%2E" => {
+                if is_last {
+                    output.push("");
+                }
+            }
+            ".." | ".%2e" | ".%2E" | "%2e." | "%2E." | "%2e%2e" | "%2E%2E" => {
+                output.pop();
+                if is_last {
+                    output.push("");
+                }
+            }
+            _ => output.push(segment),
+        }
+    }
+    output.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url() {
+        let url = "http://example.com".to_string();
+        let expected = Ok(Url {
+            url: url.clone(),
+            host: "example.com".to_string(),
+            port: "80".to_string(),
+            path: "".to_string(),
+            searchpart: "".to_string(),
+            fragment: "".to_string(),
+        });
+        assert_eq!(expected, Url::new(url).parse());
+    }
+
+    #[test]
+    fn test_url_with_port() {
+        let url = "http://example.com:8888".to_string();
+        let expected = Ok(Url {
+            url: url.clone(),
+            host: "example.com".to_string(),
+            port: "8888".to_string(),
+            path: "".to_string(),
+            searchpart: "".to_string(),
+            fragment: "".to_string(),
+        });
+        assert_eq!(expected, Url::new(url).parse());
+    }
+
+    #[test]
+    fn test_url_with_path() {
+        let url = "http://example.com/index.html".to_string();
+        let expected = Ok(Url {
+            url: url.clone(),
+            host: "example.com".to_string(),
+            port: "80".to_string(),
+            path: "index.html".to_string(),
+            searchpart: "".to_string(),
+            fragment: "".to_string(),
+        });
+        assert_eq!(expected, Url::new(url).parse());
+    }
+
+    #[test]
+    fn test_url_with_port_path() {
+        let url = "http://example.com:8888/index.html".to_string();
+        let expected = Ok(Url {
+            url: url.clone(),
+            host: "example.com".to_string(),
+            port: "8888".to_string(),
+            path: "index.html".to_string(),
+            searchpart: "".to_string(),
+            fragment: "".to_string(),
+        });
+        assert_eq!(expected, Url::new(url).parse());
+    }
+
+    #[test]
+    fn test_url_with_port_path_searchpart() {
+        let url = "http://example.com:8888/index.html?a=123&b=456".to_string();
+        let expected = Ok(Url {
+            url: url.clone(),
+            host: "example.com".to_string(),
+            port: "8888".to_string(),
+            path: "index.html".to_string(),
+            searchpart: "a=123&b=456".to_string(),
+            fragment: "".to_string(),
+        });
+        assert_eq!(expected, Url::new(url).parse());
+    }
+
+    #[test]
+    fn test_localhost() {
+        let url = "http://localhost:8000".to_string();
+        let expected = Ok(Url {
+            url: url.clone(),
+            host: "localhost".to_string(),
+            port: "8000".to_string(),
+            path: "".to_string(),
+            searchpart: "".to_string(),
+            fragment: "".to_string(),
+        });
+        assert_eq!(expected, Url::new(url).parse());
+    }
+
+    #[test]
+    fn test_host_followed_by_query_or_fragment() {
+        let url = Url::new("http://example.com?q=1".to_string())
+            .parse()
+            .expect("failed to parse url");
+        assert_eq!("example.com".to_string(), url.host());
+        assert_eq!("80".to_string(), url.port());
+
+        let url = Url::new("http://example.com:8888#frag".to_string())
+            .parse()
+            .expect("failed to parse url");
+        assert_eq!("example.com".to_string(), url.host());
+        assert_eq!("8888".to_string(), url.port());
+    }
+
+    #[test]
+    fn test_url_with_fragment() {
+        let url = Url::new("http://example.com/b.html#frag".to_string())
+            .parse()
+            .expect("failed to parse url");
+        assert_eq!("b.html".to_string(), url.path());
+        assert_eq!("".to_string(), url.search());
+        assert_eq!("#frag".to_string(), url.hash());
+
+        let url = Url::new("http://example.com/?q=1#frag".to_string())
+            .parse()
+            .expect("failed to parse url");
+        assert_eq!("".to_string(), url.path());
+        assert_eq!("?q=1".to_string(), url.search());
+        assert_eq!(Some("1".to_string()), url.search_params().get("q"));
+        assert_eq!("frag".to_string(), url.fragment());
+        assert_eq!("http://example.com/?q=1#frag".to_string(), url.href());
+    }
+
+    #[test]
+    fn test_idn() {
+        let url = "http://Bücher.example/index.html".to_string();
+        let expected = Ok(Url {
+            url: url.clone(),
+            host: "xn--bcher-kva.example".to_string(),
+            port: "80".to_string(),
+            path: "index.html".to_string(),
+            searchpart: "".to_string(),
+            fragment: "".to_string(),
+        });
+        assert_eq!(expected, Url::new(url).parse());
+    }
+
+    #[test]
+    fn test_invalid_host() {
+        let url = "http://exa mple.com".to_string();
+        assert!(Url::new(url).parse().is_err());
+    }
+
+    #[test]
+    fn test_display_url() {
+        let url = Url::new("http://xn--bcher-kva.example:8888/index.html?a=1".to_string())
+            .parse()
+            .expect("failed to parse url");
+        assert_eq!(
+            "http://bücher.example:8888/index.html?a=1".to_string(),
+            url.display_url()
+        );
+
+        // "аpple" with a Cyrillic "а" is shown as ASCII.
+        let url = Url::new("http://аpple.com".to_string())
+            .parse()
+            .expect("failed to parse url");
+        assert_eq!("http://xn--pple-43d.com".to_string(), url.display_url());
+    }
+
+    #[test]
+    fn test_search_params() {
+        let mut url = Url::new("http://example.com/search?q=saba&lang=ja".to_string())
+            .parse()
+            .expect("failed to parse url");
+        assert_eq!("?q=saba&lang=ja".to_string(), url.search());
+
+        let mut params = url.search_params();
+        assert_eq!(Some("saba".to_string()), params.get("q"));
+
+        params.set("q".to_string(), "toy browser".to_string());
+        params.delete("lang");
+        url.set_search_params(&params);
+        assert_eq!("q=toy+browser".to_string(), url.searchpart());
+        assert_eq!(
+            "http://example.com/search?q=toy+browser".to_string(),
+            url.url
+        );
+    }
+
+    #[test]
+    fn test_resolve() {
+        let base = Url::new("http://example.com:8888/docs/guide/index.html?a=1".to_string())
+            .parse()
+            .expect("failed to parse url");
+        assert_eq!(
+            "https://other.example/".to_string(),
+            base.resolve("https://other.example/")
+        );
+        assert_eq!(
+            "http://other.example/x".to_string(),
+            base.resolve("//other.example/x")
+        );
+        assert_eq!(
+            "http://example.com:8888/search?q=saba".to_string(),
+            base.resolve("/search?q=saba")
+        );
+        assert_eq!(
+            "http://example.com:8888/docs/guide/next.html".to_string(),
+            base.resolve("next.html")
+        );
+        assert_eq!(
+            "http://example.com:8888/docs/api/".to_string(),
+            base.resolve("../api/")
+        );
+        assert_eq!(
+            "http://example.com:8888/docs/guide/index.html?b=2".to_string(),
+            base.resolve("?b=2")
+        );
+        assert_eq!(
+            "http://example.com:8888/docs/guide/index.html?a=1#top".to_string(),
+            base.resolve("#top")
+        );
+        assert_eq!(
+            "http://example.com:8888/docs/guide/index.html?a=1".to_string(),
+            base.resolve("")
+        );
+
+        // The fragment of the base URL is not kept.
+        let base = Url::new("http://example.com/index.html#top".to_string())
+            .parse()
+            .expect("failed to parse url");
+        assert_eq!(
+            "http://example.com/next.html".to_string(),
+            base.resolve("next.html")
+        );
+        assert_eq!(
+            "http://example.com/index.html#bottom".to_string(),
+            base.resolve("#bottom")
+        );
+        assert!(base.equals(
+            &Url::new("http://example.com/index.html#bottom".to_string())
+                .parse()
+                .expect("failed to parse url"),
+            true
+        ));
+    }
+
+    #[test]
+    fn test_no_scheme() {
+        let url = "example.com".to_string();
+        let expected = Err("Only HTTP scheme is supported.".to_string());
+        assert_eq!(expected, Url::new(url).parse());
+    }
+
+    #[test]
+    fn test_unsupported_scheme() {
+        let url = "https://example.com:8888/index.html".to_string();
+        let expected = Err("Only HTTP scheme is supported.".to_string());
+        assert_eq!(expected, Url::new(url).parse());
+    }
+}
@@ -0,0 +1,259 @@
+//! URL Standard: application/x-www-form-urlencoded and URLSearchParams
+//! https://url.spec.whatwg.org/#application/x-www-form-urlencoded
+//! https://url.spec.whatwg.org/#interface-urlsearchparams
+
+use alloc::format;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::fmt::Display;
+use core::fmt::Formatter;
+
+/// https://url.spec.whatwg.org/#percent-decode
+fn percent_decode(input: &[u8]) -> Vec<u8> {
+    let mut output = Vec::new();
+    let mut i = 0;
+
+    while i < input.len() {
+        let byte = input[i];
+
+        // "If byte is not 0x25 (%), then append byte to output."
+        // "Otherwise, if byte is 0x25 (%) and the next two bytes after byte in input are not in
+        // the ranges 0x30 (0) to 0x39 (9), 0x41 (A) to 0x46 (F), and 0x61 (a) to 0x66 (f), all
+        // inclusive, append byte to output."
+        if byte == b'%' && i + 2 < input.len() {
+            let high = (input[i + 1] as char).to_digit(16);
+            let low = (input[i + 2] as char).to_digit(16);
+            if let (Some(high), Some(low)) = (high, low) {
+                // "Otherwise:
+                //   1. Let bytePoint be the two bytes after byte in input, decoded, and then
+                //      interpreted as hexadecimal number.
+                //   2. Append a byte whose value is bytePoint to output.
+                //   3. Skip the next two bytes in input."
+                output.push((high * 16 + low) as u8);
+                i += 3;
+                continue;
+            }
+        }
+
+        output.push(byte);
+        i += 1;
+    }
+
+    output
+}
+
+/// Returns true if `byte` is not in the application/x-www-form-urlencoded percent-encode set.
+/// https://url.spec.whatwg.org/#application-x-www-form-urlencoded-percent-encode-set
+fn is_urlencoded_safe(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'*' || byte == b'-' || byte == b'.' || byte == b'_'
+}
+
+/// https://url.spec.whatwg.org/#concept-urlencoded-byte-serializer
+fn serialize_bytes(input: &str) -> String {
+    let mut output = String::new();
+
+    for byte in input.bytes() {
+        if byte == b' ' {
+            // "If byte is 0x20 (SP), then append U+002B (+) to output."
+            output.push('+');
+        } else if is_urlencoded_safe(byte) {
+            output.push(byte as char);
+        } else {
+            output.push_str(&format!("%{:02X}", byte));
+        }
+    }
+
+    output
+}
+
+/// https://url.spec.whatwg.org/#concept-urlencoded-parser
+pub fn parse_urlencoded(input: &str) -> Vec<(String, String)> {
+    let mut output = Vec::new();
+
+    // "Let sequences be the result of splitting input on 0x26 (&)."
+    for bytes in input.split('&') {
+        // "If bytes is the empty byte sequence, then continue."
+        if bytes.is_empty() {
+            continue;
+        }
+
+        // "If bytes contains a 0x3D (=), then let name be the bytes from the start of bytes up
+        // to but excluding its first 0x3D (=), and let value be the bytes, if any, after the
+        // first 0x3D (=) up to the end of bytes. If 0x3D (=) is the first byte, then name will
+        // be the empty byte sequence. If it is the last, then value will be the empty byte
+        // sequence."
+        // "Otherwise, let name have the value of bytes and let value be the empty byte sequence."
+        let (name, value) = match bytes.split_once('=') {
+            Some((n, v)) => (n, v),
+            None => (bytes, ""),
+        };
+
+        // "Replace any 0x2B (+) in name and value with 0x20 (SP)."
+        let name = name.replace('+', " ");
+        let value = value.replace('+', " ");
+
+        // "Let nameString and valueString be the result of running UTF-8 decode without BOM on
+        // the percent-decoding of name and value, respectively."
+        output.push((
+            String::from_utf8_lossy(&percent_decode(name.as_bytes())).to_string(),
+            String::from_utf8_lossy(&percent_decode(value.as_bytes())).to_string(),
+        ));
+    }
+
+    output
+}
+
+/// https://url.spec.whatwg.org/#concept-urlencoded-serializer
+pub fn serialize_urlencoded(tuples: &[(String, String)]) -> String {
+    tuples
+        .iter()
+        .map(|(name, value)| serialize_bytes(name) + "=" + &serialize_bytes(value))
+        .collect::<Vec<String>>()
+        .join("&")
+}
+
+/// https://url.spec.whatwg.org/#interface-urlsearchparams
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct UrlSearchParams {
+    /// https://url.spec.whatwg.org/#concept-urlsearchparams-list
+    list: Vec<(String, String)>,
+}
+
+impl UrlSearchParams {
+    /// https://url.spec.whatwg.org/#dom-urlsearchparams-urlsearchparams
+    pub fn new(init: &str) -> Self {
+        // "If init is a string and starts with U+003F (?), then remove the first code point from
+        // init."
+        let init = init.strip_prefix('?').unwrap_or(init);
+
+        Self {
+            list: parse_urlencoded(init),
+        }
+    }
+
+    /// https://url.spec.whatwg.org/#dom-urlsearchparams-size
+    pub fn size(&self) -> usize {
+        self.list.len()
+    }
+
+    /// https://url.spec.whatwg.org/#dom-urlsearchparams-append
+    pub fn append(&mut self, name: String, value: String) {
+        self.list.push((name, value));
+    }
+
+    /// https://url.spec.whatwg.org/#dom-urlsearchparams-delete
+    pub fn delete(&mut self, name: &str) {
+        self.list.retain(|(n, _)| n != name);
+    }
+
+    /// https://url.spec.whatwg.org/#dom-urlsearchparams-get
+    pub fn get(&self, name: &str) -> Option<String> {
+        self.list
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.clone())
+    }
+
+    /// https://url.spec.whatwg.org/#dom-urlsearchparams-getall
+    pub fn get_all(&self, name: &str) -> Vec<String> {
+        self.list
+            .iter()
+            .filter(|(n, _)| n == name)
+            .map(|(_, v)| v.clone())
+            .collect()
+    }
+
+    /// https://url.spec.whatwg.org/#dom-urlsearchparams-has
+    pub fn has(&self, name: &str) -> bool {
+        self.list.iter().any(|(n, _)| n == name)
+    }
+
+    /// https://url.spec.whatwg.org/#dom-urlsearchparams-set
+    pub fn set(&mut self, name: String, value: String) {
+        // "If this’s list contains any name-value pairs whose name is name, then set the value
+        // of the first such name-value pair to value and remove the others."
+        match self.list.iter().position(|(n, _)| n == &name) {
+            Some(index) => {
+                self.list[index].1 = value;
+                let mut i = 0;
+                self.list.retain(|(n, _)| {
+                    i += 1;
+                    i - 1 <= index || n != &name
+                });
+            }
+            // "Otherwise, append (name, value) to this’s list."
+            None => self.list.push((name, value)),
+        }
+    }
+
+    /// Iterates name-value pairs in order.
+    /// https://url.spec.whatwg.org/#interface-urlsearchparams
+    pub fn iter(&self) -> core::slice::Iter<'_, (String, String)> {
+        self.list.iter()
+    }
+}
+
+/// https://url.spec.whatwg.org/#urlsearchparams-stringification-behavior
+impl Display for UrlSearchParams {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        write!(f, "{}", serialize_urlencoded(&self.list))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_urlencoded() {
+        let expected = [
+            ("a".to_string(), "123".to_string()),
+            ("b".to_string(), "".to_string()),
+            ("c d".to_string(), "é&".to_string()),
+        ]
+        .to_vec();
+        assert_eq!(expected, parse_urlencoded("a=123&b&&c+d=%C3%A9%26"));
+    }
+
+    #[test]
+    fn test_parse_invalid_percent() {
+        let expected = [("a".to_string(), "%zz%4".to_string())].to_vec();
+        assert_eq!(expected, parse_urlencoded("a=%zz%4"));
+    }
+
+    #[test]
+    fn test_serialize_urlencoded() {
+        let tuples = [
+            ("q".to_string(), "hello world".to_string()),
+            ("x".to_string(), "a&b=c/é".to_string()),
+        ];
+        assert_eq!(
+            "q=hello+world&x=a%26b%3Dc%2F%C3%A9".to_string(),
+            serialize_urlencoded(&tuples)
+        );
+    }
+
+    #[test]
+    fn test_search_params() {
+        let mut params = UrlSearchParams::new("?a=1&b=2&a=3");
+        assert_eq!(3, params.size());
+        assert_eq!(Some("1".to_string()), params.get("a"));
+        assert_eq!(
+            ["1".to_string(), "3".to_string()].to_vec(),
+            params.get_all("a")
+        );
+        assert!(params.has("b"));
+        assert_eq!(None, params.get("c"));
+
+        params.set("a".to_string(), "4".to_string());
+        assert_eq!("a=4&b=2".to_string(), params.to_string());
+
+        params.append("c".to_string(), "5 6".to_string());
+        params.delete("b");
+        assert_eq!("a=4&c=5+6".to_string(), params.to_string());
+
+        let names: Vec<&str> = params.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(["a", "c"].to_vec(), names);
+    }
+}
@@ -1,7 +1,9 @@
 use crate::browser::Browser;
+use crate::log::Diagnostic;
 use crate::renderer::dom::node::Node;
 use crate::renderer::js::ast::Program;
 use crate::renderer::layout::layout_object::LayoutObject;
+use crate::renderer::source::SourceRange;
 use alloc::format;
 use alloc::rc::Rc;
 use alloc::rc::Weak;
@@ -26,40 +28,75 @@ pub fn console_error(browser: &Weak<RefCell<Browser>>, log: String) {
     }
 }
 
+pub fn console_diagnostic(browser: &Weak<RefCell<Browser>>, diagnostic: Diagnostic) {
+    if let Some(browser) = browser.upgrade() {
+        browser.borrow_mut().console_diagnostic(diagnostic);
+    }
+}
+
 /// for debug
-pub fn convert_dom_to_string(root: &Option<Rc<RefCell<Node>>>) -> String {
+/// If `with_source_ranges` is true, each node is followed by the range of the markup which
+/// produced it.
+pub fn convert_dom_to_string(root: &Option<Rc<RefCell<Node>>>, with_source_ranges: bool) -> String {
     let mut result = String::from("\n");
-    convert_dom_to_string_internal(root, 0, &mut result);
+    convert_dom_to_string_internal(root, 0, with_source_ranges, &mut result);
     result
 }
 
 fn convert_dom_to_string_internal(
     node: &Option<Rc<RefCell<Node>>>,
     depth: usize,
+    with_source_ranges: bool,
     result: &mut String,
 ) {
     match node {
         Some(n) => {
             result.push_str(&"  ".repeat(depth));
             result.push_str(&format!("{:?}", n.borrow().kind()));
+            if with_source_ranges {
+                push_source_range(n.borrow().source_range(), result);
+            }
             result.push('\n');
-            convert_dom_to_string_internal(&n.borrow().first_child(), depth + 1, result);
-            convert_dom_to_string_internal(&n.borrow().next_sibling(), depth, result);
+            convert_dom_to_string_internal(
+                &n.borrow().first_child(),
+                depth + 1,
+                with_source_ranges,
+                result,
+            );
+            convert_dom_to_string_internal(
+                &n.borrow().next_sibling(),
+                depth,
+                with_source_ranges,
+                result,
+            );
         }
         None => (),
     }
 }
 
+fn push_source_range(range: Option<SourceRange>, result: &mut String) {
+    match range {
+        Some(range) => result.push_str(&format!(" @ {}", range)),
+        None => result.push_str(" @ (none)"),
+    }
+}
+
 /// for debug
-pub fn convert_layout_tree_to_string(node: &Option<Rc<RefCell<LayoutObject>>>) -> String {
+/// If `with_source_ranges` is true, each layout object is followed by the range of the markup
+/// which produced its node.
+pub fn convert_layout_tree_to_string(
+    node: &Option<Rc<RefCell<LayoutObject>>>,
+    with_source_ranges: bool,
+) -> String {
     let mut result = String::from("\n");
-    convert_layout_tree_to_string_internal(node, 0, &mut result);
+    convert_layout_tree_to_string_internal(node, 0, with_source_ranges, &mut result);
     result
 }
 
 fn convert_layout_tree_to_string_internal(
     node: &Option<Rc<RefCell<LayoutObject>>>,
     depth: usize,
+    with_source_ranges: bool,
     result: &mut String,
 ) {
     match node {
@@ -73,9 +110,22 @@ fn convert_layout_tree_to_string_internal(
                 n.borrow().point(),
                 n.borrow().node_kind(),
             ));
+            if with_source_ranges {
+                push_source_range(n.borrow().source_range(), result);
+            }
             result.push('\n');
-            convert_layout_tree_to_string_internal(&n.borrow().first_child(), depth + 1, result);
-            convert_layout_tree_to_string_internal(&n.borrow().next_sibling(), depth, result);
+            convert_layout_tree_to_string_internal(
+                &n.borrow().first_child(),
+                depth + 1,
+                with_source_ranges,
+                result,
+            );
+            convert_layout_tree_to_string_internal(
+                &n.borrow().next_sibling(),
+                depth,
+                with_source_ranges,
+                result,
+            );
         }
         None => (),
     }
@@ -0,0 +1,735 @@
+//! A conformance test harness which runs the tokenizer tests and the tree construction tests in
+//! the html5lib-tests format against `HtmlTokenizer` and `HtmlParser`.
+//! https://github.com/html5lib/html5lib-tests
+//!
+//! The cases in `tests/html5lib` always run. The cases of html5lib-tests also run when it's cloned
+//! at `tests/html5lib-tests`:
+//!
+//! ```sh
+//! git clone https://github.com/html5lib/html5lib-tests core/tests/html5lib-tests
+//! ```
+//!
+//! The result of each case is compared with `tests/html5lib_expectations.txt`, so a case that
+//! regresses or starts passing fails this test. A case which is not in the expectation file, e.g.
+//! one added to html5lib-tests after the file was updated, is only reported. Run this test with
+//! `UPDATE_EXPECTATIONS=1` to update the expectation file.
+
+use saba_core::browser::Browser;
+use saba_core::renderer::dom::node::Element;
+use saba_core::renderer::dom::node::Node;
+use saba_core::renderer::dom::node::NodeKind;
+use saba_core::renderer::html::parser::HtmlParser;
+use saba_core::renderer::html::token::HtmlToken;
+use saba_core::renderer::html::token::HtmlTokenizer;
+use saba_core::renderer::html::token::State;
+use std::cell::RefCell;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// The directories that contain the test data, relative to the manifest directory.
+const DATA_DIRS: [&str; 2] = ["tests/html5lib", "tests/html5lib-tests"];
+/// The file that records whether each case passes, relative to the manifest directory.
+const EXPECTATIONS_FILE: &str = "tests/html5lib_expectations.txt";
+/// A case that doesn't finish in this duration fails, e.g. when the parser falls into an
+/// infinite loop.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Pass,
+    Fail,
+}
+
+impl Outcome {
+    fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "PASS" => Some(Outcome::Pass),
+            "FAIL" => Some(Outcome::Fail),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Outcome::Pass => "PASS",
+            Outcome::Fail => "FAIL",
+        }
+    }
+}
+
+/// Runs `f` in another thread so that a panic or an infinite loop in the parser fails only the
+/// case.
+fn run_case<F: FnOnce() -> bool + Send + 'static>(f: F) -> Outcome {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = sender.send(f());
+    });
+    match receiver.recv_timeout(TIMEOUT) {
+        Ok(true) => Outcome::Pass,
+        // A panic drops the sender without sending a result.
+        Ok(false) | Err(_) => Outcome::Fail,
+    }
+}
+
+/// A JSON value. This is enough to read the tokenizer tests.
+#[derive(Debug, Clone, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn as_array(&self) -> Option<&Vec<Json>> {
+        match self {
+            Json::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+struct JsonParser {
+    input: Vec<char>,
+    pos: usize,
+}
+
+impl JsonParser {
+    fn parse(input: &str) -> Option<Json> {
+        let mut parser = Self {
+            input: input.chars().collect(),
+            pos: 0,
+        };
+        let value = parser.parse_value()?;
+        parser.skip_whitespace();
+        match parser.pos == parser.input.len() {
+            true => Some(value),
+            false => None,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.input.len() && self.input[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn consume(&mut self, c: char) -> Option<()> {
+        self.skip_whitespace();
+        match self.input.get(self.pos) == Some(&c) {
+            true => {
+                self.pos += 1;
+                Some(())
+            }
+            false => None,
+        }
+    }
+
+    fn consume_literal(&mut self, literal: &str) -> Option<()> {
+        for c in literal.chars() {
+            if self.input.get(self.pos) != Some(&c) {
+                return None;
+            }
+            self.pos += 1;
+        }
+        Some(())
+    }
+
+    fn parse_value(&mut self) -> Option<Json> {
+        self.skip_whitespace();
+        match self.input.get(self.pos)? {
+            'n' => self.consume_literal("null").map(|_| Json::Null),
+            't' => self.consume_literal("true").map(|_| Json::Bool(true)),
+            'f' => self.consume_literal("false").map(|_| Json::Bool(false)),
+            '"' => self.parse_string().map(Json::String),
+            '[' => {
+                self.pos += 1;
+                let mut values = Vec::new();
+                if self.consume(']').is_some() {
+                    return Some(Json::Array(values));
+                }
+                loop {
+                    values.push(self.parse_value()?);
+                    if self.consume(']').is_some() {
+                        return Some(Json::Array(values));
+                    }
+                    self.consume(',')?;
+                }
+            }
+            '{' => {
+                self.pos += 1;
+                let mut members = Vec::new();
+                if self.consume('}').is_some() {
+                    return Some(Json::Object(members));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let key = self.parse_string()?;
+                    self.consume(':')?;
+                    members.push((key, self.parse_value()?));
+                    if self.consume('}').is_some() {
+                        return Some(Json::Object(members));
+                    }
+                    self.consume(',')?;
+                }
+            }
+            _ => {
+                let start = self.pos;
+                while self.pos < self.input.len()
+                    && matches!(
+                        self.input[self.pos],
+                        '-' | '+' | '.' | 'e' | 'E' | '0'..='9'
+                    )
+                {
+                    self.pos += 1;
+                }
+                let number: String = self.input[start..self.pos].iter().collect();
+                number.parse::<f64>().ok().map(Json::Number)
+            }
+        }
+    }
+
+    fn parse_hex4(&mut self) -> Option<u32> {
+        let hex: String = self.input.get(self.pos..self.pos + 4)?.iter().collect();
+        self.pos += 4;
+        u32::from_str_radix(&hex, 16).ok()
+    }
+
+    fn parse_string(&mut self) -> Option<String> {
+        if self.input.get(self.pos) != Some(&'"') {
+            return None;
+        }
+        self.pos += 1;
+
+        let mut s = String::new();
+        loop {
+            let c = *self.input.get(self.pos)?;
+            self.pos += 1;
+            match c {
+                '"' => return Some(s),
+                '\\' => {
+                    let escaped = *self.input.get(self.pos)?;
+                    self.pos += 1;
+                    match escaped {
+                        'b' => s.push('\u{8}'),
+                        'f' => s.push('\u{C}'),
+                        'n' => s.push('\n'),
+                        'r' => s.push('\r'),
+                        't' => s.push('\t'),
+                        'u' => {
+                            let code = self.parse_hex4()?;
+                            // A surrogate pair is written as two escapes. A lone surrogate can't
+                            // be represented in a Rust string.
+                            let code = if (0xD800..0xDC00).contains(&code)
+                                && self.input.get(self.pos..self.pos + 2) == Some(&['\\', 'u'])
+                            {
+                                self.pos += 2;
+                                let low = self.parse_hex4()?;
+                                0x10000 + ((code - 0xD800) << 10) + (low.wrapping_sub(0xDC00))
+                            } else {
+                                code
+                            };
+                            s.push(char::from_u32(code).unwrap_or('\u{FFFD}'));
+                        }
+                        _ => s.push(escaped),
+                    }
+                }
+                _ => s.push(c),
+            }
+        }
+    }
+}
+
+/// Replaces "\uXXXX" in `s` with the code point. This is used for the tests which have
+/// "doubleEscaped". Returns None if `s` contains a lone surrogate, which can't be represented in
+/// a Rust string.
+fn unescape(s: &str) -> Option<String> {
+    let mut json = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            _ => json.push(c),
+        }
+    }
+    json.push('"');
+    match JsonParser::parse(&json)? {
+        Json::String(unescaped) if !unescaped.contains('\u{FFFD}') || s.contains('\u{FFFD}') => {
+            Some(unescaped)
+        }
+        _ => None,
+    }
+}
+
+/// A token in the form of the expected output of the tokenizer tests.
+#[derive(Debug, Clone, PartialEq)]
+enum TestToken {
+    Doctype {
+        name: Option<String>,
+        public_id: Option<String>,
+        system_id: Option<String>,
+        correctness: bool,
+    },
+    StartTag {
+        name: String,
+        /// The attributes sorted by their names.
+        attributes: Vec<(String, String)>,
+        self_closing: bool,
+    },
+    EndTag(String),
+    Comment(String),
+    Character(String),
+}
+
+/// Appends `token` to `tokens`. Consecutive character tokens are merged into one.
+fn push_token(tokens: &mut Vec<TestToken>, token: TestToken) {
+    if let TestToken::Character(ref s) = token {
+        if let Some(TestToken::Character(last)) = tokens.last_mut() {
+            last.push_str(s);
+            return;
+        }
+    }
+    tokens.push(token);
+}
+
+fn expected_tokens(output: &[Json], double_escaped: bool) -> Option<Vec<TestToken>> {
+    let string = |json: &Json| -> Option<String> {
+        match double_escaped {
+            true => unescape(json.as_str()?),
+            false => json.as_str().map(|s| s.to_string()),
+        }
+    };
+    let optional_string = |json: &Json| -> Option<Option<String>> {
+        match json {
+            Json::Null => Some(None),
+            _ => string(json).map(Some),
+        }
+    };
+
+    let mut tokens = Vec::new();
+    for token in output {
+        let fields = token.as_array()?;
+        let token = match fields.first()?.as_str()? {
+            "DOCTYPE" => TestToken::Doctype {
+                name: optional_string(fields.get(1)?)?,
+                public_id: optional_string(fields.get(2)?)?,
+                system_id: optional_string(fields.get(3)?)?,
+                correctness: fields.get(4)? == &Json::Bool(true),
+            },
+            "StartTag" => {
+                let mut attributes = Vec::new();
+                if let Some(Json::Object(members)) = fields.get(2) {
+                    for (name, value) in members {
+                        attributes.push((name.clone(), string(value)?));
+                    }
+                }
+                attributes.sort();
+                TestToken::StartTag {
+                    name: string(fields.get(1)?)?,
+                    attributes,
+                    self_closing: fields.get(3) == Some(&Json::Bool(true)),
+                }
+            }
+            "EndTag" => TestToken::EndTag(string(fields.get(1)?)?),
+            "Comment" => TestToken::Comment(string(fields.get(1)?)?),
+            "Character" => TestToken::Character(string(fields.get(1)?)?),
+            _ => return None,
+        };
+        push_token(&mut tokens, token);
+    }
+    Some(tokens)
+}
+
+/// Returns the tokenizer state for the name used in "initialStates". Returns None if the state
+/// is not supported.
+fn initial_state(name: &str) -> Option<State> {
+    match name {
+        "Data state" => Some(State::Data),
+        "RCDATA state" => Some(State::Rcdata),
+        "RAWTEXT state" => Some(State::Rawtext),
+        "Script data state" => Some(State::ScriptData),
+        "CDATA section state" => Some(State::CdataSection),
+        _ => None,
+    }
+}
+
+fn tokenize(input: String, state: State, last_start_tag: Option<String>) -> Vec<TestToken> {
+    let browser = Browser::new();
+    let mut t = HtmlTokenizer::new(Rc::downgrade(&browser), input);
+    t.switch_context(state);
+    if let Some(tag) = last_start_tag {
+        t.set_last_start_tag(tag);
+    }
+
+    let mut tokens = Vec::new();
+    for token in t {
+        let token = match token {
+            HtmlToken::Doctype {
+                name,
+                public_identifier,
+                system_identifier,
+                force_quirks,
+            } => TestToken::Doctype {
+                name,
+                public_id: public_identifier,
+                system_id: system_identifier,
+                correctness: !force_quirks,
+            },
+            HtmlToken::StartTag {
+                tag,
+                self_closing,
+                attributes,
+            } => {
+                let mut attributes: Vec<(String, String)> = attributes
+                    .iter()
+                    .map(|attr| (attr.name(), attr.value()))
+                    .collect();
+                attributes.sort();
+                TestToken::StartTag {
+                    name: tag,
+                    attributes,
+                    self_closing,
+                }
+            }
+            HtmlToken::EndTag { tag } => TestToken::EndTag(tag),
+            HtmlToken::Comment(s) => TestToken::Comment(s),
+            HtmlToken::Char(c) => TestToken::Character(c.to_string()),
+            HtmlToken::Eof => break,
+        };
+        push_token(&mut tokens, token);
+    }
+    tokens
+}
+
+/// Runs the tests in a tokenizer test file and appends the outcome of each case to `results`.
+/// https://github.com/html5lib/html5lib-tests/tree/master/tokenizer
+fn run_tokenizer_tests(key: &str, content: &str, results: &mut Vec<(String, Outcome)>) {
+    let tests = match JsonParser::parse(content)
+        .as_ref()
+        .and_then(|json| json.get("tests"))
+        .and_then(|tests| tests.as_array())
+    {
+        Some(tests) => tests.clone(),
+        None => panic!("{} is not a valid tokenizer test file", key),
+    };
+
+    for (i, test) in tests.iter().enumerate() {
+        let double_escaped = test.get("doubleEscaped") == Some(&Json::Bool(true));
+        let input = test.get("input").and_then(|input| match double_escaped {
+            true => unescape(input.as_str()?),
+            false => input.as_str().map(|s| s.to_string()),
+        });
+        let expected = test
+            .get("output")
+            .and_then(|output| output.as_array())
+            .and_then(|output| expected_tokens(output, double_escaped));
+        let last_start_tag = test
+            .get("lastStartTag")
+            .and_then(|tag| tag.as_str())
+            .map(|tag| tag.to_string());
+
+        let states: Vec<String> = match test.get("initialStates").and_then(|s| s.as_array()) {
+            Some(states) => states
+                .iter()
+                .filter_map(|s| s.as_str().map(|s| s.to_string()))
+                .collect(),
+            None => vec!["Data state".to_string()],
+        };
+        let has_initial_states = test.get("initialStates").is_some();
+
+        for state_name in states {
+            let case = match has_initial_states {
+                true => format!("{}#{} ({})", key, i, state_name),
+                false => format!("{}#{}", key, i),
+            };
+            let outcome = match (input.clone(), expected.clone(), initial_state(&state_name)) {
+                (Some(input), Some(expected), Some(state)) => {
+                    let last_start_tag = last_start_tag.clone();
+                    run_case(move || tokenize(input, state, last_start_tag) == expected)
+                }
+                // The input can't be represented or the state is not supported.
+                _ => Outcome::Fail,
+            };
+            results.push((case, outcome));
+        }
+    }
+}
+
+/// A test in a tree construction test file.
+#[derive(Debug, Clone, Default)]
+struct TreeConstructionTest {
+    data: String,
+    fragment_context: Option<String>,
+    document: String,
+}
+
+/// Splits the content of a tree construction test file into tests.
+/// https://github.com/html5lib/html5lib-tests/tree/master/tree-construction
+fn parse_dat(content: &str) -> Vec<TreeConstructionTest> {
+    const SECTIONS: [&str; 7] = [
+        "#data",
+        "#errors",
+        "#new-errors",
+        "#document-fragment",
+        "#script-off",
+        "#script-on",
+        "#document",
+    ];
+
+    let mut tests = Vec::new();
+    let mut sections: Vec<(String, Vec<String>)> = Vec::new();
+    let mut finish = |sections: &mut Vec<(String, Vec<String>)>| {
+        if sections.is_empty() {
+            return;
+        }
+        let mut test = TreeConstructionTest::default();
+        for (name, lines) in sections.drain(..) {
+            match name.as_str() {
+                "#data" => test.data = lines.join("\n"),
+                "#document-fragment" => test.fragment_context = lines.first().cloned(),
+                "#document" => {
+                    // Tests are separated by an empty line.
+                    let mut lines = lines;
+                    while lines.last().is_some_and(|l| l.is_empty()) {
+                        lines.pop();
+                    }
+                    test.document = lines.join("\n");
+                }
+                _ => {}
+            }
+        }
+        tests.push(test);
+    };
+
+    for line in content.lines() {
+        if SECTIONS.contains(&line) {
+            if line == "#data" {
+                finish(&mut sections);
+            }
+            sections.push((line.to_string(), Vec::new()));
+            continue;
+        }
+        if let Some((_, lines)) = sections.last_mut() {
+            lines.push(line.to_string());
+        }
+    }
+    finish(&mut sections);
+
+    tests
+}
+
+/// Appends the lines of `node` and its descendants in the format of the "#document" section.
+fn dump(node: &Rc<RefCell<Node>>, depth: usize, lines: &mut Vec<String>) {
+    let indent = "  ".repeat(depth);
+    match node.borrow().kind() {
+        NodeKind::Document => {}
+        NodeKind::DocumentType(d) => {
+            if d.public_id().is_empty() && d.system_id().is_empty() {
+                lines.push(format!("| {}<!DOCTYPE {}>", indent, d.name()));
+            } else {
+                lines.push(format!(
+                    "| {}<!DOCTYPE {} \"{}\" \"{}\">",
+                    indent,
+                    d.name(),
+                    d.public_id(),
+                    d.system_id()
+                ));
+            }
+        }
+        NodeKind::Element(e) => {
+            lines.push(format!("| {}<{}>", indent, e.local_name()));
+            let mut attributes: Vec<(String, String)> = e
+                .attributes()
+                .iter()
+                .map(|attr| (attr.name(), attr.value()))
+                .collect();
+            attributes.sort();
+            for (name, value) in attributes {
+                lines.push(format!("| {}  {}=\"{}\"", indent, name, value));
+            }
+        }
+        NodeKind::Text(s) => lines.push(format!("| {}\"{}\"", indent, s)),
+        NodeKind::Comment(s) => lines.push(format!("| {}<!-- {} -->", indent, s)),
+    }
+
+    let depth = match node.borrow().kind() {
+        NodeKind::Document => depth,
+        _ => depth + 1,
+    };
+    let mut child = node.borrow().first_child();
+    while let Some(c) = child {
+        dump(&c, depth, lines);
+        child = c.borrow().next_sibling();
+    }
+}
+
+fn construct_tree(data: String) -> String {
+    let browser = Browser::new();
+    let t = HtmlTokenizer::new(Rc::downgrade(&browser), data);
+    let window = HtmlParser::new(Rc::downgrade(&browser), t).construct_tree();
+
+    let mut lines = Vec::new();
+    dump(&window.borrow().document(), 0, &mut lines);
+    lines.join("\n")
+}
+
+fn parse_fragment(context: String, data: String) -> String {
+    let browser = Browser::new();
+    let context = Rc::new(RefCell::new(Node::new(NodeKind::Element(Element::new(
+        &context,
+        Vec::new(),
+    )))));
+    let nodes = HtmlParser::parse_fragment(Rc::downgrade(&browser), &context, data);
+
+    let mut lines = Vec::new();
+    for node in nodes {
+        dump(&node, 0, &mut lines);
+    }
+    lines.join("\n")
+}
+
+/// Runs the tests in a tree construction test file and appends the outcome of each case to
+/// `results`.
+fn run_tree_construction_tests(key: &str, content: &str, results: &mut Vec<(String, Outcome)>) {
+    for (i, test) in parse_dat(content).into_iter().enumerate() {
+        let outcome = match test.fragment_context {
+            // A context element in the SVG or MathML namespace, e.g. "svg path", is not
+            // supported.
+            Some(context) if context.contains(' ') => Outcome::Fail,
+            Some(context) => run_case(move || parse_fragment(context, test.data) == test.document),
+            None => run_case(move || construct_tree(test.data) == test.document),
+        };
+        results.push((format!("{}#{}", key, i), outcome));
+    }
+}
+
+/// Returns the files in `dir` with `extension`, sorted by their names.
+fn test_files(dir: &Path, extension: &str) -> Vec<String> {
+    let mut files: Vec<String> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .filter(|name| name.ends_with(extension))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    files.sort();
+    files
+}
+
+fn read_expectations(path: &Path) -> Vec<(String, Outcome)> {
+    let content = fs::read_to_string(path).unwrap_or_default();
+    content
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let (case, outcome) = line.rsplit_once(' ')?;
+            Some((case.to_string(), Outcome::from_str(outcome)?))
+        })
+        .collect()
+}
+
+fn write_expectations(path: &Path, expectations: &[(String, Outcome)]) {
+    let mut content = String::from(
+        "# The expected outcome of each case in the html5lib-tests format.\n\
+         # Run `UPDATE_EXPECTATIONS=1 cargo test -p saba_core --test html5lib` to update this file.\n",
+    );
+    for (case, outcome) in expectations {
+        content.push_str(&format!("{} {}\n", case, outcome.as_str()));
+    }
+    fs::write(path, content).expect("failed to write the expectation file");
+}
+
+#[test]
+fn html5lib_tests() {
+    // A panic in a case is reported as its failure.
+    std::panic::set_hook(Box::new(|_| {}));
+
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut results = Vec::new();
+    for dir in DATA_DIRS {
+        let root = manifest_dir.join(dir);
+        let name = dir.trim_start_matches("tests/");
+
+        let tokenizer_dir = root.join("tokenizer");
+        for file in test_files(&tokenizer_dir, ".test") {
+            let content = fs::read_to_string(tokenizer_dir.join(&file)).unwrap_or_default();
+            let key = format!("{}/tokenizer/{}", name, file);
+            run_tokenizer_tests(&key, &content, &mut results);
+        }
+
+        let tree_construction_dir = root.join("tree-construction");
+        for file in test_files(&tree_construction_dir, ".dat") {
+            let content = fs::read_to_string(tree_construction_dir.join(&file)).unwrap_or_default();
+            let key = format!("{}/tree-construction/{}", name, file);
+            run_tree_construction_tests(&key, &content, &mut results);
+        }
+    }
+    let _ = std::panic::take_hook();
+
+    let passed = results.iter().filter(|(_, o)| *o == Outcome::Pass).count();
+    println!("html5lib-tests: {}/{} cases passed", passed, results.len());
+
+    let expectations_path = manifest_dir.join(EXPECTATIONS_FILE);
+    let expectations = read_expectations(&expectations_path);
+
+    if std::env::var("UPDATE_EXPECTATIONS").is_ok() {
+        // Keep the expectations of the cases which didn't run, e.g. when html5lib-tests is not
+        // checked out.
+        let mut updated = results.clone();
+        for (case, outcome) in expectations {
+            if !results.iter().any(|(c, _)| *c == case) {
+                updated.push((case, outcome));
+            }
+        }
+        write_expectations(&expectations_path, &updated);
+        return;
+    }
+
+    let mut unexpected = Vec::new();
+    let mut unlisted = Vec::new();
+    for (case, outcome) in &results {
+        match expectations.iter().find(|(c, _)| c == case) {
+            Some((_, expected)) if expected == outcome => {}
+            Some((_, expected)) => unexpected.push(format!(
+                "{}: expected {} but got {}",
+                case,
+                expected.as_str(),
+                outcome.as_str()
+            )),
+            None => unlisted.push(format!("{}: {}", case, outcome.as_str())),
+        }
+    }
+    if !unlisted.is_empty() {
+        println!(
+            "{} cases have no expectation. run with UPDATE_EXPECTATIONS=1 to add them:\n{}",
+            unlisted.len(),
+            unlisted.join("\n")
+        );
+    }
+    assert!(
+        unexpected.is_empty(),
+        "unexpected results. run with UPDATE_EXPECTATIONS=1 to update {}:\n{}",
+        EXPECTATIONS_FILE,
+        unexpected.join("\n")
+    );
+}
@@ -8,13 +8,14 @@
 //! https://tools.ietf.org/html/rfc7235
 
 use dns_lookup::lookup_host;
+use saba_core::http::Header;
+use saba_core::http::HttpResponse;
 use std::io::prelude::*;
 use std::io::ErrorKind;
 use std::io::Read;
 use std::net::TcpStream;
 use std::string::String;
 use std::vec::Vec;
-use saba_core::http::HttpResponse;
 
 pub struct HttpClient {}
 
@@ -24,12 +25,39 @@ impl HttpClient {
     }
 
     pub fn get(&self, host: String, port: u16, path: String) -> std::io::Result<HttpResponse> {
+        self.send("GET", host, port, path, Vec::new(), String::new())
+    }
+
+    /// Sends a POST request with `body`. The headers describing the body such as Content-Type
+    /// are given by `headers`.
+    /// https://www.rfc-editor.org/rfc/rfc7231#section-4.3.3
+    pub fn post(
+        &self,
+        host: String,
+        port: u16,
+        path: String,
+        headers: Vec<Header>,
+        body: String,
+    ) -> std::io::Result<HttpResponse> {
+        self.send("POST", host, port, path, headers, body)
+    }
+
+    fn send(
+        &self,
+        method: &str,
+        host: String,
+        port: u16,
+        path: String,
+        headers: Vec<Header>,
+        body: String,
+    ) -> std::io::Result<HttpResponse> {
         let ips = lookup_host(&host)?.into_iter();
         let ipv4s: Vec<std::net::IpAddr> = ips.filter(|ip| ip.is_ipv4()).collect();
 
         let mut stream = TcpStream::connect((ipv4s[0], port))?;
 
-        let mut request = String::from("GET /");
+        let mut request = String::from(method);
+        request.push_str(" /");
         request.push_str(&path);
         request.push_str(" HTTP/1.1\n");
 
@@ -39,15 +67,26 @@ impl HttpClient {
         request.push('\n');
         request.push_str("Accept: */*\n");
         request.push_str("Connection: close\n");
+        for header in &headers {
+            request.push_str(&header.name());
+            request.push_str(": ");
+            request.push_str(&header.value());
+            request.push('\n');
+        }
+        if method == "POST" {
+            request.push_str(&format!("Content-Length: {}\n", body.len()));
+        }
 
         request.push('\n');
+        request.push_str(&body);
 
-        stream.write(request.as_bytes())?;
+        stream.write_all(request.as_bytes())?;
 
-        let mut buf = String::new();
-        stream.read_to_string(&mut buf)?;
+        // The body may be in an encoding other than UTF-8, so read it as bytes.
+        let mut buf = Vec::new();
+        stream.read_to_end(&mut buf)?;
 
-        match HttpResponse::new(buf.to_string()) {
+        match HttpResponse::from_bytes(&buf) {
             Ok(res) => Ok(res),
             Err(e) => Err(std::io::Error::new(
                 ErrorKind::InvalidData,
@@ -55,36 +94,4 @@ impl HttpClient {
             )),
         }
     }
-
-    // TODO: support correctly
-    /*
-        pub fn _post(&self, url: &ParsedUrl, _body: String) -> std::io::Result<HttpResponse> {
-            let ips: Vec<std::net::IpAddr> = lookup_host(&url.host)?;
-
-            let mut stream = TcpStream::connect((ips[0], url.port))?;
-
-            let mut request = String::from("POST ");
-            request.push_str(&url.path);
-            request.push_str(" HTTP/1.1\n");
-
-            /*
-            // headers
-            for h in &url.headers {
-                request.push_str(&h.key);
-                request.push_str(": ");
-                request.push_str(&h.value);
-                request.push('\n');
-            }
-            */
-
-            request.push('\n');
-
-            stream.write(request.as_bytes())?;
-
-            let mut buf = String::new();
-            stream.read_to_string(&mut buf)?;
-
-            Ok(HttpResponse::new(buf))
-        }
-    */
 }
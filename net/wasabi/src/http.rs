@@ -16,6 +16,7 @@ use noli::net::lookup_host;
 use noli::net::SocketAddr;
 use noli::net::TcpStream;
 use saba_core::error::Error;
+use saba_core::http::Header;
 use saba_core::http::HttpResponse;
 
 pub struct HttpClient {}
@@ -26,6 +27,32 @@ impl HttpClient {
     }
 
     pub fn get(&self, host: String, port: u16, path: String) -> Result<HttpResponse, Error> {
+        self.send("GET", host, port, path, Vec::new(), String::new())
+    }
+
+    /// Sends a POST request with `body`. The headers describing the body such as Content-Type
+    /// are given by `headers`.
+    /// https://www.rfc-editor.org/rfc/rfc7231#section-4.3.3
+    pub fn post(
+        &self,
+        host: String,
+        port: u16,
+        path: String,
+        headers: Vec<Header>,
+        body: String,
+    ) -> Result<HttpResponse, Error> {
+        self.send("POST", host, port, path, headers, body)
+    }
+
+    fn send(
+        &self,
+        method: &str,
+        host: String,
+        port: u16,
+        path: String,
+        headers: Vec<Header>,
+        body: String,
+    ) -> Result<HttpResponse, Error> {
         let ips = match lookup_host(&host) {
             Ok(ips) => ips,
             Err(_) => return Err(Error::Network("Failed to find IP addresses".to_string())),
@@ -46,7 +73,8 @@ impl HttpClient {
             }
         };
 
-        let mut request = String::from("GET /");
+        let mut request = String::from(method);
+        request.push_str(" /");
         request.push_str(&path);
         request.push_str(" HTTP/1.1\n");
 
@@ -56,8 +84,18 @@ impl HttpClient {
         request.push('\n');
         request.push_str("Accept: */*\n");
         request.push_str("Connection: close\n");
+        for header in &headers {
+            request.push_str(&header.name());
+            request.push_str(": ");
+            request.push_str(&header.value());
+            request.push('\n');
+        }
+        if method == "POST" {
+            request.push_str(&format!("Content-Length: {}\n", body.len()));
+        }
 
         request.push('\n');
+        request.push_str(&body);
 
         let _bytes_written = match stream.write(request.as_bytes()) {
             Ok(bytes) => bytes,
@@ -85,13 +123,11 @@ impl HttpClient {
             received.extend_from_slice(&buf[..bytes_read]);
         }
 
-        match core::str::from_utf8(&received) {
-            Ok(response) => HttpResponse::new(response.to_string()),
-            Err(e) => Err(Error::Network(format!("Invalid received response: {}", e))),
-        }
+        // The body may be in an encoding other than UTF-8, so it's decoded by the sniffed
+        // encoding.
+        HttpResponse::from_bytes(&received)
     }
 
-    pub fn post(&self) {}
     pub fn put(&self) {}
     pub fn delete(&self) {}
 }
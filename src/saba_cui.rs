@@ -4,18 +4,18 @@ use net_std as net;
 use ui_cui as ui;
 
 use alloc::rc::Rc;
-use alloc::string::String;
 use core::cell::RefCell;
 use net::http::HttpClient;
 use saba_core::browser::Browser;
 use saba_core::error::Error;
+use saba_core::http::HttpRequest;
 use saba_core::http::HttpResponse;
 use saba_core::url::Url;
 use ui::app::Tui;
 
-fn handle_url(url: String) -> Result<HttpResponse, Error> {
+fn handle_url(request: HttpRequest) -> Result<HttpResponse, Error> {
     // parse url
-    let parsed_url = match Url::new(url.to_string()).parse() {
+    let parsed_url = match Url::new(request.url()).parse() {
         Ok(url) => url,
         Err(e) => {
             return Err(Error::UnexpectedInput(format!(
@@ -27,14 +27,24 @@ fn handle_url(url: String) -> Result<HttpResponse, Error> {
 
     // send a HTTP request and get a response
     let client = HttpClient::new();
-    let response = match client.get(
-        parsed_url.host(),
-        parsed_url
-            .port()
-            .parse::<u16>()
-            .unwrap_or_else(|_| panic!("port number should be u16 but got {}", parsed_url.port())),
-        parsed_url.path(),
-    ) {
+    let port = parsed_url
+        .port()
+        .parse::<u16>()
+        .unwrap_or_else(|_| panic!("port number should be u16 but got {}", parsed_url.port()));
+    // The query string is sent as a part of the request target, and the fragment is not.
+    let path = parsed_url.path() + &parsed_url.search();
+    let result = if request.method() == "POST" {
+        client.post(
+            parsed_url.host(),
+            port,
+            path,
+            request.headers(),
+            request.body(),
+        )
+    } else {
+        client.get(parsed_url.host(), port, path)
+    };
+    let response = match result {
         Ok(res) => {
             // redirect to Location
             if res.status_code() == 302 {
@@ -77,6 +87,11 @@ fn main() {
     // initialize the main browesr struct
     let browser = Browser::new();
 
+    // A user can disable the navigation by <meta http-equiv="refresh">.
+    if std::env::args().any(|arg| arg == "--disable-meta-refresh") {
+        browser.borrow_mut().set_meta_refresh_enabled(false);
+    }
+
     // initialize the UI object
     let ui = Rc::new(RefCell::new(Tui::new(browser)));
 
@@ -7,22 +7,21 @@ extern crate alloc;
 use noli::prelude::*;
 entry_point!(main);
 
-use crate::alloc::string::ToString;
 use alloc::format;
 use alloc::rc::Rc;
-use alloc::string::String;
 use core::cell::RefCell;
 use net_wasabi::http::HttpClient;
 use noli::println;
 use saba_core::browser::Browser;
 use saba_core::error::Error;
+use saba_core::http::HttpRequest;
 use saba_core::http::HttpResponse;
 use saba_core::url::Url;
 use ui_wasabi::app::WasabiUI;
 
-fn handle_url(url: String) -> core::result::Result<HttpResponse, Error> {
+fn handle_url(request: HttpRequest) -> core::result::Result<HttpResponse, Error> {
     // parse url
-    let parsed_url = match Url::new(url.to_string()).parse() {
+    let parsed_url = match Url::new(request.url()).parse() {
         Ok(url) => url,
         Err(e) => {
             return Err(Error::UnexpectedInput(format!(
@@ -35,14 +34,24 @@ fn handle_url(url: String) -> core::result::Result<HttpResponse, Error> {
 
     // send a HTTP request and get a response
     let client = HttpClient::new();
-    let response = match client.get(
-        parsed_url.host(),
-        parsed_url.port().parse::<u16>().expect(&format!(
-            "port number should be u16 but got {}",
-            parsed_url.port()
-        )),
-        parsed_url.path(),
-    ) {
+    let port = parsed_url.port().parse::<u16>().expect(&format!(
+        "port number should be u16 but got {}",
+        parsed_url.port()
+    ));
+    // The query string is sent as a part of the request target, and the fragment is not.
+    let path = parsed_url.path() + &parsed_url.search();
+    let result = if request.method() == "POST" {
+        client.post(
+            parsed_url.host(),
+            port,
+            path,
+            request.headers(),
+            request.body(),
+        )
+    } else {
+        client.get(parsed_url.host(), port, path)
+    };
+    let response = match result {
         Ok(res) => {
             // redirect to Location
             if res.status_code() == 302 {
@@ -2,7 +2,10 @@ use alloc::rc::Rc;
 use alloc::string::ToString;
 use core::cell::RefCell;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers,
+        MouseButton, MouseEvent, MouseEventKind,
+    },
     execute,
     terminal::{
         disable_raw_mode, enable_raw_mode, size, Clear, ClearType, EnterAlternateScreen,
@@ -10,15 +13,25 @@ use crossterm::{
     },
 };
 use saba_core::browser::Browser;
+use saba_core::http::HttpRequest;
 use saba_core::http::HttpResponse;
+use saba_core::renderer::layout::color::Color as CssColor;
 use saba_core::renderer::layout::computed_style::FontSize;
+use saba_core::renderer::layout::computed_style::FontStyle;
+use saba_core::renderer::layout::computed_style::FontWeight;
 use saba_core::renderer::layout::computed_style::TextDecoration;
+use saba_core::renderer::layout::layout_point::LayoutPoint;
+use saba_core::renderer::source::SourceRange;
+use saba_core::url::Url;
 use saba_core::utils::*;
 use saba_core::{display_item::DisplayItem, error::Error};
+use std::fs;
 use std::io;
+use std::time::Duration;
+use std::time::Instant;
 use tui::{
     backend::{Backend, CrosstermBackend},
-    layout::{Constraint, Direction, Layout},
+    layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Span, Spans, Text},
     widgets::{Block, Borders, List, ListItem, Paragraph, Wrap},
@@ -26,22 +39,15 @@ use tui::{
 };
 use unicode_width::UnicodeWidthStr;
 
+/// The file which the serialized DOM of the current page is saved to.
+const SAVED_DOM_FILE: &str = "saba_dom.html";
+
 #[derive(Clone, Copy, Debug)]
 enum InputMode {
     Normal,
     Editing,
-}
-
-#[derive(Clone, Debug)]
-struct Link {
-    text: String,
-    destination: String,
-}
-
-impl Link {
-    fn new(text: String, destination: String) -> Self {
-        Self { text, destination }
-    }
+    /// A form control in the page has the focus and receives key inputs.
+    FormEditing,
 }
 
 #[derive(Clone, Debug)]
@@ -49,8 +55,18 @@ pub struct Tui {
     browser: Rc<RefCell<Browser>>,
     input_url: String,
     input_mode: InputMode,
-    // A user can focus only a link now.
-    focus: Option<Link>,
+    /// The time when the navigation scheduled by `<meta http-equiv="refresh">` comes due and its
+    /// URL.
+    scheduled_refresh: Option<(Instant, String)>,
+    /// True if the content box shows the source of the page instead of the rendered page.
+    view_source: bool,
+    /// The range of the markup which produced the content a user clicked last. It's highlighted
+    /// in the view-source display.
+    source_selection: Option<SourceRange>,
+    /// The area of the content box and the layout point of each line in it, which are used to
+    /// map a mouse click to the content.
+    content_area: Rect,
+    content_points: Vec<LayoutPoint>,
 }
 
 impl Tui {
@@ -59,13 +75,17 @@ impl Tui {
             browser,
             input_url: String::new(),
             input_mode: InputMode::Normal,
-            focus: None,
+            scheduled_refresh: None,
+            view_source: false,
+            source_selection: None,
+            content_area: Rect::default(),
+            content_points: Vec::new(),
         }
     }
 
     pub fn start(
         &mut self,
-        handle_url: fn(String) -> Result<HttpResponse, Error>,
+        handle_url: fn(HttpRequest) -> Result<HttpResponse, Error>,
     ) -> Result<(), Error> {
         // set up terminal
         match enable_raw_mode() {
@@ -128,139 +148,256 @@ impl Tui {
         self.browser.clone()
     }
 
-    fn move_focus_to_up(&mut self) {
-        let display_items = self
-            .browser
-            .borrow()
-            .current_page()
-            .borrow()
-            .display_items();
+    fn start_navigation(
+        &mut self,
+        handle_url: fn(HttpRequest) -> Result<HttpResponse, Error>,
+        request: HttpRequest,
+    ) -> Result<(), Error> {
+        self.scheduled_refresh = None;
+        let destination = request.url();
+        // Show the host in the Unicode form unless it may be used for spoofing.
+        if let Ok(url) = Url::new(destination.clone()).parse() {
+            self.input_url = url.display_url();
+        }
 
-        let mut previous_link_item: Option<Link> = None;
-        for item in display_items {
-            match item {
-                DisplayItem::Text {
-                    text,
-                    style,
-                    layout_point: _,
-                } => {
-                    if style.text_decoration() != TextDecoration::Underline {
-                        continue;
-                    }
-                    match &self.focus {
-                        Some(current_focus_item) => {
-                            if current_focus_item.text == text {
-                                if let Some(prev_link_item) = previous_link_item {
-                                    self.focus = Some(prev_link_item);
-                                    return;
-                                } else {
-                                    self.focus = None;
-                                    return;
-                                }
-                            }
-                            previous_link_item = Some(current_focus_item.clone());
-                        }
-                        None => {
-                            return;
-                        }
+        match handle_url(request) {
+            Ok(response) => {
+                self.browser.borrow_mut().clear_logs();
+                self.source_selection = None;
+
+                let page = self.browser.borrow().current_page();
+                page.borrow_mut().clear_display_items();
+                page.borrow_mut().receive_response(destination, response);
+
+                self.load_frames(handle_url);
+
+                let refresh = page.borrow().refresh();
+                self.scheduled_refresh = refresh.and_then(|r| {
+                    Instant::now()
+                        .checked_add(Duration::from_secs(r.delay()))
+                        .map(|due| (due, r.url()))
+                });
+            }
+            Err(e) => {
+                console_error(&Rc::downgrade(&self.browser), format!("{:?}", e));
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Loads the documents of the iframes in the current page, including the ones nested in
+    /// them. A frame which fails to load stays empty.
+    fn load_frames(&mut self, handle_url: fn(HttpRequest) -> Result<HttpResponse, Error>) {
+        let page = self.browser.borrow().current_page();
+        loop {
+            let requests = page.borrow_mut().take_frame_requests();
+            if requests.is_empty() {
+                return;
+            }
+            for request in requests {
+                let url = request.url();
+                match handle_url(request) {
+                    Ok(response) => {
+                        page.borrow_mut().receive_frame_response(url, response);
                     }
+                    Err(e) => console_error(&Rc::downgrade(&self.browser), format!("{:?}", e)),
                 }
-                _ => {}
             }
         }
     }
 
-    fn move_focus_to_down(&mut self) {
-        let display_items = self
-            .browser
-            .borrow()
-            .current_page()
-            .borrow()
-            .display_items();
+    /// Writes the serialized DOM of the current page to a file.
+    fn save_dom(&self) {
+        let page = self.browser.borrow().current_page();
+        let html = page.borrow().serialize_dom();
+        match fs::write(SAVED_DOM_FILE, html) {
+            Ok(_) => console_debug(
+                &Rc::downgrade(&self.browser),
+                format!("saved the DOM to {}", SAVED_DOM_FILE),
+            ),
+            Err(e) => console_error(&Rc::downgrade(&self.browser), format!("{:?}", e)),
+        }
+    }
 
-        let mut focus_item_found = false;
-        for item in display_items {
-            match item {
-                DisplayItem::Text {
-                    text,
-                    style,
-                    layout_point: _,
-                } => {
-                    if style.text_decoration() != TextDecoration::Underline {
-                        continue;
-                    }
-                    // TODO: get correct destination link from Node.
-                    let destination = "http://example.com".to_string();
-                    match &self.focus {
-                        Some(current_focus_item) => {
-                            if focus_item_found {
-                                self.focus = Some(Link::new(text, destination));
-                                return;
-                            }
+    /// Returns the layout point of the content on the clicked line. Each display item is shown
+    /// on its own line, so the line is mapped to the layout point of the display item.
+    fn content_point(&self, column: u16, row: u16) -> Option<(i64, i64)> {
+        // Skip the border of the content box.
+        let area = self.content_area;
+        if column <= area.x
+            || column >= area.x + area.width
+            || row <= area.y
+            || row >= area.y + area.height
+        {
+            return None;
+        }
+        self.content_points
+            .get((row - area.y - 1) as usize)
+            .map(|point| (point.x(), point.y()))
+    }
 
-                            if current_focus_item.text == text
-                                && current_focus_item.destination == destination
-                            {
-                                focus_item_found = true;
-                            }
-                        }
-                        None => {
-                            self.focus = Some(Link::new(text, destination));
-                            return;
-                        }
-                    }
-                }
-                _ => {}
+    /// Dispatches a click to the content on `point`, and navigates if it follows a link or
+    /// submits a form.
+    fn click(
+        &mut self,
+        handle_url: fn(HttpRequest) -> Result<HttpResponse, Error>,
+        point: (i64, i64),
+    ) -> Result<(), Error> {
+        let page = self.browser.borrow().current_page();
+        let request = page.borrow_mut().clicked(point);
+        if let Some(request) = request {
+            return self.start_navigation(handle_url, request);
+        }
+        if page.borrow().focused_form_control().is_some() {
+            self.input_mode = InputMode::FormEditing;
+        }
+        self.service_navigation_request(handle_url)
+    }
+
+    /// Starts the navigation which the page requested from the browser, e.g. by following a
+    /// link.
+    fn service_navigation_request(
+        &mut self,
+        handle_url: fn(HttpRequest) -> Result<HttpResponse, Error>,
+    ) -> Result<(), Error> {
+        let request = self.browser.borrow_mut().take_navigation_request();
+        match request {
+            Some(request) => {
+                self.input_mode = InputMode::Normal;
+                self.start_navigation(handle_url, request)
             }
+            None => Ok(()),
         }
     }
 
-    fn start_navigation(
+    /// Goes back or forward in the session history of the current page by `delta`.
+    fn traverse_history(
         &mut self,
-        handle_url: fn(String) -> Result<HttpResponse, Error>,
-        destination: String,
+        handle_url: fn(HttpRequest) -> Result<HttpResponse, Error>,
+        delta: i64,
     ) -> Result<(), Error> {
-        match handle_url(destination) {
-            Ok(response) => {
-                self.browser.borrow_mut().clear_logs();
+        let page = self.browser.borrow().current_page();
+        let request = page.borrow_mut().traverse_history(delta);
+        if let Some(request) = request {
+            return self.start_navigation(handle_url, request);
+        }
+        // An entry of the current document changes only the URL.
+        if let Ok(url) = Url::new(page.borrow().url()).parse() {
+            self.input_url = url.display_url();
+        }
+        self.service_navigation_request(handle_url)
+    }
 
-                let page = self.browser.borrow().current_page();
-                page.borrow_mut().clear_display_items();
-                page.borrow_mut().receive_response(response);
-            }
-            Err(e) => {
-                console_error(&Rc::downgrade(&self.browser), format!("{:?}", e));
-                return Err(e);
-            }
+    /// Selects the markup which produced the content on `point`.
+    fn select_source(&mut self, point: (i64, i64)) {
+        let page = self.browser.borrow().current_page();
+        let range = page.borrow().source_range_at(point);
+        if let Some(range) = range {
+            console_debug(
+                &Rc::downgrade(&self.browser),
+                format!("selected {}:{}", page.borrow().url(), range),
+            );
         }
-        Ok(())
+        self.source_selection = range;
     }
 
-    /*
-    fn push_key_event(&mut self, key_code: KeyCode) {
+    /// Returns the lines of the source of the page. The selected range is highlighted.
+    fn source_lines(&self) -> Vec<Spans<'static>> {
+        let source = self.browser.borrow().current_page().borrow().source();
+        let highlight = Style::default().bg(Color::Yellow).fg(Color::Black);
+
+        let mut lines = Vec::new();
+        let mut line_start = 0;
+        for line in source.split('\n') {
+            let line_end = line_start + line.len();
+            let spans = match self.source_selection {
+                Some(range) if range.start() <= line_end && line_start < range.end() => {
+                    let start = range.start().clamp(line_start, line_end) - line_start;
+                    let end = range.end().clamp(line_start, line_end) - line_start;
+                    Spans::from(vec![
+                        Span::raw(line[..start].to_string()),
+                        Span::styled(line[start..end].to_string(), highlight),
+                        Span::raw(line[end..].to_string()),
+                    ])
+                }
+                _ => Spans::from(line.to_string()),
+            };
+            lines.push(spans);
+            line_start = line_end + 1;
+        }
+        lines
+    }
+
+    /// Sends a key to the page, which fires keyboard events and runs the default action of the
+    /// key. The key input goes to the page while a form control is focused.
+    fn push_key_event(
+        &mut self,
+        handle_url: fn(HttpRequest) -> Result<HttpResponse, Error>,
+        key_event: KeyEvent,
+    ) -> Result<(), Error> {
         // https://docs.rs/crossterm/latest/crossterm/event/enum.KeyCode.html
-        let key = match key_code {
+        // https://w3c.github.io/uievents-key/#named-key-attribute-values
+        let mut shift_key = key_event.modifiers.contains(KeyModifiers::SHIFT);
+        let key = match key_event.code {
             KeyCode::Char(c) => c.to_string(),
+            KeyCode::Enter => "Enter".to_string(),
+            KeyCode::Tab => "Tab".to_string(),
+            KeyCode::BackTab => {
+                shift_key = true;
+                "Tab".to_string()
+            }
+            KeyCode::Backspace => "Backspace".to_string(),
+            KeyCode::Esc => "Escape".to_string(),
             _ => {
-                // TODO: propagate backspace key to browser?
-                console_debug(&self.browser, format!("{:?} is pressed", key_code));
-                return;
+                console_debug(
+                    &Rc::downgrade(&self.browser),
+                    format!("{:?} is pressed", key_event.code),
+                );
+                return Ok(());
             }
         };
+
+        let page = self.browser.borrow().current_page();
+        let request = page.borrow_mut().key_pressed(&key, shift_key);
+        self.input_mode = if page.borrow().focused_form_control().is_some() {
+            InputMode::FormEditing
+        } else {
+            InputMode::Normal
+        };
+        if let Some(request) = request {
+            self.input_mode = InputMode::Normal;
+            return self.start_navigation(handle_url, request);
+        }
+        self.service_navigation_request(handle_url)
     }
-    */
 
     fn run_app<B: Backend>(
         &mut self,
-        handle_url: fn(String) -> Result<HttpResponse, Error>,
+        handle_url: fn(HttpRequest) -> Result<HttpResponse, Error>,
         terminal: &mut Terminal<B>,
     ) -> Result<(), Error> {
         loop {
+            // A script may navigate with the Location object while the document is loaded.
+            self.service_navigation_request(handle_url)?;
+
             match terminal.draw(|frame| self.ui(frame)) {
                 Ok(_) => {}
                 Err(e) => return Err(Error::Other(format!("{:?}", e))),
             }
 
+            // Wait for a user input until the scheduled refresh comes due.
+            if let Some((due, url)) = self.scheduled_refresh.clone() {
+                match event::poll(due.saturating_duration_since(Instant::now())) {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        self.start_navigation(handle_url, HttpRequest::get(url))?;
+                        continue;
+                    }
+                    Err(e) => return Err(Error::Other(format!("{:?}", e))),
+                }
+            }
+
             let event = match event::read() {
                 Ok(event) => event,
                 Err(e) => return Err(Error::Other(format!("{:?}", e))),
@@ -268,32 +405,47 @@ impl Tui {
 
             match event {
                 Event::Key(key) => {
-                    //self.push_key_event(key.code);
-
                     match self.input_mode {
                         InputMode::Normal => match key.code {
+                            // The arrow keys move the focus in the same order as the Tab key.
                             KeyCode::Up => {
-                                self.move_focus_to_up();
+                                self.push_key_event(
+                                    handle_url,
+                                    KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT),
+                                )?;
                             }
                             KeyCode::Down => {
-                                self.move_focus_to_down();
+                                self.push_key_event(
+                                    handle_url,
+                                    KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE),
+                                )?;
+                            }
+                            KeyCode::Left => {
+                                self.traverse_history(handle_url, -1)?;
+                            }
+                            KeyCode::Right => {
+                                self.traverse_history(handle_url, 1)?;
                             }
+                            // The enter key follows the focused link.
                             KeyCode::Enter => {
-                                // do nothing when there is no focused item;
-                                if self.focus.is_none() {
-                                    continue;
-                                }
-
-                                if let Some(focus_item) = &self.focus {
-                                    self.start_navigation(
-                                        handle_url,
-                                        focus_item.destination.clone(),
-                                    )?;
-                                }
+                                self.push_key_event(handle_url, key)?;
                             }
                             KeyCode::Char('e') => {
                                 self.input_mode = InputMode::Editing;
                             }
+                            KeyCode::Tab | KeyCode::BackTab => {
+                                self.push_key_event(handle_url, key)?;
+                            }
+                            KeyCode::Char('s') => {
+                                self.save_dom();
+                            }
+                            KeyCode::Char('v') => {
+                                self.view_source = !self.view_source;
+                            }
+                            KeyCode::Esc => {
+                                // A user can cancel the scheduled refresh.
+                                self.scheduled_refresh = None;
+                            }
                             KeyCode::Char('q') => {
                                 return Ok(());
                             }
@@ -307,7 +459,7 @@ impl Tui {
                                 }
 
                                 let url: String = self.input_url.drain(..).collect();
-                                self.start_navigation(handle_url, url.clone())?;
+                                self.start_navigation(handle_url, HttpRequest::get(url))?;
                             }
                             KeyCode::Char(c) => {
                                 self.input_url.push(c);
@@ -320,10 +472,25 @@ impl Tui {
                             }
                             _ => {}
                         },
+                        InputMode::FormEditing => {
+                            self.push_key_event(handle_url, key)?;
+                        }
                     }
                 }
-                Event::Mouse(_) => {
-                    // Do not support mouse event in Tui browser.
+                Event::Mouse(MouseEvent {
+                    kind: MouseEventKind::Down(MouseButton::Left),
+                    column,
+                    row,
+                    ..
+                }) => {
+                    // A click is dispatched to the content and selects its markup, which is
+                    // shown by the view-source display.
+                    if !self.view_source {
+                        if let Some(point) = self.content_point(column, row) {
+                            self.select_source(point);
+                            self.click(handle_url, point)?;
+                        }
+                    }
                 }
                 _ => {}
             }
@@ -352,16 +519,34 @@ impl Tui {
                         "↑ (up arrow)",
                         Style::default().add_modifier(Modifier::BOLD),
                     ),
-                    Span::raw(" to move up a focused link, "),
+                    Span::raw(" to focus the previous link, "),
                     Span::styled(
                         "↓ (down arrow)",
                         Style::default().add_modifier(Modifier::BOLD),
                     ),
-                    Span::raw(" to move down a focused link, "),
+                    Span::raw(" to focus the next link, "),
+                    Span::styled(
+                        "← (left arrow)",
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ),
+                    Span::raw(" to go back, "),
+                    Span::styled(
+                        "→ (right arrow)",
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ),
+                    Span::raw(" to go forward, "),
                     Span::styled("q", Style::default().add_modifier(Modifier::BOLD)),
                     Span::raw(" to exit, "),
                     Span::styled("e", Style::default().add_modifier(Modifier::BOLD)),
                     Span::raw(" to start editing, "),
+                    Span::styled("Tab", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(" to focus a link or a form control, "),
+                    Span::styled("s", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(" to save the DOM, "),
+                    Span::styled("v", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(" to toggle the source view, "),
+                    Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(" to cancel a page refresh, "),
                     Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
                     Span::raw(" to navigation to a focused link."),
                 ],
@@ -377,6 +562,20 @@ impl Tui {
                 ],
                 Style::default(),
             ),
+            InputMode::FormEditing => (
+                vec![
+                    Span::raw("Press "),
+                    Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(" to stop editing the form, "),
+                    Span::styled("Tab", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(" to focus the next element, "),
+                    Span::styled("Space", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(" to toggle a checkbox or change an option, "),
+                    Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(" to submit the form."),
+                ],
+                Style::default(),
+            ),
         };
         let mut text = Text::from(Spans::from(msg));
         text.patch_style(style);
@@ -387,14 +586,14 @@ impl Tui {
         {
             let input = Paragraph::new(self.input_url.as_ref())
                 .style(match self.input_mode {
-                    InputMode::Normal => Style::default().fg(Color::White),
+                    InputMode::Normal | InputMode::FormEditing => Style::default().fg(Color::White),
                     InputMode::Editing => Style::default().fg(Color::Yellow),
                 })
                 .block(Block::default().borders(Borders::ALL).title("URL"));
             frame.render_widget(input, chunks[1]);
         }
         match self.input_mode {
-            InputMode::Normal =>
+            InputMode::Normal | InputMode::FormEditing =>
                 // Hide the cursor. `Frame` does this by default, so we don't need to do anything here
                 {}
 
@@ -426,6 +625,7 @@ impl Tui {
         */
 
         let mut spans: Vec<Spans> = Vec::new();
+        let mut content_points = Vec::new();
 
         //let mut i = 0;
         for item in display_items {
@@ -447,20 +647,21 @@ impl Tui {
                 DisplayItem::Text {
                     text,
                     style,
-                    layout_point: _,
+                    layout_point,
                 } => {
+                    content_points.push(layout_point);
                     if style.text_decoration() == TextDecoration::Underline {
                         // link text.
-                        if let Some(focus_item) = &self.focus {
-                            if focus_item.text == text {
-                                spans.push(Spans::from(Span::styled(
-                                    text,
-                                    Style::default()
-                                        .fg(Color::Blue)
-                                        .add_modifier(Modifier::UNDERLINED),
-                                )));
-                                continue;
-                            }
+                        // A link focused in the page is highlighted by its background color.
+                        if style.background_color() != CssColor::white() {
+                            spans.push(Spans::from(Span::styled(
+                                text,
+                                Style::default()
+                                    .fg(Color::Blue)
+                                    .bg(Color::Gray)
+                                    .add_modifier(Modifier::UNDERLINED),
+                            )));
+                            continue;
                         }
                         spans.push(Spans::from(Span::styled(
                             text,
@@ -468,14 +669,27 @@ impl Tui {
                         )));
                     } else {
                         // normal text.
-                        spans.push(if style.font_size() != FontSize::Medium {
-                            Spans::from(Span::styled(
-                                text,
-                                Style::default().add_modifier(Modifier::BOLD),
-                            ))
-                        } else {
-                            Spans::from(Span::raw(text))
-                        });
+                        let mut modifier = Modifier::empty();
+                        if style.font_size() != FontSize::Medium
+                            || style.font_weight() == FontWeight::Bold
+                        {
+                            modifier |= Modifier::BOLD;
+                        }
+                        if style.font_style() == FontStyle::Italic {
+                            modifier |= Modifier::ITALIC;
+                        }
+                        let mut text_style = Style::default().add_modifier(modifier);
+                        // Show a background color such as the highlight of a focused form
+                        // control.
+                        if style.background_color() != CssColor::white() {
+                            let code = style.background_color().code_u32();
+                            text_style = text_style.bg(Color::Rgb(
+                                (code >> 16) as u8,
+                                (code >> 8) as u8,
+                                code as u8,
+                            ));
+                        }
+                        spans.push(Spans::from(Span::styled(text, text_style)));
                     }
                 }
                 DisplayItem::Img {
@@ -488,10 +702,34 @@ impl Tui {
             }
         }
 
-        let contents = Paragraph::new(spans)
-            .block(Block::default().title("Content").borders(Borders::ALL))
-            .wrap(Wrap { trim: true });
+        // Show the title of the page on the top line of the content box.
+        let title = self.browser.borrow().current_page().borrow().title();
+        let title = if title.is_empty() {
+            "Content".to_string()
+        } else {
+            title
+        };
+        let contents = if self.view_source {
+            // Scroll to the selected range.
+            let line = match self.source_selection {
+                Some(range) => range.start_position().line() - 1,
+                None => 0,
+            };
+            Paragraph::new(self.source_lines())
+                .block(
+                    Block::default()
+                        .title(format!("view-source:{}", self.input_url))
+                        .borders(Borders::ALL),
+                )
+                .scroll((line as u16, 0))
+        } else {
+            Paragraph::new(spans)
+                .block(Block::default().title(title).borders(Borders::ALL))
+                .wrap(Wrap { trim: true })
+        };
         frame.render_widget(contents, chunks[2]);
+        self.content_area = chunks[2];
+        self.content_points = content_points;
 
         let logs: Vec<ListItem> = self
             .browser
@@ -20,8 +20,10 @@ use saba_core::{
     constants::*,
     display_item::DisplayItem,
     error::Error,
-    http::HttpResponse,
+    http::{HttpRequest, HttpResponse},
+    renderer::dom::refresh::Refresh,
     renderer::layout::computed_style::{FontSize, TextDecoration},
+    url::Url,
 };
 use tinybmp::{Bmp, RawBmp};
 
@@ -31,13 +33,20 @@ enum InputMode {
     Editing,
 }
 
+/// The window title used when the current page has no title.
+const DEFAULT_WINDOW_TITLE: &str = "SaBA";
+
 #[derive(Debug)]
 pub struct WasabiUI {
     browser: Rc<RefCell<Browser>>,
     input_url: String,
     input_mode: InputMode,
     window: Window,
+    /// The title shown in the title bar of `window`.
+    window_title: String,
     cursor: Cursor,
+    /// The navigation scheduled by `<meta http-equiv="refresh">` in the current page.
+    scheduled_refresh: Option<Refresh>,
 }
 
 impl WasabiUI {
@@ -47,7 +56,7 @@ impl WasabiUI {
             input_url: String::new(),
             input_mode: InputMode::Normal,
             window: Window::new(
-                "SaBA".to_string(),
+                DEFAULT_WINDOW_TITLE.to_string(),
                 WHITE,
                 WINDOW_INIT_X_POS,
                 WINDOW_INIT_Y_POS,
@@ -55,13 +64,15 @@ impl WasabiUI {
                 WINDOW_HEIGHT,
             )
             .expect("failed to create a window"),
+            window_title: DEFAULT_WINDOW_TITLE.to_string(),
             cursor: Cursor::new(),
+            scheduled_refresh: None,
         }
     }
 
     pub fn start(
         &mut self,
-        handle_url: fn(String) -> Result<HttpResponse, Error>,
+        handle_url: fn(HttpRequest) -> Result<HttpResponse, Error>,
     ) -> Result<(), Error> {
         self.setup()?;
 
@@ -127,20 +138,72 @@ impl WasabiUI {
 
     fn handle_key_input(
         &mut self,
-        handle_url: fn(String) -> Result<HttpResponse, Error>,
+        handle_url: fn(HttpRequest) -> Result<HttpResponse, Error>,
     ) -> Result<(), Error> {
         match self.input_mode {
             InputMode::Normal => {
-                // ignore a key when input_mode is Normal.
-                let _ = Api::read_key();
+                // Send a key to the page, which fires keyboard events at the focused element and
+                // runs the default action of the key. When nothing is focused, 's' also saves the
+                // DOM, 'r' follows the scheduled refresh, and 'b' and 'f' go back and forward in
+                // the session history.
+                let c = match Api::read_key() {
+                    Some(c) => c,
+                    None => return Ok(()),
+                };
+                // https://w3c.github.io/uievents-key/#named-key-attribute-values
+                let key = match c {
+                    '\n' => "Enter".to_string(),
+                    '\u{7F}' | '\u{08}' => "Backspace".to_string(),
+                    '\t' => "Tab".to_string(),
+                    '\u{1B}' => "Escape".to_string(),
+                    _ => c.to_string(),
+                };
+
+                let page = self.browser.borrow().current_page();
+                let nothing_focused = page.borrow().focused_element().is_none();
+                // A followed link requests the navigation from the browser.
+                let request = page
+                    .borrow_mut()
+                    .key_pressed(&key, false)
+                    .or_else(|| self.browser.borrow_mut().take_navigation_request());
+                if let Some(request) = request {
+                    let _ = self.start_navigation(handle_url, request);
+                    return Ok(());
+                }
+
+                if nothing_focused {
+                    if c == 's' {
+                        self.save_dom();
+                    } else if c == 'r' {
+                        if let Some(refresh) = self.scheduled_refresh.take() {
+                            let _ =
+                                self.start_navigation(handle_url, HttpRequest::get(refresh.url()));
+                            return Ok(());
+                        }
+                    } else if c == 'b' || c == 'f' {
+                        let delta = if c == 'b' { -1 } else { 1 };
+                        let request = page
+                            .borrow_mut()
+                            .traverse_history(delta)
+                            .or_else(|| self.browser.borrow_mut().take_navigation_request());
+                        if let Some(request) = request {
+                            let _ = self.start_navigation(handle_url, request);
+                            return Ok(());
+                        }
+                        // An entry of the current document changes only the URL.
+                        self.input_url = page.borrow().url();
+                        self.update_address_bar()?;
+                    }
+                }
+                self.update_ui()?;
             }
             InputMode::Editing => {
                 if let Some(c) = Api::read_key() {
                     if c == 0x0A as char {
                         // enter key
-                        let _ = self.start_navigation(handle_url, self.input_url.clone());
+                        let _ = self
+                            .start_navigation(handle_url, HttpRequest::get(self.input_url.clone()));
 
-                        self.input_url = String::new();
                         self.input_mode = InputMode::Normal;
                     } else if c == 0x7F as char || c == 0x08 as char {
                         // delete key
@@ -157,9 +220,17 @@ impl WasabiUI {
         Ok(())
     }
 
+    /// Dumps the serialized DOM of the current page to the console because there is no file
+    /// system to save it to.
+    fn save_dom(&self) {
+        let page = self.browser.borrow().current_page();
+        let html = page.borrow().serialize_dom();
+        println!("{}", html);
+    }
+
     fn handle_mouse_input(
         &mut self,
-        handle_url: fn(String) -> Result<HttpResponse, Error>,
+        handle_url: fn(HttpRequest) -> Result<HttpResponse, Error>,
     ) -> Result<(), Error> {
         if let Some(MouseEvent { button, position }) = Api::get_mouse_cursor_info() {
             self.window.flush_area(self.cursor.rect());
@@ -208,7 +279,14 @@ impl WasabiUI {
                     relative_pos.1 - TITLE_BAR_HEIGHT - TOOLBAR_HEIGHT,
                 );
                 let page = self.browser.borrow().current_page();
-                let next_destination = page.borrow_mut().clicked(position_in_content_area);
+                // A clicked link requests the navigation from the browser.
+                let next_destination = page
+                    .borrow_mut()
+                    .clicked(position_in_content_area)
+                    .or_else(|| self.browser.borrow_mut().take_navigation_request());
+                if let Some(range) = page.borrow().source_range_at(position_in_content_area) {
+                    println!("clicked source: {}:{}", page.borrow().url(), range);
+                }
 
                 // clear logs.
                 for log in self.browser.borrow().logs() {
@@ -216,11 +294,15 @@ impl WasabiUI {
                 }
                 self.browser.borrow_mut().clear_logs();
 
-                if let Some(url) = next_destination {
+                if let Some(request) = next_destination {
                     // navigate to the next url.
-                    self.input_url = url.clone();
+                    self.input_url = request.url();
                     self.update_address_bar()?;
-                    let _ = self.start_navigation(handle_url, url);
+                    let _ = self.start_navigation(handle_url, request);
+                } else {
+                    // A click may change the state of a form control or navigate an iframe.
+                    self.load_frames(handle_url);
+                    self.update_ui()?;
                 }
             }
         }
@@ -230,39 +312,99 @@ impl WasabiUI {
 
     fn run_app(
         &mut self,
-        handle_url: fn(String) -> Result<HttpResponse, Error>,
+        handle_url: fn(HttpRequest) -> Result<HttpResponse, Error>,
     ) -> Result<(), Error> {
         loop {
             self.handle_key_input(handle_url)?;
             self.handle_mouse_input(handle_url)?;
+
+            // A script may navigate with the Location object while the document is loaded.
+            let request = self.browser.borrow_mut().take_navigation_request();
+            if let Some(request) = request {
+                let _ = self.start_navigation(handle_url, request);
+            }
+
+            // There is no timer in this OS, so only a refresh without delay navigates
+            // automatically. A user follows the other refreshes by pressing 'r'.
+            if self
+                .scheduled_refresh
+                .as_ref()
+                .is_some_and(|refresh| refresh.delay() == 0)
+            {
+                if let Some(refresh) = self.scheduled_refresh.take() {
+                    let _ = self.start_navigation(handle_url, HttpRequest::get(refresh.url()));
+                }
+            }
         }
     }
 
     fn start_navigation(
         &mut self,
-        handle_url: fn(String) -> Result<HttpResponse, Error>,
-        destination: String,
+        handle_url: fn(HttpRequest) -> Result<HttpResponse, Error>,
+        request: HttpRequest,
     ) -> Result<(), Error> {
+        self.scheduled_refresh = None;
         self.clear_content_area()?;
+        let destination = request.url();
+
+        // Show the host in the Unicode form unless it may be used for spoofing.
+        if let Ok(url) = Url::new(destination.clone()).parse() {
+            self.input_url = url.display_url();
+            self.update_address_bar()?;
+        }
 
-        match handle_url(destination) {
+        match handle_url(request) {
             Ok(response) => {
                 self.browser.borrow_mut().clear_logs();
 
                 let page = self.browser.borrow().current_page();
                 page.borrow_mut().clear_display_items();
-                page.borrow_mut().receive_response(response);
+                page.borrow_mut().receive_response(destination, response);
+                self.load_frames(handle_url);
+
+                self.scheduled_refresh = page.borrow().refresh();
+                if let Some(refresh) = &self.scheduled_refresh {
+                    if refresh.delay() > 0 {
+                        println!(
+                            "this page refreshes to {} after {} seconds. press 'r' to follow it",
+                            refresh.url(),
+                            refresh.delay()
+                        );
+                    }
+                }
             }
             Err(e) => {
                 return Err(e);
             }
         }
 
+        self.update_window_title()?;
         self.update_ui()?;
 
         Ok(())
     }
 
+    /// Loads the documents of the iframes in the current page, including the ones nested in
+    /// them. A frame which fails to load stays empty.
+    fn load_frames(&mut self, handle_url: fn(HttpRequest) -> Result<HttpResponse, Error>) {
+        let page = self.browser.borrow().current_page();
+        loop {
+            let requests = page.borrow_mut().take_frame_requests();
+            if requests.is_empty() {
+                return;
+            }
+            for request in requests {
+                let url = request.url();
+                match handle_url(request) {
+                    Ok(response) => {
+                        page.borrow_mut().receive_frame_response(url, response);
+                    }
+                    Err(e) => println!("failed to load a frame {}: {:?}", url, e),
+                }
+            }
+        }
+    }
+
     fn update_ui(&mut self) -> Result<(), Error> {
         let display_items = self
             .browser
@@ -361,6 +503,40 @@ impl WasabiUI {
         Ok(())
     }
 
+    /// Shows the title of the current page in the title bar. The title bar is drawn only when a
+    /// window is created, so the window is created again with the new title.
+    fn update_window_title(&mut self) -> Result<(), Error> {
+        let page = self.browser.borrow().current_page();
+        let mut title = page.borrow().title();
+        if title.is_empty() {
+            title = DEFAULT_WINDOW_TITLE.to_string();
+        }
+        if title == self.window_title {
+            return Ok(());
+        }
+
+        self.window = match Window::new(
+            title.clone(),
+            WHITE,
+            WINDOW_INIT_X_POS,
+            WINDOW_INIT_Y_POS,
+            WINDOW_WIDTH,
+            WINDOW_HEIGHT,
+        ) {
+            Ok(window) => window,
+            Err(error) => {
+                return Err(Error::InvalidUI(format!(
+                    "failed to create a window with error: {:#?}",
+                    error
+                )))
+            }
+        };
+        self.window_title = title;
+
+        self.setup()?;
+        self.update_address_bar()
+    }
+
     fn update_address_bar(&mut self) -> Result<(), Error> {
         // clear address bar
         if self
@@ -459,7 +635,9 @@ impl WasabiUI {
 /// it compatible with noli library.
 fn convert_font_size(size: FontSize) -> StringSize {
     match size {
-        FontSize::Medium => StringSize::Medium,
+        FontSize::XSmall | FontSize::Small | FontSize::Medium | FontSize::Large => {
+            StringSize::Medium
+        }
         FontSize::XLarge => StringSize::Large,
         FontSize::XXLarge => StringSize::XLarge,
     }